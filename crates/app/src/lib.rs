@@ -11,12 +11,16 @@ pub use spin_locked_app::locked;
 pub use spin_locked_app::values;
 pub use spin_locked_app::{Error, MetadataKey, Result};
 
+use std::{ffi::OsString, sync::Arc};
+
 use ouroboros::self_referencing;
 use serde::Deserialize;
 use spin_core::{wasmtime, Engine, EngineBuilder, HostComponentDataHandle, StoreBuilder};
 
 use host_component::DynamicHostComponents;
-use locked::{ContentPath, LockedApp, LockedComponent, LockedComponentSource, LockedTrigger};
+use locked::{
+    ContentPath, LockedApp, LockedComponent, LockedComponentSource, LockedMap, LockedTrigger,
+};
 use spin_locked_app::MetadataExt;
 
 pub use async_trait::async_trait;
@@ -31,6 +35,18 @@ pub const APP_VERSION_KEY: MetadataKey = MetadataKey::new("version");
 pub const APP_DESCRIPTION_KEY: MetadataKey = MetadataKey::new("description");
 /// MetadataKey for extracting the OCI image digest.
 pub const OCI_IMAGE_DIGEST_KEY: MetadataKey = MetadataKey::new("oci_image_digest");
+/// MetadataKey for extracting the URL the application was loaded from.
+pub const APP_ORIGIN_KEY: MetadataKey = MetadataKey::new("origin");
+/// MetadataKey for extracting the host environment variables passed through to
+/// a component. A name ending in `*` matches every variable with that prefix.
+pub const ENVIRONMENT_ALLOWLIST_KEY: MetadataKey<Vec<String>> =
+    MetadataKey::new("environment_allowlist");
+/// MetadataKey for extracting the command-line arguments passed to a component
+/// after its name.
+pub const ARGS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("args");
+/// MetadataKey for extracting a component's initial working directory, which
+/// it is given as `PWD`.
+pub const WORKING_DIR_KEY: MetadataKey = MetadataKey::new("working_dir");
 
 /// A trait for implementing the low-level operations needed to load an [`App`].
 // TODO(lann): Should this migrate to spin-loader?
@@ -66,6 +82,39 @@ pub trait Loader {
     ) -> anyhow::Result<()>;
 }
 
+/// Lets one [`Loader`] be shared by several [`AppLoader`]s, such as those of
+/// the executors for an app's different trigger types.
+#[async_trait]
+impl<L: Loader + Send + Sync + ?Sized> Loader for Arc<L> {
+    async fn load_app(&self, uri: &str) -> anyhow::Result<LockedApp> {
+        (**self).load_app(uri).await
+    }
+
+    async fn load_component(
+        &self,
+        engine: &wasmtime::Engine,
+        source: &LockedComponentSource,
+    ) -> anyhow::Result<spin_core::Component> {
+        (**self).load_component(engine, source).await
+    }
+
+    async fn load_module(
+        &self,
+        engine: &wasmtime::Engine,
+        source: &LockedComponentSource,
+    ) -> anyhow::Result<spin_core::Module> {
+        (**self).load_module(engine, source).await
+    }
+
+    async fn mount_files(
+        &self,
+        store_builder: &mut StoreBuilder,
+        component: &AppComponent,
+    ) -> anyhow::Result<()> {
+        (**self).mount_files(store_builder, component).await
+    }
+}
+
 /// An `AppLoader` holds an implementation of [`Loader`] along with
 /// [`DynamicHostComponent`]s configuration.
 pub struct AppLoader {
@@ -322,9 +371,34 @@ impl<'a> AppComponent<'a> {
     /// In particular, the WASI 'env' and "preloaded dirs" are set up, and any
     /// [`DynamicHostComponent`]s associated with the source [`AppLoader`] are
     /// configured.
+    ///
+    /// The WASI env holds the host environment variables allowed by
+    /// [`ENVIRONMENT_ALLOWLIST_KEY`], overridden by the component's own
+    /// environment.
+    ///
+    /// Any [`ARGS_KEY`] arguments are set after the component ID as `argv[0]`,
+    /// unless the trigger has already set arguments of its own (as Wagi does).
     pub async fn apply_store_config(&self, builder: &mut StoreBuilder) -> Result<()> {
+        let allowlist = self
+            .get_metadata(ENVIRONMENT_ALLOWLIST_KEY)?
+            .unwrap_or_default();
+        if !allowlist.is_empty() {
+            let passthrough = passthrough_env(&allowlist, &self.locked.env, std::env::vars_os());
+            builder.env(passthrough).map_err(Error::CoreError)?;
+        }
         builder.env(&self.locked.env).map_err(Error::CoreError)?;
 
+        let args = self.get_metadata(ARGS_KEY)?.unwrap_or_default();
+        if !args.is_empty() && !builder.has_args() {
+            let argv = std::iter::once(self.id()).chain(args.iter().map(String::as_str));
+            builder.args(argv).map_err(Error::CoreError)?;
+        }
+        if let Some(working_dir) = self.get_metadata(WORKING_DIR_KEY)? {
+            builder
+                .working_dir(&working_dir)
+                .map_err(Error::CoreError)?;
+        }
+
         let loader = self.app.loader;
         loader
             .inner
@@ -341,6 +415,45 @@ impl<'a> AppComponent<'a> {
     }
 }
 
+// Returns the host environment variables allowed by `allowlist` which the
+// component's own `env` doesn't override.
+fn passthrough_env<'a>(
+    allowlist: &'a [String],
+    env: &'a LockedMap<String>,
+    host_env: impl IntoIterator<Item = (OsString, OsString)> + 'a,
+) -> impl Iterator<Item = (String, String)> + 'a {
+    host_env.into_iter().filter_map(|(name, value)| {
+        // Names which aren't UTF-8 can't be in the allowlist
+        let name = name.into_string().ok()?;
+        if env.contains_key(&name) || !is_allowed_env_var(allowlist, &name) {
+            return None;
+        }
+        match value.into_string() {
+            Ok(value) => Some((name, value)),
+            Err(_) => {
+                tracing::warn!(
+                    "Not passing through host environment variable {name}: its value isn't UTF-8"
+                );
+                None
+            }
+        }
+    })
+}
+
+/// Returns whether `name` is allowed by `allowlist`, where an entry ending in
+/// `*` matches a prefix. An entry with an empty prefix, such as a bare `*`,
+/// allows nothing: passing through the whole host environment would expose
+/// the host's credentials, so the loader rejects it.
+fn is_allowed_env_var(allowlist: &[String], name: &str) -> bool {
+    allowlist
+        .iter()
+        .any(|allowed| match allowed.strip_suffix('*') {
+            Some("") => false,
+            Some(prefix) => name.starts_with(prefix),
+            None => name == allowed,
+        })
+}
+
 /// An `AppTrigger` holds configuration for a Spin application trigger.
 pub struct AppTrigger<'a, L = AppLoader> {
     /// The app this trigger belongs to.
@@ -370,15 +483,26 @@ impl<'a, L> AppTrigger<'a, L> {
     /// 'component' metadata value which is conventionally a component ID.
     pub fn component(&self) -> Result<AppComponent<'a, L>> {
         let id = &self.locked.id;
-        let common_config: CommonTriggerConfig = self.typed_config()?;
-        let component_id = common_config.component.ok_or_else(|| {
+        self.optional_component()?.ok_or_else(|| {
             Error::MetadataError(format!("trigger {id:?} missing 'component' config field"))
-        })?;
-        self.app.get_component(&component_id).ok_or_else(|| {
+        })
+    }
+
+    /// Like [`AppTrigger::component`], but returns `Ok(None)` if the trigger
+    /// has no 'component' config field, as for triggers served entirely by
+    /// the host.
+    pub fn optional_component(&self) -> Result<Option<AppComponent<'a, L>>> {
+        let id = &self.locked.id;
+        let common_config: CommonTriggerConfig = self.typed_config()?;
+        let Some(component_id) = common_config.component else {
+            return Ok(None);
+        };
+        let component = self.app.get_component(&component_id).ok_or_else(|| {
             Error::MetadataError(format!(
                 "missing component {component_id:?} configured for trigger {id:?}"
             ))
-        })
+        })?;
+        Ok(Some(component))
     }
 }
 
@@ -386,3 +510,87 @@ impl<'a, L> AppTrigger<'a, L> {
 struct CommonTriggerConfig {
     component: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn env_allowlist_matches_exact_names() {
+        let allowlist = allowlist(&["AWS_REGION"]);
+        assert!(is_allowed_env_var(&allowlist, "AWS_REGION"));
+        assert!(!is_allowed_env_var(&allowlist, "AWS_REGION_2"));
+        assert!(!is_allowed_env_var(&allowlist, "AWS"));
+    }
+
+    #[test]
+    fn env_allowlist_matches_prefixes() {
+        let allowlist = allowlist(&["OTEL_*"]);
+        assert!(is_allowed_env_var(&allowlist, "OTEL_"));
+        assert!(is_allowed_env_var(&allowlist, "OTEL_SERVICE_NAME"));
+        assert!(!is_allowed_env_var(&allowlist, "OTEL"));
+        assert!(!is_allowed_env_var(&allowlist, "AWS_SECRET_ACCESS_KEY"));
+    }
+
+    #[test]
+    fn env_allowlist_bare_wildcard_allows_nothing() {
+        let allowlist = allowlist(&["*"]);
+        assert!(!is_allowed_env_var(&allowlist, "AWS_SECRET_ACCESS_KEY"));
+        assert!(!is_allowed_env_var(&allowlist, "PATH"));
+    }
+
+    #[test]
+    fn component_env_overrides_passthrough() {
+        let allowlist = allowlist(&["OTEL_*", "AWS_REGION"]);
+        let env = [("OTEL_SERVICE_NAME".to_string(), "component".to_string())]
+            .into_iter()
+            .collect();
+        let host_env = [
+            ("OTEL_SERVICE_NAME", "host"),
+            ("OTEL_EXPORTER", "otlp"),
+            ("AWS_REGION", "us-east-1"),
+            ("HOME", "/root"),
+        ]
+        .map(|(name, value)| (OsString::from(name), OsString::from(value)));
+
+        let mut passthrough = passthrough_env(&allowlist, &env, host_env).collect::<Vec<_>>();
+        passthrough.sort();
+        assert_eq!(
+            passthrough,
+            [
+                ("AWS_REGION".to_string(), "us-east-1".to_string()),
+                ("OTEL_EXPORTER".to_string(), "otlp".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn passthrough_skips_non_utf8_host_variables() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let allowlist = allowlist(&["OTEL_*"]);
+        let env = LockedMap::default();
+        let host_env = [
+            (
+                OsString::from("OTEL_SERVICE_NAME"),
+                OsString::from_vec(vec![0xff]),
+            ),
+            (
+                OsString::from_vec(b"OTEL_\xff".to_vec()),
+                OsString::from("otlp"),
+            ),
+            (OsString::from("OTEL_EXPORTER"), OsString::from("otlp")),
+        ];
+
+        let passthrough = passthrough_env(&allowlist, &env, host_env).collect::<Vec<_>>();
+        assert_eq!(
+            passthrough,
+            [("OTEL_EXPORTER".to_string(), "otlp".to_string())]
+        );
+    }
+}
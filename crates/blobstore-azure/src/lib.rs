@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use azure_core::{error::Error as AzureError, Pageable, StatusCode};
+use azure_identity::DefaultAzureCredential;
+use azure_storage::StorageCredentials;
+use azure_storage_blobs::{
+    blob::operations::GetBlobResponse,
+    prelude::{BlobClient, ClientBuilder, ContainerClient},
+};
+use futures::StreamExt;
+use spin_blobstore::{log_error, Container, Error, ObjectMetadata, ObjectReader};
+use spin_core::async_trait;
+
+/// Options for a container held in an Azure Storage blob container.
+#[derive(Clone, Debug, Default)]
+pub struct BlobStoreAzureOptions {
+    /// The storage account's access key. If not set, credentials are
+    /// discovered from the environment, a managed identity or the Azure CLI.
+    pub key: Option<String>,
+    /// Prefix prepended to the name of every object.
+    pub prefix: Option<String>,
+}
+
+pub struct BlobStoreAzure {
+    client: ContainerClient,
+    prefix: String,
+}
+
+impl BlobStoreAzure {
+    pub fn new(account: String, container: String, options: BlobStoreAzureOptions) -> Self {
+        let credentials = match options.key {
+            Some(key) => StorageCredentials::Key(account.clone(), key),
+            None => {
+                StorageCredentials::TokenCredential(Arc::new(DefaultAzureCredential::default()))
+            }
+        };
+        let client = ClientBuilder::new(account, credentials).container_client(container);
+        Self {
+            client,
+            prefix: options.prefix.unwrap_or_default(),
+        }
+    }
+
+    fn blob_client(&self, name: &str) -> BlobClient {
+        self.client.blob_client(format!("{}{name}", self.prefix))
+    }
+}
+
+fn is_not_found(err: &AzureError) -> bool {
+    err.as_http_error()
+        .is_some_and(|err| err.status() == StatusCode::NotFound)
+}
+
+#[async_trait]
+impl Container for BlobStoreAzure {
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.blob_client(name).get_content().await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if is_not_found(&err) => Ok(None),
+            Err(err) => Err(log_error(err)),
+        }
+    }
+
+    async fn put(&self, name: &str, data: &[u8]) -> Result<(), Error> {
+        self.blob_client(name)
+            .put_block_blob(data.to_vec())
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), Error> {
+        match self.blob_client(name).delete().await {
+            Ok(_) => Ok(()),
+            Err(err) if is_not_found(&err) => Ok(()),
+            Err(err) => Err(log_error(err)),
+        }
+    }
+
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<String>, Error> {
+        let mut pages = self
+            .client
+            .list_blobs()
+            .prefix(format!("{}{}", self.prefix, prefix.unwrap_or_default()))
+            .into_stream();
+        let mut names = vec![];
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(log_error)?;
+            names.extend(
+                page.blobs
+                    .blobs()
+                    .filter_map(|blob| blob.name.strip_prefix(&self.prefix))
+                    .map(ToOwned::to_owned),
+            );
+        }
+        Ok(names)
+    }
+
+    async fn metadata(&self, name: &str) -> Result<Option<ObjectMetadata>, Error> {
+        let properties = match self.blob_client(name).get_properties().await {
+            Ok(response) => response.blob.properties,
+            Err(err) if is_not_found(&err) => return Ok(None),
+            Err(err) => return Err(log_error(err)),
+        };
+        let modified_at = properties.last_modified.unix_timestamp_nanos() / 1_000_000;
+        Ok(Some(ObjectMetadata {
+            name: name.to_owned(),
+            size: properties.content_length,
+            modified_at: u64::try_from(modified_at).ok(),
+        }))
+    }
+
+    async fn reader(&self, name: &str) -> Result<Option<Box<dyn ObjectReader>>, Error> {
+        // Each item of the stream is a ranged request for the next chunk of
+        // the blob, so fetching the first reveals whether the blob exists.
+        let mut chunks = self.blob_client(name).get().into_stream();
+        let pending = match chunks.next().await {
+            Some(Ok(response)) => response.data.collect().await.map_err(log_error)?.to_vec(),
+            Some(Err(err)) if is_not_found(&err) => return Ok(None),
+            Some(Err(err)) => return Err(log_error(err)),
+            None => Vec::new(),
+        };
+        Ok(Some(Box::new(AzureReader { chunks, pending })))
+    }
+}
+
+struct AzureReader {
+    chunks: Pageable<GetBlobResponse, AzureError>,
+    // Bytes received but not yet read
+    pending: Vec<u8>,
+}
+
+#[async_trait]
+impl ObjectReader for AzureReader {
+    async fn read(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        while self.pending.is_empty() {
+            match self.chunks.next().await {
+                Some(response) => {
+                    let response = response.map_err(log_error)?;
+                    self.pending = response.data.collect().await.map_err(log_error)?.to_vec();
+                }
+                None => return Ok(Vec::new()),
+            }
+        }
+        let rest = self.pending.split_off(len.min(self.pending.len()));
+        Ok(std::mem::replace(&mut self.pending, rest))
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use spin_blobstore::{log_error, Container, Error, ObjectMetadata, ObjectReader};
+use spin_core::async_trait;
+use tokio::{fs, io::AsyncReadExt};
+
+/// A container whose objects are files under a directory. Object names
+/// containing `/` are stored in subdirectories.
+pub struct BlobStoreFs {
+    root: PathBuf,
+}
+
+impl BlobStoreFs {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, Error> {
+        let relative = Path::new(name);
+        let valid = !name.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !valid {
+            return Err(Error::Other(format!("invalid object name {name:?}")));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl Container for BlobStoreFs {
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        match fs::read(self.path(name)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(log_error(err)),
+        }
+    }
+
+    async fn put(&self, name: &str, data: &[u8]) -> Result<(), Error> {
+        let path = self.path(name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.map_err(log_error)?;
+        }
+        // Write to a temporary file first so readers never see a partial object
+        let temp_path = path.with_file_name(format!(
+            ".{}.partial",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        fs::write(&temp_path, data).await.map_err(log_error)?;
+        fs::rename(&temp_path, &path).await.map_err(log_error)
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(name)?).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(log_error(err)),
+        }
+    }
+
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<String>, Error> {
+        let mut names = vec![];
+        let mut dirs = vec![(self.root.clone(), String::new())];
+        while let Some((dir, dir_name)) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(log_error(err)),
+            };
+            while let Some(entry) = entries.next_entry().await.map_err(log_error)? {
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                if file_name.starts_with('.') {
+                    continue;
+                }
+                let name = format!("{dir_name}{file_name}");
+                if entry.file_type().await.map_err(log_error)?.is_dir() {
+                    dirs.push((entry.path(), format!("{name}/")));
+                } else if prefix.map_or(true, |prefix| name.starts_with(prefix)) {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    async fn metadata(&self, name: &str) -> Result<Option<ObjectMetadata>, Error> {
+        let metadata = match fs::metadata(self.path(name)?).await {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => return Ok(None),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(log_error(err)),
+        };
+        let modified_at = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_millis() as u64);
+        Ok(Some(ObjectMetadata {
+            name: name.to_owned(),
+            size: metadata.len(),
+            modified_at,
+        }))
+    }
+
+    async fn reader(&self, name: &str) -> Result<Option<Box<dyn ObjectReader>>, Error> {
+        match fs::File::open(self.path(name)?).await {
+            Ok(file) => Ok(Some(Box::new(FileReader(file)))),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(log_error(err)),
+        }
+    }
+}
+
+struct FileReader(fs::File);
+
+// Bounds the buffer allocated for a single read
+const MAX_READ_LEN: usize = 1024 * 1024;
+
+#[async_trait]
+impl ObjectReader for FileReader {
+    async fn read(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![0; len.min(MAX_READ_LEN)];
+        let read = self.0.read(&mut buf).await.map_err(log_error)?;
+        buf.truncate(read);
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_objects_as_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let container = BlobStoreFs::new(dir.path().to_owned());
+
+        container.put("images/cat.png", b"meow").await?;
+        container.put("readme.txt", b"hello").await?;
+        assert_eq!(std::fs::read(dir.path().join("images/cat.png"))?, b"meow");
+
+        assert_eq!(
+            container.list(None).await?,
+            vec!["images/cat.png", "readme.txt"]
+        );
+        assert_eq!(
+            container.list(Some("images/")).await?,
+            vec!["images/cat.png"]
+        );
+        assert_eq!(container.metadata("readme.txt").await?.unwrap().size, 5);
+        assert!(container.metadata("images").await?.is_none());
+
+        let mut reader = container.reader("readme.txt").await?.unwrap();
+        assert_eq!(reader.read(3).await?, b"hel");
+        assert_eq!(reader.read(10).await?, b"lo");
+
+        container.delete("readme.txt").await?;
+        assert!(container.get("readme.txt").await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_names_outside_the_directory() {
+        let container = BlobStoreFs::new(PathBuf::from("/tmp/blobs"));
+        assert!(container.get("../secret").await.is_err());
+        assert!(container.get("/etc/passwd").await.is_err());
+        assert!(container.get("").await.is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use aws_config::{BehaviorVersion, Region};
+use aws_credential_types::Credentials;
+use aws_sdk_s3::{primitives::ByteStream, Client};
+use spin_blobstore::{log_error, Container, Error, ObjectMetadata, ObjectReader};
+use spin_core::async_trait;
+use tokio::sync::OnceCell;
+
+/// The endpoint of Google Cloud Storage's S3-compatible XML API.
+pub const GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// Options for a container held in an S3 bucket, or in a bucket of a
+/// service with an S3-compatible API.
+#[derive(Clone, Debug, Default)]
+pub struct BlobStoreS3Options {
+    /// The region of the bucket. If not set, it is discovered from the
+    /// environment.
+    pub region: Option<String>,
+    /// The URL of an S3-compatible service to use instead of AWS.
+    pub endpoint: Option<String>,
+    /// Credentials to access the bucket with. If not set, they are discovered
+    /// from the environment, AWS profiles or instance metadata.
+    pub credentials: Option<S3Credentials>,
+    /// Whether to address the bucket in the URL path rather than the host
+    /// name, as some S3-compatible services require.
+    pub force_path_style: bool,
+    /// Prefix prepended to the name of every object.
+    pub prefix: Option<String>,
+}
+
+impl BlobStoreS3Options {
+    /// Options for a Google Cloud Storage bucket, accessed through its
+    /// S3-compatible API with the given HMAC key.
+    pub fn gcs(credentials: S3Credentials) -> Self {
+        Self {
+            region: Some("auto".into()),
+            endpoint: Some(GCS_ENDPOINT.into()),
+            credentials: Some(credentials),
+            force_path_style: true,
+            prefix: None,
+        }
+    }
+}
+
+/// Static S3 credentials.
+#[derive(Clone, Debug)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+pub struct BlobStoreS3 {
+    bucket: String,
+    options: BlobStoreS3Options,
+    // Created on first use
+    client: OnceCell<Client>,
+}
+
+impl BlobStoreS3 {
+    pub fn new(bucket: String, options: BlobStoreS3Options) -> Self {
+        Self {
+            bucket,
+            options,
+            client: OnceCell::new(),
+        }
+    }
+
+    async fn client(&self) -> &Client {
+        self.client.get_or_init(|| self.connect()).await
+    }
+
+    async fn connect(&self) -> Client {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = &self.options.region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        if let Some(credentials) = &self.options.credentials {
+            loader = loader.credentials_provider(Credentials::new(
+                &credentials.access_key_id,
+                &credentials.secret_access_key,
+                credentials.session_token.clone(),
+                None,
+                "spin-runtime-config",
+            ));
+        }
+        let sdk_config = loader.load().await;
+        let mut config = aws_sdk_s3::config::Builder::from(&sdk_config)
+            .force_path_style(self.options.force_path_style);
+        if let Some(endpoint) = &self.options.endpoint {
+            config = config.endpoint_url(endpoint);
+        }
+        Client::from_conf(config.build())
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!(
+            "{}{name}",
+            self.options.prefix.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+#[async_trait]
+impl Container for BlobStoreS3 {
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let Some(body) = self.get_body(name).await? else {
+            return Ok(None);
+        };
+        let data = body.collect().await.map_err(log_error)?;
+        Ok(Some(data.into_bytes().to_vec()))
+    }
+
+    async fn put(&self, name: &str, data: &[u8]) -> Result<(), Error> {
+        self.client()
+            .await
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .body(ByteStream::from(data.to_vec()))
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), Error> {
+        self.client()
+            .await
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<String>, Error> {
+        let own_prefix = self.options.prefix.as_deref().unwrap_or_default();
+        let mut pages = self
+            .client()
+            .await
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(self.key(prefix.unwrap_or_default()))
+            .into_paginator()
+            .send();
+        let mut names = vec![];
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(log_error)?;
+            names.extend(
+                page.contents()
+                    .iter()
+                    .filter_map(|object| object.key())
+                    .filter_map(|key| key.strip_prefix(own_prefix))
+                    .map(ToOwned::to_owned),
+            );
+        }
+        Ok(names)
+    }
+
+    async fn metadata(&self, name: &str) -> Result<Option<ObjectMetadata>, Error> {
+        let result = self
+            .client()
+            .await
+            .head_object()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .send()
+            .await;
+        let output = match result {
+            Ok(output) => output,
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_not_found()) => {
+                return Ok(None)
+            }
+            Err(err) => return Err(log_error(err)),
+        };
+        Ok(Some(ObjectMetadata {
+            name: name.to_owned(),
+            size: output.content_length().unwrap_or_default().max(0) as u64,
+            modified_at: output
+                .last_modified()
+                .and_then(|modified| modified.to_millis().ok())
+                .map(|millis| millis.max(0) as u64),
+        }))
+    }
+
+    async fn reader(&self, name: &str) -> Result<Option<Box<dyn ObjectReader>>, Error> {
+        Ok(self.get_body(name).await?.map(|body| {
+            Box::new(S3Reader {
+                body,
+                pending: Vec::new(),
+            }) as Box<dyn ObjectReader>
+        }))
+    }
+}
+
+impl BlobStoreS3 {
+    async fn get_body(&self, name: &str) -> Result<Option<ByteStream>, Error> {
+        let result = self
+            .client()
+            .await
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .send()
+            .await;
+        match result {
+            Ok(output) => Ok(Some(output.body)),
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+            Err(err) => Err(log_error(err)),
+        }
+    }
+}
+
+struct S3Reader {
+    body: ByteStream,
+    // Bytes received from the body but not yet read
+    pending: Vec<u8>,
+}
+
+#[async_trait]
+impl ObjectReader for S3Reader {
+    async fn read(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        // Skip any empty chunks, which would otherwise signal the end of the object
+        while self.pending.is_empty() {
+            match self.body.next().await {
+                Some(chunk) => self.pending = chunk.map_err(log_error)?.to_vec(),
+                None => return Ok(Vec::new()),
+            }
+        }
+        let rest = self.pending.split_off(len.min(self.pending.len()));
+        Ok(std::mem::replace(&mut self.pending, rest))
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{BlobStoreDispatch, Container, BLOB_STORES_KEY};
+use anyhow::anyhow;
+use spin_app::{AppComponent, DynamicHostComponent};
+use spin_core::HostComponent;
+use std::{collections::HashMap, sync::Arc};
+
+pub struct BlobStoreComponent {
+    containers: Arc<HashMap<String, Arc<dyn Container>>>,
+}
+
+impl BlobStoreComponent {
+    pub fn new(containers: impl IntoIterator<Item = (String, Arc<dyn Container>)>) -> Self {
+        Self {
+            containers: Arc::new(containers.into_iter().collect()),
+        }
+    }
+}
+
+impl HostComponent for BlobStoreComponent {
+    type Data = BlobStoreDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        spin_world::v2_1::blobstore::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        BlobStoreDispatch::new()
+    }
+}
+
+impl DynamicHostComponent for BlobStoreComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        let blob_stores = component.get_metadata(BLOB_STORES_KEY)?.unwrap_or_default();
+        data.init(blob_stores.into_iter().collect(), self.containers.clone());
+        Ok(())
+    }
+
+    fn validate_app(&self, app: &spin_app::App) -> anyhow::Result<()> {
+        let mut errors = vec![];
+
+        for component in app.components() {
+            for allowed in component.get_metadata(BLOB_STORES_KEY)?.unwrap_or_default() {
+                if !self.containers.contains_key(&allowed) {
+                    let err = format!("- Component {} uses blob store '{allowed}'", component.id());
+                    errors.push(err);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let prologue = vec![
+                "One or more components use blob stores which are not defined.",
+                "Check the spelling, or pass a runtime configuration file that defines these stores.",
+                "Details:",
+            ];
+            let lines: Vec<_> = prologue
+                .into_iter()
+                .map(|s| s.to_owned())
+                .chain(errors)
+                .collect();
+            Err(anyhow!(lines.join("\n")))
+        }
+    }
+}
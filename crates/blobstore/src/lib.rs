@@ -0,0 +1,306 @@
+use anyhow::{Context, Result};
+use spin_app::MetadataKey;
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_world::v2_1::blobstore;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use table::Table;
+
+mod host_component;
+mod util;
+
+pub use host_component::BlobStoreComponent;
+pub use util::{BufferedReader, MemoryContainer};
+
+pub const BLOB_STORES_KEY: MetadataKey<Vec<String>> = MetadataKey::new("blob_stores");
+
+const DEFAULT_CONTAINER_TABLE_CAPACITY: u32 = 256;
+const DEFAULT_STREAM_TABLE_CAPACITY: u32 = 256;
+
+pub use blobstore::{Error, ObjectMetadata};
+
+/// A named container of objects in some blob storage backend.
+#[async_trait]
+pub trait Container: Sync + Send {
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Error>;
+    async fn put(&self, name: &str, data: &[u8]) -> Result<(), Error>;
+    async fn delete(&self, name: &str) -> Result<(), Error>;
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<String>, Error>;
+    async fn metadata(&self, name: &str) -> Result<Option<ObjectMetadata>, Error>;
+
+    async fn exists(&self, name: &str) -> Result<bool, Error> {
+        Ok(self.metadata(name).await?.is_some())
+    }
+
+    /// Opens a reader over the contents of an object. The default
+    /// implementation reads the whole object into memory; backends which can
+    /// fetch objects incrementally should override it.
+    async fn reader(&self, name: &str) -> Result<Option<Box<dyn ObjectReader>>, Error> {
+        Ok(self
+            .get(name)
+            .await?
+            .map(|data| Box::new(BufferedReader::new(data)) as Box<dyn ObjectReader>))
+    }
+}
+
+/// An incremental reader over the contents of an object.
+#[async_trait]
+pub trait ObjectReader: Send {
+    /// Reads up to `len` bytes, returning an empty buffer at the end of the
+    /// object.
+    async fn read(&mut self, len: usize) -> Result<Vec<u8>, Error>;
+}
+
+// An object being written by a guest, which is stored once it is finished.
+struct ObjectWriter {
+    container: Arc<dyn Container>,
+    name: String,
+    data: Vec<u8>,
+    finished: bool,
+}
+
+impl ObjectWriter {
+    fn check_unfinished(&self) -> Result<(), Error> {
+        if self.finished {
+            return Err(Error::Other("outgoing-data already finished".into()));
+        }
+        Ok(())
+    }
+}
+
+pub struct BlobStoreDispatch {
+    allowed_containers: HashSet<String>,
+    defined_containers: Arc<HashMap<String, Arc<dyn Container>>>,
+    containers: Table<Arc<dyn Container>>,
+    readers: Table<Box<dyn ObjectReader>>,
+    writers: Table<ObjectWriter>,
+}
+
+impl BlobStoreDispatch {
+    pub fn new() -> Self {
+        Self::new_with_capacity(DEFAULT_CONTAINER_TABLE_CAPACITY)
+    }
+
+    pub fn new_with_capacity(capacity: u32) -> Self {
+        Self {
+            allowed_containers: HashSet::new(),
+            defined_containers: Default::default(),
+            containers: Table::new(capacity),
+            readers: Table::new(DEFAULT_STREAM_TABLE_CAPACITY),
+            writers: Table::new(DEFAULT_STREAM_TABLE_CAPACITY),
+        }
+    }
+
+    pub fn init(
+        &mut self,
+        allowed_containers: HashSet<String>,
+        defined_containers: Arc<HashMap<String, Arc<dyn Container>>>,
+    ) {
+        self.allowed_containers = allowed_containers;
+        self.defined_containers = defined_containers;
+    }
+
+    fn get_container(
+        &self,
+        container: Resource<blobstore::Container>,
+    ) -> anyhow::Result<&Arc<dyn Container>> {
+        self.containers
+            .get(container.rep())
+            .context("invalid container")
+    }
+}
+
+impl Default for BlobStoreDispatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl blobstore::Host for BlobStoreDispatch {}
+
+#[async_trait]
+impl blobstore::HostContainer for BlobStoreDispatch {
+    async fn open(
+        &mut self,
+        label: String,
+    ) -> Result<Result<Resource<blobstore::Container>, Error>> {
+        Ok(async {
+            if !self.allowed_containers.contains(&label) {
+                return Err(Error::AccessDenied);
+            }
+            let container = self
+                .defined_containers
+                .get(&label)
+                .ok_or(Error::NoSuchContainer)?
+                .clone();
+            let rep = self
+                .containers
+                .push(container)
+                .map_err(|()| Error::TableFull)?;
+            Ok(Resource::new_own(rep))
+        }
+        .await)
+    }
+
+    async fn get(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        name: String,
+    ) -> Result<Result<Option<Vec<u8>>, Error>> {
+        let container = self.get_container(container)?;
+        Ok(container.get(&name).await)
+    }
+
+    async fn put(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        name: String,
+        data: Vec<u8>,
+    ) -> Result<Result<(), Error>> {
+        let container = self.get_container(container)?;
+        Ok(container.put(&name, &data).await)
+    }
+
+    async fn delete(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        name: String,
+    ) -> Result<Result<(), Error>> {
+        let container = self.get_container(container)?;
+        Ok(container.delete(&name).await)
+    }
+
+    async fn exists(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        name: String,
+    ) -> Result<Result<bool, Error>> {
+        let container = self.get_container(container)?;
+        Ok(container.exists(&name).await)
+    }
+
+    async fn list_objects(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        prefix: Option<String>,
+    ) -> Result<Result<Vec<String>, Error>> {
+        let container = self.get_container(container)?;
+        Ok(container.list(prefix.as_deref()).await)
+    }
+
+    async fn metadata(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        name: String,
+    ) -> Result<Result<Option<ObjectMetadata>, Error>> {
+        let container = self.get_container(container)?;
+        Ok(container.metadata(&name).await)
+    }
+
+    async fn read_stream(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        name: String,
+    ) -> Result<Result<Option<Resource<blobstore::IncomingData>>, Error>> {
+        let container = self.get_container(container)?.clone();
+        Ok(async {
+            let Some(reader) = container.reader(&name).await? else {
+                return Ok(None);
+            };
+            let rep = self.readers.push(reader).map_err(|()| Error::TableFull)?;
+            Ok(Some(Resource::new_own(rep)))
+        }
+        .await)
+    }
+
+    async fn write_stream(
+        &mut self,
+        container: Resource<blobstore::Container>,
+        name: String,
+    ) -> Result<Result<Resource<blobstore::OutgoingData>, Error>> {
+        let container = self.get_container(container)?.clone();
+        let writer = ObjectWriter {
+            container,
+            name,
+            data: Vec::new(),
+            finished: false,
+        };
+        Ok(self
+            .writers
+            .push(writer)
+            .map(Resource::new_own)
+            .map_err(|()| Error::TableFull))
+    }
+
+    fn drop(&mut self, container: Resource<blobstore::Container>) -> Result<()> {
+        self.containers.remove(container.rep());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl blobstore::HostIncomingData for BlobStoreDispatch {
+    async fn read(
+        &mut self,
+        stream: Resource<blobstore::IncomingData>,
+        len: u64,
+    ) -> Result<Result<Vec<u8>, Error>> {
+        let reader = self
+            .readers
+            .get_mut(stream.rep())
+            .context("invalid incoming-data")?;
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        Ok(reader.read(len).await)
+    }
+
+    fn drop(&mut self, stream: Resource<blobstore::IncomingData>) -> Result<()> {
+        self.readers.remove(stream.rep());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl blobstore::HostOutgoingData for BlobStoreDispatch {
+    async fn write(
+        &mut self,
+        stream: Resource<blobstore::OutgoingData>,
+        data: Vec<u8>,
+    ) -> Result<Result<(), Error>> {
+        let writer = self
+            .writers
+            .get_mut(stream.rep())
+            .context("invalid outgoing-data")?;
+        Ok(writer
+            .check_unfinished()
+            .map(|()| writer.data.extend_from_slice(&data)))
+    }
+
+    async fn finish(
+        &mut self,
+        stream: Resource<blobstore::OutgoingData>,
+    ) -> Result<Result<(), Error>> {
+        let writer = self
+            .writers
+            .get_mut(stream.rep())
+            .context("invalid outgoing-data")?;
+        if let Err(err) = writer.check_unfinished() {
+            return Ok(Err(err));
+        }
+        writer.finished = true;
+        let data = std::mem::take(&mut writer.data);
+        Ok(writer.container.put(&writer.name, &data).await)
+    }
+
+    fn drop(&mut self, stream: Resource<blobstore::OutgoingData>) -> Result<()> {
+        self.writers.remove(stream.rep());
+        Ok(())
+    }
+}
+
+pub fn log_error(err: impl std::fmt::Debug) -> Error {
+    tracing::warn!("blobstore error: {err:?}");
+    Error::Other(format!("{err:?}"))
+}
@@ -0,0 +1,111 @@
+use crate::{Container, Error, ObjectMetadata, ObjectReader};
+use spin_core::async_trait;
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// An [`ObjectReader`] over an object already held in memory.
+pub struct BufferedReader {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl BufferedReader {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data, position: 0 }
+    }
+}
+
+#[async_trait]
+impl ObjectReader for BufferedReader {
+    async fn read(&mut self, len: usize) -> Result<Vec<u8>, Error> {
+        let end = self.position.saturating_add(len).min(self.data.len());
+        let chunk = self.data[self.position..end].to_vec();
+        self.position = end;
+        Ok(chunk)
+    }
+}
+
+/// A container whose objects are held in memory, and lost when the process
+/// exits.
+#[derive(Default)]
+pub struct MemoryContainer {
+    // Object names to their contents and modification times
+    objects: Mutex<BTreeMap<String, (Vec<u8>, u64)>>,
+}
+
+impl MemoryContainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Container for MemoryContainer {
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.get(name).map(|(data, _)| data.clone()))
+    }
+
+    async fn put(&self, name: &str, data: &[u8]) -> Result<(), Error> {
+        let modified_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut objects = self.objects.lock().unwrap();
+        objects.insert(name.to_owned(), (data.to_vec(), modified_at));
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), Error> {
+        self.objects.lock().unwrap().remove(name);
+        Ok(())
+    }
+
+    async fn list(&self, prefix: Option<&str>) -> Result<Vec<String>, Error> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects
+            .keys()
+            .filter(|name| prefix.map_or(true, |prefix| name.starts_with(prefix)))
+            .cloned()
+            .collect())
+    }
+
+    async fn metadata(&self, name: &str) -> Result<Option<ObjectMetadata>, Error> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.get(name).map(|(data, modified_at)| ObjectMetadata {
+            name: name.to_owned(),
+            size: data.len() as u64,
+            modified_at: Some(*modified_at),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_container_round_trip() {
+        let container = MemoryContainer::new();
+        container.put("a/one", b"first").await.unwrap();
+        container.put("b/two", b"second").await.unwrap();
+
+        assert_eq!(
+            container.get("a/one").await.unwrap().as_deref(),
+            Some(&b"first"[..])
+        );
+        assert_eq!(container.list(Some("a/")).await.unwrap(), vec!["a/one"]);
+        assert_eq!(container.metadata("b/two").await.unwrap().unwrap().size, 6);
+
+        let mut reader = container.reader("b/two").await.unwrap().unwrap();
+        assert_eq!(reader.read(4).await.unwrap(), b"seco");
+        assert_eq!(reader.read(4).await.unwrap(), b"nd");
+        assert!(reader.read(4).await.unwrap().is_empty());
+
+        container.delete("a/one").await.unwrap();
+        assert!(!container.exists("a/one").await.unwrap());
+        assert!(container.reader("a/one").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use spin_common::sha256::{hex_digest_from_bytes, hex_digest_from_file};
+
+use crate::{construct_workdir, manifest::ComponentBuildInfo};
+
+// Fingerprints of successful builds are kept here, relative to the app dir
+const FINGERPRINT_DIR: &str = ".spin/build";
+
+// Directories holding build outputs or tool state rather than build inputs
+const IGNORED_DIRS: &[&str] = &[".git", ".spin", "node_modules", "target"];
+
+/// A digest of the inputs to a component's build: its build command and the
+/// contents of the files matching its `watch` patterns or, if it has none,
+/// every file in its working directory.
+pub(crate) struct Fingerprint {
+    path: PathBuf,
+    digest: String,
+}
+
+impl Fingerprint {
+    /// Computes the fingerprint of the current inputs of `component`.
+    /// Returns `None` if the component has no build command.
+    pub fn compute(app_dir: &Path, component: &ComponentBuildInfo) -> Result<Option<Self>> {
+        let Some(build) = &component.build else {
+            return Ok(None);
+        };
+        let workdir = construct_workdir(app_dir, build.workdir.as_ref())?;
+        // The build output changes with every build, so it can't be an input
+        let output = component.local_source().map(|source| app_dir.join(source));
+
+        let mut files = if build.watch.is_empty() {
+            walk_files(&workdir)
+        } else {
+            glob_files(&workdir, &build.watch)?
+        };
+        files.retain(|file| Some(file) != output.as_ref());
+        files.sort();
+
+        let mut inputs = format!("{}\n{:?}\n", build.command, build.workdir);
+        for file in files {
+            let digest = hex_digest_from_file(&file)
+                .with_context(|| format!("Failed to read build input {}", file.display()))?;
+            let relative = file.strip_prefix(&workdir).unwrap_or(&file);
+            inputs.push_str(&format!("{}\0{digest}\n", relative.display()));
+        }
+
+        Ok(Some(Self {
+            path: app_dir
+                .join(FINGERPRINT_DIR)
+                .join(format!("{}.sha256", component.id)),
+            digest: hex_digest_from_bytes(inputs),
+        }))
+    }
+
+    /// Whether the component was last built successfully from the same inputs.
+    pub fn is_unchanged(&self) -> bool {
+        std::fs::read_to_string(&self.path)
+            .map(|previous| previous.trim() == self.digest)
+            .unwrap_or(false)
+    }
+
+    /// Records a successful build from these inputs.
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, &self.digest)?;
+        Ok(())
+    }
+}
+
+fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| {
+            !(entry.depth() > 0
+                && entry.file_type().is_dir()
+                && IGNORED_DIRS
+                    .iter()
+                    .any(|ignored| entry.file_name() == *ignored))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn glob_files(workdir: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for pattern in patterns {
+        let pattern = workdir.join(pattern);
+        let pattern = pattern.to_string_lossy();
+        for path in glob::glob(&pattern)
+            .with_context(|| format!("Invalid watch pattern {pattern:?}"))?
+            .filter_map(|path| path.ok())
+        {
+            if path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use spin_manifest::schema::v2::ComponentBuildConfig;
+
+    use super::*;
+
+    #[test]
+    fn changes_with_inputs() -> Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        let app_dir = app_dir.path();
+        std::fs::create_dir_all(app_dir.join("src"))?;
+        std::fs::create_dir_all(app_dir.join("target"))?;
+        std::fs::write(app_dir.join("src/lib.rs"), "fn main() {}")?;
+        let component = ComponentBuildInfo {
+            id: "hello".to_owned(),
+            source: Some("hello.wasm".into()),
+            build: Some(ComponentBuildConfig {
+                command: "cargo build".to_owned(),
+                workdir: None,
+                watch: vec![],
+                depends_on: vec![],
+            }),
+        };
+        let fingerprint = || Fingerprint::compute(app_dir, &component).map(Option::unwrap);
+
+        let first = fingerprint()?;
+        assert!(!first.is_unchanged());
+        first.save()?;
+        assert!(fingerprint()?.is_unchanged());
+
+        // Build outputs aren't inputs
+        std::fs::write(app_dir.join("hello.wasm"), "wasm")?;
+        std::fs::write(app_dir.join("target/out"), "out")?;
+        assert!(fingerprint()?.is_unchanged());
+
+        std::fs::write(app_dir.join("src/lib.rs"), "fn main() { todo!() }")?;
+        assert!(!fingerprint()?.is_unchanged());
+        Ok(())
+    }
+}
@@ -2,22 +2,44 @@
 
 //! A library for building Spin components.
 
+mod fingerprint;
 mod manifest;
 
 use anyhow::{anyhow, bail, Context, Result};
+use fingerprint::Fingerprint;
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
 use manifest::ComponentBuildInfo;
 use spin_common::{paths::parent_dir, ui::quoted_path};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, Read},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use subprocess::{Exec, Redirection};
 
 use crate::manifest::component_build_configs;
 
 /// If present, run the build command of each component.
-pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()> {
-    let components = component_build_configs(manifest_file)
+///
+/// Components whose build inputs haven't changed since they were last built
+/// successfully are skipped, unless `force` is set.
+pub async fn build(manifest_file: &Path, component_ids: &[String], force: bool) -> Result<()> {
+    build_with_profile(manifest_file, component_ids, force, None).await
+}
+
+/// As [`build`], but with the build commands of the manifest's
+/// `[profile.<name>]` overlay, if `profile` is given.
+pub async fn build_with_profile(
+    manifest_file: &Path,
+    component_ids: &[String],
+    force: bool,
+    profile: Option<&str>,
+) -> Result<()> {
+    let components = component_build_configs(manifest_file, profile)
         .await
         .with_context(|| {
             format!(
@@ -26,6 +48,7 @@ pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()>
             )
         })?;
     let app_dir = parent_dir(manifest_file)?;
+    let components = build_order(components)?;
 
     let components_to_build = if component_ids.is_empty() {
         components
@@ -53,17 +76,151 @@ pub async fn build(manifest_file: &Path, component_ids: &[String]) -> Result<()>
         return Ok(());
     }
 
-    components_to_build
-        .into_iter()
-        .map(|c| build_component(c, &app_dir))
-        .collect::<Result<Vec<_>, _>>()?;
+    build_components(components_to_build, &app_dir, force).await?;
 
     terminal::step!("Finished", "building all Spin components");
     Ok(())
 }
 
-/// Run the build command of the component.
-fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()> {
+/// Sorts components so that each comes after the components it depends on,
+/// otherwise keeping manifest order.
+fn build_order(components: Vec<ComponentBuildInfo>) -> Result<Vec<ComponentBuildInfo>> {
+    let ids: Vec<String> = components.iter().map(|c| c.id.clone()).collect();
+    let mut by_id: HashMap<String, ComponentBuildInfo> =
+        components.into_iter().map(|c| (c.id.clone(), c)).collect();
+
+    let mut visited = HashSet::new();
+    let mut ordered = vec![];
+    for id in &ids {
+        visit_dependencies(id, &by_id, &mut vec![], &mut visited, &mut ordered)?;
+    }
+    let ordered: Vec<String> = ordered.into_iter().map(str::to_owned).collect();
+
+    Ok(ordered.iter().map(|id| by_id.remove(id).unwrap()).collect())
+}
+
+fn visit_dependencies<'a>(
+    id: &'a str,
+    by_id: &'a HashMap<String, ComponentBuildInfo>,
+    path: &mut Vec<&'a str>,
+    visited: &mut HashSet<&'a str>,
+    ordered: &mut Vec<&'a str>,
+) -> Result<()> {
+    if visited.contains(id) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|p| *p == id) {
+        let cycle = path[start..].join(" -> ");
+        bail!("Component build dependencies form a cycle: {cycle} -> {id}");
+    }
+    path.push(id);
+    for dependency in by_id[id].depends_on() {
+        let Some((dependency, _)) = by_id.get_key_value(dependency) else {
+            bail!("Component {id} depends on unknown component {dependency}");
+        };
+        visit_dependencies(dependency, by_id, path, visited, ordered)?;
+    }
+    path.pop();
+    visited.insert(id);
+    ordered.push(id);
+    Ok(())
+}
+
+// Resolves to whether the component was built, rather than skipped
+type BuildFuture = Shared<BoxFuture<'static, Result<bool, Arc<anyhow::Error>>>>;
+
+/// Runs the build commands of `components`, which must be in build order.
+/// Each component is built as soon as the components it depends on have been
+/// built, so independent components are built concurrently.
+async fn build_components(
+    components: Vec<ComponentBuildInfo>,
+    app_dir: &Path,
+    force: bool,
+) -> Result<()> {
+    // With several builds running at once, tell their output apart
+    let prefix_output = components.iter().filter(|c| c.build.is_some()).count() > 1;
+
+    let mut builds: HashMap<String, BuildFuture> = HashMap::new();
+    let mut all_builds = vec![];
+    for component in components {
+        // Dependencies not selected for building are assumed to be up to date
+        let dependencies: Vec<(String, BuildFuture)> = component
+            .depends_on()
+            .iter()
+            .filter_map(|id| Some((id.clone(), builds.get(id)?.clone())))
+            .collect();
+        let id = component.id.clone();
+        let app_dir = app_dir.to_owned();
+        let build = async move {
+            // A component is rebuilt whenever any of its dependencies is
+            let mut force = force;
+            for (dependency, build) in dependencies {
+                match build.await {
+                    Ok(built) => force |= built,
+                    Err(_) => {
+                        return Err(Arc::new(anyhow!(
+                            "Component {} was not built because its dependency {dependency} failed to build",
+                            component.id
+                        )))
+                    }
+                }
+            }
+            tokio::task::spawn_blocking(move || {
+                build_component(component, &app_dir, prefix_output, force)
+            })
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result)
+            .map_err(Arc::new)
+        };
+        let build = build.boxed().shared();
+        builds.insert(id, build.clone());
+        all_builds.push(build);
+    }
+
+    let results = futures::future::join_all(all_builds).await;
+    let errors: Vec<String> = results
+        .into_iter()
+        .filter_map(|result| Some(format!("{:#}", result.err()?)))
+        .collect();
+    if !errors.is_empty() {
+        bail!("{}", errors.join("\n"));
+    }
+    Ok(())
+}
+
+/// Run the build command of the component, prefixing each line of its output
+/// with the component ID if `prefix_output` is set. Unless `force` is set,
+/// the build is skipped if its inputs are unchanged since the last successful
+/// build. Returns whether the build command was run.
+fn build_component(
+    build_info: ComponentBuildInfo,
+    app_dir: &Path,
+    prefix_output: bool,
+    force: bool,
+) -> Result<bool> {
+    let fingerprint = match Fingerprint::compute(app_dir, &build_info) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            terminal::warn!(
+                "Can't tell whether component {} has changed: {err:#}",
+                build_info.id
+            );
+            None
+        }
+    };
+    let output_exists = build_info
+        .local_source()
+        .map_or(true, |source| app_dir.join(source).exists());
+    if !force && output_exists && fingerprint.as_ref().is_some_and(Fingerprint::is_unchanged) {
+        terminal::step!(
+            "Fresh",
+            "component {} is unchanged since it was last built",
+            build_info.id
+        );
+        return Ok(false);
+    }
+
     match build_info.build {
         Some(b) => {
             terminal::step!(
@@ -77,10 +234,17 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                 println!("Working directory: {}", quoted_path(&workdir));
             }
 
-            let exit_status = Exec::shell(&b.command)
+            let output = || {
+                if prefix_output {
+                    Redirection::Pipe
+                } else {
+                    Redirection::None
+                }
+            };
+            let mut process = Exec::shell(&b.command)
                 .cwd(workdir)
-                .stdout(Redirection::None)
-                .stderr(Redirection::None)
+                .stdout(output())
+                .stderr(output())
                 .stdin(Redirection::None)
                 .popen()
                 .map_err(|err| {
@@ -90,8 +254,22 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                         build_info.id,
                         err
                     )
-                })?
-                .wait()?;
+                })?;
+            let prefix = format!("[{}] ", build_info.id);
+            let forwarders = [
+                process
+                    .stdout
+                    .take()
+                    .map(|stdout| forward_lines(stdout, prefix.clone(), false)),
+                process
+                    .stderr
+                    .take()
+                    .map(|stderr| forward_lines(stderr, prefix, true)),
+            ];
+            let exit_status = process.wait()?;
+            for forwarder in forwarders.into_iter().flatten() {
+                _ = forwarder.join();
+            }
 
             if !exit_status.success() {
                 bail!(
@@ -101,12 +279,41 @@ fn build_component(build_info: ComponentBuildInfo, app_dir: &Path) -> Result<()>
                 );
             }
 
-            Ok(())
+            if let Some(fingerprint) = fingerprint {
+                if let Err(err) = fingerprint.save() {
+                    tracing::warn!("Failed to save build fingerprint: {err:#}");
+                }
+            }
+
+            Ok(true)
         }
-        _ => Ok(()),
+        _ => Ok(false),
     }
 }
 
+/// Copies each line read from `reader` to stdout (or stderr if `to_stderr` is
+/// set), after `prefix`, until the end of input.
+fn forward_lines(
+    reader: impl Read + Send + 'static,
+    prefix: String,
+    to_stderr: bool,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = vec![];
+        while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\r', '\n']);
+            if to_stderr {
+                eprintln!("{prefix}{text}");
+            } else {
+                println!("{prefix}{text}");
+            }
+            line.clear();
+        }
+    })
+}
+
 /// Constructs the absolute working directory in which to run the build command.
 fn construct_workdir(app_dir: &Path, workdir: Option<impl AsRef<Path>>) -> Result<PathBuf> {
     let mut cwd = app_dir.to_owned();
@@ -136,6 +343,49 @@ mod tests {
     #[tokio::test]
     async fn can_load_even_if_trigger_invalid() {
         let bad_trigger_file = test_data_root().join("bad_trigger.toml");
-        build(&bad_trigger_file, &[]).await.unwrap();
+        build(&bad_trigger_file, &[], false).await.unwrap();
+    }
+
+    fn component(id: &str, depends_on: &[&str]) -> ComponentBuildInfo {
+        ComponentBuildInfo {
+            id: id.to_owned(),
+            source: None,
+            build: Some(spin_manifest::schema::v2::ComponentBuildConfig {
+                command: "true".to_owned(),
+                workdir: None,
+                watch: vec![],
+                depends_on: depends_on.iter().map(|id| id.to_string()).collect(),
+            }),
+        }
+    }
+
+    #[test]
+    fn orders_dependencies_first() {
+        let components = vec![
+            component("app", &["lib-b", "lib-a"]),
+            component("lib-a", &["lib-b"]),
+            component("lib-b", &[]),
+            component("other", &[]),
+        ];
+        let ordered = build_order(components).unwrap();
+        let ids: Vec<_> = ordered.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["lib-b", "lib-a", "app", "other"]);
+    }
+
+    #[test]
+    fn rejects_dependency_cycles() {
+        let components = vec![
+            component("a", &["b"]),
+            component("b", &["c"]),
+            component("c", &["a"]),
+        ];
+        let err = build_order(components).err().unwrap().to_string();
+        assert!(err.contains("a -> b -> c -> a"), "{err}");
+    }
+
+    #[test]
+    fn rejects_unknown_dependencies() {
+        let components = vec![component("a", &["missing"])];
+        assert!(build_order(components).is_err());
     }
 }
@@ -1,22 +1,41 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use serde::Deserialize;
 use std::{collections::BTreeMap, path::Path};
 
 use spin_manifest::{schema::v2, ManifestVersion};
 
 /// Returns a map of component IDs to [`v2::ComponentBuildConfig`]s for the
-/// given (v1 or v2) manifest path.
+/// given (v1 or v2) manifest path, with the build commands of the named
+/// `[profile.<name>]`, if given, replacing the components' own.
 pub async fn component_build_configs(
     manifest_file: impl AsRef<Path>,
+    profile: Option<&str>,
 ) -> Result<Vec<ComponentBuildInfo>> {
     let manifest_text = tokio::fs::read_to_string(manifest_file).await?;
     Ok(match ManifestVersion::detect(&manifest_text)? {
         ManifestVersion::V1 => {
+            if let Some(name) = profile {
+                bail!("No profile named `{name}`: profiles require manifest version 2");
+            }
             let v1: ManifestV1BuildInfo = toml::from_str(&manifest_text)?;
             v1.components
         }
         ManifestVersion::V2 => {
-            let v2: ManifestV2BuildInfo = toml::from_str(&manifest_text)?;
+            let mut v2: ManifestV2BuildInfo = toml::from_str(&manifest_text)?;
+            if let Some(name) = profile {
+                let profile = v2
+                    .profiles
+                    .remove(name)
+                    .with_context(|| format!("No profile named `{name}`"))?;
+                for (id, component_profile) in profile.components {
+                    let Some(component) = v2.components.get_mut(&id) else {
+                        bail!("Profile `{name}` sets component `{id}`, which does not exist");
+                    };
+                    if let Some(build) = component_profile.build {
+                        component.build = Some(build);
+                    }
+                }
+            }
             v2.components
                 .into_iter()
                 .map(|(id, mut c)| {
@@ -32,9 +51,27 @@ pub async fn component_build_configs(
 pub struct ComponentBuildInfo {
     #[serde(default)]
     pub id: String,
+    #[serde(default)]
+    pub source: Option<toml::Value>,
     pub build: Option<v2::ComponentBuildConfig>,
 }
 
+impl ComponentBuildInfo {
+    /// The IDs of the components that must be built before this one.
+    pub fn depends_on(&self) -> &[String] {
+        self.build
+            .as_ref()
+            .map(|b| b.depends_on.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The path of the component's Wasm file relative to the app dir, if it
+    /// is local rather than fetched from a URL.
+    pub fn local_source(&self) -> Option<&str> {
+        self.source.as_ref()?.as_str()
+    }
+}
+
 #[derive(Deserialize)]
 struct ManifestV1BuildInfo {
     #[serde(rename = "component")]
@@ -45,4 +82,17 @@ struct ManifestV1BuildInfo {
 struct ManifestV2BuildInfo {
     #[serde(rename = "component")]
     components: BTreeMap<String, ComponentBuildInfo>,
+    #[serde(default, rename = "profile")]
+    profiles: BTreeMap<String, ProfileBuildInfo>,
+}
+
+#[derive(Deserialize)]
+struct ProfileBuildInfo {
+    #[serde(default, rename = "component")]
+    components: BTreeMap<String, ComponentProfileBuildInfo>,
+}
+
+#[derive(Deserialize)]
+struct ComponentProfileBuildInfo {
+    build: Option<v2::ComponentBuildConfig>,
 }
@@ -11,7 +11,9 @@ mod host_component;
 mod io;
 mod limits;
 mod preview1;
+mod profile;
 mod store;
+pub mod usage;
 pub mod wasi_2023_10_18;
 
 use std::{path::PathBuf, sync::Arc, time::Duration};
@@ -19,7 +21,7 @@ use std::{path::PathBuf, sync::Arc, time::Duration};
 use anyhow::Result;
 use crossbeam_channel::Sender;
 use tracing::instrument;
-use wasmtime::{InstanceAllocationStrategy, PoolingAllocationConfig};
+use wasmtime::{InstanceAllocationStrategy, PoolingAllocationConfig, WasmBacktraceDetails};
 use wasmtime_wasi::preview2::Table;
 use wasmtime_wasi_http::types::{default_send_request, WasiHttpCtx, WasiHttpView};
 
@@ -37,7 +39,8 @@ pub use host_component::{
     AnyHostComponentDataHandle, HostComponent, HostComponentDataHandle, HostComponentsData,
 };
 pub use io::OutputBuffer;
-pub use store::{Store, StoreBuilder, Wasi, WasiVersion};
+pub use profile::GuestProfile;
+pub use store::{Store, StoreBuilder, StoreStats, Wasi, WasiVersion};
 
 /// The default [`EngineBuilder::epoch_tick_interval`].
 pub const DEFAULT_EPOCH_TICK_INTERVAL: Duration = Duration::from_millis(10);
@@ -51,6 +54,7 @@ const WASM_PAGE_SIZE: u64 = 64 * 1024;
 /// This is currently only used for advanced (undocumented) use cases.
 pub struct Config {
     inner: wasmtime::Config,
+    consume_fuel: bool,
 }
 
 impl Config {
@@ -82,6 +86,51 @@ impl Config {
             .allocation_strategy(wasmtime::InstanceAllocationStrategy::OnDemand);
         self
     }
+
+    /// Override limits of the pooling instance allocator, which is enabled
+    /// by default. High-throughput hosts may raise these to avoid falling
+    /// back to mapping memory per instance.
+    pub fn pooling_limits(&mut self, limits: &PoolingLimits) -> &mut Self {
+        self.inner
+            .allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config(limits)));
+        self
+    }
+
+    /// Compile for the given target triple rather than the host, e.g. to
+    /// precompile components for another platform. Engines configured this
+    /// way can only be used for compilation.
+    pub fn target(&mut self, target: &str) -> Result<&mut Self> {
+        self.inner.target(target)?;
+        Ok(self)
+    }
+
+    /// Enable fuel metering, so that [`Store::fuel_consumed`] reports how
+    /// much Wasm each store has executed. Fuel is metered but not limited.
+    ///
+    /// This slows down execution, so is disabled by default.
+    pub fn consume_fuel(&mut self) -> &mut Self {
+        self.inner.consume_fuel(true);
+        self.consume_fuel = true;
+        self
+    }
+
+    /// Compile guest code with native debug info and without optimizations,
+    /// so that a native debugger attached to the host process, such as GDB or
+    /// LLDB, can set breakpoints and step through guest source.
+    pub fn debug_guests(&mut self) -> &mut Self {
+        // Besides emitting DWARF, this registers each compiled module with the
+        // GDB JIT interface, which is how debuggers find guest code
+        self.inner.debug_info(true);
+        self.inner.cranelift_opt_level(wasmtime::OptLevel::None);
+        self
+    }
+
+    /// Capture a core dump of the guest when it traps, which
+    /// [`Store::coredump`] can serialize from the trap error.
+    pub fn coredump_on_trap(&mut self) -> &mut Self {
+        self.inner.coredump_on_trap(true);
+        self
+    }
 }
 
 impl Default for Config {
@@ -90,56 +139,90 @@ impl Default for Config {
         inner.async_support(true);
         inner.epoch_interruption(true);
         inner.wasm_component_model(true);
+        // Symbolicate guest backtraces with function names and file:line
+        // locations from DWARF debug info, for Wasm that has it. This only
+        // costs parsing the debug info at load time; Wasm without it is
+        // unaffected.
+        inner.wasm_backtrace_details(WasmBacktraceDetails::Enable);
 
         // By default enable the pooling instance allocator in Wasmtime. This
         // drastically reduces syscall/kernel overhead for wasm execution,
         // especially in async contexts where async stacks must be allocated.
         // The general goal here is that the default settings here rarely, if
-        // ever, need to be modified. As a result there aren't fine-grained
-        // knobs for each of these settings just yet and instead they're
-        // generally set to defaults. Environment-variable-based fallbacks are
-        // supported though as an escape valve for if this is a problem.
-        let mut pooling_config = PoolingAllocationConfig::default();
-        pooling_config
-            .total_component_instances(env("SPIN_WASMTIME_INSTANCE_COUNT", 1_000))
-            // This number accounts for internal data structures that Wasmtime allocates for each instance.
-            // Instance allocation is proportional to the number of "things" in a wasm module like functions,
-            // globals, memories, etc. Instance allocations are relatively small and are largely inconsequential
-            // compared to other runtime state, but a number needs to be chosen here so a relatively large threshold
-            // of 10MB is arbitrarily chosen. It should be unlikely that any reasonably-sized module hits this limit.
-            .max_component_instance_size(
-                env("SPIN_WASMTIME_INSTANCE_SIZE", (10 * MB) as u32) as usize
-            )
-            .max_tables_per_component(env("SPIN_WASMTIME_INSTANCE_TABLES", 20))
-            .table_elements(env("SPIN_WASMTIME_INSTANCE_TABLE_ELEMENTS", 30_000))
-            // The number of memories an instance can have effectively limits the number of inner components
-            // a composed component can have (since each inner component has its own memory). We default to 32 for now, and
-            // we'll see how often this limit gets reached.
-            .max_memories_per_component(env("SPIN_WASMTIME_INSTANCE_MEMORIES", 32))
-            .total_memories(env("SPIN_WASMTIME_TOTAL_MEMORIES", 1_000))
-            .total_tables(env("SPIN_WASMTIME_TOTAL_TABLES", 2_000))
-            // Nothing is lost from allowing the maximum size of memory for
-            // all instance as it's still limited through other the normal
-            // `StoreLimitsAsync` accounting method too.
-            .memory_pages(4 * GB / WASM_PAGE_SIZE)
-            // These numbers are completely arbitrary at something above 0.
-            .linear_memory_keep_resident((2 * MB) as usize)
-            .table_keep_resident((MB / 2) as usize);
-        inner.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config));
-
-        return Self { inner };
-
-        fn env(name: &str, default: u32) -> u32 {
-            match std::env::var(name) {
-                Ok(val) => val
-                    .parse()
-                    .unwrap_or_else(|e| panic!("failed to parse env var `{name}={val}`: {e}")),
-                Err(_) => default,
-            }
+        // ever, need to be modified. The most commonly tuned limits can be set
+        // with `Config::pooling_limits`; environment-variable-based fallbacks
+        // are supported for the rest as an escape valve.
+        inner.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config(
+            &PoolingLimits::default(),
+        )));
+
+        Self {
+            inner,
+            consume_fuel: false,
         }
     }
 }
 
+/// Limits for the pooling instance allocator. Unset limits keep Spin's
+/// defaults, which may also be overridden by `SPIN_WASMTIME_*` environment
+/// variables.
+#[derive(Clone, Debug, Default)]
+pub struct PoolingLimits {
+    /// The maximum number of concurrent component instances.
+    pub total_component_instances: Option<u32>,
+    /// The maximum number of Wasm pages for any linear memory.
+    pub memory_pages: Option<u64>,
+    /// The maximum number of elements in any table.
+    pub table_elements: Option<u32>,
+}
+
+fn pooling_config(limits: &PoolingLimits) -> PoolingAllocationConfig {
+    let total_component_instances = limits
+        .total_component_instances
+        .unwrap_or_else(|| env("SPIN_WASMTIME_INSTANCE_COUNT", 1_000));
+    let mut pooling_config = PoolingAllocationConfig::default();
+    pooling_config
+        .total_component_instances(total_component_instances)
+        // This number accounts for internal data structures that Wasmtime allocates for each instance.
+        // Instance allocation is proportional to the number of "things" in a wasm module like functions,
+        // globals, memories, etc. Instance allocations are relatively small and are largely inconsequential
+        // compared to other runtime state, but a number needs to be chosen here so a relatively large threshold
+        // of 10MB is arbitrarily chosen. It should be unlikely that any reasonably-sized module hits this limit.
+        .max_component_instance_size(env("SPIN_WASMTIME_INSTANCE_SIZE", (10 * MB) as u32) as usize)
+        .max_tables_per_component(env("SPIN_WASMTIME_INSTANCE_TABLES", 20))
+        .table_elements(
+            limits
+                .table_elements
+                .unwrap_or_else(|| env("SPIN_WASMTIME_INSTANCE_TABLE_ELEMENTS", 30_000)),
+        )
+        // The number of memories an instance can have effectively limits the number of inner components
+        // a composed component can have (since each inner component has its own memory). We default to 32 for now, and
+        // we'll see how often this limit gets reached.
+        .max_memories_per_component(env("SPIN_WASMTIME_INSTANCE_MEMORIES", 32))
+        .total_memories(env("SPIN_WASMTIME_TOTAL_MEMORIES", 1_000).max(total_component_instances))
+        .total_tables(
+            env("SPIN_WASMTIME_TOTAL_TABLES", 2_000)
+                .max(total_component_instances.saturating_mul(2)),
+        )
+        // Nothing is lost from allowing the maximum size of memory for
+        // all instance as it's still limited through other the normal
+        // `StoreLimitsAsync` accounting method too.
+        .memory_pages(limits.memory_pages.unwrap_or(4 * GB / WASM_PAGE_SIZE))
+        // These numbers are completely arbitrary at something above 0.
+        .linear_memory_keep_resident((2 * MB) as usize)
+        .table_keep_resident((MB / 2) as usize);
+    pooling_config
+}
+
+fn env(name: &str, default: u32) -> u32 {
+    match std::env::var(name) {
+        Ok(val) => val
+            .parse()
+            .unwrap_or_else(|e| panic!("failed to parse env var `{name}={val}`: {e}")),
+        Err(_) => default,
+    }
+}
+
 /// Host state data associated with individual [Store]s and [Instance]s.
 pub struct Data<T> {
     inner: T,
@@ -147,6 +230,8 @@ pub struct Data<T> {
     host_components_data: HostComponentsData,
     store_limits: limits::StoreLimitsAsync,
     table: Table,
+    initial_fuel: u64,
+    outgoing_request_sender: Option<Box<dyn OutgoingRequestSender>>,
 }
 
 impl<T> Data<T> {
@@ -154,6 +239,11 @@ impl<T> Data<T> {
     pub fn memory_consumed(&self) -> u64 {
         self.store_limits.memory_consumed()
     }
+
+    // Converts the store's remaining fuel to the fuel consumed so far
+    fn fuel_consumed(&self, remaining: u64) -> u64 {
+        self.initial_fuel.saturating_sub(remaining)
+    }
 }
 
 impl<T> AsRef<T> for Data<T> {
@@ -240,10 +330,40 @@ impl OutboundWasiHttpHandler for () {
     where
         Self: Sized,
     {
-        default_send_request(data, request)
+        send_outgoing_request(data, request)
     }
 }
 
+/// Sends the `wasi:http` outgoing requests of a [`Store`]'s guest in place of
+/// [`default_send_request`], for example through a shared connection pool.
+///
+/// A store's sender is set with [`StoreBuilder::outgoing_request_sender`].
+pub trait OutgoingRequestSender: Send + Sync {
+    /// Starts sending the request. Whether the request is allowed has already
+    /// been checked.
+    fn send(
+        &self,
+        request: wasmtime_wasi_http::types::OutgoingRequest,
+    ) -> wasmtime_wasi_http::types::HostFutureIncomingResponse;
+}
+
+/// Sends a `wasi:http` outgoing request with the store's
+/// [`OutgoingRequestSender`], or directly if it has none.
+/// [`OutboundWasiHttpHandler`] implementations should use this for the
+/// requests they don't handle themselves.
+pub fn send_outgoing_request<T: Send + OutboundWasiHttpHandler>(
+    data: &mut Data<T>,
+    request: wasmtime_wasi_http::types::OutgoingRequest,
+) -> wasmtime::Result<
+    wasmtime::component::Resource<wasmtime_wasi_http::types::HostFutureIncomingResponse>,
+> {
+    let Some(sender) = &data.outgoing_request_sender else {
+        return default_send_request(data, request);
+    };
+    let response = sender.send(request);
+    Ok(data.table.push(response)?)
+}
+
 /// An alias for [`wasmtime::Linker`] specialized to [`Data`].
 pub type ModuleLinker<T> = wasmtime::Linker<Data<T>>;
 
@@ -260,11 +380,15 @@ pub struct EngineBuilder<T> {
     host_components_builder: HostComponentsBuilder,
     epoch_tick_interval: Duration,
     epoch_ticker_thread: bool,
+    consume_fuel: bool,
 }
 
 impl<T: Send + Sync + OutboundWasiHttpHandler> EngineBuilder<T> {
     fn new(config: &Config) -> Result<Self> {
-        let engine = wasmtime::Engine::new(&config.inner)?;
+        Self::with_engine(wasmtime::Engine::new(&config.inner)?, config)
+    }
+
+    fn with_engine(engine: wasmtime::Engine, config: &Config) -> Result<Self> {
         let linker: Linker<T> = Linker::new(&engine);
         let mut module_linker = ModuleLinker::new(&engine);
 
@@ -280,6 +404,7 @@ impl<T: Send + Sync + OutboundWasiHttpHandler> EngineBuilder<T> {
             host_components_builder: HostComponents::builder(),
             epoch_tick_interval: DEFAULT_EPOCH_TICK_INTERVAL,
             epoch_ticker_thread: true,
+            consume_fuel: config.consume_fuel,
         })
     }
 }
@@ -368,6 +493,7 @@ impl<T: Send + Sync> EngineBuilder<T> {
             module_linker: self.module_linker,
             host_components,
             epoch_tick_interval: self.epoch_tick_interval,
+            consume_fuel: self.consume_fuel,
             _epoch_ticker_signal: epoch_ticker_signal,
         }
     }
@@ -381,6 +507,7 @@ pub struct Engine<T> {
     module_linker: ModuleLinker<T>,
     host_components: HostComponents,
     epoch_tick_interval: Duration,
+    consume_fuel: bool,
     // Matching receiver closes on drop
     _epoch_ticker_signal: Option<Sender<()>>,
 }
@@ -391,11 +518,25 @@ impl<T: OutboundWasiHttpHandler + Send + Sync> Engine<T> {
         EngineBuilder::new(config)
     }
 
+    /// Creates a new [`EngineBuilder`] which builds on an existing Wasmtime
+    /// engine, created from the same [`Config`], so that engines with
+    /// different data types share compiled code and instance allocation.
+    ///
+    /// The built engine doesn't run an epoch ticker thread: the epoch is
+    /// incremented by the engine which created `engine`, which must outlive
+    /// the built engine.
+    pub fn builder_for(engine: &wasmtime::Engine, config: &Config) -> Result<EngineBuilder<T>> {
+        let mut builder = EngineBuilder::with_engine(engine.clone(), config)?;
+        builder.epoch_ticker_thread(false);
+        Ok(builder)
+    }
+
     /// Creates a new [`StoreBuilder`].
     pub fn store_builder(&self, wasi_version: WasiVersion) -> StoreBuilder {
         StoreBuilder::new(
             self.inner.clone(),
             self.epoch_tick_interval,
+            self.consume_fuel,
             &self.host_components,
             wasi_version,
         )
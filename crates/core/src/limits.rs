@@ -1,13 +1,16 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use wasmtime::ResourceLimiterAsync;
+use wasmtime::{ResourceLimiterAsync, DEFAULT_INSTANCE_LIMIT, DEFAULT_TABLE_LIMIT};
 
 /// Async implementation of wasmtime's `StoreLimits`: https://github.com/bytecodealliance/wasmtime/blob/main/crates/wasmtime/src/limits.rs
-/// Used to limit the memory use and table size of each Instance
+/// Used to limit the memory use and table size of each Instance, and the
+/// number of instances and tables in a Store
 #[derive(Default)]
 pub struct StoreLimitsAsync {
-    max_memory_size: Option<usize>,
-    max_table_elements: Option<u32>,
+    pub(crate) max_memory_size: Option<usize>,
+    pub(crate) max_table_elements: Option<u32>,
+    pub(crate) max_instances: Option<usize>,
+    pub(crate) max_tables: Option<usize>,
     memory_consumed: u64,
 }
 
@@ -44,6 +47,14 @@ impl ResourceLimiterAsync for StoreLimitsAsync {
         };
         Ok(can_grow)
     }
+
+    fn instances(&self) -> usize {
+        self.max_instances.unwrap_or(DEFAULT_INSTANCE_LIMIT)
+    }
+
+    fn tables(&self) -> usize {
+        self.max_tables.unwrap_or(DEFAULT_TABLE_LIMIT)
+    }
 }
 
 impl StoreLimitsAsync {
@@ -51,7 +62,7 @@ impl StoreLimitsAsync {
         Self {
             max_memory_size,
             max_table_elements,
-            memory_consumed: 0,
+            ..Default::default()
         }
     }
 
@@ -86,4 +97,19 @@ mod tests {
         assert!(limits.table_growing(9, 10, None).await.unwrap());
         assert!(!limits.table_growing(10, 11, None).await.unwrap());
     }
+
+    #[test]
+    fn test_store_limits_counts() {
+        let limits = StoreLimitsAsync::default();
+        assert_eq!(limits.instances(), DEFAULT_INSTANCE_LIMIT);
+        assert_eq!(limits.tables(), DEFAULT_TABLE_LIMIT);
+
+        let limits = StoreLimitsAsync {
+            max_instances: Some(2),
+            max_tables: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(limits.instances(), 2);
+        assert_eq!(limits.tables(), 3);
+    }
 }
@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use wasmtime::{AsContext, FrameInfo, WasmBacktrace};
+
+/// A sampling profile of guest execution, as collected by a [`Store`] built
+/// with [`StoreBuilder::profile`].
+///
+/// The guest call stack is sampled at each epoch tick while the guest runs.
+/// [`GuestProfile::collapsed_stacks`] renders the samples in the "collapsed
+/// stacks" format read by flamegraph tools such as `inferno-flamegraph`.
+///
+/// [`Store`]: crate::Store
+/// [`StoreBuilder::profile`]: crate::StoreBuilder::profile
+#[derive(Clone, Default)]
+pub struct GuestProfile {
+    // Stack, outermost frame first -> number of samples
+    samples: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl GuestProfile {
+    /// Returns the number of samples taken.
+    pub fn sample_count(&self) -> u64 {
+        self.samples.lock().unwrap().values().sum()
+    }
+
+    /// Returns the samples as collapsed stacks: one line per distinct stack,
+    /// of `;`-separated function names, outermost first, followed by the
+    /// number of samples of that stack.
+    pub fn collapsed_stacks(&self) -> String {
+        let samples = self.samples.lock().unwrap();
+        let mut lines = samples
+            .iter()
+            .map(|(stack, count)| format!("{stack} {count}\n"))
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.concat()
+    }
+
+    // Samples the guest call stack of the given store.
+    pub(crate) fn sample(&self, store: impl AsContext) {
+        let backtrace = WasmBacktrace::capture(store);
+        // No frames means the guest is waiting on the host
+        if backtrace.frames().is_empty() {
+            return;
+        }
+        let stack = backtrace
+            .frames()
+            .iter()
+            .rev()
+            .map(frame_name)
+            .collect::<Vec<_>>()
+            .join(";");
+        self.record(stack);
+    }
+
+    fn record(&self, stack: String) {
+        *self.samples.lock().unwrap().entry(stack).or_default() += 1;
+    }
+}
+
+fn frame_name(frame: &FrameInfo) -> String {
+    match frame.func_name() {
+        // Collapsed stacks are separated by `;` and end with a space
+        Some(name) => name.replace([';', ' '], "_"),
+        None => format!("wasm-function[{}]", frame.func_index()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapsed_stacks_count_samples() {
+        let profile = GuestProfile::default();
+        profile.record("main;handle;parse".into());
+        profile.record("main;handle".into());
+        profile.record("main;handle;parse".into());
+
+        assert_eq!(profile.sample_count(), 3);
+        assert_eq!(
+            profile.collapsed_stacks(),
+            "main;handle 1\nmain;handle;parse 2\n"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
 use cap_std::ipnet::IpNet;
 use std::{
@@ -22,7 +22,9 @@ use crate::{
     host_component::{HostComponents, HostComponentsData},
     io::OutputBuffer,
     limits::StoreLimitsAsync,
-    preview1, Data,
+    preview1,
+    profile::GuestProfile,
+    Data, OutgoingRequestSender,
 };
 
 #[cfg(doc)]
@@ -72,6 +74,32 @@ pub enum WasiVersion {
 pub struct Store<T> {
     inner: wasmtime::Store<Data<T>>,
     epoch_tick_interval: Duration,
+    created_at: Instant,
+    on_drop: Vec<DropCallback>,
+    // The limit set with `StoreBuilder::execution_time_limit`
+    execution_time_limit: Option<Duration>,
+    // The deadline last set with `Store::set_deadline`
+    deadline: Option<Instant>,
+    // Set if profiling, where epoch ticks sample the guest instead of
+    // counting down to the deadline
+    profiled_deadline: Option<ProfiledDeadline>,
+}
+
+type DropCallback = Box<dyn FnOnce(&StoreStats) + Send + Sync>;
+
+type ProfiledDeadline = Arc<Mutex<Option<Instant>>>;
+
+/// Resource usage of a [`Store`] over its lifetime, as passed to
+/// [`StoreBuilder::on_drop`] callbacks.
+#[derive(Clone, Debug)]
+pub struct StoreStats {
+    /// The time between building and dropping the store
+    pub lifetime: Duration,
+    /// The fuel consumed, if fuel metering is enabled (see
+    /// [`crate::Config::consume_fuel`])
+    pub fuel_consumed: Option<u64>,
+    /// The amount of memory in bytes consumed by instances in the store
+    pub memory_consumed: u64,
 }
 
 impl<T> Store<T> {
@@ -88,6 +116,11 @@ impl<T> Store<T> {
     ///
     /// See [`wasmtime::Store::set_epoch_deadline`](https://docs.rs/wasmtime/latest/wasmtime/struct.Store.html#method.set_epoch_deadline).
     pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+        if let Some(profiled_deadline) = &self.profiled_deadline {
+            *profiled_deadline.lock().unwrap() = Some(deadline);
+            return;
+        }
         let now = Instant::now();
         let duration = deadline - now;
         let ticks = if duration.is_zero() {
@@ -100,6 +133,63 @@ impl<T> Store<T> {
         };
         self.inner.set_epoch_deadline(ticks);
     }
+
+    /// Returns the execution deadline, if one has been set.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Restarts the execution time limit set with
+    /// [`StoreBuilder::execution_time_limit`] from now, as if the store had
+    /// just been built, for stores which are built ahead of use. If `limit`
+    /// is given, the shorter of the two limits applies.
+    pub fn restart_execution_time_limit(&mut self, limit: Option<Duration>) {
+        let limit = match (self.execution_time_limit, limit) {
+            (Some(existing), Some(limit)) => existing.min(limit),
+            (Some(limit), None) | (None, Some(limit)) => limit,
+            (None, None) => return,
+        };
+        self.set_deadline(Instant::now() + limit);
+    }
+
+    /// Returns the fuel consumed so far, or `None` if fuel metering is not
+    /// enabled (see [`crate::Config::consume_fuel`]).
+    pub fn fuel_consumed(&self) -> Option<u64> {
+        let data = self.inner.data();
+        self.inner
+            .get_fuel()
+            .ok()
+            .map(|remaining| data.fuel_consumed(remaining))
+    }
+
+    /// Serializes the core dump carried by `err`, in the `wasm-coredump`
+    /// format, if it is a trap captured with [`crate::Config::coredump_on_trap`]
+    /// enabled. `name` names the process in the core dump.
+    pub fn coredump(&mut self, err: &anyhow::Error, name: &str) -> Option<Vec<u8>> {
+        let coredump = err.downcast_ref::<wasmtime::WasmCoreDump>()?;
+        Some(coredump.serialize(&mut self.inner, name))
+    }
+
+    /// Returns the resource usage of this store so far.
+    pub fn stats(&self) -> StoreStats {
+        StoreStats {
+            lifetime: self.created_at.elapsed(),
+            fuel_consumed: self.fuel_consumed(),
+            memory_consumed: self.inner.data().memory_consumed(),
+        }
+    }
+}
+
+impl<T> Drop for Store<T> {
+    fn drop(&mut self) {
+        if self.on_drop.is_empty() {
+            return;
+        }
+        let stats = self.stats();
+        for callback in self.on_drop.drain(..) {
+            callback(&stats);
+        }
+    }
 }
 
 impl<T> AsRef<wasmtime::Store<Data<T>>> for Store<T> {
@@ -137,6 +227,13 @@ pub struct StoreBuilder {
     wasi: std::result::Result<WasiCtxBuilder, String>,
     host_components_data: HostComponentsData,
     store_limits: StoreLimitsAsync,
+    execution_time_limit: Option<Duration>,
+    consume_fuel: bool,
+    max_fuel: Option<u64>,
+    on_drop: Vec<DropCallback>,
+    has_args: bool,
+    profile: Option<GuestProfile>,
+    outgoing_request_sender: Option<Box<dyn OutgoingRequestSender>>,
 }
 
 impl StoreBuilder {
@@ -144,6 +241,7 @@ impl StoreBuilder {
     pub(crate) fn new(
         engine: wasmtime::Engine,
         epoch_tick_interval: Duration,
+        consume_fuel: bool,
         host_components: &HostComponents,
         wasi: WasiVersion,
     ) -> Self {
@@ -153,15 +251,83 @@ impl StoreBuilder {
             wasi: Ok(wasi.into()),
             host_components_data: host_components.new_data(),
             store_limits: StoreLimitsAsync::default(),
+            execution_time_limit: None,
+            consume_fuel,
+            max_fuel: None,
+            on_drop: Vec::new(),
+            has_args: false,
+            profile: None,
+            outgoing_request_sender: None,
         }
     }
 
+    /// Registers a callback to be called with the [`StoreStats`] of the built
+    /// [`Store`] when it is dropped.
+    pub fn on_drop(&mut self, callback: impl FnOnce(&StoreStats) + Send + Sync + 'static) {
+        self.on_drop.push(Box::new(callback));
+    }
+
+    /// Enables sampling of the guest call stack at each epoch tick (see
+    /// [`crate::EngineBuilder::epoch_tick_interval`]), returning the profile the
+    /// samples are recorded to.
+    pub fn profile(&mut self) -> GuestProfile {
+        self.profile.get_or_insert_with(Default::default).clone()
+    }
+
     /// Sets a maximum memory allocation limit.
     ///
     /// See [`wasmtime::ResourceLimiter::memory_growing`] (`maximum`) for
     /// details on how this limit is enforced.
     pub fn max_memory_size(&mut self, max_memory_size: usize) {
-        self.store_limits = StoreLimitsAsync::new(Some(max_memory_size), None);
+        self.store_limits.max_memory_size = Some(max_memory_size);
+    }
+
+    /// Sets a maximum number of elements for each table.
+    ///
+    /// See [`wasmtime::ResourceLimiter::table_growing`] (`maximum`) for
+    /// details on how this limit is enforced.
+    pub fn max_table_elements(&mut self, max_table_elements: u32) {
+        self.store_limits.max_table_elements = Some(max_table_elements);
+    }
+
+    /// Sets a maximum number of instances in the store.
+    ///
+    /// See [`wasmtime::ResourceLimiter::instances`].
+    pub fn max_instances(&mut self, max_instances: usize) {
+        self.store_limits.max_instances = Some(max_instances);
+    }
+
+    /// Sets a maximum number of tables in the store.
+    ///
+    /// See [`wasmtime::ResourceLimiter::tables`].
+    pub fn max_tables(&mut self, max_tables: usize) {
+        self.store_limits.max_tables = Some(max_tables);
+    }
+
+    /// Sets an execution time limit, measured from when the store is built.
+    /// If a limit is set more than once, the shortest applies.
+    ///
+    /// This is equivalent to calling [`Store::set_deadline`] on the built store.
+    pub fn execution_time_limit(&mut self, limit: Duration) {
+        let limit = match self.execution_time_limit {
+            Some(existing) => existing.min(limit),
+            None => limit,
+        };
+        self.execution_time_limit = Some(limit);
+    }
+
+    /// Sets a maximum amount of fuel the store may consume; execution traps
+    /// once it is exhausted. If a limit is set more than once, the smallest
+    /// applies.
+    ///
+    /// Fuel is only metered if enabled with [`crate::Config::consume_fuel`];
+    /// otherwise this limit is ignored.
+    pub fn max_fuel(&mut self, max_fuel: u64) {
+        let max_fuel = match self.max_fuel {
+            Some(existing) => existing.min(max_fuel),
+            None => max_fuel,
+        };
+        self.max_fuel = Some(max_fuel);
     }
 
     /// Inherit stdin from the host process.
@@ -275,6 +441,22 @@ impl StoreBuilder {
         Ok(buffer)
     }
 
+    /// Sets the WASI `stderr` descriptor to an in-memory buffer which can be
+    /// retrieved after execution from the returned [`OutputBuffer`].
+    pub fn stderr_buffered(&mut self) -> Result<OutputBuffer> {
+        let buffer = OutputBuffer::default();
+        self.try_with_wasi(|wasi| match wasi {
+            WasiCtxBuilder::Preview1(_) => Err(anyhow!(
+                "`Store::stderr_buffered` only supported with WASI Preview 2"
+            )),
+            WasiCtxBuilder::Preview2(ctx) => {
+                ctx.stderr(BufferStdoutStream(buffer.clone()));
+                Ok(())
+            }
+        })?;
+        Ok(buffer)
+    }
+
     /// Inherit stdin from the host process.
     pub fn inherit_stderr(&mut self) {
         self.with_wasi(|wasi| match wasi {
@@ -301,6 +483,7 @@ impl StoreBuilder {
 
     /// Appends the given strings to the the WASI 'args'.
     pub fn args<'b>(&mut self, args: impl IntoIterator<Item = &'b str>) -> Result<()> {
+        self.has_args = true;
         self.try_with_wasi(|wasi| {
             for arg in args {
                 match wasi {
@@ -314,6 +497,26 @@ impl StoreBuilder {
         })
     }
 
+    /// Returns true if any WASI 'args' have been set.
+    pub fn has_args(&self) -> bool {
+        self.has_args
+    }
+
+    /// Sets the initial working directory of the guest, as the `PWD`
+    /// environment variable.
+    ///
+    /// WASI has no working directory of its own, and guest toolchains such as
+    /// wasi-libc start in `/` whatever `PWD` is set to, so this only takes
+    /// effect if the guest honours `PWD`, for example by changing to it
+    /// before resolving relative paths. The directory must be an absolute
+    /// guest path.
+    pub fn working_dir(&mut self, dir: &str) -> Result<()> {
+        if !dir.starts_with('/') {
+            bail!("working directory {dir:?} must be an absolute path");
+        }
+        self.env([("PWD", dir)])
+    }
+
     /// Sets the given key/value string entries on the the WASI 'env'.
     pub fn env(
         &mut self,
@@ -390,6 +593,11 @@ impl StoreBuilder {
         })
     }
 
+    /// Sends the guest's `wasi:http` outgoing requests with the given sender.
+    pub fn outgoing_request_sender(&mut self, sender: impl OutgoingRequestSender + 'static) {
+        self.outgoing_request_sender = Some(Box::new(sender));
+    }
+
     /// Returns a mutable reference to the built
     pub fn host_components_data(&mut self) -> &mut HostComponentsData {
         &mut self.host_components_data
@@ -401,6 +609,12 @@ impl StoreBuilder {
     pub fn build_with_data<T>(self, inner_data: T) -> Result<Store<T>> {
         let wasi = self.wasi.map_err(anyhow::Error::msg)?.build();
 
+        if self.max_fuel.is_some() && !self.consume_fuel {
+            tracing::warn!("Fuel limit set but fuel metering is disabled; ignoring limit");
+        }
+        // Without a limit, fuel is only metered, so start with as much as possible
+        let initial_fuel = self.max_fuel.unwrap_or(u64::MAX);
+
         let mut inner = wasmtime::Store::new(
             &self.engine,
             Data {
@@ -409,6 +623,8 @@ impl StoreBuilder {
                 host_components_data: self.host_components_data,
                 store_limits: self.store_limits,
                 table: wasi_preview2::Table::new(),
+                initial_fuel,
+                outgoing_request_sender: self.outgoing_request_sender,
             },
         );
 
@@ -420,10 +636,38 @@ impl StoreBuilder {
         // forever" for any plausible tick interval.
         inner.set_epoch_deadline(u64::MAX / 2);
 
-        Ok(Store {
+        let mut profiled_deadline = None;
+        if let Some(profile) = self.profile {
+            let deadline = ProfiledDeadline::default();
+            profiled_deadline = Some(deadline.clone());
+            inner.epoch_deadline_callback(move |ctx| {
+                profile.sample(&ctx);
+                if matches!(*deadline.lock().unwrap(), Some(deadline) if Instant::now() >= deadline)
+                {
+                    return Err(wasmtime::Trap::Interrupt.into());
+                }
+                Ok(wasmtime::UpdateDeadline::Yield(1))
+            });
+            inner.set_epoch_deadline(1);
+        }
+
+        if self.consume_fuel {
+            inner.set_fuel(initial_fuel)?;
+        }
+
+        let mut store = Store {
             inner,
             epoch_tick_interval: self.epoch_tick_interval,
-        })
+            created_at: Instant::now(),
+            on_drop: self.on_drop,
+            execution_time_limit: self.execution_time_limit,
+            deadline: None,
+            profiled_deadline,
+        };
+        if let Some(limit) = self.execution_time_limit {
+            store.set_deadline(Instant::now() + limit);
+        }
+        Ok(store)
     }
 
     /// Builds a [`Store`] from this builder with `Default` host state data.
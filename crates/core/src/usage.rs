@@ -0,0 +1,32 @@
+//! The `fermyon:spin/usage` interface, which lets a guest query the resources
+//! consumed by its current invocation.
+//!
+//! This reads the state of the Wasmtime store itself, so is linked directly
+//! rather than implemented by a [`crate::HostComponent`].
+
+use anyhow::Result;
+use wasmtime::StoreContextMut;
+
+use crate::{Data, Linker};
+
+const INTERFACE_NAME: &str = "fermyon:spin/usage@2.1.0";
+
+/// Adds the `fermyon:spin/usage` interface to the given [`Linker`].
+pub fn add_to_linker<T: Send + 'static>(linker: &mut Linker<T>) -> Result<()> {
+    let mut instance = linker.instance(INTERFACE_NAME)?;
+    instance.func_wrap(
+        "fuel-consumed",
+        |store: StoreContextMut<'_, Data<T>>, (): ()| {
+            let fuel_consumed = store
+                .get_fuel()
+                .ok()
+                .map(|remaining| store.data().fuel_consumed(remaining));
+            Ok((fuel_consumed,))
+        },
+    )?;
+    instance.func_wrap(
+        "memory-consumed",
+        |store: StoreContextMut<'_, Data<T>>, (): ()| Ok((store.data().memory_consumed(),)),
+    )?;
+    Ok(())
+}
@@ -34,6 +34,17 @@ fn main() -> Result {
                 p.read_volatile();
             }
         }
+        "args" => {
+            let args: Vec<_> = args.collect();
+            println!("{}", args.join(" "));
+        }
+        "read-from-pwd" => {
+            let path = args.next().expect("path");
+            let dir = std::env::var("PWD")?;
+            eprintln!("read {path} from {dir}");
+            std::env::set_current_dir(dir)?;
+            std::fs::read(path)?;
+        }
         "read" => {
             let path = args.next().expect("path");
             eprintln!("read {path}");
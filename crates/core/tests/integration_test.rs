@@ -1,6 +1,7 @@
 use std::{
     io::Cursor,
     path::PathBuf,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
@@ -22,6 +23,58 @@ async fn test_stdio() {
     assert_eq!(stdout, "DATA");
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_args() {
+    let stdout = run_core_wasi_test(["args", "--verbose", "input"], |store_builder| {
+        assert!(store_builder.has_args());
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stdout, "--verbose input");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_args_already_set_take_precedence() {
+    assert!(!test_engine()
+        .store_builder(WasiVersion::Preview2)
+        .has_args());
+
+    let stdout = run_core_wasi_test(["args", "trigger"], |store_builder| {
+        // As `AppComponent::apply_store_config` does with manifest args
+        if !store_builder.has_args() {
+            store_builder.args(["args", "manifest"]).unwrap();
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stdout, "trigger");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_working_dir() {
+    let filename = "test_file";
+    let tempdir = TempDir::new().unwrap();
+    std::fs::create_dir(tempdir.path().join("data")).unwrap();
+    std::fs::write(tempdir.path().join("data").join(filename), "x").unwrap();
+
+    run_core_wasi_test(["read-from-pwd", filename], |store_builder| {
+        store_builder
+            .read_only_preopened_dir(&tempdir, "/".into())
+            .unwrap();
+        store_builder.working_dir("/data").unwrap();
+    })
+    .await
+    .unwrap();
+}
+
+#[test]
+fn test_working_dir_must_be_absolute() {
+    let mut store_builder = test_engine().store_builder(WasiVersion::Preview2);
+    store_builder.working_dir("data").unwrap_err();
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_read_only_preopened_dir() {
     let filename = "test_file";
@@ -131,6 +184,98 @@ async fn test_set_deadline_violated() {
     assert_eq!(trap, Trap::Interrupt);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_time_limit_restarted_after_idling() {
+    run_core_wasi_test_engine(
+        &test_engine(),
+        ["sleep", "20"],
+        |store_builder| {
+            store_builder.execution_time_limit(Duration::from_millis(100));
+        },
+        |store| {
+            // Outlive the limit before running, as a warm instance would
+            std::thread::sleep(Duration::from_millis(200));
+            store.restart_execution_time_limit(None);
+        },
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_time_limit_restarted_with_shorter_limit() {
+    let err = run_core_wasi_test_engine(
+        &test_engine(),
+        ["sleep", "100"],
+        |store_builder| {
+            store_builder.execution_time_limit(Duration::from_millis(1000));
+        },
+        |store| {
+            store.restart_execution_time_limit(Some(Duration::from_millis(10)));
+        },
+    )
+    .await
+    .unwrap_err();
+    let trap = err.downcast::<Trap>().expect("trap");
+    assert_eq!(trap, Trap::Interrupt);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_execution_time_limit_violated() {
+    let err = run_core_wasi_test(["sleep", "100"], |store_builder| {
+        store_builder.execution_time_limit(Duration::from_millis(10));
+    })
+    .await
+    .unwrap_err();
+    let trap = err.downcast::<Trap>().expect("trap");
+    assert_eq!(trap, Trap::Interrupt);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_stats_on_drop() {
+    let mut config = test_config();
+    config.consume_fuel();
+    let engine = test_engine_with_config(&config);
+
+    let alloc = 1_000_000;
+    let stats = Arc::new(Mutex::new(None));
+    let stats_clone = stats.clone();
+    run_core_wasi_test_engine(
+        &engine,
+        ["alloc", &format!("{alloc}")],
+        |store_builder| {
+            store_builder.on_drop(move |stats| *stats_clone.lock().unwrap() = Some(stats.clone()));
+        },
+        |_| {},
+    )
+    .await
+    .unwrap();
+
+    let stats = stats.lock().unwrap().take().expect("on_drop wasn't called");
+    assert!(stats.memory_consumed >= alloc);
+    assert!(stats.fuel_consumed.unwrap() > 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_fuel_violated() {
+    let mut config = test_config();
+    config.consume_fuel();
+    let engine = test_engine_with_config(&config);
+
+    let err = run_core_wasi_test_engine(
+        &engine,
+        ["alloc", "1000000"],
+        |store_builder| {
+            store_builder.max_fuel(1_000);
+        },
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+    let trap = err.downcast::<Trap>().expect("trap");
+    assert_eq!(trap, Trap::OutOfFuel);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_host_component() {
     let stdout = run_core_wasi_test(["multiply", "5"], |_| {}).await.unwrap();
@@ -167,6 +312,21 @@ async fn test_panic() {
     assert_eq!(trap, Trap::UnreachableCodeReached);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+#[cfg(not(tarpaulin))]
+async fn test_panic_backtrace_symbolicated() {
+    // Unlike test_config, the default config leaves backtrace details to Spin
+    let engine = test_engine_with_config(&Config::default());
+    let err = run_core_wasi_test_engine(&engine, ["panic"], |_| {}, |_| {})
+        .await
+        .unwrap_err();
+    let report = format!("{err:?}");
+    assert!(
+        report.contains("main.rs"),
+        "backtrace not symbolicated: {report}"
+    );
+}
+
 fn test_config() -> Config {
     let mut config = Config::default();
     config
@@ -176,7 +336,11 @@ fn test_config() -> Config {
 }
 
 fn test_engine() -> Engine<()> {
-    let mut builder = Engine::builder(&test_config()).unwrap();
+    test_engine_with_config(&test_config())
+}
+
+fn test_engine_with_config(config: &Config) -> Engine<()> {
+    let mut builder = Engine::builder(config).unwrap();
     builder.add_host_component(MultiplierHostComponent).unwrap();
     builder
         .link_import(|l, _| wasmtime_wasi::preview2::command::add_to_linker(l))
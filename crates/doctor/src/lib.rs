@@ -10,6 +10,8 @@ use toml_edit::Document;
 
 /// Diagnoses for app manifest format problems.
 pub mod manifest;
+/// Diagnoses for runtime config problems.
+pub mod runtime_config;
 /// Diagnose for Rust-specific problems.
 pub mod rustlang;
 /// Test helpers.
@@ -38,10 +40,20 @@ impl Checkup {
             .add_diagnostic::<manifest::version::VersionDiagnostic>()
             .add_diagnostic::<manifest::trigger::TriggerDiagnostic>()
             .add_diagnostic::<rustlang::target::TargetDiagnostic>() // Do toolchain checks _before_ build check
-            .add_diagnostic::<wasm::missing::WasmMissingDiagnostic>();
+            .add_diagnostic::<wasm::missing::WasmMissingDiagnostic>()
+            .add_diagnostic::<runtime_config::parse::ParseDiagnostic>()
+            .add_diagnostic::<runtime_config::stores::StoresDiagnostic>()
+            .add_diagnostic::<runtime_config::variables::VariablesDiagnostic>();
         Ok(checkup)
     }
 
+    /// Check the runtime config file at the given path, rather than any
+    /// `runtime-config.toml` beside the app manifest.
+    pub fn set_runtime_config_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.patient.runtime_config_path = Some(path.into());
+        self
+    }
+
     /// Returns the [`PatientApp`] being checked.
     pub fn patient(&self) -> &PatientApp {
         &self.patient
@@ -82,6 +94,8 @@ pub struct PatientApp {
     pub manifest_path: PathBuf,
     /// Parsed app manifest TOML document.
     pub manifest_doc: Document,
+    /// Path to a runtime config file, if the app has one.
+    pub runtime_config_path: Option<PathBuf>,
 }
 
 impl PatientApp {
@@ -107,9 +121,15 @@ impl PatientApp {
             )
         })?;
 
+        let runtime_config_path = path
+            .parent()
+            .map(|dir| dir.join(runtime_config::DEFAULT_RUNTIME_CONFIG_FILE))
+            .filter(|path| path.is_file());
+
         Ok(Self {
             manifest_path: path,
             manifest_doc,
+            runtime_config_path,
         })
     }
 }
@@ -0,0 +1,111 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use spin_common::ui::quoted_path;
+use toml::Value;
+
+use crate::PatientApp;
+
+/// Diagnose runtime config files which can't be loaded.
+pub mod parse;
+/// Diagnose problems with key-value stores and SQLite databases.
+pub mod stores;
+/// Diagnose problems with variables providers.
+pub mod variables;
+
+/// The runtime config file name checked if none is given explicitly.
+pub const DEFAULT_RUNTIME_CONFIG_FILE: &str = "runtime-config.toml";
+
+// How long to wait when checking whether a service is reachable
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A parsed runtime config file.
+pub(crate) struct RuntimeConfigFile {
+    path: PathBuf,
+    config: Value,
+}
+
+impl RuntimeConfigFile {
+    /// Loads the patient's runtime config file, or returns `None` if it has none.
+    pub fn load(patient: &PatientApp) -> Result<Option<Self>> {
+        let Some(path) = &patient.runtime_config_path else {
+            return Ok(None);
+        };
+        let contents = std::fs::read_to_string(path).with_context(|| {
+            format!("Couldn't read runtime config file at {}", quoted_path(path))
+        })?;
+        let config = toml::from_str(&contents).with_context(|| {
+            format!(
+                "Couldn't parse runtime config file at {} as valid TOML",
+                quoted_path(path)
+            )
+        })?;
+        Ok(Some(Self {
+            path: path.clone(),
+            config,
+        }))
+    }
+
+    /// Returns the named tables of the given section, e.g. `[key_value_store.<name>]`.
+    pub fn named_tables<'a>(&'a self, section: &str) -> Vec<(&'a str, &'a Value)> {
+        self.config
+            .get(section)
+            .and_then(Value::as_table)
+            .map(|tables| tables.iter().map(|(k, v)| (k.as_str(), v)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the tables of the given array section, e.g. `[[variables_provider]]`.
+    pub fn array_tables<'a>(&'a self, section: &str) -> Vec<&'a Value> {
+        self.config
+            .get(section)
+            .and_then(Value::as_array)
+            .map(|tables| tables.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Resolves a path relative to the runtime config file, as Spin does.
+    pub fn resolve_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        match self.path.parent() {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_owned(),
+        }
+    }
+}
+
+/// Returns the string value of `key` in `table`, if it has one.
+pub(crate) fn str_field<'a>(table: &'a Value, key: &str) -> Option<&'a str> {
+    table.get(key).and_then(Value::as_str)
+}
+
+/// Checks that a TCP connection can be opened to the host of `url`.
+pub(crate) async fn check_reachable(url: &str) -> Result<()> {
+    let parsed = url::Url::parse(url).context("invalid URL")?;
+    let host = parsed.host_str().context("URL has no host")?;
+    let port = parsed
+        .port_or_known_default()
+        .or_else(|| default_port(parsed.scheme()))
+        .context("URL has no port")?;
+    match tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        tokio::net::TcpStream::connect((host, port)),
+    )
+    .await
+    {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(err)) => Err(err.into()),
+        Err(_) => Err(anyhow!("timed out connecting to {host}:{port}")),
+    }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "redis" | "rediss" => Some(6379),
+        "libsql" => Some(443),
+        _ => None,
+    }
+}
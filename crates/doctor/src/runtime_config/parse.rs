@@ -0,0 +1,175 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use toml::Value;
+
+use crate::{Diagnosis, Diagnostic, PatientApp};
+
+use super::{str_field, RuntimeConfigFile};
+
+// Top-level keys understood by Spin
+const KNOWN_SECTIONS: &[&str] = &[
+    "state_dir",
+    "log_dir",
+    "llm_compute",
+    "outbound_http",
+    "outbound_mysql",
+    "pooling_allocator",
+    "otel",
+    "variables",
+    "variables_provider",
+    "config_provider",
+    "key_value_store",
+    "sqlite_database",
+    "component_limits",
+];
+
+const KEY_VALUE_STORE_TYPES: &[&str] = &["spin", "redis", "azure_cosmos"];
+const SQLITE_DATABASE_TYPES: &[&str] = &["spin", "libsql"];
+const VARIABLES_PROVIDER_TYPES: &[&str] = &[
+    "env",
+    "vault",
+    "aws_secrets_manager",
+    "gcp_secret_manager",
+    "azure_key_vault",
+];
+
+/// ParseDiagnostic detects runtime config files which Spin can't load.
+#[derive(Default)]
+pub struct ParseDiagnostic;
+
+#[async_trait]
+impl Diagnostic for ParseDiagnostic {
+    type Diagnosis = RuntimeConfigInvalid;
+
+    async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        let file = match RuntimeConfigFile::load(patient) {
+            Ok(Some(file)) => file,
+            Ok(None) => return Ok(vec![]),
+            Err(err) => return Ok(vec![RuntimeConfigInvalid::Unloadable(format!("{err:#}"))]),
+        };
+
+        let mut diags = vec![];
+        if let Some(table) = file.config.as_table() {
+            diags.extend(
+                table
+                    .keys()
+                    .filter(|key| !KNOWN_SECTIONS.contains(&key.as_str()))
+                    .map(|key| RuntimeConfigInvalid::UnknownSection(key.clone())),
+            );
+        }
+        for (name, table) in file.named_tables("key_value_store") {
+            diags.extend(check_type(
+                "key_value_store",
+                name,
+                table,
+                KEY_VALUE_STORE_TYPES,
+            ));
+        }
+        for (name, table) in file.named_tables("sqlite_database") {
+            diags.extend(check_type(
+                "sqlite_database",
+                name,
+                table,
+                SQLITE_DATABASE_TYPES,
+            ));
+        }
+        for section in ["variables_provider", "config_provider"] {
+            for (index, table) in file.array_tables(section).into_iter().enumerate() {
+                let name = index.to_string();
+                diags.extend(check_type(section, &name, table, VARIABLES_PROVIDER_TYPES));
+            }
+        }
+        Ok(diags)
+    }
+}
+
+fn check_type(
+    section: &'static str,
+    name: &str,
+    table: &Value,
+    known_types: &'static [&'static str],
+) -> Option<RuntimeConfigInvalid> {
+    let store_type = str_field(table, "type");
+    if store_type.is_some_and(|store_type| known_types.contains(&store_type)) {
+        return None;
+    }
+    Some(RuntimeConfigInvalid::UnknownType {
+        section,
+        name: name.to_owned(),
+        store_type: store_type.map(str::to_owned),
+        known_types,
+    })
+}
+
+/// RuntimeConfigInvalid represents a runtime config file which Spin can't load.
+#[derive(Debug)]
+pub enum RuntimeConfigInvalid {
+    /// The file couldn't be read or parsed
+    Unloadable(String),
+    /// A top-level key unknown to Spin
+    UnknownSection(String),
+    /// A section with a missing or unknown type
+    UnknownType {
+        /// The kind of section, e.g. "key_value_store"
+        section: &'static str,
+        /// The name (or, for array sections, index) of the section
+        name: String,
+        /// The type given, if any
+        store_type: Option<String>,
+        /// The types Spin supports for this kind of section
+        known_types: &'static [&'static str],
+    },
+}
+
+impl Diagnosis for RuntimeConfigInvalid {
+    fn description(&self) -> String {
+        match self {
+            Self::Unloadable(msg) => msg.clone(),
+            Self::UnknownSection(key) => {
+                format!("Runtime config has unknown section {key:?}")
+            }
+            Self::UnknownType {
+                section,
+                name,
+                store_type,
+                known_types,
+            } => {
+                let known = known_types.join(", ");
+                match store_type {
+                    Some(store_type) => format!(
+                        "Runtime config {section} {name:?} has unknown type {store_type:?} (expected one of: {known})"
+                    ),
+                    None => format!(
+                        "Runtime config {section} {name:?} is missing a type (expected one of: {known})"
+                    ),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{assert_single_diagnosis, TestPatient};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_store_type() {
+        let patient = TestPatient::from_file("tests/data/manifest_version_correct.toml")
+            .with_runtime_config_str("[key_value_store.default]\ntype = 'reddis'");
+        let diag = assert_single_diagnosis::<ParseDiagnostic>(&patient).await;
+        assert!(matches!(
+            diag,
+            RuntimeConfigInvalid::UnknownType { store_type: Some(t), .. } if t == "reddis"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_toml() {
+        let patient = TestPatient::from_file("tests/data/manifest_version_correct.toml")
+            .with_runtime_config_str("[key_value_store");
+        let diag = assert_single_diagnosis::<ParseDiagnostic>(&patient).await;
+        assert!(matches!(diag, RuntimeConfigInvalid::Unloadable(_)));
+    }
+}
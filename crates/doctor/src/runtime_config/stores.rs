@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use spin_common::ui::quoted_path;
+use toml::Value;
+
+use crate::{Diagnosis, Diagnostic, PatientApp};
+
+use super::{check_reachable, str_field, RuntimeConfigFile};
+
+/// StoresDiagnostic detects key-value stores and SQLite databases that Spin
+/// won't be able to open.
+#[derive(Default)]
+pub struct StoresDiagnostic;
+
+#[async_trait]
+impl Diagnostic for StoresDiagnostic {
+    type Diagnosis = StoreProblem;
+
+    async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        // Unloadable files are reported by the parse diagnostic
+        let Ok(Some(file)) = RuntimeConfigFile::load(patient) else {
+            return Ok(vec![]);
+        };
+
+        let mut diags = vec![];
+        for section in ["key_value_store", "sqlite_database"] {
+            for (name, table) in file.named_tables(section) {
+                let store = Store {
+                    section,
+                    name: name.to_owned(),
+                };
+                diags.extend(store.diagnose(&file, table).await);
+            }
+        }
+        Ok(diags)
+    }
+}
+
+/// Identifies a `[<section>.<name>]` store config.
+#[derive(Debug)]
+pub struct Store {
+    section: &'static str,
+    name: String,
+}
+
+impl Store {
+    async fn diagnose(self, file: &RuntimeConfigFile, table: &Value) -> Option<StoreProblem> {
+        match str_field(table, "type")? {
+            "spin" => {
+                let path = file.resolve_path(str_field(table, "path")?);
+                let reason = bad_path_reason(&path)?;
+                Some(StoreProblem::BadPath {
+                    store: self,
+                    path,
+                    reason,
+                })
+            }
+            "redis" | "libsql" => {
+                let url = str_field(table, "url")?;
+                if let Some(var) = str_field(table, "token_env") {
+                    if std::env::var_os(var).is_none() {
+                        return Some(StoreProblem::MissingTokenEnv {
+                            store: self,
+                            var: var.to_owned(),
+                        });
+                    }
+                }
+                let error = check_reachable(url).await.err()?;
+                Some(StoreProblem::Unreachable {
+                    store: self,
+                    url: url.to_owned(),
+                    error: format!("{error:#}"),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+// Spin creates missing parent directories, so a path is only unusable if
+// something other than a directory is in the way.
+fn bad_path_reason(path: &Path) -> Option<&'static str> {
+    if path.is_dir() {
+        return Some("is a directory");
+    }
+    path.ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.exists())
+        .filter(|ancestor| !ancestor.is_dir())
+        .map(|_| "has a parent which is not a directory")
+}
+
+/// StoreProblem represents a key-value store or SQLite database that Spin
+/// won't be able to open.
+#[derive(Debug)]
+pub enum StoreProblem {
+    /// A database file path which can't be created or opened
+    BadPath {
+        /// The store config
+        store: Store,
+        /// The resolved database path
+        path: PathBuf,
+        /// Why the path can't be used
+        reason: &'static str,
+    },
+    /// A server which couldn't be connected to
+    Unreachable {
+        /// The store config
+        store: Store,
+        /// The server URL
+        url: String,
+        /// The connection error
+        error: String,
+    },
+    /// An auth token environment variable which isn't set
+    MissingTokenEnv {
+        /// The store config
+        store: Store,
+        /// The environment variable name
+        var: String,
+    },
+}
+
+impl Diagnosis for StoreProblem {
+    fn description(&self) -> String {
+        match self {
+            Self::BadPath {
+                store,
+                path,
+                reason,
+            } => format!(
+                "Runtime config {} {:?} path {} {reason}",
+                store.section,
+                store.name,
+                quoted_path(path)
+            ),
+            Self::Unreachable { store, url, error } => format!(
+                "Runtime config {} {:?} server {url} is unreachable: {error}",
+                store.section, store.name
+            ),
+            Self::MissingTokenEnv { store, var } => format!(
+                "Runtime config {} {:?} reads its token from {var}, which is not set",
+                store.section, store.name
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{assert_single_diagnosis, TestPatient};
+
+    use super::*;
+
+    const MANIFEST: &str = "tests/data/manifest_version_correct.toml";
+
+    #[tokio::test]
+    async fn test_sqlite_path_is_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let patient = TestPatient::from_file(MANIFEST).with_runtime_config_str(format!(
+            "[sqlite_database.default]\ntype = 'spin'\npath = {:?}",
+            dir.path()
+        ));
+        let diag = assert_single_diagnosis::<StoresDiagnostic>(&patient).await;
+        assert!(matches!(diag, StoreProblem::BadPath { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_missing_sqlite_parent_is_ok() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new/dir/data.db");
+        let patient = TestPatient::from_file(MANIFEST).with_runtime_config_str(format!(
+            "[sqlite_database.default]\ntype = 'spin'\npath = {path:?}"
+        ));
+        let diags = StoresDiagnostic.diagnose(&patient).await.unwrap();
+        assert!(diags.is_empty(), "expected no diagnoses; got {diags:?}");
+    }
+
+    #[tokio::test]
+    async fn test_redis_reachability() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let patient = TestPatient::from_file(MANIFEST).with_runtime_config_str(format!(
+            "[key_value_store.default]\ntype = 'redis'\nurl = 'redis://127.0.0.1:{port}'"
+        ));
+        let diags = StoresDiagnostic.diagnose(&patient).await.unwrap();
+        assert!(diags.is_empty(), "expected no diagnoses; got {diags:?}");
+
+        drop(listener);
+        let diag = assert_single_diagnosis::<StoresDiagnostic>(&patient).await;
+        assert!(matches!(diag, StoreProblem::Unreachable { .. }));
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{Diagnosis, Diagnostic, PatientApp};
+
+use super::{check_reachable, str_field, RuntimeConfigFile};
+
+/// VariablesDiagnostic detects variables providers that Spin won't be able
+/// to read from.
+#[derive(Default)]
+pub struct VariablesDiagnostic;
+
+#[async_trait]
+impl Diagnostic for VariablesDiagnostic {
+    type Diagnosis = VariablesProviderProblem;
+
+    async fn diagnose(&self, patient: &PatientApp) -> Result<Vec<Self::Diagnosis>> {
+        // Unloadable files are reported by the parse diagnostic
+        let Ok(Some(file)) = RuntimeConfigFile::load(patient) else {
+            return Ok(vec![]);
+        };
+
+        let mut diags = vec![];
+        let providers = file
+            .array_tables("variables_provider")
+            .into_iter()
+            .chain(file.array_tables("config_provider"));
+        for provider in providers {
+            if str_field(provider, "type") != Some("vault") {
+                continue;
+            }
+            let Some(url) = str_field(provider, "url") else {
+                continue;
+            };
+            let url = url.to_owned();
+            match (provider.get("token"), provider.get("approle")) {
+                (None, None) => diags.push(VariablesProviderProblem::VaultMissingToken { url }),
+                (Some(_), Some(_)) => {
+                    diags.push(VariablesProviderProblem::VaultConflictingAuth { url })
+                }
+                _ => {
+                    if let Err(error) = check_reachable(&url).await {
+                        diags.push(VariablesProviderProblem::Unreachable {
+                            url,
+                            error: format!("{error:#}"),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(diags)
+    }
+}
+
+/// VariablesProviderProblem represents a variables provider that Spin won't
+/// be able to read from.
+#[derive(Debug)]
+pub enum VariablesProviderProblem {
+    /// A Vault provider with no means of authenticating
+    VaultMissingToken {
+        /// The Vault server URL
+        url: String,
+    },
+    /// A Vault provider with both a token and AppRole credentials
+    VaultConflictingAuth {
+        /// The Vault server URL
+        url: String,
+    },
+    /// A provider server which couldn't be connected to
+    Unreachable {
+        /// The server URL
+        url: String,
+        /// The connection error
+        error: String,
+    },
+}
+
+impl Diagnosis for VariablesProviderProblem {
+    fn description(&self) -> String {
+        match self {
+            Self::VaultMissingToken { url } => format!(
+                "Runtime config Vault variables provider {url} has no token; set 'token' or 'approle'"
+            ),
+            Self::VaultConflictingAuth { url } => format!(
+                "Runtime config Vault variables provider {url} sets both 'token' and 'approle'"
+            ),
+            Self::Unreachable { url, error } => {
+                format!("Runtime config variables provider server {url} is unreachable: {error}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{assert_single_diagnosis, TestPatient};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_vault_missing_token() {
+        let patient = TestPatient::from_file("tests/data/manifest_version_correct.toml")
+            .with_runtime_config_str(
+                "[[variables_provider]]\ntype = 'vault'\nurl = 'http://127.0.0.1:8200'\nmount = 'secret'",
+            );
+        let diag = assert_single_diagnosis::<VariablesDiagnostic>(&patient).await;
+        assert!(matches!(
+            diag,
+            VariablesProviderProblem::VaultMissingToken { .. }
+        ));
+    }
+}
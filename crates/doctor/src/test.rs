@@ -64,6 +64,7 @@ fn test_file_path(prefix: &str, suffix: &str) -> PathBuf {
 pub struct TestPatient {
     inner: PatientApp,
     _manifest_temp: TempPath,
+    _runtime_config_temp: Option<TempPath>,
 }
 
 impl TestPatient {
@@ -72,6 +73,7 @@ impl TestPatient {
         Ok(Self {
             inner,
             _manifest_temp: manifest_temp,
+            _runtime_config_temp: None,
         })
     }
 
@@ -99,6 +101,17 @@ impl TestPatient {
     pub fn from_toml_str(manifest: impl AsRef<str>) -> Self {
         Self::from_toml(toml::from_str::<Value>(manifest.as_ref()).expect("valid TOML"))
     }
+
+    pub fn with_runtime_config_str(mut self, runtime_config: impl AsRef<str>) -> Self {
+        let mut runtime_config_file = NamedTempFile::new().expect("creating tempfile");
+        runtime_config_file
+            .write_all(runtime_config.as_ref().as_bytes())
+            .expect("writing runtime config");
+        let runtime_config_temp = runtime_config_file.into_temp_path();
+        self.inner.runtime_config_path = Some(runtime_config_temp.to_path_buf());
+        self._runtime_config_temp = Some(runtime_config_temp);
+        self
+    }
 }
 
 impl std::ops::Deref for TestPatient {
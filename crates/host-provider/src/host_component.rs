@@ -0,0 +1,43 @@
+use crate::{HostProvider, HostProviderDispatch};
+use spin_app::{AppComponent, DynamicHostComponent};
+use spin_core::HostComponent;
+use std::{collections::HashMap, sync::Arc};
+
+pub struct HostProviderComponent {
+    providers: Arc<HashMap<String, HostProvider>>,
+}
+
+impl HostProviderComponent {
+    pub fn new(providers: impl IntoIterator<Item = (String, HostProvider)>) -> Self {
+        Self {
+            providers: Arc::new(providers.into_iter().collect()),
+        }
+    }
+}
+
+impl HostComponent for HostProviderComponent {
+    type Data = HostProviderDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        spin_world::v2_1::host_provider::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        HostProviderDispatch {
+            providers: self.providers.clone(),
+            component_id: String::new(),
+        }
+    }
+}
+
+impl DynamicHostComponent for HostProviderComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        // Providers are told which component is calling, and may be limited
+        // to some components
+        data.component_id = component.id().to_owned();
+        Ok(())
+    }
+}
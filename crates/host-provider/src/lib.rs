@@ -0,0 +1,264 @@
+//! Host providers: processes run by the platform alongside Spin which
+//! implement APIs for components, with credentials and network access of
+//! their own.
+//!
+//! Components call a provider through `fermyon:spin/host-provider`, usually
+//! from a provider adapter which implements a typed interface on top of it.
+//! Each call opens a connection to the provider and sends the calling
+//! component's ID followed by the request, each as a 4-byte big-endian length
+//! followed by that many bytes. The provider replies with a status byte, 0 for
+//! success or 1 for failure, followed by the response or a UTF-8 error
+//! message, framed the same way.
+
+use anyhow::{bail, Result};
+use spin_core::async_trait;
+use spin_world::v2_1::host_provider::{self, Error};
+use std::{collections::HashMap, io, path::PathBuf, sync::Arc, time::Duration};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+mod host_component;
+
+pub use host_component::HostProviderComponent;
+
+/// The longest a provider may take to handle a call.
+const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest response accepted from a provider.
+const MAX_RESPONSE_LEN: usize = 16 * 1024 * 1024;
+
+const STATUS_OK: u8 = 0;
+
+/// The address of a host provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProviderAddress {
+    /// A TCP `host:port` address.
+    Tcp(String),
+    /// A Unix domain socket, given as `unix:<path>`.
+    Unix(PathBuf),
+}
+
+impl ProviderAddress {
+    /// Parses a `host:port` or `unix:<path>` address.
+    pub fn parse(address: &str) -> Result<Self> {
+        match address.strip_prefix("unix:") {
+            Some(path) if path.is_empty() => bail!("invalid host provider address {address:?}"),
+            Some(path) => Ok(Self::Unix(PathBuf::from(path))),
+            None if address.rsplit_once(':').is_some() => Ok(Self::Tcp(address.to_owned())),
+            None => bail!("host provider address {address:?} must be `host:port` or `unix:<path>`"),
+        }
+    }
+}
+
+/// A host provider, and the components allowed to call it.
+#[derive(Clone, Debug)]
+pub struct HostProvider {
+    address: ProviderAddress,
+    components: Option<Vec<String>>,
+    timeout: Duration,
+}
+
+impl HostProvider {
+    /// Creates a provider listening at the given address. If `components` is
+    /// given, only those components may call the provider.
+    pub fn new(address: ProviderAddress, components: Option<Vec<String>>) -> Self {
+        Self {
+            address,
+            components,
+            timeout: CALL_TIMEOUT,
+        }
+    }
+
+    fn allows(&self, component_id: &str) -> bool {
+        match &self.components {
+            Some(components) => components.iter().any(|id| id == component_id),
+            None => true,
+        }
+    }
+
+    /// Sends a request to the provider on behalf of the given component.
+    /// Returns the provider's response, or the error message it failed with.
+    async fn call(
+        &self,
+        component_id: &str,
+        request: &[u8],
+    ) -> io::Result<Result<Vec<u8>, String>> {
+        let call = async {
+            match &self.address {
+                ProviderAddress::Tcp(address) => {
+                    let stream = tokio::net::TcpStream::connect(address).await?;
+                    exchange(stream, component_id, request).await
+                }
+                #[cfg(unix)]
+                ProviderAddress::Unix(path) => {
+                    let stream = tokio::net::UnixStream::connect(path).await?;
+                    exchange(stream, component_id, request).await
+                }
+                #[cfg(not(unix))]
+                ProviderAddress::Unix(_) => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Unix domain sockets are not supported on this platform",
+                )),
+            }
+        };
+        tokio::time::timeout(self.timeout, call)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "host provider timed out"))?
+    }
+}
+
+async fn exchange(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    component_id: &str,
+    request: &[u8],
+) -> io::Result<Result<Vec<u8>, String>> {
+    write_frame(&mut stream, component_id.as_bytes()).await?;
+    write_frame(&mut stream, request).await?;
+    stream.flush().await?;
+
+    let status = stream.read_u8().await?;
+    let payload = read_frame(&mut stream).await?;
+    if status == STATUS_OK {
+        Ok(Ok(payload))
+    } else {
+        Ok(Err(String::from_utf8_lossy(&payload).into_owned()))
+    }
+}
+
+async fn write_frame(stream: &mut (impl AsyncWrite + Unpin), bytes: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "request too large"))?;
+    stream.write_u32(len).await?;
+    stream.write_all(bytes).await
+}
+
+async fn read_frame(stream: &mut (impl AsyncRead + Unpin)) -> io::Result<Vec<u8>> {
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_RESPONSE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("host provider response of {len} bytes exceeds {MAX_RESPONSE_LEN} bytes"),
+        ));
+    }
+    let mut bytes = vec![0; len];
+    stream.read_exact(&mut bytes).await?;
+    Ok(bytes)
+}
+
+/// Dispatches a component's calls to the host providers it may use.
+pub struct HostProviderDispatch {
+    providers: Arc<HashMap<String, HostProvider>>,
+    component_id: String,
+}
+
+#[async_trait]
+impl host_provider::Host for HostProviderDispatch {
+    async fn call(&mut self, provider: String, request: Vec<u8>) -> Result<Result<Vec<u8>, Error>> {
+        let Some(host_provider) = self
+            .providers
+            .get(&provider)
+            .filter(|p| p.allows(&self.component_id))
+        else {
+            return Ok(Err(Error::NoSuchProvider));
+        };
+        match host_provider.call(&self.component_id, &request).await {
+            Ok(Ok(response)) => Ok(Ok(response)),
+            Ok(Err(message)) => Ok(Err(Error::ProviderError(message))),
+            Err(err) => {
+                tracing::warn!("Host provider {provider:?} is unavailable: {err}");
+                Ok(Err(Error::Unavailable(err.to_string())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    // Serves one call, replying with the component ID and request joined by
+    // a space, or failing if the request is empty.
+    async fn serve_once(listener: TcpListener) -> io::Result<()> {
+        let (mut stream, _) = listener.accept().await?;
+        let component_id = read_frame(&mut stream).await?;
+        let request = read_frame(&mut stream).await?;
+        if request.is_empty() {
+            stream.write_u8(1).await?;
+            write_frame(&mut stream, b"empty request").await
+        } else {
+            stream.write_u8(STATUS_OK).await?;
+            write_frame(&mut stream, &[component_id, request].join(&b' ')).await
+        }
+    }
+
+    async fn dispatch(components: Option<Vec<String>>) -> (HostProviderDispatch, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = ProviderAddress::Tcp(listener.local_addr().unwrap().to_string());
+        let providers =
+            HashMap::from([("billing".to_owned(), HostProvider::new(address, components))]);
+        let dispatch = HostProviderDispatch {
+            providers: Arc::new(providers),
+            component_id: "checkout".to_owned(),
+        };
+        (dispatch, listener)
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(
+            ProviderAddress::parse("127.0.0.1:7000").unwrap(),
+            ProviderAddress::Tcp("127.0.0.1:7000".to_owned())
+        );
+        assert_eq!(
+            ProviderAddress::parse("unix:/run/billing.sock").unwrap(),
+            ProviderAddress::Unix(PathBuf::from("/run/billing.sock"))
+        );
+        assert!(ProviderAddress::parse("billing").is_err());
+        assert!(ProviderAddress::parse("unix:").is_err());
+    }
+
+    #[tokio::test]
+    async fn calls_provider_with_component_id() -> Result<()> {
+        let (mut dispatch, listener) = dispatch(None).await;
+        let server = tokio::spawn(serve_once(listener));
+        let response =
+            host_provider::Host::call(&mut dispatch, "billing".into(), b"charge".to_vec()).await?;
+        assert_eq!(response.unwrap(), b"checkout charge");
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn returns_provider_errors() -> Result<()> {
+        let (mut dispatch, listener) = dispatch(None).await;
+        let server = tokio::spawn(serve_once(listener));
+        let response = host_provider::Host::call(&mut dispatch, "billing".into(), vec![]).await?;
+        assert!(
+            matches!(response, Err(Error::ProviderError(message)) if message == "empty request")
+        );
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refuses_components_not_allowed() -> Result<()> {
+        let (mut dispatch, _listener) = dispatch(Some(vec!["admin".to_owned()])).await;
+        let response =
+            host_provider::Host::call(&mut dispatch, "billing".into(), b"charge".to_vec()).await?;
+        assert!(matches!(response, Err(Error::NoSuchProvider)));
+        let response =
+            host_provider::Host::call(&mut dispatch, "shipping".into(), b"charge".to_vec()).await?;
+        assert!(matches!(response, Err(Error::NoSuchProvider)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_unreachable_providers() -> Result<()> {
+        let (mut dispatch, listener) = dispatch(None).await;
+        drop(listener);
+        let response =
+            host_provider::Host::call(&mut dispatch, "billing".into(), b"charge".to_vec()).await?;
+        assert!(matches!(response, Err(Error::Unavailable(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+//! Inbound requests recorded by the HTTP trigger's request capture mode, to be
+//! re-sent by `spin replay`.
+
+use std::{io::Write, path::Path};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+/// Headers whose values are replaced with [`REDACTED`] unless a capture is
+/// made with [`CapturedRequest::new_unredacted`], as they carry credentials.
+pub const SENSITIVE_HEADERS: &[http::HeaderName] = &[
+    http::header::AUTHORIZATION,
+    http::header::PROXY_AUTHORIZATION,
+    http::header::COOKIE,
+];
+
+/// The value recorded for a redacted header.
+pub const REDACTED: &str = "[redacted]";
+
+/// An inbound request, as recorded to a JSON file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapturedRequest {
+    /// Request method
+    pub method: String,
+    /// Request path and query
+    pub uri: String,
+    /// Request headers, in the order received
+    pub headers: Vec<(String, String)>,
+    /// Request body, base64-encoded
+    pub body: String,
+}
+
+impl CapturedRequest {
+    /// Captures a request from its head and its buffered body, redacting any
+    /// [`SENSITIVE_HEADERS`].
+    pub fn new(parts: &http::request::Parts, body: &[u8]) -> Self {
+        let mut captured = Self::new_unredacted(parts, body);
+        for (name, value) in &mut captured.headers {
+            if SENSITIVE_HEADERS
+                .iter()
+                .any(|sensitive| sensitive.as_str() == *name)
+            {
+                *value = REDACTED.to_owned();
+            }
+        }
+        captured
+    }
+
+    /// Captures a request from its head and its buffered body, including the
+    /// values of any [`SENSITIVE_HEADERS`].
+    pub fn new_unredacted(parts: &http::request::Parts, body: &[u8]) -> Self {
+        let uri = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "/".to_owned());
+        let headers = parts
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        Self {
+            method: parts.method.to_string(),
+            uri,
+            headers,
+            body: STANDARD.encode(body),
+        }
+    }
+
+    /// Returns the decoded request body.
+    pub fn body(&self) -> Result<Vec<u8>> {
+        STANDARD
+            .decode(&self.body)
+            .context("Captured request body is not valid base64")
+    }
+
+    /// Loads a captured request from a JSON file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read(path)
+            .with_context(|| format!("Failed to read captured request {}", path.display()))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("Failed to parse captured request {}", path.display()))
+    }
+
+    /// Writes the request to a new JSON file, which
+    /// [`CapturedRequest::from_file`] can load again. On Unix the file is
+    /// readable only by its owner.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_vec_pretty(self)?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(&contents))
+            .with_context(|| format!("Failed to write captured request {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captured_requests_load_again() -> Result<()> {
+        let (parts, ()) = http::Request::post("http://localhost:3000/users?active=true")
+            .header("content-type", "application/json")
+            .header("x-trace", "a")
+            .header("x-trace", "b")
+            .body(())?
+            .into_parts();
+        let captured = CapturedRequest::new(&parts, br#"{"name": "Ada"}"#);
+        assert_eq!(captured.uri, "/users?active=true");
+        assert_eq!(captured.headers.len(), 3);
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("request.json");
+        captured.save(&path)?;
+        let loaded = CapturedRequest::from_file(&path)?;
+        assert_eq!(loaded, captured);
+        assert_eq!(loaded.body()?, br#"{"name": "Ada"}"#);
+        Ok(())
+    }
+
+    #[test]
+    fn sensitive_headers_are_redacted() -> Result<()> {
+        let (parts, ()) = http::Request::get("http://localhost:3000/")
+            .header("authorization", "Bearer secret")
+            .header("cookie", "session=secret")
+            .header("accept", "text/html")
+            .body(())?
+            .into_parts();
+        let captured = CapturedRequest::new(&parts, b"");
+        assert_eq!(
+            captured.headers,
+            [
+                ("authorization".to_owned(), REDACTED.to_owned()),
+                ("cookie".to_owned(), REDACTED.to_owned()),
+                ("accept".to_owned(), "text/html".to_owned()),
+            ]
+        );
+
+        let unredacted = CapturedRequest::new_unredacted(&parts, b"");
+        assert_eq!(unredacted.headers[0].1, "Bearer secret");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn captured_requests_are_private() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (parts, ()) = http::Request::get("http://localhost:3000/")
+            .body(())?
+            .into_parts();
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("request.json");
+        CapturedRequest::new(&parts, b"").save(&path)?;
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        Ok(())
+    }
+}
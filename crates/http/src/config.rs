@@ -1,16 +1,235 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 /// Configuration for the HTTP trigger
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct HttpTriggerConfig {
-    /// Component ID to invoke
+    /// Component ID to invoke. Empty for routes which serve a static
+    /// directory.
+    #[serde(default)]
     pub component: String,
-    /// HTTP route the component will be invoked for
+    /// HTTP route the component will be invoked for. A `:name` segment
+    /// matches any one segment, and is passed to the component as the
+    /// `spin-path-match-<name>` header
     pub route: String,
+    /// Hostname the route is constrained to, such as `api.example.com` or
+    /// `*.example.com`. Routes without a host serve requests for any host
+    /// which has no route of its own for the path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// A directory served by the host for this route instead of invoking a
+    /// component. Relative paths are resolved from the manifest's directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub static_dir: Option<String>,
     /// The HTTP executor the component requires
     #[serde(default)]
     pub executor: Option<HttpExecutorType>,
+    /// How long, in milliseconds, an invocation may run before it is
+    /// interrupted and the request fails with 503 Service Unavailable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_timeout_ms: Option<u64>,
+    /// The most requests the component may handle at once. Excess requests
+    /// wait for a request to finish
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<usize>,
+    /// With `max_concurrency`, the most requests which may wait for the
+    /// component, beyond which requests fail with 429 Too Many Requests.
+    /// Unlimited if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_queued_requests: Option<usize>,
+    /// The number of instances of the component to keep ready ahead of
+    /// requests, so that requests don't wait for instantiation. Each instance
+    /// still handles only one request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_instances: Option<usize>,
+    /// How server-sent event stream responses are sent
+    #[serde(default)]
+    pub event_stream: EventStreamConfig,
+    /// Middleware run, in order, before the component handles a request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub middleware: Vec<MiddlewareConfig>,
+    /// Caching of the component's responses, if enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheConfig>,
+    /// Compression of the component's responses, if enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionConfig>,
+}
+
+/// Response compression configuration for the HTTP trigger.
+///
+/// Responses are compressed with gzip or brotli, as the client accepts, as
+/// they are streamed from the component.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// Responses with a smaller `content-length` are sent uncompressed.
+    /// Responses of unknown length are always compressed.
+    pub min_size: u64,
+    /// Content types which are compressed. An entry ending in `/*` matches
+    /// any subtype.
+    pub content_types: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 1024,
+            content_types: [
+                "text/html",
+                "text/css",
+                "text/plain",
+                "text/javascript",
+                "application/javascript",
+                "application/json",
+                "application/xml",
+                "image/svg+xml",
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+        }
+    }
+}
+
+/// Response caching configuration for the HTTP trigger.
+///
+/// Only successful responses to `GET` and `HEAD` requests are cached, and
+/// responses which set cookies or forbid caching are never stored.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// How long, in seconds, a response is served from the cache
+    pub ttl_secs: u64,
+    /// Request headers whose values distinguish cached responses, in addition
+    /// to the method and path
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vary: Vec<String>,
+}
+
+/// Server-sent events configuration for the HTTP trigger.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventStreamConfig {
+    /// How often, in seconds, to send a keep-alive comment while the
+    /// component is not writing to the stream. Zero disables keep-alives.
+    pub keep_alive_secs: u64,
+    /// How the component's output is framed as events.
+    pub framing: EventStreamFraming,
+}
+
+impl Default for EventStreamConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive_secs: 15,
+            framing: Default::default(),
+        }
+    }
+}
+
+/// How a component's output is framed as server-sent events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventStreamFraming {
+    /// The component writes `text/event-stream` framing itself. Responses are
+    /// only treated as event streams if they have that content type.
+    #[default]
+    None,
+    /// Each line the component writes is sent as the data of an event, and
+    /// the response is always sent as an event stream.
+    Lines,
+}
+
+/// A built-in middleware which handles requests before they reach the
+/// component.
+///
+/// Middleware runs in the order it is listed for a route. Each may answer or
+/// reject a request itself, in which case later middleware and the component
+/// don't run, and each sees the response on its way back out.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", tag = "type")]
+pub enum MiddlewareConfig {
+    /// Answers CORS preflight requests and adds CORS headers to responses.
+    Cors(CorsConfig),
+    /// Rejects requests without one of the given bearer tokens.
+    ///
+    /// Tokens are app variables, resolved by the runtime config's variables
+    /// providers when the trigger starts, so that they aren't kept in the
+    /// manifest.
+    BearerAuth {
+        /// The names of the app variables holding the accepted tokens
+        token_variables: Vec<String>,
+    },
+    /// Rejects requests without valid HTTP basic auth credentials.
+    ///
+    /// Passwords are app variables, resolved like bearer tokens.
+    BasicAuth {
+        /// Accepted usernames, mapped to the names of the app variables
+        /// holding their passwords
+        password_variables: HashMap<String, String>,
+        /// The realm sent to clients which must authenticate
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        realm: Option<String>,
+    },
+    /// Rejects requests from client addresses outside the given networks.
+    IpAllowlist {
+        /// Allowed addresses or CIDR networks, e.g. `10.0.0.0/8`
+        allowed: Vec<String>,
+    },
+    /// Limits the rate of requests using a token bucket per client address.
+    RateLimit(RateLimitConfig),
+}
+
+/// CORS middleware configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to make requests, or `*` for any origin
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed in cross-origin requests
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed in cross-origin requests
+    pub allowed_headers: Vec<String>,
+    /// Whether cross-origin requests may include credentials
+    pub allow_credentials: bool,
+    /// How long, in seconds, clients may cache preflight responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_owned()],
+            allowed_methods: ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"]
+                .map(str::to_owned)
+                .to_vec(),
+            allowed_headers: vec![],
+            allow_credentials: false,
+            max_age_secs: None,
+        }
+    }
+}
+
+/// Rate limiting middleware configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// The sustained number of requests allowed per second
+    pub requests_per_second: f64,
+    /// The number of requests which may be made at once after a quiet period.
+    /// Defaults to one second's worth of requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+    /// Whether each client address gets its own limit, rather than all
+    /// clients sharing one
+    #[serde(default = "default_per_client")]
+    pub per_client: bool,
+}
+
+fn default_per_client() -> bool {
+    true
 }
 
 /// The executor for the HTTP component.
@@ -76,4 +295,31 @@ mod tests {
         assert_eq!(config.entrypoint, "_start");
         assert_eq!(config.argv, "${SCRIPT_NAME} ${ARGS}");
     }
+
+    #[test]
+    fn middleware_config_smoke_test() {
+        let config: HttpTriggerConfig = toml::toml! {
+            component = "api"
+            route = "/api/..."
+
+            [[middleware]]
+            type = "cors"
+            allowed_origins = ["https://example.com"]
+
+            [[middleware]]
+            type = "rate_limit"
+            requests_per_second = 5.0
+        }
+        .try_into()
+        .unwrap();
+        let [MiddlewareConfig::Cors(cors), MiddlewareConfig::RateLimit(rate_limit)] =
+            config.middleware.as_slice()
+        else {
+            panic!("wrong middleware: {:?}", config.middleware);
+        };
+        assert_eq!(cors.allowed_origins, ["https://example.com"]);
+        assert_eq!(cors.allowed_methods.len(), 6);
+        assert!(rate_limit.per_client);
+        assert_eq!(rate_limit.burst, None);
+    }
 }
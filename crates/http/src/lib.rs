@@ -2,6 +2,7 @@
 pub use wasmtime_wasi_http::body::HyperIncomingBody as Body;
 
 pub mod app_info;
+pub mod capture;
 pub mod config;
 pub mod routes;
 pub mod trigger;
@@ -2,7 +2,7 @@
 
 #![deny(missing_docs)]
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use http::Uri;
 use indexmap::IndexMap;
 use std::{borrow::Cow, fmt};
@@ -22,6 +22,42 @@ pub struct DuplicateRoute {
     pub replaced_id: String,
     /// The component ID corresponding to the duplicated route.
     pub effective_id: String,
+    /// The hostname the duplicated route is constrained to, if any.
+    pub host: Option<String>,
+}
+
+impl DuplicateRoute {
+    /// Formats duplicate routes as a table of each duplicated route, the
+    /// component it is unreachable for, and the component which handles it.
+    pub fn table(duplicates: &[DuplicateRoute]) -> String {
+        let header = ["ROUTE", "UNREACHABLE", "HANDLED BY"].map(String::from);
+        let rows = duplicates.iter().map(|dup| {
+            // Of three or more duplicates, only the last handles the route
+            let winner = duplicates
+                .iter()
+                .rev()
+                .find(|other| other.route == dup.route && other.host == dup.host)
+                .map_or(&dup.effective_id, |other| &other.effective_id);
+            [
+                format!(
+                    "{}{}",
+                    dup.host.as_deref().unwrap_or_default(),
+                    dup.route.full_pattern_non_empty()
+                ),
+                dup.replaced_id.clone(),
+                winner.clone(),
+            ]
+        });
+        let rows = std::iter::once(header).chain(rows).collect::<Vec<_>>();
+        let width = |col: usize| rows.iter().map(|row| row[col].len()).max().unwrap_or(0);
+        let (route_width, unreachable_width) = (width(0), width(1));
+        rows.iter()
+            .map(|[route, unreachable, winner]| {
+                format!("  {route:route_width$}  {unreachable:unreachable_width$}  {winner}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 impl Router {
@@ -38,12 +74,14 @@ impl Router {
         });
 
         for (route, component_id) in routes_iter {
+            route.validate_params()?;
             let replaced = routes.insert(route.clone(), component_id.clone());
             if let Some(replaced) = replaced {
                 duplicates.push(DuplicateRoute {
                     route: route.clone(),
                     replaced_id: replaced,
                     effective_id: component_id.clone(),
+                    host: None,
                 });
             }
         }
@@ -61,9 +99,14 @@ impl Router {
         let matches = self.routes.iter().filter(|(rp, _)| rp.matches(p));
 
         let mut best_match: (Option<&str>, Option<&RoutePattern>, usize) = (None, None, 0); // matched id, pattern and length
+        let mut parameterized_exact_match = None;
 
         for (rp, id) in matches {
             match rp {
+                RoutePattern::Exact(_m) if rp.has_params() => {
+                    // Routes with parameters are only beaten by literal exact matches.
+                    parameterized_exact_match.get_or_insert((id.as_str(), rp));
+                }
                 RoutePattern::Exact(_m) => {
                     // Exact matching routes take precedence over wildcard matches.
                     return Ok((id, rp));
@@ -78,6 +121,9 @@ impl Router {
             }
         }
 
+        if let Some(matched) = parameterized_exact_match {
+            return Ok(matched);
+        }
         let (id, rp, _) = best_match;
         id.zip(rp)
             .ok_or_else(|| anyhow!("Cannot match route for path {p}"))
@@ -88,13 +134,121 @@ impl Router {
     ///
     /// If multiple components could potentially handle the same request based on their
     /// defined routes, components with matching exact routes take precedence followed
-    /// by matching wildcard patterns with the longest matching prefix.
+    /// by matching wildcard patterns with the longest matching prefix. Exact routes
+    /// without `:name` parameters take precedence over those with them.
     pub fn route(&self, p: &str) -> Result<&str> {
         self.route_full(p).map(|(r, _)| r)
     }
 }
 
+/// Router for the HTTP trigger which selects routes by the request's
+/// hostname as well as its path.
+///
+/// Routes constrained to a hostname, such as `api.example.com`, or a wildcard
+/// hostname, such as `*.example.com`, take precedence for requests to that
+/// host. Routes without a hostname serve requests for any host which has no
+/// route of its own for the path.
+#[derive(Clone, Debug)]
+pub struct HostRouter {
+    // Lowercased hostname -> router for the routes constrained to it
+    hosts: IndexMap<String, Router>,
+    any_host: Router,
+}
+
+impl HostRouter {
+    /// Builds a router from each component ID, the hostname its route is
+    /// constrained to, if any, and its route.
+    pub fn build<'a>(
+        base: &str,
+        component_routes: impl IntoIterator<Item = (&'a str, Option<&'a str>, &'a str)>,
+    ) -> Result<(Self, Vec<DuplicateRoute>)> {
+        let mut routes_by_host = IndexMap::<Option<String>, Vec<_>>::new();
+        for (component_id, host, route) in component_routes {
+            let host = host.map(str::to_ascii_lowercase);
+            if let Some(host) = &host {
+                let name = host.strip_prefix("*.").unwrap_or(host);
+                if name.is_empty() || name.contains(['*', '/', ':']) {
+                    bail!("Invalid host {host:?} for route {route:?}: expected a hostname such as \"api.example.com\" or \"*.example.com\"");
+                }
+            }
+            routes_by_host
+                .entry(host)
+                .or_default()
+                .push((component_id, route));
+        }
+
+        let mut hosts = IndexMap::new();
+        let (mut any_host, _) = Router::build(base, [])?;
+        let mut duplicates = vec![];
+        for (host, routes) in routes_by_host {
+            let (router, host_duplicates) = Router::build(base, routes)?;
+            duplicates.extend(host_duplicates.into_iter().map(|dup| DuplicateRoute {
+                host: host.clone(),
+                ..dup
+            }));
+            match host {
+                Some(host) => {
+                    hosts.insert(host, router);
+                }
+                None => any_host = router,
+            }
+        }
+        Ok((Self { hosts, any_host }, duplicates))
+    }
+
+    /// Returns the constructed routes, with the hostname each is constrained
+    /// to, if any.
+    pub fn routes(&self) -> impl Iterator<Item = (Option<&str>, &RoutePattern, &String)> {
+        let any_host = self.any_host.routes().map(|(rp, id)| (None, rp, id));
+        let hosts = self.hosts.iter().flat_map(|(host, router)| {
+            router
+                .routes()
+                .map(move |(rp, id)| (Some(host.as_str()), rp, id))
+        });
+        any_host.chain(hosts)
+    }
+
+    /// This returns the component ID that should handle the given path on the
+    /// given host, which may include a port, or an error if no component
+    /// matches.
+    ///
+    /// Routes for the exact hostname are tried first, then those for a
+    /// wildcard matching it, then those for any host.
+    pub fn route(&self, host: Option<&str>, p: &str) -> Result<&str> {
+        if let Some(host) = host {
+            let hostname = strip_port(host).to_ascii_lowercase();
+            let wildcard = hostname
+                .split_once('.')
+                .map(|(_, parent)| format!("*.{parent}"));
+            let routers = [Some(hostname.as_str()), wildcard.as_deref()]
+                .into_iter()
+                .flatten()
+                .filter_map(|host| self.hosts.get(host));
+            for router in routers {
+                if let Ok(component_id) = router.route(p) {
+                    return Ok(component_id);
+                }
+            }
+        }
+        self.any_host.route(p)
+    }
+}
+
+// Strips the port, if any, from a `Host` header value, including bracketed
+// IPv6 addresses.
+fn strip_port(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        Some((name, port)) if !name.ends_with(':') && port.chars().all(|c| c.is_ascii_digit()) => {
+            name
+        }
+        _ => host,
+    }
+}
+
 /// Route patterns for HTTP components.
+///
+/// A path segment of the form `:name` is a parameter, which matches any one
+/// non-empty segment: `/users/:id/orders/:oid` matches `/users/1/orders/2`.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum RoutePattern {
     /// A route pattern that only matches the exact path given.
@@ -117,6 +271,9 @@ impl RoutePattern {
     /// by the route pattern.
     pub fn matches<S: Into<String>>(&self, p: S) -> bool {
         let p = Self::sanitize(p);
+        if self.has_params() {
+            return self.match_params(&p).is_some();
+        }
         match self {
             RoutePattern::Exact(path) => &p == path,
             RoutePattern::Wildcard(pattern) => {
@@ -131,12 +288,90 @@ impl RoutePattern {
             Self::Exact(path) => path,
             Self::Wildcard(prefix) => prefix,
         };
-        Ok(uri
-            .parse::<Uri>()?
-            .path()
-            .strip_prefix(base)
-            .unwrap_or_default()
-            .to_owned())
+        let uri = uri.parse::<Uri>()?;
+        if self.has_params() {
+            let path = Self::sanitize(uri.path());
+            return Ok(self
+                .match_params(&path)
+                .map(|(_, rest)| rest.to_owned())
+                .unwrap_or_default());
+        }
+        Ok(uri.path().strip_prefix(base).unwrap_or_default().to_owned())
+    }
+
+    /// Returns the name and value of each `:name` parameter in the pattern,
+    /// as captured from the given path, or nothing if the path doesn't match.
+    pub fn captures<'a>(&'a self, p: &str) -> Vec<(&'a str, String)> {
+        let p = Self::sanitize(p);
+        match self.match_params(&p) {
+            Some((captures, _)) => captures
+                .into_iter()
+                .map(|(name, value)| (name, value.to_owned()))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Returns true if the pattern has any `:name` parameters.
+    pub fn has_params(&self) -> bool {
+        self.path_or_prefix()
+            .split('/')
+            .any(|segment| segment.starts_with(':'))
+    }
+
+    // Matches a sanitized path against the pattern segment by segment,
+    // returning the captured parameters and the rest of the path after the
+    // pattern.
+    fn match_params<'a, 'p>(&'a self, path: &'p str) -> Option<(Vec<(&'a str, &'p str)>, &'p str)> {
+        let mut captures = vec![];
+        let mut rest = path;
+        for segment in self.path_or_prefix().split('/').skip(1) {
+            let after_slash = rest.strip_prefix('/')?;
+            let (value, remainder) = match after_slash.find('/') {
+                Some(end) => after_slash.split_at(end),
+                None => (after_slash, ""),
+            };
+            match segment.strip_prefix(':') {
+                Some(name) if !value.is_empty() => captures.push((name, value)),
+                Some(_) => return None,
+                None if segment == value => {}
+                None => return None,
+            }
+            rest = remainder;
+        }
+        match self {
+            Self::Exact(_) if !rest.is_empty() => None,
+            _ => Some((captures, rest)),
+        }
+    }
+
+    // Parameter names are passed to components in header and environment
+    // variable names, so are restricted to characters valid in both.
+    fn validate_params(&self) -> Result<()> {
+        let mut names = vec![];
+        for segment in self.path_or_prefix().split('/') {
+            let Some(name) = segment.strip_prefix(':') else {
+                continue;
+            };
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                bail!(
+                    "Invalid parameter {segment:?} in route {:?}: parameter names may only contain ASCII letters, digits, '_' and '-'",
+                    self.full_pattern_non_empty()
+                );
+            }
+            if names.contains(&name) {
+                bail!(
+                    "Route {:?} has more than one parameter named {name:?}",
+                    self.full_pattern_non_empty()
+                );
+            }
+            names.push(name);
+        }
+        Ok(())
     }
 
     /// The full path (for Exact) or prefix (for Wildcard).
@@ -315,6 +550,128 @@ mod route_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_param_route() -> Result<()> {
+        let rp = RoutePattern::from("/", "/users/:id/orders/:oid");
+        assert!(rp.has_params());
+        assert!(rp.matches("/users/1/orders/2"));
+        assert!(rp.matches("/users/1/orders/2/"));
+        assert!(!rp.matches("/users/1/orders"));
+        assert!(!rp.matches("/users//orders/2"));
+        assert!(!rp.matches("/users/1/orders/2/items"));
+        assert!(!rp.matches("/accounts/1/orders/2"));
+        assert_eq!(
+            rp.captures("/users/1/orders/2"),
+            [("id", "1".to_owned()), ("oid", "2".to_owned())]
+        );
+        assert!(rp.captures("/users/1").is_empty());
+
+        let rp = RoutePattern::from("/base", "/files/:owner/...");
+        assert!(rp.matches("/base/files/ada"));
+        assert!(rp.matches("/base/files/ada/notes/todo.txt"));
+        assert!(!rp.matches("/files/ada"));
+        assert_eq!(
+            rp.captures("/base/files/ada/notes/todo.txt"),
+            [("owner", "ada".to_owned())]
+        );
+        assert_eq!(
+            rp.relative("/base/files/ada/notes/todo.txt?v=2")?,
+            "/notes/todo.txt"
+        );
+
+        assert!(!RoutePattern::from("/", "/users/me").has_params());
+        Ok(())
+    }
+
+    #[test]
+    fn literal_routes_beat_param_routes() -> Result<()> {
+        let (router, _) = Router::build(
+            "/",
+            vec![
+                ("user", "/users/:id"),
+                ("me", "/users/me"),
+                ("user-files", "/users/:id/..."),
+                ("users", "/users/..."),
+            ],
+        )?;
+
+        assert_eq!(router.route("/users/me")?, "me");
+        assert_eq!(router.route("/users/ada")?, "user");
+        assert_eq!(router.route("/users/ada/avatar.png")?, "user-files");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_route_params_are_rejected() {
+        assert!(Router::build("/", vec![("a", "/users/:")]).is_err());
+        assert!(Router::build("/", vec![("a", "/users/:user.id")]).is_err());
+        assert!(Router::build("/", vec![("a", "/:id/orders/:id")]).is_err());
+        assert!(Router::build("/", vec![("a", "/:user_id/orders/:order-id")]).is_ok());
+    }
+
+    #[test]
+    fn host_routes_take_precedence_for_their_host() -> Result<()> {
+        let (router, duplicates) = HostRouter::build(
+            "/",
+            vec![
+                ("site", None, "/..."),
+                ("api", Some("API.example.com"), "/..."),
+                ("tenant", Some("*.apps.example.com"), "/..."),
+                ("admin", Some("api.example.com"), "/admin"),
+            ],
+        )?;
+        assert!(duplicates.is_empty());
+
+        assert_eq!(router.route(Some("api.example.com:3000"), "/users")?, "api");
+        assert_eq!(router.route(Some("api.example.com"), "/admin")?, "admin");
+        assert_eq!(router.route(Some("one.apps.example.com"), "/")?, "tenant");
+        assert_eq!(router.route(Some("apps.example.com"), "/")?, "site");
+        assert_eq!(router.route(Some("www.example.com"), "/users")?, "site");
+        assert_eq!(router.route(None, "/users")?, "site");
+        Ok(())
+    }
+
+    #[test]
+    fn host_routes_fall_back_to_any_host() -> Result<()> {
+        let (router, _) = HostRouter::build(
+            "/",
+            vec![
+                ("api", Some("api.example.com"), "/api/..."),
+                ("site", None, "/..."),
+            ],
+        )?;
+
+        assert_eq!(
+            router.route(Some("api.example.com"), "/index.html")?,
+            "site"
+        );
+        assert!(
+            HostRouter::build("/", vec![("api", Some("api.example.com"), "/api")])?
+                .0
+                .route(Some("www.example.com"), "/api")
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_routes_are_per_host() -> Result<()> {
+        let (_, duplicates) = HostRouter::build(
+            "/",
+            vec![
+                ("one", Some("one.example.com"), "/"),
+                ("two", Some("two.example.com"), "/"),
+                ("other-two", Some("two.example.com"), "/"),
+            ],
+        )?;
+
+        assert_eq!(1, duplicates.len());
+        assert_eq!(duplicates[0].host.as_deref(), Some("two.example.com"));
+        assert_eq!(duplicates[0].replaced_id, "two");
+        assert!(HostRouter::build("/", vec![("bad", Some("example.com:80"), "/")]).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_router() -> Result<()> {
         let mut routes = IndexMap::new();
@@ -482,4 +839,30 @@ mod route_tests {
         assert_eq!("first /foo", duplicates[0].replaced_id);
         assert_eq!("second /foo", duplicates[0].effective_id);
     }
+
+    #[test]
+    fn duplicate_routes_table_shows_which_component_wins() {
+        let (_, duplicates) = Router::build(
+            "/",
+            vec![
+                ("first", "/api/..."),
+                ("second", "/api/..."),
+                ("third", "/api/..."),
+                ("root", "/"),
+                ("other-root", "/"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            DuplicateRoute::table(&duplicates),
+            [
+                "  ROUTE     UNREACHABLE  HANDLED BY",
+                "  /api/...  first        third",
+                "  /api/...  second       third",
+                "  /         root         other-root",
+            ]
+            .join("\n")
+        );
+    }
 }
@@ -0,0 +1,397 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use aws_config::{BehaviorVersion, Region};
+use aws_credential_types::Credentials;
+use aws_sdk_dynamodb::{primitives::Blob, types::AttributeValue, Client};
+use spin_core::async_trait;
+use spin_key_value::{
+    add_to_counter, key_namespace, log_error, parse_counter, Error, Store, StoreManager,
+};
+use tokio::sync::OnceCell;
+
+// The attributes of items in the table. The table's partition key must be a
+// string attribute named `key`.
+const KEY_ATTR: &str = "key";
+const VALUE_ATTR: &str = "value";
+// In seconds since the Unix epoch, so that DynamoDB's time to live can delete
+// expired items if it is enabled on this attribute
+const EXPIRES_AT_ATTR: &str = "expires_at";
+
+// Attempts at incrementing a value which is being changed concurrently
+const INCREMENT_ATTEMPTS: usize = 10;
+
+type Item = HashMap<String, AttributeValue>;
+
+/// Options for a DynamoDB key-value store.
+#[derive(Clone, Debug, Default)]
+pub struct KeyValueAwsDynamoOptions {
+    /// The AWS region of the table. If not set, it is discovered from the
+    /// environment.
+    pub region: Option<String>,
+    /// Credentials to access the table with. If not set, they are discovered
+    /// from the environment, AWS profiles or instance metadata.
+    pub credentials: Option<AwsCredentials>,
+    /// Whether reads are strongly, rather than eventually, consistent.
+    pub consistent_read: bool,
+    /// Prefix prepended (followed by `:`) to every key.
+    pub key_prefix: Option<String>,
+    /// Whether to give each component its own keyspace, by prefixing keys
+    /// with the component ID.
+    pub namespace_by_component: bool,
+}
+
+/// Static AWS credentials.
+#[derive(Clone, Debug)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+pub struct KeyValueAwsDynamo {
+    table: String,
+    options: KeyValueAwsDynamoOptions,
+    // Created on first use, and shared by all component-scoped managers
+    client: Arc<OnceCell<Client>>,
+    component_id: Option<String>,
+}
+
+impl KeyValueAwsDynamo {
+    pub fn new(table: String, options: KeyValueAwsDynamoOptions) -> Self {
+        Self {
+            table,
+            options,
+            client: Default::default(),
+            component_id: None,
+        }
+    }
+
+    async fn connect(&self) -> Client {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = &self.options.region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        if let Some(credentials) = &self.options.credentials {
+            loader = loader.credentials_provider(Credentials::new(
+                &credentials.access_key_id,
+                &credentials.secret_access_key,
+                credentials.session_token.clone(),
+                None,
+                "spin-runtime-config",
+            ));
+        }
+        Client::new(&loader.load().await)
+    }
+}
+
+#[async_trait]
+impl StoreManager for KeyValueAwsDynamo {
+    async fn get(&self, _name: &str) -> Result<Arc<dyn Store>, Error> {
+        let client = self.client.get_or_init(|| self.connect()).await;
+        Ok(Arc::new(DynamoStore {
+            client: client.clone(),
+            table: self.table.clone(),
+            namespace: key_namespace(
+                self.options.key_prefix.as_deref(),
+                self.component_id.as_deref(),
+            ),
+            consistent_read: self.options.consistent_read,
+        }))
+    }
+
+    fn is_defined(&self, _store_name: &str) -> bool {
+        true
+    }
+
+    fn for_component(&self, component_id: &str) -> Option<Arc<dyn StoreManager>> {
+        if !self.options.namespace_by_component {
+            return None;
+        }
+        Some(Arc::new(Self {
+            table: self.table.clone(),
+            options: self.options.clone(),
+            client: self.client.clone(),
+            component_id: Some(component_id.to_owned()),
+        }))
+    }
+}
+
+struct DynamoStore {
+    client: Client,
+    table: String,
+    namespace: String,
+    consistent_read: bool,
+}
+
+impl DynamoStore {
+    fn key(&self, key: &str) -> AttributeValue {
+        AttributeValue::S(format!("{}{key}", self.namespace))
+    }
+
+    // Returns the value of a live item.
+    async fn fetch(&self, key: &str, consistent_read: bool) -> Result<Option<Vec<u8>>, Error> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table)
+            .key(KEY_ATTR, self.key(key))
+            .consistent_read(consistent_read)
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(output.item.filter(is_live).and_then(value_of))
+    }
+}
+
+#[async_trait]
+impl Store for DynamoStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.fetch(key, self.consistent_read).await
+    }
+
+    async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .item(KEY_ATTR, self.key(key))
+            .item(VALUE_ATTR, blob(value))
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.client
+            .delete_item()
+            .table_name(&self.table)
+            .key(KEY_ATTR, self.key(key))
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, Error> {
+        Ok(self.get(key).await?.is_some())
+    }
+
+    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+        let mut keys = Vec::new();
+        let mut start_key = None;
+        loop {
+            let mut scan = self
+                .client
+                .scan()
+                .table_name(&self.table)
+                .projection_expression("#k, #e")
+                .expression_attribute_names("#k", KEY_ATTR)
+                .expression_attribute_names("#e", EXPIRES_AT_ATTR)
+                .consistent_read(self.consistent_read)
+                .set_exclusive_start_key(start_key);
+            if !self.namespace.is_empty() {
+                scan = scan
+                    .filter_expression("begins_with(#k, :prefix)")
+                    .expression_attribute_values(
+                        ":prefix",
+                        AttributeValue::S(self.namespace.clone()),
+                    );
+            }
+            let page = scan.send().await.map_err(log_error)?;
+            for item in page.items.unwrap_or_default() {
+                if !is_live(&item) {
+                    continue;
+                }
+                if let Some(AttributeValue::S(key)) = item.get(KEY_ATTR) {
+                    if let Some(key) = key.strip_prefix(&self.namespace) {
+                        keys.push(key.to_owned());
+                    }
+                }
+            }
+            start_key = page.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(keys);
+            }
+        }
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        // Update the value only if nobody else has since it was read, retrying
+        // if they have
+        for _ in 0..INCREMENT_ATTEMPTS {
+            let current = self.fetch(key, true).await?;
+            let value = add_to_counter(parse_counter(current.as_deref())?, delta)?;
+            let update = self
+                .client
+                .update_item()
+                .table_name(&self.table)
+                .key(KEY_ATTR, self.key(key))
+                .expression_attribute_names("#v", VALUE_ATTR)
+                .expression_attribute_names("#e", EXPIRES_AT_ATTR)
+                .expression_attribute_values(":new", blob(value.to_string().as_bytes()))
+                .expression_attribute_values(":now", now_attr());
+            let update = match &current {
+                // As in Redis, incrementing a value keeps its expiry
+                Some(current) => update
+                    .update_expression("SET #v = :new")
+                    .condition_expression("#v = :old AND (attribute_not_exists(#e) OR #e > :now)")
+                    .expression_attribute_values(":old", blob(current)),
+                None => update
+                    .update_expression("SET #v = :new REMOVE #e")
+                    .condition_expression("attribute_not_exists(#k) OR #e <= :now")
+                    .expression_attribute_names("#k", KEY_ATTR),
+            };
+            match update.send().await {
+                Ok(_) => return Ok(value),
+                Err(e)
+                    if e.as_service_error()
+                        .is_some_and(|e| e.is_conditional_check_failed_exception()) => {}
+                Err(e) => return Err(log_error(e)),
+            }
+        }
+        Err(Error::Other(
+            "increment failed as the value is being changed concurrently".into(),
+        ))
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        old: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let put = self
+            .client
+            .put_item()
+            .table_name(&self.table)
+            .item(KEY_ATTR, self.key(key))
+            .item(VALUE_ATTR, blob(value))
+            .expression_attribute_names("#e", EXPIRES_AT_ATTR)
+            .expression_attribute_values(":now", now_attr());
+        let put = match old {
+            Some(old) => put
+                .condition_expression("#v = :old AND (attribute_not_exists(#e) OR #e > :now)")
+                .expression_attribute_names("#v", VALUE_ATTR)
+                .expression_attribute_values(":old", blob(old)),
+            None => put
+                .condition_expression("attribute_not_exists(#k) OR #e <= :now")
+                .expression_attribute_names("#k", KEY_ATTR),
+        };
+        match put.send().await {
+            Ok(_) => Ok(true),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(log_error(e)),
+        }
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        self.client
+            .put_item()
+            .table_name(&self.table)
+            .item(KEY_ATTR, self.key(key))
+            .item(VALUE_ATTR, blob(value))
+            .item(EXPIRES_AT_ATTR, expires_at_attr(ttl))
+            .send()
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, Error> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table)
+            .key(KEY_ATTR, self.key(key))
+            .update_expression("SET #e = :expires_at")
+            .condition_expression(
+                "attribute_exists(#k) AND (attribute_not_exists(#e) OR #e > :now)",
+            )
+            .expression_attribute_names("#k", KEY_ATTR)
+            .expression_attribute_names("#e", EXPIRES_AT_ATTR)
+            .expression_attribute_values(":expires_at", expires_at_attr(ttl))
+            .expression_attribute_values(":now", now_attr())
+            .send()
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(log_error(e)),
+        }
+    }
+}
+
+fn blob(value: &[u8]) -> AttributeValue {
+    AttributeValue::B(Blob::new(value))
+}
+
+fn value_of(mut item: Item) -> Option<Vec<u8>> {
+    match item.remove(VALUE_ATTR)? {
+        AttributeValue::B(value) => Some(value.into_inner()),
+        _ => None,
+    }
+}
+
+// DynamoDB deletes expired items some time after they expire, so reads must
+// skip them until then.
+fn is_live(item: &Item) -> bool {
+    match item.get(EXPIRES_AT_ATTR) {
+        Some(AttributeValue::N(expires_at)) => expires_at
+            .parse::<u64>()
+            .map_or(true, |expires_at| expires_at > now_secs()),
+        _ => true,
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn now_attr() -> AttributeValue {
+    AttributeValue::N(now_secs().to_string())
+}
+
+// Expiry times are in whole seconds, so are rounded up
+fn expires_at_attr(ttl: Duration) -> AttributeValue {
+    let ttl_secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+    AttributeValue::N(now_secs().saturating_add(ttl_secs.max(1)).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_expired_items() {
+        let mut item = Item::from([
+            (KEY_ATTR.to_owned(), AttributeValue::S("a".into())),
+            (VALUE_ATTR.to_owned(), blob(b"1")),
+        ]);
+        assert!(is_live(&item));
+        item.insert(
+            EXPIRES_AT_ATTR.to_owned(),
+            expires_at_attr(Duration::from_millis(1)),
+        );
+        assert!(is_live(&item));
+        item.insert(EXPIRES_AT_ATTR.to_owned(), AttributeValue::N("1".into()));
+        assert!(!is_live(&item));
+        assert_eq!(value_of(item).as_deref(), Some(b"1" as &[_]));
+    }
+}
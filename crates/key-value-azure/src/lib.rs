@@ -1,27 +1,64 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
+use azure_core::{error::Error as AzureError, request_options::IfMatchCondition, StatusCode};
 use azure_data_cosmos::{
-    prelude::{AuthorizationToken, CollectionClient, CosmosClient, Query},
+    prelude::{AuthorizationToken, CollectionClient, CosmosClient, Param, Query},
+    resources::document::DocumentAttributes,
     CosmosEntity,
 };
+use azure_identity::DefaultAzureCredential;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use spin_core::async_trait;
-use spin_key_value::{log_error, Error, Store, StoreManager};
+use spin_key_value::{
+    add_to_counter, key_namespace, log_error, parse_counter, Error, Store, StoreManager,
+};
+
+// Attempts at incrementing a value which is being changed concurrently
+const INCREMENT_ATTEMPTS: usize = 10;
+
+/// Options for an Azure Cosmos DB key-value store.
+#[derive(Clone, Debug, Default)]
+pub struct KeyValueAzureCosmosOptions {
+    /// The account's primary key. If not set, credentials are discovered from
+    /// the environment, a managed identity or the Azure CLI.
+    pub key: Option<String>,
+    /// Prefix prepended (followed by `:`) to every key.
+    pub key_prefix: Option<String>,
+    /// Whether to give each component its own keyspace, by prefixing keys
+    /// with the component ID.
+    pub namespace_by_component: bool,
+}
 
 pub struct KeyValueAzureCosmos {
     client: CollectionClient,
+    options: KeyValueAzureCosmosOptions,
+    component_id: Option<String>,
 }
 
 impl KeyValueAzureCosmos {
-    pub fn new(key: String, account: String, database: String, container: String) -> Result<Self> {
-        let token = AuthorizationToken::primary_from_base64(&key).map_err(log_error)?;
+    pub fn new(
+        account: String,
+        database: String,
+        container: String,
+        options: KeyValueAzureCosmosOptions,
+    ) -> Result<Self> {
+        let token = match &options.key {
+            Some(key) => AuthorizationToken::primary_from_base64(key).map_err(log_error)?,
+            None => AuthorizationToken::from_token_credential(Arc::new(
+                DefaultAzureCredential::default(),
+            )),
+        };
         let cosmos_client = CosmosClient::new(account, token);
         let database_client = cosmos_client.database_client(database);
         let client = database_client.collection_client(container);
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            options,
+            component_id: None,
+        })
     }
 }
 
@@ -31,17 +68,34 @@ impl StoreManager for KeyValueAzureCosmos {
         Ok(Arc::new(AzureCosmosStore {
             _name: name.to_owned(),
             client: self.client.clone(),
+            namespace: key_namespace(
+                self.options.key_prefix.as_deref(),
+                self.component_id.as_deref(),
+            ),
         }))
     }
 
     fn is_defined(&self, _store_name: &str) -> bool {
         true
     }
+
+    fn for_component(&self, component_id: &str) -> Option<Arc<dyn StoreManager>> {
+        if !self.options.namespace_by_component {
+            return None;
+        }
+        Some(Arc::new(Self {
+            client: self.client.clone(),
+            options: self.options.clone(),
+            component_id: Some(component_id.to_owned()),
+        }))
+    }
 }
 
 struct AzureCosmosStore {
     _name: String,
     client: CollectionClient,
+    // Prefix of the IDs of this store's documents
+    namespace: String,
 }
 
 #[async_trait]
@@ -53,20 +107,17 @@ impl Store for AzureCosmosStore {
 
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
         let pair = Pair {
-            id: key.to_string(),
+            id: self.id(key),
             value: value.to_vec(),
+            ttl: None,
         };
-        self.client
-            .create_document(pair)
-            .is_upsert(true)
-            .await
-            .map_err(log_error)?;
-        Ok(())
+        self.upsert(pair).await
     }
 
     async fn delete(&self, key: &str) -> Result<(), Error> {
         if self.exists(key).await? {
-            let document_client = self.client.document_client(key, &key).map_err(log_error)?;
+            let id = self.id(key);
+            let document_client = self.client.document_client(&id, &id).map_err(log_error)?;
             document_client.delete_document().await.map_err(log_error)?;
         }
         Ok(())
@@ -79,13 +130,136 @@ impl Store for AzureCosmosStore {
     async fn get_keys(&self) -> Result<Vec<String>, Error> {
         self.get_keys().await
     }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        // Update the value only if nobody else has since it was read, retrying
+        // if they have
+        for _ in 0..INCREMENT_ATTEMPTS {
+            let current = self.get_versioned(key).await?;
+            let value = add_to_counter(
+                parse_counter(current.as_ref().map(|(pair, _)| pair.value.as_slice()))?,
+                delta,
+            )?;
+            let pair = Pair {
+                id: self.id(key),
+                value: value.to_string().into_bytes(),
+                // As in Redis, incrementing a value keeps its expiry, though
+                // Cosmos DB counts it from the item's last write
+                ttl: current.as_ref().and_then(|(pair, _)| pair.ttl),
+            };
+            if self
+                .write_if_unchanged(pair, current.map(|(_, etag)| etag))
+                .await?
+            {
+                return Ok(value);
+            }
+        }
+        Err(Error::Other(
+            "increment failed as the value is being changed concurrently".into(),
+        ))
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        old: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let current = self.get_versioned(key).await?;
+        if current.as_ref().map(|(pair, _)| pair.value.as_slice()) != old {
+            return Ok(false);
+        }
+        let pair = Pair {
+            id: self.id(key),
+            value: value.to_vec(),
+            ttl: None,
+        };
+        self.write_if_unchanged(pair, current.map(|(_, etag)| etag))
+            .await
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let pair = Pair {
+            id: self.id(key),
+            value: value.to_vec(),
+            ttl: Some(ttl_secs(ttl)),
+        };
+        self.upsert(pair).await
+    }
+
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, Error> {
+        let Some(mut pair) = self.get_pair(key).await? else {
+            return Ok(false);
+        };
+        pair.ttl = Some(ttl_secs(ttl));
+        self.upsert(pair).await?;
+        Ok(true)
+    }
 }
 
 impl AzureCosmosStore {
+    fn id(&self, key: &str) -> String {
+        format!("{}{key}", self.namespace)
+    }
+
+    async fn upsert(&self, pair: Pair) -> Result<(), Error> {
+        self.client
+            .create_document(pair)
+            .is_upsert(true)
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+
+    // Writes the pair if its document is unchanged since it was read with
+    // the given ETag, or if there was no document, still doesn't exist.
+    // Returns whether the pair was written.
+    async fn write_if_unchanged(&self, pair: Pair, etag: Option<String>) -> Result<bool, Error> {
+        let result = match etag {
+            Some(etag) => {
+                let id = pair.id.clone();
+                let document_client = self.client.document_client(&id, &id).map_err(log_error)?;
+                document_client
+                    .replace_document(pair)
+                    .if_match_condition(IfMatchCondition::Match(etag))
+                    .await
+                    .map(drop)
+            }
+            // Creating a document fails if one with its ID exists
+            None => self.client.create_document(pair).await.map(drop),
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(err) if is_concurrent_change(&err) => Ok(false),
+            Err(err) => Err(log_error(err)),
+        }
+    }
+
+    // Returns the pair for the key with the ETag of its document
+    async fn get_versioned(&self, key: &str) -> Result<Option<(Pair, String)>, Error> {
+        match self.get_document(key).await? {
+            Some((pair, Some(attributes))) => Ok(Some((pair, attributes.etag().to_owned()))),
+            Some((_, None)) => Err(Error::Other(
+                "Cosmos DB returned a document without an ETag".into(),
+            )),
+            None => Ok(None),
+        }
+    }
+
     async fn get_pair(&self, key: &str) -> Result<Option<Pair>, Error> {
+        Ok(self.get_document(key).await?.map(|(pair, _)| pair))
+    }
+
+    async fn get_document(
+        &self,
+        key: &str,
+    ) -> Result<Option<(Pair, Option<DocumentAttributes>)>, Error> {
         let query = self
             .client
-            .query_documents(Query::new(format!("SELECT * FROM c WHERE c.id='{}'", key)))
+            .query_documents(Query::with_params(
+                "SELECT * FROM c WHERE c.id = @id".to_owned(),
+                vec![Param::new("@id".to_owned(), self.id(key))],
+            ))
             .query_cross_partition(true)
             .max_item_count(1);
 
@@ -95,10 +269,7 @@ impl AzureCosmosStore {
         match res {
             Some(r) => {
                 let r = r.map_err(log_error)?;
-                match r.results.first().cloned() {
-                    Some((p, _)) => Ok(Some(p)),
-                    None => Ok(None),
-                }
+                Ok(r.results.into_iter().next())
             }
             None => Ok(None),
         }
@@ -107,7 +278,10 @@ impl AzureCosmosStore {
     async fn get_keys(&self) -> Result<Vec<String>, Error> {
         let query = self
             .client
-            .query_documents(Query::new("SELECT * FROM c".to_string()))
+            .query_documents(Query::with_params(
+                "SELECT * FROM c WHERE STARTSWITH(c.id, @prefix)".to_owned(),
+                vec![Param::new("@prefix".to_owned(), self.namespace.clone())],
+            ))
             .query_cross_partition(true);
         let mut res = Vec::new();
 
@@ -115,7 +289,9 @@ impl AzureCosmosStore {
         while let Some(resp) = stream.next().await {
             let resp = resp.map_err(log_error)?;
             for (pair, _) in resp.results {
-                res.push(pair.id);
+                if let Some(key) = pair.id.strip_prefix(&self.namespace) {
+                    res.push(key.to_owned());
+                }
             }
         }
 
@@ -128,6 +304,28 @@ pub struct Pair {
     // In Azure CosmosDB, the default partition key is "/id", and this implementation assumes that partition ID is not changed.
     pub id: String,
     pub value: Vec<u8>,
+    // Seconds until the item expires. Cosmos DB only honours this if time to
+    // live is enabled on the container; if not set, the container's default
+    // applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i32>,
+}
+
+// Whether a conditional write failed because the document was changed,
+// created or deleted since it was read
+fn is_concurrent_change(err: &AzureError) -> bool {
+    err.as_http_error().is_some_and(|err| {
+        matches!(
+            err.status(),
+            StatusCode::PreconditionFailed | StatusCode::Conflict | StatusCode::NotFound
+        )
+    })
+}
+
+// Cosmos DB expiries are in whole seconds, and must be positive
+fn ttl_secs(ttl: Duration) -> i32 {
+    let secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+    secs.clamp(1, i32::MAX as u64) as i32
 }
 
 impl CosmosEntity for Pair {
@@ -1,96 +1,345 @@
 use anyhow::{Context, Result};
-use redis::{aio::Connection, parse_redis_url, AsyncCommands};
+use redis::{
+    aio::MultiplexedConnection, parse_redis_url, AsyncCommands, IntoConnectionInfo, Script,
+};
 use spin_core::async_trait;
-use spin_key_value::{log_error, Error, Store, StoreManager};
-use std::sync::Arc;
-use tokio::sync::{Mutex, OnceCell};
+use spin_key_value::{key_namespace, log_error, Error, Store, StoreManager};
+use spin_outbound_networking::dns::Resolver;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::OnceCell;
 use url::Url;
 
+const DEFAULT_POOL_SIZE: usize = 1;
+
+/// Options for a Redis key-value store.
+#[derive(Clone, Debug, Default)]
+pub struct KeyValueRedisOptions {
+    /// Number of connections to open to the server. Requests are spread
+    /// across connections round-robin.
+    pub pool_size: Option<usize>,
+    /// Expiry applied to every value written.
+    pub ttl: Option<Duration>,
+    /// Prefix prepended (followed by `:`) to every key.
+    pub key_prefix: Option<String>,
+    /// Whether to give each component its own keyspace, by prefixing keys
+    /// with the component ID.
+    pub namespace_by_component: bool,
+}
+
 pub struct KeyValueRedis {
     database_url: Url,
-    connection: OnceCell<Arc<Mutex<Connection>>>,
+    options: KeyValueRedisOptions,
+    resolver: Resolver,
+    // Shared by all component-scoped managers
+    pool: Arc<ConnectionPool>,
+    component_id: Option<String>,
 }
 
 impl KeyValueRedis {
-    pub fn new(address: String) -> Result<Self> {
+    pub fn new(address: String, options: KeyValueRedisOptions) -> Result<Self> {
         let database_url = parse_redis_url(&address).context("Invalid Redis URL")?;
 
         Ok(Self {
             database_url,
-            connection: OnceCell::new(),
+            options,
+            resolver: Resolver::default(),
+            pool: Default::default(),
+            component_id: None,
         })
     }
+
+    /// Resolves the server's hostname with `resolver` rather than the
+    /// system resolver.
+    pub fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    // Returns the prefix for keys in this manager's stores.
+    fn namespace(&self) -> String {
+        key_namespace(
+            self.options.key_prefix.as_deref(),
+            self.component_id.as_deref(),
+        )
+    }
 }
 
 #[async_trait]
 impl StoreManager for KeyValueRedis {
     async fn get(&self, _name: &str) -> Result<Arc<dyn Store>, Error> {
+        let pool_size = self.options.pool_size.unwrap_or(DEFAULT_POOL_SIZE);
         let connection = self
-            .connection
-            .get_or_try_init(|| async {
-                redis::Client::open(self.database_url.clone())?
-                    .get_async_connection()
-                    .await
-                    .map(Mutex::new)
-                    .map(Arc::new)
-            })
+            .pool
+            .get(&self.database_url, &self.resolver, pool_size)
             .await
             .map_err(log_error)?;
 
         Ok(Arc::new(RedisStore {
-            connection: connection.clone(),
+            connection,
+            namespace: self.namespace(),
+            ttl: self.options.ttl,
         }))
     }
 
     fn is_defined(&self, _store_name: &str) -> bool {
         true
     }
+
+    fn for_component(&self, component_id: &str) -> Option<Arc<dyn StoreManager>> {
+        if !self.options.namespace_by_component {
+            return None;
+        }
+        Some(Arc::new(Self {
+            database_url: self.database_url.clone(),
+            options: self.options.clone(),
+            resolver: self.resolver.clone(),
+            pool: self.pool.clone(),
+            component_id: Some(component_id.to_owned()),
+        }))
+    }
+}
+
+/// A fixed-size set of multiplexed connections, opened on first use.
+#[derive(Default)]
+struct ConnectionPool {
+    connections: OnceCell<Vec<MultiplexedConnection>>,
+    next: AtomicUsize,
+}
+
+impl ConnectionPool {
+    async fn get(
+        &self,
+        database_url: &Url,
+        resolver: &Resolver,
+        pool_size: usize,
+    ) -> Result<MultiplexedConnection> {
+        let connections = self
+            .connections
+            .get_or_try_init(|| async {
+                let info = database_url.clone().into_connection_info()?;
+                let mut connections = Vec::with_capacity(pool_size);
+                for _ in 0..pool_size.max(1) {
+                    let connection = resolver
+                        .connect_redis(info.clone(), |client| async move {
+                            client.get_multiplexed_tokio_connection().await
+                        })
+                        .await?;
+                    connections.push(connection);
+                }
+                Ok::<_, anyhow::Error>(connections)
+            })
+            .await?;
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % connections.len();
+        Ok(connections[idx].clone())
+    }
 }
 
 struct RedisStore {
-    connection: Arc<Mutex<Connection>>,
+    connection: MultiplexedConnection,
+    namespace: String,
+    ttl: Option<Duration>,
+}
+
+impl RedisStore {
+    fn key(&self, key: &str) -> String {
+        format!("{}{key}", self.namespace)
+    }
 }
 
 #[async_trait]
 impl Store for RedisStore {
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
-        let mut conn = self.connection.lock().await;
-        conn.get(key).await.map_err(log_error)
+        let mut conn = self.connection.clone();
+        conn.get(self.key(key)).await.map_err(log_error)
     }
 
     async fn set(&self, key: &str, value: &[u8]) -> Result<(), Error> {
-        self.connection
-            .lock()
-            .await
-            .set(key, value)
-            .await
-            .map_err(log_error)
+        let mut conn = self.connection.clone();
+        match self.ttl {
+            Some(ttl) => conn
+                .set_ex(self.key(key), value, ttl_secs(ttl))
+                .await
+                .map_err(log_error),
+            None => conn.set(self.key(key), value).await.map_err(log_error),
+        }
     }
 
     async fn delete(&self, key: &str) -> Result<(), Error> {
-        self.connection
-            .lock()
-            .await
-            .del(key)
-            .await
-            .map_err(log_error)
+        let mut conn = self.connection.clone();
+        conn.del(self.key(key)).await.map_err(log_error)
     }
 
     async fn exists(&self, key: &str) -> Result<bool, Error> {
-        self.connection
-            .lock()
+        let mut conn = self.connection.clone();
+        conn.exists(self.key(key)).await.map_err(log_error)
+    }
+
+    async fn get_keys(&self) -> Result<Vec<String>, Error> {
+        let mut conn = self.connection.clone();
+        let pattern = format!("{}*", escape_pattern(&self.namespace));
+        let keys: Vec<String> = conn.keys(pattern).await.map_err(log_error)?;
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.namespace).map(str::to_owned))
+            .collect())
+    }
+
+    async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut conn = self.connection.clone();
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(keys.iter().map(|key| self.key(key)).collect::<Vec<_>>())
+            .query_async(&mut conn)
             .await
-            .exists(key)
+            .map_err(log_error)?;
+        Ok(keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect())
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        if key_values.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (key, value) in &key_values {
+            match self.ttl {
+                Some(ttl) => pipe.set_ex(self.key(key), value, ttl_secs(ttl)),
+                None => pipe.set(self.key(key), value),
+            }
+            .ignore();
+        }
+        let mut conn = self.connection.clone();
+        pipe.query_async(&mut conn).await.map_err(log_error)
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        let key = self.key(key);
+        let mut pipe = redis::pipe();
+        pipe.atomic().incr(&key, delta);
+        if let Some(ttl) = self.ttl {
+            pipe.expire(&key, ttl_secs(ttl)).ignore();
+        }
+        let mut conn = self.connection.clone();
+        let (value,): (i64,) = pipe.query_async(&mut conn).await.map_err(log_error)?;
+        Ok(value)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        old: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let mut conn = self.connection.clone();
+        let script = Script::new(COMPARE_AND_SWAP_SCRIPT);
+        let mut invocation = script.key(self.key(key));
+        match old {
+            Some(old) => invocation.arg(1).arg(old),
+            None => invocation.arg(0).arg(""),
+        }
+        .arg(value)
+        .arg(self.ttl.map(ttl_secs).unwrap_or(0));
+        invocation.invoke_async(&mut conn).await.map_err(log_error)
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        let mut conn = self.connection.clone();
+        conn.pset_ex(self.key(key), value, ttl_millis(ttl))
             .await
             .map_err(log_error)
     }
 
-    async fn get_keys(&self) -> Result<Vec<String>, Error> {
-        self.connection
-            .lock()
-            .await
-            .keys("*")
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, Error> {
+        let mut conn = self.connection.clone();
+        conn.pexpire(self.key(key), ttl_millis(ttl))
             .await
             .map_err(log_error)
     }
 }
+
+// Sets KEYS[1] to ARGV[3] if it currently has the value ARGV[2] (or, if
+// ARGV[1] is 0, does not exist), with an expiry of ARGV[4] seconds if nonzero.
+const COMPARE_AND_SWAP_SCRIPT: &str = r"
+local current = redis.call('GET', KEYS[1])
+local matches
+if ARGV[1] == '1' then
+  matches = current == ARGV[2]
+else
+  matches = not current
+end
+if not matches then
+  return 0
+end
+if ARGV[4] == '0' then
+  redis.call('SET', KEYS[1], ARGV[3])
+else
+  redis.call('SET', KEYS[1], ARGV[3], 'EX', ARGV[4])
+end
+return 1
+";
+
+fn ttl_secs(ttl: Duration) -> usize {
+    ttl.as_secs().max(1) as usize
+}
+
+// Redis rejects an expiry of 0, so the shortest is 1ms
+fn ttl_millis(ttl: Duration) -> usize {
+    ttl.as_millis().clamp(1, usize::MAX as u128) as usize
+}
+
+// Escapes glob characters so that `s` matches literally in a `KEYS` pattern.
+fn escape_pattern(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(options: KeyValueRedisOptions) -> KeyValueRedis {
+        KeyValueRedis::new("redis://localhost:6379".into(), options).unwrap()
+    }
+
+    #[test]
+    fn namespaces_keys() {
+        let unprefixed = manager(Default::default());
+        assert_eq!(unprefixed.namespace(), "");
+        assert!(unprefixed.for_component("hello").is_none());
+
+        let prefixed = manager(KeyValueRedisOptions {
+            key_prefix: Some("app".into()),
+            namespace_by_component: true,
+            ..Default::default()
+        });
+        assert_eq!(prefixed.namespace(), "app:");
+        let scoped = KeyValueRedis {
+            component_id: Some("hello".into()),
+            ..prefixed
+        };
+        assert_eq!(scoped.namespace(), "app:hello:");
+    }
+
+    #[test]
+    fn escapes_patterns() {
+        assert_eq!(escape_pattern("a*b?[c]\\"), "a\\*b\\?\\[c\\]\\\\");
+    }
+}
@@ -2,12 +2,21 @@ use anyhow::Result;
 use once_cell::sync::OnceCell;
 use rusqlite::Connection;
 use spin_core::async_trait;
-use spin_key_value::{log_error, Error, Store, StoreManager};
+use spin_key_value::{add_to_counter, log_error, parse_counter, Error, Store, StoreManager};
 use std::{
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::task;
+use tokio::{task, time::MissedTickBehavior};
+
+// How often expired tuples are deleted from the database. Until then, reads
+// skip them.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// Setting a value removes any expiry
+const SET_QUERY: &str = "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
+                         ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=NULL";
 
 pub enum DatabaseLocation {
     InMemory,
@@ -42,17 +51,21 @@ impl StoreManager for KeyValueSqlite {
                 connection
                     .execute(
                         "CREATE TABLE IF NOT EXISTS spin_key_value (
-                           store TEXT NOT NULL,
-                           key   TEXT NOT NULL,
-                           value BLOB NOT NULL,
+                           store      TEXT NOT NULL,
+                           key        TEXT NOT NULL,
+                           value      BLOB NOT NULL,
+                           expires_at INTEGER,
 
                            PRIMARY KEY (store, key)
                         )",
                         [],
                     )
                     .map_err(log_error)?;
+                migrate(&connection).map_err(log_error)?;
 
-                Ok(Arc::new(Mutex::new(connection)))
+                let connection = Arc::new(Mutex::new(connection));
+                tokio::spawn(sweep_expired(Arc::downgrade(&connection)));
+                Ok(connection)
             })
         })?;
 
@@ -79,9 +92,14 @@ impl Store for SqliteStore {
             self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+                .prepare_cached(
+                    "SELECT value FROM spin_key_value WHERE store=$1 AND key=$2
+                     AND (expires_at IS NULL OR expires_at > $3)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name, key], |row| row.get(0))
+                .query_map(rusqlite::params![&self.name, key, now_millis()], |row| {
+                    row.get(0)
+                })
                 .map_err(log_error)?
                 .next()
                 .transpose()
@@ -94,10 +112,7 @@ impl Store for SqliteStore {
             self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached(
-                    "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
-                     ON CONFLICT(store, key) DO UPDATE SET value=$3",
-                )
+                .prepare_cached(SET_QUERY)
                 .map_err(log_error)?
                 .execute(rusqlite::params![&self.name, key, value])
                 .map_err(log_error)
@@ -127,14 +142,195 @@ impl Store for SqliteStore {
             self.connection
                 .lock()
                 .unwrap()
-                .prepare_cached("SELECT key FROM spin_key_value WHERE store=$1")
+                .prepare_cached(
+                    "SELECT key FROM spin_key_value WHERE store=$1
+                     AND (expires_at IS NULL OR expires_at > $2)",
+                )
                 .map_err(log_error)?
-                .query_map([&self.name], |row| row.get(0))
+                .query_map(rusqlite::params![&self.name, now_millis()], |row| {
+                    row.get(0)
+                })
                 .map_err(log_error)?
                 .map(|r| r.map_err(log_error))
                 .collect()
         })
     }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        task::block_in_place(|| {
+            let mut connection = self.connection.lock().unwrap();
+            let tx = connection.transaction().map_err(log_error)?;
+            for (key, value) in &key_values {
+                tx.prepare_cached(SET_QUERY)
+                    .map_err(log_error)?
+                    .execute(rusqlite::params![&self.name, key, value])
+                    .map_err(log_error)?;
+            }
+            tx.commit().map_err(log_error)
+        })
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        task::block_in_place(|| {
+            let mut connection = self.connection.lock().unwrap();
+            let tx = connection.transaction().map_err(log_error)?;
+            delete_if_expired(&tx, &self.name, key).map_err(log_error)?;
+            let current: Option<Vec<u8>> = tx
+                .prepare_cached("SELECT value FROM spin_key_value WHERE store=$1 AND key=$2")
+                .map_err(log_error)?
+                .query_map([&self.name, key], |row| row.get(0))
+                .map_err(log_error)?
+                .next()
+                .transpose()
+                .map_err(log_error)?;
+            let value = add_to_counter(parse_counter(current.as_deref())?, delta)?;
+            tx.prepare_cached(
+                "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
+                 ON CONFLICT(store, key) DO UPDATE SET value=$3",
+            )
+            .map_err(log_error)?
+            .execute(rusqlite::params![
+                &self.name,
+                key,
+                value.to_string().as_bytes()
+            ])
+            .map_err(log_error)?;
+            tx.commit().map_err(log_error)?;
+            Ok(value)
+        })
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        old: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        task::block_in_place(|| {
+            let mut connection = self.connection.lock().unwrap();
+            let tx = connection.transaction().map_err(log_error)?;
+            delete_if_expired(&tx, &self.name, key).map_err(log_error)?;
+            let changed = match old {
+                Some(old) => tx
+                    .prepare_cached(
+                        "UPDATE spin_key_value SET value=$4 WHERE store=$1 AND key=$2 AND value=$3",
+                    )
+                    .map_err(log_error)?
+                    .execute(rusqlite::params![&self.name, key, old, value]),
+                None => tx
+                    .prepare_cached(
+                        "INSERT INTO spin_key_value (store, key, value) VALUES ($1, $2, $3)
+                         ON CONFLICT(store, key) DO NOTHING",
+                    )
+                    .map_err(log_error)?
+                    .execute(rusqlite::params![&self.name, key, value]),
+            }
+            .map_err(log_error)?;
+            tx.commit().map_err(log_error)?;
+            Ok(changed > 0)
+        })
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        task::block_in_place(|| {
+            self.connection
+                .lock()
+                .unwrap()
+                .prepare_cached(
+                    "INSERT INTO spin_key_value (store, key, value, expires_at) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT(store, key) DO UPDATE SET value=$3, expires_at=$4",
+                )
+                .map_err(log_error)?
+                .execute(rusqlite::params![&self.name, key, value, expires_at(ttl)])
+                .map_err(log_error)
+                .map(drop)
+        })
+    }
+
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, Error> {
+        task::block_in_place(|| {
+            let changed = self
+                .connection
+                .lock()
+                .unwrap()
+                .prepare_cached(
+                    "UPDATE spin_key_value SET expires_at=$3 WHERE store=$1 AND key=$2
+                     AND (expires_at IS NULL OR expires_at > $4)",
+                )
+                .map_err(log_error)?
+                .execute(rusqlite::params![
+                    &self.name,
+                    key,
+                    expires_at(ttl),
+                    now_millis()
+                ])
+                .map_err(log_error)?;
+            Ok(changed > 0)
+        })
+    }
+}
+
+// Adds the expiry column to databases created before tuples could expire.
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    let has_expiry = connection
+        .prepare("SELECT 1 FROM pragma_table_info('spin_key_value') WHERE name='expires_at'")?
+        .exists([])?;
+    if !has_expiry {
+        connection.execute(
+            "ALTER TABLE spin_key_value ADD COLUMN expires_at INTEGER",
+            [],
+        )?;
+    }
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS spin_key_value_expires_at ON spin_key_value (expires_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+// Deletes expired tuples periodically, until the database is closed.
+async fn sweep_expired(connection: Weak<Mutex<Connection>>) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let Some(connection) = connection.upgrade() else {
+            return;
+        };
+        let result = task::block_in_place(|| delete_expired(&connection.lock().unwrap()));
+        if let Err(e) = result {
+            log_error(e);
+        }
+    }
+}
+
+fn delete_expired(connection: &Connection) -> rusqlite::Result<usize> {
+    connection
+        .prepare_cached("DELETE FROM spin_key_value WHERE expires_at <= $1")?
+        .execute([now_millis()])
+}
+
+// Deletes a tuple that has expired but not yet been swept, so that atomic
+// operations treat it as missing.
+fn delete_if_expired(connection: &Connection, store: &str, key: &str) -> rusqlite::Result<()> {
+    connection
+        .prepare_cached(
+            "DELETE FROM spin_key_value WHERE store=$1 AND key=$2 AND expires_at <= $3",
+        )?
+        .execute(rusqlite::params![store, key, now_millis()])
+        .map(drop)
+}
+
+// Expiry times are stored as milliseconds since the Unix epoch
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+fn expires_at(ttl: Duration) -> i64 {
+    now_millis().saturating_add(i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX))
 }
 
 #[cfg(test)]
@@ -142,7 +338,7 @@ mod test {
     use super::*;
     use spin_core::wasmtime::component::Resource;
     use spin_key_value::{DelegatingStoreManager, KeyValueDispatch};
-    use spin_world::v2::key_value::HostStore;
+    use spin_world::v2_1::key_value::HostStore;
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
     async fn all() -> Result<()> {
@@ -232,4 +428,166 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn bulk_and_atomic() -> Result<()> {
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)),
+        );
+        let rep = kv.open("default".to_owned()).await??.rep();
+
+        kv.set_many(
+            Resource::new_own(rep),
+            vec![
+                ("a".to_owned(), b"1".to_vec()),
+                ("b".to_owned(), b"2".to_vec()),
+            ],
+        )
+        .await??;
+        let mut values = kv
+            .get_many(
+                Resource::new_own(rep),
+                vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+            )
+            .await??;
+        values.sort();
+        assert_eq!(
+            vec![
+                ("a".to_owned(), b"1".to_vec()),
+                ("b".to_owned(), b"2".to_vec())
+            ],
+            values
+        );
+
+        assert_eq!(
+            11,
+            kv.increment(Resource::new_own(rep), "a".to_owned(), 10)
+                .await??
+        );
+        assert_eq!(
+            -1,
+            kv.increment(Resource::new_own(rep), "c".to_owned(), -1)
+                .await??
+        );
+        kv.set(Resource::new_own(rep), "d".to_owned(), b"nope".to_vec())
+            .await??;
+        assert!(kv
+            .increment(Resource::new_own(rep), "d".to_owned(), 1)
+            .await?
+            .is_err());
+
+        assert!(
+            !kv.compare_and_swap(
+                Resource::new_own(rep),
+                "b".to_owned(),
+                Some(b"3".to_vec()),
+                b"4".to_vec()
+            )
+            .await??
+        );
+        assert!(
+            kv.compare_and_swap(
+                Resource::new_own(rep),
+                "b".to_owned(),
+                Some(b"2".to_vec()),
+                b"4".to_vec()
+            )
+            .await??
+        );
+        assert!(
+            !kv.compare_and_swap(Resource::new_own(rep), "b".to_owned(), None, b"5".to_vec())
+                .await??
+        );
+        assert!(
+            kv.compare_and_swap(Resource::new_own(rep), "e".to_owned(), None, b"5".to_vec())
+                .await??
+        );
+        assert_eq!(
+            Some(b"4" as &[_]),
+            kv.get(Resource::new_own(rep), "b".to_owned())
+                .await??
+                .as_deref()
+        );
+
+        kv.drop(Resource::new_own(rep))?;
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn expiry() -> Result<()> {
+        let mut kv = KeyValueDispatch::new();
+        kv.init(
+            ["default"].into_iter().map(ToOwned::to_owned).collect(),
+            Arc::new(KeyValueSqlite::new(DatabaseLocation::InMemory)),
+        );
+        let rep = kv.open("default".to_owned()).await??.rep();
+
+        kv.set_with_ttl(
+            Resource::new_own(rep),
+            "a".to_owned(),
+            b"1".to_vec(),
+            60_000,
+        )
+        .await??;
+        kv.set_with_ttl(Resource::new_own(rep), "b".to_owned(), b"2".to_vec(), 1)
+            .await??;
+        kv.set(Resource::new_own(rep), "c".to_owned(), b"3".to_vec())
+            .await??;
+        assert!(
+            kv.expire(Resource::new_own(rep), "c".to_owned(), 1)
+                .await??
+        );
+        assert!(
+            !kv.expire(Resource::new_own(rep), "d".to_owned(), 1)
+                .await??
+        );
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        assert_eq!(
+            Some(b"1" as &[_]),
+            kv.get(Resource::new_own(rep), "a".to_owned())
+                .await??
+                .as_deref()
+        );
+        assert!(!kv.exists(Resource::new_own(rep), "b".to_owned()).await??);
+        assert_eq!(
+            &["a".to_owned()] as &[_],
+            &kv.get_keys(Resource::new_own(rep)).await??
+        );
+        assert!(
+            !kv.expire(Resource::new_own(rep), "c".to_owned(), 60_000)
+                .await??
+        );
+        // Expired values are treated as missing by atomic operations
+        assert_eq!(
+            1,
+            kv.increment(Resource::new_own(rep), "b".to_owned(), 1)
+                .await??
+        );
+        assert!(
+            kv.compare_and_swap(Resource::new_own(rep), "c".to_owned(), None, b"4".to_vec())
+                .await??
+        );
+
+        kv.drop(Resource::new_own(rep))?;
+
+        // Expired tuples are eventually deleted
+        let manager = KeyValueSqlite::new(DatabaseLocation::InMemory);
+        let store = manager.get("default").await.unwrap();
+        store
+            .set_with_ttl("a", b"1", Duration::from_millis(1))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let connection = manager.connection.get().unwrap().lock().unwrap();
+        delete_expired(&connection)?;
+        let count: i64 =
+            connection.query_row("SELECT COUNT(*) FROM spin_key_value", [], |row| row.get(0))?;
+        assert_eq!(0, count);
+
+        Ok(())
+    }
 }
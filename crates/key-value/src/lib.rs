@@ -1,8 +1,8 @@
 use anyhow::{Context, Result};
 use spin_app::MetadataKey;
 use spin_core::{async_trait, wasmtime::component::Resource};
-use spin_world::v2::key_value;
-use std::{collections::HashSet, sync::Arc};
+use spin_world::v2_1::key_value;
+use std::{collections::HashSet, sync::Arc, time::Duration};
 use table::Table;
 
 mod host_component;
@@ -21,6 +21,12 @@ pub use key_value::Error;
 pub trait StoreManager: Sync + Send {
     async fn get(&self, name: &str) -> Result<Arc<dyn Store>, Error>;
     fn is_defined(&self, store_name: &str) -> bool;
+
+    /// Returns a manager whose stores are private to the given component, or
+    /// `None` if this manager's stores are shared by all components.
+    fn for_component(&self, _component_id: &str) -> Option<Arc<dyn StoreManager>> {
+        None
+    }
 }
 
 #[async_trait]
@@ -30,6 +36,83 @@ pub trait Store: Sync + Send {
     async fn delete(&self, key: &str) -> Result<(), Error>;
     async fn exists(&self, key: &str) -> Result<bool, Error>;
     async fn get_keys(&self) -> Result<Vec<String>, Error>;
+
+    async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let mut results = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some(value) = self.get(&key).await? {
+                results.push((key, value));
+            }
+        }
+        Ok(results)
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        for (key, value) in key_values {
+            self.set(&key, &value).await?;
+        }
+        Ok(())
+    }
+
+    /// The default implementations of the atomic operations fail, since any
+    /// implementation built on the other methods would not be atomic.
+    async fn increment(&self, _key: &str, _delta: i64) -> Result<i64, Error> {
+        Err(Error::Other(
+            "increment is not supported by this store".into(),
+        ))
+    }
+
+    async fn compare_and_swap(
+        &self,
+        _key: &str,
+        _old: Option<&[u8]>,
+        _value: &[u8],
+    ) -> Result<bool, Error> {
+        Err(Error::Other(
+            "compare-and-swap is not supported by this store".into(),
+        ))
+    }
+
+    /// The default implementations of the expiry operations fail, since
+    /// expiry can't be built on the other methods.
+    async fn set_with_ttl(&self, _key: &str, _value: &[u8], _ttl: Duration) -> Result<(), Error> {
+        Err(Error::Other(
+            "set-with-ttl is not supported by this store".into(),
+        ))
+    }
+
+    async fn expire(&self, _key: &str, _ttl: Duration) -> Result<bool, Error> {
+        Err(Error::Other("expire is not supported by this store".into()))
+    }
+}
+
+/// Parses a stored value as an integer for [`Store::increment`], treating a
+/// missing value as 0.
+pub fn parse_counter(value: Option<&[u8]>) -> Result<i64, Error> {
+    let Some(value) = value else {
+        return Ok(0);
+    };
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| Error::Other("value is not an integer".into()))
+}
+
+/// Adds `delta` to `value`, failing on overflow, for [`Store::increment`].
+pub fn add_to_counter(value: i64, delta: i64) -> Result<i64, Error> {
+    value
+        .checked_add(delta)
+        .ok_or_else(|| Error::Other("increment overflowed".into()))
+}
+
+/// Returns the prefix for the keys of stores in backends shared by several
+/// apps or components: each part, followed by `:`.
+pub fn key_namespace(key_prefix: Option<&str>, component_id: Option<&str>) -> String {
+    key_prefix
+        .into_iter()
+        .chain(component_id)
+        .map(|part| format!("{part}:"))
+        .collect()
 }
 
 pub struct KeyValueDispatch {
@@ -132,6 +215,68 @@ impl key_value::HostStore for KeyValueDispatch {
         Ok(store.get_keys().await)
     }
 
+    async fn get_many(
+        &mut self,
+        store: Resource<key_value::Store>,
+        keys: Vec<String>,
+    ) -> Result<Result<Vec<(String, Vec<u8>)>, Error>> {
+        let store = self.get_store(store)?;
+        Ok(store.get_many(keys).await)
+    }
+
+    async fn set_many(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key_values: Vec<(String, Vec<u8>)>,
+    ) -> Result<Result<(), Error>> {
+        let store = self.get_store(store)?;
+        Ok(store.set_many(key_values).await)
+    }
+
+    async fn increment(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key: String,
+        delta: i64,
+    ) -> Result<Result<i64, Error>> {
+        let store = self.get_store(store)?;
+        Ok(store.increment(&key, delta).await)
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key: String,
+        old: Option<Vec<u8>>,
+        value: Vec<u8>,
+    ) -> Result<Result<bool, Error>> {
+        let store = self.get_store(store)?;
+        Ok(store.compare_and_swap(&key, old.as_deref(), &value).await)
+    }
+
+    async fn set_with_ttl(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key: String,
+        value: Vec<u8>,
+        ttl_ms: u64,
+    ) -> Result<Result<(), Error>> {
+        let store = self.get_store(store)?;
+        Ok(store
+            .set_with_ttl(&key, &value, Duration::from_millis(ttl_ms))
+            .await)
+    }
+
+    async fn expire(
+        &mut self,
+        store: Resource<key_value::Store>,
+        key: String,
+        ttl_ms: u64,
+    ) -> Result<Result<bool, Error>> {
+        let store = self.get_store(store)?;
+        Ok(store.expire(&key, Duration::from_millis(ttl_ms)).await)
+    }
+
     fn drop(&mut self, store: Resource<key_value::Store>) -> Result<()> {
         self.stores.remove(store.rep());
         Ok(())
@@ -203,3 +348,81 @@ impl spin_world::v1::key_value::Host for KeyValueDispatch {
         <Self as key_value::HostStore>::drop(self, this)
     }
 }
+
+use spin_world::v2::key_value as v2_0_0;
+
+fn to_v2_0_0_store(store: Resource<key_value::Store>) -> Resource<v2_0_0::Store> {
+    Resource::new_own(store.rep())
+}
+
+fn from_v2_0_0_store(store: Resource<v2_0_0::Store>) -> Resource<key_value::Store> {
+    Resource::new_borrow(store.rep())
+}
+
+#[async_trait]
+impl v2_0_0::Host for KeyValueDispatch {}
+
+#[async_trait]
+impl v2_0_0::HostStore for KeyValueDispatch {
+    async fn open(
+        &mut self,
+        name: String,
+    ) -> Result<Result<Resource<v2_0_0::Store>, v2_0_0::Error>> {
+        let result = <Self as key_value::HostStore>::open(self, name).await?;
+        Ok(result.map(to_v2_0_0_store).map_err(Into::into))
+    }
+
+    async fn get(
+        &mut self,
+        store: Resource<v2_0_0::Store>,
+        key: String,
+    ) -> Result<Result<Option<Vec<u8>>, v2_0_0::Error>> {
+        let store = from_v2_0_0_store(store);
+        let result = <Self as key_value::HostStore>::get(self, store, key).await?;
+        Ok(result.map_err(Into::into))
+    }
+
+    async fn set(
+        &mut self,
+        store: Resource<v2_0_0::Store>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<Result<(), v2_0_0::Error>> {
+        let store = from_v2_0_0_store(store);
+        let result = <Self as key_value::HostStore>::set(self, store, key, value).await?;
+        Ok(result.map_err(Into::into))
+    }
+
+    async fn delete(
+        &mut self,
+        store: Resource<v2_0_0::Store>,
+        key: String,
+    ) -> Result<Result<(), v2_0_0::Error>> {
+        let store = from_v2_0_0_store(store);
+        let result = <Self as key_value::HostStore>::delete(self, store, key).await?;
+        Ok(result.map_err(Into::into))
+    }
+
+    async fn exists(
+        &mut self,
+        store: Resource<v2_0_0::Store>,
+        key: String,
+    ) -> Result<Result<bool, v2_0_0::Error>> {
+        let store = from_v2_0_0_store(store);
+        let result = <Self as key_value::HostStore>::exists(self, store, key).await?;
+        Ok(result.map_err(Into::into))
+    }
+
+    async fn get_keys(
+        &mut self,
+        store: Resource<v2_0_0::Store>,
+    ) -> Result<Result<Vec<String>, v2_0_0::Error>> {
+        let store = from_v2_0_0_store(store);
+        let result = <Self as key_value::HostStore>::get_keys(self, store).await?;
+        Ok(result.map_err(Into::into))
+    }
+
+    fn drop(&mut self, store: Resource<v2_0_0::Store>) -> Result<()> {
+        <Self as key_value::HostStore>::drop(self, Resource::new_own(store.rep()))
+    }
+}
@@ -6,6 +6,7 @@ use std::{
     future::Future,
     num::NonZeroUsize,
     sync::Arc,
+    time::Duration,
 };
 use tokio::{
     sync::Mutex as AsyncMutex,
@@ -36,6 +37,28 @@ impl DelegatingStoreManager {
         let delegates = delegates.into_iter().collect();
         Self { delegates }
     }
+
+    /// Returns a manager with each delegate replaced by its
+    /// [`StoreManager::for_component`] scoped manager, or `None` if no
+    /// delegate is scoped per component.
+    pub fn component_scoped(&self, component_id: &str) -> Option<Self> {
+        let mut scoped = false;
+        let delegates = self
+            .delegates
+            .iter()
+            .map(|(name, delegate)| {
+                let delegate = match delegate.for_component(component_id) {
+                    Some(delegate) => {
+                        scoped = true;
+                        delegate
+                    }
+                    None => delegate.clone(),
+                };
+                (name.clone(), delegate)
+            })
+            .collect();
+        scoped.then_some(Self { delegates })
+    }
 }
 
 #[async_trait]
@@ -202,6 +225,124 @@ impl Store for CachingStore {
         Ok(self.get(key).await?.is_some())
     }
 
+    async fn get_many(&self, keys: Vec<String>) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        // Serve what we can from the cache, fetching the rest from the backing store in one batch.
+
+        let mut state = self.state.lock().await;
+
+        let mut results = Vec::with_capacity(keys.len());
+        let mut uncached = Vec::new();
+        for key in keys {
+            match state.cache.get(&key) {
+                Some(Some(value)) => results.push((key, value.clone())),
+                Some(None) => {}
+                None => uncached.push(key),
+            }
+        }
+
+        if !uncached.is_empty() {
+            // As in `get`, flush outstanding writes so the guest reads its own writes.
+            state.flush().await?;
+
+            let fetched = self.inner.get_many(uncached.clone()).await?;
+            let fetched_keys = fetched
+                .iter()
+                .map(|(k, _)| k.clone())
+                .collect::<HashSet<_>>();
+            for key in uncached {
+                if !fetched_keys.contains(&key) {
+                    state.cache.put(key, None);
+                }
+            }
+            for (key, value) in fetched {
+                state.cache.put(key.clone(), Some(value.clone()));
+                results.push((key, value));
+            }
+        }
+
+        Ok(results)
+    }
+
+    async fn set_many(&self, key_values: Vec<(String, Vec<u8>)>) -> Result<(), Error> {
+        // Update the cache and spawn a task to update the backing store asynchronously.
+
+        let mut state = self.state.lock().await;
+
+        for (key, value) in &key_values {
+            state.cache.put(key.clone(), Some(value.clone()));
+        }
+
+        let inner = self.inner.clone();
+        state.spawn(async move { inner.set_many(key_values).await });
+
+        Ok(())
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64, Error> {
+        // Atomic operations must go to the backing store synchronously, after any outstanding writes.
+
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        let value = self.inner.increment(key, delta).await?;
+
+        state
+            .cache
+            .put(key.to_owned(), Some(value.to_string().into_bytes()));
+
+        Ok(value)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &str,
+        old: Option<&[u8]>,
+        value: &[u8],
+    ) -> Result<bool, Error> {
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        let swapped = self.inner.compare_and_swap(key, old, value).await?;
+
+        if swapped {
+            state.cache.put(key.to_owned(), Some(value.to_owned()));
+        } else {
+            // Someone else changed the value, so whatever we have cached is stale.
+            state.cache.pop(key);
+        }
+
+        Ok(swapped)
+    }
+
+    async fn set_with_ttl(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), Error> {
+        // Tuples with an expiry go to the backing store synchronously and are not cached, since the cache has no
+        // notion of expiry.
+
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        self.inner.set_with_ttl(key, value, ttl).await?;
+
+        state.cache.pop(key);
+
+        Ok(())
+    }
+
+    async fn expire(&self, key: &str, ttl: Duration) -> Result<bool, Error> {
+        let mut state = self.state.lock().await;
+
+        state.flush().await?;
+
+        let existed = self.inner.expire(key, ttl).await?;
+
+        state.cache.pop(key);
+
+        Ok(existed)
+    }
+
     async fn get_keys(&self) -> Result<Vec<String>, Error> {
         // Get the keys from the backing store, remove any which are `None` in the cache, and add any which are
         // `Some` in the cache, returning the result.
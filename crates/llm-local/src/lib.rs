@@ -11,8 +11,8 @@ use llm::{
 use rand::SeedableRng;
 use spin_common::ui::quoted_path;
 use spin_core::async_trait;
-use spin_llm::{LlmEngine, MODEL_ALL_MINILM_L6_V2};
-use spin_world::v2::llm::{self as wasi_llm};
+use spin_llm::{InferenceStream, LlmEngine, MODEL_ALL_MINILM_L6_V2};
+use spin_world::v2_1::llm::{self as wasi_llm};
 use std::{
     collections::hash_map::Entry,
     collections::HashMap,
@@ -39,54 +39,11 @@ impl LlmEngine for LocalLlmEngine {
         params: wasi_llm::InferencingParams,
     ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
         let model = self.inferencing_model(model).await?;
-        let cfg = InferenceSessionConfig {
-            memory_k_type: ModelKVMemoryType::Float16,
-            memory_v_type: ModelKVMemoryType::Float16,
-            n_batch: 8,
-            n_threads: num_cpus::get(),
-        };
-
-        let mut session = Model::start_session(model.as_ref(), cfg);
-        let inference_params = InferenceParameters {
-            sampler: generate_sampler(params),
-        };
-        let mut rng = rand::rngs::StdRng::from_entropy();
         let mut text = String::new();
-
-        #[cfg(debug_assertions)]
-        {
-            terminal::warn!(
-                "\
-                This is a debug build - running inference might be prohibitively slow\n\
-                You may want to consider switching to the release build"
-            )
-        }
-        let res = session.infer::<Infallible>(
-            model.as_ref(),
-            &mut rng,
-            &llm::InferenceRequest {
-                prompt: prompt.as_str().into(),
-                parameters: &inference_params,
-                play_back_previous_tokens: false,
-                maximum_token_count: Some(params.max_tokens as usize),
-            },
-            &mut Default::default(),
-            |r| {
-                match r {
-                    InferenceResponse::InferredToken(t) => text.push_str(&t),
-                    InferenceResponse::EotToken => return Ok(InferenceFeedback::Halt),
-                    _ => {}
-                };
-                Ok(InferenceFeedback::Continue)
-            },
-        );
-        let stats = res.map_err(|e| {
-            wasi_llm::Error::RuntimeError(format!("Error occurred during inferencing: {e}"))
+        let usage = run_inference(model.as_ref(), &prompt, params, |token| {
+            text.push_str(token);
+            true
         })?;
-        let usage = wasi_llm::InferencingUsage {
-            prompt_token_count: stats.prompt_tokens as u32,
-            generated_token_count: (stats.predict_tokens - stats.prompt_tokens) as u32,
-        };
         let response = wasi_llm::InferencingResult { text, usage };
         Ok(response)
     }
@@ -101,6 +58,124 @@ impl LlmEngine for LocalLlmEngine {
             wasi_llm::Error::RuntimeError(format!("Error occurred generating embeddings: {e}"))
         })
     }
+
+    async fn infer_stream(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+    ) -> Result<Box<dyn InferenceStream>, wasi_llm::Error> {
+        let model = self.inferencing_model(model).await?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        tokio::task::spawn_blocking(move || {
+            let result = run_inference(model.as_ref(), &prompt, params, |token| {
+                // Stop inferencing if the guest dropped the stream
+                sender
+                    .blocking_send(StreamMessage::Token(token.to_owned()))
+                    .is_ok()
+            });
+            let _ = sender.blocking_send(match result {
+                Ok(usage) => StreamMessage::Done(usage),
+                Err(e) => StreamMessage::Failed(e),
+            });
+        });
+        Ok(Box::new(LocalInferenceStream {
+            receiver,
+            usage: None,
+        }))
+    }
+}
+
+// Runs inferencing to completion, passing each generated token to `on_token`,
+// which returns whether to continue.
+fn run_inference(
+    model: &dyn Model,
+    prompt: &str,
+    params: wasi_llm::InferencingParams,
+    mut on_token: impl FnMut(&str) -> bool,
+) -> Result<wasi_llm::InferencingUsage, wasi_llm::Error> {
+    let cfg = InferenceSessionConfig {
+        memory_k_type: ModelKVMemoryType::Float16,
+        memory_v_type: ModelKVMemoryType::Float16,
+        n_batch: 8,
+        n_threads: num_cpus::get(),
+    };
+
+    let mut session = Model::start_session(model, cfg);
+    let inference_params = InferenceParameters {
+        sampler: generate_sampler(params),
+    };
+    let mut rng = rand::rngs::StdRng::from_entropy();
+
+    #[cfg(debug_assertions)]
+    {
+        terminal::warn!(
+            "\
+            This is a debug build - running inference might be prohibitively slow\n\
+            You may want to consider switching to the release build"
+        )
+    }
+    let res = session.infer::<Infallible>(
+        model,
+        &mut rng,
+        &llm::InferenceRequest {
+            prompt: prompt.into(),
+            parameters: &inference_params,
+            play_back_previous_tokens: false,
+            maximum_token_count: Some(params.max_tokens as usize),
+        },
+        &mut Default::default(),
+        |r| {
+            match r {
+                InferenceResponse::InferredToken(t) => {
+                    if !on_token(&t) {
+                        return Ok(InferenceFeedback::Halt);
+                    }
+                }
+                InferenceResponse::EotToken => return Ok(InferenceFeedback::Halt),
+                _ => {}
+            };
+            Ok(InferenceFeedback::Continue)
+        },
+    );
+    let stats = res.map_err(|e| {
+        wasi_llm::Error::RuntimeError(format!("Error occurred during inferencing: {e}"))
+    })?;
+    Ok(wasi_llm::InferencingUsage {
+        prompt_token_count: stats.prompt_tokens as u32,
+        generated_token_count: (stats.predict_tokens - stats.prompt_tokens) as u32,
+    })
+}
+
+enum StreamMessage {
+    Token(String),
+    Done(wasi_llm::InferencingUsage),
+    Failed(wasi_llm::Error),
+}
+
+/// Tokens generated by local inferencing running on a blocking thread.
+struct LocalInferenceStream {
+    receiver: tokio::sync::mpsc::Receiver<StreamMessage>,
+    usage: Option<wasi_llm::InferencingUsage>,
+}
+
+#[async_trait]
+impl InferenceStream for LocalInferenceStream {
+    async fn next(&mut self) -> Result<Option<String>, wasi_llm::Error> {
+        match self.receiver.recv().await {
+            Some(StreamMessage::Token(token)) => Ok(Some(token)),
+            Some(StreamMessage::Done(usage)) => {
+                self.usage = Some(usage);
+                Ok(None)
+            }
+            Some(StreamMessage::Failed(e)) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    fn usage(&self) -> Option<wasi_llm::InferencingUsage> {
+        self.usage
+    }
 }
 
 impl LocalLlmEngine {
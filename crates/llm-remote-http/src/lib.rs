@@ -1,3 +1,5 @@
+mod open_ai;
+
 use anyhow::Result;
 use reqwest::{
     header::{HeaderMap, HeaderValue},
@@ -7,7 +9,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use spin_core::async_trait;
 use spin_llm::LlmEngine;
-use spin_world::v2::llm::{self as wasi_llm};
+use spin_world::v2_1::llm::{self as wasi_llm};
+
+pub use open_ai::{OpenAiApi, OpenAiLlmEngine, OpenAiModel};
 
 #[derive(Clone)]
 pub struct RemoteHttpLlmEngine {
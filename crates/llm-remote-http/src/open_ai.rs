@@ -0,0 +1,417 @@
+//! Inferencing with OpenAI-compatible APIs, including Azure OpenAI.
+
+use std::collections::{HashMap, VecDeque};
+
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+use serde_json::json;
+use spin_core::async_trait;
+use spin_llm::{InferenceStream, LlmEngine};
+use spin_world::v2_1::llm::{self as wasi_llm};
+
+/// The flavour of API served by an OpenAI-compatible endpoint.
+#[derive(Clone, Debug)]
+pub enum OpenAiApi {
+    /// The OpenAI API, or one compatible with it: models are chosen by name
+    /// in the request body, and requests are authorized with a bearer token.
+    OpenAi,
+    /// Azure OpenAI: models are chosen by deployment name in the URL, and
+    /// requests are authorized with an `api-key` header.
+    Azure {
+        /// The API version, e.g. `2024-02-01`.
+        api_version: String,
+    },
+}
+
+/// Where a model the app uses is served, if not by the engine's defaults.
+#[derive(Clone, Debug, Default)]
+pub struct OpenAiModel {
+    /// The model (or, for Azure, deployment) name the endpoint knows the
+    /// model by. Defaults to the name the app uses.
+    pub name: Option<String>,
+    /// The endpoint serving the model. Defaults to the engine's URL.
+    pub url: Option<Url>,
+}
+
+#[derive(Clone)]
+pub struct OpenAiLlmEngine {
+    api: OpenAiApi,
+    url: Url,
+    api_key: String,
+    // App model name -> where the model is served
+    models: HashMap<String, OpenAiModel>,
+    client: Client,
+}
+
+impl OpenAiLlmEngine {
+    pub fn new(
+        api: OpenAiApi,
+        url: Url,
+        api_key: String,
+        models: HashMap<String, OpenAiModel>,
+    ) -> Self {
+        Self {
+            api,
+            url,
+            api_key,
+            models,
+            client: Default::default(),
+        }
+    }
+
+    // Builds a POST request to the given operation (e.g. `chat/completions`)
+    // of the endpoint serving the model, returning the request and the name
+    // the endpoint knows the model by.
+    fn request(
+        &self,
+        model: &str,
+        operation: &str,
+    ) -> Result<(RequestBuilder, String), wasi_llm::Error> {
+        let mapping = self.models.get(model);
+        let name = mapping
+            .and_then(|m| m.name.clone())
+            .unwrap_or_else(|| model.to_owned());
+        let base = mapping.and_then(|m| m.url.as_ref()).unwrap_or(&self.url);
+        let url = match &self.api {
+            OpenAiApi::OpenAi => join(base, operation),
+            OpenAiApi::Azure { api_version } => {
+                join(base, &format!("openai/deployments/{name}/{operation}")).map(|mut url| {
+                    url.query_pairs_mut()
+                        .append_pair("api-version", api_version);
+                    url
+                })
+            }
+        }?;
+        tracing::info!("Sending remote inference request to {url}");
+        let request = self.client.post(url);
+        let request = match &self.api {
+            OpenAiApi::OpenAi => request.bearer_auth(&self.api_key),
+            OpenAiApi::Azure { .. } => request.header("api-key", &self.api_key),
+        };
+        Ok((request, name))
+    }
+
+    async fn send_chat_request(
+        &self,
+        model: &str,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+        stream: bool,
+    ) -> Result<reqwest::Response, wasi_llm::Error> {
+        let (request, name) = self.request(model, "chat/completions")?;
+        let mut body = json!({
+            "model": name,
+            "messages": [{ "role": "user", "content": prompt }],
+            "max_tokens": params.max_tokens,
+            "temperature": params.temperature,
+            "top_p": params.top_p,
+        });
+        if stream {
+            body["stream"] = true.into();
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        send(request.json(&body), "chat completions").await
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    prompt_tokens: u32,
+    #[serde(default)]
+    completion_tokens: u32,
+}
+
+impl From<ChatUsage> for wasi_llm::InferencingUsage {
+    fn from(usage: ChatUsage) -> Self {
+        Self {
+            prompt_token_count: usage.prompt_tokens,
+            generated_token_count: usage.completion_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatResponseBody {
+    choices: Vec<ChatChoice>,
+    usage: ChatUsage,
+}
+
+#[derive(Deserialize)]
+struct ChatChunkChoice {
+    delta: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChatChunkChoice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingUsage {
+    prompt_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponseBody {
+    data: Vec<Embedding>,
+    usage: EmbeddingUsage,
+}
+
+#[async_trait]
+impl LlmEngine for OpenAiLlmEngine {
+    async fn infer(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+    ) -> Result<wasi_llm::InferencingResult, wasi_llm::Error> {
+        let resp = self
+            .send_chat_request(&model, prompt, params, false)
+            .await?;
+        let body = resp.json::<ChatResponseBody>().await.map_err(|err| {
+            wasi_llm::Error::RuntimeError(format!(
+                "Failed to deserialize chat completions response: {err}"
+            ))
+        })?;
+        let text = body
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+        Ok(wasi_llm::InferencingResult {
+            text,
+            usage: body.usage.into(),
+        })
+    }
+
+    async fn generate_embeddings(
+        &mut self,
+        model: wasi_llm::EmbeddingModel,
+        data: Vec<String>,
+    ) -> Result<wasi_llm::EmbeddingsResult, wasi_llm::Error> {
+        let (request, name) = self.request(&model, "embeddings")?;
+        let body = json!({ "model": name, "input": data });
+        let resp = send(request.json(&body), "embeddings").await?;
+        let body = resp.json::<EmbeddingResponseBody>().await.map_err(|err| {
+            wasi_llm::Error::RuntimeError(format!(
+                "Failed to deserialize embeddings response: {err}"
+            ))
+        })?;
+        Ok(wasi_llm::EmbeddingsResult {
+            embeddings: body.data.into_iter().map(|e| e.embedding).collect(),
+            usage: wasi_llm::EmbeddingsUsage {
+                prompt_token_count: body.usage.prompt_tokens,
+            },
+        })
+    }
+
+    async fn infer_stream(
+        &mut self,
+        model: wasi_llm::InferencingModel,
+        prompt: String,
+        params: wasi_llm::InferencingParams,
+    ) -> Result<Box<dyn InferenceStream>, wasi_llm::Error> {
+        let response = self.send_chat_request(&model, prompt, params, true).await?;
+        Ok(Box::new(ChatStream {
+            response: Some(response),
+            events: Default::default(),
+            pending: Default::default(),
+            usage: None,
+        }))
+    }
+}
+
+/// Generated text read from a chat completions response's server-sent events.
+struct ChatStream {
+    // None once the response has been read to the end
+    response: Option<reqwest::Response>,
+    // Received bytes not yet parsed as complete lines
+    events: Vec<u8>,
+    // Parsed text not yet returned
+    pending: VecDeque<String>,
+    usage: Option<ChatUsage>,
+}
+
+impl ChatStream {
+    // Parses an event line, queueing any text it carries.
+    fn parse_line(&mut self, line: &[u8]) -> Result<(), wasi_llm::Error> {
+        let line = std::str::from_utf8(line)
+            .map_err(|_| wasi_llm::Error::RuntimeError("invalid UTF-8 in stream".into()))?
+            .trim();
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            return Ok(());
+        };
+        if data == "[DONE]" {
+            return Ok(());
+        }
+        let chunk: ChatChunk = serde_json::from_str(data).map_err(|err| {
+            wasi_llm::Error::RuntimeError(format!("Failed to deserialize stream chunk: {err}"))
+        })?;
+        self.pending.extend(
+            chunk
+                .choices
+                .into_iter()
+                .filter_map(|choice| choice.delta.content)
+                .filter(|text| !text.is_empty()),
+        );
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InferenceStream for ChatStream {
+    async fn next(&mut self) -> Result<Option<String>, wasi_llm::Error> {
+        loop {
+            if let Some(text) = self.pending.pop_front() {
+                return Ok(Some(text));
+            }
+            let Some(response) = &mut self.response else {
+                return Ok(None);
+            };
+            let chunk = response.chunk().await.map_err(|err| {
+                wasi_llm::Error::RuntimeError(format!("Failed to read stream: {err}"))
+            })?;
+            match chunk {
+                Some(bytes) => self.events.extend_from_slice(&bytes),
+                None => {
+                    // Treat anything after the last newline as a final line
+                    self.response = None;
+                    self.events.push(b'\n');
+                }
+            }
+            while let Some(end) = self.events.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.events.drain(..=end).collect();
+                self.parse_line(&line)?;
+            }
+        }
+    }
+
+    fn usage(&self) -> Option<wasi_llm::InferencingUsage> {
+        self.usage
+            .as_ref()
+            .map(|usage| wasi_llm::InferencingUsage {
+                prompt_token_count: usage.prompt_tokens,
+                generated_token_count: usage.completion_tokens,
+            })
+            .filter(|_| self.response.is_none())
+    }
+}
+
+// Joins a relative path to a base URL, keeping any path the base has. `Url::join`
+// would replace the base's last path segment unless it ends in a slash.
+fn join(base: &Url, path: &str) -> Result<Url, wasi_llm::Error> {
+    let mut base = base.clone();
+    if !base.path().ends_with('/') {
+        base.set_path(&format!("{}/", base.path()));
+    }
+    base.join(path)
+        .map_err(|_| wasi_llm::Error::RuntimeError("Failed to create URL".to_string()))
+}
+
+async fn send(
+    request: RequestBuilder,
+    operation: &str,
+) -> Result<reqwest::Response, wasi_llm::Error> {
+    let resp = request.send().await.map_err(|err| {
+        wasi_llm::Error::RuntimeError(format!("{operation} request error: {err}"))
+    })?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(wasi_llm::Error::RuntimeError(format!(
+            "{operation} request failed with status {status}: {body}"
+        )));
+    }
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(api: OpenAiApi, url: &str) -> OpenAiLlmEngine {
+        let models = HashMap::from([(
+            "llama2-chat".to_owned(),
+            OpenAiModel {
+                name: Some("gpt-chat".into()),
+                url: None,
+            },
+        )]);
+        OpenAiLlmEngine::new(api, url.parse().unwrap(), "key".into(), models)
+    }
+
+    #[test]
+    fn builds_request_urls() {
+        let openai = engine(OpenAiApi::OpenAi, "https://api.example.com/v1");
+        let (request, name) = openai.request("llama2-chat", "chat/completions").unwrap();
+        let request = request.build().unwrap();
+        assert_eq!(name, "gpt-chat");
+        assert_eq!(
+            request.url().as_str(),
+            "https://api.example.com/v1/chat/completions"
+        );
+        assert_eq!(request.headers()["authorization"], "Bearer key");
+
+        let azure = engine(
+            OpenAiApi::Azure {
+                api_version: "2024-02-01".into(),
+            },
+            "https://res.example.com",
+        );
+        let (request, name) = azure.request("other-model", "embeddings").unwrap();
+        let request = request.build().unwrap();
+        assert_eq!(name, "other-model");
+        assert_eq!(
+            request.url().as_str(),
+            "https://res.example.com/openai/deployments/other-model/embeddings?api-version=2024-02-01"
+        );
+        assert_eq!(request.headers()["api-key"], "key");
+    }
+
+    #[test]
+    fn parses_stream_events() {
+        let mut stream = ChatStream {
+            response: None,
+            events: vec![],
+            pending: Default::default(),
+            usage: None,
+        };
+        for line in [
+            r#"data: {"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"content":"Hello"}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":" world"}}]}"#,
+            r#"data: {"choices":[],"usage":{"prompt_tokens":3,"completion_tokens":2}}"#,
+            "data: [DONE]",
+        ] {
+            stream.parse_line(line.as_bytes()).unwrap();
+        }
+        assert_eq!(stream.pending, ["Hello", " world"]);
+        let usage = stream.usage().unwrap();
+        assert_eq!(usage.prompt_token_count, 3);
+        assert_eq!(usage.generated_token_count, 2);
+    }
+}
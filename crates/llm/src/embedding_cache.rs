@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use spin_core::async_trait;
+use spin_key_value::Store;
+use spin_world::v2_1::llm::{self as v2_1};
+
+use crate::{InferenceStream, LlmEngine};
+
+// Cached embeddings are stored under this prefix, followed by the model and
+// a hash of the text
+const CACHE_KEY_PREFIX: &str = "spin-llm-embedding";
+
+/// An engine which serves embeddings of text it has seen before from a
+/// key-value store, delegating everything else to another engine.
+pub struct EmbeddingCache {
+    engine: Box<dyn LlmEngine>,
+    store: Arc<dyn Store>,
+}
+
+impl EmbeddingCache {
+    pub fn new(engine: Box<dyn LlmEngine>, store: Arc<dyn Store>) -> Self {
+        Self { engine, store }
+    }
+
+    // Returns the cached embedding of the text, if any. Failures to read the
+    // cache are treated as misses.
+    async fn get(&self, key: &str) -> Option<Vec<f32>> {
+        match self.store.get(key).await {
+            Ok(value) => value.and_then(|bytes| decode(&bytes)),
+            Err(e) => {
+                tracing::warn!("Failed to read embedding cache: {e}");
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LlmEngine for EmbeddingCache {
+    async fn infer(
+        &mut self,
+        model: v2_1::InferencingModel,
+        prompt: String,
+        params: v2_1::InferencingParams,
+    ) -> Result<v2_1::InferencingResult, v2_1::Error> {
+        self.engine.infer(model, prompt, params).await
+    }
+
+    async fn generate_embeddings(
+        &mut self,
+        model: v2_1::EmbeddingModel,
+        data: Vec<String>,
+    ) -> Result<v2_1::EmbeddingsResult, v2_1::Error> {
+        let keys: Vec<String> = data.iter().map(|text| cache_key(&model, text)).collect();
+        let mut embeddings = Vec::with_capacity(data.len());
+        for key in &keys {
+            embeddings.push(self.get(key).await);
+        }
+
+        // Only the texts which were not cached are sent to the model
+        let (missing_indexes, missing_texts): (Vec<usize>, Vec<String>) = embeddings
+            .iter()
+            .zip(data)
+            .enumerate()
+            .filter(|(_, (embedding, _))| embedding.is_none())
+            .map(|(index, (_, text))| (index, text))
+            .unzip();
+        let mut prompt_token_count = 0;
+        if !missing_texts.is_empty() {
+            let result = self
+                .engine
+                .generate_embeddings(model, missing_texts)
+                .await?;
+            prompt_token_count = result.usage.prompt_token_count;
+            for (index, embedding) in missing_indexes.into_iter().zip(result.embeddings) {
+                if let Err(e) = self.store.set(&keys[index], &encode(&embedding)).await {
+                    tracing::warn!("Failed to write embedding cache: {e}");
+                }
+                embeddings[index] = Some(embedding);
+            }
+        }
+
+        Ok(v2_1::EmbeddingsResult {
+            embeddings: embeddings
+                .into_iter()
+                .map(|embedding| embedding.ok_or_else(missing_embedding))
+                .collect::<Result<_, _>>()?,
+            usage: v2_1::EmbeddingsUsage { prompt_token_count },
+        })
+    }
+
+    async fn infer_stream(
+        &mut self,
+        model: v2_1::InferencingModel,
+        prompt: String,
+        params: v2_1::InferencingParams,
+    ) -> Result<Box<dyn InferenceStream>, v2_1::Error> {
+        self.engine.infer_stream(model, prompt, params).await
+    }
+}
+
+fn cache_key(model: &str, text: &str) -> String {
+    let digest = spin_common::sha256::hex_digest_from_bytes(text);
+    format!("{CACHE_KEY_PREFIX}:{model}:{digest}")
+}
+
+fn missing_embedding() -> v2_1::Error {
+    v2_1::Error::RuntimeError("the model returned fewer embeddings than texts".into())
+}
+
+// Embeddings are stored as little-endian f32s
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_embeddings() {
+        let embedding = vec![0.5, -1.25, 3.0];
+        assert_eq!(decode(&encode(&embedding)), Some(embedding));
+        assert_eq!(decode(&[0, 1, 2]), None);
+        assert_ne!(cache_key("model", "a"), cache_key("model", "b"));
+        assert_ne!(cache_key("model", "a"), cache_key("other", "a"));
+    }
+}
@@ -1,7 +1,10 @@
+use std::sync::Arc;
+
 use spin_app::DynamicHostComponent;
 use spin_core::HostComponent;
+use spin_key_value::Store;
 
-use crate::{LlmDispatch, LlmEngine, AI_MODELS_KEY};
+use crate::{EmbeddingCache, LlmDispatch, LlmEngine, AI_MODELS_KEY};
 
 pub struct LlmComponent {
     create_engine: Box<dyn Fn() -> Box<dyn LlmEngine> + Send + Sync>,
@@ -16,6 +19,12 @@ impl LlmComponent {
             create_engine: Box::new(create_engine),
         }
     }
+
+    /// Serves embeddings of previously seen text from the given store.
+    pub fn with_embedding_cache(self, store: Arc<dyn Store>) -> Self {
+        let create_engine = self.create_engine;
+        Self::new(move || Box::new(EmbeddingCache::new(create_engine(), store.clone())))
+    }
 }
 
 impl HostComponent for LlmComponent {
@@ -26,13 +35,15 @@ impl HostComponent for LlmComponent {
         get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
     ) -> anyhow::Result<()> {
         spin_world::v1::llm::add_to_linker(linker, get)?;
-        spin_world::v2::llm::add_to_linker(linker, get)
+        spin_world::v2::llm::add_to_linker(linker, get)?;
+        spin_world::v2_1::llm::add_to_linker(linker, get)
     }
 
     fn build_data(&self) -> Self::Data {
         LlmDispatch {
             engine: (self.create_engine)(),
             allowed_models: Default::default(),
+            streams: table::Table::new(16),
         }
     }
 }
@@ -1,11 +1,14 @@
+mod embedding_cache;
 pub mod host_component;
 
 use spin_app::MetadataKey;
-use spin_core::async_trait;
+use spin_core::{async_trait, wasmtime::component::Resource};
 use spin_world::v1::llm::{self as v1};
-use spin_world::v2::llm::{self as v2};
+use spin_world::v2::llm as v2;
+use spin_world::v2_1::llm::{self as v2_1};
 use std::collections::HashSet;
 
+pub use crate::embedding_cache::EmbeddingCache;
 pub use crate::host_component::LlmComponent;
 
 pub const MODEL_ALL_MINILM_L6_V2: &str = "all-minilm-l6-v2";
@@ -17,54 +20,114 @@ pub trait LlmEngine: Send + Sync {
         &mut self,
         model: v1::InferencingModel,
         prompt: String,
-        params: v2::InferencingParams,
-    ) -> Result<v2::InferencingResult, v2::Error>;
+        params: v2_1::InferencingParams,
+    ) -> Result<v2_1::InferencingResult, v2_1::Error>;
 
     async fn generate_embeddings(
         &mut self,
-        model: v2::EmbeddingModel,
+        model: v2_1::EmbeddingModel,
         data: Vec<String>,
-    ) -> Result<v2::EmbeddingsResult, v2::Error>;
+    ) -> Result<v2_1::EmbeddingsResult, v2_1::Error>;
+
+    /// Performs inferencing, returning the generated text as it is produced.
+    /// By default the text is returned all at once when inferencing is done.
+    async fn infer_stream(
+        &mut self,
+        model: v2_1::InferencingModel,
+        prompt: String,
+        params: v2_1::InferencingParams,
+    ) -> Result<Box<dyn InferenceStream>, v2_1::Error> {
+        let result = self.infer(model, prompt, params).await?;
+        Ok(Box::new(CompletedInference {
+            text: Some(result.text),
+            usage: result.usage,
+        }))
+    }
+}
+
+/// Text generated by a streaming inferencing request.
+#[async_trait]
+pub trait InferenceStream: Send + Sync {
+    /// Returns the next piece of generated text, or `None` once the model has
+    /// finished.
+    async fn next(&mut self) -> Result<Option<String>, v2_1::Error>;
+
+    /// Returns usage information, if known, once the model has finished.
+    fn usage(&self) -> Option<v2_1::InferencingUsage>;
+}
+
+// An inferencing result returned as a stream of one piece.
+struct CompletedInference {
+    text: Option<String>,
+    usage: v2_1::InferencingUsage,
+}
+
+#[async_trait]
+impl InferenceStream for CompletedInference {
+    async fn next(&mut self) -> Result<Option<String>, v2_1::Error> {
+        Ok(self.text.take())
+    }
+
+    fn usage(&self) -> Option<v2_1::InferencingUsage> {
+        Some(v2_1::InferencingUsage {
+            prompt_token_count: self.usage.prompt_token_count,
+            generated_token_count: self.usage.generated_token_count,
+        })
+    }
 }
 
 pub struct LlmDispatch {
     engine: Box<dyn LlmEngine>,
     allowed_models: HashSet<String>,
+    streams: table::Table<Box<dyn InferenceStream>>,
 }
 
 #[async_trait]
-impl v2::Host for LlmDispatch {
+impl v2_1::Host for LlmDispatch {
     async fn infer(
         &mut self,
-        model: v2::InferencingModel,
+        model: v2_1::InferencingModel,
         prompt: String,
-        params: Option<v2::InferencingParams>,
-    ) -> anyhow::Result<Result<v2::InferencingResult, v2::Error>> {
+        params: Option<v2_1::InferencingParams>,
+    ) -> anyhow::Result<Result<v2_1::InferencingResult, v2_1::Error>> {
         if !self.allowed_models.contains(&model) {
             return Ok(Err(access_denied_error(&model)));
         }
         Ok(self
             .engine
-            .infer(
-                model,
-                prompt,
-                params.unwrap_or(v2::InferencingParams {
-                    max_tokens: 100,
-                    repeat_penalty: 1.1,
-                    repeat_penalty_last_n_token_count: 64,
-                    temperature: 0.8,
-                    top_k: 40,
-                    top_p: 0.9,
-                }),
-            )
+            .infer(model, prompt, params.unwrap_or_else(default_params))
             .await)
     }
 
+    async fn infer_stream(
+        &mut self,
+        model: v2_1::InferencingModel,
+        prompt: String,
+        params: Option<v2_1::InferencingParams>,
+    ) -> anyhow::Result<Result<Resource<v2_1::InferenceStream>, v2_1::Error>> {
+        if !self.allowed_models.contains(&model) {
+            return Ok(Err(access_denied_error(&model)));
+        }
+        let stream = match self
+            .engine
+            .infer_stream(model, prompt, params.unwrap_or_else(default_params))
+            .await
+        {
+            Ok(stream) => stream,
+            Err(err) => return Ok(Err(err)),
+        };
+        Ok(self
+            .streams
+            .push(stream)
+            .map(Resource::new_own)
+            .map_err(|()| v2_1::Error::RuntimeError("too many inference streams".into())))
+    }
+
     async fn generate_embeddings(
         &mut self,
         m: v1::EmbeddingModel,
         data: Vec<String>,
-    ) -> anyhow::Result<Result<v2::EmbeddingsResult, v2::Error>> {
+    ) -> anyhow::Result<Result<v2_1::EmbeddingsResult, v2_1::Error>> {
         if !self.allowed_models.contains(&m) {
             return Ok(Err(access_denied_error(&m)));
         }
@@ -72,6 +135,64 @@ impl v2::Host for LlmDispatch {
     }
 }
 
+#[async_trait]
+impl v2_1::HostInferenceStream for LlmDispatch {
+    async fn next(
+        &mut self,
+        stream: Resource<v2_1::InferenceStream>,
+    ) -> anyhow::Result<Result<Option<String>, v2_1::Error>> {
+        let stream = self
+            .streams
+            .get_mut(stream.rep())
+            .ok_or_else(|| anyhow::anyhow!("unknown inference stream"))?;
+        Ok(stream.next().await)
+    }
+
+    async fn usage(
+        &mut self,
+        stream: Resource<v2_1::InferenceStream>,
+    ) -> anyhow::Result<Option<v2_1::InferencingUsage>> {
+        let stream = self
+            .streams
+            .get(stream.rep())
+            .ok_or_else(|| anyhow::anyhow!("unknown inference stream"))?;
+        Ok(stream.usage())
+    }
+
+    fn drop(&mut self, stream: Resource<v2_1::InferenceStream>) -> anyhow::Result<()> {
+        self.streams.remove(stream.rep());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl v2::Host for LlmDispatch {
+    async fn infer(
+        &mut self,
+        model: v2::InferencingModel,
+        prompt: String,
+        params: Option<v2::InferencingParams>,
+    ) -> anyhow::Result<Result<v2::InferencingResult, v2::Error>> {
+        Ok(
+            <Self as v2_1::Host>::infer(self, model, prompt, params.map(Into::into))
+                .await?
+                .map(Into::into)
+                .map_err(Into::into),
+        )
+    }
+
+    async fn generate_embeddings(
+        &mut self,
+        model: v2::EmbeddingModel,
+        data: Vec<String>,
+    ) -> anyhow::Result<Result<v2::EmbeddingsResult, v2::Error>> {
+        Ok(<Self as v2_1::Host>::generate_embeddings(self, model, data)
+            .await?
+            .map(Into::into)
+            .map_err(Into::into))
+    }
+}
+
 #[async_trait]
 impl v1::Host for LlmDispatch {
     async fn infer(
@@ -100,8 +221,19 @@ impl v1::Host for LlmDispatch {
     }
 }
 
-fn access_denied_error(model: &str) -> v2::Error {
-    v2::Error::InvalidInput(format!(
+fn default_params() -> v2_1::InferencingParams {
+    v2_1::InferencingParams {
+        max_tokens: 100,
+        repeat_penalty: 1.1,
+        repeat_penalty_last_n_token_count: 64,
+        temperature: 0.8,
+        top_k: 40,
+        top_p: 0.9,
+    }
+}
+
+fn access_denied_error(model: &str) -> v2_1::Error {
+    v2_1::Error::InvalidInput(format!(
         "The component does not have access to use '{model}'. To give the component access, add '{model}' to the 'ai_models' key for the component in your spin.toml manifest"
     ))
 }
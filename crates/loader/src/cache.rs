@@ -1,6 +1,7 @@
 //! Cache for OCI registry entities.
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use spin_common::sha256::hex_digest_from_file;
 use tokio::fs;
 
 use std::path::{Path, PathBuf};
@@ -77,16 +78,32 @@ impl Cache {
         Ok(path)
     }
 
+    /// Return the path to a wasm file given its digest, after checking that
+    /// its contents match the digest. A file which doesn't match is removed
+    /// from the cache, so that it can be fetched again.
+    pub fn verified_wasm_file(&self, digest: impl AsRef<str>) -> Result<PathBuf> {
+        let path = self.wasm_file(&digest)?;
+        verify_file(&path, digest.as_ref())?;
+        Ok(path)
+    }
+
+    /// Return the path to a data file given its digest, after checking that
+    /// its contents match the digest. A file which doesn't match is removed
+    /// from the cache, so that it can be fetched again.
+    pub fn verified_data_file(&self, digest: impl AsRef<str>) -> Result<PathBuf> {
+        let path = self.data_file(&digest)?;
+        verify_file(&path, digest.as_ref())?;
+        Ok(path)
+    }
+
     /// Write the contents in the cache's wasm directory.
     pub async fn write_wasm(&self, bytes: impl AsRef<[u8]>, digest: impl AsRef<str>) -> Result<()> {
-        fs::write(self.wasm_path(digest), bytes.as_ref()).await?;
-        Ok(())
+        write_atomic(&self.wasm_path(digest), bytes.as_ref()).await
     }
 
     /// Write the contents in the cache's data directory.
     pub async fn write_data(&self, bytes: impl AsRef<[u8]>, digest: impl AsRef<str>) -> Result<()> {
-        fs::write(self.data_path(digest), bytes.as_ref()).await?;
-        Ok(())
+        write_atomic(&self.data_path(digest), bytes.as_ref()).await
     }
 
     /// The path of contents in the cache's wasm directory, which may or may not exist.
@@ -133,6 +150,31 @@ impl Cache {
     }
 }
 
+// Checks the contents of a cached file against its (SHA-256) digest.
+fn verify_file(path: &Path, digest: &str) -> Result<()> {
+    let expected = digest
+        .strip_prefix("sha256:")
+        .with_context(|| format!("unsupported digest {digest}; only sha256 is supported"))?;
+    let actual = hex_digest_from_file(path)
+        .with_context(|| format!("cannot read cached file for digest {digest}"))?;
+    if actual != expected {
+        std::fs::remove_file(path)
+            .with_context(|| format!("cannot remove corrupt cached file {path:?}"))?;
+        bail!("cached file for digest {digest} was corrupt (sha256:{actual}) and has been removed");
+    }
+    Ok(())
+}
+
+// Writes via a temporary file, so that concurrent pulls or interrupted
+// writes never leave a partial file at a digest path.
+async fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let dir = path.parent().context("cache path has no parent")?;
+    let temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    fs::write(temp_file.path(), bytes).await?;
+    temp_file.persist(path)?;
+    Ok(())
+}
+
 #[cfg(windows)]
 fn safe_name(digest: impl AsRef<str>) -> impl AsRef<Path> {
     digest.as_ref().replace(':', "_")
@@ -166,4 +208,21 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn removes_corrupt_files() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let cache = Cache::new(Some(temp_dir.path().to_owned())).await?;
+
+        let data = "hello".as_bytes();
+        let digest = format!("sha256:{}", hex_digest_from_bytes(data));
+        cache.write_data(data, &digest).await?;
+        assert!(cache.verified_data_file(&digest).is_ok());
+
+        std::fs::write(cache.data_path(&digest), "goodbye")?;
+        assert!(cache.verified_data_file(&digest).is_err());
+        assert!(cache.data_file(&digest).is_err());
+
+        Ok(())
+    }
 }
@@ -1,3 +1,5 @@
+//! Downloads of remote content over HTTP(S).
+
 use std::path::Path;
 
 use anyhow::{ensure, Context, Result};
@@ -44,3 +46,26 @@ pub async fn verified_download(url: &str, digest: &str, dest: &Path) -> Result<(
 
     Ok(())
 }
+
+/// Downloads content from `url` into memory. If `digest` is given the content
+/// will be verified to match it.
+pub async fn download_bytes(url: &str, digest: Option<&str>) -> Result<Vec<u8>> {
+    tracing::debug!("Downloading content from {url:?}");
+
+    let bytes = reqwest::get(url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?
+        .to_vec();
+
+    if let Some(digest) = digest {
+        let actual_digest = format!("sha256:{:x}", sha2::Sha256::digest(&bytes));
+        ensure!(
+            actual_digest == digest,
+            "invalid content digest; expected {digest}, downloaded {actual_digest}"
+        );
+    }
+
+    Ok(bytes)
+}
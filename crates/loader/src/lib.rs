@@ -18,7 +18,7 @@ use spin_common::paths::parent_dir;
 use spin_locked_app::locked::LockedApp;
 
 pub mod cache;
-mod http;
+pub mod http;
 mod local;
 
 /// Maximum number of files to copy (or download) concurrently
@@ -38,13 +38,30 @@ pub async fn from_file(
     loader.load_file(path).await
 }
 
+/// Load a Spin locked app from a spin.toml manifest file, as [`from_file`],
+/// first applying the manifest's `[profile.<name>]` overlay if `profile` is
+/// given.
+pub async fn from_file_with_profile(
+    manifest_path: impl AsRef<Path>,
+    files_mount_strategy: FilesMountStrategy,
+    cache_root: Option<PathBuf>,
+    profile: Option<String>,
+) -> Result<LockedApp> {
+    let path = manifest_path.as_ref();
+    let app_root = parent_dir(path)?;
+    let loader = LocalLoader::new(&app_root, files_mount_strategy, cache_root)
+        .await?
+        .with_profile(profile);
+    loader.load_file(path).await
+}
+
 /// The strategy to use for mounting WASI files into a guest.
 #[derive(Debug)]
 pub enum FilesMountStrategy {
     /// Copy files into the given mount root directory.
     Copy(PathBuf),
-    /// Mount files directly from their source director(ies). This only
-    /// supports mounting full directories; mounting single files, glob
-    /// patterns, and `exclude_files` are not supported.
+    /// Mount files directly from their source director(ies) or file(s). Glob
+    /// patterns are expanded when the app is loaded, so files created later
+    /// will not be mounted. `exclude_files` is not supported.
     Direct,
 }
@@ -22,6 +22,7 @@ pub struct LocalLoader {
     files_mount_strategy: FilesMountStrategy,
     cache: Cache,
     file_loading_permits: Semaphore,
+    profile: Option<String>,
 }
 
 impl LocalLoader {
@@ -38,20 +39,31 @@ impl LocalLoader {
             cache: Cache::new(cache_root).await?,
             // Limit concurrency to avoid hitting system resource limits
             file_loading_permits: Semaphore::new(crate::MAX_FILE_LOADING_CONCURRENCY),
+            profile: None,
         })
     }
 
+    // Applies the named manifest profile to loaded manifests.
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+
     // Load the manifest file (spin.toml) at the given path into a LockedApp,
     // preparing all its content for execution.
     pub async fn load_file(&self, path: impl AsRef<Path>) -> Result<LockedApp> {
         // Parse manifest
         let path = path.as_ref();
-        let manifest = spin_manifest::manifest_from_file(path).with_context(|| {
+        let mut manifest = spin_manifest::manifest_from_file(path).with_context(|| {
             format!(
                 "Failed to read Spin app manifest from {}",
                 quoted_path(path)
             )
         })?;
+        if let Some(profile) = &self.profile {
+            spin_manifest::profile::apply_profile(&mut manifest, profile)
+                .with_context(|| format!("Invalid profile in {}", quoted_path(path)))?;
+        }
         let mut locked = self
             .load_manifest(manifest)
             .await
@@ -71,10 +83,16 @@ impl LocalLoader {
 
         let AppManifest {
             spin_manifest_version: _,
+            // Resolved when the manifest file was read
+            include: _,
             application,
             variables,
             triggers,
+            // Applied to triggers by normalization
+            trigger_defaults: _,
             components,
+            // Applied by the caller, if one was selected
+            profiles: _,
         } = manifest;
 
         let metadata = locked_metadata(application, triggers.keys().cloned())?;
@@ -122,20 +140,42 @@ impl LocalLoader {
             .context("`allowed_http_hosts` is malformed")?;
         let _ = spin_outbound_networking::AllowedHostsConfig::parse(&allowed_outbound_hosts)
             .context("`allowed_outbound_hosts` is malformed")?;
+        // Passing through the whole host environment would expose the host's
+        // credentials, so each entry must name a variable or a prefix.
+        ensure!(
+            !component.environment_allowlist.iter().any(|entry| entry == "*"),
+            "`environment_allowlist` may not contain \"*\", which would pass through the entire host environment; list variable names or prefixes such as \"OTEL_*\""
+        );
 
         let metadata = ValuesMapBuilder::new()
             .string("description", component.description)
             .string_array("allowed_outbound_hosts", allowed_outbound_hosts)
             .string_array("key_value_stores", component.key_value_stores)
             .string_array("databases", component.sqlite_databases)
+            .string_array("read_only_databases", component.read_only_sqlite_databases)
+            .string_array("blob_stores", component.blob_stores)
+            .string_array("environment_allowlist", component.environment_allowlist)
+            .string_array("args", component.args)
+            .string_option("working_dir", component.working_dir)
             .string_array("ai_models", component.ai_models)
             .serializable("build", component.build)?
+            .serializable("limits", component.limits)?
+            .serializable("outbound_http", component.outbound_http)?
             .take();
 
-        let source = self
+        let mut source = self
             .load_component_source(component.source.clone())
             .await
             .with_context(|| format!("Failed to load Wasm source {}", component.source))?;
+        for (import_name, dependency) in component.dependencies {
+            let dependency_source = self
+                .load_dependency_source(dependency.clone())
+                .await
+                .with_context(|| {
+                    format!("Failed to load dependency {import_name:?} from {dependency}")
+                })?;
+            source.dependencies.insert(import_name, dependency_source);
+        }
 
         let env = component.environment.into_iter().collect();
 
@@ -152,10 +192,30 @@ impl LocalLoader {
                     .await?;
 
                     // All component files (copies) are in `component_mount_root` now
-                    vec![ContentPath {
-                        content: file_content_ref(component_mount_root)?,
+                    let mut files = vec![ContentPath {
+                        content: file_content_ref(&component_mount_root)?,
                         path: "/".into(),
-                    }]
+                        writable: false,
+                    }];
+                    // Writable placements are additionally mounted at their
+                    // destinations, shadowing the read-only root mount
+                    for mount in &component.files {
+                        if let WasiFilesMount::Placement {
+                            destination,
+                            writable: true,
+                            ..
+                        } = mount
+                        {
+                            let copy_path =
+                                component_mount_root.join(destination.trim_start_matches('/'));
+                            files.push(ContentPath {
+                                content: file_content_ref(copy_path)?,
+                                path: destination.into(),
+                                writable: true,
+                            });
+                        }
+                    }
+                    files
                 }
                 FilesMountStrategy::Direct => {
                     ensure!(
@@ -164,8 +224,8 @@ impl LocalLoader {
                     );
                     let mut files = vec![];
                     for mount in &component.files {
-                        // Validate (and canonicalize) direct mount directory
-                        files.push(self.resolve_direct_mount(mount).await?);
+                        // Validate (and canonicalize) direct mounts
+                        files.extend(self.resolve_direct_mounts(mount).await?);
                     }
                     files
                 }
@@ -203,6 +263,30 @@ impl LocalLoader {
         Ok(LockedComponentSource {
             content_type: "application/wasm".into(),
             content,
+            dependencies: Default::default(),
+        })
+    }
+
+    // Load a dependency component source. Registry dependencies are left for
+    // the runtime loader to pull.
+    async fn load_dependency_source(
+        &self,
+        dependency: v2::ComponentDependency,
+    ) -> Result<LockedComponentSource> {
+        let content = match dependency {
+            v2::ComponentDependency::Local { path } => file_content_ref(self.app_root.join(path))?,
+            v2::ComponentDependency::Remote { url, digest } => {
+                self.load_http_source(&url, &digest).await?
+            }
+            v2::ComponentDependency::Registry { reference } => ContentRef {
+                source: Some(format!("oci://{reference}")),
+                ..Default::default()
+            },
+        };
+        Ok(LockedComponentSource {
+            content_type: "application/wasm".into(),
+            content,
+            dependencies: Default::default(),
         })
     }
 
@@ -242,6 +326,7 @@ impl LocalLoader {
             WasiFilesMount::Placement {
                 source,
                 destination,
+                ..
             } => {
                 let src = Path::new(source);
                 let dest = dest_root.join(destination.trim_start_matches('/'));
@@ -379,24 +464,52 @@ impl LocalLoader {
         Ok(())
     }
 
-    // Resolve the given direct mount directory, checking that it is valid for
-    // direct mounting and returning its canonicalized source path.
-    async fn resolve_direct_mount(&self, mount: &WasiFilesMount) -> Result<ContentPath> {
-        let (src, dest) = match mount {
-            WasiFilesMount::Pattern(pattern) => (pattern, pattern),
+    // Resolve the given direct mount, checking that it is valid for direct
+    // mounting and returning its canonicalized source path(s). Glob patterns
+    // are expanded into a mount for each matching file.
+    async fn resolve_direct_mounts(&self, mount: &WasiFilesMount) -> Result<Vec<ContentPath>> {
+        let (src, dest, writable) = match mount {
+            WasiFilesMount::Pattern(pattern) => (pattern, pattern, false),
             WasiFilesMount::Placement {
                 source,
                 destination,
-            } => (source, destination),
+                writable,
+            } => (source, destination, *writable),
         };
         let path = self.app_root.join(src);
-        if !path.is_dir() {
-            bail!("Only directory mounts are supported with `--direct-mounts`; {src:?} is not a directory.");
+        if path.exists() {
+            return Ok(vec![ContentPath {
+                content: file_content_ref(path)?,
+                path: dest.into(),
+                writable,
+            }]);
         }
-        Ok(ContentPath {
-            content: file_content_ref(src)?,
-            path: dest.into(),
-        })
+        let WasiFilesMount::Pattern(pattern) = mount else {
+            bail!("invalid file mount source {}", quoted_path(src));
+        };
+        if !looks_like_glob_pattern(pattern) {
+            bail!("{pattern:?} does not exist and doesn't appear to be a glob pattern");
+        }
+
+        let glob_pattern = path
+            .to_str()
+            .with_context(|| format!("invalid (non-utf8) file pattern {path:?}"))?;
+        let paths = glob::glob(glob_pattern)
+            .with_context(|| format!("Failed to resolve glob pattern {glob_pattern:?}"))?;
+        let mut files = vec![];
+        for path_res in paths {
+            let src = path_res?;
+            if !src.is_file() {
+                continue;
+            }
+            let relative_path = src.strip_prefix(&self.app_root)?.to_owned();
+            files.push(ContentPath {
+                content: file_content_ref(src)?,
+                path: relative_path,
+                writable: false,
+            });
+        }
+        Ok(files)
     }
 }
 
@@ -0,0 +1,38 @@
+use crate::{LockDispatch, LockManager};
+use spin_app::{AppComponent, DynamicHostComponent, APP_NAME_KEY};
+use spin_core::HostComponent;
+use std::sync::Arc;
+
+pub struct LockComponent {
+    manager: Arc<dyn LockManager>,
+}
+
+impl LockComponent {
+    pub fn new(manager: Arc<dyn LockManager>) -> Self {
+        Self { manager }
+    }
+}
+
+impl HostComponent for LockComponent {
+    type Data = LockDispatch;
+
+    fn add_to_linker<T: Send>(
+        linker: &mut spin_core::Linker<T>,
+        get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
+    ) -> anyhow::Result<()> {
+        spin_world::spin::lock::lock::add_to_linker(linker, get)
+    }
+
+    fn build_data(&self) -> Self::Data {
+        LockDispatch::new()
+    }
+}
+
+impl DynamicHostComponent for LockComponent {
+    fn update_data(&self, data: &mut Self::Data, component: &AppComponent) -> anyhow::Result<()> {
+        // Locks are shared by all the components of an app, but not between apps
+        let app_name = component.app.require_metadata(APP_NAME_KEY)?;
+        data.init(self.manager.clone(), &app_name);
+        Ok(())
+    }
+}
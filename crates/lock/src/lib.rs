@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_world::spin::lock::lock::{self, Lease as LeaseResource};
+use std::{sync::Arc, time::Duration};
+use table::Table;
+
+mod host_component;
+mod redis;
+mod sqlite;
+
+pub use crate::redis::LockRedis;
+pub use crate::sqlite::{DatabaseLocation, LockSqlite};
+pub use host_component::LockComponent;
+pub use lock::Error;
+
+const DEFAULT_LEASE_TABLE_CAPACITY: u32 = 256;
+
+/// A backend holding named locks. Each lease on a lock is identified by a
+/// token unique to the lease, which must be presented to renew or release it.
+#[async_trait]
+pub trait LockManager: Sync + Send {
+    /// Acquires the lock `name` for `ttl` unless another unexpired lease on it
+    /// is held, returning whether it was acquired.
+    async fn acquire(&self, name: &str, token: &str, ttl: Duration) -> Result<bool, Error>;
+    /// Extends the lease so it expires after `ttl`, returning `false` if the
+    /// lease has already expired.
+    async fn renew(&self, name: &str, token: &str, ttl: Duration) -> Result<bool, Error>;
+    /// Releases the lease, if it is still held.
+    async fn release(&self, name: &str, token: &str) -> Result<(), Error>;
+}
+
+struct Lease {
+    // The lock's name in the backend, qualified by the app name
+    key: String,
+    name: String,
+    token: String,
+    released: bool,
+}
+
+pub struct LockDispatch {
+    manager: Option<Arc<dyn LockManager>>,
+    // Prefix of the backend keys of this app's locks
+    namespace: String,
+    leases: Table<Lease>,
+}
+
+impl LockDispatch {
+    pub fn new() -> Self {
+        Self::new_with_capacity(DEFAULT_LEASE_TABLE_CAPACITY)
+    }
+
+    pub fn new_with_capacity(capacity: u32) -> Self {
+        Self {
+            manager: None,
+            namespace: String::new(),
+            leases: Table::new(capacity),
+        }
+    }
+
+    pub fn init(&mut self, manager: Arc<dyn LockManager>, app_name: &str) {
+        self.manager = Some(manager);
+        self.namespace = format!("{app_name}:");
+    }
+
+    fn manager(&self) -> anyhow::Result<&Arc<dyn LockManager>> {
+        self.manager
+            .as_ref()
+            .context("lock manager not initialized")
+    }
+
+    fn get_lease(&self, lease: &Resource<LeaseResource>) -> anyhow::Result<&Lease> {
+        self.leases.get(lease.rep()).context("invalid lease")
+    }
+}
+
+impl Default for LockDispatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl lock::Host for LockDispatch {}
+
+#[async_trait]
+impl lock::HostLease for LockDispatch {
+    async fn acquire(
+        &mut self,
+        name: String,
+        ttl_ms: u64,
+    ) -> Result<Result<Option<Resource<LeaseResource>>, Error>> {
+        let manager = self.manager()?.clone();
+        let key = format!("{}{name}", self.namespace);
+        let token = uuid::Uuid::new_v4().to_string();
+        let ttl = Duration::from_millis(ttl_ms);
+        if let Err(err) = ensure_positive(ttl) {
+            return Ok(Err(err));
+        }
+        match manager.acquire(&key, &token, ttl).await {
+            Ok(true) => {}
+            Ok(false) => return Ok(Ok(None)),
+            Err(err) => return Ok(Err(err)),
+        }
+        let lease = Lease {
+            key: key.clone(),
+            name,
+            token: token.clone(),
+            released: false,
+        };
+        match self.leases.push(lease) {
+            Ok(rep) => Ok(Ok(Some(Resource::new_own(rep)))),
+            Err(()) => {
+                // Don't leave the lock held by a lease the guest never saw
+                release_in_background(manager, key, token);
+                Ok(Err(Error::TooManyLeases))
+            }
+        }
+    }
+
+    async fn name(&mut self, lease: Resource<LeaseResource>) -> Result<String> {
+        Ok(self.get_lease(&lease)?.name.clone())
+    }
+
+    async fn renew(
+        &mut self,
+        lease: Resource<LeaseResource>,
+        ttl_ms: u64,
+    ) -> Result<Result<bool, Error>> {
+        let manager = self.manager()?.clone();
+        let lease = self.get_lease(&lease)?;
+        let ttl = Duration::from_millis(ttl_ms);
+        if lease.released {
+            return Ok(Err(Error::Released));
+        }
+        if let Err(err) = ensure_positive(ttl) {
+            return Ok(Err(err));
+        }
+        Ok(manager.renew(&lease.key, &lease.token, ttl).await)
+    }
+
+    async fn release(&mut self, lease: Resource<LeaseResource>) -> Result<Result<(), Error>> {
+        let manager = self.manager()?.clone();
+        let lease = self.leases.get_mut(lease.rep()).context("invalid lease")?;
+        if lease.released {
+            return Ok(Err(Error::Released));
+        }
+        lease.released = true;
+        Ok(manager.release(&lease.key, &lease.token).await)
+    }
+
+    fn drop(&mut self, lease: Resource<LeaseResource>) -> Result<()> {
+        let Some(lease) = self.leases.remove(lease.rep()) else {
+            return Ok(());
+        };
+        if let (false, Some(manager)) = (lease.released, self.manager.clone()) {
+            release_in_background(manager, lease.key, lease.token);
+        }
+        Ok(())
+    }
+}
+
+// Dropping a lease can't wait for the backend, so the release happens in a
+// task of its own.
+fn release_in_background(manager: Arc<dyn LockManager>, key: String, token: String) {
+    tokio::spawn(async move {
+        if let Err(err) = manager.release(&key, &token).await {
+            tracing::warn!("failed to release dropped lease on lock {key}: {err:?}");
+        }
+    });
+}
+
+fn ensure_positive(ttl: Duration) -> Result<(), Error> {
+    if ttl.is_zero() {
+        return Err(Error::Other("lease TTL must be positive".into()));
+    }
+    Ok(())
+}
+
+pub fn log_error(err: impl std::fmt::Debug) -> Error {
+    tracing::warn!("lock error: {err:?}");
+    Error::Other(format!("{err:?}"))
+}
@@ -0,0 +1,117 @@
+use crate::{log_error, Error, LockManager};
+use anyhow::{Context, Result};
+use redis::{aio::MultiplexedConnection, parse_redis_url, IntoConnectionInfo, Script};
+use spin_core::async_trait;
+use spin_outbound_networking::dns::Resolver;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use url::Url;
+
+// Extends the lease in KEYS[1] if it is still held with the token ARGV[1]
+const RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+  return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+// Deletes the lease in KEYS[1] if it is still held with the token ARGV[1]
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+  return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+/// Locks held as expiring keys in a Redis server.
+pub struct LockRedis {
+    database_url: Url,
+    key_prefix: String,
+    resolver: Resolver,
+    connection: OnceCell<MultiplexedConnection>,
+}
+
+impl LockRedis {
+    /// Creates a manager whose locks are held in keys starting with
+    /// `key_prefix` (followed by `:`), or `spin-lock:` by default.
+    pub fn new(address: &str, key_prefix: Option<String>) -> Result<Self> {
+        let database_url = parse_redis_url(address).context("Invalid Redis URL")?;
+        Ok(Self {
+            database_url,
+            key_prefix: format!("{}:", key_prefix.as_deref().unwrap_or("spin-lock")),
+            resolver: Resolver::default(),
+            connection: OnceCell::new(),
+        })
+    }
+
+    /// Resolves the server's hostname with `resolver` rather than the
+    /// system resolver.
+    pub fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection, Error> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| async {
+                let info = self.database_url.clone().into_connection_info()?;
+                self.resolver
+                    .connect_redis(info, |client| async move {
+                        client.get_multiplexed_tokio_connection().await
+                    })
+                    .await
+            })
+            .await
+            .map_err(log_error)?;
+        Ok(connection.clone())
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{name}", self.key_prefix)
+    }
+}
+
+#[async_trait]
+impl LockManager for LockRedis {
+    async fn acquire(&self, name: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        let mut conn = self.connection().await?;
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(self.key(name))
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_millis(ttl))
+            .query_async(&mut conn)
+            .await
+            .map_err(log_error)?;
+        Ok(reply.is_some())
+    }
+
+    async fn renew(&self, name: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        let mut conn = self.connection().await?;
+        let renewed: i64 = Script::new(RENEW_SCRIPT)
+            .key(self.key(name))
+            .arg(token)
+            .arg(ttl_millis(ttl))
+            .invoke_async(&mut conn)
+            .await
+            .map_err(log_error)?;
+        Ok(renewed == 1)
+    }
+
+    async fn release(&self, name: &str, token: &str) -> Result<(), Error> {
+        let mut conn = self.connection().await?;
+        let _: i64 = Script::new(RELEASE_SCRIPT)
+            .key(self.key(name))
+            .arg(token)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(log_error)?;
+        Ok(())
+    }
+}
+
+fn ttl_millis(ttl: Duration) -> u64 {
+    u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,167 @@
+use crate::{log_error, Error, LockManager};
+use rusqlite::Connection;
+use spin_core::async_trait;
+use std::{
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::task;
+
+// Takes over the lock if it is free or its lease has expired
+const ACQUIRE_QUERY: &str = "INSERT INTO spin_lock (name, token, expires_at) VALUES ($1, $2, $3)
+                             ON CONFLICT(name) DO UPDATE SET token=$2, expires_at=$3
+                             WHERE expires_at <= $4";
+
+pub enum DatabaseLocation {
+    InMemory,
+    Path(PathBuf),
+}
+
+/// Locks held as rows of a SQLite database, which may be shared by the Spin
+/// processes on a single host.
+pub struct LockSqlite {
+    location: DatabaseLocation,
+    connection: OnceLock<Mutex<Connection>>,
+}
+
+impl LockSqlite {
+    pub fn new(location: DatabaseLocation) -> Self {
+        Self {
+            location,
+            connection: OnceLock::new(),
+        }
+    }
+
+    fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, Error> {
+        task::block_in_place(|| {
+            let connection = match self.connection.get() {
+                Some(connection) => connection,
+                None => {
+                    let connection = self.open().map_err(log_error)?;
+                    // Another thread may have won the race to open the database
+                    let _ = self.connection.set(Mutex::new(connection));
+                    self.connection.get().unwrap()
+                }
+            };
+            f(&connection.lock().unwrap()).map_err(log_error)
+        })
+    }
+
+    fn open(&self) -> rusqlite::Result<Connection> {
+        let connection = match &self.location {
+            DatabaseLocation::InMemory => Connection::open_in_memory(),
+            DatabaseLocation::Path(path) => Connection::open(path),
+        }?;
+        // Other processes may hold the database briefly while taking locks
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS spin_lock (
+               name       TEXT PRIMARY KEY,
+               token      TEXT NOT NULL,
+               expires_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        Ok(connection)
+    }
+}
+
+#[async_trait]
+impl LockManager for LockSqlite {
+    async fn acquire(&self, name: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        let now = now_millis();
+        let changed = self.with_connection(|connection| {
+            connection
+                .prepare_cached(ACQUIRE_QUERY)?
+                .execute(rusqlite::params![name, token, expires_at(now, ttl), now])
+        })?;
+        Ok(changed == 1)
+    }
+
+    async fn renew(&self, name: &str, token: &str, ttl: Duration) -> Result<bool, Error> {
+        let now = now_millis();
+        let changed = self.with_connection(|connection| {
+            connection
+                .prepare_cached(
+                    "UPDATE spin_lock SET expires_at=$3
+                     WHERE name=$1 AND token=$2 AND expires_at > $4",
+                )?
+                .execute(rusqlite::params![name, token, expires_at(now, ttl), now])
+        })?;
+        Ok(changed == 1)
+    }
+
+    async fn release(&self, name: &str, token: &str) -> Result<(), Error> {
+        self.with_connection(|connection| {
+            connection
+                .prepare_cached("DELETE FROM spin_lock WHERE name=$1 AND token=$2")?
+                .execute(rusqlite::params![name, token])
+        })?;
+        Ok(())
+    }
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+fn expires_at(now: i64, ttl: Duration) -> i64 {
+    now.saturating_add(i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn one_lease_at_a_time() -> anyhow::Result<()> {
+        let locks = LockSqlite::new(DatabaseLocation::InMemory);
+        let ttl = Duration::from_secs(60);
+
+        assert!(locks.acquire("app:leader", "first", ttl).await?);
+        assert!(!locks.acquire("app:leader", "second", ttl).await?);
+        assert!(locks.acquire("app:other", "second", ttl).await?);
+
+        assert!(locks.renew("app:leader", "first", ttl).await?);
+        assert!(!locks.renew("app:leader", "second", ttl).await?);
+
+        // Releasing with the wrong token leaves the lease held
+        locks.release("app:leader", "second").await?;
+        assert!(!locks.acquire("app:leader", "second", ttl).await?);
+
+        locks.release("app:leader", "first").await?;
+        assert!(locks.acquire("app:leader", "second", ttl).await?);
+        assert!(!locks.renew("app:leader", "first", ttl).await?);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn expired_leases_can_be_taken_over() -> anyhow::Result<()> {
+        let locks = LockSqlite::new(DatabaseLocation::InMemory);
+
+        assert!(
+            locks
+                .acquire("app:leader", "first", Duration::from_millis(1))
+                .await?
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(
+            !locks
+                .renew("app:leader", "first", Duration::from_secs(60))
+                .await?
+        );
+        assert!(
+            locks
+                .acquire("app:leader", "second", Duration::from_secs(60))
+                .await?
+        );
+        Ok(())
+    }
+}
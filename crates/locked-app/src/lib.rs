@@ -22,6 +22,8 @@ pub const APP_VERSION_KEY: MetadataKey = MetadataKey::new("version");
 pub const APP_DESCRIPTION_KEY: MetadataKey = MetadataKey::new("description");
 /// MetadataKey for extracting the OCI image digest.
 pub const OCI_IMAGE_DIGEST_KEY: MetadataKey = MetadataKey::new("oci_image_digest");
+/// MetadataKey for extracting the URL the application was loaded from.
+pub const APP_ORIGIN_KEY: MetadataKey = MetadataKey::new("origin");
 
 /// Type alias for a [`Result`]s with [`Error`].
 pub type Result<T> = std::result::Result<T, Error>;
@@ -44,6 +46,10 @@ pub enum Error {
     /// An error indicating failed JSON (de)serialization.
     #[error("json error: {0}")]
     JsonError(#[from] serde_json::Error),
+    /// An error indicating a lock file schema version this version of Spin
+    /// can't load.
+    #[error("unsupported lock file: {0}")]
+    LockVersionError(String),
     /// A validation error that can be presented directly to the user.
     #[error(transparent)]
     ValidationError(anyhow::Error),
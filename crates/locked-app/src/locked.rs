@@ -6,16 +6,26 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use spin_serde::FixedVersion;
 
-use crate::{metadata::MetadataExt, values::ValuesMap};
+use crate::{metadata::MetadataExt, values::ValuesMap, Error};
 
 /// A String-keyed map with deterministic serialization order.
 pub type LockedMap<T> = std::collections::BTreeMap<String, T>;
 
+/// The lock file schema version written by this version of Spin.
+pub const SPIN_LOCK_VERSION: usize = 0;
+
+const SPIN_LOCK_VERSION_KEY: &str = "spin_lock_version";
+
+/// Migrations from each older schema version to the next, indexed by the
+/// version migrated from. Changing the schema incompatibly means bumping
+/// [`SPIN_LOCK_VERSION`] and adding the migration from the previous version.
+const MIGRATIONS: [fn(&mut ValuesMap) -> crate::Result<()>; SPIN_LOCK_VERSION] = [];
+
 /// A LockedApp represents a "fully resolved" Spin application.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LockedApp {
     /// Locked schema version
-    pub spin_lock_version: FixedVersion<0>,
+    pub spin_lock_version: FixedVersion<SPIN_LOCK_VERSION>,
     /// Application metadata
     #[serde(default, skip_serializing_if = "ValuesMap::is_empty")]
     pub metadata: ValuesMap,
@@ -30,8 +40,23 @@ pub struct LockedApp {
 
 impl LockedApp {
     /// Deserializes a [`LockedApp`] from the given JSON data.
-    pub fn from_json(contents: &[u8]) -> serde_json::Result<Self> {
-        serde_json::from_slice(contents)
+    ///
+    /// Lock files of older schema versions are migrated to the current
+    /// version. Lock files of newer schema versions are rejected, as they may
+    /// depend on features this version of Spin doesn't have.
+    pub fn from_json(contents: &[u8]) -> crate::Result<Self> {
+        let mut locked: ValuesMap = serde_json::from_slice(contents)?;
+        let version = lock_version(&locked)?;
+        if version > SPIN_LOCK_VERSION {
+            return Err(Error::LockVersionError(format!(
+                "this application's lock file has schema version {version}, but this version of Spin only supports versions up to {SPIN_LOCK_VERSION}; upgrade Spin to run it"
+            )));
+        }
+        for from_version in version..SPIN_LOCK_VERSION {
+            MIGRATIONS[from_version](&mut locked)?;
+            locked.insert(SPIN_LOCK_VERSION_KEY.into(), (from_version + 1).into());
+        }
+        Ok(serde_json::from_value(locked.into())?)
     }
 
     /// Serializes the [`LockedApp`] into JSON data.
@@ -63,6 +88,22 @@ impl LockedApp {
     }
 }
 
+fn lock_version(locked: &ValuesMap) -> crate::Result<usize> {
+    let version = locked.get(SPIN_LOCK_VERSION_KEY).ok_or_else(|| {
+        Error::LockVersionError(format!(
+            "missing `{SPIN_LOCK_VERSION_KEY}`; this may not be a Spin lock file"
+        ))
+    })?;
+    version
+        .as_u64()
+        .and_then(|version| version.try_into().ok())
+        .ok_or_else(|| {
+            Error::LockVersionError(format!(
+                "`{SPIN_LOCK_VERSION_KEY}` must be a non-negative integer, got {version}"
+            ))
+        })
+}
+
 /// A LockedComponent represents a "fully resolved" Spin component.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LockedComponent {
@@ -84,6 +125,11 @@ pub struct LockedComponent {
     pub config: LockedMap<String>,
 }
 
+/// The [`LockedComponentSource::content_type`] of a component precompiled
+/// ahead of time for a specific target and engine configuration.
+pub const PRECOMPILED_COMPONENT_CONTENT_TYPE: &str =
+    "application/vnd.wasmtime.precompiled-component";
+
 /// A LockedComponentSource specifies a Wasm source.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LockedComponentSource {
@@ -92,6 +138,10 @@ pub struct LockedComponentSource {
     /// Wasm source content specification
     #[serde(flatten)]
     pub content: ContentRef,
+    /// Sources of components to compose with this one, keyed by the name of
+    /// the import each satisfies
+    #[serde(default, skip_serializing_if = "LockedMap::is_empty")]
+    pub dependencies: LockedMap<LockedComponentSource>,
 }
 
 /// A ContentPath specifies content mapped to a WASI path.
@@ -102,6 +152,10 @@ pub struct ContentPath {
     pub content: ContentRef,
     /// WASI mount path
     pub path: PathBuf,
+    /// Whether the guest may write to this mount. Writes are transient; they
+    /// may or may not be visible to other instances.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub writable: bool,
 }
 
 /// A ContentRef represents content used by an application.
@@ -150,3 +204,38 @@ pub struct Variable {
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub secret: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_file(version: Value) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "spin_lock_version": version,
+            "triggers": [],
+            "components": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_current_version() {
+        LockedApp::from_json(&lock_file(SPIN_LOCK_VERSION.into())).unwrap();
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let err = LockedApp::from_json(&lock_file((SPIN_LOCK_VERSION + 1).into())).unwrap_err();
+        assert!(matches!(err, Error::LockVersionError(_)), "{err:?}");
+        assert!(err.to_string().contains("upgrade Spin"), "{err}");
+    }
+
+    #[test]
+    fn rejects_invalid_version() {
+        let err = LockedApp::from_json(&lock_file("0".into())).unwrap_err();
+        assert!(matches!(err, Error::LockVersionError(_)), "{err:?}");
+
+        let err = LockedApp::from_json(br#"{"triggers": [], "components": []}"#).unwrap_err();
+        assert!(matches!(err, Error::LockVersionError(_)), "{err:?}");
+    }
+}
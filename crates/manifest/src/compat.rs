@@ -74,15 +74,23 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
             component_id.clone(),
             v2::Component {
                 source: component.source,
+                dependencies: Default::default(),
                 description: component.description,
                 variables,
                 environment: component.environment,
+                environment_allowlist: Vec::new(),
+                args: Vec::new(),
+                working_dir: None,
                 files: component.files,
                 exclude_files: component.exclude_files,
                 key_value_stores,
                 sqlite_databases,
+                read_only_sqlite_databases: Vec::new(),
+                blob_stores: Vec::new(),
                 ai_models,
                 build: component.build,
+                limits: None,
+                outbound_http: None,
                 tool: Default::default(),
                 allowed_outbound_hosts,
                 allowed_http_hosts: Vec::new(),
@@ -100,10 +108,13 @@ pub fn v1_to_v2_app(manifest: v1::AppManifestV1) -> Result<v2::AppManifest, Erro
     }
     Ok(v2::AppManifest {
         spin_manifest_version: Default::default(),
+        include: Default::default(),
         application,
         variables: app_variables,
         triggers,
+        trigger_defaults: Default::default(),
         components,
+        profiles: Default::default(),
     })
 }
 
@@ -0,0 +1,423 @@
+//! Manifest fragments: `include = ["services/*/spin.toml"]` merges the
+//! components, triggers and variables of other manifests into an app, so that
+//! each part of a monorepo can keep its components in its own directory.
+
+use std::path::{Component as PathComponent, Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use spin_serde::FixedVersion;
+
+use crate::{
+    schema::v2::{
+        AppManifest, Component, ComponentDependency, ComponentSource, ComponentSpec, KebabId, Map,
+        SnakeId, Trigger, Variable, WasiFilesMount,
+    },
+    Error,
+};
+
+/// A manifest named by `include`. Its `[application]`, if any, is ignored,
+/// so that an included manifest may also be run as an app of its own.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestFragment {
+    #[allow(dead_code)]
+    spin_manifest_version: FixedVersion<2>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    application: Option<toml::Table>,
+    #[serde(default)]
+    variables: Map<SnakeId, Variable>,
+    #[serde(rename = "trigger", default)]
+    triggers: Map<String, Vec<Trigger>>,
+    #[serde(rename = "component", default)]
+    components: Map<KebabId, Component>,
+}
+
+/// Merges the manifests matched by the manifest's `include` patterns, which
+/// are relative to `manifest_dir`, into the manifest.
+///
+/// Paths in included components are relative to the included manifest, and
+/// are rewritten to be relative to `manifest_dir`, through `..` if the
+/// included manifest is outside it. A component without a
+/// build `workdir` is built in the included manifest's directory. Included
+/// components may not have the same ID as another component, and included
+/// variables must be defined the same way wherever they are defined.
+pub fn resolve_includes(manifest: &mut AppManifest, manifest_dir: &Path) -> Result<(), Error> {
+    for pattern in std::mem::take(&mut manifest.include) {
+        let paths = include_paths(manifest_dir, &pattern)?;
+        if paths.is_empty() {
+            return Err(Error::ValidationError(anyhow!(
+                "`include` pattern {pattern:?} matches no manifests"
+            )));
+        }
+        for path in paths {
+            let fragment = read_fragment(&path)?;
+            let fragment_dir = path
+                .parent()
+                .and_then(|dir| relative_dir(dir, manifest_dir))
+                .ok_or_else(|| {
+                    Error::ValidationError(anyhow!(
+                        "can't include {}: its path can't be made relative to {}",
+                        path.display(),
+                        manifest_dir.display()
+                    ))
+                })?;
+            merge_fragment(manifest, fragment, &fragment_dir).map_err(|e| {
+                Error::ValidationError(anyhow!("can't include {}: {e}", path.display()))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+// The path of `dir` relative to `base`, going up with `..` where `dir` isn't
+// within `base`. Paths are compared as written, so this fails if only one of
+// them is absolute, or if `base` goes up past where they diverge.
+fn relative_dir(dir: &Path, base: &Path) -> Option<PathBuf> {
+    if dir.is_absolute() != base.is_absolute() {
+        return None;
+    }
+    let dir = dir.components().collect::<Vec<_>>();
+    let base = base.components().collect::<Vec<_>>();
+    let common = dir.iter().zip(&base).take_while(|(d, b)| d == b).count();
+    let mut relative = PathBuf::new();
+    for component in &base[common..] {
+        match component {
+            PathComponent::Normal(_) => relative.push(PathComponent::ParentDir),
+            PathComponent::CurDir => (),
+            _ => return None,
+        }
+    }
+    relative.extend(&dir[common..]);
+    Some(relative)
+}
+
+fn include_paths(manifest_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let full_pattern = manifest_dir.join(pattern);
+    let full_pattern = full_pattern.to_str().ok_or_else(|| {
+        Error::ValidationError(anyhow!("invalid (non-utf8) `include` pattern {pattern:?}"))
+    })?;
+    let paths = glob::glob(full_pattern).map_err(|e| {
+        Error::ValidationError(anyhow!("invalid `include` pattern {pattern:?}: {e}"))
+    })?;
+    let mut paths = paths
+        .map(|p| p.map_err(|e| Error::Io(e.into_error())))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Components keep a stable order whatever order the filesystem lists them in
+    paths.sort();
+    Ok(paths)
+}
+
+fn read_fragment(path: &Path) -> Result<ManifestFragment, Error> {
+    let text = std::fs::read_to_string(path)?;
+    toml::from_str(&text).map_err(|e| {
+        Error::ValidationError(anyhow!(
+            "included manifest {} is invalid: {e}",
+            path.display()
+        ))
+    })
+}
+
+fn merge_fragment(
+    manifest: &mut AppManifest,
+    fragment: ManifestFragment,
+    fragment_dir: &Path,
+) -> anyhow::Result<()> {
+    for (name, variable) in fragment.variables {
+        match manifest.variables.get(&name) {
+            Some(existing) if existing != &variable => {
+                anyhow::bail!("variable `{name}` is already defined differently");
+            }
+            Some(_) => (),
+            None => {
+                manifest.variables.insert(name, variable);
+            }
+        }
+    }
+
+    for (id, mut component) in fragment.components {
+        if manifest.components.contains_key(&id) {
+            anyhow::bail!("component `{id}` is already defined");
+        }
+        rebase_component(&mut component, fragment_dir)
+            .map_err(|e| anyhow!("component `{id}`: {e}"))?;
+        manifest.components.insert(id, component);
+    }
+
+    for (trigger_type, mut triggers) in fragment.triggers {
+        for trigger in &mut triggers {
+            let inline_specs = trigger
+                .component
+                .iter_mut()
+                .chain(trigger.components.values_mut().flat_map(|c| c.0.iter_mut()));
+            for spec in inline_specs {
+                if let ComponentSpec::Inline(component) = spec {
+                    rebase_component(component, fragment_dir)?;
+                }
+            }
+        }
+        manifest
+            .triggers
+            .entry(trigger_type)
+            .or_default()
+            .extend(triggers);
+    }
+    Ok(())
+}
+
+// Makes the component's paths relative to the including manifest rather than
+// the included one.
+fn rebase_component(component: &mut Component, dir: &Path) -> anyhow::Result<()> {
+    if dir.as_os_str().is_empty() {
+        return Ok(());
+    }
+    let rebase = |path: &str| dir.join(path).to_string_lossy().into_owned();
+
+    if let ComponentSource::Local(path) = &mut component.source {
+        *path = rebase(path);
+    }
+    for dependency in component.dependencies.values_mut() {
+        if let ComponentDependency::Local { path } = dependency {
+            *path = rebase(path);
+        }
+    }
+    for exclude in &mut component.exclude_files {
+        *exclude = rebase(exclude);
+    }
+    for mount in &mut component.files {
+        *mount = rebase_files_mount(mount, dir)?;
+    }
+    // Watch patterns are relative to the workdir, so stay as they are
+    if let Some(build) = &mut component.build {
+        build.workdir = Some(match &build.workdir {
+            Some(workdir) => rebase(workdir),
+            None => dir.to_string_lossy().into_owned(),
+        });
+    }
+    Ok(())
+}
+
+// A pattern mount is placed at the same relative path in the guest as it has
+// on the host, so becomes a placement to keep the guest path the same.
+fn rebase_files_mount(mount: &WasiFilesMount, dir: &Path) -> anyhow::Result<WasiFilesMount> {
+    let rebase = |path: &str| dir.join(path).to_string_lossy().into_owned();
+    match mount {
+        WasiFilesMount::Pattern(pattern) => {
+            // "static/**/*" mounts the whole directory, like "static"
+            let path = pattern.strip_suffix("/**/*").unwrap_or(pattern);
+            if path.contains(['*', '?', '[']) {
+                anyhow::bail!(
+                    "file pattern {pattern:?} can't be used in an included manifest: mount a directory or use {{ source = ..., destination = ... }}"
+                );
+            }
+            Ok(WasiFilesMount::Placement {
+                source: rebase(path),
+                destination: format!("/{}", path.trim_start_matches('/')),
+                writable: false,
+            })
+        }
+        WasiFilesMount::Placement {
+            source,
+            destination,
+            writable,
+        } => Ok(WasiFilesMount::Placement {
+            source: rebase(source),
+            destination: destination.clone(),
+            writable: *writable,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, text: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn merges_included_manifests_with_rebased_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            &root.join("services/cart/spin.toml"),
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "cart"
+            [variables]
+            region = { default = "eu" }
+            [[trigger.http]]
+            route = "/cart/..."
+            component = "cart"
+            [component.cart]
+            source = "target/cart.wasm"
+            files = ["static/**/*", { source = "config", destination = "/etc" }]
+            build = { command = "cargo build", watch = ["src/**/*.rs"] }
+            "#,
+        );
+        let mut manifest: AppManifest = toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            include = ["services/*/spin.toml"]
+            [application]
+            name = "shop"
+            [variables]
+            region = { default = "eu" }
+            [[trigger.http]]
+            route = "/..."
+            component = "home"
+            [component.home]
+            source = "home.wasm"
+            "#,
+        )
+        .unwrap();
+
+        resolve_includes(&mut manifest, root).unwrap();
+
+        assert!(manifest.include.is_empty());
+        assert_eq!(manifest.triggers["http"].len(), 2);
+        let cart = &manifest.components[&KebabId::try_from("cart".to_owned()).unwrap()];
+        let ComponentSource::Local(source) = &cart.source else {
+            panic!("source should be local");
+        };
+        assert_eq!(
+            Path::new(source),
+            Path::new("services/cart/target/cart.wasm")
+        );
+        let sources = cart
+            .files
+            .iter()
+            .map(|mount| match mount {
+                WasiFilesMount::Placement {
+                    source,
+                    destination,
+                    ..
+                } => (PathBuf::from(source), destination.as_str()),
+                WasiFilesMount::Pattern(p) => panic!("pattern {p:?} should be a placement"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            [
+                (PathBuf::from("services/cart/static"), "/static"),
+                (PathBuf::from("services/cart/config"), "/etc"),
+            ]
+        );
+        let build = cart.build.as_ref().unwrap();
+        assert_eq!(
+            Path::new(build.workdir.as_deref().unwrap()),
+            Path::new("services/cart")
+        );
+        assert_eq!(build.watch, ["src/**/*.rs"]);
+    }
+
+    #[test]
+    fn rebases_includes_outside_the_manifest_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("app");
+        write(
+            &dir.path().join("shared/spin.toml"),
+            r#"
+            spin_manifest_version = 2
+            [component.auth]
+            source = "auth.wasm"
+            "#,
+        );
+        let shared = dir.path().join("shared/spin.toml");
+        let mut manifest: AppManifest = toml::from_str(&format!(
+            r#"
+            spin_manifest_version = 2
+            include = [{:?}]
+            [application]
+            name = "app"
+            "#,
+            shared.to_str().unwrap()
+        ))
+        .unwrap();
+
+        resolve_includes(&mut manifest, &root).unwrap();
+
+        let auth = &manifest.components[&KebabId::try_from("auth".to_owned()).unwrap()];
+        let ComponentSource::Local(source) = &auth.source else {
+            panic!("source should be local");
+        };
+        assert_eq!(Path::new(source), Path::new("../shared/auth.wasm"));
+    }
+
+    #[test]
+    fn relative_dirs() {
+        assert_eq!(
+            relative_dir(Path::new("/app/services/cart"), Path::new("/app")).unwrap(),
+            Path::new("services/cart")
+        );
+        assert_eq!(
+            relative_dir(Path::new("/shared"), Path::new("/app/web")).unwrap(),
+            Path::new("../../shared")
+        );
+        assert_eq!(
+            relative_dir(Path::new("services"), Path::new("")).unwrap(),
+            Path::new("services")
+        );
+        assert!(relative_dir(Path::new("/shared"), Path::new("app")).is_none());
+        assert!(relative_dir(Path::new("/shared"), Path::new("/app/../web")).is_none());
+    }
+
+    #[test]
+    fn rejects_conflicting_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            &root.join("a/spin.toml"),
+            r#"
+            spin_manifest_version = 2
+            [component.shared]
+            source = "a.wasm"
+            "#,
+        );
+        write(
+            &root.join("b/spin.toml"),
+            r#"
+            spin_manifest_version = 2
+            [component.shared]
+            source = "b.wasm"
+            "#,
+        );
+        write(
+            &root.join("c/spin.toml"),
+            r#"
+            spin_manifest_version = 2
+            [variables]
+            region = { default = "us" }
+            "#,
+        );
+        let manifest = |include: &[&str]| -> AppManifest {
+            toml::from_str(&format!(
+                r#"
+                spin_manifest_version = 2
+                include = {include:?}
+                [application]
+                name = "app"
+                [variables]
+                region = {{ default = "eu" }}
+                "#
+            ))
+            .unwrap()
+        };
+
+        let err =
+            resolve_includes(&mut manifest(&["a/spin.toml", "b/spin.toml"]), root).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("component `shared` is already defined"),
+            "{err}"
+        );
+        let err = resolve_includes(&mut manifest(&["c/spin.toml"]), root).unwrap_err();
+        assert!(err.to_string().contains("variable `region`"), "{err}");
+        let err = resolve_includes(&mut manifest(&["d/*.toml"]), root).unwrap_err();
+        assert!(err.to_string().contains("matches no manifests"), "{err}");
+    }
+}
@@ -4,7 +4,9 @@
 
 pub mod compat;
 pub mod error;
+pub mod include;
 pub mod normalize;
+pub mod profile;
 pub mod schema;
 
 use std::path::Path;
@@ -13,13 +15,19 @@ use schema::v2::AppManifest;
 
 pub use error::Error;
 
-/// Parses a V1 or V2 app manifest file into a [`AppManifest`].
+/// Parses a V1 or V2 app manifest file into a [`AppManifest`], merging in
+/// the manifests it includes.
 pub fn manifest_from_file(path: impl AsRef<Path>) -> Result<AppManifest, Error> {
+    let path = path.as_ref();
     let manifest_str = std::fs::read_to_string(path)?;
-    manifest_from_str(&manifest_str)
+    let mut manifest = manifest_from_str(&manifest_str)?;
+    let manifest_dir = path.parent().unwrap_or(Path::new(""));
+    include::resolve_includes(&mut manifest, manifest_dir)?;
+    Ok(manifest)
 }
 
-/// Parses a V1 or V2 app manifest into a [`AppManifest`].
+/// Parses a V1 or V2 app manifest into a [`AppManifest`]. The manifests it
+/// includes, if any, are not merged in.
 pub fn manifest_from_str(v1_or_v2_toml: &str) -> Result<AppManifest, Error> {
     // TODO: would it be faster to parse into a toml::Table rather than parse twice?
     match ManifestVersion::detect(v1_or_v2_toml)? {
@@ -5,14 +5,35 @@ use std::collections::HashSet;
 use crate::schema::v2::{AppManifest, ComponentSpec, KebabId};
 
 /// Normalizes some optional [`AppManifest`] features into a canonical form:
+/// - Trigger defaults are copied into each trigger of their type which
+///   doesn't override them, and removed.
 /// - Inline components in trigger configs are moved into top-level
 ///   components and replaced with a reference.
 /// - Any triggers without an ID are assigned a generated ID.
 pub fn normalize_manifest(manifest: &mut AppManifest) {
+    normalize_trigger_defaults(manifest);
     normalize_trigger_ids(manifest);
     normalize_inline_components(manifest);
 }
 
+fn normalize_trigger_defaults(manifest: &mut AppManifest) {
+    for (trigger_type, defaults) in std::mem::take(&mut manifest.trigger_defaults) {
+        let Some(triggers) = manifest.triggers.get_mut(&trigger_type) else {
+            continue;
+        };
+        for trigger in triggers {
+            // A trigger's own value replaces the default entirely, rather than
+            // being merged with it, so that e.g. overriding `executor` never
+            // inherits options of a different executor type
+            for (key, value) in &defaults {
+                if !trigger.config.contains_key(key) {
+                    trigger.config.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
 fn normalize_inline_components(manifest: &mut AppManifest) {
     // Normalize inline components
     let components = &mut manifest.components;
@@ -0,0 +1,103 @@
+//! Environment overlays: `[profile.<name>]` manifest sections which override
+//! variables, allowed hosts, and build commands for one environment.
+
+use anyhow::anyhow;
+
+use crate::{schema::v2::AppManifest, Error};
+
+/// Applies the named `[profile.<name>]` overlay to the manifest, returning
+/// an error if it has no such profile or the profile refers to components
+/// which don't exist.
+///
+/// Profile variable definitions replace those of the same name, and profile
+/// component settings replace the component's own.
+pub fn apply_profile(manifest: &mut AppManifest, name: &str) -> Result<(), Error> {
+    let Some(profile) = manifest.profiles.get(name).cloned() else {
+        let mut names = manifest
+            .profiles
+            .keys()
+            .map(|n| n.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        let available = if names.is_empty() {
+            "the manifest defines no profiles".to_owned()
+        } else {
+            format!("available profiles are {}", names.join(", "))
+        };
+        return Err(Error::ValidationError(anyhow!(
+            "no profile named `{name}`: {available}"
+        )));
+    };
+
+    manifest.variables.extend(profile.variables);
+
+    for (id, component_profile) in profile.components {
+        let component = manifest.components.get_mut(&id).ok_or_else(|| {
+            Error::ValidationError(anyhow!(
+                "profile `{name}` sets component `{id}`, which does not exist"
+            ))
+        })?;
+        if let Some(hosts) = component_profile.allowed_outbound_hosts {
+            component.allowed_outbound_hosts = hosts;
+            // Legacy hosts would be allowed alongside the profile's
+            component.allowed_http_hosts.clear();
+        }
+        if let Some(build) = component_profile.build {
+            component.build = Some(build);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::v2::{KebabId, SnakeId};
+
+    use super::*;
+
+    fn manifest() -> AppManifest {
+        toml::from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "profiles"
+            [variables]
+            api_url = { default = "http://localhost:8080" }
+            [[trigger.http]]
+            route = "/..."
+            component = "web"
+            [component.web]
+            source = "web.wasm"
+            allowed_outbound_hosts = ["http://localhost:8080"]
+            build = { command = "cargo build" }
+            [profile.prod.variables]
+            api_url = { default = "https://api.example.com" }
+            [profile.prod.component.web]
+            allowed_outbound_hosts = ["https://api.example.com"]
+            build = { command = "cargo build --release" }
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn profile_overrides_variables_hosts_and_build() {
+        let mut manifest = manifest();
+        apply_profile(&mut manifest, "prod").unwrap();
+
+        let api_url = &manifest.variables[&SnakeId::try_from("api_url".to_owned()).unwrap()];
+        assert_eq!(api_url.default.as_deref(), Some("https://api.example.com"));
+        let web = &manifest.components[&KebabId::try_from("web".to_owned()).unwrap()];
+        assert_eq!(web.allowed_outbound_hosts, ["https://api.example.com"]);
+        assert_eq!(web.build.as_ref().unwrap().command, "cargo build --release");
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let err = apply_profile(&mut manifest(), "staging").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no profile named `staging`: available profiles are prod"
+        );
+    }
+}
@@ -3,7 +3,7 @@ use std::fmt::Display;
 use serde::{Deserialize, Serialize};
 
 /// Variable definition
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Variable {
     /// `required = true`
@@ -53,6 +53,9 @@ pub enum WasiFilesMount {
         source: String,
         /// `destination = "/"`
         destination: String,
+        /// `writable = true`
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        writable: bool,
     },
 }
 
@@ -68,6 +71,9 @@ pub struct ComponentBuildConfig {
     /// watch = ["src/**/*.rs"]
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub watch: Vec<String>,
+    /// `depends_on = ["shared-lib"]`: components to build before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
 }
 
 fn is_false(v: &bool) -> bool {
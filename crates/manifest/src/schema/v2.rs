@@ -6,12 +6,22 @@ pub use super::common::{ComponentBuildConfig, ComponentSource, Variable, WasiFil
 
 pub(crate) type Map<K, V> = indexmap::IndexMap<K, V>;
 
+/// A JSON schema of the manifest, for editor integration. It describes the
+/// same format as [`AppManifest`], but is only a guide: validate manifests by
+/// deserializing them.
+pub const JSON_SCHEMA: &str = include_str!("v2.schema.json");
+
 /// App manifest
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct AppManifest {
     /// `spin_manifest_version = 2`
     pub spin_manifest_version: FixedVersion<2>,
+    /// `include = ["services/*/spin.toml"]`: manifests whose components,
+    /// triggers and variables are merged into this one, by
+    /// [`resolve_includes`](crate::include::resolve_includes)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
     /// `[application]`
     pub application: AppDetails,
     /// `[variables]`
@@ -20,10 +30,66 @@ pub struct AppManifest {
     /// `[[trigger.<type>]]`
     #[serde(rename = "trigger")]
     pub triggers: Map<String, Vec<Trigger>>,
+    /// `[trigger_defaults.<type>]`: trigger config inherited by each
+    /// `[[trigger.<type>]]` which doesn't set it
+    #[serde(
+        default,
+        skip_serializing_if = "Map::is_empty",
+        deserialize_with = "deserialize_trigger_defaults"
+    )]
+    pub trigger_defaults: Map<String, toml::Table>,
     /// `[component.<id>]`
     #[serde(rename = "component")]
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub components: Map<KebabId, Component>,
+    /// `[profile.<name>]`
+    #[serde(rename = "profile")]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub profiles: Map<String, Profile>,
+}
+
+// Trigger IDs and components identify each trigger, so can't be inherited.
+fn deserialize_trigger_defaults<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Map<String, toml::Table>, D::Error> {
+    let defaults = Map::<String, toml::Table>::deserialize(deserializer)?;
+    for (trigger_type, config) in &defaults {
+        for key in ["id", "component", "components"] {
+            if config.contains_key(key) {
+                return Err(serde::de::Error::custom(format!(
+                    "`trigger_defaults.{trigger_type}` can't set `{key}`, which is specific to each trigger"
+                )));
+            }
+        }
+    }
+    Ok(defaults)
+}
+
+/// An environment overlay, applied with
+/// [`apply_profile`](crate::profile::apply_profile)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// `[profile.<name>.variables]`: variable definitions replacing or
+    /// adding to `[variables]`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub variables: Map<SnakeId, Variable>,
+    /// `[profile.<name>.component.<id>]`
+    #[serde(rename = "component")]
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub components: Map<KebabId, ComponentProfile>,
+}
+
+/// Component settings replaced by a [`Profile`]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentProfile {
+    /// `allowed_outbound_hosts = ["https://api.example.com"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_outbound_hosts: Option<Vec<String>>,
+    /// `[profile.<name>.component.<id>.build]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<ComponentBuildConfig>,
 }
 
 /// App details
@@ -101,6 +167,9 @@ impl TryFrom<toml::Value> for ComponentSpec {
 pub struct Component {
     /// `source = ...`
     pub source: ComponentSource,
+    /// `dependencies = { "example:calc/adder" = { path = "adder.wasm" } }`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub dependencies: Map<String, ComponentDependency>,
     /// `description = "Component description"`
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub description: String,
@@ -110,6 +179,20 @@ pub struct Component {
     /// `environment = { VAR = "value" }`
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub environment: Map<String, String>,
+    /// `environment_allowlist = ["AWS_REGION", "OTEL_*"]`: host environment
+    /// variables passed through to the component. A trailing `*` matches a
+    /// prefix, but a bare `*` is rejected. Values in `environment` take
+    /// precedence.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environment_allowlist: Vec<String>,
+    /// `args = ["--verbose"]`: command-line arguments passed to the component
+    /// after its name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// `working_dir = "/data"`: the component's initial working directory,
+    /// given to it as `PWD`. The component must change to it itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
     /// `files = [...]`
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub files: Vec<WasiFilesMount>,
@@ -128,18 +211,35 @@ pub struct Component {
     /// `sqlite_databases = ["default"]`
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub sqlite_databases: Vec<SnakeId>,
+    /// `read_only_sqlite_databases = ["reports"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read_only_sqlite_databases: Vec<SnakeId>,
+    /// `blob_stores = ["default"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blob_stores: Vec<SnakeId>,
     /// `ai_models = ["llama2-chat"]`
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub ai_models: Vec<KebabId>,
     /// Build configuration
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub build: Option<ComponentBuildConfig>,
+    /// Resource limits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ComponentLimits>,
+    /// Outbound HTTP request policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbound_http: Option<OutboundHttpPolicy>,
     /// Settings for custom tools or plugins. Spin ignores this field.
     #[serde(default, skip_serializing_if = "Map::is_empty")]
     pub tool: Map<String, toml::Table>,
 }
 
 impl Component {
+    /// The hosts in the deprecated `allowed_http_hosts` field.
+    pub fn deprecated_allowed_http_hosts(&self) -> &[String] {
+        &self.allowed_http_hosts
+    }
+
     /// Combine `allowed_outbound_hosts` with the deprecated `allowed_http_hosts` into
     /// one array all normalized to the syntax of `allowed_outbound_hosts`.
     pub fn normalized_allowed_outbound_hosts(&self) -> anyhow::Result<Vec<String>> {
@@ -161,6 +261,91 @@ impl Component {
     }
 }
 
+/// A component which satisfies an import of another component. The
+/// dependency is composed with the importing component when it is loaded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, untagged)]
+pub enum ComponentDependency {
+    /// `{ path = "adder.wasm" }`
+    Local {
+        /// `path = "adder.wasm"`
+        path: String,
+    },
+    /// `{ url = "https://example.test/adder.wasm", digest = "sha256:abc123..." }`
+    Remote {
+        /// `url = "https://example.test/adder.wasm"`
+        url: String,
+        /// `digest = "sha256:abc123..."`
+        digest: String,
+    },
+    /// `{ reference = "ghcr.io/example/adder:1.0.0" }`
+    Registry {
+        /// `reference = "ghcr.io/example/adder:1.0.0"`
+        reference: String,
+    },
+}
+
+impl std::fmt::Display for ComponentDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local { path } => write!(f, "{path:?}"),
+            Self::Remote { url, digest } => write!(f, "{url:?} with digest {digest:?}"),
+            Self::Registry { reference } => write!(f, "registry reference {reference:?}"),
+        }
+    }
+}
+
+/// Component resource limits
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentLimits {
+    /// `max_memory_size = 67108864` (bytes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_size: Option<usize>,
+    /// `max_table_elements = 10000`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_table_elements: Option<u32>,
+    /// `max_instances = 10`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_instances: Option<usize>,
+    /// `max_tables = 10`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tables: Option<usize>,
+    /// `max_execution_time_ms = 30000`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_execution_time_ms: Option<u64>,
+}
+
+/// Component outbound HTTP request policy
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutboundHttpPolicy {
+    /// `timeout_ms = 10000`, covering the whole request including the response body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// `retries = 2`; only requests with idempotent methods are retried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// `retry_backoff_ms = 100`, doubling after each retry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_ms: Option<u64>,
+    /// `circuit_breaker = { failure_threshold = 5, reset_timeout_ms = 30000 }`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+/// Outbound HTTP circuit breaker configuration
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// `failure_threshold = 5`: consecutive failures to a host before requests
+    /// to it fail immediately
+    pub failure_threshold: u32,
+    /// `reset_timeout_ms = 30000`: how long requests fail immediately before
+    /// the host is tried again
+    pub reset_timeout_ms: u64,
+}
+
 mod one_or_many {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -230,6 +415,48 @@ mod tests {
         FakeTriggerConfig::deserialize(manifest.triggers["fake"][0].config.clone()).unwrap();
     }
 
+    fn assert_schema_covers(properties: &serde_json::Value, value: &serde_json::Value) {
+        for key in value.as_object().unwrap().keys() {
+            assert!(properties.get(key).is_some(), "schema lacks `{key}`");
+        }
+    }
+
+    #[test]
+    fn json_schema_covers_maximal_manifest() {
+        let schema: serde_json::Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+        let manifest: AppManifest =
+            toml::from_str(include_str!("../../tests/ui/maximal.toml")).unwrap();
+        let manifest = serde_json::to_value(manifest).unwrap();
+        let definitions = &schema["definitions"];
+
+        assert_schema_covers(&schema["properties"], &manifest);
+        assert_schema_covers(
+            &definitions["application"]["properties"],
+            &manifest["application"],
+        );
+        for component in manifest["component"].as_object().unwrap().values() {
+            assert_schema_covers(&definitions["component"]["properties"], component);
+        }
+    }
+
+    #[test]
+    fn trigger_defaults_cannot_set_components() {
+        let err = AppManifest::deserialize(toml! {
+            spin_manifest_version = 2
+            [application]
+            name = "trigger-defaults"
+            [trigger_defaults.fake]
+            component = "shared"
+            [[trigger.fake]]
+            component = { source = "inline.wasm" }
+        })
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("can't set `component`"),
+            "unexpected error: {err}"
+        );
+    }
+
     #[derive(Deserialize)]
     #[allow(dead_code)]
     struct FakeGlobalToolConfig {
@@ -0,0 +1,357 @@
+//! Per-component metrics for Spin trigger hosts, rendered in the Prometheus
+//! text exposition format.
+//!
+//! Metrics are opt-in: until [`enable`] is called, the `record_*` functions
+//! do nothing, so host components can call them unconditionally.
+
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration};
+
+use once_cell::sync::OnceCell;
+
+static REGISTRY: OnceCell<Registry> = OnceCell::new();
+
+// Upper bounds in seconds of the invocation duration histogram buckets
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Kinds of outbound call, used as the `kind` label of
+/// `spin_component_outbound_calls_total`.
+pub mod outbound {
+    /// An outbound HTTP request, via `fermyon:spin/http` or `wasi:http`
+    pub const HTTP: &str = "http";
+    /// An outbound Redis command
+    pub const REDIS: &str = "redis";
+    /// An outbound MQTT message
+    pub const MQTT: &str = "mqtt";
+    /// An outbound PostgreSQL statement
+    pub const POSTGRES: &str = "postgres";
+    /// An outbound MySQL statement
+    pub const MYSQL: &str = "mysql";
+}
+
+/// Enables metrics recording for the rest of the process's lifetime.
+pub fn enable() {
+    REGISTRY.get_or_init(Registry::default);
+}
+
+/// Returns whether [`enable`] has been called.
+pub fn is_enabled() -> bool {
+    REGISTRY.get().is_some()
+}
+
+/// Resource usage of a single component invocation.
+#[derive(Debug, Default)]
+pub struct Invocation {
+    /// How long the invocation took
+    pub duration: Duration,
+    /// The fuel consumed by the invocation, if fuel metering is enabled
+    pub fuel_consumed: Option<u64>,
+    /// The peak linear memory in bytes allocated by the invocation
+    pub memory_consumed: u64,
+}
+
+/// Records an invocation of the component `component_id`.
+pub fn record_invocation(component_id: &str, invocation: &Invocation) {
+    if let Some(registry) = REGISTRY.get() {
+        registry.record_invocation(component_id, invocation);
+    }
+}
+
+/// Records an outbound call of the given `kind` (see [`outbound`]) made by
+/// the component `component_id`.
+pub fn record_outbound_call(component_id: &str, kind: &'static str) {
+    if let Some(registry) = REGISTRY.get() {
+        registry.record_outbound_call(component_id, kind);
+    }
+}
+
+/// Records an invocation of the component `component_id` which was
+/// interrupted for exceeding its execution timeout.
+pub fn record_timeout(component_id: &str) {
+    if let Some(registry) = REGISTRY.get() {
+        registry.record_timeout(component_id);
+    }
+}
+
+/// Renders all recorded metrics in the Prometheus text exposition format.
+/// Returns an empty string if metrics are not enabled.
+pub fn render() -> String {
+    REGISTRY.get().map(Registry::render).unwrap_or_default()
+}
+
+#[derive(Default)]
+struct Registry {
+    components: Mutex<BTreeMap<String, ComponentMetrics>>,
+}
+
+#[derive(Default)]
+struct ComponentMetrics {
+    invocations: u64,
+    // Cumulative counts for each of `DURATION_BUCKETS`
+    duration_buckets: [u64; DURATION_BUCKETS.len()],
+    duration_sum: f64,
+    fuel_consumed: Option<u64>,
+    memory_high_water: u64,
+    outbound_calls: BTreeMap<&'static str, u64>,
+    timeouts: u64,
+}
+
+impl Registry {
+    fn record_invocation(&self, component_id: &str, invocation: &Invocation) {
+        self.with_component(component_id, |metrics| {
+            let seconds = invocation.duration.as_secs_f64();
+            metrics.invocations += 1;
+            metrics.duration_sum += seconds;
+            for (count, bound) in metrics.duration_buckets.iter_mut().zip(DURATION_BUCKETS) {
+                if seconds <= *bound {
+                    *count += 1;
+                }
+            }
+            if let Some(fuel) = invocation.fuel_consumed {
+                *metrics.fuel_consumed.get_or_insert(0) += fuel;
+            }
+            metrics.memory_high_water = metrics.memory_high_water.max(invocation.memory_consumed);
+        });
+    }
+
+    fn record_outbound_call(&self, component_id: &str, kind: &'static str) {
+        self.with_component(component_id, |metrics| {
+            *metrics.outbound_calls.entry(kind).or_default() += 1;
+        });
+    }
+
+    fn record_timeout(&self, component_id: &str) {
+        self.with_component(component_id, |metrics| {
+            metrics.timeouts += 1;
+        });
+    }
+
+    fn with_component(&self, component_id: &str, f: impl FnOnce(&mut ComponentMetrics)) {
+        let mut components = self.components.lock().unwrap();
+        match components.get_mut(component_id) {
+            Some(metrics) => f(metrics),
+            None => f(components.entry(component_id.to_owned()).or_default()),
+        }
+    }
+
+    fn render(&self) -> String {
+        let components = self.components.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "spin_component_invocations_total",
+            "counter",
+            "Number of component invocations.",
+        );
+        for (id, metrics) in components.iter() {
+            let labels = format_labels(&[("component", id.as_str())]);
+            sample(
+                &mut out,
+                "spin_component_invocations_total",
+                &labels,
+                metrics.invocations,
+            );
+        }
+
+        header(
+            &mut out,
+            "spin_component_invocation_duration_seconds",
+            "histogram",
+            "Duration of component invocations in seconds.",
+        );
+        for (id, metrics) in components.iter() {
+            let name = "spin_component_invocation_duration_seconds";
+            for (count, bound) in metrics.duration_buckets.iter().zip(DURATION_BUCKETS) {
+                let labels = format_labels(&[
+                    ("component", id.as_str()),
+                    ("le", bound.to_string().as_str()),
+                ]);
+                sample(&mut out, &format!("{name}_bucket"), &labels, count);
+            }
+            let labels = format_labels(&[("component", id.as_str()), ("le", "+Inf")]);
+            sample(
+                &mut out,
+                &format!("{name}_bucket"),
+                &labels,
+                metrics.invocations,
+            );
+            let labels = format_labels(&[("component", id.as_str())]);
+            sample(
+                &mut out,
+                &format!("{name}_sum"),
+                &labels,
+                metrics.duration_sum,
+            );
+            sample(
+                &mut out,
+                &format!("{name}_count"),
+                &labels,
+                metrics.invocations,
+            );
+        }
+
+        header(
+            &mut out,
+            "spin_component_fuel_consumed_total",
+            "counter",
+            "Wasm fuel consumed by component invocations.",
+        );
+        for (id, metrics) in components.iter() {
+            if let Some(fuel) = metrics.fuel_consumed {
+                let labels = format_labels(&[("component", id.as_str())]);
+                sample(
+                    &mut out,
+                    "spin_component_fuel_consumed_total",
+                    &labels,
+                    fuel,
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "spin_component_memory_high_water_bytes",
+            "gauge",
+            "Largest linear memory allocation of any single component invocation.",
+        );
+        for (id, metrics) in components.iter() {
+            let labels = format_labels(&[("component", id.as_str())]);
+            sample(
+                &mut out,
+                "spin_component_memory_high_water_bytes",
+                &labels,
+                metrics.memory_high_water,
+            );
+        }
+
+        header(
+            &mut out,
+            "spin_component_outbound_calls_total",
+            "counter",
+            "Number of outbound calls made by components.",
+        );
+        for (id, metrics) in components.iter() {
+            for (kind, count) in &metrics.outbound_calls {
+                let labels = format_labels(&[("component", id.as_str()), ("kind", *kind)]);
+                sample(
+                    &mut out,
+                    "spin_component_outbound_calls_total",
+                    &labels,
+                    count,
+                );
+            }
+        }
+
+        header(
+            &mut out,
+            "spin_component_timeouts_total",
+            "counter",
+            "Number of component invocations interrupted for exceeding their execution timeout.",
+        );
+        for (id, metrics) in components.iter() {
+            let labels = format_labels(&[("component", id.as_str())]);
+            sample(
+                &mut out,
+                "spin_component_timeouts_total",
+                &labels,
+                metrics.timeouts,
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    _ = writeln!(out, "# HELP {name} {help}");
+    _ = writeln!(out, "# TYPE {name} {metric_type}");
+}
+
+fn sample(out: &mut String, name: &str, labels: &str, value: impl std::fmt::Display) {
+    _ = writeln!(out, "{name}{{{labels}}} {value}");
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', r"\\")
+                .replace('"', r#"\""#)
+                .replace('\n', r"\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_prometheus_text() {
+        let registry = Registry::default();
+        registry.record_invocation(
+            "hello",
+            &Invocation {
+                duration: Duration::from_millis(20),
+                fuel_consumed: Some(1000),
+                memory_consumed: 1 << 20,
+            },
+        );
+        registry.record_invocation(
+            "hello",
+            &Invocation {
+                duration: Duration::from_secs(3),
+                fuel_consumed: Some(500),
+                memory_consumed: 1 << 16,
+            },
+        );
+        registry.record_outbound_call("hello", outbound::HTTP);
+        registry.record_outbound_call("hello", outbound::HTTP);
+        registry.record_outbound_call("hello", outbound::REDIS);
+        registry.record_timeout("hello");
+
+        let text = registry.render();
+        let lines = text.lines().collect::<Vec<_>>();
+        for expected in [
+            "# TYPE spin_component_invocations_total counter",
+            r#"spin_component_invocations_total{component="hello"} 2"#,
+            r#"spin_component_invocation_duration_seconds_bucket{component="hello",le="0.01"} 0"#,
+            r#"spin_component_invocation_duration_seconds_bucket{component="hello",le="0.025"} 1"#,
+            r#"spin_component_invocation_duration_seconds_bucket{component="hello",le="5"} 2"#,
+            r#"spin_component_invocation_duration_seconds_bucket{component="hello",le="+Inf"} 2"#,
+            r#"spin_component_invocation_duration_seconds_sum{component="hello"} 3.02"#,
+            r#"spin_component_invocation_duration_seconds_count{component="hello"} 2"#,
+            r#"spin_component_fuel_consumed_total{component="hello"} 1500"#,
+            r#"spin_component_memory_high_water_bytes{component="hello"} 1048576"#,
+            r#"spin_component_outbound_calls_total{component="hello",kind="http"} 2"#,
+            r#"spin_component_outbound_calls_total{component="hello",kind="redis"} 1"#,
+            r#"spin_component_timeouts_total{component="hello"} 1"#,
+        ] {
+            assert!(
+                lines.contains(&expected),
+                "missing {expected:?} in:\n{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn fuel_omitted_without_metering() {
+        let registry = Registry::default();
+        registry.record_invocation("hello", &Invocation::default());
+        let text = registry.render();
+        assert!(!text.contains("spin_component_fuel_consumed_total{"));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(
+            format_labels(&[("component", "a\"b\\c")]),
+            r#"component="a\"b\\c""#
+        );
+    }
+}
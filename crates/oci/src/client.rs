@@ -1,14 +1,20 @@
 //! Spin's client for distributing applications via OCI registries
 
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use docker_credential::DockerCredential;
 use futures_util::future;
 use futures_util::stream::{self, StreamExt, TryStreamExt};
 use oci_distribution::{
-    client::ImageLayer, config::ConfigFile, manifest::OciImageManifest, secrets::RegistryAuth,
-    token_cache::RegistryTokenType, Reference, RegistryOperation,
+    client::ImageLayer,
+    config::ConfigFile,
+    manifest::{ImageIndexEntry, OciImageIndex, OciImageManifest, OciManifest, Platform},
+    secrets::RegistryAuth,
+    token_cache::RegistryTokenType,
+    Reference, RegistryOperation,
 };
 use reqwest::Url;
 use spin_common::sha256;
@@ -29,9 +35,25 @@ pub const SPIN_APPLICATION_MEDIA_TYPE: &str = "application/vnd.fermyon.spin.appl
 pub const DATA_MEDIATYPE: &str = "application/vnd.wasm.content.layer.v1+data";
 /// Media type for a layer representing a compressed archive of one or more files used by a Spin application
 pub const ARCHIVE_MEDIATYPE: &str = "application/vnd.wasm.content.bundle.v1.tar+gzip";
+/// Media type for a layer representing a platform-specific artifact published alongside a Spin application
+pub const PLATFORM_ARTIFACT_MEDIA_TYPE: &str = "application/vnd.fermyon.spin.platform-artifact.v1";
 // Note: this will be updated with a canonical value once defined upstream
 const WASM_LAYER_MEDIA_TYPE: &str = "application/vnd.wasm.content.layer.v1+wasm";
 
+const OCI_IMAGE_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const OCI_IMAGE_INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+const OCI_IMAGE_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+
+// The platform of Spin applications within an image index
+const WASM_OS: &str = "wasip1";
+const WASM_ARCHITECTURE: &str = "wasm";
+
+// Well-known annotation names, from the OCI image spec
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+const VERSION_ANNOTATION: &str = "org.opencontainers.image.version";
+const DESCRIPTION_ANNOTATION: &str = "org.opencontainers.image.description";
+const AUTHORS_ANNOTATION: &str = "org.opencontainers.image.authors";
+
 const CONFIG_FILE: &str = "config.json";
 const LATEST_TAG: &str = "latest";
 const MANIFEST_FILE: &str = "manifest.json";
@@ -45,6 +67,53 @@ const MAX_LAYER_COUNT: usize = 500;
 // Inline content into ContentRef iff < this size.
 const CONTENT_REF_INLINE_MAX_SIZE: usize = 128;
 
+/// Options for pushing a Spin application to an OCI registry.
+#[derive(Debug, Default)]
+pub struct PushOptions {
+    /// Annotations for the pushed manifest, such as
+    /// `org.opencontainers.image.source`. The title, version, description
+    /// and authors annotations default to the application's metadata.
+    pub annotations: BTreeMap<String, String>,
+    /// Platform-specific artifacts, such as plugin or runtime binaries, to
+    /// publish alongside the application. If any are given, the reference
+    /// points to an image index of the application and the artifacts.
+    pub platform_artifacts: Vec<PlatformArtifact>,
+}
+
+/// A platform-specific file to publish alongside a Spin application.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlatformArtifact {
+    /// Operating system, as in the OCI image spec, e.g. "linux"
+    pub os: String,
+    /// CPU architecture, as in the OCI image spec, e.g. "amd64"
+    pub architecture: String,
+    /// Path to the artifact file
+    pub path: PathBuf,
+}
+
+impl FromStr for PlatformArtifact {
+    type Err = anyhow::Error;
+
+    /// Parses an artifact of the form `<os>/<architecture>=<path>`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (platform, path) = s
+            .split_once('=')
+            .context("platform artifact must be of the form `<os>/<architecture>=<path>`")?;
+        let (os, architecture) = platform
+            .split_once('/')
+            .context("platform must be of the form `<os>/<architecture>`")?;
+        ensure!(
+            !os.is_empty() && !architecture.is_empty() && !path.is_empty(),
+            "platform artifact must be of the form `<os>/<architecture>=<path>`"
+        );
+        Ok(Self {
+            os: os.to_owned(),
+            architecture: architecture.to_owned(),
+            path: path.into(),
+        })
+    }
+}
+
 /// Client for interacting with an OCI registry for Spin applications.
 pub struct Client {
     /// Global cache for the metadata, Wasm modules, and static assets pulled from OCI registries.
@@ -68,6 +137,7 @@ impl Client {
         &mut self,
         manifest_path: &Path,
         reference: impl AsRef<str>,
+        options: &PushOptions,
     ) -> Result<Option<String>> {
         let reference: Reference = reference
             .as_ref()
@@ -86,7 +156,8 @@ impl Client {
         )
         .await?;
 
-        self.push_locked_core(locked, auth, reference).await
+        self.push_locked_core(locked, auth, reference, options)
+            .await
     }
 
     /// Push a Spin application to an OCI registry and return the digest (or None
@@ -95,6 +166,7 @@ impl Client {
         &mut self,
         locked: LockedApp,
         reference: impl AsRef<str>,
+        options: &PushOptions,
     ) -> Result<Option<String>> {
         let reference: Reference = reference
             .as_ref()
@@ -102,7 +174,8 @@ impl Client {
             .with_context(|| format!("cannot parse reference {}", reference.as_ref()))?;
         let auth = Self::auth(&reference).await?;
 
-        self.push_locked_core(locked, auth, reference).await
+        self.push_locked_core(locked, auth, reference, options)
+            .await
     }
 
     /// Push a Spin application to an OCI registry and return the digest (or None
@@ -112,7 +185,10 @@ impl Client {
         mut locked: LockedApp,
         auth: RegistryAuth,
         reference: Reference,
+        options: &PushOptions,
     ) -> Result<Option<String>> {
+        let annotations = manifest_annotations(&locked, &options.annotations);
+
         // For each component in the application, add a layer for the wasm module and
         // separate layers for all static assets if application total will be under MAX_LAYER_COUNT,
         // else an archive layer for all static assets per file entry if not.
@@ -138,8 +214,35 @@ impl Client {
 
             layers.push(layer);
 
+            // Add layers for dependencies loaded from disk; registry
+            // dependencies are pulled when the app is run.
+            for dependency in c.source.dependencies.values_mut() {
+                let source = dependency
+                    .content
+                    .source
+                    .as_deref()
+                    .context("dependency loaded from disk should contain a source")?;
+                if source.starts_with("oci://") {
+                    continue;
+                }
+                let source = parse_file_url(source)?;
+                let layer = Self::wasm_layer(&source).await?;
+                dependency.content = Self::content_ref_for_layer(&layer);
+                layers.push(layer);
+            }
+
             let mut files = Vec::new();
             for f in c.files {
+                if f.writable {
+                    // Writable mounts are copies of (part of) the component's
+                    // read-only root mount, whose files are pushed below
+                    tracing::warn!(
+                        "Writable files mount {:?} of component {:?} will be read-only when run from a registry",
+                        f.path,
+                        c.id
+                    );
+                    continue;
+                }
                 let source = f
                     .content
                     .source
@@ -187,14 +290,70 @@ impl Client {
         };
         let oci_config =
             oci_distribution::client::Config::oci_v1_from_config_file(oci_config_file, None)?;
-        let manifest = OciImageManifest::build(&layers, &oci_config, None);
+        let manifest = OciImageManifest::build(&layers, &oci_config, Some(annotations.clone()));
+
+        if options.platform_artifacts.is_empty() {
+            let response = self
+                .oci
+                .push(&reference, &layers, oci_config, &auth, Some(manifest))
+                .await
+                .map(|push_response| push_response.manifest_url)
+                .context("cannot push Spin application")?;
 
+            tracing::info!("Pushed {:?}", response);
+
+            let digest = digest_from_url(&response);
+            return Ok(digest);
+        }
+
+        // With platform artifacts, the app and each artifact are pushed by
+        // digest, and the reference tags an index of them all
+        let wasm_platform = Platform {
+            architecture: WASM_ARCHITECTURE.to_owned(),
+            os: WASM_OS.to_owned(),
+            os_version: None,
+            os_features: None,
+            variant: None,
+            features: None,
+        };
+        let mut index_entries = vec![index_entry(
+            &manifest,
+            wasm_platform,
+            Some(annotations.clone()),
+        )?];
+        let app_reference = digest_reference(&reference, &index_entries[0].digest);
+        self.oci
+            .push(&app_reference, &layers, oci_config, &auth, Some(manifest))
+            .await
+            .context("cannot push Spin application")?;
+
+        for artifact in &options.platform_artifacts {
+            let entry = self
+                .push_platform_artifact(&reference, &auth, artifact)
+                .await
+                .with_context(|| {
+                    format!(
+                        "cannot push platform artifact {}",
+                        quoted_path(&artifact.path)
+                    )
+                })?;
+            index_entries.push(entry);
+        }
+
+        let index = OciManifest::ImageIndex(OciImageIndex {
+            schema_version: 2,
+            media_type: Some(OCI_IMAGE_INDEX_MEDIA_TYPE.to_owned()),
+            manifests: index_entries,
+            annotations: Some(annotations),
+        });
+        self.oci
+            .auth(&reference, &auth, RegistryOperation::Push)
+            .await?;
         let response = self
             .oci
-            .push(&reference, &layers, oci_config, &auth, Some(manifest))
+            .push_manifest(&reference, &index)
             .await
-            .map(|push_response| push_response.manifest_url)
-            .context("cannot push Spin application")?;
+            .context("cannot push Spin application index")?;
 
         tracing::info!("Pushed {:?}", response);
 
@@ -202,6 +361,50 @@ impl Client {
         Ok(digest)
     }
 
+    /// Push a platform-specific artifact as a single-layer manifest, returning
+    /// its entry in the app's image index.
+    async fn push_platform_artifact(
+        &mut self,
+        reference: &Reference,
+        auth: &RegistryAuth,
+        artifact: &PlatformArtifact,
+    ) -> Result<ImageIndexEntry> {
+        let title = artifact
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        let layer = ImageLayer::new(
+            fs::read(&artifact.path).await?,
+            PLATFORM_ARTIFACT_MEDIA_TYPE.to_string(),
+            title.map(|title| HashMap::from([(TITLE_ANNOTATION.to_owned(), title)])),
+        );
+        let config = oci_distribution::client::Config::new(
+            serde_json::to_vec(&serde_json::json!({
+                "architecture": artifact.architecture,
+                "os": artifact.os,
+            }))?,
+            OCI_IMAGE_CONFIG_MEDIA_TYPE.to_owned(),
+            None,
+        );
+        let layers = [layer];
+        let manifest = OciImageManifest::build(&layers, &config, None);
+
+        let platform = Platform {
+            architecture: artifact.architecture.clone(),
+            os: artifact.os.clone(),
+            os_version: None,
+            os_features: None,
+            variant: None,
+            features: None,
+        };
+        let entry = index_entry(&manifest, platform, None)?;
+        let artifact_reference = digest_reference(reference, &entry.digest);
+        self.oci
+            .push(&artifact_reference, &layers, config, auth, Some(manifest))
+            .await?;
+        Ok(entry)
+    }
+
     /// Archive all of the files recursively under the source directory
     /// and push as a compressed archive layer
     async fn push_archive_layer(
@@ -225,6 +428,7 @@ impl Client {
             files.push(ContentPath {
                 content,
                 path: rel_path.into(),
+                writable: false,
             });
         }
 
@@ -267,6 +471,7 @@ impl Client {
             files.push(ContentPath {
                 content,
                 path: rel_path.into(),
+                writable: false,
             });
             // As a workaround for OCI implementations that don't support very small blobs,
             // don't push very small content that has been inlined into the manifest:
@@ -284,6 +489,12 @@ impl Client {
         let reference: Reference = reference.parse().context("cannot parse reference")?;
         let auth = Self::auth(&reference).await?;
 
+        // Content pinned by digest can't change, so needn't be pulled again
+        if reference.digest().is_some() && self.is_cached(&reference).await {
+            tracing::info!("Using cached {}", reference);
+            return Ok(());
+        }
+
         // Pull the manifest from the registry.
         let (manifest, digest) = self.oci.pull_image_manifest(&reference, &auth).await?;
 
@@ -301,6 +512,7 @@ impl Client {
         self.oci
             .pull_blob(&reference, &manifest.config.digest, &mut cfg_bytes)
             .await?;
+        verify_digest(&cfg_bytes, &manifest.config.digest)?;
         self.write_locked_app_config(&reference.to_string(), &cfg_bytes)
             .await
             .context("unable to write locked app config to cache")?;
@@ -313,9 +525,7 @@ impl Client {
                 let reference = reference.clone();
                 async move {
                     // Skip pulling if the digest already exists in the wasm or data directories.
-                    if this.cache.wasm_file(&layer.digest).is_ok()
-                        || this.cache.data_file(&layer.digest).is_ok()
-                    {
+                    if this.cache.verified_wasm_file(&layer.digest).is_ok() {
                         tracing::debug!("Layer {} already exists in cache", &layer.digest);
                         return anyhow::Ok(());
                     }
@@ -325,6 +535,7 @@ impl Client {
                     this.oci
                         .pull_blob(&reference, &layer.digest, &mut bytes)
                         .await?;
+                    verify_digest(&bytes, &layer.digest)?;
                     match layer.media_type.as_str() {
                         SPIN_APPLICATION_MEDIA_TYPE => {
                             this.write_locked_app_config(&reference.to_string(), &bytes)
@@ -352,6 +563,156 @@ impl Client {
         Ok(())
     }
 
+    /// Pull a single Wasm component from an OCI registry and return the path
+    /// to its (digest-verified) copy in the cache.
+    ///
+    /// The reference must resolve to an artifact with exactly one Wasm layer,
+    /// or with exactly one layer of any media type.
+    pub async fn pull_component(&mut self, reference: &str) -> Result<PathBuf> {
+        let reference: Reference = reference.parse().context("cannot parse reference")?;
+        let auth = Self::auth(&reference).await?;
+
+        let (manifest, _) = self.oci.pull_image_manifest(&reference, &auth).await?;
+        let mut wasm_layers = manifest
+            .layers
+            .iter()
+            .filter(|layer| layer.media_type == WASM_LAYER_MEDIA_TYPE)
+            .collect::<Vec<_>>();
+        if wasm_layers.is_empty() && manifest.layers.len() == 1 {
+            wasm_layers.push(&manifest.layers[0]);
+        }
+        let [layer] = wasm_layers.as_slice() else {
+            bail!("registry reference {reference} does not contain exactly one Wasm layer");
+        };
+
+        if let Ok(path) = self.cache.verified_wasm_file(&layer.digest) {
+            tracing::debug!("Component layer {} already exists in cache", &layer.digest);
+            return Ok(path);
+        }
+
+        tracing::debug!("Pulling component layer {}", &layer.digest);
+        let mut bytes = Vec::with_capacity(layer.size.try_into()?);
+        self.oci
+            .pull_blob(&reference, &layer.digest, &mut bytes)
+            .await?;
+        verify_digest(&bytes, &layer.digest).context("invalid component layer")?;
+        self.cache.write_wasm(&bytes, &layer.digest).await?;
+        tracing::info!("Pulled component {}@{}", reference, &layer.digest);
+
+        self.cache.wasm_file(&layer.digest)
+    }
+
+    /// Push the contents of a directory, such as a set of templates, to an
+    /// OCI registry as an artifact with a single archive layer, and return
+    /// the digest (or None if the digest cannot be determined).
+    pub async fn push_archive(
+        &mut self,
+        source: &Path,
+        reference: impl AsRef<str>,
+    ) -> Result<Option<String>> {
+        let reference: Reference = reference
+            .as_ref()
+            .parse()
+            .with_context(|| format!("cannot parse reference {}", reference.as_ref()))?;
+        let auth = Self::auth(&reference).await?;
+        let working_dir = tempfile::tempdir()?;
+
+        let archive_path = crate::utils::archive(source, working_dir.path())
+            .await
+            .with_context(|| format!("cannot archive {}", quoted_path(source)))?;
+        let layer = Self::data_layer(&archive_path, ARCHIVE_MEDIATYPE.to_string()).await?;
+        let layers = vec![layer];
+
+        let oci_config =
+            oci_distribution::client::Config::oci_v1_from_config_file(ConfigFile::default(), None)?;
+        let manifest = OciImageManifest::build(&layers, &oci_config, None);
+        let response = self
+            .oci
+            .push(&reference, &layers, oci_config, &auth, Some(manifest))
+            .await
+            .map(|push_response| push_response.manifest_url)
+            .with_context(|| format!("cannot push {}", quoted_path(source)))?;
+
+        tracing::info!("Pushed {:?}", response);
+
+        Ok(digest_from_url(&response))
+    }
+
+    /// Pull an artifact with a single archive layer, as pushed by
+    /// [`Client::push_archive`], and unpack it into `dest`. Returns the digest
+    /// of the artifact's manifest, with which the reference can be pinned to
+    /// exactly this content. If the reference is already pinned, the pulled
+    /// manifest must have that digest.
+    pub async fn pull_archive(&mut self, reference: &str, dest: &Path) -> Result<String> {
+        let reference: Reference = reference.parse().context("cannot parse reference")?;
+        let auth = Self::auth(&reference).await?;
+
+        let (manifest, digest) = self.oci.pull_image_manifest(&reference, &auth).await?;
+        if let Some(pinned) = reference.digest() {
+            ensure!(
+                pinned == digest,
+                "registry reference {reference} resolved to manifest {digest} rather than the pinned digest"
+            );
+        }
+        let [layer] = manifest.layers.as_slice() else {
+            bail!("registry reference {reference} does not contain exactly one layer");
+        };
+        ensure!(
+            layer.media_type == ARCHIVE_MEDIATYPE,
+            "registry reference {reference} contains a {} layer rather than an archive",
+            layer.media_type
+        );
+
+        tracing::debug!("Pulling archive layer {}", &layer.digest);
+        let mut bytes = Vec::with_capacity(layer.size.try_into()?);
+        self.oci
+            .pull_blob(&reference, &layer.digest, &mut bytes)
+            .await?;
+        verify_digest(&bytes, &layer.digest).context("invalid archive layer")?;
+
+        let staging_dir = tempfile::tempdir()?;
+        let archive_path = staging_dir.path().join("archive.tar.gz");
+        fs::write(&archive_path, &bytes).await?;
+        crate::utils::unarchive(&archive_path, dest).await?;
+        tracing::info!("Pulled {}@{}", reference, digest);
+
+        Ok(digest)
+    }
+
+    /// Get the cache directory for the manifest and config of a reference.
+    /// References pinned by digest are cached separately from tags, as a
+    /// tag may later point to different content.
+    fn reference_cache_dir(&self, reference: &Reference) -> PathBuf {
+        let version = match reference.digest() {
+            Some(digest) => digest.replace(':', "_"),
+            None => reference.tag().unwrap_or(LATEST_TAG).to_owned(),
+        };
+        self.cache
+            .manifests_dir()
+            .join(reference.registry())
+            .join(reference.repository())
+            .join(version)
+    }
+
+    /// Whether a previous pull of the reference left its config and all its
+    /// layers, intact, in the cache.
+    async fn is_cached(&self, reference: &Reference) -> bool {
+        let dir = self.reference_cache_dir(reference);
+        if !dir.join(CONFIG_FILE).is_file() {
+            return false;
+        }
+        let Ok(manifest_json) = fs::read(dir.join(MANIFEST_FILE)).await else {
+            return false;
+        };
+        let Ok(manifest) = serde_json::from_slice::<OciImageManifest>(&manifest_json) else {
+            return false;
+        };
+        manifest.layers.iter().all(|layer| {
+            layer.media_type == SPIN_APPLICATION_MEDIA_TYPE
+                || self.cache.verified_wasm_file(&layer.digest).is_ok()
+        })
+    }
+
     /// Get the file path to an OCI manifest given a reference.
     /// If the directory for the manifest does not exist, this will create it.
     async fn manifest_path(&self, reference: impl AsRef<str>) -> Result<PathBuf> {
@@ -359,12 +720,7 @@ impl Client {
             .as_ref()
             .parse()
             .context("cannot parse OCI reference")?;
-        let p = self
-            .cache
-            .manifests_dir()
-            .join(reference.registry())
-            .join(reference.repository())
-            .join(reference.tag().unwrap_or(LATEST_TAG));
+        let p = self.reference_cache_dir(&reference);
 
         if !p.is_dir() {
             fs::create_dir_all(&p)
@@ -381,12 +737,7 @@ impl Client {
             .as_ref()
             .parse()
             .context("cannot parse reference")?;
-        let p = self
-            .cache
-            .manifests_dir()
-            .join(reference.registry())
-            .join(reference.repository())
-            .join(reference.tag().unwrap_or(LATEST_TAG));
+        let p = self.reference_cache_dir(&reference);
 
         if !p.is_dir() {
             fs::create_dir_all(&p)
@@ -460,7 +811,7 @@ impl Client {
             if entry.file_type().is_file() && !entry.file_type().is_dir() {
                 let bytes = tokio::fs::read(entry.path()).await?;
                 let digest = format!("sha256:{}", sha256::hex_digest_from_bytes(&bytes));
-                if self.cache.data_file(&digest).is_ok() {
+                if self.cache.verified_data_file(&digest).is_ok() {
                     tracing::debug!(
                         "Skipping unpacked asset {:?}; file already exists",
                         entry.path()
@@ -575,11 +926,105 @@ impl Client {
 
         oci_distribution::client::ClientConfig {
             protocol,
+            // Apps pushed with platform artifacts are image indexes
+            platform_resolver: Some(Box::new(wasm_platform_resolver)),
             ..Default::default()
         }
     }
 }
 
+// Selects the Spin application from an image index
+fn wasm_platform_resolver(manifests: &[ImageIndexEntry]) -> Option<String> {
+    manifests
+        .iter()
+        .find(|entry| {
+            entry.platform.as_ref().is_some_and(|platform| {
+                platform.os == WASM_OS && platform.architecture == WASM_ARCHITECTURE
+            })
+        })
+        .map(|entry| entry.digest.clone())
+}
+
+// Annotations given explicitly take precedence over those inferred from the
+// app metadata.
+fn manifest_annotations(
+    locked: &LockedApp,
+    annotations: &BTreeMap<String, String>,
+) -> HashMap<String, String> {
+    let metadata_str = |key: &str| {
+        locked
+            .metadata
+            .get(key)
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    };
+    let authors = locked
+        .metadata
+        .get("authors")
+        .and_then(|value| value.as_array())
+        .map(|authors| {
+            authors
+                .iter()
+                .filter_map(|author| author.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|authors| !authors.is_empty());
+
+    let mut inferred = HashMap::new();
+    for (key, value) in [
+        (TITLE_ANNOTATION, metadata_str("name")),
+        (VERSION_ANNOTATION, metadata_str("version")),
+        (DESCRIPTION_ANNOTATION, metadata_str("description")),
+        (AUTHORS_ANNOTATION, authors),
+    ] {
+        if let Some(value) = value {
+            inferred.insert(key.to_owned(), value);
+        }
+    }
+    inferred.extend(annotations.clone());
+    inferred
+}
+
+fn index_entry(
+    manifest: &OciImageManifest,
+    platform: Platform,
+    annotations: Option<HashMap<String, String>>,
+) -> Result<ImageIndexEntry> {
+    // The registry checks this digest against the manifest as serialized
+    // when pushed
+    let manifest_bytes = serde_json::to_vec(manifest)?;
+    Ok(ImageIndexEntry {
+        media_type: OCI_IMAGE_MANIFEST_MEDIA_TYPE.to_owned(),
+        digest: format!("sha256:{}", sha256::hex_digest_from_bytes(&manifest_bytes)),
+        size: manifest_bytes.len().try_into()?,
+        platform: Some(platform),
+        annotations,
+    })
+}
+
+fn digest_reference(reference: &Reference, digest: &str) -> Reference {
+    Reference::with_digest(
+        reference.registry().to_owned(),
+        reference.repository().to_owned(),
+        digest.to_owned(),
+    )
+}
+
+// Checks pulled content against its digest before it is cached.
+fn verify_digest(bytes: &[u8], digest: &str) -> Result<()> {
+    let expected = digest
+        .strip_prefix("sha256:")
+        .with_context(|| format!("unsupported digest {digest}; only sha256 is supported"))?;
+    let actual = sha256::hex_digest_from_bytes(bytes);
+    ensure!(
+        actual == expected,
+        "invalid digest; expected {digest}, pulled sha256:{actual}"
+    );
+    Ok(())
+}
+
 fn digest_from_url(manifest_url: &str) -> Option<String> {
     // The URL is in the form "https://host/v2/refname/manifests/sha256:..."
     let manifest_url = Url::parse(manifest_url).ok()?;
@@ -595,7 +1040,7 @@ fn digest_from_url(manifest_url: &str) -> Option<String> {
 async fn layer_count(locked: LockedApp) -> Result<usize> {
     let mut layer_count = 0;
     for c in locked.components {
-        layer_count += 1;
+        layer_count += 1 + c.source.dependencies.len();
         for f in c.files {
             let source = f
                 .content
@@ -617,6 +1062,55 @@ async fn layer_count(locked: LockedApp) -> Result<usize> {
 mod test {
     use super::*;
 
+    #[test]
+    fn can_parse_platform_artifact() {
+        let artifact: PlatformArtifact = "linux/amd64=target/release/plugin".parse().unwrap();
+        assert_eq!(artifact.os, "linux");
+        assert_eq!(artifact.architecture, "amd64");
+        assert_eq!(artifact.path, PathBuf::from("target/release/plugin"));
+
+        assert!("linux=plugin".parse::<PlatformArtifact>().is_err());
+        assert!("linux/amd64".parse::<PlatformArtifact>().is_err());
+    }
+
+    #[test]
+    fn explicit_annotations_override_inferred() {
+        let locked: LockedApp = spin_testing::from_json!({
+            "spin_lock_version": 0,
+            "metadata": {
+                "name": "hello",
+                "version": "1.0.0",
+                "authors": ["Alice", "Bob"],
+            },
+            "triggers": [],
+            "components": [],
+        });
+        let explicit = BTreeMap::from([
+            (VERSION_ANNOTATION.to_owned(), "1.0.1".to_owned()),
+            (
+                "org.opencontainers.image.source".to_owned(),
+                "https://example.com/hello".to_owned(),
+            ),
+        ]);
+        let annotations = manifest_annotations(&locked, &explicit);
+        assert_eq!(annotations[TITLE_ANNOTATION], "hello");
+        assert_eq!(annotations[VERSION_ANNOTATION], "1.0.1");
+        assert_eq!(annotations[AUTHORS_ANNOTATION], "Alice, Bob");
+        assert_eq!(
+            annotations["org.opencontainers.image.source"],
+            "https://example.com/hello"
+        );
+        assert!(!annotations.contains_key(DESCRIPTION_ANNOTATION));
+    }
+
+    #[test]
+    fn verifies_pulled_digests() {
+        let digest = format!("sha256:{}", sha256::hex_digest_from_bytes("spin"));
+        assert!(verify_digest(b"spin", &digest).is_ok());
+        assert!(verify_digest(b"nips", &digest).is_err());
+        assert!(verify_digest(b"spin", "md5:abc").is_err());
+    }
+
     #[test]
     fn can_parse_digest_from_manifest_url() {
         let manifest_url = "https://ghcr.io/v2/itowlson/osf/manifests/sha256:0a867093096e0ef01ef749b12b6e7a90e4952eda107f89a676eeedce63a8361f";
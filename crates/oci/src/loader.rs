@@ -79,9 +79,20 @@ impl OciLoader {
     ) -> Result<()> {
         // Update wasm content path
         let wasm_digest = content_digest(&component.source.content)?;
-        let wasm_path = cache.wasm_file(wasm_digest)?;
+        let wasm_path = cache.verified_wasm_file(wasm_digest)?;
         component.source.content = content_ref(wasm_path)?;
 
+        // Update dependency content paths; registry dependencies are left
+        // for the runtime loader to pull
+        for dependency in component.source.dependencies.values_mut() {
+            if dependency.content.digest.is_none() {
+                continue;
+            }
+            let digest = content_digest(&dependency.content)?;
+            let path = cache.verified_wasm_file(digest)?;
+            dependency.content = content_ref(path)?;
+        }
+
         if !component.files.is_empty() {
             let mount_dir = self.working_dir.join("assets").join(&component.id);
             for file in &mut component.files {
@@ -108,7 +119,7 @@ impl OciLoader {
                 } else {
                     // Copy content
                     let digest = content_digest(&file.content)?;
-                    let content_path = cache.data_file(digest)?;
+                    let content_path = cache.verified_data_file(digest)?;
                     // TODO: parallelize
                     tokio::fs::copy(&content_path, &mount_path)
                         .await
@@ -124,6 +135,7 @@ impl OciLoader {
             component.files = vec![ContentPath {
                 content: content_ref(mount_dir)?,
                 path: "/".into(),
+                writable: false,
             }]
         }
 
@@ -1,13 +1,153 @@
-use anyhow::Result;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
+use anyhow::{bail, Context, Result};
+use reqwest::Client;
 use spin_app::DynamicHostComponent;
 use spin_core::{Data, HostComponent, Linker};
-use spin_outbound_networking::{AllowedHostsConfig, ALLOWED_HOSTS_KEY};
+use spin_outbound_networking::{dns::Resolver, AllowedHostsConfig, ALLOWED_HOSTS_KEY};
 use spin_world::v1::http;
 
-use crate::host_impl::OutboundHttp;
+use crate::{
+    host_impl::OutboundHttp,
+    policy::{CircuitBreakers, OUTBOUND_HTTP_POLICY_KEY},
+};
 
-pub struct OutboundHttpComponent;
+/// Outbound HTTP host component.
+///
+/// All instances share one connection pool, so that connections are kept
+/// alive and reused across guest requests. Pooled connections are only ever
+/// used for requests that pass the requesting component's allowed hosts check.
+/// Destinations with their own TLS options have their own clients, and pools.
+/// Triggers also send guests' `wasi:http` outgoing requests with these
+/// clients, through the instance data's [`spin_core::OutgoingRequestSender`],
+/// so the same TLS options apply to them.
+pub struct OutboundHttpComponent {
+    client: Client,
+    destination_clients: Arc<Vec<DestinationClient>>,
+    circuit_breakers: Arc<CircuitBreakers>,
+}
+
+/// A client for requests to destinations with their own TLS options.
+pub(crate) struct DestinationClient {
+    pub hosts: AllowedHostsConfig,
+    pub client: Client,
+}
+
+/// Connection pool options for [`OutboundHttpComponent`].
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionPoolConfig {
+    /// Maximum number of idle connections kept open to each destination host.
+    pub max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept open.
+    pub idle_timeout: Option<Duration>,
+}
+
+/// TLS options for requests to particular destinations, such as internal
+/// services which require mutual TLS.
+#[derive(Clone, Debug, Default)]
+pub struct DestinationTlsConfig {
+    /// Destinations the options apply to, in the same form as a component's
+    /// allowed outbound hosts, e.g. `https://*.internal.example.com:8443`.
+    pub hosts: Vec<String>,
+    /// PEM certificates of CAs to trust, in addition to the system's.
+    pub ca_certs: Vec<Vec<u8>>,
+    /// A PEM client certificate chain and PKCS#8 key to present.
+    pub client_identity: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl OutboundHttpComponent {
+    /// Creates a component whose connection pools have the given
+    /// configuration, using the given TLS options for matching destinations.
+    /// Where several match a destination, the first is used. Destination
+    /// hosts are resolved with `resolver`.
+    pub fn new(
+        pool_config: ConnectionPoolConfig,
+        destination_tls: Vec<DestinationTlsConfig>,
+        resolver: Resolver,
+    ) -> Result<Self> {
+        let client = build_client(&pool_config, None, &resolver)?;
+        let destination_clients = destination_tls
+            .iter()
+            .map(|tls| {
+                Ok(DestinationClient {
+                    hosts: AllowedHostsConfig::parse(&tls.hosts)?,
+                    client: build_client(&pool_config, Some(tls), &resolver)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            client,
+            destination_clients: Arc::new(destination_clients),
+            circuit_breakers: Default::default(),
+        })
+    }
+}
+
+fn build_client(
+    pool_config: &ConnectionPoolConfig,
+    tls: Option<&DestinationTlsConfig>,
+    resolver: &Resolver,
+) -> Result<Client> {
+    let mut builder = Client::builder();
+    if resolver.is_custom() {
+        builder = builder.dns_resolver(Arc::new(DnsResolver(resolver.clone())));
+    }
+    if let Some(max_idle_per_host) = pool_config.max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle_per_host);
+    }
+    if let Some(idle_timeout) = pool_config.idle_timeout {
+        builder = builder.pool_idle_timeout(idle_timeout);
+    }
+    if let Some(tls) = tls {
+        for pem in &tls.ca_certs {
+            let certs = pem_certificates(pem);
+            if certs.is_empty() {
+                bail!("no certificates found in CA bundle");
+            }
+            for cert in certs {
+                let cert =
+                    reqwest::Certificate::from_pem(cert).context("invalid CA certificate")?;
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some((cert, key)) = &tls.client_identity {
+            let identity = reqwest::Identity::from_pkcs8_pem(cert, key)
+                .context("invalid client certificate or key")?;
+            builder = builder.identity(identity);
+        }
+    }
+    builder
+        .build()
+        .context("failed to build outbound HTTP client")
+}
+
+struct DnsResolver(Resolver);
+
+impl reqwest::dns::Resolve for DnsResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            // The request's port replaces this one
+            let addrs: reqwest::dns::Addrs =
+                Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+// Splits a PEM bundle into its certificates.
+fn pem_certificates(pem: &[u8]) -> Vec<&[u8]> {
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+    let mut certs = vec![];
+    let mut rest = pem;
+    while let Some(end) = rest.windows(END.len()).position(|window| window == END) {
+        let (cert, remaining) = rest.split_at(end + END.len());
+        certs.push(cert);
+        rest = remaining;
+    }
+    certs
+}
 
 impl HostComponent for OutboundHttpComponent {
     type Data = OutboundHttp;
@@ -20,7 +160,11 @@ impl HostComponent for OutboundHttpComponent {
     }
 
     fn build_data(&self) -> Self::Data {
-        Default::default()
+        OutboundHttp::new(
+            self.client.clone(),
+            self.destination_clients.clone(),
+            self.circuit_breakers.clone(),
+        )
     }
 }
 
@@ -34,6 +178,42 @@ impl DynamicHostComponent for OutboundHttpComponent {
             .get_metadata(ALLOWED_HOSTS_KEY)?
             .unwrap_or_default();
         data.allowed_hosts = AllowedHostsConfig::parse(&hosts)?;
+        data.component_id = component.id().to_owned();
+        data.policy = component
+            .get_metadata(OUTBOUND_HTTP_POLICY_KEY)?
+            .unwrap_or_default();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use spin_outbound_networking::OutboundUrl;
+
+    use super::*;
+
+    #[test]
+    fn splits_pem_bundles() {
+        let bundle = b"-----BEGIN CERTIFICATE-----\nA\n-----END CERTIFICATE-----\n\
+            -----BEGIN CERTIFICATE-----\nB\n-----END CERTIFICATE-----\n";
+        let certs = pem_certificates(bundle);
+        assert_eq!(certs.len(), 2);
+        assert!(certs[1].ends_with(b"B\n-----END CERTIFICATE-----"));
+        assert!(pem_certificates(b"not a certificate").is_empty());
+    }
+
+    #[test]
+    fn builds_destination_clients() {
+        let component = OutboundHttpComponent::new(
+            Default::default(),
+            vec![DestinationTlsConfig {
+                hosts: vec!["https://*.internal.example.com".into()],
+                ..Default::default()
+            }],
+            Default::default(),
+        )
+        .unwrap();
+        let internal = OutboundUrl::parse("https://api.internal.example.com", "https").unwrap();
+        assert!(component.destination_clients[0].hosts.allows(&internal));
+    }
+}
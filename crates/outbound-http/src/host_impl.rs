@@ -1,5 +1,8 @@
+use std::sync::Arc;
+
 use anyhow::Result;
 use http::HeaderMap;
+use hyper::body::Bytes;
 use reqwest::Client;
 use spin_core::async_trait;
 use spin_outbound_networking::{AllowedHostsConfig, OutboundUrl};
@@ -8,6 +11,11 @@ use spin_world::v1::{
     http_types::{Headers, HttpError, Method, Request, Response},
 };
 
+use crate::{
+    host_component::DestinationClient,
+    policy::{CircuitBreakers, RequestPolicy},
+};
+
 /// A very simple implementation for outbound HTTP requests.
 #[derive(Default, Clone)]
 pub struct OutboundHttp {
@@ -16,10 +24,45 @@ pub struct OutboundHttp {
     /// During an incoming HTTP request, origin is set to the host of that incoming HTTP request.
     /// This is used to direct outbound requests to the same host when allowed.
     pub origin: String,
+    /// ID of the component making requests.
+    pub(crate) component_id: String,
+    /// Timeout, retry and circuit breaker policy for the component's requests.
+    pub(crate) policy: RequestPolicy,
     client: Option<Client>,
+    destination_clients: Arc<Vec<DestinationClient>>,
+    circuit_breakers: Arc<CircuitBreakers>,
 }
 
 impl OutboundHttp {
+    /// Creates an instance which sends requests using the given client.
+    pub(crate) fn new(
+        client: Client,
+        destination_clients: Arc<Vec<DestinationClient>>,
+        circuit_breakers: Arc<CircuitBreakers>,
+    ) -> Self {
+        Self {
+            client: Some(client),
+            destination_clients,
+            circuit_breakers,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the client for requests to a URL: that of the first destination
+    /// with its own TLS options which matches it, or otherwise the shared one.
+    pub(crate) fn client_for(&mut self, url: &reqwest::Url) -> Client {
+        let destination = OutboundUrl::parse(url.as_str(), "https").ok();
+        let destination_client = destination.and_then(|destination| {
+            self.destination_clients
+                .iter()
+                .find(|client| client.hosts.allows(&destination))
+        });
+        match destination_client {
+            Some(destination_client) => destination_client.client.clone(),
+            None => self.client.get_or_insert_with(Default::default).clone(),
+        }
+    }
+
     /// Check if guest module is allowed to send request to URL, based on the list of
     /// allowed hosts defined by the runtime. If the url passed in is a relative path,
     /// only allow if allowed_hosts contains `self`. If the list of allowed hosts contains
@@ -38,6 +81,15 @@ impl OutboundHttp {
 
 #[async_trait]
 impl outbound_http::Host for OutboundHttp {
+    #[tracing::instrument(
+        name = "spin_outbound_http.send_request",
+        skip_all,
+        fields(
+            otel.kind = "client",
+            http.request.method = ?req.method,
+            server.address = tracing::field::Empty,
+        )
+    )]
     async fn send_request(&mut self, req: Request) -> Result<Result<Response, HttpError>> {
         Ok(async {
             tracing::log::trace!("Attempting to send outbound HTTP request to {}", req.uri);
@@ -63,31 +115,105 @@ impl outbound_http::Host for OutboundHttp {
 
             let req_url = reqwest::Url::parse(&abs_url).map_err(|_| HttpError::InvalidUrl)?;
 
-            let headers = request_headers(req.headers).map_err(|_| HttpError::RuntimeError)?;
+            let mut headers =
+                request_headers(req.headers).map_err(|_| HttpError::RuntimeError)?;
+            // Continue the trace unless the component is propagating its own
+            if !headers.contains_key(spin_telemetry::TRACEPARENT_HEADER) {
+                spin_telemetry::inject_trace_context(&mut headers);
+            }
             let body = req.body.unwrap_or_default().to_vec();
 
             if !req.params.is_empty() {
                 tracing::log::warn!("HTTP params field is deprecated");
             }
 
-            // Allow reuse of Client's internal connection pool for multiple requests
-            // in a single component execution
-            let client = self.client.get_or_insert_with(Default::default);
+            // The client is normally shared by all component executions, allowing reuse
+            // of its internal connection pool across requests
+            let client = self.client_for(&req_url);
 
-            let resp = client
-                .request(method, req_url)
-                .headers(headers)
-                .body(body)
-                .send()
+            tracing::Span::current()
+                .record("server.address", req_url.origin().ascii_serialization());
+            spin_metrics::record_outbound_call(&self.component_id, spin_metrics::outbound::HTTP);
+            let result = match self
+                .send_with_policy(&client, method, req_url, headers, body.into())
                 .await
-                .map_err(log_reqwest_error)?;
+            {
+                Ok(resp) => response_from_reqwest(resp).await,
+                Err(SendError::CircuitOpen) => Err(HttpError::RuntimeError),
+                Err(SendError::Request(err)) => Err(log_reqwest_error(err)),
+            };
             tracing::log::trace!("Returning response from outbound request to {}", req.uri);
-            response_from_reqwest(resp).await
+            result
         }
         .await)
     }
 }
 
+/// Why a request sent with a component's policy failed.
+pub(crate) enum SendError {
+    /// The circuit to the destination is open.
+    CircuitOpen,
+    Request(reqwest::Error),
+}
+
+impl OutboundHttp {
+    /// Sends a request with the component's timeout, retry and circuit breaker
+    /// policy. A 5xx response counts as a failure, and is returned once there
+    /// are no retries left.
+    pub(crate) async fn send_with_policy(
+        &self,
+        client: &Client,
+        method: http::Method,
+        url: reqwest::Url,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<reqwest::Response, SendError> {
+        let destination = url.origin().ascii_serialization();
+        let circuit_breaker = self.policy.circuit_breaker.as_ref();
+        if circuit_breaker.is_some()
+            && !self
+                .circuit_breakers
+                .allows(&self.component_id, &destination)
+        {
+            tracing::warn!("Outbound HTTP circuit to {destination} is open; failing request");
+            return Err(SendError::CircuitOpen);
+        }
+
+        // Only idempotent requests can safely be sent more than once
+        let retries = if is_idempotent(&method) {
+            self.policy.retries.unwrap_or_default()
+        } else {
+            0
+        };
+        let mut retry = 0;
+        loop {
+            let mut request = client
+                .request(method.clone(), url.clone())
+                .headers(headers.clone())
+                .body(body.clone());
+            if let Some(timeout) = self.policy.timeout() {
+                request = request.timeout(timeout);
+            }
+            let result = request.send().await;
+            let failed = match &result {
+                Ok(resp) => resp.status().is_server_error(),
+                Err(_) => true,
+            };
+            if let Some(config) = circuit_breaker {
+                self.circuit_breakers
+                    .record(config, &self.component_id, &destination, !failed);
+            }
+            if !failed || retry >= retries {
+                return result.map_err(SendError::Request);
+            }
+            let backoff = self.policy.retry_backoff(retry);
+            tracing::log::debug!("Retrying outbound request to {url} in {backoff:?}");
+            tokio::time::sleep(backoff).await;
+            retry += 1;
+        }
+    }
+}
+
 fn log_reqwest_error(err: reqwest::Error) -> HttpError {
     let error_desc = if err.is_timeout() {
         "timeout error"
@@ -111,6 +237,17 @@ fn log_reqwest_error(err: reqwest::Error) -> HttpError {
     HttpError::RuntimeError
 }
 
+fn is_idempotent(method: &http::Method) -> bool {
+    matches!(
+        *method,
+        http::Method::GET
+            | http::Method::HEAD
+            | http::Method::PUT
+            | http::Method::DELETE
+            | http::Method::OPTIONS
+    )
+}
+
 fn method_from(m: Method) -> http::Method {
     match m {
         Method::Get => http::Method::GET,
@@ -2,9 +2,17 @@
 mod host_component;
 #[cfg(feature = "runtime")]
 mod host_impl;
+#[cfg(feature = "runtime")]
+mod policy;
+#[cfg(feature = "runtime")]
+mod wasi;
 
 #[cfg(feature = "runtime")]
-pub use host_component::OutboundHttpComponent;
+pub use host_component::{ConnectionPoolConfig, DestinationTlsConfig, OutboundHttpComponent};
+#[cfg(feature = "runtime")]
+pub use host_impl::OutboundHttp;
+#[cfg(feature = "runtime")]
+pub use policy::{CircuitBreakerConfig, RequestPolicy, OUTBOUND_HTTP_POLICY_KEY};
 
 use spin_locked_app::MetadataKey;
 
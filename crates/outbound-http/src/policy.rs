@@ -0,0 +1,134 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use spin_locked_app::MetadataKey;
+
+/// Metadata key for a component's outbound HTTP request policy.
+pub const OUTBOUND_HTTP_POLICY_KEY: MetadataKey<RequestPolicy> = MetadataKey::new("outbound_http");
+
+/// A component's outbound HTTP request policy.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RequestPolicy {
+    pub timeout_ms: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CircuitBreakerConfig {
+    pub failure_threshold: u32,
+    pub reset_timeout_ms: u64,
+}
+
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+impl RequestPolicy {
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Returns how long to wait before the given retry (counting from 0).
+    pub(crate) fn retry_backoff(&self, retry: u32) -> Duration {
+        let initial = self
+            .retry_backoff_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_RETRY_BACKOFF);
+        initial.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// Circuit breaker state for each (component, destination) pair, shared by
+/// all instances.
+///
+/// After `failure_threshold` consecutive failed requests to a destination, the
+/// circuit opens and requests fail immediately for `reset_timeout_ms`. The
+/// next request after that is let through; if it also fails, the circuit
+/// opens again.
+#[derive(Debug, Default)]
+pub(crate) struct CircuitBreakers {
+    circuits: Mutex<HashMap<(String, String), Circuit>>,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreakers {
+    /// Returns whether a request may be made to `destination`.
+    pub(crate) fn allows(&self, component_id: &str, destination: &str) -> bool {
+        let circuits = self.circuits.lock().unwrap();
+        match circuits.get(&(component_id.to_owned(), destination.to_owned())) {
+            Some(Circuit {
+                open_until: Some(open_until),
+                ..
+            }) => Instant::now() >= *open_until,
+            _ => true,
+        }
+    }
+
+    /// Records the outcome of a request to `destination`.
+    pub(crate) fn record(
+        &self,
+        config: &CircuitBreakerConfig,
+        component_id: &str,
+        destination: &str,
+        succeeded: bool,
+    ) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let key = (component_id.to_owned(), destination.to_owned());
+        if succeeded {
+            circuits.remove(&key);
+            return;
+        }
+        let circuit = circuits.entry(key).or_default();
+        circuit.consecutive_failures += 1;
+        if circuit.consecutive_failures >= config.failure_threshold {
+            if circuit.consecutive_failures == config.failure_threshold {
+                tracing::warn!(
+                    "Outbound HTTP circuit to {destination} opened for component {component_id} after {} consecutive failures",
+                    circuit.consecutive_failures
+                );
+            }
+            circuit.open_until =
+                Some(Instant::now() + Duration::from_millis(config.reset_timeout_ms));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles() {
+        let policy = RequestPolicy {
+            retry_backoff_ms: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(policy.retry_backoff(0), Duration::from_millis(50));
+        assert_eq!(policy.retry_backoff(2), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn circuit_opens_after_threshold_and_closes_on_success() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            reset_timeout_ms: 60_000,
+        };
+        let breakers = CircuitBreakers::default();
+        breakers.record(&config, "c", "https://example.com", false);
+        assert!(breakers.allows("c", "https://example.com"));
+        breakers.record(&config, "c", "https://example.com", false);
+        assert!(!breakers.allows("c", "https://example.com"));
+        assert!(breakers.allows("other", "https://example.com"));
+        breakers.record(&config, "c", "https://example.com", true);
+        assert!(breakers.allows("c", "https://example.com"));
+    }
+}
@@ -0,0 +1,78 @@
+//! Sending of `wasi:http` outgoing requests with the outbound HTTP clients,
+//! so that they share connection pools with Spin's own outbound HTTP
+//! interface.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use futures::TryStreamExt;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::body::Frame;
+use spin_core::OutgoingRequestSender;
+use wasmtime_wasi_http::types::{
+    HostFutureIncomingResponse, IncomingResponseInternal, OutgoingRequest,
+};
+
+use crate::host_impl::{OutboundHttp, SendError};
+
+impl OutgoingRequestSender for OutboundHttp {
+    fn send(&self, request: OutgoingRequest) -> HostFutureIncomingResponse {
+        let handle = wasmtime_wasi::preview2::spawn(self.clone().send_wasi_request(request));
+        HostFutureIncomingResponse::new(handle)
+    }
+}
+
+impl OutboundHttp {
+    // Requests are sent with the component's outbound HTTP policy, so the
+    // body is buffered for retries. The `wasi:http` connect timeout is covered
+    // by the first byte timeout.
+    async fn send_wasi_request(
+        mut self,
+        request: OutgoingRequest,
+    ) -> Result<IncomingResponseInternal> {
+        let OutgoingRequest {
+            use_tls,
+            authority,
+            request,
+            first_byte_timeout,
+            between_bytes_timeout,
+            ..
+        } = request;
+        let (parts, body) = request.into_parts();
+        let scheme = if use_tls { "https" } else { "http" };
+        let path_and_query = parts.uri.path_and_query().map_or("/", |p| p.as_str());
+        let url = reqwest::Url::parse(&format!("{scheme}://{authority}{path_and_query}"))
+            .with_context(|| format!("invalid outgoing request URL for {authority:?}"))?;
+        let body = body
+            .collect()
+            .await
+            .context("failed to read outgoing request body")?
+            .to_bytes();
+
+        // Destinations with their own TLS options have their own clients
+        let client = self.client_for(&url);
+        let request = self.send_with_policy(&client, parts.method, url, parts.headers, body);
+        let response = match tokio::time::timeout(first_byte_timeout, request)
+            .await
+            .context("timed out waiting for the response to an outgoing request")?
+        {
+            Ok(response) => response,
+            Err(SendError::CircuitOpen) => anyhow::bail!("the circuit to this destination is open"),
+            Err(SendError::Request(err)) => return Err(err.into()),
+        };
+
+        let mut resp = hyper::Response::builder().status(response.status());
+        *resp.headers_mut().unwrap() = response.headers().clone();
+        let body = StreamBody::new(
+            response
+                .bytes_stream()
+                .map_ok(Frame::data)
+                .map_err(anyhow::Error::from),
+        );
+        Ok(IncomingResponseInternal {
+            resp: resp.body(body.boxed())?,
+            worker: Arc::new(wasmtime_wasi::preview2::spawn(async { Ok(()) })),
+            between_bytes_timeout,
+        })
+    }
+}
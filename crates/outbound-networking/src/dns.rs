@@ -0,0 +1,227 @@
+//! Hostname resolution for outbound connections, which may be configured to
+//! use particular DNS servers and static host overrides, as in air-gapped and
+//! service mesh deployments.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use hickory_resolver::{
+    config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+
+/// How long resolved addresses are reused if not otherwise configured.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// How outbound hostnames are resolved.
+#[derive(Clone, Debug)]
+pub struct DnsConfig {
+    /// DNS servers to query instead of the system's resolver.
+    pub servers: Vec<SocketAddr>,
+    /// Addresses to use for particular hostnames without querying DNS.
+    pub hosts: HashMap<String, Vec<IpAddr>>,
+    /// How long resolved addresses are reused. Zero disables caching.
+    pub cache_ttl: Duration,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            servers: vec![],
+            hosts: Default::default(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+        }
+    }
+}
+
+/// Resolves hostnames for outbound connections. Clones share a cache.
+///
+/// The default resolver uses the system's resolver, and caches addresses for
+/// [`DEFAULT_CACHE_TTL`].
+#[derive(Clone)]
+pub struct Resolver {
+    inner: Arc<ResolverInner>,
+}
+
+struct ResolverInner {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    // The system resolver is used if there are no configured servers
+    servers: Option<TokioAsyncResolver>,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, Vec<IpAddr>)>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new(DnsConfig::default())
+    }
+}
+
+impl Resolver {
+    pub fn new(config: DnsConfig) -> Self {
+        let servers = (!config.servers.is_empty()).then(|| {
+            let mut resolver_config = ResolverConfig::new();
+            for server in &config.servers {
+                resolver_config.add_name_server(NameServerConfig::new(*server, Protocol::Udp));
+                resolver_config.add_name_server(NameServerConfig::new(*server, Protocol::Tcp));
+            }
+            TokioAsyncResolver::tokio(resolver_config, ResolverOpts::default())
+        });
+        let hosts = config
+            .hosts
+            .into_iter()
+            .filter(|(_, addrs)| !addrs.is_empty())
+            .map(|(host, addrs)| (host.to_ascii_lowercase(), addrs))
+            .collect();
+        Self {
+            inner: Arc::new(ResolverInner {
+                hosts,
+                servers,
+                cache_ttl: config.cache_ttl,
+                cache: Default::default(),
+            }),
+        }
+    }
+
+    /// Whether this resolves hostnames differently from the system
+    /// resolver, so that connections must be made to the addresses it
+    /// returns rather than by name.
+    pub fn is_custom(&self) -> bool {
+        !self.inner.hosts.is_empty() || self.inner.servers.is_some()
+    }
+
+    /// Returns the addresses of the given host, of which there is at least
+    /// one. An IP address resolves to itself.
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>> {
+        if let Some(addrs) = self.known_addrs(host) {
+            return Ok(addrs);
+        }
+        let addrs = match &self.inner.servers {
+            Some(servers) => servers
+                .lookup_ip(host)
+                .await
+                .with_context(|| format!("failed to resolve {host:?}"))?
+                .iter()
+                .collect(),
+            None => tokio::net::lookup_host((host, 0))
+                .await
+                .with_context(|| format!("failed to resolve {host:?}"))?
+                .map(|addr| addr.ip())
+                .collect(),
+        };
+        self.cache(host, addrs)
+    }
+
+    // Returns the addresses of a host if they are known without a query.
+    fn known_addrs(&self, host: &str) -> Option<Vec<IpAddr>> {
+        if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse() {
+            return Some(vec![ip]);
+        }
+        let host = host.to_ascii_lowercase();
+        if let Some(addrs) = self.inner.hosts.get(&host) {
+            return Some(addrs.clone());
+        }
+        let cache = self.inner.cache.lock().unwrap();
+        match cache.get(&host) {
+            Some((resolved_at, addrs)) if resolved_at.elapsed() < self.inner.cache_ttl => {
+                Some(addrs.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn cache(&self, host: &str, addrs: Vec<IpAddr>) -> Result<Vec<IpAddr>> {
+        if addrs.is_empty() {
+            bail!("{host:?} has no addresses");
+        }
+        if !self.inner.cache_ttl.is_zero() {
+            self.inner
+                .cache
+                .lock()
+                .unwrap()
+                .insert(host.to_ascii_lowercase(), (Instant::now(), addrs.clone()));
+        }
+        Ok(addrs)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl Resolver {
+    /// Connects to the Redis server at `info` with `connect`, trying each of
+    /// the server's addresses in turn and returning the last error if none
+    /// succeed. TLS connections are made by name, which certificates are
+    /// verified against.
+    pub async fn connect_redis<T, Fut>(
+        &self,
+        info: redis::ConnectionInfo,
+        mut connect: impl FnMut(redis::Client) -> Fut,
+    ) -> Result<T>
+    where
+        Fut: std::future::Future<Output = redis::RedisResult<T>>,
+    {
+        let infos = match &info.addr {
+            redis::ConnectionAddr::Tcp(host, port) if self.is_custom() => self
+                .lookup(host)
+                .await?
+                .into_iter()
+                .map(|ip| redis::ConnectionInfo {
+                    addr: redis::ConnectionAddr::Tcp(ip.to_string(), *port),
+                    redis: info.redis.clone(),
+                })
+                .collect(),
+            _ => vec![info],
+        };
+        let mut last_err = None;
+        for info in infos {
+            match connect(redis::Client::open(info)?).await {
+                Ok(connection) => return Ok(connection),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.expect("there is at least one address").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn static_hosts_override_dns() {
+        let db: IpAddr = "10.1.2.3".parse().unwrap();
+        let resolver = Resolver::new(DnsConfig {
+            hosts: [("DB.internal".to_owned(), vec![db])].into(),
+            ..Default::default()
+        });
+        assert!(resolver.is_custom());
+        assert_eq!(resolver.lookup("db.internal").await.unwrap(), [db]);
+        assert_eq!(resolver.lookup("db.INTERNAL").await.unwrap(), [db]);
+        assert_eq!(
+            resolver.lookup("[::1]").await.unwrap(),
+            ["::1".parse::<IpAddr>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn lookups_are_cached_unless_disabled() {
+        let db: IpAddr = "10.1.2.3".parse().unwrap();
+        let resolver = Resolver::default();
+        assert!(!resolver.is_custom());
+        assert!(resolver.known_addrs("db.internal").is_none());
+        resolver.cache("db.internal", vec![db]).unwrap();
+        assert_eq!(resolver.known_addrs("DB.internal"), Some(vec![db]));
+
+        let resolver = Resolver::new(DnsConfig {
+            cache_ttl: Duration::ZERO,
+            ..Default::default()
+        });
+        resolver.cache("db.internal", vec![db]).unwrap();
+        assert!(resolver.known_addrs("db.internal").is_none());
+        assert!(resolver.cache("db.internal", vec![]).is_err());
+    }
+}
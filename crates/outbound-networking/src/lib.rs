@@ -3,8 +3,25 @@ use std::ops::Range;
 use anyhow::{bail, ensure, Context};
 use spin_locked_app::MetadataKey;
 
+pub mod dns;
+
 pub const ALLOWED_HOSTS_KEY: MetadataKey<Vec<String>> = MetadataKey::new("allowed_outbound_hosts");
 
+/// The domain under which the components of an app are addressed by each other,
+/// as `<component-id>.spin.internal`.
+pub const SERVICE_CHAINING_DOMAIN_SUFFIX: &str = ".spin.internal";
+
+/// Returns the ID of the component a host addresses, if it is in the service
+/// chaining domain.
+pub fn service_chaining_target(host: &str) -> Option<String> {
+    let host = host.to_ascii_lowercase();
+    let component_id = host.strip_suffix(SERVICE_CHAINING_DOMAIN_SUFFIX)?;
+    if component_id.is_empty() || component_id.contains('.') {
+        return None;
+    }
+    Some(component_id.to_owned())
+}
+
 /// Checks address against allowed hosts
 ///
 /// Emits several warnings
@@ -81,6 +98,15 @@ impl AllowedHostConfig {
         &self.port
     }
 
+    /// Whether some URL could be allowed by both this and `other`. Hostnames
+    /// are compared as written, so a name is not taken to overlap a CIDR
+    /// range its addresses may fall in.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.scheme.overlaps(&other.scheme)
+            && self.host.overlaps(&other.host)
+            && self.port.overlaps(&other.port)
+    }
+
     fn allows(&self, url: &OutboundUrl) -> bool {
         self.scheme.allows(&url.scheme)
             && self.host.allows(&url.host)
@@ -138,12 +164,21 @@ impl SchemeConfig {
             SchemeConfig::List(l) => l.iter().any(|s| s.as_str() == scheme),
         }
     }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SchemeConfig::Any, _) | (_, SchemeConfig::Any) => true,
+            (SchemeConfig::List(l), other) => l.iter().any(|s| other.allows(s)),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum HostConfig {
     Any,
     ToSelf,
+    /// Any component of the same app, as `*.spin.internal`
+    AnyComponent,
     List(Vec<String>),
     Cidr(ipnet::IpNet),
 }
@@ -159,6 +194,10 @@ impl HostConfig {
             return Ok(Self::ToSelf);
         }
 
+        if host.strip_prefix('*') == Some(SERVICE_CHAINING_DOMAIN_SUFFIX) {
+            return Ok(Self::AnyComponent);
+        }
+
         if host.starts_with('{') {
             ensure!(host.ends_with('}'));
             bail!("host lists are not yet supported")
@@ -180,6 +219,7 @@ impl HostConfig {
             HostConfig::Any => true,
             HostConfig::List(l) => l.iter().any(|h| h.as_str() == host),
             HostConfig::ToSelf => false,
+            HostConfig::AnyComponent => service_chaining_target(host).is_some(),
             HostConfig::Cidr(c) => {
                 let Ok(ip) = host.parse::<ipnet::IpNet>() else {
                     return false;
@@ -192,6 +232,46 @@ impl HostConfig {
     fn allows_relative(&self) -> bool {
         matches!(self, Self::Any | Self::ToSelf)
     }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        match (self, other) {
+            (HostConfig::Any, _) | (_, HostConfig::Any) => true,
+            (HostConfig::ToSelf, HostConfig::ToSelf) => true,
+            (HostConfig::AnyComponent, HostConfig::AnyComponent) => true,
+            (HostConfig::AnyComponent, HostConfig::List(l))
+            | (HostConfig::List(l), HostConfig::AnyComponent) => {
+                l.iter().any(|h| service_chaining_target(h).is_some())
+            }
+            (HostConfig::List(a), HostConfig::List(b)) => a
+                .iter()
+                .any(|a| b.iter().any(|b| host_patterns_overlap(a, b))),
+            (HostConfig::List(l), HostConfig::Cidr(c))
+            | (HostConfig::Cidr(c), HostConfig::List(l)) => l.iter().any(|h| {
+                h.trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<std::net::IpAddr>()
+                    .is_ok_and(|ip| c.contains(&ip))
+            }),
+            (HostConfig::Cidr(a), HostConfig::Cidr(b)) => {
+                a.contains(&b.network()) || b.contains(&a.network())
+            }
+            _ => false,
+        }
+    }
+}
+
+// Whether two hosts, either of which may be a `*.`-prefixed wildcard
+// subdomain, could match the same name.
+fn host_patterns_overlap(a: &str, b: &str) -> bool {
+    let (a, b) = (a.to_ascii_lowercase(), b.to_ascii_lowercase());
+    match (a.strip_prefix('*'), b.strip_prefix('*')) {
+        (Some(a_suffix), Some(b_suffix)) => {
+            a_suffix.ends_with(b_suffix) || b_suffix.ends_with(a_suffix)
+        }
+        (Some(suffix), None) => b.ends_with(suffix),
+        (None, Some(suffix)) => a.ends_with(suffix),
+        (None, None) => a == b,
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -233,6 +313,15 @@ impl PortConfig {
             }
         }
     }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PortConfig::Any, _) | (_, PortConfig::Any) => true,
+            (PortConfig::List(a), PortConfig::List(b)) => {
+                a.iter().any(|a| b.iter().any(|b| a.overlaps(b)))
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -263,6 +352,17 @@ impl IndividualPortConfig {
             IndividualPortConfig::Range(r) => r.contains(&port),
         }
     }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        match (self, other) {
+            (IndividualPortConfig::Port(p), other) | (other, IndividualPortConfig::Port(p)) => {
+                other.allows(*p)
+            }
+            (IndividualPortConfig::Range(a), IndividualPortConfig::Range(b)) => {
+                a.start < b.end && b.start < a.end
+            }
+        }
+    }
 }
 
 fn well_known_port(scheme: &str) -> Option<u16> {
@@ -270,6 +370,8 @@ fn well_known_port(scheme: &str) -> Option<u16> {
         "postgres" => Some(5432),
         "mysql" => Some(3306),
         "redis" => Some(6379),
+        "mqtt" => Some(1883),
+        "mqtts" => Some(8883),
         "http" => Some(80),
         "https" => Some(443),
         _ => None,
@@ -488,6 +590,33 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_allowed_hosts_accepts_components() {
+        assert_eq!(
+            AllowedHostConfig::new(
+                SchemeConfig::new("http"),
+                HostConfig::AnyComponent,
+                PortConfig::new(80)
+            ),
+            AllowedHostConfig::parse("http://*.spin.internal").unwrap()
+        );
+        let allowed =
+            AllowedHostsConfig::parse(&["http://*.spin.internal", "http://api.spin.internal"])
+                .unwrap();
+        assert!(
+            allowed.allows(&OutboundUrl::parse("http://backend.spin.internal/", "http").unwrap())
+        );
+        assert!(!allowed.allows(&OutboundUrl::parse("http://a.b.spin.internal/", "http").unwrap()));
+        assert!(!allowed
+            .allows(&OutboundUrl::parse("https://backend.spin.internal/", "https").unwrap()));
+        assert_eq!(
+            service_chaining_target("API.spin.internal").as_deref(),
+            Some("api")
+        );
+        assert_eq!(service_chaining_target("spin.internal"), None);
+        assert_eq!(service_chaining_target("api.example.com"), None);
+    }
+
     #[test]
     fn test_allowed_hosts_accepts_localhost_addresses() {
         assert!(AllowedHostConfig::parse("localhost").is_err());
@@ -600,6 +729,54 @@ mod test {
         assert!(allowed.allows(&OutboundUrl::parse("example.com:8383", "http").unwrap()));
     }
 
+    #[test]
+    fn test_allowed_hosts_overlap() {
+        let overlaps = |a: &str, b: &str| {
+            let (a, b) = (
+                AllowedHostConfig::parse(a).unwrap(),
+                AllowedHostConfig::parse(b).unwrap(),
+            );
+            assert_eq!(a.overlaps(&b), b.overlaps(&a), "{a} and {b} disagree");
+            a.overlaps(&b)
+        };
+        for allowed in [
+            "https://*:*",
+            "*://*:443",
+            "http://*",
+            "redis://db.internal",
+        ] {
+            assert!(overlaps(allowed, "*://*:*"), "{allowed}");
+        }
+        assert!(overlaps("https://api.example.com", "*://*.example.com:443"));
+        assert!(overlaps("*://*.example.com:*", "http://*.api.example.com"));
+        assert!(overlaps("*://10.1.2.3:5432", "*://10.0.0.0/8:*"));
+        assert!(overlaps("*://10.1.0.0/16:*", "*://10.0.0.0/8:*"));
+        assert!(overlaps(
+            "http://*.spin.internal",
+            "http://api.spin.internal"
+        ));
+        assert!(overlaps(
+            "http://example.com:8000..9000",
+            "http://example.com:8080"
+        ));
+
+        assert!(!overlaps(
+            "https://api.example.com",
+            "http://api.example.com"
+        ));
+        assert!(!overlaps(
+            "https://api.example.com",
+            "https://api.example.org"
+        ));
+        assert!(!overlaps("https://example.com", "*://*.example.com:*"));
+        assert!(!overlaps("*://db.internal:5432", "*://10.0.0.0/8:*"));
+        assert!(!overlaps(
+            "http://example.com:8000..9000",
+            "http://example.com:9000"
+        ));
+        assert!(!overlaps("http://self", "http://*.spin.internal"));
+    }
+
     #[test]
     fn test_hash_char_in_db_password() {
         let allowed = AllowedHostsConfig::parse(&["mysql://xyz.com"]).unwrap();
@@ -1,12 +1,16 @@
+use std::collections::HashMap;
+
 use anyhow::{anyhow, Context, Result};
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
 use spin_app::DynamicHostComponent;
 use spin_core::{async_trait, wasmtime::component::Resource, HostComponent};
+use spin_outbound_networking::dns::Resolver;
 use spin_world::v1::postgres as v1;
 use spin_world::v1::rdbms_types as v1_types;
-use spin_world::v2::postgres::{self as v2, Connection};
+use spin_world::v2::postgres as v2;
 use spin_world::v2::rdbms_types::{Column, DbDataType, DbValue, ParameterValue, RowSet};
+use spin_world::v2_1::postgres::{self as v2_1, Connection, Transaction};
 use tokio_postgres::{
     config::SslMode,
     types::{ToSql, Type},
@@ -18,24 +22,130 @@ use tokio_postgres::{
 pub struct OutboundPg {
     allowed_hosts: spin_outbound_networking::AllowedHostsConfig,
     pub connections: table::Table<Client>,
+    transactions: table::Table<PgTransaction>,
+    // Transaction state of connections with a transaction, by connection rep
+    transaction_states: HashMap<u32, TransactionState>,
+    // For attributing outbound calls in metrics
+    component_id: String,
+    resolver: Resolver,
+}
+
+struct PgTransaction {
+    connection: u32,
+    finished: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TransactionState {
+    Open,
+    // Dropped without being committed or rolled back; rolled back before the
+    // connection is next used.
+    Abandoned,
 }
 
 impl OutboundPg {
-    async fn open_connection(&mut self, address: &str) -> Result<Resource<Connection>, v2::Error> {
+    /// Creates a component which resolves database hosts with the given
+    /// resolver.
+    pub fn new(resolver: Resolver) -> Self {
+        Self {
+            resolver,
+            ..Default::default()
+        }
+    }
+
+    async fn open_connection(
+        &mut self,
+        address: &str,
+    ) -> Result<Resource<Connection>, v2_1::Error> {
         self.connections
             .push(
-                build_client(address)
+                build_client(address, &self.resolver)
                     .await
-                    .map_err(|e| v2::Error::ConnectionFailed(format!("{e:?}")))?,
+                    .map_err(|e| v2_1::Error::ConnectionFailed(format!("{e:?}")))?,
             )
-            .map_err(|_| v2::Error::ConnectionFailed("too many connections".into()))
+            .map_err(|_| v2_1::Error::ConnectionFailed("too many connections".into()))
             .map(Resource::new_own)
     }
 
-    async fn get_client(&mut self, connection: Resource<Connection>) -> Result<&Client, v2::Error> {
+    async fn get_client(
+        &mut self,
+        connection: Resource<Connection>,
+    ) -> Result<&Client, v2_1::Error> {
+        let rep = connection.rep();
+        self.rollback_abandoned(rep).await?;
+        if self.transaction_states.contains_key(&rep) {
+            return Err(v2_1::Error::Other(
+                "connection has an open transaction; statements must be run through it".into(),
+            ));
+        }
+        self.client(rep)
+    }
+
+    // Every statement is run through this, so it also records metrics.
+    fn client(&self, rep: u32) -> Result<&Client, v2_1::Error> {
+        spin_metrics::record_outbound_call(&self.component_id, spin_metrics::outbound::POSTGRES);
         self.connections
-            .get(connection.rep())
-            .ok_or_else(|| v2::Error::ConnectionFailed("no connection found".into()))
+            .get(rep)
+            .ok_or_else(|| v2_1::Error::ConnectionFailed("no connection found".into()))
+    }
+
+    // Returns the client for an open transaction.
+    fn transaction_client(
+        &self,
+        transaction: &Resource<Transaction>,
+    ) -> Result<&Client, v2_1::Error> {
+        let transaction = self
+            .transactions
+            .get(transaction.rep())
+            .ok_or_else(|| v2_1::Error::Other("no transaction found".into()))?;
+        if transaction.finished {
+            return Err(v2_1::Error::Other(
+                "transaction has already been committed or rolled back".into(),
+            ));
+        }
+        self.client(transaction.connection)
+    }
+
+    // Rolls back any abandoned transaction on the given connection.
+    async fn rollback_abandoned(&mut self, rep: u32) -> Result<(), v2_1::Error> {
+        if self.transaction_states.get(&rep) == Some(&TransactionState::Abandoned) {
+            self.client(rep)?
+                .batch_execute("ROLLBACK")
+                .await
+                .map_err(|e| v2_1::Error::QueryFailed(format!("{:?}", e)))?;
+            self.transaction_states.remove(&rep);
+        }
+        Ok(())
+    }
+
+    // Runs `statement` (which must be a transaction control statement taking
+    // no parameters) in the given transaction.
+    async fn transaction_control(
+        &mut self,
+        transaction: &Resource<Transaction>,
+        statement: &str,
+    ) -> Result<(), v2_1::Error> {
+        self.transaction_client(transaction)?
+            .batch_execute(statement)
+            .await
+            .map_err(|e| v2_1::Error::QueryFailed(format!("{:?}", e)))
+    }
+
+    // Ends the given transaction with `COMMIT` or `ROLLBACK`.
+    async fn finish_transaction(
+        &mut self,
+        transaction: Resource<Transaction>,
+        statement: &str,
+    ) -> Result<(), v2_1::Error> {
+        let result = self.transaction_control(&transaction, statement).await;
+        if let Some(transaction) = self.transactions.get_mut(transaction.rep()) {
+            if !transaction.finished {
+                // Postgres ends the transaction even if the statement fails
+                transaction.finished = true;
+                self.transaction_states.remove(&transaction.connection);
+            }
+        }
+        result
     }
 
     fn is_address_allowed(&self, address: &str) -> bool {
@@ -75,11 +185,12 @@ impl HostComponent for OutboundPg {
         get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
     ) -> anyhow::Result<()> {
         v1::add_to_linker(linker, get)?;
-        v2::add_to_linker(linker, get)
+        v2::add_to_linker(linker, get)?;
+        v2_1::add_to_linker(linker, get)
     }
 
     fn build_data(&self) -> Self::Data {
-        Default::default()
+        Self::new(self.resolver.clone())
     }
 }
 
@@ -94,18 +205,19 @@ impl DynamicHostComponent for OutboundPg {
             .unwrap_or_default();
         data.allowed_hosts = spin_outbound_networking::AllowedHostsConfig::parse(&hosts)
             .context("`allowed_outbound_hosts` contained an invalid url")?;
+        data.component_id = component.id().to_owned();
         Ok(())
     }
 }
 
 #[async_trait]
-impl v2::Host for OutboundPg {}
+impl v2_1::Host for OutboundPg {}
 
 #[async_trait]
-impl v2::HostConnection for OutboundPg {
-    async fn open(&mut self, address: String) -> Result<Result<Resource<Connection>, v2::Error>> {
+impl v2_1::HostConnection for OutboundPg {
+    async fn open(&mut self, address: String) -> Result<Result<Resource<Connection>, v2_1::Error>> {
         if !self.is_address_allowed(&address) {
-            return Ok(Err(v2::Error::ConnectionFailed(format!(
+            return Ok(Err(v2_1::Error::ConnectionFailed(format!(
                 "address {address} is not permitted"
             ))));
         }
@@ -117,22 +229,10 @@ impl v2::HostConnection for OutboundPg {
         connection: Resource<Connection>,
         statement: String,
         params: Vec<ParameterValue>,
-    ) -> Result<Result<u64, v2::Error>> {
+    ) -> Result<Result<u64, v2_1::Error>> {
         Ok(async {
-            let params: Vec<&(dyn ToSql + Sync)> = params
-                .iter()
-                .map(to_sql_parameter)
-                .collect::<anyhow::Result<Vec<_>>>()
-                .map_err(|e| v2::Error::ValueConversionFailed(format!("{:?}", e)))?;
-
-            let nrow = self
-                .get_client(connection)
-                .await?
-                .execute(&statement, params.as_slice())
-                .await
-                .map_err(|e| v2::Error::QueryFailed(format!("{:?}", e)))?;
-
-            Ok(nrow)
+            let client = self.get_client(connection).await?;
+            execute_on(client, &statement, params).await
         }
         .await)
     }
@@ -142,46 +242,231 @@ impl v2::HostConnection for OutboundPg {
         connection: Resource<Connection>,
         statement: String,
         params: Vec<ParameterValue>,
-    ) -> Result<Result<RowSet, v2::Error>> {
+    ) -> Result<Result<RowSet, v2_1::Error>> {
         Ok(async {
-            let params: Vec<&(dyn ToSql + Sync)> = params
-                .iter()
-                .map(to_sql_parameter)
-                .collect::<anyhow::Result<Vec<_>>>()
-                .map_err(|e| v2::Error::BadParameter(format!("{:?}", e)))?;
-
-            let results = self
-                .get_client(connection)
+            let client = self.get_client(connection).await?;
+            query_on(client, &statement, params).await
+        }
+        .await)
+    }
+
+    async fn begin_transaction(
+        &mut self,
+        connection: Resource<Connection>,
+    ) -> Result<Result<Resource<Transaction>, v2_1::Error>> {
+        Ok(async {
+            let rep = connection.rep();
+            self.get_client(connection)
                 .await?
-                .query(&statement, params.as_slice())
+                .batch_execute("BEGIN")
                 .await
-                .map_err(|e| v2::Error::QueryFailed(format!("{:?}", e)))?;
+                .map_err(|e| v2_1::Error::QueryFailed(format!("{:?}", e)))?;
+            self.transaction_states.insert(rep, TransactionState::Open);
+            self.transactions
+                .push(PgTransaction {
+                    connection: rep,
+                    finished: false,
+                })
+                .map_err(|_| v2_1::Error::Other("too many transactions".into()))
+                .map(Resource::new_own)
+        }
+        .await)
+    }
 
-            if results.is_empty() {
-                return Ok(RowSet {
-                    columns: vec![],
-                    rows: vec![],
-                });
-            }
+    fn drop(&mut self, connection: Resource<Connection>) -> anyhow::Result<()> {
+        // Closing the connection rolls back any open transaction
+        self.connections.remove(connection.rep());
+        self.transaction_states.remove(&connection.rep());
+        Ok(())
+    }
+}
 
-            let columns = infer_columns(&results[0]);
-            let rows = results
-                .iter()
-                .map(convert_row)
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| v2::Error::QueryFailed(format!("{:?}", e)))?;
+#[async_trait]
+impl v2_1::HostTransaction for OutboundPg {
+    async fn query(
+        &mut self,
+        transaction: Resource<Transaction>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<Result<RowSet, v2_1::Error>> {
+        Ok(async {
+            let client = self.transaction_client(&transaction)?;
+            query_on(client, &statement, params).await
+        }
+        .await)
+    }
 
-            Ok(RowSet { columns, rows })
+    async fn execute(
+        &mut self,
+        transaction: Resource<Transaction>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<Result<u64, v2_1::Error>> {
+        Ok(async {
+            let client = self.transaction_client(&transaction)?;
+            execute_on(client, &statement, params).await
         }
         .await)
     }
 
-    fn drop(&mut self, connection: Resource<Connection>) -> anyhow::Result<()> {
-        self.connections.remove(connection.rep());
+    async fn savepoint(
+        &mut self,
+        transaction: Resource<Transaction>,
+        name: String,
+    ) -> Result<Result<(), v2_1::Error>> {
+        let statement = format!("SAVEPOINT {}", quote_identifier(&name));
+        Ok(self.transaction_control(&transaction, &statement).await)
+    }
+
+    async fn rollback_to_savepoint(
+        &mut self,
+        transaction: Resource<Transaction>,
+        name: String,
+    ) -> Result<Result<(), v2_1::Error>> {
+        let statement = format!("ROLLBACK TO SAVEPOINT {}", quote_identifier(&name));
+        Ok(self.transaction_control(&transaction, &statement).await)
+    }
+
+    async fn release_savepoint(
+        &mut self,
+        transaction: Resource<Transaction>,
+        name: String,
+    ) -> Result<Result<(), v2_1::Error>> {
+        let statement = format!("RELEASE SAVEPOINT {}", quote_identifier(&name));
+        Ok(self.transaction_control(&transaction, &statement).await)
+    }
+
+    async fn commit(
+        &mut self,
+        transaction: Resource<Transaction>,
+    ) -> Result<Result<(), v2_1::Error>> {
+        Ok(self.finish_transaction(transaction, "COMMIT").await)
+    }
+
+    async fn rollback(
+        &mut self,
+        transaction: Resource<Transaction>,
+    ) -> Result<Result<(), v2_1::Error>> {
+        Ok(self.finish_transaction(transaction, "ROLLBACK").await)
+    }
+
+    fn drop(&mut self, transaction: Resource<Transaction>) -> anyhow::Result<()> {
+        if let Some(transaction) = self.transactions.remove(transaction.rep()) {
+            // Rolling back needs an async call, so is done before the connection is next used
+            if !transaction.finished && self.connections.get(transaction.connection).is_some() {
+                self.transaction_states
+                    .insert(transaction.connection, TransactionState::Abandoned);
+            }
+        }
         Ok(())
     }
 }
 
+#[async_trait]
+impl v2::Host for OutboundPg {}
+
+#[async_trait]
+impl v2::HostConnection for OutboundPg {
+    async fn open(
+        &mut self,
+        address: String,
+    ) -> Result<Result<Resource<v2::Connection>, v2::Error>> {
+        let result = <Self as v2_1::HostConnection>::open(self, address).await?;
+        Ok(result.map(|connection| Resource::new_own(connection.rep())))
+    }
+
+    async fn execute(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<Result<u64, v2::Error>> {
+        let connection = Resource::new_borrow(connection.rep());
+        <Self as v2_1::HostConnection>::execute(self, connection, statement, params).await
+    }
+
+    async fn query(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        statement: String,
+        params: Vec<ParameterValue>,
+    ) -> Result<Result<RowSet, v2::Error>> {
+        let connection = Resource::new_borrow(connection.rep());
+        <Self as v2_1::HostConnection>::query(self, connection, statement, params).await
+    }
+
+    fn drop(&mut self, connection: Resource<v2::Connection>) -> anyhow::Result<()> {
+        <Self as v2_1::HostConnection>::drop(self, Resource::new_own(connection.rep()))
+    }
+}
+
+#[tracing::instrument(
+    name = "spin_outbound_pg.execute",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql", db.statement = statement)
+)]
+async fn execute_on(
+    client: &Client,
+    statement: &str,
+    params: Vec<ParameterValue>,
+) -> Result<u64, v2_1::Error> {
+    let params: Vec<&(dyn ToSql + Sync)> = params
+        .iter()
+        .map(to_sql_parameter)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| v2_1::Error::ValueConversionFailed(format!("{:?}", e)))?;
+
+    let nrow = client
+        .execute(statement, params.as_slice())
+        .await
+        .map_err(|e| v2_1::Error::QueryFailed(format!("{:?}", e)))?;
+
+    Ok(nrow)
+}
+
+#[tracing::instrument(
+    name = "spin_outbound_pg.query",
+    skip_all,
+    fields(otel.kind = "client", db.system = "postgresql", db.statement = statement)
+)]
+async fn query_on(
+    client: &Client,
+    statement: &str,
+    params: Vec<ParameterValue>,
+) -> Result<RowSet, v2_1::Error> {
+    let params: Vec<&(dyn ToSql + Sync)> = params
+        .iter()
+        .map(to_sql_parameter)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| v2_1::Error::BadParameter(format!("{:?}", e)))?;
+
+    let results = client
+        .query(statement, params.as_slice())
+        .await
+        .map_err(|e| v2_1::Error::QueryFailed(format!("{:?}", e)))?;
+
+    if results.is_empty() {
+        return Ok(RowSet {
+            columns: vec![],
+            rows: vec![],
+        });
+    }
+
+    let columns = infer_columns(&results[0]);
+    let rows = results
+        .iter()
+        .map(convert_row)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| v2_1::Error::QueryFailed(format!("{:?}", e)))?;
+
+    Ok(RowSet { columns, rows })
+}
+
+// Quotes a savepoint name so it can't be used to inject SQL.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
 fn to_sql_parameter(value: &ParameterValue) -> anyhow::Result<&(dyn ToSql + Sync)> {
     match value {
         ParameterValue::Boolean(v) => Ok(v),
@@ -312,16 +597,69 @@ fn convert_entry(row: &Row, index: usize) -> Result<DbValue, tokio_postgres::Err
     Ok(value)
 }
 
-async fn build_client(address: &str) -> anyhow::Result<Client> {
+async fn build_client(address: &str, resolver: &Resolver) -> anyhow::Result<Client> {
     let config = address.parse::<tokio_postgres::Config>()?;
+    let configs = if resolver.is_custom() {
+        pin_host_addrs(config, resolver).await?
+    } else {
+        vec![config]
+    };
 
     tracing::debug!("Build new connection: {}", address);
 
-    if config.get_ssl_mode() == SslMode::Disable {
-        connect(config).await
-    } else {
-        connect_tls(config).await
+    let mut last_err = None;
+    for config in configs {
+        let result = if config.get_ssl_mode() == SslMode::Disable {
+            connect(config).await
+        } else {
+            connect_tls(config).await
+        };
+        match result {
+            Ok(client) => return Ok(client),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.expect("at least one config is tried"))
+}
+
+// Returns configs which connect to the resolver's addresses for the hosts, to
+// be tried in turn: the first uses each host's first address, the second each
+// host's second address, if it has one, and so on. The hosts are still used
+// to verify TLS certificates.
+async fn pin_host_addrs(
+    config: tokio_postgres::Config,
+    resolver: &Resolver,
+) -> Result<Vec<tokio_postgres::Config>> {
+    if !config.get_hostaddrs().is_empty() {
+        return Ok(vec![config]);
+    }
+    let hosts = config
+        .get_hosts()
+        .iter()
+        .map(|host| match host {
+            tokio_postgres::config::Host::Tcp(host) => Some(host.clone()),
+            #[cfg(unix)]
+            tokio_postgres::config::Host::Unix(_) => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    let Some(hosts) = hosts.filter(|hosts| !hosts.is_empty()) else {
+        return Ok(vec![config]);
+    };
+    let mut host_addrs = vec![];
+    for host in hosts {
+        host_addrs.push(resolver.lookup(&host).await?);
     }
+    let attempts = host_addrs.iter().map(Vec::len).max().unwrap_or_default();
+    Ok((0..attempts)
+        .map(|attempt| {
+            let mut config = config.clone();
+            // Each address pairs with the host in the same position
+            for addrs in &host_addrs {
+                config.hostaddr(addrs[attempt.min(addrs.len() - 1)]);
+            }
+            config
+        })
+        .collect())
 }
 
 async fn connect(config: tokio_postgres::Config) -> anyhow::Result<Client> {
@@ -395,7 +733,7 @@ impl std::fmt::Debug for PgNull {
     }
 }
 
-/// Delegate a function call to the v2::HostConnection implementation
+/// Delegate a function call to the v2_1::HostConnection implementation
 macro_rules! delegate {
     ($self:ident.$name:ident($address:expr, $($arg:expr),*)) => {{
         if !$self.is_address_allowed(&$address) {
@@ -407,7 +745,7 @@ macro_rules! delegate {
             Ok(c) => c,
             Err(e) => return Ok(Err(e.into())),
         };
-        Ok(<Self as v2::HostConnection>::$name($self, connection, $($arg),*)
+        Ok(<Self as v2_1::HostConnection>::$name($self, connection, $($arg),*)
             .await?
             .map_err(|e| e.into()))
     }};
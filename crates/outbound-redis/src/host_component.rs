@@ -1,10 +1,22 @@
 use anyhow::Context;
 use spin_app::DynamicHostComponent;
 use spin_core::HostComponent;
+use spin_outbound_networking::dns::Resolver;
 
 use crate::OutboundRedis;
 
-pub struct OutboundRedisComponent;
+#[derive(Default)]
+pub struct OutboundRedisComponent {
+    resolver: Resolver,
+}
+
+impl OutboundRedisComponent {
+    /// Creates a component which resolves Redis hosts with the given
+    /// resolver.
+    pub fn new(resolver: Resolver) -> Self {
+        Self { resolver }
+    }
+}
 
 impl HostComponent for OutboundRedisComponent {
     type Data = OutboundRedis;
@@ -13,11 +25,15 @@ impl HostComponent for OutboundRedisComponent {
         get: impl Fn(&mut spin_core::Data<T>) -> &mut Self::Data + Send + Sync + Copy + 'static,
     ) -> anyhow::Result<()> {
         spin_world::v1::redis::add_to_linker(linker, get)?;
-        spin_world::v2::redis::add_to_linker(linker, get)
+        spin_world::v2::redis::add_to_linker(linker, get)?;
+        spin_world::v2_1::redis::add_to_linker(linker, get)
     }
 
     fn build_data(&self) -> Self::Data {
-        Default::default()
+        OutboundRedis {
+            resolver: self.resolver.clone(),
+            ..Default::default()
+        }
     }
 }
 
@@ -32,6 +48,7 @@ impl DynamicHostComponent for OutboundRedisComponent {
             .unwrap_or_default();
         data.allowed_hosts = spin_outbound_networking::AllowedHostsConfig::parse(&hosts)
             .context("`allowed_outbound_hosts` contained an invalid url")?;
+        data.component_id = component.id().to_owned();
         Ok(())
     }
 }
@@ -1,12 +1,22 @@
 mod host_component;
 
+use std::time::Duration;
+
 use anyhow::Result;
-use redis::{aio::Connection, AsyncCommands, FromRedisValue, Value};
+use futures::StreamExt;
+use redis::{
+    aio::{Connection, PubSub},
+    AsyncCommands, ConnectionInfo, FromRedisValue, IntoConnectionInfo, Value,
+};
 use spin_core::{async_trait, wasmtime::component::Resource};
+use spin_outbound_networking::dns::Resolver;
 use spin_world::v1::redis as v1;
-use spin_world::v2::redis::{
-    self as v2, Connection as RedisConnection, Error, RedisParameter, RedisResult,
+use spin_world::v2::redis as v2;
+use spin_world::v2_1::redis::{
+    self as v2_1, Connection as RedisConnection, Error, Message, RedisCommand, RedisParameter,
+    RedisResult, StreamEntry, Subscription as RedisSubscription,
 };
+use tokio::{sync::mpsc, task::JoinHandle};
 
 pub use host_component::OutboundRedisComponent;
 
@@ -30,9 +40,96 @@ impl FromRedisValue for RedisResults {
     }
 }
 
+struct StreamEntries(Vec<StreamEntry>);
+
+impl FromRedisValue for StreamEntries {
+    // An XREADGROUP reply is nil if no entries arrived in time, or else a list
+    // of [stream name, [[entry ID, [field, value, ...]], ...]] for each stream.
+    fn from_redis_value(value: &Value) -> redis::RedisResult<Self> {
+        fn type_error() -> redis::RedisError {
+            (redis::ErrorKind::TypeError, "unexpected stream reply").into()
+        }
+        let streams = match value {
+            Value::Nil => return Ok(StreamEntries(vec![])),
+            Value::Bulk(streams) => streams,
+            _ => return Err(type_error()),
+        };
+        let mut entries = vec![];
+        for stream in streams {
+            let Value::Bulk(stream) = stream else {
+                return Err(type_error());
+            };
+            let Some(Value::Bulk(stream_entries)) = stream.get(1) else {
+                return Err(type_error());
+            };
+            for entry in stream_entries {
+                let Value::Bulk(entry) = entry else {
+                    return Err(type_error());
+                };
+                let (Some(id), Some(fields)) = (entry.first(), entry.get(1)) else {
+                    return Err(type_error());
+                };
+                let fields = match fields {
+                    // The entry was deleted after it was delivered
+                    Value::Nil => vec![],
+                    Value::Bulk(fields) => fields
+                        .chunks(2)
+                        .map(|pair| match pair {
+                            [name, value] => Ok((
+                                String::from_redis_value(name)?,
+                                Vec::<u8>::from_redis_value(value)?,
+                            )),
+                            _ => Err(type_error()),
+                        })
+                        .collect::<redis::RedisResult<_>>()?,
+                    _ => return Err(type_error()),
+                };
+                entries.push(StreamEntry {
+                    id: String::from_redis_value(id)?,
+                    fields,
+                });
+            }
+        }
+        Ok(StreamEntries(entries))
+    }
+}
+
+/// The longest a single `next-message` call waits for a message.
+const MAX_MESSAGE_WAIT: Duration = Duration::from_secs(30);
+
+/// The number of messages a subscription buffers before it stops reading
+/// from its connection.
+const SUBSCRIPTION_BUFFER: usize = 64;
+
+struct OpenConnection {
+    connection: Connection,
+    // Subscriptions connect to the same instance, with connections of their own
+    info: ConnectionInfo,
+}
+
+/// A subscription's messages, received by a task which owns its connection.
+///
+/// Subscriptions are held in the component's store like connections, so the
+/// task is aborted and its connection closed when the guest drops the
+/// subscription or, at the latest, when the invocation ends.
+struct OpenSubscription {
+    messages: mpsc::Receiver<Message>,
+    receiver: JoinHandle<()>,
+}
+
+impl Drop for OpenSubscription {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
 pub struct OutboundRedis {
     allowed_hosts: spin_outbound_networking::AllowedHostsConfig,
-    connections: table::Table<Connection>,
+    connections: table::Table<OpenConnection>,
+    subscriptions: table::Table<OpenSubscription>,
+    // For attributing outbound calls in metrics
+    component_id: String,
+    resolver: Resolver,
 }
 
 impl Default for OutboundRedis {
@@ -40,6 +137,9 @@ impl Default for OutboundRedis {
         Self {
             allowed_hosts: Default::default(),
             connections: table::Table::new(1024),
+            subscriptions: table::Table::new(256),
+            component_id: Default::default(),
+            resolver: Default::default(),
         }
     }
 }
@@ -54,13 +154,19 @@ impl OutboundRedis {
         address: String,
     ) -> Result<Result<Resource<RedisConnection>, Error>> {
         Ok(async {
-            let conn = redis::Client::open(address.as_str())
-                .map_err(|_| Error::InvalidAddress)?
-                .get_async_connection()
+            let info = address
+                .as_str()
+                .into_connection_info()
+                .map_err(|_| Error::InvalidAddress)?;
+            let connection = self
+                .resolver
+                .connect_redis(info.clone(), |client| async move {
+                    client.get_async_connection().await
+                })
                 .await
                 .map_err(other_error)?;
             self.connections
-                .push(conn)
+                .push(OpenConnection { connection, info })
                 .map(Resource::new_own)
                 .map_err(|_| Error::TooManyConnections)
         }
@@ -68,10 +174,10 @@ impl OutboundRedis {
     }
 }
 
-impl v2::Host for OutboundRedis {}
+impl v2_1::Host for OutboundRedis {}
 
 #[async_trait]
-impl v2::HostConnection for OutboundRedis {
+impl v2_1::HostConnection for OutboundRedis {
     async fn open(&mut self, address: String) -> Result<Result<Resource<RedisConnection>, Error>> {
         if !self.is_address_allowed(&address) {
             return Ok(Err(Error::InvalidAddress));
@@ -80,6 +186,11 @@ impl v2::HostConnection for OutboundRedis {
         self.establish_connection(address).await
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.publish",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn publish(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -96,6 +207,51 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.subscribe",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
+    async fn subscribe(
+        &mut self,
+        connection: Resource<RedisConnection>,
+        channels: Vec<String>,
+    ) -> Result<Result<Resource<RedisSubscription>, Error>> {
+        Ok(async {
+            spin_metrics::record_outbound_call(&self.component_id, spin_metrics::outbound::REDIS);
+            let info = self
+                .connections
+                .get(connection.rep())
+                .ok_or(Error::Other(
+                    "could not find connection for resource".into(),
+                ))?
+                .info
+                .clone();
+            let mut pubsub = self
+                .resolver
+                .connect_redis(info, |client| async move {
+                    Ok(client.get_async_connection().await?.into_pubsub())
+                })
+                .await
+                .map_err(other_error)?;
+            for channel in &channels {
+                pubsub.subscribe(channel).await.map_err(other_error)?;
+            }
+            let (sender, messages) = mpsc::channel(SUBSCRIPTION_BUFFER);
+            let receiver = tokio::spawn(receive_messages(pubsub, sender));
+            self.subscriptions
+                .push(OpenSubscription { messages, receiver })
+                .map(Resource::new_own)
+                .map_err(|_| Error::TooManyConnections)
+        }
+        .await)
+    }
+
+    #[tracing::instrument(
+        name = "spin_outbound_redis.get",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn get(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -109,6 +265,11 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.set",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn set(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -123,6 +284,11 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.incr",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn incr(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -136,6 +302,11 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.del",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn del(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -149,6 +320,11 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.sadd",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn sadd(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -169,6 +345,11 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.smembers",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn smembers(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -182,6 +363,11 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.srem",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
     async fn srem(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -196,6 +382,11 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.execute",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis", db.operation = command.as_str())
+    )]
     async fn execute(
         &mut self,
         connection: Resource<RedisConnection>,
@@ -204,17 +395,8 @@ impl v2::HostConnection for OutboundRedis {
     ) -> Result<Result<Vec<RedisResult>, Error>> {
         Ok(async {
             let conn = self.get_conn(connection).await?;
-            let mut cmd = redis::cmd(&command);
-            arguments.iter().for_each(|value| match value {
-                RedisParameter::Int64(v) => {
-                    cmd.arg(v);
-                }
-                RedisParameter::Binary(v) => {
-                    cmd.arg(v);
-                }
-            });
-
-            cmd.query_async::<_, RedisResults>(conn)
+            build_cmd(&command, &arguments)
+                .query_async::<_, RedisResults>(conn)
                 .await
                 .map(|values| values.0)
                 .map_err(other_error)
@@ -222,12 +404,329 @@ impl v2::HostConnection for OutboundRedis {
         .await)
     }
 
+    #[tracing::instrument(
+        name = "spin_outbound_redis.pipeline",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis", db.operation = "pipeline")
+    )]
+    async fn pipeline(
+        &mut self,
+        connection: Resource<RedisConnection>,
+        commands: Vec<RedisCommand>,
+        atomic: bool,
+    ) -> Result<Result<Vec<Vec<RedisResult>>, Error>> {
+        Ok(async {
+            let conn = self.get_conn(connection).await?;
+            let mut pipe = redis::pipe();
+            if atomic {
+                pipe.atomic();
+            }
+            for command in &commands {
+                pipe.add_command(build_cmd(&command.command, &command.arguments));
+            }
+            let values: Vec<Value> = pipe.query_async(conn).await.map_err(other_error)?;
+            values
+                .iter()
+                .map(|value| RedisResults::from_redis_value(value).map(|values| values.0))
+                .collect::<redis::RedisResult<_>>()
+                .map_err(other_error)
+        }
+        .await)
+    }
+
+    #[tracing::instrument(
+        name = "spin_outbound_redis.xadd",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
+    async fn xadd(
+        &mut self,
+        connection: Resource<RedisConnection>,
+        key: String,
+        fields: Vec<(String, Vec<u8>)>,
+    ) -> Result<Result<String, Error>> {
+        Ok(async {
+            let conn = self.get_conn(connection).await?;
+            let mut cmd = redis::cmd("XADD");
+            cmd.arg(&key).arg("*");
+            for (name, value) in &fields {
+                cmd.arg(name).arg(value);
+            }
+            cmd.query_async(conn).await.map_err(other_error)
+        }
+        .await)
+    }
+
+    #[tracing::instrument(
+        name = "spin_outbound_redis.xgroup_create",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
+    async fn xgroup_create(
+        &mut self,
+        connection: Resource<RedisConnection>,
+        key: String,
+        group: String,
+        start_id: String,
+    ) -> Result<Result<(), Error>> {
+        Ok(async {
+            let conn = self.get_conn(connection).await?;
+            redis::cmd("XGROUP")
+                .arg("CREATE")
+                .arg(&key)
+                .arg(&group)
+                .arg(&start_id)
+                .arg("MKSTREAM")
+                .query_async(conn)
+                .await
+                .map_err(other_error)
+        }
+        .await)
+    }
+
+    #[tracing::instrument(
+        name = "spin_outbound_redis.xreadgroup",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
+    async fn xreadgroup(
+        &mut self,
+        connection: Resource<RedisConnection>,
+        key: String,
+        group: String,
+        consumer: String,
+        count: u32,
+        block_ms: Option<u64>,
+    ) -> Result<Result<Vec<StreamEntry>, Error>> {
+        Ok(async {
+            let conn = self.get_conn(connection).await?;
+            let mut cmd = redis::cmd("XREADGROUP");
+            cmd.arg("GROUP").arg(&group).arg(&consumer);
+            cmd.arg("COUNT").arg(count);
+            if let Some(block_ms) = block_ms {
+                cmd.arg("BLOCK").arg(block_ms);
+            }
+            // `>` reads only entries never delivered to the group
+            cmd.arg("STREAMS").arg(&key).arg(">");
+            cmd.query_async::<_, StreamEntries>(conn)
+                .await
+                .map(|entries| entries.0)
+                .map_err(|e| {
+                    if e.kind() == redis::ErrorKind::TypeError {
+                        Error::TypeError
+                    } else {
+                        Error::Other(e.to_string())
+                    }
+                })
+        }
+        .await)
+    }
+
+    #[tracing::instrument(
+        name = "spin_outbound_redis.xack",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
+    async fn xack(
+        &mut self,
+        connection: Resource<RedisConnection>,
+        key: String,
+        group: String,
+        ids: Vec<String>,
+    ) -> Result<Result<u32, Error>> {
+        Ok(async {
+            let conn = self.get_conn(connection).await?;
+            redis::cmd("XACK")
+                .arg(&key)
+                .arg(&group)
+                .arg(&ids)
+                .query_async(conn)
+                .await
+                .map_err(other_error)
+        }
+        .await)
+    }
+
     fn drop(&mut self, connection: Resource<RedisConnection>) -> anyhow::Result<()> {
         self.connections.remove(connection.rep());
         Ok(())
     }
 }
 
+#[async_trait]
+impl v2_1::HostSubscription for OutboundRedis {
+    #[tracing::instrument(
+        name = "spin_outbound_redis.next_message",
+        skip_all,
+        fields(otel.kind = "client", db.system = "redis")
+    )]
+    async fn next_message(
+        &mut self,
+        subscription: Resource<RedisSubscription>,
+        timeout_ms: u64,
+    ) -> Result<Result<Option<Message>, Error>> {
+        Ok(async {
+            let subscription =
+                self.subscriptions
+                    .get_mut(subscription.rep())
+                    .ok_or(Error::Other(
+                        "could not find subscription for resource".into(),
+                    ))?;
+            let wait = Duration::from_millis(timeout_ms).min(MAX_MESSAGE_WAIT);
+            match tokio::time::timeout(wait, subscription.messages.recv()).await {
+                Ok(Some(message)) => Ok(Some(message)),
+                Ok(None) => Err(Error::Other("subscription connection closed".into())),
+                Err(_) => Ok(None),
+            }
+        }
+        .await)
+    }
+
+    fn drop(&mut self, subscription: Resource<RedisSubscription>) -> anyhow::Result<()> {
+        self.subscriptions.remove(subscription.rep());
+        Ok(())
+    }
+}
+
+// Forwards a subscription's messages until its connection closes or the
+// subscription is dropped.
+async fn receive_messages(mut pubsub: PubSub, sender: mpsc::Sender<Message>) {
+    let mut stream = pubsub.on_message();
+    while let Some(msg) = stream.next().await {
+        let message = Message {
+            channel: msg.get_channel_name().to_owned(),
+            payload: msg.get_payload_bytes().to_owned(),
+        };
+        if sender.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+impl v2::Host for OutboundRedis {}
+
+/// Delegate a function call to the v2_1::HostConnection implementation
+macro_rules! delegate_v2_1 {
+    ($self:ident.$name:ident($connection:expr, $($arg:expr),*)) => {{
+        let connection = Resource::new_borrow($connection.rep());
+        Ok(<Self as v2_1::HostConnection>::$name($self, connection, $($arg),*)
+            .await?
+            .map_err(Into::into))
+    }};
+}
+
+#[async_trait]
+impl v2::HostConnection for OutboundRedis {
+    async fn open(
+        &mut self,
+        address: String,
+    ) -> Result<Result<Resource<v2::Connection>, v2::Error>> {
+        let result = <Self as v2_1::HostConnection>::open(self, address).await?;
+        Ok(result
+            .map(|connection| Resource::new_own(connection.rep()))
+            .map_err(Into::into))
+    }
+
+    async fn publish(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        channel: String,
+        payload: Vec<u8>,
+    ) -> Result<Result<(), v2::Error>> {
+        delegate_v2_1!(self.publish(connection, channel, payload))
+    }
+
+    async fn get(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        key: String,
+    ) -> Result<Result<Option<Vec<u8>>, v2::Error>> {
+        delegate_v2_1!(self.get(connection, key))
+    }
+
+    async fn set(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<Result<(), v2::Error>> {
+        delegate_v2_1!(self.set(connection, key, value))
+    }
+
+    async fn incr(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        key: String,
+    ) -> Result<Result<i64, v2::Error>> {
+        delegate_v2_1!(self.incr(connection, key))
+    }
+
+    async fn del(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        keys: Vec<String>,
+    ) -> Result<Result<u32, v2::Error>> {
+        delegate_v2_1!(self.del(connection, keys))
+    }
+
+    async fn sadd(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        key: String,
+        values: Vec<String>,
+    ) -> Result<Result<u32, v2::Error>> {
+        delegate_v2_1!(self.sadd(connection, key, values))
+    }
+
+    async fn smembers(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        key: String,
+    ) -> Result<Result<Vec<String>, v2::Error>> {
+        delegate_v2_1!(self.smembers(connection, key))
+    }
+
+    async fn srem(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        key: String,
+        values: Vec<String>,
+    ) -> Result<Result<u32, v2::Error>> {
+        delegate_v2_1!(self.srem(connection, key, values))
+    }
+
+    async fn execute(
+        &mut self,
+        connection: Resource<v2::Connection>,
+        command: String,
+        arguments: Vec<v2::RedisParameter>,
+    ) -> Result<Result<Vec<v2::RedisResult>, v2::Error>> {
+        delegate_v2_1!(self.execute(
+            connection,
+            command,
+            arguments.into_iter().map(Into::into).collect()
+        ))
+        .map(|r| r.map(|v| v.into_iter().map(Into::into).collect()))
+    }
+
+    fn drop(&mut self, connection: Resource<v2::Connection>) -> anyhow::Result<()> {
+        <Self as v2_1::HostConnection>::drop(self, Resource::new_own(connection.rep()))
+    }
+}
+
+fn build_cmd(command: &str, arguments: &[RedisParameter]) -> redis::Cmd {
+    let mut cmd = redis::cmd(command);
+    arguments.iter().for_each(|value| match value {
+        RedisParameter::Int64(v) => {
+            cmd.arg(v);
+        }
+        RedisParameter::Binary(v) => {
+            cmd.arg(v);
+        }
+    });
+    cmd
+}
+
 fn other_error(e: impl std::fmt::Display) -> Error {
     Error::Other(e.to_string())
 }
@@ -242,7 +741,7 @@ macro_rules! delegate {
             Ok(c) => c,
             Err(_) => return Ok(Err(v1::Error::Error)),
         };
-        Ok(<Self as v2::HostConnection>::$name($self, connection, $($arg),*)
+        Ok(<Self as v2::HostConnection>::$name($self, Resource::new_own(connection.rep()), $($arg),*)
             .await?
             .map_err(|_| v1::Error::Error))
     }};
@@ -326,10 +825,43 @@ impl OutboundRedis {
         &mut self,
         connection: Resource<RedisConnection>,
     ) -> Result<&mut Connection, Error> {
+        spin_metrics::record_outbound_call(&self.component_id, spin_metrics::outbound::REDIS);
         self.connections
             .get_mut(connection.rep())
+            .map(|conn| &mut conn.connection)
             .ok_or(Error::Other(
                 "could not find connection for resource".into(),
             ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stream_entries() {
+        let data = |s: &str| Value::Data(s.as_bytes().to_vec());
+        let reply = Value::Bulk(vec![Value::Bulk(vec![
+            data("events"),
+            Value::Bulk(vec![
+                Value::Bulk(vec![
+                    data("1-0"),
+                    Value::Bulk(vec![data("kind"), data("created")]),
+                ]),
+                Value::Bulk(vec![data("2-0"), Value::Nil]),
+            ]),
+        ])]);
+        let entries = StreamEntries::from_redis_value(&reply).unwrap().0;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "1-0");
+        assert_eq!(entries[0].fields, [("kind".into(), b"created".to_vec())]);
+        assert!(entries[1].fields.is_empty());
+
+        assert!(StreamEntries::from_redis_value(&Value::Nil)
+            .unwrap()
+            .0
+            .is_empty());
+        StreamEntries::from_redis_value(&Value::Int(1)).unwrap_err();
+    }
+}
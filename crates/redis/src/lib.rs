@@ -6,7 +6,7 @@ use std::collections::HashMap;
 
 use anyhow::{anyhow, Context, Result};
 use futures::{future::join_all, StreamExt};
-use redis::{Client, ConnectionLike};
+use redis::{ConnectionLike, IntoConnectionInfo};
 use serde::{de::IgnoredAny, Deserialize, Serialize};
 use spin_app::MetadataKey;
 use spin_core::async_trait;
@@ -26,6 +26,8 @@ pub struct RedisTrigger {
     address: String,
     // Mapping of subscription channels to component IDs
     channel_components: HashMap<String, Vec<String>>,
+    // Mapping of subscription channel patterns to component IDs
+    pattern_components: HashMap<String, Vec<String>>,
 }
 
 /// Redis trigger configuration.
@@ -34,7 +36,9 @@ pub struct RedisTrigger {
 pub struct RedisTriggerConfig {
     /// Component ID to invoke
     pub component: String,
-    /// Channel to subscribe to
+    /// Channel to subscribe to. This may be a glob-style pattern such as
+    /// `orders:*`, in which case the component receives messages published
+    /// to any matching channel.
     pub channel: String,
     /// Trigger executor (currently unused)
     #[serde(default, skip_serializing)]
@@ -59,9 +63,15 @@ impl TriggerExecutor for RedisTrigger {
         let address = engine.app().require_metadata(TRIGGER_METADATA_KEY)?.address;
 
         let mut channel_components: HashMap<String, Vec<String>> = HashMap::new();
+        let mut pattern_components: HashMap<String, Vec<String>> = HashMap::new();
 
         for (_, config) in engine.trigger_configs() {
-            channel_components
+            let subscriptions = if is_channel_pattern(&config.channel) {
+                &mut pattern_components
+            } else {
+                &mut channel_components
+            };
+            subscriptions
                 .entry(config.channel.clone())
                 .or_default()
                 .push(config.component.clone());
@@ -70,30 +80,53 @@ impl TriggerExecutor for RedisTrigger {
             engine,
             address,
             channel_components,
+            pattern_components,
         })
     }
 
-    /// Run the Redis trigger indefinitely.
+    /// Run the Redis trigger until the connection closes or it is shut down.
     async fn run(self, _config: Self::RunConfig) -> Result<()> {
         let address = &self.address;
 
         tracing::info!("Connecting to Redis server at {}", address);
-        let mut client = Client::open(address.to_string())?;
-        let mut pubsub = client
-            .get_async_connection()
+        let (mut client, connection) = self
+            .engine
+            .resolver()
+            .connect_redis(
+                address.as_str().into_connection_info()?,
+                |client| async move {
+                    let connection = client.get_async_connection().await?;
+                    Ok::<_, redis::RedisError>((client, connection))
+                },
+            )
             .await
-            .with_context(|| anyhow!("Redis trigger failed to connect to {}", address))?
-            .into_pubsub();
+            .with_context(|| anyhow!("Redis trigger failed to connect to {}", address))?;
+        let mut pubsub = connection.into_pubsub();
 
-        // Subscribe to channels
-        for (channel, component) in self.channel_components.iter() {
-            tracing::info!("Subscribing component {component:?} to channel {channel:?}");
+        // Subscribe to each channel and pattern once; messages are routed to
+        // components by `handle`
+        for (channel, components) in self.channel_components.iter() {
+            tracing::info!("Subscribing components {components:?} to channel {channel:?}");
             pubsub.subscribe(channel).await?;
         }
+        for (pattern, components) in self.pattern_components.iter() {
+            tracing::info!("Subscribing components {components:?} to channel pattern {pattern:?}");
+            pubsub.psubscribe(pattern).await?;
+        }
 
+        let shutdown_signal = self.engine.shutdown_signal().clone();
         let mut stream = pubsub.on_message();
         loop {
-            match stream.next().await {
+            // Messages are handled one at a time, so there is nothing in
+            // flight to wait for once the signal is seen here
+            let msg = tokio::select! {
+                msg = stream.next() => msg,
+                _ = shutdown_signal.triggered() => {
+                    tracing::info!("Redis trigger shutting down");
+                    break Ok(());
+                }
+            };
+            match msg {
                 Some(msg) => drop(self.handle(msg).await),
                 None => {
                     tracing::trace!("Empty message");
@@ -109,11 +142,29 @@ impl TriggerExecutor for RedisTrigger {
 
 impl RedisTrigger {
     // Handle the message.
+    #[tracing::instrument(
+        name = "spin_trigger_redis.handle_message",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            messaging.system = "redis",
+            messaging.destination.name = msg.get_channel_name(),
+        )
+    )]
     async fn handle(&self, msg: redis::Msg) -> Result<()> {
         let channel = msg.get_channel_name();
         tracing::info!("Received message on channel {:?}", channel);
 
-        if let Some(component_ids) = self.channel_components.get(channel) {
+        // A message matching both a channel and a pattern subscription is
+        // delivered once for each, so route by the matched subscription
+        let component_ids = if msg.from_pattern() {
+            let pattern: String = msg.get_pattern()?;
+            self.pattern_components.get(&pattern)
+        } else {
+            self.channel_components.get(channel)
+        };
+
+        if let Some(component_ids) = component_ids {
             let futures = component_ids.iter().map(|id| {
                 tracing::trace!("Executing Redis component {id:?}");
                 SpinRedisExecutor.execute(&self.engine, id, channel, msg.get_payload_bytes())
@@ -134,6 +185,12 @@ impl RedisTrigger {
     }
 }
 
+/// Returns whether the given channel is a glob-style pattern, to be subscribed
+/// to with `PSUBSCRIBE` rather than `SUBSCRIBE`.
+fn is_channel_pattern(channel: &str) -> bool {
+    channel.contains(['*', '?', '['])
+}
+
 /// The Redis executor trait.
 /// All Redis executors must implement this trait.
 #[async_trait]
@@ -11,6 +11,11 @@ pub struct SpinRedisExecutor;
 
 #[async_trait]
 impl RedisExecutor for SpinRedisExecutor {
+    #[tracing::instrument(
+        name = "spin_trigger_redis.execute",
+        skip_all,
+        fields(spin.component_id = component_id)
+    )]
     async fn execute(
         &self,
         engine: &TriggerAppEngine<RedisTrigger>,
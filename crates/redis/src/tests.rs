@@ -12,6 +12,24 @@ fn create_trigger_event(channel: &str, payload: &str) -> redis::Msg {
     .unwrap()
 }
 
+fn create_pattern_trigger_event(pattern: &str, channel: &str, payload: &str) -> redis::Msg {
+    Msg::from_value(&redis::Value::Bulk(vec![
+        Value::Data("pmessage".into()),
+        Value::Data(pattern.into()),
+        Value::Data(channel.into()),
+        Value::Data(payload.into()),
+    ]))
+    .unwrap()
+}
+
+#[test]
+fn test_is_channel_pattern() {
+    assert!(is_channel_pattern("orders:*"));
+    assert!(is_channel_pattern("orders:?"));
+    assert!(is_channel_pattern("orders:[ab]"));
+    assert!(!is_channel_pattern("orders"));
+}
+
 #[tokio::test]
 async fn test_pubsub() -> Result<()> {
     let trigger: RedisTrigger = RedisTestConfig::default()
@@ -24,3 +42,22 @@ async fn test_pubsub() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_pattern_pubsub() -> Result<()> {
+    let trigger: RedisTrigger = RedisTestConfig::default()
+        .test_program("redis-rust.wasm")
+        .build_trigger("orders:*")
+        .await;
+
+    assert!(trigger.channel_components.is_empty());
+    assert_eq!(
+        trigger.pattern_components["orders:*"],
+        vec!["test-component".to_string()]
+    );
+
+    let msg = create_pattern_trigger_event("orders:*", "orders:new", "hello");
+    trigger.handle(msg).await?;
+
+    Ok(())
+}
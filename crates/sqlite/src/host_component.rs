@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{ConnectionsStore, SqliteDispatch, DATABASES_KEY};
+use crate::{ConnectionsStore, SqliteDispatch, DATABASES_KEY, READ_ONLY_DATABASES_KEY};
 use anyhow::anyhow;
 use spin_app::{AppComponent, DynamicHostComponent};
 use spin_core::HostComponent;
@@ -64,7 +64,14 @@ impl DynamicHostComponent for SqliteComponent {
         let allowed_databases = component
             .get_metadata(crate::DATABASES_KEY)?
             .unwrap_or_default();
-        data.component_init(allowed_databases, (self.init_connections_store)(component));
+        let read_only_databases = component
+            .get_metadata(READ_ONLY_DATABASES_KEY)?
+            .unwrap_or_default();
+        data.component_init(
+            allowed_databases,
+            read_only_databases,
+            (self.init_connections_store)(component),
+        );
         Ok(())
     }
 
@@ -73,8 +80,12 @@ impl DynamicHostComponent for SqliteComponent {
 
         for component in app.components() {
             let connections_store = (self.init_connections_store)(&component);
-            for allowed in component.get_metadata(DATABASES_KEY)?.unwrap_or_default() {
-                if !connections_store.has_connection_for(&allowed) {
+            let allowed_databases = component.get_metadata(DATABASES_KEY)?.unwrap_or_default();
+            let read_only_databases = component
+                .get_metadata(READ_ONLY_DATABASES_KEY)?
+                .unwrap_or_default();
+            for allowed in allowed_databases.union(&read_only_databases) {
+                if !connections_store.has_connection_for(allowed) {
                     let err = format!("- Component {} uses database '{allowed}'", component.id());
                     errors.push(err);
                 }
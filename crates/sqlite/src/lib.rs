@@ -8,6 +8,10 @@ use std::{collections::HashSet, sync::Arc};
 pub use host_component::SqliteComponent;
 
 pub const DATABASES_KEY: MetadataKey<HashSet<String>> = MetadataKey::new("databases");
+/// Databases a component may only read from. A database listed here and in
+/// [`DATABASES_KEY`] is read-only to the component.
+pub const READ_ONLY_DATABASES_KEY: MetadataKey<HashSet<String>> =
+    MetadataKey::new("read_only_databases");
 
 /// A store of connections for all accessible databases for an application
 #[async_trait]
@@ -36,6 +40,7 @@ pub trait Connection: Send + Sync {
 /// An implementation of the SQLite host
 pub struct SqliteDispatch {
     allowed_databases: HashSet<String>,
+    read_only_databases: HashSet<String>,
     connections: table::Table<Arc<dyn Connection>>,
     connections_store: Arc<dyn ConnectionsStore>,
 }
@@ -45,6 +50,7 @@ impl SqliteDispatch {
         Self {
             connections: table::Table::new(256),
             allowed_databases: HashSet::new(),
+            read_only_databases: HashSet::new(),
             connections_store,
         }
     }
@@ -53,9 +59,11 @@ impl SqliteDispatch {
     pub fn component_init(
         &mut self,
         allowed_databases: HashSet<String>,
+        read_only_databases: HashSet<String>,
         connections_store: Arc<dyn ConnectionsStore>,
     ) {
         self.allowed_databases = allowed_databases;
+        self.read_only_databases = read_only_databases;
         self.connections_store = connections_store;
     }
 
@@ -78,7 +86,8 @@ impl sqlite::HostConnection for SqliteDispatch {
         &mut self,
         database: String,
     ) -> anyhow::Result<Result<Resource<sqlite::Connection>, sqlite::Error>> {
-        if !self.allowed_databases.contains(&database) {
+        let read_only = self.read_only_databases.contains(&database);
+        if !read_only && !self.allowed_databases.contains(&database) {
             return Ok(Err(sqlite::Error::AccessDenied));
         }
         Ok(self
@@ -86,6 +95,13 @@ impl sqlite::HostConnection for SqliteDispatch {
             .get_connection(&database)
             .await
             .and_then(|conn| conn.ok_or(sqlite::Error::NoSuchDatabase))
+            .map(|conn| {
+                if read_only {
+                    Arc::new(ReadOnlyConnection(conn)) as Arc<dyn Connection>
+                } else {
+                    conn
+                }
+            })
             .and_then(|conn| {
                 self.connections
                     .push(conn)
@@ -145,6 +161,48 @@ impl spin_world::v1::sqlite::Host for SqliteDispatch {
         <Self as sqlite::HostConnection>::drop(self, Resource::new_own(connection))
     }
 }
+/// A connection which rejects statements other than queries, for components
+/// with read-only access to a database.
+struct ReadOnlyConnection(Arc<dyn Connection>);
+
+#[async_trait]
+impl Connection for ReadOnlyConnection {
+    async fn query(
+        &self,
+        query: &str,
+        parameters: Vec<sqlite::Value>,
+    ) -> Result<sqlite::QueryResult, sqlite::Error> {
+        if !is_select(query) {
+            return Err(sqlite::Error::AccessDenied);
+        }
+        self.0.query(query, parameters).await
+    }
+
+    async fn execute_batch(&self, _statements: &str) -> anyhow::Result<()> {
+        anyhow::bail!("database is read-only")
+    }
+}
+
+// Returns whether a statement is a SELECT, ignoring leading whitespace and
+// comments.
+fn is_select(mut query: &str) -> bool {
+    loop {
+        query = query.trim_start();
+        if let Some(rest) = query.strip_prefix("--") {
+            query = rest.split_once('\n').map_or("", |(_, rest)| rest);
+        } else if let Some(rest) = query.strip_prefix("/*") {
+            query = rest.split_once("*/").map_or("", |(_, rest)| rest);
+        } else {
+            break;
+        }
+    }
+    let keyword = query
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+    keyword.eq_ignore_ascii_case("select")
+}
+
 use spin_world::v1::sqlite as v1;
 
 fn to_legacy_error(error: sqlite::Error) -> v1::Error {
@@ -189,3 +247,19 @@ fn from_legacy_value(value: v1::Value) -> sqlite::Value {
         v1::Value::Null => sqlite::Value::Null,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_selects_are_read_only() {
+        assert!(is_select("SELECT * FROM items"));
+        assert!(is_select("  select 1"));
+        assert!(is_select("-- recent\n/* all */ SELECT name FROM items"));
+        assert!(!is_select("INSERT INTO items VALUES (1)"));
+        assert!(!is_select("WITH t AS (SELECT 1) DELETE FROM items"));
+        assert!(!is_select("-- SELECT\nDROP TABLE items"));
+        assert!(!is_select(""));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    fmt::{
+        format::Writer,
+        time::{FormatTime, SystemTime},
+        FmtContext, FormatEvent, FormatFields, FormattedFields,
+    },
+    registry::LookupSpan,
+};
+
+// Span fields promoted to top-level keys of each log line, so that lines can
+// be attributed to a component and request without walking the span tree
+const CONTEXT_FIELDS: &[(&str, &str)] = &[
+    ("spin.component_id", "component_id"),
+    ("spin.request_id", "request_id"),
+];
+
+/// Formats events as JSON lines. Must be used with
+/// [`JsonFields`](tracing_subscriber::fmt::format::JsonFields), so that span
+/// fields can be read back as JSON.
+pub(crate) struct JsonLines {
+    pub trigger_type: String,
+}
+
+impl JsonLines {
+    // The keys shared by every line, whatever its source
+    fn base_line(&self, level: &str, target: &str) -> Map<String, Value> {
+        let mut timestamp = String::new();
+        _ = SystemTime.format_time(&mut Writer::new(&mut timestamp));
+        let mut line = Map::new();
+        line.insert("timestamp".into(), timestamp.into());
+        line.insert("level".into(), level.into());
+        line.insert("target".into(), target.into());
+        line.insert("trigger_type".into(), self.trigger_type.as_str().into());
+        line
+    }
+
+    /// Formats a line written by a component to its stdout or stderr.
+    pub fn component_output(&self, component_id: &str, stream: &str, output: &str) -> String {
+        let mut line = self.base_line("INFO", "component");
+        line.insert("component_id".into(), component_id.into());
+        line.insert("stream".into(), stream.into());
+        line.insert("message".into(), output.into());
+        Value::Object(line).to_string()
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for JsonLines
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut line = self.base_line(metadata.level().as_str(), metadata.target());
+
+        // Innermost spans take precedence
+        for span in ctx
+            .event_scope()
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+        {
+            let extensions = span.extensions();
+            let Some(fields) = extensions.get::<FormattedFields<N>>() else {
+                continue;
+            };
+            let Ok(fields) = serde_json::from_str::<Map<String, Value>>(&fields.fields) else {
+                continue;
+            };
+            for (field, key) in CONTEXT_FIELDS {
+                if let Some(value) = fields.get(*field) {
+                    line.insert((*key).into(), value.clone());
+                }
+            }
+        }
+
+        event.record(&mut FieldVisitor(&mut line));
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::{fmt::format::JsonFields, layer::SubscriberExt};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn includes_span_context() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let layer = tracing_subscriber::fmt::layer()
+            .fmt_fields(JsonFields::new())
+            .event_format(JsonLines {
+                trigger_type: "http".into(),
+            })
+            .with_writer(move || writer.clone());
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "request",
+                spin.request_id = "req-1",
+                spin.component_id = tracing::field::Empty
+            );
+            let _guard = span.enter();
+            span.record("spin.component_id", "hello");
+            tracing::warn!(status = 500, "Request failed");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Map<String, Value> = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["message"], "Request failed");
+        assert_eq!(line["status"], 500);
+        assert_eq!(line["trigger_type"], "http");
+        assert_eq!(line["component_id"], "hello");
+        assert_eq!(line["request_id"], "req-1");
+    }
+
+    #[test]
+    fn formats_component_output() {
+        let format = JsonLines {
+            trigger_type: "redis".into(),
+        };
+        let line = format.component_output("hello", "stdout", "Hello, \"world\"");
+        let line: Map<String, Value> = serde_json::from_str(&line).unwrap();
+        assert_eq!(line["component_id"], "hello");
+        assert_eq!(line["stream"], "stdout");
+        assert_eq!(line["message"], "Hello, \"world\"");
+    }
+}
@@ -0,0 +1,214 @@
+//! Logging and tracing for Spin.
+//!
+//! [`init`] installs the global `tracing` subscriber, which logs to stderr as
+//! filtered by `RUST_LOG`. Trigger hosts may then call [`enable_json_logs`] to
+//! log JSON lines instead of text, and [`enable_otlp`] to also export spans to
+//! an OpenTelemetry collector.
+
+mod json;
+mod propagation;
+
+use std::{collections::HashMap, io::IsTerminal};
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::OnceCell;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace, Resource};
+use serde::Deserialize;
+use tracing_subscriber::{
+    filter::Filtered,
+    fmt::format::JsonFields,
+    layer::{Layered, SubscriberExt},
+    reload,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+use json::JsonLines;
+
+pub use propagation::{extract_trace_context, inject_trace_context, TRACEPARENT_HEADER};
+
+type OtelLayer = tracing_opentelemetry::OpenTelemetryLayer<Registry, trace::Tracer>;
+
+// The subscriber beneath the stderr logging layer
+type OtelSubscriber = Layered<
+    Filtered<
+        reload::Layer<Option<OtelLayer>, Registry>,
+        reload::Layer<EnvFilter, Registry>,
+        Registry,
+    >,
+    Registry,
+>;
+
+type FmtLayer = Box<dyn Layer<OtelSubscriber> + Send + Sync>;
+
+// Handles for swapping in layers once they are configured
+struct Handles {
+    otel_layer: reload::Handle<Option<OtelLayer>, Registry>,
+    otel_filter: reload::Handle<EnvFilter, Registry>,
+    fmt_layer: reload::Handle<FmtLayer, OtelSubscriber>,
+}
+
+static HANDLES: OnceCell<Handles> = OnceCell::new();
+
+// Set once JSON logging is enabled
+static JSON_LINES: OnceCell<JsonLines> = OnceCell::new();
+
+/// Installs the global `tracing` subscriber.
+pub fn init() -> Result<()> {
+    // Until `enable_otlp` is called the OpenTelemetry layer is absent and
+    // filters out everything, so costs nothing
+    let (otel_layer, otel_layer_handle) = reload::Layer::new(None);
+    let (otel_filter, otel_filter_handle) = reload::Layer::new(EnvFilter::new("off"));
+
+    let text_layer: FmtLayer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .boxed();
+    let (fmt_layer, fmt_layer_handle) = reload::Layer::new(text_layer);
+
+    tracing_subscriber::registry()
+        .with(otel_layer.with_filter(otel_filter))
+        .with(
+            fmt_layer
+                .with_filter(EnvFilter::from_default_env().add_directive("watchexec=off".parse()?)),
+        )
+        .init();
+
+    _ = HANDLES.set(Handles {
+        otel_layer: otel_layer_handle,
+        otel_filter: otel_filter_handle,
+        fmt_layer: fmt_layer_handle,
+    });
+    Ok(())
+}
+
+/// Switches logging on stderr from text to JSON lines, for ingestion by log
+/// aggregators. Each line carries the level, the `trigger_type`, and the
+/// component and request ids of the enclosing spans, if any.
+///
+/// Must be called after [`init`]. Spans created before the switch don't
+/// contribute ids to log lines.
+pub fn enable_json_logs(trigger_type: &str) -> Result<()> {
+    let handles = HANDLES
+        .get()
+        .context("JSON logging can't be enabled: tracing was not initialized by Spin")?;
+    let json_layer: FmtLayer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .fmt_fields(JsonFields::new())
+        .event_format(JsonLines {
+            trigger_type: trigger_type.to_owned(),
+        })
+        .boxed();
+    handles.fmt_layer.reload(json_layer)?;
+    _ = JSON_LINES.set(JsonLines {
+        trigger_type: trigger_type.to_owned(),
+    });
+    Ok(())
+}
+
+/// Returns whether [`enable_json_logs`] has been called.
+pub fn json_logs_enabled() -> bool {
+    JSON_LINES.get().is_some()
+}
+
+/// Formats `output`, a line written by the component `component_id` to
+/// `stream` (`stdout` or `stderr`), as a JSON log line without a trailing
+/// newline. Returns `None` unless JSON logging is enabled.
+pub fn component_output_json_line(
+    component_id: &str,
+    stream: &str,
+    output: &str,
+) -> Option<String> {
+    JSON_LINES
+        .get()
+        .map(|format| format.component_output(component_id, stream, output))
+}
+
+/// The protocol used to export spans to an OTLP collector.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    /// OTLP over gRPC
+    #[default]
+    #[serde(rename = "grpc")]
+    Grpc,
+    /// OTLP over HTTP with protobuf-encoded bodies
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+}
+
+/// Configuration for exporting spans to an OTLP collector.
+#[derive(Clone, Debug)]
+pub struct OtlpConfig {
+    /// The collector endpoint, e.g. `http://localhost:4317`
+    pub endpoint: String,
+    /// The export protocol
+    pub protocol: OtlpProtocol,
+    /// Headers (gRPC metadata) to send with each export, e.g. for auth
+    pub headers: HashMap<String, String>,
+    /// The `service.name` resource attribute
+    pub service_name: String,
+    /// Which spans to export, in `RUST_LOG` syntax
+    pub filter: String,
+}
+
+/// Starts exporting spans to the OTLP collector described by `config`, and
+/// propagating W3C trace context through [`extract_trace_context`] and
+/// [`inject_trace_context`].
+///
+/// Must be called from within a Tokio runtime, after [`init`].
+pub fn enable_otlp(config: &OtlpConfig) -> Result<()> {
+    let handles = HANDLES
+        .get()
+        .context("OpenTelemetry can't be enabled: tracing was not initialized by Spin")?;
+    let filter = EnvFilter::try_new(&config.filter)
+        .with_context(|| format!("Invalid OpenTelemetry filter {:?}", config.filter))?;
+
+    let exporter: opentelemetry_otlp::SpanExporterBuilder = match config.protocol {
+        OtlpProtocol::Grpc => opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(&config.endpoint)
+            .with_metadata(grpc_metadata(&config.headers)?)
+            .into(),
+        OtlpProtocol::HttpProtobuf => opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(&config.endpoint)
+            .with_headers(config.headers.clone())
+            .into(),
+    };
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace::config().with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            config.service_name.clone(),
+        )])))
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .context("Failed to start OpenTelemetry exporter")?;
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    handles
+        .otel_layer
+        .reload(Some(tracing_opentelemetry::layer().with_tracer(tracer)))?;
+    handles.otel_filter.reload(filter)?;
+    tracing::info!("Exporting traces to {}", config.endpoint);
+    Ok(())
+}
+
+/// Flushes any spans not yet exported. Call before exiting.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+fn grpc_metadata(headers: &HashMap<String, String>) -> Result<tonic::metadata::MetadataMap> {
+    let mut map = http::HeaderMap::new();
+    for (name, value) in headers {
+        let name = http::HeaderName::try_from(name)
+            .map_err(|_| anyhow!("Invalid OpenTelemetry header name {name:?}"))?;
+        let value = http::HeaderValue::try_from(value)
+            .map_err(|_| anyhow!("Invalid value for OpenTelemetry header {name:?}"))?;
+        map.insert(name, value);
+    }
+    Ok(tonic::metadata::MetadataMap::from_headers(map))
+}
@@ -0,0 +1,72 @@
+use http::{HeaderMap, HeaderName, HeaderValue};
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// The W3C trace context header naming the parent span.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// Makes the trace context in `headers`, if any, the parent of `span`.
+pub fn extract_trace_context(span: &tracing::Span, headers: &HeaderMap) {
+    let context = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(context);
+}
+
+/// Sets the trace context headers in `headers` to the current span, replacing
+/// any already present.
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let context = tracing::Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) else {
+            return;
+        };
+        self.0.insert(name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_trace_context() {
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let mut incoming = HeaderMap::new();
+        incoming.insert(TRACEPARENT_HEADER, HeaderValue::from_static(traceparent));
+        incoming.insert("tracestate", HeaderValue::from_static("spin=1"));
+
+        let propagator = TraceContextPropagator::new();
+        let context = propagator.extract(&HeaderExtractor(&incoming));
+
+        let mut outgoing = HeaderMap::new();
+        propagator.inject_context(&context, &mut HeaderInjector(&mut outgoing));
+        assert_eq!(outgoing.get(TRACEPARENT_HEADER).unwrap(), traceparent);
+        assert_eq!(outgoing.get("tracestate").unwrap(), "spin=1");
+    }
+}
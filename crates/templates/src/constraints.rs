@@ -3,6 +3,7 @@ use regex::Regex;
 #[derive(Clone, Debug)]
 pub(crate) struct StringConstraints {
     pub regex: Option<Regex>,
+    pub allowed_values: Option<Vec<String>>,
 }
 
 impl StringConstraints {
@@ -12,6 +13,15 @@ impl StringConstraints {
                 anyhow::bail!("Input '{}' does not match pattern '{}'", text, regex);
             }
         }
+        if let Some(allowed_values) = self.allowed_values.as_ref() {
+            if !allowed_values.contains(&text) {
+                anyhow::bail!(
+                    "Input '{}' is not one of the allowed values ({})",
+                    text,
+                    allowed_values.join(", ")
+                );
+            }
+        }
         Ok(text)
     }
 }
@@ -2,13 +2,13 @@ use std::{collections::HashMap, path::Path};
 
 use crate::{
     cancellable::Cancellable,
-    template::{TemplateParameter, TemplateParameterDataType},
+    template::{PostGenerateHook, TemplateParameter, TemplateParameterDataType},
     Run,
 };
 
 use anyhow::anyhow;
 // use console::style;
-use dialoguer::{Confirm, Input};
+use dialoguer::{Confirm, Input, Select};
 
 pub(crate) trait InteractionStrategy {
     fn allow_generate_into(&self, target_dir: &Path) -> Cancellable<(), anyhow::Error>;
@@ -33,6 +33,7 @@ pub(crate) trait InteractionStrategy {
         run: &Run,
         parameter: &TemplateParameter,
     ) -> Cancellable<String, anyhow::Error>;
+    fn allow_run_hook(&self, run: &Run, hook: &PostGenerateHook) -> anyhow::Result<bool>;
 }
 
 pub(crate) struct Interactive;
@@ -71,6 +72,17 @@ impl InteractionStrategy for Interactive {
             },
         }
     }
+
+    fn allow_run_hook(&self, run: &Run, hook: &PostGenerateHook) -> anyhow::Result<bool> {
+        if run.options.accept_defaults {
+            return Ok(true);
+        }
+        let prompt = format!("Run '{}'?", hook.command().join(" "));
+        Ok(Confirm::new()
+            .with_prompt(prompt)
+            .default(true)
+            .interact()?)
+    }
 }
 
 impl InteractionStrategy for Silent {
@@ -99,6 +111,10 @@ impl InteractionStrategy for Silent {
             },
         }
     }
+
+    fn allow_run_hook(&self, _run: &Run, _hook: &PostGenerateHook) -> anyhow::Result<bool> {
+        Ok(true)
+    }
 }
 
 pub(crate) fn confirm(text: &str) -> std::io::Result<bool> {
@@ -111,7 +127,11 @@ pub(crate) fn prompt_parameter(parameter: &TemplateParameter) -> Option<String>
 
     loop {
         let input = match parameter.data_type() {
-            TemplateParameterDataType::String(_) => ask_free_text(prompt, default_value),
+            TemplateParameterDataType::String(constraints) => match &constraints.allowed_values {
+                Some(allowed_values) => ask_choice(prompt, allowed_values, default_value),
+                None => ask_free_text(prompt, default_value),
+            },
+            TemplateParameterDataType::Bool => ask_yes_no(prompt, default_value),
         };
 
         match input {
@@ -138,6 +158,33 @@ fn ask_free_text(prompt: &str, default_value: &Option<String>) -> anyhow::Result
     Ok(result)
 }
 
+fn ask_choice(
+    prompt: &str,
+    allowed_values: &[String],
+    default_value: &Option<String>,
+) -> anyhow::Result<String> {
+    let default_index = default_value
+        .as_ref()
+        .and_then(|d| allowed_values.iter().position(|v| v == d))
+        .unwrap_or_default();
+    let index = Select::new()
+        .with_prompt(prompt)
+        .items(allowed_values)
+        .default(default_index)
+        .interact()?;
+    Ok(allowed_values[index].clone())
+}
+
+fn ask_yes_no(prompt: &str, default_value: &Option<String>) -> anyhow::Result<String> {
+    let mut confirm = Confirm::new();
+    confirm.with_prompt(prompt);
+    if let Some(s) = default_value {
+        confirm.default(s == "true");
+    }
+    let result = confirm.interact()?;
+    Ok(result.to_string())
+}
+
 fn is_directory_empty(path: &Path) -> bool {
     if !path.exists() {
         return true;
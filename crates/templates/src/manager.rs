@@ -3,6 +3,7 @@ use std::path::Path;
 use anyhow::Context;
 
 use crate::{
+    reader::RawInstalledFrom,
     source::TemplateSource,
     store::{TemplateLayout, TemplateStore},
     template::Template,
@@ -111,6 +112,13 @@ impl TemplateManager {
         Ok(Self::new(store))
     }
 
+    /// Creates a `TemplateManager` for templates installed in the given
+    /// directory. This allows templates to be used without adding them to
+    /// the default install location.
+    pub fn in_dir(dir: impl AsRef<Path>) -> Self {
+        Self::new(TemplateStore::new(dir))
+    }
+
     pub(crate) fn new(store: TemplateStore) -> Self {
         Self { store }
     }
@@ -134,13 +142,14 @@ impl TemplateManager {
             .template_directories()
             .await
             .context("Could not find templates in source")?;
+        let install_record = source.to_install_record(local_source.resolved_digest.as_deref());
 
         let mut installed = vec![];
         let mut skipped = vec![];
 
         for template_dir in template_dirs {
             let install_result = self
-                .install_one(&template_dir, options, source, reporter)
+                .install_one(&template_dir, options, &install_record, reporter)
                 .await
                 .with_context(|| {
                     format!("Failed to install template from {}", template_dir.display())
@@ -161,7 +170,7 @@ impl TemplateManager {
         &self,
         source_dir: &Path,
         options: &InstallOptions,
-        source: &TemplateSource,
+        install_record: &Option<RawInstalledFrom>,
         reporter: &impl ProgressReporter,
     ) -> anyhow::Result<InstallationResult> {
         let layout = TemplateLayout::new(source_dir);
@@ -195,11 +204,11 @@ impl TemplateManager {
                     ))
                 }
                 ExistsBehaviour::Update => {
-                    copy_template_over_existing(id, source_dir, &dest_dir, source).await?
+                    copy_template_over_existing(id, source_dir, &dest_dir, install_record).await?
                 }
             }
         } else {
-            copy_template_into(id, source_dir, &dest_dir, source).await?
+            copy_template_into(id, source_dir, &dest_dir, install_record).await?
         };
 
         Ok(InstallationResult::Installed(template))
@@ -273,7 +282,7 @@ async fn copy_template_over_existing(
     id: &str,
     source_dir: &Path,
     dest_dir: &Path,
-    source: &TemplateSource,
+    install_record: &Option<RawInstalledFrom>,
 ) -> anyhow::Result<Template> {
     // The nearby directory to which we initially copy the source
     let stage_dir = dest_dir.with_extension(".stage");
@@ -307,7 +316,7 @@ async fn copy_template_over_existing(
 
     // Copy template source into stage directory, and do best effort
     // cleanup if it goes wrong.
-    let copy_to_stage_err = copy_template_into(id, source_dir, &stage_dir, source)
+    let copy_to_stage_err = copy_template_into(id, source_dir, &stage_dir, install_record)
         .await
         .err();
     if let Some(e) = copy_to_stage_err {
@@ -359,7 +368,7 @@ async fn copy_template_into(
     id: &str,
     source_dir: &Path,
     dest_dir: &Path,
-    source: &TemplateSource,
+    install_record: &Option<RawInstalledFrom>,
 ) -> anyhow::Result<Template> {
     tokio::fs::create_dir_all(&dest_dir)
         .await
@@ -380,18 +389,17 @@ async fn copy_template_into(
         )
     })?;
 
-    write_install_record(dest_dir, source);
+    write_install_record(dest_dir, install_record);
 
     load_template_from(id, dest_dir)
 }
 
-fn write_install_record(dest_dir: &Path, source: &TemplateSource) {
+fn write_install_record(dest_dir: &Path, install_record: &Option<RawInstalledFrom>) {
     let layout = TemplateLayout::new(dest_dir);
     let install_record_path = layout.installation_record_file();
 
     // A failure here shouldn't fail the install
-    let install_record = source.to_install_record();
-    if let Ok(record_text) = toml::to_string_pretty(&install_record) {
+    if let Ok(record_text) = toml::to_string_pretty(install_record) {
         _ = std::fs::write(install_record_path, record_text);
     }
 }
@@ -1330,6 +1338,106 @@ mod tests {
         assert_contains(&err_str, "unknown filter 'lol_snort'");
     }
 
+    #[tokio::test]
+    async fn can_choose_files_and_run_hooks_by_parameter_values() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path());
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(test_data_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let dest_temp_dir = tempdir().unwrap();
+
+        let defaults_dir = dest_temp_dir.path().join("defaults");
+        {
+            let template = manager.get("testing-conditionals").unwrap().unwrap();
+            let options = RunOptions {
+                variant: crate::template::TemplateVariantInfo::NewApplication,
+                output_path: defaults_dir.clone(),
+                name: "defaults".to_owned(),
+                values: HashMap::new(),
+                accept_defaults: true,
+            };
+            template.run(options).silent().await.unwrap();
+        }
+
+        let index = fs::read_to_string(defaults_dir.join("index.txt")).unwrap();
+        assert_eq!("Using TypeScript with none", index.trim());
+        assert!(defaults_dir.join("tsconfig.json").exists());
+        assert!(!defaults_dir.join("Cargo.toml").exists());
+
+        let chosen_dir = dest_temp_dir.path().join("chosen");
+        {
+            let template = manager.get("testing-conditionals").unwrap().unwrap();
+            let values = [
+                ("framework".to_owned(), "router".to_owned()),
+                ("typescript".to_owned(), "false".to_owned()),
+            ]
+            .into_iter()
+            .collect();
+            let options = RunOptions {
+                variant: crate::template::TemplateVariantInfo::NewApplication,
+                output_path: chosen_dir.clone(),
+                name: "chosen".to_owned(),
+                values,
+                accept_defaults: false,
+            };
+            template.run(options).silent().await.unwrap();
+        }
+
+        let index = fs::read_to_string(chosen_dir.join("index.txt")).unwrap();
+        assert_eq!("Using JavaScript with router", index.trim());
+        assert!(!chosen_dir.join("tsconfig.json").exists());
+        assert!(
+            chosen_dir.join("Cargo.toml").exists(),
+            "expected post-generate command to have run"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_values_not_allowed_by_parameter_type() {
+        let temp_dir = tempdir().unwrap();
+        let store = TemplateStore::new(temp_dir.path());
+        let manager = TemplateManager { store };
+        let source = TemplateSource::File(test_data_root());
+
+        manager
+            .install(&source, &InstallOptions::default(), &DiscardingReporter)
+            .await
+            .unwrap();
+
+        let template = manager.get("testing-conditionals").unwrap().unwrap();
+
+        let dest_temp_dir = tempdir().unwrap();
+        let values = [
+            ("framework".to_owned(), "express".to_owned()),
+            ("typescript".to_owned(), "yes".to_owned()),
+        ]
+        .into_iter()
+        .collect();
+        let options = RunOptions {
+            variant: crate::template::TemplateVariantInfo::NewApplication,
+            output_path: dest_temp_dir.path().join("myproj"),
+            name: "myproj".to_owned(),
+            values,
+            accept_defaults: false,
+        };
+
+        let err = template
+            .run(options)
+            .silent()
+            .await
+            .expect_err("Expected invalid values to be rejected");
+
+        let err_str = err.to_string();
+        assert_contains(&err_str, "not one of the allowed values (none, router)");
+        assert_contains(&err_str, "must be 'true' or 'false'");
+    }
+
     fn assert_contains(actual: &str, expected: &str) {
         assert!(
             actual.contains(expected),
@@ -22,6 +22,8 @@ pub(crate) struct RawTemplateManifestV1 {
     pub new_application: Option<RawTemplateVariant>,
     pub add_component: Option<RawTemplateVariant>,
     pub parameters: Option<IndexMap<String, RawParameter>>,
+    pub conditionals: Option<IndexMap<String, RawConditional>>,
+    pub post_generate: Option<Vec<RawPostGenerateHook>>,
     pub custom_filters: Option<serde::de::IgnoredAny>, // kept for error messaging
 }
 
@@ -43,6 +45,27 @@ pub(crate) struct RawParameter {
     #[serde(rename = "default")]
     pub default_value: Option<String>,
     pub pattern: Option<String>,
+    pub allowed_values: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) struct RawConditional {
+    pub condition: RawCondition,
+    pub skip_files: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", tag = "type")]
+pub(crate) enum RawCondition {
+    ParameterEquals { parameter: String, value: String },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) struct RawPostGenerateHook {
+    pub command: Vec<String>,
+    pub condition: Option<RawCondition>,
 }
 
 pub(crate) fn parse_manifest_toml(text: impl AsRef<str>) -> anyhow::Result<RawTemplateManifest> {
@@ -54,6 +77,7 @@ pub(crate) fn parse_manifest_toml(text: impl AsRef<str>) -> anyhow::Result<RawTe
 pub(crate) enum RawInstalledFrom {
     Git { git: String },
     File { dir: String },
+    Oci { oci: String, digest: Option<String> },
 }
 
 pub(crate) fn parse_installed_from(text: impl AsRef<str>) -> Option<RawInstalledFrom> {
@@ -12,7 +12,7 @@ use crate::{
     cancellable::Cancellable,
     interaction::{InteractionStrategy, Interactive, Silent},
     renderer::MergeTarget,
-    template::TemplateVariantInfo,
+    template::{TemplateParameterDataType, TemplateVariantInfo},
 };
 use crate::{
     renderer::{RenderOperation, TemplateContent, TemplateRenderer},
@@ -46,7 +46,8 @@ impl Run {
     }
 
     /// Runs the template interactively. The user will be prompted for any
-    /// information or input the template needs, such as parameter values.
+    /// information or input the template needs, such as parameter values,
+    /// and asked before each of the template's post-generate commands is run.
     /// Execution will block while waiting on user responses.
     pub async fn interactive(&self) -> anyhow::Result<()> {
         self.run(Interactive).await
@@ -55,24 +56,33 @@ impl Run {
     /// Runs the template silently. The template will be executed without
     /// user interaction, and will not wait on the user. If the template needs
     /// any information or input that was not provided in the `RunOptions`,
-    /// execution will fail and result in an error.
+    /// execution will fail and result in an error. The template's post-generate
+    /// commands are run without confirmation.
     pub async fn silent(&self) -> anyhow::Result<()> {
         self.run(Silent).await
     }
 
     async fn run(&self, interaction: impl InteractionStrategy) -> anyhow::Result<()> {
-        self.build_renderer(interaction)
+        self.build_renderer(&interaction)
             .await
-            .and_then(|t| t.render())
-            .and_then_async(|o| async move { o.write().await })
+            .and_then(|(t, values)| Ok((t.render()?, values)))
+            .and_then_async(|(o, values)| async move {
+                o.write().await?;
+                Ok(values)
+            })
+            .await
+            .and_then_async(|values| {
+                let interaction = &interaction;
+                async move { self.run_post_generate_hooks(interaction, &values).await }
+            })
             .await
             .err()
     }
 
     async fn build_renderer(
         &self,
-        interaction: impl InteractionStrategy,
-    ) -> Cancellable<TemplateRenderer, anyhow::Error> {
+        interaction: &impl InteractionStrategy,
+    ) -> Cancellable<(TemplateRenderer, HashMap<String, String>), anyhow::Error> {
         self.build_renderer_raw(interaction).await.into()
     }
 
@@ -81,10 +91,13 @@ impl Run {
     // means error. Why have this ugly representation? Because it makes it terser to
     // write using the Rust `?` operator to early-return. It would be lovely to find
     // a better way but I don't see one yet...
+    //
+    // The parameter values are returned alongside the renderer for evaluating
+    // post-generate hook conditions.
     async fn build_renderer_raw(
         &self,
-        interaction: impl InteractionStrategy,
-    ) -> anyhow::Result<Option<TemplateRenderer>> {
+        interaction: &impl InteractionStrategy,
+    ) -> anyhow::Result<Option<(TemplateRenderer, HashMap<String, String>)>> {
         self.validate_version()?;
         self.validate_trigger()?;
 
@@ -99,13 +112,21 @@ impl Run {
 
         self.validate_provided_values()?;
 
+        // Conditionals choose files by parameter values, so parameters must be
+        // populated before files are read
+        let parameter_values = match interaction.populate_parameters(self) {
+            Cancellable::Ok(parameter_values) => parameter_values,
+            Cancellable::Cancelled => return Ok(None),
+            Cancellable::Err(e) => return Err(e),
+        };
+
         let files = match self.template.content_dir() {
             None => vec![],
             Some(path) => {
                 let from = path
                     .absolutize()
                     .context("Failed to get absolute path of template directory")?;
-                self.included_files(&from, &to)?
+                self.included_files(&from, &to, &parameter_values)?
             }
         };
 
@@ -118,30 +139,77 @@ impl Run {
 
         let render_operations = files.into_iter().chain(snippets).collect();
 
-        match interaction.populate_parameters(self) {
-            Cancellable::Ok(parameter_values) => {
-                let values = self
-                    .special_values()
-                    .await
-                    .into_iter()
-                    .chain(parameter_values)
-                    .collect();
-                let prepared_template = TemplateRenderer {
-                    render_operations,
-                    parameter_values: values,
-                };
-                Ok(Some(prepared_template))
+        let values = self
+            .special_values()
+            .await
+            .into_iter()
+            .map(|(k, v)| (k, liquid_core::Value::scalar(v)))
+            .chain(
+                parameter_values
+                    .iter()
+                    .map(|(k, v)| (k.clone(), self.parameter_liquid_value(k, v))),
+            )
+            .collect();
+        let prepared_template = TemplateRenderer {
+            render_operations,
+            parameter_values: values,
+        };
+        Ok(Some((prepared_template, parameter_values)))
+    }
+
+    // Bool parameters are rendered as booleans so that templates can test
+    // them with `{% if %}`: the string "false" would be truthy.
+    fn parameter_liquid_value(&self, name: &str, value: &str) -> liquid_core::Value {
+        match self.template.parameter(name).map(|p| p.data_type()) {
+            Some(TemplateParameterDataType::Bool) => liquid_core::Value::scalar(value == "true"),
+            _ => liquid_core::Value::scalar(value.to_owned()),
+        }
+    }
+
+    async fn run_post_generate_hooks(
+        &self,
+        interaction: &impl InteractionStrategy,
+        values: &HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let dir = self.generation_target_dir();
+        for hook in self.template.post_generate_hooks(values) {
+            if !interaction.allow_run_hook(self, hook)? {
+                continue;
+            }
+            let command_text = hook.command().join(" ");
+            let (program, args) = hook
+                .command()
+                .split_first()
+                .expect("hook commands are checked to be non-empty");
+            let status = tokio::process::Command::new(program)
+                .args(args)
+                .current_dir(&dir)
+                .status()
+                .await
+                .with_context(|| format!("Failed to run post-generate command '{command_text}'"))?;
+            if !status.success() {
+                return Err(anyhow!(
+                    "Post-generate command '{command_text}' failed with {status}. The template's files were generated in {}",
+                    dir.display()
+                ));
             }
-            Cancellable::Cancelled => Ok(None),
-            Cancellable::Err(e) => Err(e),
         }
+        Ok(())
     }
 
-    fn included_files(&self, from: &Path, to: &Path) -> anyhow::Result<Vec<RenderOperation>> {
+    fn included_files(
+        &self,
+        from: &Path,
+        to: &Path,
+        parameter_values: &HashMap<String, String>,
+    ) -> anyhow::Result<Vec<RenderOperation>> {
         let all_content_files = Self::list_content_files(from)?;
-        let included_files =
-            self.template
-                .included_files(from, all_content_files, &self.options.variant);
+        let included_files = self.template.included_files(
+            from,
+            all_content_files,
+            &self.options.variant,
+            parameter_values,
+        );
         let template_contents = self.read_all(included_files)?;
         let outputs = Self::to_output_paths(from, to, template_contents);
         let file_ops = outputs
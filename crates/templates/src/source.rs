@@ -25,6 +25,13 @@ pub enum TemplateSource {
     /// Templates much be in a `/templates` directory under the specified
     /// root.
     File(PathBuf),
+    /// Install from an OCI artifact at the specified reference, which may be
+    /// pinned by digest (`registry/repo@sha256:...`). The artifact must
+    /// consist of a single archive layer, as pushed by `spin templates push`.
+    ///
+    /// Templates must be in a `/templates` directory at the root of the
+    /// archive.
+    Oci(String),
 }
 
 /// Settings for installing templates from a Git repository.
@@ -57,11 +64,18 @@ impl TemplateSource {
         }))
     }
 
-    pub(crate) fn to_install_record(&self) -> Option<crate::reader::RawInstalledFrom> {
+    pub(crate) fn to_install_record(
+        &self,
+        resolved_digest: Option<&str>,
+    ) -> Option<crate::reader::RawInstalledFrom> {
         match self {
             Self::Git(g) => Some(crate::reader::RawInstalledFrom::Git {
                 git: g.url.to_string(),
             }),
+            Self::Oci(reference) => Some(crate::reader::RawInstalledFrom::Oci {
+                oci: reference.clone(),
+                digest: resolved_digest.map(|d| d.to_owned()),
+            }),
             Self::File(p) => {
                 // Saving a relative path would be meaningless (but should never happen)
                 if p.is_absolute() {
@@ -88,6 +102,8 @@ impl TemplateSource {
 
 pub(crate) struct LocalTemplateSource {
     root: PathBuf,
+    /// For an OCI source, the digest of the pulled artifact.
+    pub resolved_digest: Option<String>,
     _temp_dir: Option<TempDir>,
 }
 
@@ -96,6 +112,7 @@ impl TemplateSource {
         match self {
             Self::Git(git_source) => clone_local(git_source).await,
             Self::File(path) => check_local(path).await,
+            Self::Oci(reference) => pull_local(reference).await,
         }
     }
 
@@ -103,6 +120,7 @@ impl TemplateSource {
         match self {
             Self::Git { .. } => true,
             Self::File(_) => false,
+            Self::Oci(_) => true,
         }
     }
 }
@@ -152,12 +170,29 @@ async fn clone_local(git_source: &GitTemplateSource) -> anyhow::Result<LocalTemp
     match clone_result {
         Ok(_) => Ok(LocalTemplateSource {
             root: path,
+            resolved_digest: None,
             _temp_dir: Some(temp_dir),
         }),
         Err(e) => Err(anyhow!("Error cloning Git repo {}: {}", url_str, e)),
     }
 }
 
+async fn pull_local(reference: &str) -> anyhow::Result<LocalTemplateSource> {
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().to_owned();
+
+    let mut client = spin_oci::Client::new(false, None).await?;
+    let digest = client
+        .pull_archive(reference, &path)
+        .await
+        .with_context(|| format!("Error pulling OCI artifact {reference}"))?;
+    Ok(LocalTemplateSource {
+        root: path,
+        resolved_digest: Some(digest),
+        _temp_dir: Some(temp_dir),
+    })
+}
+
 async fn version_matched_tag(url: &str, spin_version: &str) -> Option<String> {
     let preferred_tag = version_preferred_tag(spin_version);
 
@@ -181,10 +216,20 @@ fn version_preferred_tag(text: &str) -> String {
     format!("{}{}", TEMPLATE_VERSION_TAG_PREFIX, mm_version)
 }
 
+// Shows the digest templates were installed from, so that the same
+// templates can be installed elsewhere
+pub(crate) fn pinned_oci_reference(reference: String, digest: Option<String>) -> String {
+    match digest {
+        Some(digest) if !reference.contains('@') => format!("{reference}@{digest}"),
+        _ => reference,
+    }
+}
+
 async fn check_local(path: &Path) -> anyhow::Result<LocalTemplateSource> {
     if path.exists() {
         Ok(LocalTemplateSource {
             root: path.to_owned(),
+            resolved_digest: None,
             _temp_dir: None,
         })
     } else {
@@ -217,6 +262,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn pinned_oci_reference_adds_digest_only_if_unpinned() {
+        assert_eq!(
+            "example.com/tpls:v1@sha256:abc",
+            pinned_oci_reference(
+                "example.com/tpls:v1".to_owned(),
+                Some("sha256:abc".to_owned())
+            )
+        );
+        assert_eq!(
+            "example.com/tpls@sha256:abc",
+            pinned_oci_reference(
+                "example.com/tpls@sha256:abc".to_owned(),
+                Some("sha256:abc".to_owned())
+            )
+        );
+        assert_eq!(
+            "example.com/tpls:v1",
+            pinned_oci_reference("example.com/tpls:v1".to_owned(), None)
+        );
+    }
+
     #[test]
     fn preferred_tag_defaults_sensibly_on_bad_semver() {
         assert_eq!("spin/templates/v1.2", version_preferred_tag("1.2"));
@@ -9,7 +9,10 @@ use regex::Regex;
 
 use crate::{
     constraints::StringConstraints,
-    reader::{RawParameter, RawTemplateManifest, RawTemplateManifestV1, RawTemplateVariant},
+    reader::{
+        RawCondition, RawConditional, RawParameter, RawPostGenerateHook, RawTemplateManifest,
+        RawTemplateManifestV1, RawTemplateVariant,
+    },
     run::{Run, RunOptions},
     store::TemplateLayout,
 };
@@ -24,6 +27,8 @@ pub struct Template {
     trigger: TemplateTriggerCompatibility,
     variants: HashMap<TemplateVariantKind, TemplateVariant>,
     parameters: Vec<TemplateParameter>,
+    conditionals: Vec<TemplateConditional>,
+    post_generate: Vec<PostGenerateHook>,
     snippets_dir: Option<PathBuf>,
     content_dir: Option<PathBuf>, // TODO: maybe always need a spin.toml file in there?
 }
@@ -32,6 +37,7 @@ pub struct Template {
 enum InstalledFrom {
     Git(String),
     Directory(String),
+    Oci(String),
     Unknown,
 }
 
@@ -103,6 +109,7 @@ pub(crate) enum TemplateTriggerCompatibility {
 #[derive(Clone, Debug)]
 pub(crate) enum TemplateParameterDataType {
     String(StringConstraints),
+    Bool,
 }
 
 #[derive(Debug)]
@@ -113,6 +120,27 @@ pub(crate) struct TemplateParameter {
     default_value: Option<String>,
 }
 
+// Files which are generated only if a condition on the parameter values
+// is not met.
+#[derive(Debug)]
+pub(crate) struct TemplateConditional {
+    condition: Condition,
+    skip_files: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Condition {
+    ParameterEquals { parameter: String, value: String },
+}
+
+/// A command run in the generated directory after the template's files
+/// have been written, such as `npm install`.
+#[derive(Debug)]
+pub(crate) struct PostGenerateHook {
+    command: Vec<String>,
+    condition: Option<Condition>,
+}
+
 impl Template {
     pub(crate) fn load_from(layout: &TemplateLayout) -> anyhow::Result<Self> {
         let manifest_path = layout.manifest_path();
@@ -147,17 +175,24 @@ impl Template {
         let installed_from = read_install_record(layout);
 
         let template = match raw {
-            RawTemplateManifest::V1(raw) => Self {
-                id: raw.id.clone(),
-                tags: raw.tags.map(Self::normalize_tags).unwrap_or_default(),
-                description: raw.description.clone(),
-                installed_from,
-                trigger: Self::parse_trigger_type(raw.trigger_type, layout),
-                variants: Self::parse_template_variants(raw.new_application, raw.add_component),
-                parameters: Self::parse_parameters(&raw.parameters)?,
-                snippets_dir,
-                content_dir,
-            },
+            RawTemplateManifest::V1(raw) => {
+                let parameters = Self::parse_parameters(&raw.parameters)?;
+                let conditionals = Self::parse_conditionals(raw.conditionals, &parameters)?;
+                let post_generate = Self::parse_post_generate(raw.post_generate, &parameters)?;
+                Self {
+                    id: raw.id.clone(),
+                    tags: raw.tags.map(Self::normalize_tags).unwrap_or_default(),
+                    description: raw.description.clone(),
+                    installed_from,
+                    trigger: Self::parse_trigger_type(raw.trigger_type, layout),
+                    variants: Self::parse_template_variants(raw.new_application, raw.add_component),
+                    parameters,
+                    conditionals,
+                    post_generate,
+                    snippets_dir,
+                    content_dir,
+                }
+            }
         };
         Ok(template)
     }
@@ -214,6 +249,7 @@ impl Template {
         match &self.installed_from {
             InstalledFrom::Git(repo) => repo,
             InstalledFrom::Directory(path) => path,
+            InstalledFrom::Oci(reference) => reference,
             InstalledFrom::Unknown => "",
         }
     }
@@ -237,6 +273,15 @@ impl Template {
         self.parameters.iter().find(|p| p.id == name.as_ref())
     }
 
+    pub(crate) fn post_generate_hooks<'a>(
+        &'a self,
+        values: &'a HashMap<String, String>,
+    ) -> impl Iterator<Item = &'a PostGenerateHook> {
+        self.post_generate
+            .iter()
+            .filter(|h| h.condition.as_ref().map_or(true, |c| c.is_met(values)))
+    }
+
     pub(crate) fn content_dir(&self) -> &Option<PathBuf> {
         &self.content_dir
     }
@@ -343,16 +388,72 @@ impl Template {
         }
     }
 
+    fn parse_conditionals(
+        raw: Option<IndexMap<String, RawConditional>>,
+        parameters: &[TemplateParameter],
+    ) -> anyhow::Result<Vec<TemplateConditional>> {
+        raw.unwrap_or_default()
+            .into_iter()
+            .map(|(name, conditional)| {
+                let condition = Condition::from_raw(conditional.condition, parameters)
+                    .with_context(|| format!("Invalid condition for conditional '{name}'"))?;
+                Ok(TemplateConditional {
+                    condition,
+                    skip_files: conditional.skip_files,
+                })
+            })
+            .collect()
+    }
+
+    fn parse_post_generate(
+        raw: Option<Vec<RawPostGenerateHook>>,
+        parameters: &[TemplateParameter],
+    ) -> anyhow::Result<Vec<PostGenerateHook>> {
+        raw.unwrap_or_default()
+            .into_iter()
+            .map(|hook| {
+                if hook.command.is_empty() {
+                    anyhow::bail!("Post-generate hook command must not be empty");
+                }
+                let condition = hook
+                    .condition
+                    .map(|c| Condition::from_raw(c, parameters))
+                    .transpose()
+                    .with_context(|| {
+                        format!(
+                            "Invalid condition for post-generate hook '{}'",
+                            hook.command.join(" ")
+                        )
+                    })?;
+                Ok(PostGenerateHook {
+                    command: hook.command,
+                    condition,
+                })
+            })
+            .collect()
+    }
+
     pub(crate) fn included_files(
         &self,
         base: &std::path::Path,
         all_files: Vec<PathBuf>,
         variant_kind: &TemplateVariantInfo,
+        values: &HashMap<String, String>,
     ) -> Vec<PathBuf> {
         let variant = self.variant(variant_kind).unwrap(); // TODO: for now
+        let skipping_conditionals = self
+            .conditionals
+            .iter()
+            .filter(|c| c.condition.is_met(values))
+            .collect::<Vec<_>>();
         all_files
             .into_iter()
             .filter(|path| !variant.skip_file(base, path))
+            .filter(|path| {
+                !skipping_conditionals
+                    .iter()
+                    .any(|c| c.skip_file(base, path))
+            })
             .collect()
     }
 
@@ -414,7 +515,18 @@ impl Template {
 
 impl TemplateParameter {
     fn from_raw(id: &str, raw: &RawParameter) -> anyhow::Result<Self> {
-        let data_type = TemplateParameterDataType::parse(raw)?;
+        let data_type = TemplateParameterDataType::parse(raw)
+            .with_context(|| format!("Invalid parameter '{id}'"))?;
+        // Pattern defaults aren't checked, so as not to break existing templates
+        let check_default = match &data_type {
+            TemplateParameterDataType::String(constraints) => constraints.allowed_values.is_some(),
+            TemplateParameterDataType::Bool => true,
+        };
+        if let (true, Some(default_value)) = (check_default, &raw.default_value) {
+            data_type
+                .validate_value(default_value.clone())
+                .with_context(|| format!("Invalid default for parameter '{id}'"))?;
+        }
 
         Ok(Self {
             id: id.to_owned(),
@@ -449,6 +561,12 @@ impl TemplateParameterDataType {
     fn parse(raw: &RawParameter) -> anyhow::Result<Self> {
         match &raw.data_type[..] {
             "string" => Ok(Self::String(parse_string_constraints(raw)?)),
+            "bool" => {
+                if raw.pattern.is_some() || raw.allowed_values.is_some() {
+                    anyhow::bail!("A bool parameter can't have a pattern or allowed values");
+                }
+                Ok(Self::Bool)
+            }
             _ => Err(anyhow!("Unrecognised data type '{}'", raw.data_type)),
         }
     }
@@ -456,8 +574,49 @@ impl TemplateParameterDataType {
     fn validate_value(&self, value: String) -> anyhow::Result<String> {
         match self {
             TemplateParameterDataType::String(constraints) => constraints.validate(value),
+            TemplateParameterDataType::Bool => match &value[..] {
+                "true" | "false" => Ok(value),
+                _ => Err(anyhow!("Input '{}' must be 'true' or 'false'", value)),
+            },
+        }
+    }
+}
+
+impl TemplateConditional {
+    fn skip_file(&self, base: &std::path::Path, path: &std::path::Path) -> bool {
+        self.skip_files
+            .iter()
+            .map(|s| base.join(s))
+            .any(|f| path == f)
+    }
+}
+
+impl Condition {
+    fn from_raw(raw: RawCondition, parameters: &[TemplateParameter]) -> anyhow::Result<Self> {
+        match raw {
+            RawCondition::ParameterEquals { parameter, value } => {
+                let Some(p) = parameters.iter().find(|p| p.id == parameter) else {
+                    anyhow::bail!("Template does not contain a parameter named '{parameter}'");
+                };
+                p.validate_value(&value)?;
+                Ok(Self::ParameterEquals { parameter, value })
+            }
         }
     }
+
+    // A condition on a parameter which was not populated, because the variant
+    // skips it, is never met.
+    fn is_met(&self, values: &HashMap<String, String>) -> bool {
+        match self {
+            Self::ParameterEquals { parameter, value } => values.get(parameter) == Some(value),
+        }
+    }
+}
+
+impl PostGenerateHook {
+    pub(crate) fn command(&self) -> &[String] {
+        &self.command
+    }
 }
 
 impl TemplateVariant {
@@ -476,7 +635,19 @@ impl TemplateVariant {
 fn parse_string_constraints(raw: &RawParameter) -> anyhow::Result<StringConstraints> {
     let regex = raw.pattern.as_ref().map(|re| Regex::new(re)).transpose()?;
 
-    Ok(StringConstraints { regex })
+    if let Some(allowed_values) = &raw.allowed_values {
+        if allowed_values.is_empty() {
+            anyhow::bail!("Allowed values must not be empty");
+        }
+        if raw.pattern.is_some() {
+            anyhow::bail!("A parameter can't have both a pattern and allowed values");
+        }
+    }
+
+    Ok(StringConstraints {
+        regex,
+        allowed_values: raw.allowed_values.clone(),
+    })
 }
 
 fn read_install_record(layout: &TemplateLayout) -> InstalledFrom {
@@ -486,6 +657,9 @@ fn read_install_record(layout: &TemplateLayout) -> InstalledFrom {
     match installed_from_text.and_then(parse_installed_from) {
         Some(RawInstalledFrom::Git { git }) => InstalledFrom::Git(git),
         Some(RawInstalledFrom::File { dir }) => InstalledFrom::Directory(dir),
+        Some(RawInstalledFrom::Oci { oci, digest }) => {
+            InstalledFrom::Oci(crate::source::pinned_oci_reference(oci, digest))
+        }
         None => InstalledFrom::Unknown,
     }
 }
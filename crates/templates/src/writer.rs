@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use anyhow::Context;
 
@@ -18,6 +18,11 @@ impl TemplateOutputs {
     }
 
     pub async fn write(&self) -> anyhow::Result<()> {
+        // Check for conflicts with the existing manifest before writing
+        // anything, so that a conflicting component doesn't leave files behind
+        for output in &self.outputs {
+            output.check().await?;
+        }
         for output in &self.outputs {
             output.write().await?;
         }
@@ -26,6 +31,17 @@ impl TemplateOutputs {
 }
 
 impl TemplateOutput {
+    async fn check(&self) -> anyhow::Result<()> {
+        if let TemplateOutput::AppendToml(path, text) = &self {
+            let existing_toml = tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Can't open {} to append", path.display()))?;
+            check_append_toml(&existing_toml, text)
+                .with_context(|| format!("Can't add to {}", path.display()))?;
+        }
+        Ok(())
+    }
+
     pub async fn write(&self) -> anyhow::Result<()> {
         match &self {
             TemplateOutput::WriteFile(path, contents) => {
@@ -43,6 +59,11 @@ impl TemplateOutput {
                 let existing_toml = tokio::fs::read_to_string(path)
                     .await
                     .with_context(|| format!("Can't open {} to append", path.display()))?;
+                if check_append_toml(&existing_toml, text)? == Append::AlreadyPresent {
+                    return Ok(());
+                }
+                // Appending the text, rather than editing the parsed manifest,
+                // keeps the manifest's comments and formatting as they were
                 let new_toml = format!("{}\n\n{}", existing_toml.trim_end(), text);
                 tokio::fs::write(path, new_toml)
                     .await
@@ -62,6 +83,102 @@ impl TemplateOutput {
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum Append {
+    Needed,
+    AlreadyPresent,
+}
+
+// The components and HTTP routes of a manifest or snippet, of either manifest
+// version
+#[derive(Default)]
+struct ManifestEntries {
+    components: HashMap<String, toml::Value>,
+    routes: Vec<(String, String)>, // route, component
+}
+
+// Adding the same component again is a no-op, so that `spin add` can be
+// re-run safely; otherwise components and routes must not already exist.
+fn check_append_toml(existing: &str, text: &str) -> anyhow::Result<Append> {
+    // If either isn't valid TOML yet there's nothing to check against
+    let (Ok(existing), Ok(appending)) = (
+        toml::from_str::<toml::Value>(existing),
+        toml::from_str::<toml::Value>(text),
+    ) else {
+        return Ok(Append::Needed);
+    };
+    let existing = ManifestEntries::from_manifest(&existing);
+    let appending = ManifestEntries::from_manifest(&appending);
+
+    let already_present = !appending.components.is_empty()
+        && appending
+            .components
+            .iter()
+            .all(|(id, c)| existing.components.get(id) == Some(c))
+        && appending.routes.iter().all(|r| existing.routes.contains(r));
+    if already_present {
+        return Ok(Append::AlreadyPresent);
+    }
+
+    if let Some(id) = appending
+        .components
+        .keys()
+        .find(|id| existing.components.contains_key(*id))
+    {
+        anyhow::bail!("The application already contains a component named '{id}'");
+    }
+    for (route, _) in &appending.routes {
+        if let Some((_, component)) = existing.routes.iter().find(|(r, _)| r == route) {
+            anyhow::bail!("Route '{route}' is already used by component '{component}'");
+        }
+    }
+    Ok(Append::Needed)
+}
+
+impl ManifestEntries {
+    fn from_manifest(manifest: &toml::Value) -> Self {
+        let mut entries = Self::default();
+        match manifest.get("component") {
+            // Version 2: `[component.<id>]` tables, with routes in `[[trigger.http]]`
+            Some(toml::Value::Table(components)) => {
+                entries.components = components.clone().into_iter().collect();
+                let triggers = manifest
+                    .get("trigger")
+                    .and_then(|t| t.get("http"))
+                    .and_then(|h| h.as_array());
+                for trigger in triggers.into_iter().flatten() {
+                    let route = trigger.get("route").and_then(|r| r.as_str());
+                    let component = trigger.get("component").and_then(|c| c.as_str());
+                    if let Some(route) = route {
+                        let component = component.unwrap_or("(inline)");
+                        entries
+                            .routes
+                            .push((route.to_owned(), component.to_owned()));
+                    }
+                }
+            }
+            // Version 1: `[[component]]` tables, each with its route
+            Some(toml::Value::Array(components)) => {
+                for component in components {
+                    let Some(id) = component.get("id").and_then(|id| id.as_str()) else {
+                        continue;
+                    };
+                    let route = component
+                        .get("trigger")
+                        .and_then(|t| t.get("route"))
+                        .and_then(|r| r.as_str());
+                    if let Some(route) = route {
+                        entries.routes.push((route.to_owned(), id.to_owned()));
+                    }
+                    entries.components.insert(id.to_owned(), component.clone());
+                }
+            }
+            _ => (),
+        }
+        entries
+    }
+}
+
 fn merge_toml(existing: &str, target: &str, text: &str) -> anyhow::Result<String> {
     use toml_edit::{Document, Entry, Item};
 
@@ -126,6 +243,85 @@ url = { required = true }
         );
     }
 
+    const MANIFEST_WITH_HELLO: &str = r#"spin_manifest_version = 2
+
+# The hello component
+[[trigger.http]]
+route = "/hello"
+component = "hello"
+
+[component.hello]
+source = "hello.wasm"
+"#;
+
+    #[test]
+    fn appending_same_component_again_is_a_no_op() {
+        let snippet = r#"[[trigger.http]]
+route = "/hello"
+component = "hello"
+
+[component.hello]
+source = "hello.wasm"
+"#;
+        assert_eq!(
+            Append::AlreadyPresent,
+            check_append_toml(MANIFEST_WITH_HELLO, snippet).unwrap()
+        );
+
+        let new_snippet = snippet.replace("hello", "goodbye");
+        assert_eq!(
+            Append::Needed,
+            check_append_toml(MANIFEST_WITH_HELLO, &new_snippet).unwrap()
+        );
+    }
+
+    #[test]
+    fn appending_conflicting_component_or_route_fails() {
+        let same_id = r#"[[trigger.http]]
+route = "/other"
+component = "hello"
+
+[component.hello]
+source = "other.wasm"
+"#;
+        let err = check_append_toml(MANIFEST_WITH_HELLO, same_id).unwrap_err();
+        assert!(err.to_string().contains("component named 'hello'"), "{err}");
+
+        let same_route = r#"[[trigger.http]]
+route = "/hello"
+component = "other"
+
+[component.other]
+source = "other.wasm"
+"#;
+        let err = check_append_toml(MANIFEST_WITH_HELLO, same_route).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Route '/hello' is already used by component 'hello'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn can_detect_route_conflicts_in_v1_manifest() {
+        let manifest = r#"spin_manifest_version = "1"
+
+[[component]]
+id = "hello"
+source = "hello.wasm"
+[component.trigger]
+route = "/hello"
+"#;
+        let snippet = r#"[[component]]
+id = "other"
+source = "other.wasm"
+[component.trigger]
+route = "/hello"
+"#;
+        let err = check_append_toml(manifest, snippet).unwrap_err();
+        assert!(err.to_string().contains("Route '/hello'"), "{err}");
+    }
+
     #[test]
     fn can_merge_variables_into_manifest() {
         let manifest = r#"spin_version = "1"
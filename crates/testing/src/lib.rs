@@ -72,7 +72,17 @@ impl HttpTestConfig {
         self.http_trigger_config = HttpTriggerConfig {
             component: "test-component".to_string(),
             route: route.into(),
+            host: None,
+            static_dir: None,
             executor: None,
+            execution_timeout_ms: None,
+            max_concurrency: None,
+            max_queued_requests: None,
+            warm_instances: None,
+            event_stream: Default::default(),
+            middleware: vec![],
+            cache: None,
+            compression: None,
         };
         self
     }
@@ -85,7 +95,17 @@ impl HttpTestConfig {
         self.http_trigger_config = HttpTriggerConfig {
             component: "test-component".to_string(),
             route: route.into(),
+            host: None,
+            static_dir: None,
             executor: Some(HttpExecutorType::Wagi(wagi_config)),
+            execution_timeout_ms: None,
+            max_concurrency: None,
+            max_queued_requests: None,
+            warm_instances: None,
+            event_stream: Default::default(),
+            middleware: vec![],
+            cache: None,
+            compression: None,
         };
         self
     }
@@ -107,7 +127,7 @@ impl HttpTestConfig {
         TriggerExecutorBuilder::new(self.build_loader())
             .build(
                 TEST_APP_URI.to_string(),
-                RuntimeConfig::default(),
+                &RuntimeConfig::default(),
                 HostComponentInitData::default(),
             )
             .await
@@ -147,7 +167,7 @@ impl RedisTestConfig {
         TriggerExecutorBuilder::new(self.build_loader())
             .build(
                 TEST_APP_URI.to_string(),
-                RuntimeConfig::default(),
+                &RuntimeConfig::default(),
                 HostComponentInitData::default(),
             )
             .await
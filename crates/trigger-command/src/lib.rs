@@ -0,0 +1,123 @@
+//! Implementation for the Spin command trigger.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use spin_core::{async_trait, I32Exit, WasiVersion};
+use spin_trigger::{cli::NoArgs, EitherInstance, TriggerAppEngine, TriggerExecutor};
+
+pub(crate) type RuntimeData = ();
+
+const RUN_EXPORT: &str = "wasi:cli/run@0.2.0-rc-2023-10-18";
+const RUN_FUNC: &str = "run";
+
+/// The Spin command trigger, which runs a component once and exits with its
+/// exit status.
+pub struct CommandTrigger {
+    engine: TriggerAppEngine<Self>,
+    component_id: String,
+}
+
+/// Command trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandTriggerConfig {
+    /// Component ID to invoke
+    pub component: String,
+    /// Trigger executor (currently unused)
+    #[serde(default, skip_serializing)]
+    pub executor: IgnoredAny,
+}
+
+#[async_trait]
+impl TriggerExecutor for CommandTrigger {
+    const TRIGGER_TYPE: &'static str = "command";
+    type RuntimeData = RuntimeData;
+    type TriggerConfig = CommandTriggerConfig;
+    type RunConfig = NoArgs;
+
+    async fn new(engine: TriggerAppEngine<Self>) -> Result<Self> {
+        let mut components = engine
+            .trigger_configs()
+            .map(|(_, config)| config.component.clone());
+        let (Some(component_id), None) = (components.next(), components.next()) else {
+            bail!("an app with a command trigger must have exactly one command trigger");
+        };
+        Ok(Self {
+            engine,
+            component_id,
+        })
+    }
+
+    /// Run the component to completion.
+    ///
+    /// If the component exits with a non-zero status, this returns an
+    /// [`I32Exit`] error carrying that status.
+    async fn run(self, _config: Self::RunConfig) -> Result<()> {
+        let component_id = &self.component_id;
+        tracing::info!("Running component {component_id:?}");
+        let shutdown_signal = self.engine.shutdown_signal();
+        let code = tokio::select! {
+            code = self.execute() => code?,
+            _ = shutdown_signal.triggered() => {
+                tracing::info!("Interrupted component {component_id:?}");
+                return Ok(());
+            }
+        };
+        tracing::info!("Component {component_id:?} exited with status {code}");
+        if code != 0 {
+            return Err(I32Exit(code).into());
+        }
+        Ok(())
+    }
+}
+
+impl CommandTrigger {
+    // Runs the component, returning its exit status.
+    #[tracing::instrument(
+        name = "spin_trigger_command.execute",
+        skip_all,
+        fields(spin.component_id = self.component_id.as_str())
+    )]
+    async fn execute(&self) -> Result<i32> {
+        let component_id = &self.component_id;
+        let mut store_builder = self
+            .engine
+            .store_builder(component_id, WasiVersion::Preview2)?;
+        // The component's standard streams are the process's own, so that it
+        // can be used in a pipeline. Its stderr is still logged as configured.
+        store_builder.inherit_stdin();
+        store_builder.inherit_stdout();
+        let (instance, mut store) = self
+            .engine
+            .prepare_instance_with_store(component_id, store_builder)
+            .await?;
+        let EitherInstance::Component(instance) = instance else {
+            unreachable!()
+        };
+
+        let exported = instance
+            .exports(&mut store)
+            .instance(RUN_EXPORT)
+            .map(|mut exports| exports.typed_func::<(), (Result<(), ()>,)>(RUN_FUNC))
+            .transpose()?;
+        let func = match exported {
+            Some(func) => func,
+            None => instance
+                .get_typed_func::<(), (Result<(), ()>,)>(&mut store, RUN_FUNC)
+                .map_err(|_| {
+                    anyhow!(
+                        "component {component_id:?} exports neither {RUN_EXPORT} nor `{RUN_FUNC}`"
+                    )
+                })?,
+        };
+
+        match func.call_async(&mut store, ()).await {
+            Ok((Ok(()),)) => Ok(0),
+            Ok((Err(()),)) => Ok(1),
+            Err(err) => match err.root_cause().downcast_ref::<I32Exit>() {
+                Some(exit) => Ok(exit.0),
+                None => Err(err),
+            },
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Implementation for the Spin cron trigger.
+
+mod schedule;
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use futures::future::try_join_all;
+use rand::Rng;
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use spin_core::async_trait;
+use spin_trigger::{cli::NoArgs, EitherInstance, TriggerAppEngine, TriggerExecutor};
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
+
+pub use crate::schedule::Schedule;
+
+pub(crate) type RuntimeData = ();
+
+const TIMER_TRIGGER_EXPORT: &str = "fermyon:spin/timer-trigger@2.1.0";
+
+/// The Spin cron trigger.
+pub struct CronTrigger {
+    engine: TriggerAppEngine<Self>,
+    schedules: Vec<ComponentSchedule>,
+    // Read-locked by each running invocation, so that shutdown can wait for them
+    in_flight: Arc<RwLock<()>>,
+}
+
+struct ComponentSchedule {
+    component_id: String,
+    expression: String,
+    schedule: Schedule,
+    jitter: Duration,
+    overlap: OverlapPolicy,
+    // Held while an invocation runs, for the `skip` and `queue` policies
+    running: Arc<Mutex<()>>,
+}
+
+/// Cron trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CronTriggerConfig {
+    /// Component ID to invoke
+    pub component: String,
+    /// Cron schedule, e.g. `*/5 * * * *` to run every five minutes
+    pub schedule: String,
+    /// Maximum random delay, in milliseconds, added to each invocation
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// What to do when an invocation is due while the previous one is running
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+    /// Trigger executor (currently unused)
+    #[serde(default, skip_serializing)]
+    pub executor: IgnoredAny,
+}
+
+/// What to do when an invocation is due while the previous invocation of the
+/// same trigger is still running.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    /// Skip the new invocation.
+    #[default]
+    Skip,
+    /// Run the new invocation once the previous one finishes.
+    Queue,
+    /// Run the new invocation alongside the previous one.
+    Concurrent,
+}
+
+#[async_trait]
+impl TriggerExecutor for CronTrigger {
+    const TRIGGER_TYPE: &'static str = "cron";
+    type RuntimeData = RuntimeData;
+    type TriggerConfig = CronTriggerConfig;
+    type RunConfig = NoArgs;
+
+    async fn new(engine: TriggerAppEngine<Self>) -> Result<Self> {
+        let schedules = engine
+            .trigger_configs()
+            .map(|(_, config)| {
+                let schedule = config.schedule.parse().with_context(|| {
+                    format!("invalid schedule for component {:?}", config.component)
+                })?;
+                Ok(ComponentSchedule {
+                    component_id: config.component.clone(),
+                    expression: config.schedule.clone(),
+                    schedule,
+                    jitter: Duration::from_millis(config.jitter_ms),
+                    overlap: config.overlap,
+                    running: Default::default(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            engine,
+            schedules,
+            in_flight: Default::default(),
+        })
+    }
+
+    /// Run the cron trigger until it is shut down.
+    async fn run(self, _config: Self::RunConfig) -> Result<()> {
+        let self_ = Arc::new(self);
+        let schedulers = (0..self_.schedules.len()).map(|idx| {
+            let self_ = self_.clone();
+            tokio::spawn(async move { self_.run_schedule(idx).await })
+        });
+        try_join_all(schedulers).await?;
+        // Wait for running invocations to finish
+        drop(self_.in_flight.write().await);
+        Ok(())
+    }
+}
+
+impl CronTrigger {
+    // Invokes the scheduled component at each time matching its schedule,
+    // until shut down.
+    async fn run_schedule(self: Arc<Self>, idx: usize) {
+        let scheduled = &self.schedules[idx];
+        let component_id = &scheduled.component_id;
+        let shutdown_signal = self.engine.shutdown_signal();
+        tracing::info!(
+            "Scheduling component {component_id:?} with schedule {:?}",
+            scheduled.expression
+        );
+        let mut previous_time = Utc::now();
+        loop {
+            // Never schedule the same time twice, even if the clock goes back
+            let after = previous_time.max(Utc::now());
+            let Some(scheduled_time) = scheduled.schedule.next_after(after) else {
+                tracing::warn!("Schedule for component {component_id:?} will never run again");
+                return;
+            };
+            let delay = (scheduled_time - Utc::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(delay + jitter(scheduled.jitter)) => {}
+                _ = shutdown_signal.triggered() => return,
+            }
+            previous_time = scheduled_time;
+
+            match scheduled.overlap {
+                OverlapPolicy::Concurrent => self.spawn_invocation(idx, scheduled_time, None).await,
+                OverlapPolicy::Queue => {
+                    let running = scheduled.running.clone().lock_owned().await;
+                    if shutdown_signal.is_triggered() {
+                        return;
+                    }
+                    self.spawn_invocation(idx, scheduled_time, Some(running))
+                        .await;
+                }
+                OverlapPolicy::Skip => match scheduled.running.clone().try_lock_owned() {
+                    Ok(running) => {
+                        self.spawn_invocation(idx, scheduled_time, Some(running))
+                            .await
+                    }
+                    Err(_) => {
+                        tracing::warn!(
+                            "Skipping invocation of component {component_id:?} scheduled for {scheduled_time}: previous invocation still running"
+                        );
+                    }
+                },
+            }
+        }
+    }
+
+    // Runs an invocation in the background, holding `running` (if any) until
+    // it finishes.
+    async fn spawn_invocation(
+        self: &Arc<Self>,
+        idx: usize,
+        scheduled_time: DateTime<Utc>,
+        running: Option<OwnedMutexGuard<()>>,
+    ) {
+        let in_flight = self.in_flight.clone().read_owned().await;
+        let self_ = self.clone();
+        tokio::spawn(async move {
+            self_.invoke(idx, scheduled_time).await;
+            drop(running);
+            drop(in_flight);
+        });
+    }
+
+    async fn invoke(&self, idx: usize, scheduled_time: DateTime<Utc>) {
+        let component_id = &self.schedules[idx].component_id;
+        tracing::info!("Executing component {component_id:?} scheduled for {scheduled_time}");
+        if let Err(err) = self.execute(component_id, scheduled_time).await {
+            tracing::error!("Error from component {component_id:?}: {err:?}");
+        }
+    }
+
+    #[tracing::instrument(
+        name = "spin_trigger_cron.execute",
+        skip_all,
+        fields(spin.component_id = component_id, spin.scheduled_time = %scheduled_time)
+    )]
+    async fn execute(&self, component_id: &str, scheduled_time: DateTime<Utc>) -> Result<()> {
+        let (instance, mut store) = self.engine.prepare_instance(component_id).await?;
+        let EitherInstance::Component(instance) = instance else {
+            unreachable!()
+        };
+
+        let func = instance
+            .exports(&mut store)
+            .instance(TIMER_TRIGGER_EXPORT)
+            .ok_or_else(|| anyhow!("no {TIMER_TRIGGER_EXPORT} instance found"))?
+            .typed_func::<(u64,), (Result<(), String>,)>("handle-tick")?;
+
+        let scheduled_time_ms = scheduled_time.timestamp_millis().try_into()?;
+        let (result,) = func.call_async(store, (scheduled_time_ms,)).await?;
+        result.map_err(|err| anyhow!("`handle-tick` returned an error: {err}"))
+    }
+}
+
+// Returns a random delay of up to `max`.
+fn jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    rand::thread_rng().gen_range(Duration::ZERO..=max)
+}
@@ -0,0 +1,261 @@
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+
+// Don't search further ahead than this for a matching time; a schedule with
+// no match in this period (e.g. February 30th) never fires.
+const MAX_SEARCH_DAYS: i64 = 5 * 366;
+
+/// A cron schedule.
+///
+/// Schedules have five fields (`minute hour day-of-month month day-of-week`),
+/// or six with a leading `second` field. Each field is `*`, a value, a range
+/// (`1-5`), or a comma-separated list of these, optionally with a step
+/// (`*/15`, `0-30/10`). Day-of-week is 0-7, where both 0 and 7 are Sunday.
+/// The `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` shorthands
+/// are also supported. All times are UTC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    seconds: Field,
+    minutes: Field,
+    hours: Field,
+    days_of_month: Field,
+    months: Field,
+    days_of_week: Field,
+}
+
+impl Schedule {
+    /// Returns the first time matching this schedule strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.with_nanosecond(0)? + Duration::seconds(1);
+        let limit = t + Duration::days(MAX_SEARCH_DAYS);
+        while t <= limit {
+            if !self.months.contains(t.month()) {
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    month => (t.year(), month + 1),
+                };
+                t = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.matches_day(t) {
+                t = t.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc();
+            } else if !self.hours.contains(t.hour()) {
+                t = t.with_minute(0)?.with_second(0)? + Duration::hours(1);
+            } else if !self.minutes.contains(t.minute()) {
+                t = t.with_second(0)? + Duration::minutes(1);
+            } else if !self.seconds.contains(t.second()) {
+                t += Duration::seconds(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    // As in standard cron, if both day fields are restricted a day matching
+    // either one matches.
+    fn matches_day(&self, t: DateTime<Utc>) -> bool {
+        let day_of_month = self.days_of_month.contains(t.day());
+        let day_of_week = self
+            .days_of_week
+            .contains(t.weekday().num_days_from_sunday());
+        match (self.days_of_month.is_any(), self.days_of_week.is_any()) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let expanded = match s.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let fields = match fields.as_slice() {
+            [minute, hour, dom, month, dow] => ["0", minute, hour, dom, month, dow],
+            [second, minute, hour, dom, month, dow] => [second, minute, hour, dom, month, dow],
+            _ => bail!("invalid cron schedule {s:?}: expected 5 or 6 fields"),
+        };
+        let parse = |idx: usize, name: &str, min: u32, max: u32| {
+            Field::parse(fields[idx], min, max)
+                .with_context(|| format!("invalid {name} field in cron schedule {s:?}"))
+        };
+        let mut days_of_week = parse(5, "day-of-week", 0, 7)?;
+        // Sunday may be written as 7
+        if days_of_week.contains(7) {
+            days_of_week.0 |= 1;
+        }
+        Ok(Self {
+            seconds: parse(0, "second", 0, 59)?,
+            minutes: parse(1, "minute", 0, 59)?,
+            hours: parse(2, "hour", 0, 23)?,
+            days_of_month: parse(3, "day-of-month", 1, 31)?,
+            months: parse(4, "month", 1, 12)?,
+            days_of_week,
+        })
+    }
+}
+
+/// The set of values matched by a schedule field, as a bitmask.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Field(u64, bool);
+
+impl Field {
+    fn parse(s: &str, min: u32, max: u32) -> Result<Self> {
+        let mut mask = 0;
+        for part in s.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step.parse().context("invalid step")?;
+                    ensure!(step > 0, "step must be greater than zero");
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+                    None => {
+                        let value = parse_value(range)?;
+                        // `5/10` means `5-max/10`
+                        (value, if step > 1 { max } else { value })
+                    }
+                },
+            };
+            ensure!(
+                min <= start && start <= end && end <= max,
+                "{part:?} is out of range {min}-{max}"
+            );
+            for value in (start..=end).step_by(step as usize) {
+                mask |= 1 << value;
+            }
+        }
+        Ok(Self(mask, s == "*"))
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.0 & (1 << value) != 0
+    }
+
+    fn is_any(&self) -> bool {
+        self.1
+    }
+}
+
+fn parse_value(s: &str) -> Result<u32> {
+    s.parse().with_context(|| format!("invalid value {s:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn next(schedule: &str, after: &str) -> DateTime<Utc> {
+        schedule
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(utc(after))
+            .unwrap()
+    }
+
+    #[test]
+    fn every_minute() {
+        assert_eq!(
+            next("* * * * *", "2023-11-28T10:15:30Z"),
+            utc("2023-11-28T10:16:00Z")
+        );
+    }
+
+    #[test]
+    fn with_seconds() {
+        assert_eq!(
+            next("*/15 * * * * *", "2023-11-28T10:15:30Z"),
+            utc("2023-11-28T10:15:45Z")
+        );
+    }
+
+    #[test]
+    fn steps_and_ranges() {
+        assert_eq!(
+            next("0 9-17/4 * * *", "2023-11-28T13:00:00Z"),
+            utc("2023-11-28T17:00:00Z")
+        );
+        assert_eq!(
+            next("0 9-17/4 * * *", "2023-11-28T17:00:00Z"),
+            utc("2023-11-29T09:00:00Z")
+        );
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            next("0,30 * * * *", "2023-11-28T10:15:00Z"),
+            utc("2023-11-28T10:30:00Z")
+        );
+    }
+
+    #[test]
+    fn rolls_over_months_and_years() {
+        assert_eq!(
+            next("@yearly", "2023-11-28T10:15:00Z"),
+            utc("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            next("0 0 31 * *", "2023-11-28T10:15:00Z"),
+            utc("2023-12-31T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn days_of_week() {
+        // 2023-11-28 is a Tuesday
+        assert_eq!(
+            next("0 0 * * 0", "2023-11-28T10:15:00Z"),
+            utc("2023-12-03T00:00:00Z")
+        );
+        assert_eq!(
+            "0 0 * * 0".parse::<Schedule>().unwrap(),
+            "0 0 * * 7".parse::<Schedule>().unwrap()
+        );
+        // Restricting both day fields matches either
+        assert_eq!(
+            next("0 0 1 * 0", "2023-11-28T10:15:00Z"),
+            utc("2023-12-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn impossible_schedule_never_fires() {
+        let schedule: Schedule = "0 0 30 2 *".parse().unwrap();
+        assert_eq!(schedule.next_after(utc("2023-11-28T10:15:00Z")), None);
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        for schedule in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "a * * * *",
+        ] {
+            schedule
+                .parse::<Schedule>()
+                .expect_err(&format!("{schedule:?} should be invalid"));
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! gRPC message framing and status trailers.
+
+use http::{HeaderMap, HeaderValue};
+use hyper::body::Bytes;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use wasmtime::component::{ComponentType, Lift};
+
+// Each message is prefixed with a compressed flag and a 4-byte big-endian length
+const PREFIX_LEN: usize = 5;
+
+// Status messages are percent-encoded, leaving printable ASCII as is
+const STATUS_MESSAGE_ENCODE_SET: &AsciiSet = &CONTROLS.add(b'%');
+
+// Status codes used by the host; see
+// https://grpc.github.io/grpc/core/md_doc_statuscodes.html
+const OK: u32 = 0;
+const CANCELLED: u32 = 1;
+const UNIMPLEMENTED: u32 = 12;
+const INTERNAL: u32 = 13;
+
+/// The final status of a call.
+#[derive(Clone, Debug, PartialEq, Eq, ComponentType, Lift)]
+#[component(record)]
+pub(crate) struct Status {
+    code: u32,
+    message: String,
+}
+
+impl Status {
+    fn new(code: u32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn ok() -> Self {
+        Self::new(OK, "")
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(CANCELLED, "the client cancelled the call")
+    }
+
+    pub fn unimplemented(message: impl Into<String>) -> Self {
+        Self::new(UNIMPLEMENTED, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(INTERNAL, message)
+    }
+
+    /// Returns the trailers which end a response with this status.
+    pub fn to_trailers(&self) -> HeaderMap {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from(self.code));
+        if !self.message.is_empty() {
+            let message = utf8_percent_encode(&self.message, STATUS_MESSAGE_ENCODE_SET).to_string();
+            // Percent-encoding leaves only printable ASCII, so this can't fail
+            if let Ok(message) = HeaderValue::try_from(message) {
+                trailers.insert("grpc-message", message);
+            }
+        }
+        trailers
+    }
+}
+
+/// Decodes a request body holding exactly one message.
+pub(crate) fn decode_message(body: &Bytes) -> Result<Bytes, Status> {
+    if body.len() < PREFIX_LEN {
+        return Err(Status::internal("request message is truncated"));
+    }
+    if body[0] != 0 {
+        return Err(Status::unimplemented(
+            "compressed request messages are not supported",
+        ));
+    }
+    let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+    if body.len() - PREFIX_LEN != len {
+        return Err(Status::internal(
+            "request body must contain exactly one message",
+        ));
+    }
+    Ok(body.slice(PREFIX_LEN..))
+}
+
+/// Encodes an uncompressed message for a response body.
+pub(crate) fn encode_message(message: &[u8]) -> Bytes {
+    let mut framed = Vec::with_capacity(PREFIX_LEN + message.len());
+    framed.push(0);
+    framed.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    framed.extend_from_slice(message);
+    framed.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_messages() {
+        let framed = encode_message(b"hello");
+        assert_eq!(&framed[..], b"\0\0\0\0\x05hello");
+        assert_eq!(
+            decode_message(&framed).unwrap(),
+            Bytes::from_static(b"hello")
+        );
+    }
+
+    #[test]
+    fn rejects_bad_request_bodies() {
+        let compressed = Bytes::from_static(b"\x01\0\0\0\x01x");
+        assert_eq!(decode_message(&compressed).unwrap_err().code, UNIMPLEMENTED);
+        let truncated = Bytes::from_static(b"\0\0\0\0\x05hel");
+        assert_eq!(decode_message(&truncated).unwrap_err().code, INTERNAL);
+        let two_messages = Bytes::from_static(b"\0\0\0\0\x01a\0\0\0\0\x01b");
+        assert_eq!(decode_message(&two_messages).unwrap_err().code, INTERNAL);
+    }
+
+    #[test]
+    fn percent_encodes_status_messages() {
+        let trailers = Status::internal("50% done\n").to_trailers();
+        assert_eq!(trailers["grpc-status"], "13");
+        assert_eq!(trailers["grpc-message"], "50%25 done%0A");
+    }
+}
@@ -0,0 +1,501 @@
+//! Implementation for the Spin gRPC trigger.
+
+mod codec;
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use futures::{channel::mpsc, SinkExt, Stream};
+use http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, Request, Response, StatusCode};
+use http_body_util::BodyExt;
+use hyper::{
+    body::{Bytes, Frame, Incoming},
+    server::conn::http2,
+    service::service_fn,
+};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use spin_core::{async_trait, Data, EngineBuilder, Linker};
+use spin_trigger::{EitherInstance, TriggerAppEngine, TriggerExecutor};
+use tokio::{net::TcpListener, task::JoinSet};
+use wasmtime::{
+    component::{ComponentType, Lower},
+    StoreContextMut,
+};
+use wasmtime_wasi_http::types::{HostFutureIncomingResponse, OutgoingRequest};
+
+use codec::Status;
+
+const GRPC_TRIGGER_EXPORT: &str = "fermyon:spin/grpc-trigger@2.1.0";
+const GRPC_STREAM_INTERFACE: &str = "fermyon:spin/grpc-stream@2.1.0";
+
+// The number of response messages which may be buffered for a call before a
+// streaming component waits for the client to catch up
+const STREAM_CAPACITY: usize = 8;
+
+/// The Spin gRPC trigger.
+pub struct GrpcTrigger {
+    engine: TriggerAppEngine<Self>,
+    // Method path, e.g. "/helloworld.Greeter/SayHello" -> route
+    routes: HashMap<String, Route>,
+}
+
+/// gRPC trigger configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GrpcTriggerConfig {
+    /// Component ID to invoke
+    pub component: String,
+    /// Fully qualified service name, e.g. `helloworld.Greeter`
+    pub service: String,
+    /// The service's methods handled by the component, and the kind of each
+    pub methods: HashMap<String, MethodKind>,
+    /// Trigger executor (currently unused)
+    #[serde(default, skip_serializing)]
+    pub executor: IgnoredAny,
+}
+
+/// The kinds of gRPC method the trigger can dispatch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MethodKind {
+    /// A single request message and a single response message
+    Unary,
+    /// A single request message and a stream of response messages
+    ServerStreaming,
+}
+
+#[derive(Clone, Debug)]
+struct Route {
+    component: String,
+    kind: MethodKind,
+}
+
+#[derive(Args)]
+pub struct CliArgs {
+    /// IP address and port to listen on
+    #[clap(long = "listen", default_value = "127.0.0.1:50051", value_parser = parse_listen_addr)]
+    pub address: SocketAddr,
+}
+
+/// Per-call state for the gRPC trigger.
+#[derive(Default)]
+pub struct GrpcRuntimeData {
+    // Where `grpc-stream.send` writes messages, if the call is server-streaming
+    response_stream: Option<mpsc::Sender<Frame<Bytes>>>,
+}
+
+impl spin_core::OutboundWasiHttpHandler for GrpcRuntimeData {
+    fn send_request(
+        data: &mut Data<Self>,
+        request: OutgoingRequest,
+    ) -> wasmtime::Result<wasmtime::component::Resource<HostFutureIncomingResponse>>
+    where
+        Self: Sized,
+    {
+        spin_core::send_outgoing_request(data, request)
+    }
+}
+
+// The record passed to the guest for each call
+#[derive(ComponentType, Lower)]
+#[component(record)]
+struct GrpcRequest {
+    method: String,
+    metadata: Vec<(String, String)>,
+    message: Vec<u8>,
+}
+
+#[async_trait]
+impl TriggerExecutor for GrpcTrigger {
+    const TRIGGER_TYPE: &'static str = "grpc";
+    type RuntimeData = GrpcRuntimeData;
+    type TriggerConfig = GrpcTriggerConfig;
+    type RunConfig = CliArgs;
+
+    async fn new(engine: TriggerAppEngine<Self>) -> Result<Self> {
+        let routes = build_routes(engine.trigger_configs().map(|(_, config)| config))?;
+        Ok(Self { engine, routes })
+    }
+
+    /// Run the gRPC trigger until it is shut down.
+    async fn run(self, config: Self::RunConfig) -> Result<()> {
+        self.serve(config.address).await
+    }
+
+    fn configure_engine(builder: &mut EngineBuilder<Self::RuntimeData>) -> Result<()> {
+        builder.link_import(|linker, _| add_grpc_stream_to_linker(linker))
+    }
+}
+
+impl GrpcTrigger {
+    async fn serve(self, listen_addr: SocketAddr) -> Result<()> {
+        let self_ = Arc::new(self);
+        let shutdown_signal = self_.engine.shutdown_signal().clone();
+
+        let listener = TcpListener::bind(listen_addr)
+            .await
+            .with_context(|| format!("Unable to listen on {}", listen_addr))?;
+        tracing::info!("Serving gRPC on {listen_addr}");
+
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted?;
+                    Self::serve_connection(&mut connections, self_.clone(), stream);
+                }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                _ = shutdown_signal.triggered() => break,
+            }
+        }
+
+        // Stop accepting connections and wait for open connections to finish
+        // their in-flight calls
+        drop(listener);
+        tracing::info!(
+            "Waiting for {} open connection(s) to finish",
+            connections.len()
+        );
+        while connections.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    fn serve_connection(
+        connections: &mut JoinSet<()>,
+        self_: Arc<Self>,
+        stream: tokio::net::TcpStream,
+    ) {
+        let shutdown_signal = self_.engine.shutdown_signal().clone();
+        connections.spawn(async move {
+            let connection = http2::Builder::new(TokioExecutor).serve_connection(
+                stream,
+                service_fn(move |request| {
+                    let self_ = self_.clone();
+                    async move { Ok::<_, Infallible>(self_.handle(request).await) }
+                }),
+            );
+            tokio::pin!(connection);
+            let mut draining = false;
+            let result = loop {
+                tokio::select! {
+                    result = connection.as_mut() => break result,
+                    // Finish any in-flight calls, then close the connection
+                    _ = shutdown_signal.triggered(), if !draining => {
+                        draining = true;
+                        connection.as_mut().graceful_shutdown();
+                    }
+                }
+            };
+            if let Err(err) = result {
+                tracing::warn!("{err:?}");
+            }
+        });
+    }
+
+    // Starts handling a call, returning the response headers immediately. The
+    // response messages and final status follow on the body as the component
+    // produces them.
+    async fn handle(self: Arc<Self>, request: Request<Incoming>) -> Response<ResponseBody> {
+        let is_grpc = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/grpc"));
+        if !is_grpc {
+            let mut response = Response::new(ResponseBody::empty());
+            *response.status_mut() = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+            return response;
+        }
+
+        let (mut frames, body) = ResponseBody::channel();
+        let response = Response::builder()
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/grpc"))
+            .body(body)
+            .unwrap();
+        tokio::spawn(async move {
+            let status = self.call(request, frames.clone()).await;
+            // The client may have gone away, in which case there's no one to
+            // tell
+            _ = frames.send(Frame::trailers(status.to_trailers())).await;
+        });
+        response
+    }
+
+    // Runs a call to completion, returning its final status.
+    async fn call(&self, request: Request<Incoming>, frames: mpsc::Sender<Frame<Bytes>>) -> Status {
+        let method = request.uri().path().to_owned();
+        let Some(route) = self.routes.get(&method) else {
+            return Status::unimplemented(format!("unknown method {method}"));
+        };
+
+        let (parts, body) = request.into_parts();
+        let body = match body.collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(err) => return Status::internal(format!("failed to read request: {err}")),
+        };
+        let message = match codec::decode_message(&body) {
+            Ok(message) => message,
+            Err(status) => return status,
+        };
+        let request = GrpcRequest {
+            method: method.clone(),
+            metadata: metadata(&parts.headers),
+            message: message.to_vec(),
+        };
+
+        match self.execute(route, request, frames).await {
+            Ok(status) => status,
+            Err(err) => {
+                tracing::error!(
+                    "Error from component {:?} handling {method}: {err:?}",
+                    route.component
+                );
+                Status::internal("component error")
+            }
+        }
+    }
+
+    #[tracing::instrument(
+        name = "spin_trigger_grpc.handle_call",
+        skip_all,
+        fields(
+            otel.kind = "server",
+            spin.component_id = route.component.as_str(),
+            rpc.system = "grpc",
+            rpc.method = request.method.as_str(),
+        )
+    )]
+    async fn execute(
+        &self,
+        route: &Route,
+        request: GrpcRequest,
+        mut frames: mpsc::Sender<Frame<Bytes>>,
+    ) -> Result<Status> {
+        tracing::trace!(
+            "Executing component {:?} for {}",
+            route.component,
+            request.method
+        );
+        let (instance, mut store) = self.engine.prepare_instance(&route.component).await?;
+        let EitherInstance::Component(instance) = instance else {
+            unreachable!()
+        };
+        let missing_export = || anyhow!("no {GRPC_TRIGGER_EXPORT} instance found");
+
+        match route.kind {
+            MethodKind::Unary => {
+                let func = instance
+                    .exports(&mut store)
+                    .instance(GRPC_TRIGGER_EXPORT)
+                    .ok_or_else(missing_export)?
+                    .typed_func::<(GrpcRequest,), (Result<Vec<u8>, Status>,)>("handle-unary")?;
+                let (result,) = func.call_async(store, (request,)).await?;
+                let message = match result {
+                    Ok(message) => message,
+                    Err(status) => return Ok(status),
+                };
+                if frames
+                    .send(Frame::data(codec::encode_message(&message)))
+                    .await
+                    .is_err()
+                {
+                    return Ok(Status::cancelled());
+                }
+                Ok(Status::ok())
+            }
+            MethodKind::ServerStreaming => {
+                let func = instance
+                    .exports(&mut store)
+                    .instance(GRPC_TRIGGER_EXPORT)
+                    .ok_or_else(missing_export)?
+                    .typed_func::<(GrpcRequest,), (Result<(), Status>,)>(
+                        "handle-server-streaming",
+                    )?;
+                store.as_mut().data_mut().as_mut().response_stream = Some(frames);
+                let (result,) = func.call_async(store, (request,)).await?;
+                Ok(result.err().unwrap_or_else(Status::ok))
+            }
+        }
+    }
+}
+
+// Builds the method routing table, rejecting methods handled by more than one
+// component.
+fn build_routes<'a>(
+    configs: impl IntoIterator<Item = &'a GrpcTriggerConfig>,
+) -> Result<HashMap<String, Route>> {
+    let mut routes = HashMap::new();
+    for config in configs {
+        for (method, kind) in &config.methods {
+            let path = format!("/{}/{method}", config.service);
+            let route = Route {
+                component: config.component.clone(),
+                kind: *kind,
+            };
+            if let Some(existing) = routes.insert(path.clone(), route) {
+                bail!(
+                    "gRPC method {path} is handled by both component {:?} and component {:?}",
+                    existing.component,
+                    config.component
+                );
+            }
+        }
+    }
+    Ok(routes)
+}
+
+// The call's metadata, less the headers which are part of the gRPC protocol
+// itself. Values which aren't valid strings are skipped.
+fn metadata(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter(|(name, _)| *name != CONTENT_TYPE && *name != "te")
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+        .collect()
+}
+
+// Links the `grpc-stream` interface, which sends messages on the current
+// server-streaming call.
+fn add_grpc_stream_to_linker(linker: &mut Linker<GrpcRuntimeData>) -> Result<()> {
+    let mut instance = linker.instance(GRPC_STREAM_INTERFACE)?;
+    instance.func_wrap_async(
+        "send",
+        |mut store: StoreContextMut<'_, Data<GrpcRuntimeData>>, (message,): (Vec<u8>,)| {
+            let sender = store.data_mut().as_mut().response_stream.clone();
+            Box::new(async move {
+                let Some(mut sender) = sender else {
+                    return Ok((Err("the current call is not server-streaming".to_owned()),));
+                };
+                // Waits while the buffer is full, so a slow client applies
+                // backpressure to the component
+                let result = sender
+                    .send(Frame::data(codec::encode_message(&message)))
+                    .await
+                    .map_err(|_| "the client has closed the call".to_owned());
+                Ok((result,))
+            })
+        },
+    )?;
+    Ok(())
+}
+
+/// A gRPC response body, whose frames are sent by the task running the call.
+pub struct ResponseBody {
+    frames: Option<mpsc::Receiver<Frame<Bytes>>>,
+}
+
+impl ResponseBody {
+    fn channel() -> (mpsc::Sender<Frame<Bytes>>, Self) {
+        let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+        let body = Self {
+            frames: Some(receiver),
+        };
+        (sender, body)
+    }
+
+    fn empty() -> Self {
+        Self { frames: None }
+    }
+}
+
+impl hyper::body::Body for ResponseBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match &mut self.frames {
+            Some(frames) => Pin::new(frames).poll_next(cx).map(|frame| frame.map(Ok)),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct TokioExecutor;
+
+impl<F> hyper::rt::Executor<F> for TokioExecutor
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::spawn(fut);
+    }
+}
+
+fn parse_listen_addr(addr: &str) -> anyhow::Result<SocketAddr> {
+    let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+    // Prefer 127.0.0.1 over e.g. [::1], as the HTTP trigger does
+    if let Some(addr) = addrs
+        .iter()
+        .find(|addr| addr.is_ipv4() && addr.ip() == Ipv4Addr::LOCALHOST)
+    {
+        return Ok(*addr);
+    }
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("could not resolve {addr}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(component: &str, methods: serde_json::Value) -> GrpcTriggerConfig {
+        serde_json::from_value(serde_json::json!({
+            "component": component,
+            "service": "helloworld.Greeter",
+            "methods": methods,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn routes_methods_by_path() {
+        let configs = [config(
+            "greeter",
+            serde_json::json!({ "SayHello": "unary", "SayHellos": "server-streaming" }),
+        )];
+        let routes = build_routes(&configs).unwrap();
+        let route = &routes["/helloworld.Greeter/SayHellos"];
+        assert_eq!(route.component, "greeter");
+        assert_eq!(route.kind, MethodKind::ServerStreaming);
+        assert_eq!(
+            routes["/helloworld.Greeter/SayHello"].kind,
+            MethodKind::Unary
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_methods() {
+        let configs = [
+            config("one", serde_json::json!({ "SayHello": "unary" })),
+            config("two", serde_json::json!({ "SayHello": "unary" })),
+        ];
+        assert!(build_routes(&configs).is_err());
+    }
+
+    #[test]
+    fn metadata_skips_protocol_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        headers.insert("te", HeaderValue::from_static("trailers"));
+        headers.insert("x-request-id", HeaderValue::from_static("abc"));
+        assert_eq!(
+            metadata(&headers),
+            vec![("x-request-id".to_owned(), "abc".to_owned())]
+        );
+    }
+}
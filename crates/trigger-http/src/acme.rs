@@ -0,0 +1,290 @@
+//! Obtaining and renewing certificates from an ACME provider such as Let's
+//! Encrypt, using the TLS-ALPN-01 challenge on the trigger's own listener.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use instant_acme::{
+    Account, AuthorizationStatus, ChallengeType, Identifier, NewAccount, NewOrder, Order,
+    OrderStatus,
+};
+use tokio_rustls::rustls;
+use tracing::log;
+
+use crate::tls::{self, CertPaths, CertResolver};
+
+/// The ALPN protocol ACME providers use to validate TLS-ALPN-01 challenges.
+pub(crate) const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+/// The directory of Let's Encrypt's production ACME service.
+pub const LETS_ENCRYPT_DIRECTORY: &str = "https://acme-v02.api.letsencrypt.org/directory";
+/// The directory of Let's Encrypt's staging ACME service, for testing.
+pub const LETS_ENCRYPT_STAGING_DIRECTORY: &str =
+    "https://acme-staging-v02.api.letsencrypt.org/directory";
+
+// Let's Encrypt certificates are valid for 90 days, and renewing with a
+// third of that remaining leaves time to retry
+const RENEW_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+const RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+// Polling of orders while the provider validates challenges and issues the
+// certificate
+const POLL_ATTEMPTS: u32 = 10;
+const POLL_INITIAL_DELAY: Duration = Duration::from_millis(250);
+
+/// ACME certificate configuration.
+#[derive(Clone, Debug)]
+pub struct AcmeConfig {
+    /// Domains the certificate is for.
+    pub domains: Vec<String>,
+    /// Email address the provider may contact about the account.
+    pub contact_email: Option<String>,
+    /// URL of the provider's ACME directory.
+    pub directory_url: String,
+    /// Directory in which the account and certificates are stored.
+    pub cache_dir: PathBuf,
+}
+
+impl AcmeConfig {
+    // Accounts and certificates from different providers are kept apart
+    fn provider_dir(&self) -> Result<PathBuf> {
+        let url = url::Url::parse(&self.directory_url)
+            .with_context(|| format!("invalid ACME directory URL {:?}", self.directory_url))?;
+        let host = url
+            .host_str()
+            .with_context(|| format!("ACME directory URL {:?} has no host", self.directory_url))?;
+        Ok(self.cache_dir.join(host))
+    }
+
+    fn cert_paths(&self) -> Result<CertPaths> {
+        let dir = self.provider_dir()?;
+        let name = &self.domains[0];
+        Ok(CertPaths {
+            cert_path: dir.join(format!("{name}.crt")),
+            key_path: dir.join(format!("{name}.key")),
+        })
+    }
+}
+
+/// Keeps the resolver serving a current certificate for the configured
+/// domains, obtaining one at startup if none is stored and renewing it before
+/// it expires. Runs until the trigger exits.
+pub(crate) async fn maintain_certificate(config: AcmeConfig, resolver: Arc<CertResolver>) {
+    loop {
+        let wait = match renew_if_due(&config, &resolver).await {
+            Ok(wait) => wait,
+            Err(e) => {
+                log::error!(
+                    "Failed to obtain a certificate for {}: {e:?}",
+                    config.domains.join(", ")
+                );
+                RETRY_AFTER
+            }
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+// Serves the stored certificate if it is recent enough, and otherwise
+// obtains a new one. Returns how long until the certificate should be renewed.
+async fn renew_if_due(config: &AcmeConfig, resolver: &CertResolver) -> Result<Duration> {
+    let paths = config.cert_paths()?;
+    let age = fs::metadata(&paths.cert_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok());
+    if let Some(age) = age.filter(|age| *age < RENEW_AFTER) {
+        if !resolver.contains(&config.domains[0]) {
+            install(config, resolver, &paths)?;
+        }
+        return Ok(RENEW_AFTER - age);
+    }
+
+    log::info!(
+        "Requesting a certificate for {} from {}",
+        config.domains.join(", "),
+        config.directory_url
+    );
+    let result = obtain_certificate(config, resolver, &paths).await;
+    for domain in &config.domains {
+        resolver.set_challenge(domain, None);
+    }
+    result?;
+    install(config, resolver, &paths)?;
+    Ok(RENEW_AFTER)
+}
+
+fn install(config: &AcmeConfig, resolver: &CertResolver, paths: &CertPaths) -> Result<()> {
+    let key = paths.load()?;
+    for domain in &config.domains {
+        resolver.insert(domain, key.clone());
+    }
+    Ok(())
+}
+
+async fn obtain_certificate(
+    config: &AcmeConfig,
+    resolver: &CertResolver,
+    paths: &CertPaths,
+) -> Result<()> {
+    let account = account(config).await?;
+    let identifiers = config
+        .domains
+        .iter()
+        .map(|domain| Identifier::Dns(domain.clone()))
+        .collect::<Vec<_>>();
+    let mut order = account
+        .new_order(&NewOrder {
+            identifiers: &identifiers,
+        })
+        .await?;
+
+    // Answer each challenge from the TLS listener, then tell the provider to
+    // validate it
+    for authorization in order.authorizations().await? {
+        if authorization.status != AuthorizationStatus::Pending {
+            continue;
+        }
+        let Identifier::Dns(domain) = &authorization.identifier;
+        let challenge = authorization
+            .challenges
+            .iter()
+            .find(|challenge| challenge.r#type == ChallengeType::TlsAlpn01)
+            .with_context(|| {
+                format!("ACME provider offered no TLS-ALPN-01 challenge for {domain}")
+            })?;
+        let key_authorization = order.key_authorization(challenge);
+        let key = challenge_cert(domain, key_authorization.digest().as_ref())?;
+        resolver.set_challenge(domain, Some(key));
+        order.set_challenge_ready(&challenge.url).await?;
+    }
+    let status = poll_order(&mut order, OrderStatus::Pending).await?;
+
+    let mut params = rcgen::CertificateParams::new(config.domains.clone());
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    let cert = rcgen::Certificate::from_params(params)?;
+    if status == OrderStatus::Ready {
+        order.finalize(&cert.serialize_request_der()?).await?;
+        poll_order(&mut order, OrderStatus::Processing).await?;
+    }
+    let Some(chain) = order.certificate().await? else {
+        bail!("ACME provider issued no certificate");
+    };
+
+    if let Some(dir) = paths.cert_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_private(&paths.key_path, cert.serialize_private_key_pem().as_bytes())?;
+    fs::write(&paths.cert_path, chain)?;
+    log::info!("Stored new certificate in {}", paths.cert_path.display());
+    Ok(())
+}
+
+// Waits for an order to leave the given status, returning its new status.
+async fn poll_order(order: &mut Order, waiting: OrderStatus) -> Result<OrderStatus> {
+    let mut delay = POLL_INITIAL_DELAY;
+    for _ in 0..POLL_ATTEMPTS {
+        let state = order.refresh().await?;
+        match state.status {
+            OrderStatus::Invalid => bail!("ACME provider rejected the order: {state:?}"),
+            status if status != waiting => return Ok(status),
+            _ => {}
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+    bail!("timed out waiting for the ACME provider")
+}
+
+// Loads the stored account for the provider, creating one if there is none.
+async fn account(config: &AcmeConfig) -> Result<Account> {
+    let path = config.provider_dir()?.join("account.json");
+    if let Ok(json) = fs::read(&path) {
+        let credentials = serde_json::from_slice(&json)
+            .with_context(|| format!("invalid ACME account in {}", path.display()))?;
+        return Ok(Account::from_credentials(credentials)?);
+    }
+
+    let contact = config
+        .contact_email
+        .iter()
+        .map(|email| format!("mailto:{email}"))
+        .collect::<Vec<_>>();
+    let contact = contact.iter().map(String::as_str).collect::<Vec<_>>();
+    let (account, credentials) = Account::create(
+        &NewAccount {
+            contact: &contact,
+            terms_of_service_agreed: true,
+            only_return_existing: false,
+        },
+        &config.directory_url,
+        None,
+    )
+    .await?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_private(&path, &serde_json::to_vec(&credentials)?)?;
+    Ok(account)
+}
+
+// Creates the self-signed certificate proving control of a domain for a
+// TLS-ALPN-01 challenge (RFC 8737).
+fn challenge_cert(
+    domain: &str,
+    key_authorization_digest: &[u8],
+) -> Result<rustls::sign::CertifiedKey> {
+    let mut params = rcgen::CertificateParams::new(vec![domain.to_owned()]);
+    params.custom_extensions = vec![rcgen::CustomExtension::new_acme_identifier(
+        key_authorization_digest,
+    )];
+    let cert = rcgen::Certificate::from_params(params)?;
+    tls::certified_key(
+        vec![rustls::Certificate(cert.serialize_der()?)],
+        rustls::PrivateKey(cert.serialize_private_key_der()),
+    )
+}
+
+// Writes a file only its owner can read, as it holds a private key.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    std::io::Write::write_all(&mut file, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_certificates_per_provider() {
+        let config = AcmeConfig {
+            domains: vec!["example.com".into(), "www.example.com".into()],
+            contact_email: None,
+            directory_url: LETS_ENCRYPT_STAGING_DIRECTORY.into(),
+            cache_dir: PathBuf::from("/data/acme"),
+        };
+        let paths = config.cert_paths().unwrap();
+        let dir = Path::new("/data/acme/acme-staging-v02.api.letsencrypt.org");
+        assert_eq!(paths.cert_path, dir.join("example.com.crt"));
+        assert_eq!(paths.key_path, dir.join("example.com.key"));
+    }
+
+    #[test]
+    fn creates_challenge_certificates() {
+        let digest = [7; 32];
+        let key = challenge_cert("example.com", &digest).unwrap();
+        assert_eq!(key.cert.len(), 1);
+    }
+}
@@ -0,0 +1,494 @@
+//! Caching of component responses.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{
+    header::{
+        AGE, AUTHORIZATION, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, SET_COOKIE, VARY,
+    },
+    HeaderName, HeaderValue, Method, StatusCode,
+};
+use http_body_util::BodyExt;
+use hyper::{body::Bytes, Request, Response};
+use redis::{aio::MultiplexedConnection, AsyncCommands, IntoConnectionInfo};
+use serde::{Deserialize, Serialize};
+use spin_http::{body, config::CacheConfig};
+use spin_outbound_networking::dns::Resolver;
+
+use crate::{capture::PrefixedBody, Body};
+
+// Past this many entries, expired responses are evicted from the in-memory
+// cache, and new responses aren't stored until there is room
+const MAX_MEMORY_ENTRIES: usize = 1_000;
+
+// Response bodies larger than this are not cached
+const MAX_CACHED_BODY_LEN: usize = 1024 * 1024;
+
+/// A cache of component responses, shared by all routes.
+pub(crate) enum ResponseCache {
+    Memory(Mutex<HashMap<String, CachedResponse>>),
+    Redis {
+        connection: MultiplexedConnection,
+        // Keeps the keys of different applications sharing a server apart
+        prefix: String,
+    },
+}
+
+/// The key a request's response is cached under.
+#[derive(Debug)]
+pub(crate) struct CacheKey {
+    key: String,
+    // Whether the request carries credentials which the key doesn't include,
+    // so that only responses explicitly marked as shared may be served for it
+    credentialed: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct CachedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    // Base64-encoded
+    body: String,
+    // Seconds since the Unix epoch, so that entries shared through Redis
+    // can be aged by any process
+    stored_at: u64,
+    expires_at: u64,
+    // Whether the response may be served for requests with credentials
+    #[serde(default)]
+    shared: bool,
+}
+
+impl ResponseCache {
+    pub fn memory() -> Self {
+        Self::Memory(Default::default())
+    }
+
+    pub async fn redis(url: &str, app_name: &str, resolver: &Resolver) -> Result<Self> {
+        let connection = resolver
+            .connect_redis(url.into_connection_info()?, |client| async move {
+                client.get_multiplexed_tokio_connection().await
+            })
+            .await
+            .with_context(|| format!("HTTP response cache failed to connect to {url}"))?;
+        Ok(Self::Redis {
+            connection,
+            prefix: format!("spin-http-cache:{app_name}:"),
+        })
+    }
+
+    /// Returns the key a request's response is cached under, or `None` if the
+    /// request can't be served from the cache. Responses are kept apart by
+    /// the route key of the component which produced them and by the host
+    /// requested, since the same path may be routed differently per host.
+    pub fn key(route_key: &str, req: &Request<Body>, config: &CacheConfig) -> Option<CacheKey> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return None;
+        }
+        let host = req
+            .uri()
+            .authority()
+            .map(|authority| authority.as_str().to_ascii_lowercase())
+            .unwrap_or_default();
+        let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+        let mut key = format!("{route_key} {} {host}{path}", req.method());
+        for name in &config.vary {
+            let values = req.headers().get_all(name.as_str());
+            let values = values
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()))
+                .collect::<Vec<_>>();
+            key.push_str(&format!(
+                "\n{}: {}",
+                name.to_ascii_lowercase(),
+                values.join(", ")
+            ));
+        }
+        let credentialed = [AUTHORIZATION, COOKIE]
+            .iter()
+            .any(|name| req.headers().contains_key(name) && !varies_on(config, name.as_str()));
+        Some(CacheKey { key, credentialed })
+    }
+
+    /// Returns the cached response for a key, if there is one. Failures to
+    /// read from the cache are logged and treated as misses.
+    pub async fn get(&self, key: &CacheKey) -> Option<Response<Body>> {
+        let CacheKey { key, credentialed } = key;
+        let now = unix_now();
+        let cached = match self {
+            Self::Memory(entries) => entries.lock().unwrap().get(key).cloned(),
+            Self::Redis { connection, prefix } => {
+                let result: redis::RedisResult<Option<Vec<u8>>> =
+                    connection.clone().get(format!("{prefix}{key}")).await;
+                let bytes = match result {
+                    Ok(bytes) => bytes?,
+                    Err(err) => {
+                        tracing::warn!("Failed to read from HTTP response cache: {err}");
+                        return None;
+                    }
+                };
+                match serde_json::from_slice(&bytes) {
+                    Ok(cached) => Some(cached),
+                    Err(err) => {
+                        tracing::warn!("Ignoring invalid cached response: {err}");
+                        None
+                    }
+                }
+            }
+        };
+        let cached: CachedResponse =
+            cached.filter(|cached| cached.expires_at > now && (cached.shared || !credentialed))?;
+        match cached.to_response(now) {
+            Ok(response) => Some(response),
+            Err(err) => {
+                tracing::warn!("Ignoring invalid cached response: {err:?}");
+                None
+            }
+        }
+    }
+
+    /// Caches a response if it may be cached, returning it to be sent. The
+    /// response body is read in full to do so, unless it turns out to be too
+    /// large to cache, in which case the rest of it is passed through.
+    pub async fn store(
+        &self,
+        key: CacheKey,
+        config: &CacheConfig,
+        response: Response<Body>,
+    ) -> Result<Response<Body>> {
+        let shared = is_shared(&response);
+        let content_length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+        if config.ttl_secs == 0
+            || !is_cacheable(&response, config)
+            || (key.credentialed && !shared)
+            || content_length.is_some_and(|len| len > MAX_CACHED_BODY_LEN)
+        {
+            return Ok(response);
+        }
+        let (parts, mut body) = response.into_parts();
+        let mut bytes = Vec::new();
+        while let Some(frame) = body.frame().await {
+            let frame = frame.map_err(|err| anyhow!("failed to read response body: {err:?}"))?;
+            let data = match frame.into_data() {
+                Ok(data) => data,
+                // Trailers can't be cached, so pass them on uncached
+                Err(frame) => {
+                    let trailers = futures::stream::iter([Ok(frame)]);
+                    let rest = http_body_util::StreamBody::new(trailers).boxed();
+                    let body = PrefixedBody::new(bytes.into(), rest);
+                    return Ok(Response::from_parts(parts, body));
+                }
+            };
+            bytes.extend_from_slice(&data);
+            if bytes.len() > MAX_CACHED_BODY_LEN {
+                let body = PrefixedBody::new(bytes.into(), body);
+                return Ok(Response::from_parts(parts, body));
+            }
+        }
+        let body = Bytes::from(bytes);
+        let response = Response::from_parts(parts, body::full(body.clone()));
+        let key = key.key;
+
+        let now = unix_now();
+        let cached = CachedResponse::new(&response, &body, now, config.ttl_secs, shared);
+        match self {
+            Self::Memory(entries) => {
+                let mut entries = entries.lock().unwrap();
+                if entries.len() >= MAX_MEMORY_ENTRIES {
+                    entries.retain(|_, cached| cached.expires_at > now);
+                }
+                if entries.len() < MAX_MEMORY_ENTRIES || entries.contains_key(&key) {
+                    entries.insert(key, cached);
+                }
+            }
+            Self::Redis { connection, prefix } => {
+                let value = serde_json::to_vec(&cached)?;
+                let result: redis::RedisResult<()> = connection
+                    .clone()
+                    .set_ex(format!("{prefix}{key}"), value, config.ttl_secs as usize)
+                    .await;
+                if let Err(err) = result {
+                    tracing::warn!("Failed to write to HTTP response cache: {err:?}");
+                }
+            }
+        }
+        Ok(response)
+    }
+}
+
+impl CachedResponse {
+    fn new(response: &Response<Body>, body: &Bytes, now: u64, ttl_secs: u64, shared: bool) -> Self {
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        Self {
+            status: response.status().as_u16(),
+            headers,
+            body: STANDARD.encode(body),
+            stored_at: now,
+            expires_at: now.saturating_add(ttl_secs),
+            shared,
+        }
+    }
+
+    fn to_response(&self, now: u64) -> Result<Response<Body>> {
+        let body = STANDARD.decode(&self.body)?;
+        let mut response = Response::new(body::full(body.into()));
+        *response.status_mut() = StatusCode::from_u16(self.status)?;
+        let headers = response.headers_mut();
+        for (name, value) in &self.headers {
+            headers.append(
+                HeaderName::try_from(name.as_str())?,
+                HeaderValue::try_from(value.as_str())?,
+            );
+        }
+        headers.insert(AGE, HeaderValue::from(now.saturating_sub(self.stored_at)));
+        Ok(response)
+    }
+}
+
+// Whether a response is successful, complete and safe to share between
+// clients.
+fn is_cacheable(response: &Response<Body>, config: &CacheConfig) -> bool {
+    let headers = response.headers();
+    let forbids_caching = cache_control_directives(response).any(|directive| {
+        directive.eq_ignore_ascii_case("no-store") || directive.eq_ignore_ascii_case("private")
+    });
+    // Event streams never complete, so can't be stored
+    let is_event_stream = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"));
+    // The key only distinguishes responses by the configured request headers
+    let varies_on_other_headers = headers
+        .get_all(VARY)
+        .iter()
+        .flat_map(|value| value.to_str().unwrap_or("*").split(','))
+        .map(str::trim)
+        .any(|name| !name.is_empty() && (name == "*" || !varies_on(config, name)));
+    response.status() == StatusCode::OK
+        && !forbids_caching
+        && !is_event_stream
+        && !varies_on_other_headers
+        && !headers.contains_key(SET_COOKIE)
+}
+
+// Whether a response says it may be served by shared caches even for requests
+// with credentials.
+fn is_shared(response: &Response<Body>) -> bool {
+    cache_control_directives(response).any(|directive| {
+        directive.eq_ignore_ascii_case("public")
+            || directive
+                .get(..9)
+                .is_some_and(|name| name.eq_ignore_ascii_case("s-maxage="))
+    })
+}
+
+fn cache_control_directives(response: &Response<Body>) -> impl Iterator<Item = &str> {
+    response
+        .headers()
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+}
+
+// Whether cached responses are distinguished by the given request header.
+fn varies_on(config: &CacheConfig, name: &str) -> bool {
+    config
+        .vary
+        .iter()
+        .any(|vary| vary.eq_ignore_ascii_case(name))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(vary: &[&str]) -> CacheConfig {
+        CacheConfig {
+            ttl_secs: 60,
+            vary: vary.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    fn key(key: &str, credentialed: bool) -> CacheKey {
+        CacheKey {
+            key: key.to_owned(),
+            credentialed,
+        }
+    }
+
+    #[test]
+    fn keys_on_route_method_host_path_and_vary_headers() {
+        let req = Request::builder()
+            .uri("http://localhost/items?page=2")
+            .header("accept-language", "en")
+            .body(body::empty())
+            .unwrap();
+        assert_eq!(
+            ResponseCache::key("items", &req, &config(&["Accept-Language"]))
+                .unwrap()
+                .key,
+            "items GET localhost/items?page=2\naccept-language: en"
+        );
+
+        let post = Request::builder()
+            .method(Method::POST)
+            .uri("/items")
+            .body(body::empty())
+            .unwrap();
+        assert!(ResponseCache::key("items", &post, &config(&[])).is_none());
+    }
+
+    #[test]
+    fn keys_differ_by_host_and_component() {
+        let get = |uri: &str| Request::builder().uri(uri).body(body::empty()).unwrap();
+        let key = |route_key: &str, uri: &str| {
+            ResponseCache::key(route_key, &get(uri), &config(&[]))
+                .unwrap()
+                .key
+        };
+        assert_ne!(
+            key("site-a", "http://a.example.com/"),
+            key("site-b", "http://b.example.com/")
+        );
+        assert_ne!(
+            key("site-a", "http://a.example.com/"),
+            key("site-b", "http://a.example.com/")
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_stored_responses() {
+        let cache = ResponseCache::memory();
+        let response = Response::builder()
+            .header(CONTENT_TYPE, "text/plain")
+            .body(body::full(Bytes::from_static(b"hello")))
+            .unwrap();
+        let response = cache
+            .store(key("GET /", false), &config(&[]), response)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.into_body().collect().await.unwrap().to_bytes(),
+            "hello"
+        );
+
+        let hit = cache.get(&key("GET /", false)).await.unwrap();
+        assert_eq!(hit.headers()[CONTENT_TYPE], "text/plain");
+        assert_eq!(hit.headers()[AGE], "0");
+        assert_eq!(hit.into_body().collect().await.unwrap().to_bytes(), "hello");
+        assert!(cache.get(&key("GET /other", false)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn skips_uncacheable_responses() {
+        let cache = ResponseCache::memory();
+        for response in [
+            Response::builder()
+                .header(CACHE_CONTROL, "max-age=0, private")
+                .body(body::empty()),
+            Response::builder()
+                .header(SET_COOKIE, "session=abc")
+                .body(body::empty()),
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(body::empty()),
+            Response::builder()
+                .header(VARY, "Accept-Encoding")
+                .body(body::empty()),
+        ] {
+            cache
+                .store(key("GET /", false), &config(&[]), response.unwrap())
+                .await
+                .unwrap();
+            assert!(cache.get(&key("GET /", false)).await.is_none());
+        }
+    }
+
+    #[test]
+    fn requests_with_credentials_are_marked() {
+        let req = |name: &str| {
+            Request::builder()
+                .uri("/")
+                .header(name, "secret")
+                .body(body::empty())
+                .unwrap()
+        };
+        for name in ["Authorization", "Cookie"] {
+            let key = ResponseCache::key("items", &req(name), &config(&[])).unwrap();
+            assert!(key.credentialed);
+            // Unless the credentials are part of the key
+            let key = ResponseCache::key("items", &req(name), &config(&[name])).unwrap();
+            assert!(!key.credentialed);
+        }
+    }
+
+    #[tokio::test]
+    async fn only_shared_responses_are_cached_for_credentialed_requests() {
+        let cache = ResponseCache::memory();
+        let private = Response::builder().body(body::empty()).unwrap();
+        cache
+            .store(key("GET /private", true), &config(&[]), private)
+            .await
+            .unwrap();
+        assert!(cache.get(&key("GET /private", true)).await.is_none());
+        assert!(cache.get(&key("GET /private", false)).await.is_none());
+
+        let public = Response::builder()
+            .header(CACHE_CONTROL, "public, max-age=60")
+            .body(body::empty())
+            .unwrap();
+        cache
+            .store(key("GET /public", true), &config(&[]), public)
+            .await
+            .unwrap();
+        assert!(cache.get(&key("GET /public", true)).await.is_some());
+
+        // Responses to anonymous requests aren't served to credentialed ones
+        let anonymous = Response::builder().body(body::empty()).unwrap();
+        cache
+            .store(key("GET /anonymous", false), &config(&[]), anonymous)
+            .await
+            .unwrap();
+        assert!(cache.get(&key("GET /anonymous", false)).await.is_some());
+        assert!(cache.get(&key("GET /anonymous", true)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn large_bodies_are_passed_through_uncached() {
+        let cache = ResponseCache::memory();
+        let contents = Bytes::from(vec![b'x'; MAX_CACHED_BODY_LEN + 1]);
+        let response = Response::builder()
+            .body(body::full(contents.clone()))
+            .unwrap();
+        let response = cache
+            .store(key("GET /", false), &config(&[]), response)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.into_body().collect().await.unwrap().to_bytes(),
+            contents
+        );
+        assert!(cache.get(&key("GET /", false)).await.is_none());
+    }
+}
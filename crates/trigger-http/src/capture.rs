@@ -0,0 +1,148 @@
+use std::{
+    path::PathBuf,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context as TaskContext, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::{
+    body::{Bytes, Frame},
+    Request,
+};
+use spin_http::{body, capture::CapturedRequest};
+
+use crate::Body;
+
+/// Records each inbound request, with its headers and body, to a JSON file in
+/// a directory, for `spin replay` to re-send.
+pub struct RequestCapture {
+    dir: PathBuf,
+    // Requests with larger bodies are not recorded
+    max_body_bytes: usize,
+    redact_sensitive_headers: bool,
+    // Orders requests received in the same millisecond
+    sequence: AtomicU64,
+}
+
+impl RequestCapture {
+    pub fn new(
+        dir: PathBuf,
+        max_body_bytes: usize,
+        redact_sensitive_headers: bool,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create request capture dir {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            max_body_bytes,
+            redact_sensitive_headers,
+            sequence: Default::default(),
+        })
+    }
+
+    /// Buffers the body of a request to record it, returning the request with
+    /// the buffered body. A request whose body is larger than the limit is
+    /// returned with its body still streaming, unrecorded. Failing to write
+    /// the record doesn't fail the request.
+    pub async fn record(&self, req: Request<Body>) -> Result<Request<Body>> {
+        let (parts, mut body) = req.into_parts();
+        let mut bytes = Vec::new();
+        while let Some(frame) = body.frame().await {
+            let frame = frame.map_err(|err| anyhow!("Failed to read request body: {err:?}"))?;
+            let Ok(data) = frame.into_data() else {
+                continue;
+            };
+            bytes.extend_from_slice(&data);
+            if bytes.len() > self.max_body_bytes {
+                tracing::warn!(
+                    "Not capturing request to {}: body is larger than {} bytes",
+                    parts.uri,
+                    self.max_body_bytes
+                );
+                let body = PrefixedBody::new(bytes.into(), body);
+                return Ok(Request::from_parts(parts, body));
+            }
+        }
+        let bytes = Bytes::from(bytes);
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        // File names sort in the order requests were received
+        let path = self.dir.join(format!("{millis}-{sequence:06}.json"));
+        let captured = if self.redact_sensitive_headers {
+            CapturedRequest::new(&parts, &bytes)
+        } else {
+            CapturedRequest::new_unredacted(&parts, &bytes)
+        };
+        // The request doesn't wait for the record to be written
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = captured.save(&path) {
+                tracing::warn!("Failed to capture request: {err:?}");
+            }
+        });
+
+        Ok(Request::from_parts(parts, body::full(bytes)))
+    }
+}
+
+/// A body whose first bytes have already been read from `rest`.
+pub(crate) struct PrefixedBody {
+    prefix: Option<Bytes>,
+    rest: Body,
+}
+
+impl PrefixedBody {
+    pub fn new(prefix: Bytes, rest: Body) -> Body {
+        BoxBody::new(Self {
+            prefix: Some(prefix),
+            rest,
+        })
+    }
+}
+
+impl hyper::body::Body for PrefixedBody {
+    type Data = Bytes;
+    type Error = <Body as hyper::body::Body>::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if let Some(prefix) = self.prefix.take() {
+            return Poll::Ready(Some(Ok(Frame::data(prefix))));
+        }
+        Pin::new(&mut self.rest).poll_frame(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(body: &'static [u8]) -> Request<Body> {
+        Request::post("/upload")
+            .body(body::full(Bytes::from_static(body)))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn large_bodies_are_passed_through_unrecorded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let capture = RequestCapture::new(dir.path().to_owned(), 4, true)?;
+
+        let req = capture.record(request(b"0123456789")).await?;
+        let body = req.into_body().collect().await?.to_bytes();
+        assert_eq!(body, "0123456789");
+
+        let req = capture.record(request(b"0123")).await?;
+        let body = req.into_body().collect().await?.to_bytes();
+        assert_eq!(body, "0123");
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+//! Local service chaining: requests from a component to another component of
+//! the same app, addressed as `http://<component-id>.spin.internal`, are
+//! handled in-process instead of going through the network stack.
+//!
+//! Chained requests are subject to the callee's concurrency limit, as inbound
+//! requests are, and a chain of requests may be at most [`MAX_CHAIN_DEPTH`]
+//! requests long, so that components calling each other in a loop fail rather
+//! than exhausting the host.
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use http::StatusCode;
+use hyper::{body::Bytes, Response};
+use spin_http::{
+    body,
+    config::{HttpExecutorType, HttpTriggerConfig},
+};
+use spin_trigger::TriggerAppEngine;
+use tracing::Instrument;
+use wasmtime::component::Resource;
+use wasmtime_wasi_http::{
+    types::{HostFutureIncomingResponse, IncomingResponseInternal, OutgoingRequest},
+    WasiHttpView,
+};
+
+use crate::{
+    concurrency::ConcurrencyLimit, deferred::DeferredTaskQueue, handler::HttpHandlerExecutor,
+    HttpExecutor, HttpRuntimeData, HttpTrigger,
+};
+
+// Chained requests don't match a route, so the component sees the whole path
+// as its path info
+const CHAINED_BASE: &str = "/";
+const CHAINED_ROUTE: &str = "/...";
+
+/// The most chained requests which may be nested within one inbound request.
+pub(crate) const MAX_CHAIN_DEPTH: usize = 16;
+
+/// Executes requests to the app's components on behalf of another component.
+#[derive(Clone)]
+pub(crate) struct ChainedRequestHandler {
+    engine: Arc<TriggerAppEngine<HttpTrigger>>,
+    // Component ID -> component trigger config
+    component_trigger_configs: Arc<HashMap<String, HttpTriggerConfig>>,
+    // Accepts tasks the callee defers until after its response
+    deferred_tasks: DeferredTaskQueue,
+    // Component ID -> limit on the requests the component handles at once
+    concurrency_limits: Arc<HashMap<String, ConcurrencyLimit>>,
+    // The number of chained requests the requests sent by this handler are
+    // nested within
+    depth: usize,
+}
+
+impl ChainedRequestHandler {
+    pub fn new(
+        engine: Arc<TriggerAppEngine<HttpTrigger>>,
+        component_trigger_configs: Arc<HashMap<String, HttpTriggerConfig>>,
+        deferred_tasks: DeferredTaskQueue,
+        concurrency_limits: Arc<HashMap<String, ConcurrencyLimit>>,
+    ) -> Self {
+        Self {
+            engine,
+            component_trigger_configs,
+            deferred_tasks,
+            concurrency_limits,
+            depth: 0,
+        }
+    }
+
+    // Returns the handler for the requests sent by the callee of a chained
+    // request.
+    fn nested(&self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ..self.clone()
+        }
+    }
+
+    /// Sends a request to a component of the app, returning its response as
+    /// the response to the calling component's outbound request.
+    pub fn send_request(
+        &self,
+        data: &mut spin_core::Data<HttpRuntimeData>,
+        component_id: String,
+        request: OutgoingRequest,
+    ) -> wasmtime::Result<Resource<HostFutureIncomingResponse>> {
+        let trigger = self
+            .component_trigger_configs
+            .get(&component_id)
+            .ok_or_else(|| anyhow!("no HTTP component {component_id:?} in this app"))?;
+        if trigger.static_dir.is_some()
+            || matches!(trigger.executor, Some(HttpExecutorType::Wagi(_)))
+        {
+            anyhow::bail!("component {component_id:?} does not support chained requests");
+        }
+        let executor = HttpHandlerExecutor {
+            execution_timeout: trigger.execution_timeout_ms.map(Duration::from_millis),
+            chained_handler: self.nested(),
+            deferred_tasks: self.deferred_tasks.clone(),
+            instance_pool: None,
+        };
+        let engine = self.engine.clone();
+        let concurrency_limits = self.concurrency_limits.clone();
+        let depth = self.depth;
+        let between_bytes_timeout = request.between_bytes_timeout;
+        let req = request.request;
+
+        let span = tracing::info_span!(
+            "spin_trigger_http.handle_chained_request",
+            otel.kind = "server",
+            http.request.method = %req.method(),
+            url.path = req.uri().path(),
+            spin.component_id = component_id.as_str(),
+        );
+        spin_telemetry::extract_trace_context(&span, req.headers());
+        // The callee is not reached over the network, so has no client address
+        let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let response = async move {
+            let resp = async {
+                if depth >= MAX_CHAIN_DEPTH {
+                    tracing::warn!(
+                        "Rejecting chained request to {component_id:?}: requests are nested more than {MAX_CHAIN_DEPTH} deep"
+                    );
+                    return loop_detected();
+                }
+                // Held until the callee has produced its response
+                let _permit = match concurrency_limits.get(&component_id) {
+                    Some(limit) => match limit.acquire().await {
+                        Some(permit) => Some(permit),
+                        None => {
+                            tracing::warn!(
+                                "Component {component_id:?} is at its concurrency limit: rejecting chained request"
+                            );
+                            return HttpTrigger::too_many_requests();
+                        }
+                    },
+                    None => None,
+                };
+                executor
+                    .execute(
+                        &engine,
+                        &component_id,
+                        CHAINED_BASE,
+                        CHAINED_ROUTE,
+                        req,
+                        client_addr,
+                    )
+                    .await
+            }
+            .await?;
+            Ok(IncomingResponseInternal {
+                resp,
+                worker: Arc::new(wasmtime_wasi::preview2::spawn(async { Ok(()) })),
+                between_bytes_timeout,
+            })
+        };
+        let handle = wasmtime_wasi::preview2::spawn(response.instrument(span));
+        Ok(data.table().push(HostFutureIncomingResponse::new(handle))?)
+    }
+}
+
+fn loop_detected() -> anyhow::Result<Response<crate::Body>> {
+    Ok(Response::builder()
+        .status(StatusCode::LOOP_DETECTED)
+        .body(body::full(Bytes::from_static(
+            b"Too many nested chained requests",
+        )))?)
+}
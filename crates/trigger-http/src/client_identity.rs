@@ -0,0 +1,132 @@
+//! The identity of clients authenticated by a TLS client certificate, as
+//! passed to components.
+
+use anyhow::{anyhow, Result};
+use http::{HeaderMap, HeaderValue};
+use tokio_rustls::rustls;
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+/// The subject of the client's certificate, e.g. `CN=client, O=Example`.
+pub(crate) const CLIENT_CERT_SUBJECT_HEADER: &str = "spin-client-cert-subject";
+/// The subject alternative names of the client's certificate, comma
+/// separated, e.g. `DNS:client.example.com, URI:spiffe://example.com/client`.
+pub(crate) const CLIENT_CERT_SANS_HEADER: &str = "spin-client-cert-sans";
+
+/// The identity in a verified client certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ClientIdentity {
+    subject: String,
+    sans: Vec<String>,
+}
+
+impl ClientIdentity {
+    /// Returns the identity in the client's certificate chain, if it
+    /// presented one. The chain must already have been verified.
+    pub fn from_peer_certificates(certs: Option<&[rustls::Certificate]>) -> Result<Option<Self>> {
+        let Some(cert) = certs.and_then(|certs| certs.first()) else {
+            return Ok(None);
+        };
+        let (_, cert) = X509Certificate::from_der(&cert.0)
+            .map_err(|e| anyhow!("invalid client certificate: {e}"))?;
+        let sans = cert
+            .subject_alternative_name()
+            .map_err(|e| anyhow!("invalid client certificate: {e}"))?
+            .map(|ext| {
+                ext.value
+                    .general_names
+                    .iter()
+                    .filter_map(format_san)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Some(Self {
+            subject: cert.subject().to_string(),
+            sans,
+        }))
+    }
+
+    /// Sets the identity headers of a request from the client's identity.
+    /// Any the client sent itself are removed, so that components can trust
+    /// them.
+    pub fn set_headers(identity: Option<&Self>, headers: &mut HeaderMap) {
+        headers.remove(CLIENT_CERT_SUBJECT_HEADER);
+        headers.remove(CLIENT_CERT_SANS_HEADER);
+        let Some(identity) = identity else {
+            return;
+        };
+        // Names may hold UTF-8, which header values can carry as opaque bytes
+        if let Ok(subject) = HeaderValue::from_bytes(identity.subject.as_bytes()) {
+            headers.insert(CLIENT_CERT_SUBJECT_HEADER, subject);
+        }
+        if !identity.sans.is_empty() {
+            if let Ok(sans) = HeaderValue::from_bytes(identity.sans.join(", ").as_bytes()) {
+                headers.insert(CLIENT_CERT_SANS_HEADER, sans);
+            }
+        }
+    }
+}
+
+// Formats a name as OpenSSL does, skipping kinds which have no simple text
+// form.
+fn format_san(name: &GeneralName) -> Option<String> {
+    match name {
+        GeneralName::DNSName(name) => Some(format!("DNS:{name}")),
+        GeneralName::RFC822Name(email) => Some(format!("email:{email}")),
+        GeneralName::URI(uri) => Some(format!("URI:{uri}")),
+        GeneralName::IPAddress(bytes) => {
+            let ip = match bytes.len() {
+                4 => std::net::IpAddr::from(<[u8; 4]>::try_from(*bytes).ok()?),
+                16 => std::net::IpAddr::from(<[u8; 16]>::try_from(*bytes).ok()?),
+                _ => return None,
+            };
+            Some(format!("IP:{ip}"))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_identity_from_certificate() {
+        let mut params = rcgen::CertificateParams::new(vec!["client.example.com".to_owned()]);
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "client");
+        params
+            .subject_alt_names
+            .push(rcgen::SanType::IpAddress([10, 0, 0, 1].into()));
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        let der = rustls::Certificate(cert.serialize_der().unwrap());
+
+        let identity = ClientIdentity::from_peer_certificates(Some(&[der]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(identity.subject, "CN=client");
+        assert_eq!(identity.sans, ["DNS:client.example.com", "IP:10.0.0.1"]);
+        assert!(ClientIdentity::from_peer_certificates(None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn replaces_client_sent_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CLIENT_CERT_SUBJECT_HEADER,
+            HeaderValue::from_static("CN=admin"),
+        );
+        ClientIdentity::set_headers(None, &mut headers);
+        assert!(headers.is_empty());
+
+        let identity = ClientIdentity {
+            subject: "CN=client".into(),
+            sans: vec!["DNS:a.test".into(), "DNS:b.test".into()],
+        };
+        ClientIdentity::set_headers(Some(&identity), &mut headers);
+        assert_eq!(headers[CLIENT_CERT_SUBJECT_HEADER], "CN=client");
+        assert_eq!(headers[CLIENT_CERT_SANS_HEADER], "DNS:a.test, DNS:b.test");
+    }
+}
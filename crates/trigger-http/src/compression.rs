@@ -0,0 +1,401 @@
+//! Compression of component responses as they are streamed.
+
+use std::{
+    io::Write,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+
+use flate2::write::GzEncoder;
+use http::{
+    header::{
+        ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE,
+        CONTENT_TYPE, VARY,
+    },
+    HeaderMap, HeaderValue, Method, StatusCode,
+};
+use http_body_util::combinators::BoxBody;
+use hyper::{
+    body::{Bytes, Frame},
+    Request, Response,
+};
+use spin_http::config::CompressionConfig;
+
+use crate::Body;
+
+// Brotli settings suited to compressing on the fly rather than ahead of time
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_QUALITY: u32 = 4;
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+/// A content coding the host can compress responses with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Brotli => "br",
+        }
+    }
+}
+
+/// Returns the encoding the client would most like its response in, if it
+/// accepts any the host supports.
+pub(crate) fn negotiate(req: &Request<Body>) -> Option<Encoding> {
+    // HEAD responses have no body to compress
+    if req.method() == Method::HEAD {
+        return None;
+    }
+    let mut best: Option<(Encoding, f32)> = None;
+    let codings = req
+        .headers()
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for coding in codings {
+        let mut params = coding.split(';');
+        let name = params.next().unwrap_or_default().trim();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok());
+        let Some(quality) = quality.filter(|q| *q > 0.0) else {
+            continue;
+        };
+        let encodings: &[Encoding] = match name.to_ascii_lowercase().as_str() {
+            "br" => &[Encoding::Brotli],
+            "gzip" => &[Encoding::Gzip],
+            "*" => &[Encoding::Brotli, Encoding::Gzip],
+            _ => &[],
+        };
+        for &encoding in encodings {
+            // Brotli wins ties, as it compresses better
+            let better = best.map_or(true, |(best_encoding, best_quality)| {
+                quality > best_quality
+                    || (quality == best_quality && best_encoding == Encoding::Gzip)
+            });
+            if better {
+                best = Some((encoding, quality));
+            }
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Compresses a response with `encoding`, if the route's configuration and
+/// the response allow it. Other responses are returned unchanged.
+pub(crate) fn compress_response(
+    response: Response<Body>,
+    config: &CompressionConfig,
+    encoding: Option<Encoding>,
+) -> Response<Body> {
+    let (mut parts, body) = response.into_parts();
+    if !is_compressible_type(&parts.headers, config) {
+        return Response::from_parts(parts, body);
+    }
+    // Whether the response is compressed depends on the request, so caches
+    // must key on it
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("accept-encoding"));
+
+    let Some(encoding) = encoding else {
+        return Response::from_parts(parts, body);
+    };
+    let headers = &parts.headers;
+    let too_small = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok()?.parse::<u64>().ok())
+        .is_some_and(|len| len < config.min_size);
+    let no_transform = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| directive.trim().eq_ignore_ascii_case("no-transform"));
+    let has_body =
+        parts.status != StatusCode::NO_CONTENT && parts.status != StatusCode::NOT_MODIFIED;
+    if too_small
+        || no_transform
+        || !has_body
+        || headers.contains_key(CONTENT_ENCODING)
+        || headers.contains_key(CONTENT_RANGE)
+    {
+        return Response::from_parts(parts, body);
+    }
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+    Response::from_parts(parts, compress_body(body, encoding))
+}
+
+/// Returns `body` compressed with `encoding` as it is read.
+pub(crate) fn compress_body(body: Body, encoding: Encoding) -> Body {
+    BoxBody::new(CompressedBody {
+        inner: body,
+        encoder: Some(Encoder::new(encoding)),
+        trailers: None,
+    })
+}
+
+fn is_compressible_type(headers: &HeaderMap, config: &CompressionConfig) -> bool {
+    let Some(media_type) = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+    else {
+        return false;
+    };
+    let media_type = media_type.trim().to_ascii_lowercase();
+    config.content_types.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) if prefix.ends_with('/') => media_type.starts_with(prefix),
+            _ => media_type == pattern,
+        }
+    })
+}
+
+/// A response body which compresses the component's output as it is read.
+///
+/// Each chunk of output is flushed through the encoder, so that output is
+/// sent to the client as promptly as if it were uncompressed.
+struct CompressedBody {
+    inner: Body,
+    // `None` once the output has ended
+    encoder: Option<Encoder>,
+    // Trailers sent after the end of the compressed output
+    trailers: Option<Frame<Bytes>>,
+}
+
+impl hyper::body::Body for CompressedBody {
+    type Data = Bytes;
+    type Error = <Body as hyper::body::Body>::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        if let Some(trailers) = this.trailers.take() {
+            return Poll::Ready(Some(Ok(trailers)));
+        }
+        loop {
+            let Some(encoder) = &mut this.encoder else {
+                return Poll::Ready(None);
+            };
+            let frame = match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => frame,
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    let remaining = this.encoder.take().unwrap().finish();
+                    return Poll::Ready(Some(Ok(Frame::data(remaining))));
+                }
+            };
+            match frame.into_data() {
+                Ok(data) => {
+                    let compressed = encoder.compress(&data);
+                    // Wait for more output rather than sending an empty frame
+                    if !compressed.is_empty() {
+                        return Poll::Ready(Some(Ok(Frame::data(compressed))));
+                    }
+                }
+                // Trailers end the body, so end the compressed output first
+                Err(trailers) => {
+                    this.trailers = Some(trailers);
+                    let remaining = this.encoder.take().unwrap().finish();
+                    return Poll::Ready(Some(Ok(Frame::data(remaining))));
+                }
+            }
+        }
+    }
+}
+
+struct Encoder {
+    writer: EncoderWriter,
+    output: SharedBuffer,
+}
+
+enum EncoderWriter {
+    Gzip(GzEncoder<SharedBuffer>),
+    Brotli(Box<brotli::CompressorWriter<SharedBuffer>>),
+}
+
+// Writing to memory can't fail, so neither can encoding into it
+const INFALLIBLE: &str = "compressing into memory should not fail";
+
+impl Encoder {
+    fn new(encoding: Encoding) -> Self {
+        let output = SharedBuffer::default();
+        let writer = match encoding {
+            Encoding::Gzip => EncoderWriter::Gzip(GzEncoder::new(
+                output.clone(),
+                flate2::Compression::default(),
+            )),
+            Encoding::Brotli => EncoderWriter::Brotli(Box::new(brotli::CompressorWriter::new(
+                output.clone(),
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW_BITS,
+            ))),
+        };
+        Self { writer, output }
+    }
+
+    // Compresses and flushes a chunk of output, returning the compressed bytes.
+    fn compress(&mut self, data: &[u8]) -> Bytes {
+        let writer: &mut dyn Write = match &mut self.writer {
+            EncoderWriter::Gzip(encoder) => encoder,
+            EncoderWriter::Brotli(encoder) => encoder.as_mut(),
+        };
+        writer.write_all(data).expect(INFALLIBLE);
+        writer.flush().expect(INFALLIBLE);
+        self.output.take()
+    }
+
+    // Ends the compressed output, returning its remaining bytes.
+    fn finish(self) -> Bytes {
+        match self.writer {
+            EncoderWriter::Gzip(encoder) => {
+                encoder.finish().expect(INFALLIBLE);
+            }
+            EncoderWriter::Brotli(encoder) => {
+                encoder.into_inner();
+            }
+        }
+        self.output.take()
+    }
+}
+
+// A buffer the encoders write into, shared so that output can be taken from
+// it while encoding continues.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Bytes {
+        std::mem::take(&mut *self.0.lock().unwrap()).into()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use http_body_util::BodyExt;
+    use spin_http::body;
+
+    use super::*;
+
+    fn request(accept_encoding: &str) -> Request<Body> {
+        Request::builder()
+            .header(ACCEPT_ENCODING, accept_encoding)
+            .body(body::empty())
+            .unwrap()
+    }
+
+    fn text_response(text: &str) -> Response<Body> {
+        Response::builder()
+            .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(body::full(Bytes::copy_from_slice(text.as_bytes())))
+            .unwrap()
+    }
+
+    #[test]
+    fn negotiates_preferred_encoding() {
+        assert_eq!(negotiate(&request("gzip, br")), Some(Encoding::Brotli));
+        assert_eq!(
+            negotiate(&request("br;q=0.5, gzip;q=0.8")),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(negotiate(&request("br;q=0, *")), Some(Encoding::Brotli));
+        assert_eq!(negotiate(&request("identity, deflate")), None);
+    }
+
+    #[tokio::test]
+    async fn compresses_with_gzip() {
+        let text = "hello, world\n".repeat(200);
+        let config = CompressionConfig::default();
+        let response = compress_response(text_response(&text), &config, Some(Encoding::Gzip));
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[VARY], "accept-encoding");
+        assert!(response.headers().get(CONTENT_LENGTH).is_none());
+
+        let compressed = response.into_body().collect().await.unwrap().to_bytes();
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[tokio::test]
+    async fn compresses_with_brotli() {
+        let text = "hello, world\n".repeat(200);
+        let config = CompressionConfig::default();
+        let response = compress_response(text_response(&text), &config, Some(Encoding::Brotli));
+        assert_eq!(response.headers()[CONTENT_ENCODING], "br");
+
+        let compressed = response.into_body().collect().await.unwrap().to_bytes();
+        let mut decoded = String::new();
+        brotli::Decompressor::new(&compressed[..], BROTLI_BUFFER_SIZE)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn skips_ineligible_responses() {
+        let config = CompressionConfig {
+            min_size: 100,
+            ..Default::default()
+        };
+        let mut small = text_response("short");
+        small
+            .headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from_static("5"));
+        let small = compress_response(small, &config, Some(Encoding::Gzip));
+        assert!(small.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(small.headers()[VARY], "accept-encoding");
+
+        let image = Response::builder()
+            .header(CONTENT_TYPE, "image/png")
+            .body(body::empty())
+            .unwrap();
+        let image = compress_response(image, &config, Some(Encoding::Gzip));
+        assert!(image.headers().get(CONTENT_ENCODING).is_none());
+        assert!(image.headers().get(VARY).is_none());
+    }
+
+    #[test]
+    fn matches_content_type_wildcards() {
+        let config = CompressionConfig {
+            content_types: vec!["text/*".to_owned()],
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("Text/CSV"));
+        assert!(is_compressible_type(&headers, &config));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        assert!(!is_compressible_type(&headers, &config));
+    }
+}
@@ -0,0 +1,133 @@
+//! Per-component concurrency limits: a component with `max_concurrency` set
+//! handles at most that many requests at once, queueing the excess, and
+//! shedding requests the queue has no room for.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use anyhow::{ensure, Result};
+use spin_http::config::HttpTriggerConfig;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bounds the requests a component handles at once.
+pub struct ConcurrencyLimit {
+    permits: Arc<Semaphore>,
+    // Requests waiting for a permit
+    queued: AtomicUsize,
+    // `None` queues without bound
+    max_queued: Option<usize>,
+}
+
+impl ConcurrencyLimit {
+    /// Returns the limit configured for a route, if any.
+    pub fn from_config(config: &HttpTriggerConfig) -> Result<Option<Self>> {
+        let Some(max_concurrency) = config.max_concurrency else {
+            ensure!(
+                config.max_queued_requests.is_none(),
+                "Route {:?} sets max_queued_requests without max_concurrency",
+                config.route
+            );
+            return Ok(None);
+        };
+        ensure!(
+            max_concurrency > 0,
+            "Route {:?} must have a max_concurrency of at least 1",
+            config.route
+        );
+        Ok(Some(Self {
+            permits: Arc::new(Semaphore::new(max_concurrency)),
+            queued: AtomicUsize::new(0),
+            max_queued: config.max_queued_requests,
+        }))
+    }
+
+    /// Waits until the component has capacity for another request, returning
+    /// a permit to hold while handling it, or `None` if the request should be
+    /// shed because too many requests are already waiting.
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        // The slot is released when this future completes or is dropped,
+        // e.g. because the client went away while the request was queued
+        let slot = QueueSlot::take(&self.queued);
+        if self.max_queued.is_some_and(|max| slot.position >= max) {
+            return None;
+        }
+        self.permits.clone().acquire_owned().await.ok()
+    }
+}
+
+struct QueueSlot<'a> {
+    queued: &'a AtomicUsize,
+    // The number of requests already waiting when this one was queued
+    position: usize,
+}
+
+impl<'a> QueueSlot<'a> {
+    fn take(queued: &'a AtomicUsize) -> Self {
+        let position = queued.fetch_add(1, Ordering::AcqRel);
+        Self { queued, position }
+    }
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(max_concurrency: usize, max_queued_requests: Option<usize>) -> ConcurrencyLimit {
+        let config = HttpTriggerConfig {
+            route: "/slow".into(),
+            max_concurrency: Some(max_concurrency),
+            max_queued_requests,
+            ..Default::default()
+        };
+        ConcurrencyLimit::from_config(&config).unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn excess_requests_wait_for_a_permit() {
+        let limit = Arc::new(limit(1, None));
+        let first = limit.acquire().await.unwrap();
+
+        let waiting = tokio::spawn({
+            let limit = limit.clone();
+            async move { limit.acquire().await.is_some() }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        assert!(waiting.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_the_queue_are_shed() {
+        let limit = limit(1, Some(0));
+        let _first = limit.acquire().await.unwrap();
+        assert!(limit.acquire().await.is_none());
+    }
+
+    #[test]
+    fn invalid_limits_are_rejected() {
+        let zero = HttpTriggerConfig {
+            max_concurrency: Some(0),
+            ..Default::default()
+        };
+        assert!(ConcurrencyLimit::from_config(&zero).is_err());
+
+        let queue_only = HttpTriggerConfig {
+            max_queued_requests: Some(10),
+            ..Default::default()
+        };
+        assert!(ConcurrencyLimit::from_config(&queue_only).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Deferred tasks: follow-up invocations that a component enqueues through the
+//! `deferred-tasks` interface, run by a pool of workers after the enqueuing
+//! request has been responded to.
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use http::{HeaderName, HeaderValue, Method, Request};
+use hyper::body::Bytes;
+use spin_core::{Data, Linker};
+use spin_http::{
+    body,
+    config::{HttpExecutorType, HttpTriggerConfig},
+};
+use spin_trigger::TriggerAppEngine;
+use tokio::sync::{mpsc, Mutex};
+use tracing::Instrument;
+use wasmtime::StoreContextMut;
+
+use crate::{
+    chaining::ChainedRequestHandler, handler::HttpHandlerExecutor, HttpExecutor, HttpRuntimeData,
+    HttpTrigger,
+};
+
+const DEFERRED_TASKS_INTERFACE: &str = "fermyon:spin/deferred-tasks@2.1.0";
+
+/// The header carrying a deferred task's ID to the component running it.
+pub const DEFERRED_TASK_ID_HEADER: &str = "spin-deferred-task-id";
+
+// Tasks which may be waiting for a worker before enqueuing fails
+const QUEUE_CAPACITY: usize = 1024;
+
+// Deferred tasks don't match a route, so the component sees the whole path as
+// its path info
+const DEFERRED_BASE: &str = "/";
+const DEFERRED_ROUTE: &str = "/...";
+
+struct DeferredTask {
+    id: String,
+    component_id: String,
+    request: Request<Bytes>,
+}
+
+/// Accepts deferred tasks for the app's components.
+#[derive(Clone)]
+pub(crate) struct DeferredTaskQueue {
+    sender: mpsc::Sender<DeferredTask>,
+    // Component ID -> component trigger config
+    component_trigger_configs: Arc<HashMap<String, HttpTriggerConfig>>,
+}
+
+/// The receiving end of a [`DeferredTaskQueue`], which runs its tasks once
+/// workers are started.
+pub(crate) struct DeferredTaskWorkers {
+    receiver: mpsc::Receiver<DeferredTask>,
+}
+
+impl DeferredTaskQueue {
+    pub fn new(
+        component_trigger_configs: Arc<HashMap<String, HttpTriggerConfig>>,
+    ) -> (Self, DeferredTaskWorkers) {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let queue = Self {
+            sender,
+            component_trigger_configs,
+        };
+        (queue, DeferredTaskWorkers { receiver })
+    }
+
+    fn enqueue(
+        &self,
+        component_id: String,
+        path: &str,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> Result<String> {
+        let trigger = self
+            .component_trigger_configs
+            .get(&component_id)
+            .ok_or_else(|| anyhow!("no HTTP component {component_id:?} in this app"))?;
+        if trigger.static_dir.is_some()
+            || matches!(trigger.executor, Some(HttpExecutorType::Wagi(_)))
+        {
+            anyhow::bail!("component {component_id:?} does not support deferred tasks");
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut builder = Request::builder().method(Method::POST).uri(path);
+        for (name, value) in headers {
+            builder = builder.header(
+                HeaderName::try_from(name).context("invalid header name")?,
+                HeaderValue::try_from(value).context("invalid header value")?,
+            );
+        }
+        let request = builder
+            .header(DEFERRED_TASK_ID_HEADER, &id)
+            .body(Bytes::from(body))
+            .context("invalid task request")?;
+
+        self.sender
+            .try_send(DeferredTask {
+                id: id.clone(),
+                component_id,
+                request,
+            })
+            .map_err(|err| match err {
+                mpsc::error::TrySendError::Full(_) => anyhow!("the deferred task queue is full"),
+                mpsc::error::TrySendError::Closed(_) => anyhow!("deferred tasks are not running"),
+            })?;
+        Ok(id)
+    }
+}
+
+impl DeferredTaskWorkers {
+    /// Starts `count` workers, each running one task at a time.
+    pub fn start(
+        self,
+        count: usize,
+        engine: Arc<TriggerAppEngine<HttpTrigger>>,
+        chained_handler: ChainedRequestHandler,
+        deferred_tasks: DeferredTaskQueue,
+    ) {
+        let receiver = Arc::new(Mutex::new(self.receiver));
+        for _ in 0..count.max(1) {
+            let receiver = receiver.clone();
+            let engine = engine.clone();
+            let chained_handler = chained_handler.clone();
+            let deferred_tasks = deferred_tasks.clone();
+            tokio::spawn(async move {
+                loop {
+                    // Release the receiver while the task runs so other
+                    // workers can take the next one
+                    let Some(task) = receiver.lock().await.recv().await else {
+                        return;
+                    };
+                    run_task(&engine, &chained_handler, &deferred_tasks, task).await;
+                }
+            });
+        }
+    }
+}
+
+async fn run_task(
+    engine: &TriggerAppEngine<HttpTrigger>,
+    chained_handler: &ChainedRequestHandler,
+    deferred_tasks: &DeferredTaskQueue,
+    task: DeferredTask,
+) {
+    let component_id = task.component_id.as_str();
+    let span = tracing::info_span!(
+        "spin_trigger_http.run_deferred_task",
+        otel.kind = "consumer",
+        spin.component_id = component_id,
+        spin.deferred_task_id = task.id.as_str(),
+    );
+    spin_telemetry::extract_trace_context(&span, task.request.headers());
+
+    let execution_timeout = deferred_tasks
+        .component_trigger_configs
+        .get(component_id)
+        .and_then(|trigger| trigger.execution_timeout_ms)
+        .map(Duration::from_millis);
+    let executor = HttpHandlerExecutor {
+        execution_timeout,
+        chained_handler: chained_handler.clone(),
+        deferred_tasks: deferred_tasks.clone(),
+        instance_pool: None,
+    };
+    let request = task.request.map(body::full);
+    // The task is not received over the network, so has no client address
+    let client_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+
+    let result = executor
+        .execute(
+            engine,
+            component_id,
+            DEFERRED_BASE,
+            DEFERRED_ROUTE,
+            request,
+            client_addr,
+        )
+        .instrument(span)
+        .await;
+    match result {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => terminal::warn!(
+            "Deferred task {} for component {component_id:?} failed with status {}",
+            task.id,
+            response.status()
+        ),
+        Err(err) => terminal::error!(
+            "Deferred task {} for component {component_id:?} failed: {err:#}",
+            task.id
+        ),
+    }
+}
+
+// Links the `deferred-tasks` interface, which enqueues tasks on the queue of
+// the current request.
+pub(crate) fn add_deferred_tasks_to_linker(linker: &mut Linker<HttpRuntimeData>) -> Result<()> {
+    let mut instance = linker.instance(DEFERRED_TASKS_INTERFACE)?;
+    instance.func_wrap(
+        "enqueue",
+        |mut store: StoreContextMut<'_, Data<HttpRuntimeData>>,
+         (component, path, headers, body): (
+            Option<String>,
+            String,
+            Vec<(String, String)>,
+            Vec<u8>,
+        )| {
+            let data = store.data_mut().as_mut();
+            let Some(deferred_tasks) = &data.deferred_tasks else {
+                return Ok((Err("deferred tasks are not supported here".to_owned()),));
+            };
+            let component_id = component.unwrap_or_else(|| data.component_id.clone());
+            let result = deferred_tasks
+                .enqueue(component_id, &path, headers, body)
+                .map_err(|err| format!("{err:#}"));
+            Ok((result,))
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> (DeferredTaskQueue, DeferredTaskWorkers) {
+        let configs = [
+            (
+                "worker".to_owned(),
+                HttpTriggerConfig {
+                    component: "worker".into(),
+                    route: "/worker".into(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "wagi".to_owned(),
+                HttpTriggerConfig {
+                    component: "wagi".into(),
+                    route: "/wagi".into(),
+                    executor: Some(HttpExecutorType::Wagi(Default::default())),
+                    ..Default::default()
+                },
+            ),
+        ];
+        DeferredTaskQueue::new(Arc::new(configs.into_iter().collect()))
+    }
+
+    #[test]
+    fn enqueued_tasks_are_post_requests_with_ids() -> Result<()> {
+        let (queue, mut workers) = queue();
+        let headers = vec![("x-job".to_owned(), "resize".to_owned())];
+        let id = queue.enqueue("worker".into(), "/jobs/resize", headers, b"42".to_vec())?;
+
+        let task = workers.receiver.try_recv()?;
+        assert_eq!(task.id, id);
+        assert_eq!(task.component_id, "worker");
+        assert_eq!(task.request.method(), Method::POST);
+        assert_eq!(task.request.uri().path(), "/jobs/resize");
+        assert_eq!(task.request.headers()["x-job"], "resize");
+        assert_eq!(task.request.headers()[DEFERRED_TASK_ID_HEADER], id.as_str());
+        assert_eq!(task.request.body().as_ref(), b"42");
+        Ok(())
+    }
+
+    #[test]
+    fn tasks_for_unsupported_components_are_rejected() {
+        let (queue, _workers) = queue();
+        assert!(queue
+            .enqueue("missing".into(), "/", vec![], vec![])
+            .is_err());
+        assert!(queue.enqueue("wagi".into(), "/", vec![], vec![]).is_err());
+    }
+}
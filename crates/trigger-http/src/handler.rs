@@ -1,6 +1,9 @@
-use std::{net::SocketAddr, str, str::FromStr};
+use std::{net::SocketAddr, str, str::FromStr, time::Duration};
 
-use crate::{Body, HttpExecutor, HttpTrigger, Store};
+use crate::{
+    chaining::ChainedRequestHandler, deferred::DeferredTaskQueue, pool::InstancePool, Body,
+    HttpExecutor, HttpTrigger, Store,
+};
 use anyhow::bail;
 use anyhow::{anyhow, Context, Result};
 use futures::TryFutureExt;
@@ -10,16 +13,25 @@ use hyper::{Request, Response};
 use outbound_http::OutboundHttpComponent;
 use spin_core::async_trait;
 use spin_core::wasi_2023_10_18::exports::wasi::http::incoming_handler::IncomingHandler as IncomingHandler2023_10_18;
-use spin_core::Instance;
+use spin_core::{Instance, WasiVersion};
 use spin_http::body;
-use spin_trigger::{EitherInstance, TriggerAppEngine};
+use spin_trigger::{CrashReporter, EitherInstance, TriggerAppEngine};
 use spin_world::v1::http_types;
 use std::sync::Arc;
 use tokio::{sync::oneshot, task};
+use tracing::Instrument;
 use wasmtime_wasi_http::{proxy::Proxy, WasiHttpView};
 
 #[derive(Clone)]
-pub struct HttpHandlerExecutor;
+pub struct HttpHandlerExecutor {
+    pub execution_timeout: Option<Duration>,
+    /// Handles requests the component makes to other components of the app
+    pub chained_handler: ChainedRequestHandler,
+    /// Accepts tasks the component defers until after its response
+    pub deferred_tasks: DeferredTaskQueue,
+    /// Warm instances of the component, if it keeps any
+    pub instance_pool: Option<Arc<InstancePool>>,
+}
 
 #[async_trait]
 impl HttpExecutor for HttpHandlerExecutor {
@@ -37,17 +49,38 @@ impl HttpExecutor for HttpHandlerExecutor {
             component_id
         );
 
-        let (instance, mut store) = engine.prepare_instance(component_id).await?;
-        let EitherInstance::Component(instance) = instance else {
-            unreachable!()
+        let warm = self
+            .instance_pool
+            .as_ref()
+            .and_then(|pool| pool.take(self.execution_timeout));
+        let (instance, mut store) = match warm {
+            Some(warm) => warm,
+            None => {
+                let mut store_builder =
+                    engine.store_builder(component_id, WasiVersion::Preview2)?;
+                if let Some(timeout) = self.execution_timeout {
+                    store_builder.execution_time_limit(timeout);
+                }
+                let (instance, store) = engine
+                    .prepare_instance_with_store(component_id, store_builder)
+                    .await?;
+                let EitherInstance::Component(instance) = instance else {
+                    unreachable!()
+                };
+                (instance, store)
+            }
         };
 
+        let crash = CrashCapture::new(engine, component_id, &req);
         set_http_origin_from_request(&mut store, engine, &req);
+        store.as_mut().data_mut().as_mut().component_id = component_id.to_owned();
+        store.as_mut().data_mut().as_mut().chained_handler = Some(self.chained_handler.clone());
+        store.as_mut().data_mut().as_mut().deferred_tasks = Some(self.deferred_tasks.clone());
 
         let resp = match HandlerType::from_exports(instance.exports(&mut store)) {
-            Some(HandlerType::Wasi) => Self::execute_wasi(store, instance, base, raw_route, req, client_addr).await?,
+            Some(HandlerType::Wasi) => Self::execute_wasi(store, instance, base, raw_route, req, client_addr, crash).await?,
             Some(HandlerType::Spin) => {
-                Self::execute_spin(store, instance, base, raw_route, req, client_addr)
+                Self::execute_spin(store, instance, base, raw_route, req, client_addr, crash)
                     .await
                     .map_err(contextualise_err)?
             }
@@ -65,7 +98,57 @@ impl HttpExecutor for HttpHandlerExecutor {
     }
 }
 
+/// Captures a core dump if the guest traps while handling a request, with the
+/// request as its context.
+struct CrashCapture {
+    reporter: CrashReporter,
+    component_id: String,
+    context: serde_json::Value,
+}
+
+impl CrashCapture {
+    fn new(
+        engine: &TriggerAppEngine<HttpTrigger>,
+        component_id: &str,
+        req: &Request<Body>,
+    ) -> Option<Self> {
+        let reporter = engine.crash_reporter()?.clone();
+        let headers = req
+            .headers()
+            .iter()
+            // Keep credentials out of crash files
+            .filter(|(name, _)| {
+                *name != http::header::AUTHORIZATION && *name != http::header::COOKIE
+            })
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let context = serde_json::json!({
+            "method": req.method().as_str(),
+            "uri": req.uri().to_string(),
+            "headers": headers,
+        });
+        Some(Self {
+            reporter,
+            component_id: component_id.to_owned(),
+            context,
+        })
+    }
+
+    fn capture(&self, store: &mut Store, err: &anyhow::Error) {
+        self.reporter
+            .capture(&self.component_id, store, err, self.context.clone());
+    }
+}
+
 impl HttpHandlerExecutor {
+    /// Executes a `fermyon:spin/inbound-http` handler. This interface passes
+    /// request and response bodies as byte lists, so both are buffered in full;
+    /// components that need streaming bodies should export `wasi:http`.
     pub async fn execute_spin(
         mut store: Store,
         instance: Instance,
@@ -73,6 +156,7 @@ impl HttpHandlerExecutor {
         raw_route: &str,
         req: Request<Body>,
         client_addr: SocketAddr,
+        crash: Option<CrashCapture>,
     ) -> Result<Response<Body>> {
         let headers = Self::headers(&req, raw_route, base, client_addr)?;
         let func = instance
@@ -111,7 +195,15 @@ impl HttpHandlerExecutor {
             body: Some(bytes),
         };
 
-        let (resp,) = func.call_async(&mut store, (req,)).await?;
+        let (resp,) = match func.call_async(&mut store, (req,)).await {
+            Ok(resp) => resp,
+            Err(err) => {
+                if let Some(crash) = &crash {
+                    crash.capture(&mut store, &err);
+                }
+                return Err(err);
+            }
+        };
 
         if resp.status < 100 || resp.status > 600 {
             tracing::error!("malformed HTTP status code");
@@ -146,6 +238,10 @@ impl HttpHandlerExecutor {
         })
     }
 
+    /// Executes a `wasi:http/incoming-handler`. Request and response bodies
+    /// are streamed: the guest reads the incoming body as it arrives, and the
+    /// response is sent as soon as the guest sets it, with the body forwarded
+    /// while the guest continues writing it.
     async fn execute_wasi(
         mut store: Store,
         instance: Instance,
@@ -153,6 +249,7 @@ impl HttpHandlerExecutor {
         raw_route: &str,
         mut req: Request<Body>,
         client_addr: SocketAddr,
+        crash: Option<CrashCapture>,
     ) -> anyhow::Result<Response<Body>> {
         let headers = Self::headers(&req, raw_route, base, client_addr)?;
         req.headers_mut().clear();
@@ -193,7 +290,7 @@ impl HttpHandlerExecutor {
             None => Handler::Latest(Proxy::new(&mut store, &instance)?),
         };
 
-        let handle = task::spawn(async move {
+        let guest = async move {
             let result = match handler {
                 Handler::Latest(proxy) => {
                     proxy
@@ -210,9 +307,14 @@ impl HttpHandlerExecutor {
                 "wasi-http memory consumed: {}",
                 store.as_ref().data().memory_consumed()
             );
+            if let (Err(err), Some(crash)) = (&result, &crash) {
+                crash.capture(&mut store, err);
+            }
 
             result
-        });
+        };
+        // Outbound requests made while streaming the response continue the trace
+        let handle = task::spawn(guest.in_current_span());
 
         match response_rx.await {
             Ok(response) => {
@@ -277,6 +379,12 @@ impl HttpHandlerExecutor {
         {
             res.push((Self::prepare_header_key(keys[0]), val));
         }
+        for (name, val) in crate::compute_path_params(req.uri(), raw, base) {
+            res.push((
+                format!("spin-path-match-{}", name.to_ascii_lowercase()),
+                val,
+            ));
+        }
 
         Ok(res)
     }
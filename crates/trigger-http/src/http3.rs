@@ -0,0 +1,202 @@
+//! Experimental serving of HTTP/3 over QUIC.
+
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use anyhow::{anyhow, Context, Result};
+use bytes::{Buf, Bytes};
+use h3::server::RequestStream;
+use http::{
+    header::{CONNECTION, TRANSFER_ENCODING},
+    uri::Scheme,
+    Request, Response,
+};
+use http_body_util::BodyExt;
+use hyper::body::Frame;
+use tokio::{sync::mpsc, task::JoinSet};
+use tokio_rustls::rustls;
+use tracing::log;
+
+use crate::{client_identity::ClientIdentity, tls::TlsServer, HttpTrigger};
+
+const ALPN_HTTP3: &[u8] = b"h3";
+
+// Request body frames read ahead of the component
+const REQUEST_BODY_BUFFER_FRAMES: usize = 4;
+
+type H3Stream = RequestStream<h3_quinn::BidiStream<Bytes>, Bytes>;
+type H3RecvStream = RequestStream<h3_quinn::RecvStream, Bytes>;
+
+impl HttpTrigger {
+    /// Serves HTTP/3 on a UDP socket until the trigger shuts down.
+    pub(crate) async fn serve_http3(
+        self: Arc<Self>,
+        listen_addr: SocketAddr,
+        tls: Arc<TlsServer>,
+    ) -> Result<()> {
+        let shutdown_signal = self.engine.shutdown_signal().clone();
+
+        let crypto = tls.server_config(&[ALPN_HTTP3]);
+        let config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+        let endpoint = quinn::Endpoint::server(config, listen_addr)
+            .with_context(|| format!("Unable to listen on UDP {}", listen_addr))?;
+
+        let mut connections = JoinSet::new();
+        loop {
+            tokio::select! {
+                Some(connecting) = endpoint.accept() => {
+                    let self_ = self.clone();
+                    let tls = tls.clone();
+                    connections.spawn(async move {
+                        if let Err(e) = self_.serve_http3_connection(connecting, &tls).await {
+                            log::warn!("{e:?}");
+                        }
+                    });
+                }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                _ = shutdown_signal.triggered() => break,
+            }
+        }
+        log::info!(
+            "Waiting for {} open HTTP/3 connection(s) to finish",
+            connections.len()
+        );
+        while connections.join_next().await.is_some() {}
+        endpoint.close(0u32.into(), b"server shutting down");
+        endpoint.wait_idle().await;
+        Ok(())
+    }
+
+    async fn serve_http3_connection(
+        self: Arc<Self>,
+        connecting: quinn::Connecting,
+        tls: &TlsServer,
+    ) -> Result<()> {
+        let connection = connecting.await?;
+        let addr = connection.remote_address();
+        let peer_certs = connection
+            .peer_identity()
+            .and_then(|identity| identity.downcast::<Vec<rustls::Certificate>>().ok());
+        let client_identity = tls
+            .client_identity(peer_certs.as_deref().map(Vec::as_slice))
+            .with_context(|| format!("Refusing connection from {addr}"))?;
+        let mut connection =
+            h3::server::Connection::<_, Bytes>::new(h3_quinn::Connection::new(connection)).await?;
+        let shutdown_signal = self.engine.shutdown_signal().clone();
+
+        let mut requests = JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = connection.accept() => {
+                    let Some((req, stream)) = accepted? else {
+                        break;
+                    };
+                    let self_ = self.clone();
+                    let client_identity = client_identity.clone();
+                    requests.spawn(async move {
+                        let result = self_
+                            .serve_http3_request(req, stream, addr, client_identity.as_ref())
+                            .await;
+                        if let Err(e) = result {
+                            log::warn!("{e:?}");
+                        }
+                    });
+                }
+                Some(_) = requests.join_next(), if !requests.is_empty() => {}
+                // Refuse new requests, then finish in-flight ones
+                _ = shutdown_signal.triggered() => {
+                    connection.shutdown(0).await?;
+                    break;
+                }
+            }
+        }
+        while requests.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    async fn serve_http3_request(
+        &self,
+        mut req: Request<()>,
+        stream: H3Stream,
+        addr: SocketAddr,
+        client_identity: Option<&ClientIdentity>,
+    ) -> Result<()> {
+        ClientIdentity::set_headers(client_identity, req.headers_mut());
+        // The request body is streamed to the component as it arrives
+        let (mut stream, recv_stream) = stream.split();
+        let req = req.map(|()| RequestBody::spawn(recv_stream).boxed());
+
+        let res = self.handle(req, Scheme::HTTPS, addr).await?;
+        let (mut parts, mut body) = res.into_parts();
+        // Connection-specific headers are not allowed in HTTP/3
+        parts.headers.remove(CONNECTION);
+        parts.headers.remove(TRANSFER_ENCODING);
+        stream
+            .send_response(Response::from_parts(parts, ()))
+            .await?;
+
+        while let Some(frame) = body.frame().await {
+            let frame = frame.map_err(|e| anyhow!("failed to read response body: {e:?}"))?;
+            match frame.into_data() {
+                Ok(data) => stream.send_data(data).await?,
+                Err(frame) => {
+                    if let Ok(trailers) = frame.into_trailers() {
+                        stream.send_trailers(trailers).await?;
+                    }
+                }
+            }
+        }
+        stream.finish().await?;
+        Ok(())
+    }
+}
+
+/// The body of an HTTP/3 request, read from the QUIC stream by a task and
+/// handed to the component frame by frame as it arrives.
+struct RequestBody {
+    frames: mpsc::Receiver<Result<Frame<Bytes>>>,
+}
+
+impl RequestBody {
+    fn spawn(mut stream: H3RecvStream) -> Self {
+        let (tx, frames) = mpsc::channel(REQUEST_BODY_BUFFER_FRAMES);
+        tokio::spawn(async move {
+            let result = async {
+                while let Some(mut chunk) = stream.recv_data().await? {
+                    let data = chunk.copy_to_bytes(chunk.remaining());
+                    if tx.send(Ok(Frame::data(data))).await.is_err() {
+                        // The component dropped the body without reading it all
+                        return Ok(());
+                    }
+                }
+                if let Some(trailers) = stream.recv_trailers().await? {
+                    let _ = tx.send(Ok(Frame::trailers(trailers))).await;
+                }
+                Ok::<_, h3::Error>(())
+            }
+            .await;
+            if let Err(err) = result {
+                let _ = tx
+                    .send(Err(anyhow!("failed to read request body: {err:?}")))
+                    .await;
+            }
+        });
+        Self { frames }
+    }
+}
+
+impl hyper::body::Body for RequestBody {
+    type Data = Bytes;
+    type Error = anyhow::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        self.frames.poll_recv(cx)
+    }
+}
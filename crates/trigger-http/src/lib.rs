@@ -1,6 +1,19 @@
 //! Implementation for the Spin HTTP engine.
 
+mod acme;
+mod cache;
+mod capture;
+mod chaining;
+mod client_identity;
+mod compression;
+mod concurrency;
+mod deferred;
 mod handler;
+mod http3;
+mod middleware;
+mod pool;
+mod sse;
+mod static_files;
 mod tls;
 mod wagi;
 
@@ -9,52 +22,96 @@ use std::{
     net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use clap::Args;
-use http::{uri::Scheme, StatusCode, Uri};
+use http::{header::ALT_SVC, uri::Scheme, HeaderValue, StatusCode, Uri};
 use http_body_util::BodyExt;
 use hyper::{
     body::{Bytes, Incoming},
-    server::conn::http1,
+    server::conn::{http1, http2},
     service::service_fn,
     Request, Response,
 };
 use spin_app::{AppComponent, APP_DESCRIPTION_KEY};
-use spin_core::{Engine, OutboundWasiHttpHandler};
+use spin_core::{Engine, EngineBuilder, OutboundWasiHttpHandler, Trap};
 use spin_http::{
     app_info::AppInfo,
     body,
     config::{HttpExecutorType, HttpTriggerConfig},
-    routes::{RoutePattern, Router},
+    routes::{DuplicateRoute, HostRouter, RoutePattern},
 };
 use spin_outbound_networking::{AllowedHostsConfig, OutboundUrl};
 use spin_trigger::{EitherInstancePre, TriggerAppEngine, TriggerExecutor};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
-    net::TcpListener,
-    task,
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
 };
+use tokio_rustls::TlsAcceptor;
 use tracing::log;
 use wasmtime_wasi_http::body::HyperIncomingBody as Body;
 
-use crate::{handler::HttpHandlerExecutor, wagi::WagiHttpExecutor};
+use crate::{
+    cache::ResponseCache,
+    capture::RequestCapture,
+    chaining::ChainedRequestHandler,
+    client_identity::ClientIdentity,
+    concurrency::ConcurrencyLimit,
+    deferred::{DeferredTaskQueue, DeferredTaskWorkers},
+    handler::HttpHandlerExecutor,
+    middleware::{MiddlewareChain, Outcome},
+    pool::InstancePool,
+    static_files::StaticDir,
+    tls::TlsServer,
+    wagi::WagiHttpExecutor,
+};
+
+// Prefixes the route keys of static directory routes, which can't clash with
+// component IDs
+const STATIC_ROUTE_PREFIX: &str = "static:";
 
-pub use tls::TlsConfig;
+pub use acme::AcmeConfig;
+pub use tls::{CertPaths, ClientAuthConfig, TlsConfig};
 
 pub(crate) type RuntimeData = HttpRuntimeData;
 pub(crate) type Store = spin_core::Store<RuntimeData>;
 
 /// The Spin HTTP trigger.
 pub struct HttpTrigger {
-    engine: TriggerAppEngine<Self>,
-    router: Router,
+    engine: Arc<TriggerAppEngine<Self>>,
+    router: HostRouter,
+    // Routes which are unreachable because a later route has the same pattern
+    duplicate_routes: Vec<DuplicateRoute>,
     // Base path for component routes.
     base: String,
     // Component ID -> component trigger config
-    component_trigger_configs: HashMap<String, HttpTriggerConfig>,
+    component_trigger_configs: Arc<HashMap<String, HttpTriggerConfig>>,
+    // Executes requests components make to each other
+    chained_handler: ChainedRequestHandler,
+    // Accepts tasks components defer until after their responses
+    deferred_tasks: DeferredTaskQueue,
+    // Runs deferred tasks, once started
+    deferred_task_workers: Option<DeferredTaskWorkers>,
+    // Component ID -> middleware for the component's route
+    component_middleware: HashMap<String, MiddlewareChain>,
+    // Component ID -> limit on the requests the component handles at once
+    concurrency_limits: Arc<HashMap<String, ConcurrencyLimit>>,
+    // Component ID -> warm instances, for components which keep them
+    instance_pools: HashMap<String, Arc<InstancePool>>,
+    // Responses cached for routes with caching enabled
+    cache: ResponseCache,
+    // Route key -> directory served for each static directory route
+    static_dirs: HashMap<String, StaticDir>,
+    // Whether HTTP/2 is served alongside HTTP/1.1
+    http2: bool,
+    // Advertises HTTP/3 on responses over TCP, if it is served
+    alt_svc: Option<HeaderValue>,
+    // Records inbound requests, if capture is enabled
+    request_capture: Option<RequestCapture>,
 }
 
 #[derive(Args)]
@@ -70,18 +127,109 @@ pub struct CliArgs {
     /// The path to the certificate key to use for https, if this is not set, normal http will be used. The key should be in PKCS#8 format
     #[clap(long, env = "SPIN_TLS_KEY", requires = "tls-cert")]
     pub tls_key: Option<PathBuf>,
+
+    /// The URL of a Redis server in which to cache responses for routes with caching enabled. If this is not set, responses are cached in memory
+    #[clap(long, env = "SPIN_HTTP_CACHE_REDIS_URL")]
+    pub cache_redis_url: Option<String>,
+
+    /// Serve HTTP/2 as well as HTTP/1.1. With TLS, the protocol is negotiated through ALPN; without it, clients must use HTTP/2 with prior knowledge
+    #[clap(long)]
+    pub http2: bool,
+
+    /// Experimental: also serve HTTP/3 over QUIC, on the same port over UDP. Requires TLS
+    #[clap(long)]
+    pub experimental_http3: bool,
+
+    /// A certificate for a specific hostname, selected by SNI, as HOSTNAME=CERT_PATH,KEY_PATH. The hostname may be a wildcard such as *.example.com. May be repeated
+    #[clap(long = "tls-sni-cert", value_parser = parse_sni_cert)]
+    pub tls_sni_certs: Vec<(String, CertPaths)>,
+
+    /// A domain to obtain and renew a certificate for from an ACME provider, Let's Encrypt by default, using the TLS-ALPN-01 challenge. The server must be reachable on port 443 at the domain. Using this agrees to the provider's terms of service. May be repeated
+    #[clap(long = "acme-domain")]
+    pub acme_domains: Vec<String>,
+
+    /// The email address the ACME provider may contact about the account
+    #[clap(long, requires = "acme-domains")]
+    pub acme_email: Option<String>,
+
+    /// The URL of the ACME provider's directory
+    #[clap(long, default_value = acme::LETS_ENCRYPT_DIRECTORY)]
+    pub acme_directory: String,
+
+    /// The path to the certificates of the CAs to verify client certificates with, in PEM format. If this is set, clients must present a certificate signed by one of them, and components receive its subject and SANs in the spin-client-cert-subject and spin-client-cert-sans headers
+    #[clap(long, env = "SPIN_TLS_CLIENT_CA")]
+    pub tls_client_ca: Option<PathBuf>,
+
+    /// Also serve clients which present no certificate, without the client certificate headers
+    #[clap(long, requires = "tls-client-ca")]
+    pub tls_client_cert_optional: bool,
+
+    /// Use Let's Encrypt's staging environment, which issues untrusted certificates without production rate limits, instead of the ACME directory
+    #[clap(long, requires = "acme-domains")]
+    pub acme_staging: bool,
+
+    /// The number of deferred tasks, enqueued by components to run after they have responded, which may run concurrently
+    #[clap(long, default_value = "4")]
+    pub deferred_task_workers: usize,
+
+    /// Record each inbound request, with its headers and body, to a JSON file in this directory, for `spin replay` to re-send
+    #[clap(long)]
+    pub capture_requests: Option<PathBuf>,
+
+    /// The largest request body, in bytes, to record with --capture-requests; requests with larger bodies are served without being recorded
+    #[clap(long, default_value = "1048576", requires = "capture-requests")]
+    pub capture_max_body_bytes: usize,
+
+    /// Record the values of Authorization, Proxy-Authorization and Cookie headers with --capture-requests, rather than redacting them
+    #[clap(long, requires = "capture-requests")]
+    pub capture_sensitive_headers: bool,
+
+    /// Serve the application even if some of its routes are unreachable because a later route has the same pattern, warning about them instead of failing
+    #[clap(long)]
+    pub allow_shadowed_routes: bool,
 }
 
 impl CliArgs {
-    fn into_tls_config(self) -> Option<TlsConfig> {
-        match (self.tls_cert, self.tls_key) {
-            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+    fn into_tls_config(self) -> Result<Option<TlsConfig>> {
+        let default_cert = match (self.tls_cert, self.tls_key) {
+            (Some(cert_path), Some(key_path)) => Some(CertPaths {
                 cert_path,
                 key_path,
             }),
             (None, None) => None,
             _ => unreachable!(),
+        };
+        let acme = if self.acme_domains.is_empty() {
+            None
+        } else {
+            let directory_url = if self.acme_staging {
+                acme::LETS_ENCRYPT_STAGING_DIRECTORY.to_owned()
+            } else {
+                self.acme_directory
+            };
+            Some(AcmeConfig {
+                domains: self.acme_domains,
+                contact_email: self.acme_email,
+                directory_url,
+                cache_dir: spin_common::data_dir::default_data_dir()?.join("acme"),
+            })
+        };
+        if default_cert.is_none() && self.tls_sni_certs.is_empty() && acme.is_none() {
+            if self.tls_client_ca.is_some() {
+                anyhow::bail!("Client certificates require TLS to be configured");
+            }
+            return Ok(None);
         }
+        let client_auth = self.tls_client_ca.map(|ca_path| ClientAuthConfig {
+            ca_path,
+            required: !self.tls_client_cert_optional,
+        });
+        Ok(Some(TlsConfig {
+            default_cert,
+            sni_certs: self.tls_sni_certs,
+            acme,
+            client_auth,
+        }))
     }
 }
 
@@ -101,23 +249,35 @@ impl TriggerExecutor for HttpTrigger {
             base = format!("/{base}");
         }
 
-        let component_routes = engine
+        // Routes are keyed by component ID, or for static directory routes,
+        // which have no component, by a key derived from the trigger ID
+        let route_targets = engine
             .trigger_configs()
-            .map(|(_, config)| (config.component.as_str(), config.route.as_str()));
-
-        let (router, duplicate_routes) = Router::build(&base, component_routes)?;
-
-        if !duplicate_routes.is_empty() {
-            log::error!("The following component routes are duplicates and will never be used:");
-            for dup in &duplicate_routes {
-                log::error!(
-                    "  {}: {} (duplicate of {})",
-                    dup.replaced_id,
-                    dup.route.full_pattern_non_empty(),
-                    dup.effective_id,
-                );
-            }
-        }
+            .map(|(trigger, config)| {
+                let key = match (&config.static_dir, config.component.is_empty()) {
+                    (None, false) => config.component.clone(),
+                    (Some(_), true) => format!("{STATIC_ROUTE_PREFIX}{}", trigger.id()),
+                    (None, true) => anyhow::bail!(
+                        "HTTP trigger {:?} must have either a component or a static_dir",
+                        trigger.id()
+                    ),
+                    (Some(_), false) => anyhow::bail!(
+                        "HTTP trigger {:?} can't have both a component and a static_dir",
+                        trigger.id()
+                    ),
+                };
+                Ok((key, config))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        check_shared_route_settings(&route_targets)?;
+
+        let component_routes = route_targets
+            .iter()
+            .map(|(key, config)| (key.as_str(), config.host.as_deref(), config.route.as_str()));
+
+        let (router, mut duplicate_routes) = HostRouter::build(&base, component_routes)?;
+        // A component routed twice to the same pattern is reachable either way
+        duplicate_routes.retain(|dup| dup.replaced_id != dup.effective_id);
 
         log::trace!(
             "Constructed router for application {}: {:?}",
@@ -125,22 +285,135 @@ impl TriggerExecutor for HttpTrigger {
             router.routes().collect::<Vec<_>>()
         );
 
-        let component_trigger_configs = engine
-            .trigger_configs()
-            .map(|(_, config)| (config.component.clone(), config.clone()))
+        let component_trigger_configs = Arc::new(
+            route_targets
+                .iter()
+                .map(|(key, config)| (key.clone(), (*config).clone()))
+                .collect(),
+        );
+
+        let mut component_middleware = HashMap::new();
+        let engine_ref = &engine;
+        for (key, config) in &route_targets {
+            let chain = MiddlewareChain::new(&config.middleware, move |name| async move {
+                engine_ref.resolve_variable(&name).await
+            })
+            .await
+            .with_context(|| format!("Invalid middleware for route {:?}", config.route))?;
+            component_middleware.insert(key.clone(), chain);
+        }
+
+        let mut concurrency_limits = HashMap::new();
+        for (key, config) in &route_targets {
+            if let Some(limit) = ConcurrencyLimit::from_config(config)? {
+                concurrency_limits.insert(key.clone(), limit);
+            }
+        }
+        let concurrency_limits = Arc::new(concurrency_limits);
+
+        let static_dirs = route_targets
+            .iter()
+            .filter_map(|(key, config)| Some((key, config, config.static_dir.as_ref()?)))
+            .map(|(key, config, dir)| {
+                let route = RoutePattern::from(base.as_str(), config.route.as_str());
+                let static_dir = StaticDir::new(engine.app(), dir, route)
+                    .with_context(|| format!("Invalid static_dir for route {:?}", config.route))?;
+                Ok((key.clone(), static_dir))
+            })
+            .collect::<Result<_>>()?;
+
+        // Wagi modules get per-request arguments and stdin when their stores
+        // are built, so can't be pre-warmed
+        let warm_instances = route_targets
+            .iter()
+            .filter(|(_, config)| config.static_dir.is_none())
+            .filter(|(_, config)| matches!(config.executor, None | Some(HttpExecutorType::Http)))
+            .filter_map(|(key, config)| {
+                Some((key.clone(), config.warm_instances.filter(|n| *n > 0)?))
+            })
+            .collect::<Vec<_>>();
+
+        let engine = Arc::new(engine);
+        let instance_pools = warm_instances
+            .into_iter()
+            .map(|(key, size)| {
+                let pool = InstancePool::start(engine.clone(), key.clone(), size);
+                (key, Arc::new(pool))
+            })
             .collect();
+        let (deferred_tasks, deferred_task_workers) =
+            DeferredTaskQueue::new(component_trigger_configs.clone());
+        let chained_handler = ChainedRequestHandler::new(
+            engine.clone(),
+            component_trigger_configs.clone(),
+            deferred_tasks.clone(),
+            concurrency_limits.clone(),
+        );
 
         Ok(Self {
             engine,
             router,
+            duplicate_routes,
             base,
             component_trigger_configs,
+            chained_handler,
+            deferred_tasks,
+            deferred_task_workers: Some(deferred_task_workers),
+            component_middleware,
+            concurrency_limits,
+            instance_pools,
+            cache: ResponseCache::memory(),
+            static_dirs,
+            http2: false,
+            alt_svc: None,
+            request_capture: None,
         })
     }
 
-    async fn run(self, config: Self::RunConfig) -> Result<()> {
+    async fn run(mut self, mut config: Self::RunConfig) -> Result<()> {
+        if !self.duplicate_routes.is_empty() {
+            let table = DuplicateRoute::table(&self.duplicate_routes);
+            if !config.allow_shadowed_routes {
+                anyhow::bail!(
+                    "Some routes are unreachable because a later route has the same pattern:\n{table}\n\
+                    Change or remove the duplicate routes, or run with --allow-shadowed-routes to serve the application anyway"
+                );
+            }
+            terminal::warn!(
+                "Some routes are unreachable because a later route has the same pattern:\n{table}"
+            );
+        }
         let listen_addr = config.address;
-        let tls = config.into_tls_config();
+        if let Some(url) = config.cache_redis_url.take() {
+            self.cache =
+                ResponseCache::redis(&url, &self.engine.app_name, self.engine.resolver()).await?;
+        }
+        self.http2 = config.http2;
+        if let Some(dir) = config.capture_requests.take() {
+            println!("Capturing requests to {}", dir.display());
+            self.request_capture = Some(RequestCapture::new(
+                dir,
+                config.capture_max_body_bytes,
+                !config.capture_sensitive_headers,
+            )?);
+        }
+        if let Some(workers) = self.deferred_task_workers.take() {
+            workers.start(
+                config.deferred_task_workers,
+                self.engine.clone(),
+                self.chained_handler.clone(),
+                self.deferred_tasks.clone(),
+            );
+        }
+        let http3 = config.experimental_http3;
+        if http3 {
+            let alt_svc = format!("h3=\":{}\"; ma=86400", listen_addr.port());
+            self.alt_svc = Some(HeaderValue::try_from(alt_svc)?);
+        }
+        let tls = config.into_tls_config()?;
+        if http3 && tls.is_none() {
+            anyhow::bail!("HTTP/3 requires TLS to be configured");
+        }
 
         // Print startup messages
         let scheme = if tls.is_some() { "https" } else { "http" };
@@ -149,7 +422,20 @@ impl TriggerExecutor for HttpTrigger {
         log::info!("Serving {}", base_url);
 
         println!("Available Routes:");
-        for (route, component_id) in self.router.routes() {
+        for (host, route, component_id) in self.router.routes() {
+            let base_url = match host {
+                Some(host) => format!("{scheme}://{host}:{}", listen_addr.port()),
+                None => base_url.clone(),
+            };
+            if let Some(static_dir) = self.static_dirs.get(component_id) {
+                println!(
+                    "  (static) {}: {}{}",
+                    static_dir.root().display(),
+                    base_url,
+                    route
+                );
+                continue;
+            }
             println!("  {}: {}{}", component_id, base_url, route);
             if let Some(component) = self.engine.app().get_component(component_id) {
                 if let Some(description) = component.get_metadata(APP_DESCRIPTION_KEY)? {
@@ -158,14 +444,31 @@ impl TriggerExecutor for HttpTrigger {
             }
         }
 
-        if let Some(tls) = tls {
-            self.serve_tls(listen_addr, tls).await?
-        } else {
-            self.serve(listen_addr).await?
+        let self_ = Arc::new(self);
+        let Some(tls) = tls else {
+            return self_.serve(listen_addr).await;
         };
+        let tls_server = Arc::new(tls.server()?);
+        if let Some(acme) = tls.acme {
+            let resolver = tls_server.resolver.clone();
+            tokio::spawn(acme::maintain_certificate(acme, resolver));
+        }
+        if http3 {
+            log::info!("Serving HTTP/3 on UDP {}", listen_addr);
+            tokio::try_join!(
+                self_.clone().serve_tls(listen_addr, tls_server.clone()),
+                self_.serve_http3(listen_addr, tls_server),
+            )?;
+        } else {
+            self_.serve_tls(listen_addr, tls_server).await?;
+        }
         Ok(())
     }
 
+    fn configure_engine(builder: &mut EngineBuilder<Self::RuntimeData>) -> Result<()> {
+        builder.link_import(|linker, _| deferred::add_deferred_tasks_to_linker(linker))
+    }
+
     async fn instantiate_pre(
         engine: &Engine<Self::RuntimeData>,
         component: &AppComponent,
@@ -185,12 +488,35 @@ impl TriggerExecutor for HttpTrigger {
 
 impl HttpTrigger {
     /// Handles incoming requests using an HTTP executor.
+    #[tracing::instrument(
+        name = "spin_trigger_http.handle_http_request",
+        skip_all,
+        fields(
+            otel.kind = "server",
+            http.request.method = %req.method(),
+            url.path = req.uri().path(),
+            http.route = tracing::field::Empty,
+            spin.component_id = tracing::field::Empty,
+            spin.request_id = tracing::field::Empty,
+            http.response.status_code = tracing::field::Empty,
+        )
+    )]
     pub async fn handle(
         &self,
         mut req: Request<Body>,
         scheme: Scheme,
         addr: SocketAddr,
     ) -> Result<Response<Body>> {
+        let span = tracing::Span::current();
+        spin_telemetry::extract_trace_context(&span, req.headers());
+        span.record("spin.request_id", request_id(&req).as_str());
+        // HTTP/2 requests carry the host in the URI rather than a header
+        let host = req
+            .headers()
+            .get(http::header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| req.uri().authority().map(|authority| authority.as_str()))
+            .map(str::to_owned);
         set_req_uri(&mut req, scheme)?;
 
         log::info!(
@@ -210,29 +536,93 @@ impl HttpTrigger {
             };
         }
 
+        if let Some(capture) = &self.request_capture {
+            req = capture.record(req).await?;
+        }
+        let path = req.uri().path();
+
         // Route to app component
-        match self.router.route(path) {
+        match self.router.route(host.as_deref(), path) {
             Ok(component_id) => {
                 let trigger = self.component_trigger_configs.get(component_id).unwrap();
+                let static_dir = self.static_dirs.get(component_id);
+                if static_dir.is_none() {
+                    span.record("spin.component_id", component_id);
+                }
+                span.record("http.route", trigger.route.as_str());
+                let middleware = &self.component_middleware[component_id];
+                let context = match middleware.start(&req, addr.ip()) {
+                    Outcome::Continue(context) => context,
+                    Outcome::Respond(res) => {
+                        span.record("http.response.status_code", res.status().as_u16());
+                        return Ok(res);
+                    }
+                };
+                let cache_entry = trigger.cache.as_ref().and_then(|config| {
+                    Some((ResponseCache::key(component_id, &req, config)?, config))
+                });
+                let encoding = trigger
+                    .compression
+                    .as_ref()
+                    .and_then(|_| compression::negotiate(&req));
+                if let Some((key, _)) = &cache_entry {
+                    if let Some(res) = self.cache.get(key).await {
+                        log::trace!("Serving cached response for {key:?}");
+                        span.record("http.response.status_code", res.status().as_u16());
+                        let mut res = match &trigger.compression {
+                            Some(config) => compression::compress_response(res, config, encoding),
+                            None => res,
+                        };
+                        middleware.finish(&context, &mut res);
+                        return Ok(res);
+                    }
+                }
+                // Held until the component has produced its response
+                let _permit = match self.concurrency_limits.get(component_id) {
+                    Some(limit) => match limit.acquire().await {
+                        Some(permit) => Some(permit),
+                        None => {
+                            log::warn!(
+                                "Component {component_id:?} is at its concurrency limit: rejecting request"
+                            );
+                            span.record(
+                                "http.response.status_code",
+                                StatusCode::TOO_MANY_REQUESTS.as_u16(),
+                            );
+                            return Self::too_many_requests();
+                        }
+                    },
+                    None => None,
+                };
+                // Let the component continue the trace from this span
+                spin_telemetry::inject_trace_context(req.headers_mut());
 
                 let executor = trigger.executor.as_ref().unwrap_or(&HttpExecutorType::Http);
-
-                let res = match executor {
-                    HttpExecutorType::Http => {
-                        HttpHandlerExecutor
-                            .execute(
-                                &self.engine,
-                                component_id,
-                                &self.base,
-                                &trigger.route,
-                                req,
-                                addr,
-                            )
-                            .await
+                let execution_timeout = trigger.execution_timeout_ms.map(Duration::from_millis);
+
+                let res = match (static_dir, executor) {
+                    (Some(static_dir), _) => static_dir.serve(&req).await,
+                    (None, HttpExecutorType::Http) => {
+                        HttpHandlerExecutor {
+                            execution_timeout,
+                            chained_handler: self.chained_handler.clone(),
+                            deferred_tasks: self.deferred_tasks.clone(),
+                            instance_pool: self.instance_pools.get(component_id).cloned(),
+                        }
+                        .execute(
+                            &self.engine,
+                            component_id,
+                            &self.base,
+                            &trigger.route,
+                            req,
+                            addr,
+                        )
+                        .await
                     }
-                    HttpExecutorType::Wagi(wagi_config) => {
+                    (None, HttpExecutorType::Wagi(wagi_config)) => {
                         let executor = WagiHttpExecutor {
                             wagi_config: wagi_config.clone(),
+                            execution_timeout,
                         };
                         executor
                             .execute(
@@ -247,7 +637,34 @@ impl HttpTrigger {
                     }
                 };
                 match res {
-                    Ok(res) => Ok(res),
+                    Ok(mut res) => {
+                        span.record("http.response.status_code", res.status().as_u16());
+                        if let Some((key, config)) = cache_entry {
+                            res = match self.cache.store(key, config, res).await {
+                                Ok(res) => res,
+                                Err(e) => {
+                                    log::error!("Error processing request: {:?}", e);
+                                    return Self::internal_error(None);
+                                }
+                            };
+                        }
+                        let res = sse::event_stream_response(res, &trigger.event_stream);
+                        let mut res = match &trigger.compression {
+                            Some(config) => compression::compress_response(res, config, encoding),
+                            None => res,
+                        };
+                        middleware.finish(&context, &mut res);
+                        Ok(res)
+                    }
+                    Err(e) if e.downcast_ref::<Trap>() == Some(&Trap::Interrupt) => {
+                        log::error!("Component {component_id:?} timed out processing request");
+                        spin_metrics::record_timeout(component_id);
+                        span.record(
+                            "http.response.status_code",
+                            StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                        );
+                        Self::timed_out()
+                    }
                     Err(e) => {
                         log::error!("Error processing request: {:?}", e);
                         Self::internal_error(None)
@@ -279,6 +696,25 @@ impl HttpTrigger {
             .body(body)?)
     }
 
+    /// Creates an HTTP 503 response for an invocation which exceeded its
+    /// execution timeout.
+    fn timed_out() -> Result<Response<Body>> {
+        Ok(Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(body::full(Bytes::from_static(
+                b"Component execution timed out",
+            )))?)
+    }
+
+    /// Creates an HTTP 429 response.
+    fn too_many_requests() -> Result<Response<Body>> {
+        Ok(Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .body(body::full(Bytes::from_static(
+                b"Component is at its concurrency limit",
+            )))?)
+    }
+
     /// Creates an HTTP 404 response.
     fn not_found() -> Result<Response<Body>> {
         Ok(Response::builder()
@@ -286,67 +722,218 @@ impl HttpTrigger {
             .body(body::empty())?)
     }
 
-    fn serve_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+    async fn serve_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
         self_: Arc<Self>,
         stream: S,
         addr: SocketAddr,
+        use_http2: bool,
+        client_identity: Option<ClientIdentity>,
     ) {
-        task::spawn(async move {
-            if let Err(e) = http1::Builder::new()
-                .keep_alive(true)
-                .serve_connection(
-                    stream,
-                    service_fn(move |request| {
-                        let self_ = self_.clone();
-                        async move {
-                            self_
-                                .handle(
-                                    request.map(|body: Incoming| {
-                                        body.map_err(wasmtime_wasi_http::hyper_response_error)
-                                            .boxed()
-                                    }),
-                                    Scheme::HTTP,
-                                    addr,
-                                )
-                                .await
-                        }
-                    }),
-                )
-                .await
-            {
-                log::warn!("{e:?}");
+        let shutdown_signal = self_.engine.shutdown_signal().clone();
+        let service = service_fn(move |mut request: Request<Incoming>| {
+            let self_ = self_.clone();
+            ClientIdentity::set_headers(client_identity.as_ref(), request.headers_mut());
+            async move {
+                let mut res = self_
+                    .handle(
+                        request.map(|body: Incoming| {
+                            body.map_err(wasmtime_wasi_http::hyper_response_error)
+                                .boxed()
+                        }),
+                        Scheme::HTTP,
+                        addr,
+                    )
+                    .await?;
+                if let Some(alt_svc) = &self_.alt_svc {
+                    res.headers_mut().insert(ALT_SVC, alt_svc.clone());
+                }
+                Ok::<_, anyhow::Error>(res)
             }
         });
+        // Finishes any in-flight requests, then closes the connection, once
+        // the trigger is shutting down
+        macro_rules! serve_until_shutdown {
+            ($connection:expr) => {{
+                let connection = $connection;
+                tokio::pin!(connection);
+                let mut draining = false;
+                loop {
+                    tokio::select! {
+                        result = connection.as_mut() => break result,
+                        _ = shutdown_signal.triggered(), if !draining => {
+                            draining = true;
+                            connection.as_mut().graceful_shutdown();
+                        }
+                    }
+                }
+            }};
+        }
+        let result = if use_http2 {
+            serve_until_shutdown!(
+                http2::Builder::new(TokioExecutor).serve_connection(stream, service)
+            )
+        } else {
+            serve_until_shutdown!(http1::Builder::new()
+                .keep_alive(true)
+                .serve_connection(stream, service))
+        };
+        if let Err(e) = result {
+            log::warn!("{e:?}");
+        }
     }
 
-    async fn serve(self, listen_addr: SocketAddr) -> Result<()> {
-        let self_ = Arc::new(self);
+    async fn serve(self: Arc<Self>, listen_addr: SocketAddr) -> Result<()> {
+        let shutdown_signal = self.engine.shutdown_signal().clone();
 
         let listener = TcpListener::bind(listen_addr)
             .await
             .with_context(|| format!("Unable to listen on {}", listen_addr))?;
 
+        let mut connections = JoinSet::new();
         loop {
-            let (stream, addr) = listener.accept().await?;
-            Self::serve_connection(self_.clone(), stream, addr);
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, addr) = accepted?;
+                    let self_ = self.clone();
+                    connections.spawn(async move {
+                        let use_http2 = self_.http2 && is_http2_prior_knowledge(&stream).await;
+                        Self::serve_connection(self_, stream, addr, use_http2, None).await;
+                    });
+                }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                _ = shutdown_signal.triggered() => break,
+            }
         }
+        Self::drain(listener, connections).await;
+        Ok(())
     }
 
-    async fn serve_tls(self, listen_addr: SocketAddr, tls: TlsConfig) -> Result<()> {
-        let self_ = Arc::new(self);
+    async fn serve_tls(
+        self: Arc<Self>,
+        listen_addr: SocketAddr,
+        tls: Arc<TlsServer>,
+    ) -> Result<()> {
+        let shutdown_signal = self.engine.shutdown_signal().clone();
 
         let listener = TcpListener::bind(listen_addr)
             .await
             .with_context(|| format!("Unable to listen on {}", listen_addr))?;
 
-        let acceptor = tls.server_config()?;
+        let mut alpn_protocols = vec![ALPN_HTTP1];
+        if self.http2 {
+            alpn_protocols.insert(0, ALPN_HTTP2);
+        }
+        if tls.acme {
+            alpn_protocols.push(acme::ACME_TLS_ALPN);
+        }
+        let acceptor = TlsAcceptor::from(Arc::new(tls.server_config(&alpn_protocols)));
 
+        let mut connections = JoinSet::new();
         loop {
-            let (stream, addr) = listener.accept().await?;
-            let stream = acceptor.accept(stream).await?;
-            Self::serve_connection(self_.clone(), stream, addr);
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, addr) = accepted?;
+                    let self_ = self.clone();
+                    let acceptor = acceptor.clone();
+                    let tls = tls.clone();
+                    // Handshake off the accept loop, so a slow client doesn't
+                    // hold up others
+                    connections.spawn(async move {
+                        let stream = match acceptor.accept(stream).await {
+                            Ok(stream) => stream,
+                            Err(e) => {
+                                log::warn!("TLS handshake with {addr} failed: {e:?}");
+                                return;
+                            }
+                        };
+                        let session = stream.get_ref().1;
+                        let alpn_protocol = session.alpn_protocol();
+                        // The challenge is answered by the handshake alone
+                        if alpn_protocol == Some(acme::ACME_TLS_ALPN) {
+                            return;
+                        }
+                        let use_http2 = alpn_protocol == Some(ALPN_HTTP2);
+                        let client_identity = match tls.client_identity(session.peer_certificates()) {
+                            Ok(identity) => identity,
+                            Err(e) => {
+                                log::info!("Refusing connection from {addr}: {e}");
+                                return;
+                            }
+                        };
+                        Self::serve_connection(self_, stream, addr, use_http2, client_identity)
+                            .await;
+                    });
+                }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                _ = shutdown_signal.triggered() => break,
+            }
         }
+        Self::drain(listener, connections).await;
+        Ok(())
     }
+
+    // Stops accepting connections and waits for open connections to finish
+    // their in-flight requests.
+    async fn drain(listener: TcpListener, mut connections: JoinSet<()>) {
+        drop(listener);
+        log::info!(
+            "Waiting for {} open connection(s) to finish",
+            connections.len()
+        );
+        while connections.join_next().await.is_some() {}
+    }
+}
+
+const ALPN_HTTP1: &[u8] = b"http/1.1";
+const ALPN_HTTP2: &[u8] = b"h2";
+
+// Every HTTP/2 connection starts with this preface
+const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Returns whether a cleartext client has started the connection with the
+/// HTTP/2 preface, rather than an HTTP/1 request.
+async fn is_http2_prior_knowledge(stream: &TcpStream) -> bool {
+    let mut buf = [0; HTTP2_PREFACE.len()];
+    match stream.peek(&mut buf).await {
+        Ok(len) => starts_http2_preface(&buf[..len]),
+        Err(_) => false,
+    }
+}
+
+// No HTTP/1 method begins like the preface, so a partial preface is enough
+// to tell the protocols apart
+fn starts_http2_preface(bytes: &[u8]) -> bool {
+    !bytes.is_empty() && HTTP2_PREFACE.starts_with(bytes)
+}
+
+/// Runs the background tasks of HTTP/2 connections on the Tokio runtime.
+#[derive(Clone, Copy)]
+struct TokioExecutor;
+
+impl<F> hyper::rt::Executor<F> for TokioExecutor
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::spawn(fut);
+    }
+}
+
+fn parse_sni_cert(arg: &str) -> anyhow::Result<(String, CertPaths)> {
+    let (hostname, paths) = arg
+        .split_once('=')
+        .context("expected HOSTNAME=CERT_PATH,KEY_PATH")?;
+    let (cert_path, key_path) = paths
+        .split_once(',')
+        .context("expected HOSTNAME=CERT_PATH,KEY_PATH")?;
+    Ok((
+        hostname.to_owned(),
+        CertPaths {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        },
+    ))
 }
 
 fn parse_listen_addr(addr: &str) -> anyhow::Result<SocketAddr> {
@@ -362,6 +949,21 @@ fn parse_listen_addr(addr: &str) -> anyhow::Result<SocketAddr> {
     addrs.into_iter().next().context("couldn't resolve address")
 }
 
+// A request id set by a proxy in front of Spin is reused, so that logs from
+// both can be correlated
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Returns the id of the request for logging: the value of its
+/// `x-request-id` header if any, otherwise a new random one.
+fn request_id<B>(req: &Request<B>) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty())
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
 fn set_req_uri(req: &mut Request<Body>, scheme: Scheme) -> Result<()> {
     const DEFAULT_HOST: &str = "localhost";
 
@@ -432,6 +1034,18 @@ pub(crate) fn compute_default_headers<'a>(
     Ok(res)
 }
 
+/// Returns the name and value of each `:name` parameter of the route captured
+/// from the request path. Executors pass these to components alongside the
+/// default headers, as `spin-path-match-<name>` headers or
+/// `X_PATH_MATCH_<NAME>` environment variables.
+pub(crate) fn compute_path_params(uri: &Uri, raw: &str, base: &str) -> Vec<(String, String)> {
+    RoutePattern::from(base, raw)
+        .captures(uri.path())
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value))
+        .collect()
+}
+
 /// The HTTP executor trait.
 /// All HTTP executors must implement this trait.
 #[async_trait]
@@ -454,6 +1068,12 @@ pub struct HttpRuntimeData {
     origin: Option<String>,
     /// The hosts this app is allowed to make outbound requests to
     allowed_hosts: AllowedHostsConfig,
+    /// The component handling the request, for attributing outbound calls in metrics
+    component_id: String,
+    /// Executes the component's requests to `*.spin.internal` in-process
+    chained_handler: Option<ChainedRequestHandler>,
+    /// Accepts the tasks the component defers until after its response
+    deferred_tasks: Option<DeferredTaskQueue>,
 }
 
 impl OutboundWasiHttpHandler for HttpRuntimeData {
@@ -531,8 +1151,56 @@ impl OutboundWasiHttpHandler for HttpRuntimeData {
             anyhow::bail!("destination-not-allowed (error 1)")
         }
 
-        wasmtime_wasi_http::types::default_send_request(data, request)
+        spin_metrics::record_outbound_call(&this.component_id, spin_metrics::outbound::HTTP);
+        // Continue the trace unless the component is propagating its own
+        if !request
+            .request
+            .headers()
+            .contains_key(spin_telemetry::TRACEPARENT_HEADER)
+        {
+            spin_telemetry::inject_trace_context(request.request.headers_mut());
+        }
+
+        let chaining_target = request
+            .request
+            .uri()
+            .host()
+            .and_then(spin_outbound_networking::service_chaining_target);
+        if let Some(component_id) = chaining_target {
+            let Some(chained_handler) = this.chained_handler.clone() else {
+                anyhow::bail!("requests to other components are not supported here");
+            };
+            return chained_handler.send_request(data, component_id, request);
+        }
+        spin_core::send_outgoing_request(data, request)
+    }
+}
+
+// Middleware, limits and the other per-route settings are kept per component,
+// so a component routed by several triggers must have the same settings on
+// each. Otherwise, for example, one route's authentication would be dropped
+// in favour of another route's lack of it.
+fn check_shared_route_settings(route_targets: &[(String, &HttpTriggerConfig)]) -> Result<()> {
+    let mut settings = HashMap::new();
+    for (key, config) in route_targets {
+        let route_settings = serde_json::to_value(HttpTriggerConfig {
+            route: String::new(),
+            host: None,
+            ..(*config).clone()
+        })?;
+        match settings.get(key) {
+            None => {
+                settings.insert(key, (config.route.as_str(), route_settings));
+            }
+            Some((route, other_settings)) if *other_settings != route_settings => anyhow::bail!(
+                "Component {key:?} is routed at both {route:?} and {:?} with different settings. \
+                Routes to the same component must have the same middleware, cache, limits and executor",
+                config.route
+            ),
+            Some(_) => (),
+        }
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -545,6 +1213,20 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn path_params_are_captured_from_the_route() -> Result<()> {
+        let uri = "http://localhost:3000/base/users/42/orders/7?detail=full".parse::<Uri>()?;
+        assert_eq!(
+            compute_path_params(&uri, "/users/:id/orders/:oid", "/base"),
+            [
+                ("id".to_owned(), "42".to_owned()),
+                ("oid".to_owned(), "7".to_owned())
+            ]
+        );
+        assert!(compute_path_params(&uri, "/...", "/base").is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_default_headers_with_base_path() -> Result<()> {
         let scheme = "https";
@@ -729,10 +1411,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn routes_to_a_component_must_share_settings() {
+        let config = |route: &str, middleware: serde_json::Value| -> HttpTriggerConfig {
+            serde_json::from_value(serde_json::json!({
+                "component": "api",
+                "route": route,
+                "middleware": middleware,
+            }))
+            .unwrap()
+        };
+        let auth = serde_json::json!([{ "type": "bearer_auth", "token_variables": ["token"] }]);
+        let public = config("/public/...", serde_json::json!([]));
+        let private = config("/private/...", auth.clone());
+        let also_private = config("/admin/...", auth);
+
+        let key = "api".to_owned();
+        check_shared_route_settings(&[(key.clone(), &private), (key.clone(), &also_private)])
+            .unwrap();
+        let err = check_shared_route_settings(&[(key.clone(), &private), (key, &public)])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("different settings"), "{err}");
+    }
+
     #[test]
     fn parse_listen_addr_prefers_ipv4() {
         let addr = parse_listen_addr("localhost:12345").unwrap();
         assert_eq!(addr.ip(), Ipv4Addr::LOCALHOST);
         assert_eq!(addr.port(), 12345);
     }
+
+    #[test]
+    fn detects_http2_preface() {
+        assert!(starts_http2_preface(HTTP2_PREFACE));
+        assert!(starts_http2_preface(b"PRI * HTTP/2"));
+        assert!(!starts_http2_preface(b"GET / HTTP/1.1\r\n"));
+        assert!(!starts_http2_preface(b""));
+    }
+
+    #[test]
+    fn request_id_reuses_header() {
+        let req = http::Request::builder()
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .body("")
+            .unwrap();
+        assert_eq!(request_id(&req), "abc-123");
+
+        let req = http::Request::builder().body("").unwrap();
+        let generated = request_id(&req);
+        assert_eq!(generated.len(), 36);
+        assert_ne!(generated, request_id(&req));
+    }
 }
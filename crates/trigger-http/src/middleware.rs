@@ -0,0 +1,631 @@
+//! Built-in middleware which handles requests before they reach a component.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{
+    header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+        ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+        ACCESS_CONTROL_REQUEST_METHOD, AUTHORIZATION, ORIGIN, RETRY_AFTER, VARY, WWW_AUTHENTICATE,
+    },
+    HeaderMap, HeaderValue, Method, StatusCode,
+};
+use hyper::{Request, Response};
+use ipnet::IpNet;
+use spin_http::{
+    body,
+    config::{CorsConfig, MiddlewareConfig, RateLimitConfig},
+};
+
+use crate::Body;
+
+// Past this many tracked clients, buckets which have refilled are discarded,
+// and if none have, further clients share one bucket
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// The middleware configured for a route.
+pub(crate) struct MiddlewareChain {
+    middlewares: Vec<Middleware>,
+}
+
+enum Middleware {
+    Cors(Cors),
+    BearerAuth(Vec<String>),
+    BasicAuth {
+        // Base64-encoded `user:password` credentials
+        credentials: Vec<String>,
+        challenge: HeaderValue,
+    },
+    IpAllowlist(Vec<IpNet>),
+    RateLimit(RateLimiter),
+}
+
+/// What the middleware chain decided about a request.
+pub(crate) enum Outcome {
+    /// The request should be passed to the component. The response must then
+    /// be passed to [`MiddlewareChain::finish`].
+    Continue(RequestContext),
+    /// The request was answered by middleware, and the component shouldn't run.
+    Respond(Response<Body>),
+}
+
+/// The parts of a request which middleware needs to process its response.
+pub(crate) struct RequestContext {
+    origin: Option<HeaderValue>,
+}
+
+impl MiddlewareChain {
+    /// Builds the middleware for a route, resolving the app variables which
+    /// hold its secrets with `resolve_variable`.
+    pub async fn new<F, Fut>(configs: &[MiddlewareConfig], resolve_variable: F) -> Result<Self>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        let mut middlewares = Vec::with_capacity(configs.len());
+        for config in configs {
+            middlewares.push(Middleware::new(config, &resolve_variable).await?);
+        }
+        Ok(Self { middlewares })
+    }
+
+    /// Runs a request through each middleware in turn, stopping at the first
+    /// which answers it.
+    pub fn start(&self, req: &Request<Body>, client_addr: IpAddr) -> Outcome {
+        let context = RequestContext {
+            origin: req.headers().get(ORIGIN).cloned(),
+        };
+        for (index, middleware) in self.middlewares.iter().enumerate() {
+            if let Some(mut response) = middleware.handle(req, client_addr) {
+                // Middleware which already ran still sees the response, so
+                // that e.g. a rejection carries CORS headers
+                process_response(&self.middlewares[..=index], &context, &mut response);
+                return Outcome::Respond(response);
+            }
+        }
+        Outcome::Continue(context)
+    }
+
+    /// Processes the component's response to a request which passed through
+    /// the chain.
+    pub fn finish(&self, context: &RequestContext, response: &mut Response<Body>) {
+        process_response(&self.middlewares, context, response);
+    }
+}
+
+// Passes a response back out through the given middleware, innermost first.
+fn process_response(
+    middlewares: &[Middleware],
+    context: &RequestContext,
+    response: &mut Response<Body>,
+) {
+    for middleware in middlewares.iter().rev() {
+        if let Middleware::Cors(cors) = middleware {
+            cors.add_response_headers(context, response.headers_mut());
+        }
+    }
+}
+
+impl Middleware {
+    async fn new<F, Fut>(config: &MiddlewareConfig, resolve_variable: &F) -> Result<Self>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        Ok(match config {
+            MiddlewareConfig::Cors(config) => Self::Cors(Cors::new(config)?),
+            MiddlewareConfig::BearerAuth { token_variables } => {
+                if token_variables.is_empty() {
+                    bail!("bearer_auth middleware must have at least one token variable");
+                }
+                let mut tokens = Vec::with_capacity(token_variables.len());
+                for variable in token_variables {
+                    let token = resolve_variable(variable.clone()).await?;
+                    // An empty token would accept an empty Authorization header
+                    ensure!(
+                        !token.is_empty(),
+                        "bearer_auth token variable {variable:?} is empty"
+                    );
+                    tokens.push(token);
+                }
+                Self::BearerAuth(tokens)
+            }
+            MiddlewareConfig::BasicAuth {
+                password_variables,
+                realm,
+            } => {
+                if password_variables.is_empty() {
+                    bail!("basic_auth middleware must have at least one user");
+                }
+                let mut credentials = Vec::with_capacity(password_variables.len());
+                for (user, variable) in password_variables {
+                    let password = resolve_variable(variable.clone()).await?;
+                    credentials.push(STANDARD.encode(format!("{user}:{password}")));
+                }
+                let realm = realm.as_deref().unwrap_or("spin");
+                let challenge = HeaderValue::try_from(format!("Basic realm={realm:?}"))
+                    .with_context(|| format!("invalid basic_auth realm {realm:?}"))?;
+                Self::BasicAuth {
+                    credentials,
+                    challenge,
+                }
+            }
+            MiddlewareConfig::IpAllowlist { allowed } => Self::IpAllowlist(
+                allowed
+                    .iter()
+                    .map(|network| parse_network(network))
+                    .collect::<Result<_>>()?,
+            ),
+            MiddlewareConfig::RateLimit(config) => Self::RateLimit(RateLimiter::new(config)?),
+        })
+    }
+
+    // Returns a response if the middleware answers the request itself.
+    fn handle(&self, req: &Request<Body>, client_addr: IpAddr) -> Option<Response<Body>> {
+        match self {
+            Self::Cors(cors) => cors.preflight_response(req),
+            Self::BearerAuth(tokens) => {
+                let token = authorization(req.headers(), "Bearer");
+                if token.is_some_and(|token| tokens.iter().any(|t| constant_time_eq(t, token))) {
+                    return None;
+                }
+                Some(unauthorized(HeaderValue::from_static("Bearer")))
+            }
+            Self::BasicAuth {
+                credentials,
+                challenge,
+            } => {
+                let given = authorization(req.headers(), "Basic");
+                if given.is_some_and(|given| credentials.iter().any(|c| constant_time_eq(c, given)))
+                {
+                    return None;
+                }
+                Some(unauthorized(challenge.clone()))
+            }
+            Self::IpAllowlist(networks) => {
+                let client_addr = canonical_addr(client_addr);
+                if networks
+                    .iter()
+                    .any(|network| network.contains(&client_addr))
+                {
+                    return None;
+                }
+                tracing::debug!("Rejected request from {client_addr}: not in IP allowlist");
+                Some(status_response(StatusCode::FORBIDDEN))
+            }
+            Self::RateLimit(limiter) => {
+                let retry_after = limiter.acquire(client_addr, Instant::now()).err()?;
+                let mut response = status_response(StatusCode::TOO_MANY_REQUESTS);
+                // Retry-After is in whole seconds, so round up
+                let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                response
+                    .headers_mut()
+                    .insert(RETRY_AFTER, HeaderValue::from(secs));
+                Some(response)
+            }
+        }
+    }
+}
+
+struct Cors {
+    // `None` allows any origin
+    allowed_origins: Option<Vec<HeaderValue>>,
+    allowed_methods: HeaderValue,
+    allowed_headers: Option<HeaderValue>,
+    allow_credentials: bool,
+    max_age: Option<HeaderValue>,
+}
+
+impl Cors {
+    fn new(config: &CorsConfig) -> Result<Self> {
+        let allowed_origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+            if config.allow_credentials {
+                bail!("cors middleware can't allow credentials from any origin");
+            }
+            None
+        } else {
+            Some(
+                config
+                    .allowed_origins
+                    .iter()
+                    .map(|origin| {
+                        HeaderValue::try_from(origin.as_str())
+                            .with_context(|| format!("invalid cors origin {origin:?}"))
+                    })
+                    .collect::<Result<_>>()?,
+            )
+        };
+        let allowed_methods = HeaderValue::try_from(config.allowed_methods.join(", "))
+            .context("invalid cors allowed_methods")?;
+        let allowed_headers = if config.allowed_headers.is_empty() {
+            None
+        } else {
+            Some(
+                HeaderValue::try_from(config.allowed_headers.join(", "))
+                    .context("invalid cors allowed_headers")?,
+            )
+        };
+        Ok(Self {
+            allowed_origins,
+            allowed_methods,
+            allowed_headers,
+            allow_credentials: config.allow_credentials,
+            max_age: config.max_age_secs.map(HeaderValue::from),
+        })
+    }
+
+    // The value of Access-Control-Allow-Origin for a request from `origin`,
+    // if it is allowed.
+    fn allow_origin(&self, origin: Option<&HeaderValue>) -> Option<HeaderValue> {
+        match &self.allowed_origins {
+            None => Some(HeaderValue::from_static("*")),
+            Some(allowed) => {
+                let origin = origin?;
+                allowed.contains(origin).then(|| origin.clone())
+            }
+        }
+    }
+
+    fn preflight_response(&self, req: &Request<Body>) -> Option<Response<Body>> {
+        let headers = req.headers();
+        let is_preflight = req.method() == Method::OPTIONS
+            && headers.contains_key(ORIGIN)
+            && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+        if !is_preflight {
+            return None;
+        }
+        // Disallowed preflights get no CORS headers, which the client treats
+        // as a refusal
+        let mut response = status_response(StatusCode::NO_CONTENT);
+        if self.allow_origin(headers.get(ORIGIN)).is_some() {
+            let response_headers = response.headers_mut();
+            response_headers.insert(ACCESS_CONTROL_ALLOW_METHODS, self.allowed_methods.clone());
+            if let Some(allowed_headers) = &self.allowed_headers {
+                response_headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers.clone());
+            }
+            if let Some(max_age) = &self.max_age {
+                response_headers.insert(ACCESS_CONTROL_MAX_AGE, max_age.clone());
+            }
+        }
+        Some(response)
+    }
+
+    fn add_response_headers(&self, context: &RequestContext, headers: &mut HeaderMap) {
+        if self.allowed_origins.is_some() {
+            // The response depends on the origin, so caches must key on it
+            headers.append(VARY, HeaderValue::from_static("origin"));
+        }
+        let Some(allow_origin) = self.allow_origin(context.origin.as_ref()) else {
+            return;
+        };
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.allow_credentials {
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+}
+
+struct RateLimiter {
+    // Tokens added per second
+    rate: f64,
+    capacity: f64,
+    per_client: bool,
+    // Client address (or `None` if shared, or past MAX_TRACKED_CLIENTS) -> bucket
+    buckets: Mutex<HashMap<Option<IpAddr>, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    fn new(config: &RateLimitConfig) -> Result<Self> {
+        let rate = config.requests_per_second;
+        if !(rate.is_finite() && rate > 0.0) {
+            bail!("rate_limit requests_per_second must be positive, got {rate}");
+        }
+        let capacity = match config.burst {
+            Some(0) => bail!("rate_limit burst must be at least 1"),
+            Some(burst) => burst.into(),
+            None => rate.ceil(),
+        };
+        Ok(Self {
+            rate,
+            capacity,
+            per_client: config.per_client,
+            buckets: Default::default(),
+        })
+    }
+
+    // Takes a token for the client, or returns how long until one is available.
+    fn acquire(&self, client_addr: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut key = self.per_client.then_some(client_addr);
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.capacity);
+            // Forgetting a client which is being limited would let it start
+            // over with a full bucket, so new clients share one instead
+            if buckets.len() >= MAX_TRACKED_CLIENTS {
+                key = None;
+            }
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+        let tokens = self.refill(bucket, now);
+        if tokens >= 1.0 {
+            bucket.tokens = tokens - 1.0;
+            bucket.updated = now;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - tokens) / self.rate))
+        }
+    }
+
+    // The tokens in the bucket at `now`.
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        (bucket.tokens + elapsed * self.rate).min(self.capacity)
+    }
+}
+
+// Returns the credentials of an Authorization header with the given scheme.
+fn authorization<'a>(headers: &'a HeaderMap, scheme: &str) -> Option<&'a str> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (given_scheme, credentials) = value.split_once(' ')?;
+    given_scheme
+        .eq_ignore_ascii_case(scheme)
+        .then(|| credentials.trim())
+}
+
+// Compares secrets without leaking how much of them matched through timing.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn parse_network(network: &str) -> Result<IpNet> {
+    if let Ok(addr) = network.parse::<IpAddr>() {
+        return Ok(addr.into());
+    }
+    network
+        .parse()
+        .with_context(|| format!("invalid ip_allowlist address or network {network:?}"))
+}
+
+// Treats IPv4 clients connecting over IPv6 as IPv4, so that IPv4 networks
+// match them.
+fn canonical_addr(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(addr, IpAddr::V4),
+        IpAddr::V4(_) => addr,
+    }
+}
+
+fn unauthorized(challenge: HeaderValue) -> Response<Body> {
+    let mut response = status_response(StatusCode::UNAUTHORIZED);
+    response.headers_mut().insert(WWW_AUTHENTICATE, challenge);
+    response
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 168, 1, 10));
+
+    async fn try_chain(configs: serde_json::Value) -> Result<MiddlewareChain> {
+        let configs: Vec<MiddlewareConfig> = serde_json::from_value(configs).unwrap();
+        MiddlewareChain::new(&configs, |name| async move {
+            match name.as_str() {
+                "api_token" => Ok("s3cret".to_owned()),
+                "admin_password" => Ok("hunter2".to_owned()),
+                "empty" => Ok(String::new()),
+                _ => bail!("no variable {name:?}"),
+            }
+        })
+        .await
+    }
+
+    async fn chain(configs: serde_json::Value) -> MiddlewareChain {
+        try_chain(configs).await.unwrap()
+    }
+
+    fn request(headers: &[(&str, &str)]) -> Request<Body> {
+        let mut builder = Request::builder().uri("/api");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(body::empty()).unwrap()
+    }
+
+    fn rejection(outcome: Outcome) -> Response<Body> {
+        match outcome {
+            Outcome::Respond(response) => response,
+            Outcome::Continue(_) => panic!("request should have been rejected"),
+        }
+    }
+
+    #[tokio::test]
+    async fn bearer_auth_checks_tokens() {
+        let chain = chain(serde_json::json!([
+            { "type": "bearer_auth", "token_variables": ["api_token"] },
+        ]))
+        .await;
+        let allowed = request(&[("authorization", "Bearer s3cret")]);
+        assert!(matches!(
+            chain.start(&allowed, CLIENT),
+            Outcome::Continue(_)
+        ));
+
+        let denied = request(&[("authorization", "Bearer guess")]);
+        let response = rejection(chain.start(&denied, CLIENT));
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+    }
+
+    #[tokio::test]
+    async fn basic_auth_checks_credentials() {
+        let chain = chain(serde_json::json!([{
+            "type": "basic_auth",
+            "password_variables": { "admin": "admin_password" },
+            "realm": "admin area",
+        }]))
+        .await;
+        let credentials = format!("Basic {}", STANDARD.encode("admin:hunter2"));
+        let allowed = request(&[("authorization", &credentials)]);
+        assert!(matches!(
+            chain.start(&allowed, CLIENT),
+            Outcome::Continue(_)
+        ));
+
+        let response = rejection(chain.start(&request(&[]), CLIENT));
+        assert_eq!(
+            response.headers()[WWW_AUTHENTICATE],
+            "Basic realm=\"admin area\""
+        );
+    }
+
+    #[tokio::test]
+    async fn ip_allowlist_checks_networks() {
+        let chain = chain(serde_json::json!([{
+            "type": "ip_allowlist",
+            "allowed": ["10.0.0.0/8", "192.168.1.10"],
+        }]))
+        .await;
+        assert!(matches!(
+            chain.start(&request(&[]), CLIENT),
+            Outcome::Continue(_)
+        ));
+        let mapped: IpAddr = "::ffff:10.1.2.3".parse().unwrap();
+        assert!(matches!(
+            chain.start(&request(&[]), mapped),
+            Outcome::Continue(_)
+        ));
+        let outside: IpAddr = "172.16.0.1".parse().unwrap();
+        let response = rejection(chain.start(&request(&[]), outside));
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn rate_limit_refills_over_time() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            requests_per_second: 2.0,
+            burst: Some(2),
+            per_client: true,
+        })
+        .unwrap();
+        let start = Instant::now();
+        assert!(limiter.acquire(CLIENT, start).is_ok());
+        assert!(limiter.acquire(CLIENT, start).is_ok());
+        assert_eq!(
+            limiter.acquire(CLIENT, start),
+            Err(Duration::from_millis(500))
+        );
+        // Other clients have their own buckets
+        let other: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.acquire(other, start).is_ok());
+        assert!(limiter
+            .acquire(CLIENT, start + Duration::from_millis(500))
+            .is_ok());
+    }
+
+    #[test]
+    fn rate_limit_caps_tracked_clients() {
+        let limiter = RateLimiter::new(&RateLimitConfig {
+            requests_per_second: 1.0,
+            burst: Some(1),
+            per_client: true,
+        })
+        .unwrap();
+        let now = Instant::now();
+        for n in 0..MAX_TRACKED_CLIENTS as u128 {
+            assert!(limiter.acquire(IpAddr::V6(n.into()), now).is_ok());
+        }
+        // Clients past the cap share a bucket, rather than evicting clients
+        // which are being limited
+        assert!(limiter.acquire(CLIENT, now).is_ok());
+        let other: IpAddr = "10.0.0.1".parse().unwrap();
+        assert!(limiter.acquire(other, now).is_err());
+        assert!(limiter.acquire(IpAddr::V6(0u128.into()), now).is_err());
+        assert!(limiter.buckets.lock().unwrap().len() <= MAX_TRACKED_CLIENTS + 1);
+    }
+
+    #[tokio::test]
+    async fn cors_answers_preflights_and_decorates_rejections() {
+        let chain = chain(serde_json::json!([
+            { "type": "cors", "allowed_origins": ["https://example.com"], "max_age_secs": 600 },
+            { "type": "bearer_auth", "token_variables": ["api_token"] },
+        ]))
+        .await;
+        let mut preflight = request(&[
+            ("origin", "https://example.com"),
+            ("access-control-request-method", "POST"),
+        ]);
+        *preflight.method_mut() = Method::OPTIONS;
+        let response = rejection(chain.start(&preflight, CLIENT));
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+
+        let unauthenticated = request(&[("origin", "https://example.com")]);
+        let response = rejection(chain.start(&unauthenticated, CLIENT));
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+
+        let other_origin = request(&[
+            ("origin", "https://evil.example"),
+            ("authorization", "Bearer s3cret"),
+        ]);
+        let Outcome::Continue(context) = chain.start(&other_origin, CLIENT) else {
+            panic!("request should have continued");
+        };
+        let mut response = status_response(StatusCode::OK);
+        chain.finish(&context, &mut response);
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+        assert_eq!(response.headers()[VARY], "origin");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_config() {
+        for configs in [
+            serde_json::json!([{ "type": "cors", "allow_credentials": true }]),
+            serde_json::json!([{ "type": "ip_allowlist", "allowed": ["10.0.0.0/33"] }]),
+            serde_json::json!([{ "type": "bearer_auth", "token_variables": ["undefined"] }]),
+            serde_json::json!([{ "type": "bearer_auth", "token_variables": ["empty"] }]),
+        ] {
+            assert!(try_chain(configs.clone()).await.is_err(), "{configs}");
+        }
+    }
+}
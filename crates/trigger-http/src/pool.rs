@@ -0,0 +1,174 @@
+//! Pre-warmed instances: a component with `warm_instances` set has that many
+//! stores and instances created ahead of requests, so that requests to it
+//! don't wait for instantiation.
+//!
+//! Each warm instance handles a single request and is then dropped, as an
+//! instance created on demand would be, so no guest state carries over from
+//! one request to the next. Per-request host state, such as the request's
+//! origin and execution deadlines, is set when an instance is taken from the
+//! pool rather than when it is created. Instances warmed before the component
+//! is hot reloaded are discarded rather than handed out.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use spin_core::{Instance, WasiVersion};
+use spin_trigger::{EitherInstance, TriggerAppEngine};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{HttpTrigger, Store};
+
+// How long to wait before retrying after failing to instantiate a component
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// A warm instance, with the component's reload count when it was prepared
+struct WarmInstance {
+    reload_count: u64,
+    instance: Instance,
+    store: Store,
+}
+
+/// Keeps a number of instances of a component ready to handle requests.
+pub struct InstancePool {
+    engine: Arc<TriggerAppEngine<HttpTrigger>>,
+    component_id: String,
+    instances: Mutex<mpsc::Receiver<WarmInstance>>,
+    refill: JoinHandle<()>,
+}
+
+impl InstancePool {
+    /// Starts filling a pool of `size` instances of the component, which is
+    /// refilled in the background as instances are taken.
+    pub fn start(
+        engine: Arc<TriggerAppEngine<HttpTrigger>>,
+        component_id: String,
+        size: usize,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(size);
+        let refill = tokio::spawn({
+            let engine = engine.clone();
+            let component_id = component_id.clone();
+            async move {
+                // Waiting for room keeps at most `size` instances warm
+                while let Ok(slot) = sender.reserve().await {
+                    // Read before instantiating, so that an instance is never
+                    // newer than its count says
+                    let reload_count = engine.reload_count(&component_id);
+                    match prepare_instance(&engine, &component_id).await {
+                        Ok((instance, store)) => slot.send(WarmInstance {
+                            reload_count,
+                            instance,
+                            store,
+                        }),
+                        Err(err) => {
+                            drop(slot);
+                            tracing::error!(
+                                "Failed to pre-warm an instance of component {component_id:?}: {err:?}"
+                            );
+                            tokio::time::sleep(RETRY_DELAY).await;
+                        }
+                    }
+                }
+            }
+        });
+        Self {
+            engine,
+            component_id,
+            instances: Mutex::new(receiver),
+            refill,
+        }
+    }
+
+    /// Takes a warm instance, if one is ready, restarting its execution time
+    /// limit with the given limit from now. Requests should instantiate the
+    /// component themselves if not, rather than wait for the pool.
+    ///
+    /// Instances warmed before the component was last reloaded are dropped,
+    /// making room for the pool to refill with the reloaded component.
+    pub fn take(&self, execution_time_limit: Option<Duration>) -> Option<(Instance, Store)> {
+        let reload_count = self.engine.reload_count(&self.component_id);
+        let mut instances = self.instances.lock().unwrap();
+        loop {
+            let warm = instances.try_recv().ok()?;
+            if warm.reload_count == reload_count {
+                let mut store = warm.store;
+                // The deadline runs from the start of the request, not from
+                // when the instance was warmed
+                store.restart_execution_time_limit(execution_time_limit);
+                return Some((warm.instance, store));
+            }
+        }
+    }
+}
+
+impl Drop for InstancePool {
+    fn drop(&mut self) {
+        self.refill.abort();
+    }
+}
+
+async fn prepare_instance(
+    engine: &TriggerAppEngine<HttpTrigger>,
+    component_id: &str,
+) -> Result<(Instance, Store)> {
+    let store_builder = engine.store_builder(component_id, WasiVersion::Preview2)?;
+    let (instance, store) = engine
+        .prepare_instance_with_store(component_id, store_builder)
+        .await?;
+    let EitherInstance::Component(instance) = instance else {
+        bail!("component {component_id:?} is a module, which can't be pre-warmed");
+    };
+    Ok((instance, store))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    const WARM_UP_TIMEOUT: Duration = Duration::from_secs(30);
+
+    async fn test_pool() -> InstancePool {
+        let trigger: HttpTrigger = spin_testing::HttpTestConfig::default()
+            .test_program("rust-http-test.wasm")
+            .http_spin_trigger("/test")
+            .build_trigger()
+            .await;
+        InstancePool::start(trigger.engine.clone(), "test-component".into(), 1)
+    }
+
+    // Waits for a warm instance, returning it and when it was taken
+    async fn take_when_warm(pool: &InstancePool, limit: Option<Duration>) -> (Store, Instant) {
+        let wait = async {
+            loop {
+                let taken_at = Instant::now();
+                if let Some((_, store)) = pool.take(limit) {
+                    return (store, taken_at);
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(WARM_UP_TIMEOUT, wait)
+            .await
+            .expect("no instance was warmed in time")
+    }
+
+    #[tokio::test]
+    async fn pool_refills_and_taken_instances_get_fresh_deadlines() {
+        let pool = test_pool().await;
+        let limit = Duration::from_secs(60);
+
+        let (store, taken_at) = take_when_warm(&pool, Some(limit)).await;
+        assert!(store.deadline().unwrap() >= taken_at + limit);
+
+        // The pool refills once an instance is taken; give the new instance
+        // time to sit warm before taking it
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let (store, taken_at) = take_when_warm(&pool, Some(limit)).await;
+        assert!(store.deadline().unwrap() >= taken_at + limit);
+    }
+}
@@ -0,0 +1,251 @@
+//! Server-sent events support for HTTP responses.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use http::{
+    header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE},
+    HeaderMap, HeaderValue,
+};
+use http_body_util::combinators::BoxBody;
+use hyper::{
+    body::{Bytes, Frame},
+    Response,
+};
+use spin_http::config::{EventStreamConfig, EventStreamFraming};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+
+use crate::Body;
+
+const EVENT_STREAM_CONTENT_TYPE: &str = "text/event-stream";
+
+// An SSE comment line, which clients ignore
+const KEEP_ALIVE_COMMENT: &[u8] = b": keep-alive\n\n";
+
+/// Prepares a response to be sent as a server-sent event stream, if it is
+/// one. Other responses are returned unchanged.
+pub(crate) fn event_stream_response(
+    response: Response<Body>,
+    config: &EventStreamConfig,
+) -> Response<Body> {
+    let (mut parts, body) = response.into_parts();
+    match config.framing {
+        EventStreamFraming::None if !is_event_stream(&parts.headers) => {
+            return Response::from_parts(parts, body);
+        }
+        EventStreamFraming::None => (),
+        EventStreamFraming::Lines => {
+            parts.headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static(EVENT_STREAM_CONTENT_TYPE),
+            );
+        }
+    }
+
+    // The stream's length isn't known up front, and intermediaries must pass
+    // events on as they arrive
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .entry(CACHE_CONTROL)
+        .or_insert(HeaderValue::from_static("no-cache"));
+    parts
+        .headers
+        .entry("x-accel-buffering")
+        .or_insert(HeaderValue::from_static("no"));
+
+    let body = EventStreamBody::new(body, config);
+    Response::from_parts(parts, BoxBody::new(body))
+}
+
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|media_type| {
+            media_type
+                .trim()
+                .eq_ignore_ascii_case(EVENT_STREAM_CONTENT_TYPE)
+        })
+}
+
+/// A response body which frames the component's output as events, if
+/// required, and sends keep-alive comments while the component isn't writing.
+///
+/// Output is only read from the component as the client is ready to receive
+/// it, and keep-alives are only sent in place of output, so a slow client
+/// applies backpressure to the component rather than causing output to be
+/// buffered.
+struct EventStreamBody {
+    inner: Body,
+    framing: EventStreamFraming,
+    // Output after the last complete line, when framing lines
+    partial_line: Vec<u8>,
+    keep_alive: Option<Interval>,
+}
+
+impl EventStreamBody {
+    fn new(inner: Body, config: &EventStreamConfig) -> Self {
+        let keep_alive = (config.keep_alive_secs > 0).then(|| {
+            let period = Duration::from_secs(config.keep_alive_secs);
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        Self {
+            inner,
+            framing: config.framing,
+            partial_line: Vec::new(),
+            keep_alive,
+        }
+    }
+
+    // Frames each complete line of output as the data of an event, holding
+    // back any incomplete final line until more output arrives.
+    fn frame_lines(&mut self, data: &[u8]) -> Bytes {
+        self.partial_line.extend_from_slice(data);
+        let Some(end) = self.partial_line.iter().rposition(|&b| b == b'\n') else {
+            return Bytes::new();
+        };
+        let complete = self.partial_line.drain(..=end).collect::<Vec<_>>();
+        frame_events(complete[..end].split(|&b| b == b'\n'))
+    }
+}
+
+fn frame_events<'a>(lines: impl Iterator<Item = &'a [u8]>) -> Bytes {
+    let mut framed = Vec::new();
+    for line in lines {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        // Clients don't dispatch events with no data
+        if line.is_empty() {
+            continue;
+        }
+        framed.extend_from_slice(b"data: ");
+        framed.extend_from_slice(line);
+        framed.extend_from_slice(b"\n\n");
+    }
+    framed.into()
+}
+
+impl hyper::body::Body for EventStreamBody {
+    type Data = Bytes;
+    type Error = <Body as hyper::body::Body>::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        loop {
+            let frame = match Pin::new(&mut this.inner).poll_frame(cx) {
+                Poll::Ready(Some(Ok(frame))) => frame,
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => {
+                    // Send any final line which wasn't terminated
+                    let remaining = std::mem::take(&mut this.partial_line);
+                    let framed = frame_events(std::iter::once(remaining.as_slice()));
+                    if framed.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Ok(Frame::data(framed))));
+                }
+                Poll::Pending => {
+                    let keep_alive_due = this
+                        .keep_alive
+                        .as_mut()
+                        .is_some_and(|keep_alive| keep_alive.poll_tick(cx).is_ready());
+                    if keep_alive_due {
+                        let comment = Bytes::from_static(KEEP_ALIVE_COMMENT);
+                        return Poll::Ready(Some(Ok(Frame::data(comment))));
+                    }
+                    return Poll::Pending;
+                }
+            };
+
+            // Output restarts the keep-alive period
+            if let Some(keep_alive) = &mut this.keep_alive {
+                keep_alive.reset();
+            }
+            let data = match frame.into_data() {
+                Ok(data) => data,
+                Err(frame) => return Poll::Ready(Some(Ok(frame))),
+            };
+            let data = match this.framing {
+                EventStreamFraming::None => data,
+                EventStreamFraming::Lines => this.frame_lines(&data),
+            };
+            // Wait for more output rather than sending an empty frame
+            if !data.is_empty() {
+                return Poll::Ready(Some(Ok(Frame::data(data))));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+    use spin_http::body;
+
+    use super::*;
+
+    fn lines_config() -> EventStreamConfig {
+        EventStreamConfig {
+            keep_alive_secs: 0,
+            framing: EventStreamFraming::Lines,
+        }
+    }
+
+    #[tokio::test]
+    async fn frames_lines_as_events() {
+        let response = Response::new(body::full(Bytes::from_static(b"one\r\ntwo\n\nthree")));
+        let response = event_stream_response(response, &lines_config());
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/event-stream");
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-cache");
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            body,
+            Bytes::from_static(b"data: one\n\ndata: two\n\ndata: three\n\n")
+        );
+    }
+
+    #[test]
+    fn holds_back_partial_lines() {
+        let mut body = EventStreamBody::new(body::empty(), &lines_config());
+        assert!(body.frame_lines(b"hel").is_empty());
+        assert_eq!(
+            body.frame_lines(b"lo\nwor"),
+            Bytes::from_static(b"data: hello\n\n")
+        );
+        assert_eq!(body.partial_line, b"wor");
+    }
+
+    #[test]
+    fn passes_through_other_responses() {
+        let response = Response::builder()
+            .header(CONTENT_TYPE, "text/plain")
+            .header(CONTENT_LENGTH, "2")
+            .body(body::full(Bytes::from_static(b"hi")))
+            .unwrap();
+        let response = event_stream_response(response, &EventStreamConfig::default());
+        assert_eq!(response.headers()[CONTENT_LENGTH], "2");
+        assert!(response.headers().get(CACHE_CONTROL).is_none());
+    }
+
+    #[test]
+    fn recognises_event_stream_content_types() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("Text/Event-Stream; charset=utf-8"),
+        );
+        assert!(is_event_stream(&headers));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        assert!(!is_event_stream(&headers));
+    }
+}
@@ -0,0 +1,514 @@
+//! Serving of static directories by the host, for routes with no component.
+
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{ready, Context as TaskContext, Poll},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result};
+use futures::Stream;
+use http::{
+    header::{
+        ACCEPT_ENCODING, ACCEPT_RANGES, ALLOW, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE,
+        CONTENT_TYPE, ETAG, IF_NONE_MATCH, IF_RANGE, RANGE, VARY,
+    },
+    HeaderMap, HeaderValue, Method, StatusCode,
+};
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::{
+    body::{Bytes, Frame, SizeHint},
+    Request, Response,
+};
+use percent_encoding::percent_decode_str;
+use spin_app::{App, APP_ORIGIN_KEY};
+use spin_http::{body, routes::RoutePattern};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, Take};
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    compression::{self, Encoding},
+    Body,
+};
+
+// The file served for a request for a directory
+const INDEX_FILE: &str = "index.html";
+
+// Files smaller than this aren't worth compressing
+const MIN_COMPRESS_LEN: u64 = 1024;
+
+// The size of the chunks files are streamed in
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A directory of files served for a route.
+pub(crate) struct StaticDir {
+    root: PathBuf,
+    route: RoutePattern,
+}
+
+impl StaticDir {
+    /// Returns a `StaticDir` for `dir`, which is resolved relative to the
+    /// directory of the app's manifest.
+    pub fn new(app: &App, dir: &str, route: RoutePattern) -> Result<Self> {
+        let dir = Path::new(dir);
+        let root = if dir.is_absolute() {
+            dir.to_owned()
+        } else {
+            let origin = app
+                .get_metadata(APP_ORIGIN_KEY)?
+                .context("static_dir routes require an app loaded from a local manifest")?;
+            let manifest_path = spin_common::url::parse_file_url(&origin)
+                .context("static_dir routes require an app loaded from a local manifest")?;
+            manifest_path
+                .parent()
+                .context("manifest path has no parent directory")?
+                .join(dir)
+        };
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("static_dir {} not found", root.display()))?;
+        if !root.is_dir() {
+            anyhow::bail!("static_dir {} is not a directory", root.display());
+        }
+        Ok(Self { root, route })
+    }
+
+    /// The directory files are served from.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Serves the file a request is for.
+    pub async fn serve(&self, req: &Request<Body>) -> Result<Response<Body>> {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            let mut response = status_response(StatusCode::METHOD_NOT_ALLOWED);
+            response
+                .headers_mut()
+                .insert(ALLOW, HeaderValue::from_static("GET, HEAD"));
+            return Ok(response);
+        }
+        let relative = self.route.relative(&req.uri().to_string())?;
+        let Some(path) = self.resolve(&relative).await else {
+            return Ok(status_response(StatusCode::NOT_FOUND));
+        };
+        File::open(path).await?.respond(req).await
+    }
+
+    // Maps a request path to a file in the directory, if it safely can.
+    async fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        let decoded = percent_decode_str(relative).decode_utf8().ok()?;
+        let mut path = self.root.clone();
+        for segment in decoded.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return None,
+                _ if segment.contains(['\\', '\0']) => return None,
+                _ => path.push(segment),
+            }
+        }
+        if tokio::fs::metadata(&path).await.ok()?.is_dir() {
+            path.push(INDEX_FILE);
+        }
+        // Don't follow symlinks out of the directory
+        let path = tokio::fs::canonicalize(&path).await.ok()?;
+        let is_file = tokio::fs::metadata(&path).await.ok()?.is_file();
+        (is_file && path.starts_with(&self.root)).then_some(path)
+    }
+}
+
+struct File {
+    path: PathBuf,
+    len: u64,
+    // Modification time, in nanoseconds since the Unix epoch
+    modified: u128,
+    content_type: mime_guess::Mime,
+}
+
+impl File {
+    async fn open(path: PathBuf) -> Result<Self> {
+        let metadata = tokio::fs::metadata(&path).await?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |modified| modified.as_nanos());
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+        Ok(Self {
+            path,
+            len: metadata.len(),
+            modified,
+            content_type,
+        })
+    }
+
+    fn etag(&self, gzip: bool) -> HeaderValue {
+        let suffix = if gzip { "-gzip" } else { "" };
+        let etag = format!("\"{:x}-{:x}{suffix}\"", self.len, self.modified);
+        HeaderValue::try_from(etag).expect("ETag should be a valid header value")
+    }
+
+    fn is_compressible(&self) -> bool {
+        let essence = self.content_type.essence_str();
+        let compressible_type = essence.starts_with("text/")
+            || matches!(
+                essence,
+                "application/javascript"
+                    | "application/json"
+                    | "application/wasm"
+                    | "application/xml"
+                    | "image/svg+xml"
+            );
+        compressible_type && self.len >= MIN_COMPRESS_LEN
+    }
+
+    async fn respond(&self, req: &Request<Body>) -> Result<Response<Body>> {
+        let headers = req.headers();
+        let range = headers
+            .get(RANGE)
+            .filter(|_| if_range_matches(headers, &self.etag(false)))
+            .and_then(|range| range.to_str().ok())
+            // Ranges in other units are ignored
+            .filter(|range| range.starts_with("bytes="))
+            .map(|range| parse_range(range, self.len));
+        let gzip = range.is_none() && self.is_compressible() && accepts_gzip(headers);
+        let etag = self.etag(gzip);
+
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(ETAG, etag.clone());
+        response_headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        if self.is_compressible() {
+            response_headers.insert(VARY, HeaderValue::from_static("accept-encoding"));
+        }
+        if etag_matches(headers.get(IF_NONE_MATCH), &etag) {
+            return Ok(response(
+                StatusCode::NOT_MODIFIED,
+                response_headers,
+                body::empty(),
+            ));
+        }
+
+        let (status, start, end) = match range {
+            None => (StatusCode::OK, 0, self.len),
+            Some(Some((start, end))) => {
+                let content_range = format!("bytes {start}-{}/{}", end - 1, self.len);
+                response_headers.insert(CONTENT_RANGE, HeaderValue::try_from(content_range)?);
+                (StatusCode::PARTIAL_CONTENT, start, end)
+            }
+            Some(None) => {
+                let content_range = format!("bytes */{}", self.len);
+                response_headers.insert(CONTENT_RANGE, HeaderValue::try_from(content_range)?);
+                return Ok(response(
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    response_headers,
+                    body::empty(),
+                ));
+            }
+        };
+        response_headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::try_from(self.content_type.as_ref())?,
+        );
+
+        // A compressed response is streamed as it is compressed, so its
+        // length isn't known in advance
+        if gzip {
+            response_headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        } else {
+            response_headers.insert(CONTENT_LENGTH, HeaderValue::from(end - start));
+        }
+        if req.method() == Method::HEAD {
+            return Ok(response(status, response_headers, body::empty()));
+        }
+        let mut contents = self.read(start, end).await?;
+        if gzip {
+            contents = compression::compress_body(contents, Encoding::Gzip);
+        }
+        Ok(response(status, response_headers, contents))
+    }
+
+    // Returns a body which streams the half-open byte range `start..end`.
+    async fn read(&self, start: u64, end: u64) -> Result<Body> {
+        let mut file = tokio::fs::File::open(&self.path)
+            .await
+            .with_context(|| format!("failed to open {}", self.path.display()))?;
+        file.seek(SeekFrom::Start(start)).await?;
+        let body = FileBody {
+            stream: ReaderStream::with_capacity(file.take(end - start), READ_CHUNK_SIZE),
+            remaining: end - start,
+        };
+        Ok(BoxBody::new(body.map_err(Into::into)))
+    }
+}
+
+/// A body which streams a range of a file as it is read.
+struct FileBody {
+    stream: ReaderStream<Take<tokio::fs::File>>,
+    // The bytes of the range still to be read
+    remaining: u64,
+}
+
+impl hyper::body::Body for FileBody {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+            Some(Ok(data)) => {
+                self.remaining = self.remaining.saturating_sub(data.len() as u64);
+                Poll::Ready(Some(Ok(Frame::data(data))))
+            }
+            Some(Err(err)) => Poll::Ready(Some(Err(err))),
+            // The file was truncated while being read
+            None if self.remaining > 0 => {
+                self.remaining = 0;
+                Poll::Ready(Some(Err(std::io::ErrorKind::UnexpectedEof.into())))
+            }
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
+
+// Parses a `Range` header for a file of length `len`, returning the half-open
+// byte range requested, or `None` if it can't be satisfied. Multiple ranges
+// aren't supported, so only the first is served.
+fn parse_range(range: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    let first = spec.split(',').next()?.trim();
+    let (start, end) = first.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        // The last `suffix` bytes
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.saturating_sub(suffix), len)
+        }
+        (start, "") => (start.parse().ok()?, len),
+        (start, end) => {
+            let end: u64 = end.parse().ok()?;
+            (start.parse().ok()?, end.saturating_add(1).min(len))
+        }
+    };
+    (start < end).then_some((start, end))
+}
+
+// Whether a range request should be honoured: if the request is conditional
+// on an `If-Range` ETag, the file must still have it.
+fn if_range_matches(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    headers
+        .get(IF_RANGE)
+        .map_or(true, |if_range| if_range == etag)
+}
+
+fn etag_matches(if_none_match: Option<&HeaderValue>, etag: &HeaderValue) -> bool {
+    let Some(if_none_match) = if_none_match.and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+    if_none_match.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        // Weak comparison, as If-None-Match requires
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut params = coding.split(';');
+            let name = params.next().unwrap_or_default().trim();
+            let refused = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    == Some(0.0)
+            });
+            name.eq_ignore_ascii_case("gzip") && !refused
+        })
+}
+
+fn response(status: StatusCode, headers: HeaderMap, contents: Body) -> Response<Body> {
+    let mut response = Response::new(contents);
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    response
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    response(status, HeaderMap::new(), body::empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    fn static_dir(root: &Path) -> StaticDir {
+        StaticDir {
+            root: root.canonicalize().unwrap(),
+            route: RoutePattern::from("/", "/static/..."),
+        }
+    }
+
+    fn request(path: &str, headers: &[(&str, &str)]) -> Request<Body> {
+        let mut builder = Request::builder().uri(format!("http://localhost{path}"));
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(body::empty()).unwrap()
+    }
+
+    async fn body_bytes(response: Response<Body>) -> Vec<u8> {
+        response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec()
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 100), Some((0, 10)));
+        assert_eq!(parse_range("bytes=90-", 100), Some((90, 100)));
+        assert_eq!(parse_range("bytes=-10", 100), Some((90, 100)));
+        assert_eq!(parse_range("bytes=95-200", 100), Some((95, 100)));
+        assert_eq!(parse_range("bytes=100-", 100), None);
+        assert_eq!(parse_range("bytes=5-2", 100), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_paths_outside_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("public")).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        std::fs::write(dir.path().join("public/index.html"), "<h1>hi</h1>").unwrap();
+        let static_dir = static_dir(&dir.path().join("public"));
+
+        assert!(static_dir.resolve("/../secret.txt").await.is_none());
+        assert!(static_dir.resolve("/%2e%2e/secret.txt").await.is_none());
+        assert_eq!(
+            static_dir.resolve("/").await.unwrap(),
+            static_dir.root().join("index.html")
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_files_with_etags_and_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("data.bin"), b"0123456789").unwrap();
+        let static_dir = static_dir(dir.path());
+
+        let response = static_dir
+            .serve(&request("/static/data.bin", &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[ETAG].to_str().unwrap().to_owned();
+        assert_eq!(body_bytes(response).await, b"0123456789");
+
+        let response = static_dir
+            .serve(&request("/static/data.bin", &[("if-none-match", &etag)]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = static_dir
+            .serve(&request("/static/data.bin", &[("range", "bytes=2-4")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 2-4/10");
+        assert_eq!(body_bytes(response).await, b"234");
+
+        let response = static_dir
+            .serve(&request("/static/missing.txt", &[]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn streams_large_files_in_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let contents = (0..=255u8)
+            .cycle()
+            .take(3 * READ_CHUNK_SIZE)
+            .collect::<Vec<_>>();
+        std::fs::write(dir.path().join("large.bin"), &contents).unwrap();
+        let static_dir = static_dir(dir.path());
+
+        let response = static_dir
+            .serve(&request("/static/large.bin", &[]))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[CONTENT_LENGTH],
+            contents.len().to_string()
+        );
+        let mut body = response.into_body();
+        let first = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        // The file isn't read into memory all at once
+        assert!(first.len() <= READ_CHUNK_SIZE);
+
+        let range = format!("bytes={}-", READ_CHUNK_SIZE + 10);
+        let response = static_dir
+            .serve(&request("/static/large.bin", &[("range", &range)]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            body_bytes(response).await,
+            &contents[READ_CHUNK_SIZE + 10..]
+        );
+    }
+
+    #[tokio::test]
+    async fn compresses_text_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = "hello, world\n".repeat(200);
+        std::fs::write(dir.path().join("hello.txt"), &text).unwrap();
+        let static_dir = static_dir(dir.path());
+
+        let response = static_dir
+            .serve(&request(
+                "/static/hello.txt",
+                &[("accept-encoding", "br;q=1.0, gzip;q=0.8")],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[VARY], "accept-encoding");
+        let compressed = body_bytes(response).await;
+        let mut decoded = String::new();
+        std::io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(&compressed[..]),
+            &mut decoded,
+        )
+        .unwrap();
+        assert_eq!(decoded, text);
+
+        let response = static_dir
+            .serve(&request(
+                "/static/hello.txt",
+                &[("accept-encoding", "gzip;q=0")],
+            ))
+            .await
+            .unwrap();
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    }
+}
@@ -1,14 +1,52 @@
+use anyhow::Context;
 use rustls_pemfile::{certs, pkcs8_private_keys};
 use std::{
+    collections::HashMap,
     fs, io,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, RwLock},
+};
+use tokio_rustls::rustls::{
+    self,
+    server::{
+        AllowAnyAnonymousOrAuthenticatedClient, ClientCertVerifier, ClientHello, ResolvesServerCert,
+    },
+    sign::CertifiedKey,
+};
+
+use crate::{
+    acme::{AcmeConfig, ACME_TLS_ALPN},
+    client_identity::ClientIdentity,
 };
-use tokio_rustls::{rustls, TlsAcceptor};
 
 /// TLS configuration for the server.
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct TlsConfig {
+    /// Certificate for clients whose hostname matches no other certificate.
+    pub default_cert: Option<CertPaths>,
+    /// Certificates for specific hostnames, selected by SNI. A hostname may
+    /// be a wildcard such as `*.example.com`.
+    pub sni_certs: Vec<(String, CertPaths)>,
+    /// Certificates obtained from an ACME provider, if enabled.
+    pub acme: Option<AcmeConfig>,
+    /// Verification of client certificates, if enabled.
+    pub client_auth: Option<ClientAuthConfig>,
+}
+
+/// Client certificate (mutual TLS) configuration.
+#[derive(Clone, Debug)]
+pub struct ClientAuthConfig {
+    /// Path to the PEM certificates of the CAs which may sign client
+    /// certificates.
+    pub ca_path: PathBuf,
+    /// Whether clients must present a certificate. If not, clients without
+    /// one are served without a verified identity.
+    pub required: bool,
+}
+
+/// Paths to a PEM certificate chain and its PKCS#8 key.
+#[derive(Clone, Debug)]
+pub struct CertPaths {
     /// Path to TLS certificate.
     pub cert_path: PathBuf,
     /// Path to TLS key.
@@ -16,31 +54,227 @@ pub struct TlsConfig {
 }
 
 impl TlsConfig {
-    // Creates a TLS acceptor from server config.
-    pub(super) fn server_config(&self) -> anyhow::Result<TlsAcceptor> {
-        let certs = load_certs(&self.cert_path)?;
-        let mut keys = load_keys(&self.key_path)?;
+    // Loads the configured certificates for the listeners.
+    pub(super) fn server(&self) -> anyhow::Result<TlsServer> {
+        let client_verifier = self
+            .client_auth
+            .as_ref()
+            .map(ClientAuthConfig::verifier)
+            .transpose()?;
+        Ok(TlsServer {
+            resolver: self.cert_resolver()?,
+            client_verifier,
+            client_cert_required: self.client_auth.as_ref().is_some_and(|auth| auth.required),
+            acme: self.acme.is_some(),
+        })
+    }
+
+    // Creates a certificate resolver holding the configured certificates.
+    fn cert_resolver(&self) -> anyhow::Result<Arc<CertResolver>> {
+        let default = self
+            .default_cert
+            .as_ref()
+            .map(CertPaths::load)
+            .transpose()?
+            .map(Arc::new);
+        let resolver = CertResolver {
+            default,
+            ..Default::default()
+        };
+        for (hostname, paths) in &self.sni_certs {
+            resolver.insert(hostname, paths.load()?);
+        }
+        Ok(Arc::new(resolver))
+    }
+}
+
+impl CertPaths {
+    pub(super) fn load(&self) -> anyhow::Result<CertifiedKey> {
+        let certs = load_certs(&self.cert_path)
+            .with_context(|| format!("failed to load {}", self.cert_path.display()))?;
+        let mut keys = load_keys(&self.key_path)
+            .with_context(|| format!("failed to load {}", self.key_path.display()))?;
+        if keys.is_empty() {
+            anyhow::bail!("no PKCS#8 key found in {}", self.key_path.display());
+        }
+        certified_key(certs, keys.remove(0))
+    }
+}
+
+impl ClientAuthConfig {
+    // Client certificates are verified if presented, but not demanded in the
+    // handshake: ACME providers validating a challenge have none, so clients
+    // without one are turned away once the handshake is done instead.
+    fn verifier(&self) -> anyhow::Result<Arc<dyn ClientCertVerifier>> {
+        let mut roots = rustls::RootCertStore::empty();
+        let certs = load_certs(&self.ca_path)
+            .with_context(|| format!("failed to load {}", self.ca_path.display()))?;
+        for cert in &certs {
+            roots.add(cert)?;
+        }
+        Ok(Arc::new(AllowAnyAnonymousOrAuthenticatedClient::new(roots)))
+    }
+}
+
+/// The TLS setup shared by the server's listeners.
+pub(crate) struct TlsServer {
+    pub resolver: Arc<CertResolver>,
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+    // Whether connections without a verified client certificate are refused
+    client_cert_required: bool,
+    // Whether ACME challenges are answered by the listener
+    pub acme: bool,
+}
+
+impl TlsServer {
+    // Creates a rustls server config offering the given ALPN protocols.
+    pub fn server_config(&self, alpn_protocols: &[&[u8]]) -> rustls::ServerConfig {
+        let builder = rustls::ServerConfig::builder().with_safe_defaults();
+        let builder = match &self.client_verifier {
+            Some(verifier) => builder.with_client_cert_verifier(verifier.clone()),
+            None => builder.with_no_client_auth(),
+        };
+        let mut cfg = builder.with_cert_resolver(self.resolver.clone());
+        cfg.alpn_protocols = alpn_protocols.iter().map(|p| p.to_vec()).collect();
+        cfg
+    }
 
-        let cfg = rustls::ServerConfig::builder()
-            .with_safe_defaults()
-            .with_no_client_auth()
-            .with_single_cert(certs, keys.remove(0))
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    // Returns the identity of a client from its verified certificates, or an
+    // error if it must present a certificate and has none.
+    pub fn client_identity(
+        &self,
+        certs: Option<&[rustls::Certificate]>,
+    ) -> anyhow::Result<Option<ClientIdentity>> {
+        let identity = ClientIdentity::from_peer_certificates(certs)?;
+        if identity.is_none() && self.client_cert_required {
+            anyhow::bail!("no client certificate presented");
+        }
+        Ok(identity)
+    }
+}
+
+/// Selects the certificate for each TLS handshake by the hostname the client
+/// asked for (SNI).
+#[derive(Default)]
+pub(crate) struct CertResolver {
+    default: Option<Arc<CertifiedKey>>,
+    // Lowercase hostname -> certificate; replaced as ACME certificates renew
+    certs: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+    // Lowercase hostname -> certificate answering an ACME TLS-ALPN-01 challenge
+    challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl CertResolver {
+    pub fn insert(&self, hostname: &str, key: CertifiedKey) {
+        let hostname = hostname.to_ascii_lowercase();
+        self.certs.write().unwrap().insert(hostname, Arc::new(key));
+    }
 
-        Ok(Arc::new(cfg).into())
+    pub fn contains(&self, hostname: &str) -> bool {
+        let hostname = hostname.to_ascii_lowercase();
+        self.certs.read().unwrap().contains_key(&hostname)
+    }
+
+    pub fn set_challenge(&self, hostname: &str, key: Option<CertifiedKey>) {
+        let hostname = hostname.to_ascii_lowercase();
+        let mut challenges = self.challenges.write().unwrap();
+        match key {
+            Some(key) => challenges.insert(hostname, Arc::new(key)),
+            None => challenges.remove(&hostname),
+        };
+    }
+
+    fn lookup(&self, hostname: Option<&str>, is_acme_challenge: bool) -> Option<Arc<CertifiedKey>> {
+        let hostname = hostname.map(str::to_ascii_lowercase);
+        if is_acme_challenge {
+            return self.challenges.read().unwrap().get(&hostname?).cloned();
+        }
+        if let Some(hostname) = hostname {
+            let certs = self.certs.read().unwrap();
+            let wildcard = hostname
+                .split_once('.')
+                .map(|(_, parent)| format!("*.{parent}"));
+            let key = certs
+                .get(&hostname)
+                .or_else(|| certs.get(wildcard.as_deref()?));
+            if let Some(key) = key {
+                return Some(key.clone());
+            }
+        }
+        self.default.clone()
     }
 }
 
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let is_acme_challenge = client_hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|p| p == ACME_TLS_ALPN));
+        self.lookup(client_hello.server_name(), is_acme_challenge)
+    }
+}
+
+pub(super) fn certified_key(
+    certs: Vec<rustls::Certificate>,
+    key: rustls::PrivateKey,
+) -> anyhow::Result<CertifiedKey> {
+    let key = rustls::sign::any_supported_type(&key)
+        .map_err(|_| anyhow::anyhow!("unsupported private key type"))?;
+    Ok(CertifiedKey::new(certs, key))
+}
+
 // Loads public certificate from file.
-fn load_certs(path: impl AsRef<Path>) -> io::Result<Vec<rustls::Certificate>> {
+pub(super) fn load_certs(path: impl AsRef<Path>) -> io::Result<Vec<rustls::Certificate>> {
     certs(&mut io::BufReader::new(fs::File::open(path)?))
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid cert"))
         .map(|mut certs| certs.drain(..).map(rustls::Certificate).collect())
 }
 
 // Loads private key from file.
-fn load_keys(path: impl AsRef<Path>) -> io::Result<Vec<rustls::PrivateKey>> {
+pub(super) fn load_keys(path: impl AsRef<Path>) -> io::Result<Vec<rustls::PrivateKey>> {
     pkcs8_private_keys(&mut io::BufReader::new(fs::File::open(path)?))
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))
         .map(|mut keys| keys.drain(..).map(rustls::PrivateKey).collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn self_signed(dir: &Path, hostname: &str) -> CertPaths {
+        let cert = rcgen::generate_simple_self_signed(vec![hostname.to_owned()]).unwrap();
+        let paths = CertPaths {
+            cert_path: dir.join(format!("{hostname}.crt")),
+            key_path: dir.join(format!("{hostname}.key")),
+        };
+        fs::write(&paths.cert_path, cert.serialize_pem().unwrap()).unwrap();
+        fs::write(&paths.key_path, cert.serialize_private_key_pem()).unwrap();
+        paths
+    }
+
+    #[test]
+    fn selects_certificates_by_hostname() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = TlsConfig {
+            default_cert: Some(self_signed(dir.path(), "default.test")),
+            sni_certs: vec![
+                ("app.test".into(), self_signed(dir.path(), "app.test")),
+                ("*.apps.test".into(), self_signed(dir.path(), "apps.test")),
+            ],
+            acme: None,
+            client_auth: None,
+        };
+        let resolver = config.cert_resolver().unwrap();
+        let default = resolver.lookup(None, false).unwrap();
+        let app = resolver.lookup(Some("APP.test"), false).unwrap();
+        let wildcard = resolver.lookup(Some("one.apps.test"), false).unwrap();
+        assert!(!Arc::ptr_eq(&app, &default));
+        assert!(!Arc::ptr_eq(&wildcard, &default));
+        assert!(Arc::ptr_eq(
+            &resolver.lookup(Some("other.test"), false).unwrap(),
+            &default
+        ));
+        // Challenges are only answered with challenge certificates
+        assert!(resolver.lookup(Some("app.test"), true).is_none());
+    }
+}
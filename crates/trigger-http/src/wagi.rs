@@ -1,4 +1,4 @@
-use std::{io::Cursor, net::SocketAddr};
+use std::{io::Cursor, net::SocketAddr, time::Duration};
 
 use anyhow::{anyhow, ensure, Context, Result};
 use async_trait::async_trait;
@@ -14,6 +14,7 @@ use crate::{Body, HttpExecutor, HttpTrigger};
 #[derive(Clone)]
 pub struct WagiHttpExecutor {
     pub wagi_config: WagiTriggerConfig,
+    pub execution_timeout: Option<Duration>,
 }
 
 #[async_trait]
@@ -79,6 +80,10 @@ impl HttpExecutor for WagiHttpExecutor {
         {
             headers.insert(keys[1].to_string(), val);
         }
+        for (name, val) in crate::compute_path_params(&parts.uri, raw_route, base) {
+            let name = name.replace('-', "_").to_ascii_uppercase();
+            headers.insert(format!("X_PATH_MATCH_{name}"), val);
+        }
 
         let stdout = WritePipe::new_in_memory();
 
@@ -88,6 +93,9 @@ impl HttpExecutor for WagiHttpExecutor {
         store_builder.env(headers)?;
         store_builder.stdin_pipe(Cursor::new(body));
         store_builder.stdout(Box::new(stdout.clone()))?;
+        if let Some(timeout) = self.execution_timeout {
+            store_builder.execution_time_limit(timeout);
+        }
 
         let (instance, mut store) = engine
             .prepare_instance_with_store(component, store_builder)
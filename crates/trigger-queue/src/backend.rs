@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// A message received from a queue.
+#[derive(Clone, Debug)]
+pub struct Message {
+    /// Backend-assigned message ID
+    pub id: String,
+    /// Handle by which this receipt of the message is settled, which may
+    /// differ from its ID
+    pub receipt: String,
+    /// Message content
+    pub body: Vec<u8>,
+    /// Number of times this message has been received, including this time
+    pub receive_count: u32,
+}
+
+/// A message queue from which the queue trigger receives messages.
+///
+/// Received messages are invisible to other receivers until their visibility
+/// timeout expires, after which they may be received again. A message is
+/// removed from the queue only once it is acknowledged.
+#[async_trait]
+pub trait QueueBackend: Send + Sync {
+    /// Receives up to `max_messages` messages, which will be invisible for
+    /// `visibility_timeout`. Returns an empty batch if no messages are
+    /// available.
+    async fn receive(
+        &self,
+        max_messages: usize,
+        visibility_timeout: Duration,
+    ) -> Result<Vec<Message>>;
+
+    /// Removes a received message from the queue.
+    async fn ack(&self, message: &Message) -> Result<()>;
+
+    /// Keeps a received message invisible for `visibility_timeout` from now.
+    async fn extend_visibility(
+        &self,
+        message: &Message,
+        visibility_timeout: Duration,
+    ) -> Result<()>;
+
+    /// Makes a received message immediately available to be received again.
+    async fn release(&self, message: &Message) -> Result<()>;
+
+    /// Moves a received message to the given dead-letter queue.
+    async fn dead_letter(&self, message: &Message, dead_letter_queue: &str) -> Result<()>;
+}
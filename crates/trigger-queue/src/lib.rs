@@ -0,0 +1,365 @@
+//! Implementation for the Spin message queue trigger.
+
+mod backend;
+mod redis_queue;
+mod sqs_queue;
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use futures::future::{join_all, try_join_all};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use spin_core::async_trait;
+use spin_outbound_networking::dns::Resolver;
+use spin_trigger::{cli::NoArgs, EitherInstance, TriggerAppEngine, TriggerExecutor};
+
+pub use crate::backend::{Message, QueueBackend};
+pub use crate::redis_queue::RedisQueue;
+pub use crate::sqs_queue::SqsQueue;
+
+pub(crate) type RuntimeData = ();
+
+const QUEUE_TRIGGER_EXPORT: &str = "fermyon:spin/queue-trigger@2.1.0";
+
+// Don't extend message visibility more often than this
+const MIN_EXTEND_PERIOD: Duration = Duration::from_millis(100);
+
+// After a failure to receive, wait this long before reconnecting and
+// receiving again, doubling the wait after each consecutive failure up to
+// the maximum
+const INITIAL_RECEIVE_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RECEIVE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The Spin message queue trigger.
+pub struct QueueTrigger {
+    engine: TriggerAppEngine<Self>,
+    trigger_configs: Vec<QueueTriggerConfig>,
+}
+
+/// Queue trigger configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueueTriggerConfig {
+    /// Component ID to invoke
+    pub component: String,
+    /// Queue backend type
+    pub backend: QueueBackendType,
+    /// Backend address: for Redis, the server URL, e.g.
+    /// `redis://localhost:6379`; for SQS, the queue's AWS region or an
+    /// endpoint URL
+    pub address: String,
+    /// Queue to receive messages from: for SQS, the queue URL
+    pub queue: String,
+    /// Maximum number of messages to receive, and handle concurrently, at once
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// How long a received message is hidden from other receivers. This is
+    /// extended while the component is handling the message.
+    #[serde(default = "default_visibility_timeout_secs")]
+    pub visibility_timeout_secs: u64,
+    /// How long to wait before polling again when the queue is empty
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Queue to move messages to once they have been received this many
+    /// times without being handled successfully
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<DeadLetterConfig>,
+    /// Trigger executor (currently unused)
+    #[serde(default, skip_serializing)]
+    pub executor: IgnoredAny,
+}
+
+/// Dead-letter configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeadLetterConfig {
+    /// Queue to move messages to, named as for the trigger's `queue`
+    pub queue: String,
+    /// Number of receives after which a failing message is dead-lettered
+    pub max_receive_count: u32,
+}
+
+/// Supported queue backends.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueBackendType {
+    /// A Redis list; see [`RedisQueue`].
+    Redis,
+    /// An Amazon SQS queue; see [`SqsQueue`].
+    Sqs,
+}
+
+fn default_batch_size() -> usize {
+    1
+}
+
+fn default_visibility_timeout_secs() -> u64 {
+    30
+}
+
+fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+#[async_trait]
+impl TriggerExecutor for QueueTrigger {
+    const TRIGGER_TYPE: &'static str = "queue";
+    type RuntimeData = RuntimeData;
+    type TriggerConfig = QueueTriggerConfig;
+    type RunConfig = NoArgs;
+
+    async fn new(engine: TriggerAppEngine<Self>) -> Result<Self> {
+        let trigger_configs = engine
+            .trigger_configs()
+            .map(|(_, config)| config.clone())
+            .collect();
+        Ok(Self {
+            engine,
+            trigger_configs,
+        })
+    }
+
+    /// Run the queue trigger until it is shut down.
+    async fn run(self, _config: Self::RunConfig) -> Result<()> {
+        let self_ = Arc::new(self);
+        let receivers = (0..self_.trigger_configs.len()).map(|idx| {
+            let self_ = self_.clone();
+            let receiver = tokio::spawn(async move { self_.receive_loop(idx).await });
+            async move { receiver.await? }
+        });
+        try_join_all(receivers).await?;
+        Ok(())
+    }
+}
+
+impl QueueTrigger {
+    async fn connect(
+        config: &QueueTriggerConfig,
+        resolver: &Resolver,
+    ) -> Result<Box<dyn QueueBackend>> {
+        tracing::info!(
+            "Connecting to {:?} queue {:?} at {}",
+            config.backend,
+            config.queue,
+            config.address
+        );
+        match config.backend {
+            QueueBackendType::Redis => Ok(Box::new(
+                RedisQueue::connect(&config.address, &config.queue, resolver).await?,
+            )),
+            QueueBackendType::Sqs => Ok(Box::new(
+                SqsQueue::connect(&config.address, &config.queue).await?,
+            )),
+        }
+    }
+
+    // Receives and handles batches of messages for the given trigger.
+    async fn receive_loop(&self, idx: usize) -> Result<()> {
+        let config = &self.trigger_configs[idx];
+        let mut backend = Self::connect(config, self.engine.resolver()).await?;
+        let visibility_timeout = Duration::from_secs(config.visibility_timeout_secs);
+        let shutdown_signal = self.engine.shutdown_signal();
+        let mut failures = 0;
+        // Each batch is handled before receiving the next, so once shut down
+        // there are no messages in flight
+        while !shutdown_signal.is_triggered() {
+            let messages = match backend
+                .receive(config.batch_size.max(1), visibility_timeout)
+                .await
+            {
+                Ok(messages) => {
+                    failures = 0;
+                    messages
+                }
+                Err(err) => {
+                    failures += 1;
+                    let backoff = receive_backoff(failures);
+                    tracing::error!(
+                        "Failed to receive from queue {:?}, retrying in {backoff:?}: {err:#}",
+                        config.queue
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff) => {}
+                        _ = shutdown_signal.triggered() => break,
+                    }
+                    // The connection may have been lost
+                    match Self::connect(config, self.engine.resolver()).await {
+                        Ok(reconnected) => backend = reconnected,
+                        Err(err) => tracing::error!(
+                            "Failed to reconnect to queue {:?}: {err:#}",
+                            config.queue
+                        ),
+                    }
+                    continue;
+                }
+            };
+            if messages.is_empty() {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(config.poll_interval_ms)) => {}
+                    _ = shutdown_signal.triggered() => break,
+                }
+                continue;
+            }
+            let handlers = messages
+                .iter()
+                .map(|message| self.handle(config, &*backend, message));
+            join_all(handlers).await;
+        }
+        tracing::info!("Stopped receiving from queue {:?}", config.queue);
+        Ok(())
+    }
+
+    // Handles a message, extending its visibility until the component returns,
+    // then acknowledges, releases or dead-letters it.
+    async fn handle(
+        &self,
+        config: &QueueTriggerConfig,
+        backend: &dyn QueueBackend,
+        message: &Message,
+    ) {
+        let visibility_timeout = Duration::from_secs(config.visibility_timeout_secs);
+        let execute = self.execute(&config.component, message);
+        tokio::pin!(execute);
+        let extend_period = (visibility_timeout / 2).max(MIN_EXTEND_PERIOD);
+        let mut extend_interval = tokio::time::interval(extend_period);
+        extend_interval.tick().await; // The first tick completes immediately
+        let result = loop {
+            tokio::select! {
+                result = &mut execute => break result,
+                _ = extend_interval.tick() => {
+                    if let Err(err) = backend.extend_visibility(message, visibility_timeout).await {
+                        tracing::warn!("Failed to extend visibility of message {:?}: {err:#}", message.id);
+                    }
+                }
+            }
+        };
+
+        let settled = match result {
+            Ok(()) => backend.ack(message).await,
+            Err(err) => {
+                tracing::error!(
+                    "Error from component {:?} handling message {:?}: {err:?}",
+                    config.component,
+                    message.id
+                );
+                match &config.dead_letter {
+                    Some(dead_letter) if message.receive_count >= dead_letter.max_receive_count => {
+                        tracing::warn!(
+                            "Moving message {:?} to dead-letter queue {:?} after {} receives",
+                            message.id,
+                            dead_letter.queue,
+                            message.receive_count
+                        );
+                        backend.dead_letter(message, &dead_letter.queue).await
+                    }
+                    _ => backend.release(message).await,
+                }
+            }
+        };
+        if let Err(err) = settled {
+            tracing::error!("Failed to settle message {:?}: {err:#}", message.id);
+        }
+    }
+
+    #[tracing::instrument(
+        name = "spin_trigger_queue.handle_message",
+        skip_all,
+        fields(
+            otel.kind = "consumer",
+            spin.component_id = component_id,
+            spin.request_id = message.id.as_str(),
+            messaging.message.id = message.id.as_str(),
+        )
+    )]
+    async fn execute(&self, component_id: &str, message: &Message) -> Result<()> {
+        tracing::trace!(
+            "Executing component {component_id:?} for message {:?}",
+            message.id
+        );
+        let (instance, mut store) = self.engine.prepare_instance(component_id).await?;
+        let EitherInstance::Component(instance) = instance else {
+            unreachable!()
+        };
+
+        let func = instance
+            .exports(&mut store)
+            .instance(QUEUE_TRIGGER_EXPORT)
+            .ok_or_else(|| anyhow!("no {QUEUE_TRIGGER_EXPORT} instance found"))?
+            .typed_func::<(&str, &[u8], u32), (Result<(), String>,)>("handle-message")?;
+
+        let (result,) = func
+            .call_async(
+                store,
+                (
+                    message.id.as_str(),
+                    message.body.as_slice(),
+                    message.receive_count,
+                ),
+            )
+            .await?;
+        result.map_err(|err| anyhow!("`handle-message` returned an error: {err}"))
+    }
+}
+
+/// Returns how long to wait after the given number of consecutive failures
+/// to receive.
+fn receive_backoff(failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+    INITIAL_RECEIVE_BACKOFF
+        .saturating_mul(factor)
+        .min(MAX_RECEIVE_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_defaults() {
+        let config: QueueTriggerConfig = serde_json::from_value(serde_json::json!({
+            "component": "worker",
+            "backend": "redis",
+            "address": "redis://localhost:6379",
+            "queue": "orders",
+        }))
+        .unwrap();
+        assert_eq!(config.batch_size, 1);
+        assert_eq!(config.visibility_timeout_secs, 30);
+        assert!(config.dead_letter.is_none());
+    }
+
+    #[test]
+    fn config_dead_letter() {
+        let config: QueueTriggerConfig = serde_json::from_value(serde_json::json!({
+            "component": "worker",
+            "backend": "redis",
+            "address": "redis://localhost:6379",
+            "queue": "orders",
+            "dead_letter": { "queue": "orders-dlq", "max_receive_count": 5 },
+        }))
+        .unwrap();
+        let dead_letter = config.dead_letter.unwrap();
+        assert_eq!(dead_letter.queue, "orders-dlq");
+        assert_eq!(dead_letter.max_receive_count, 5);
+    }
+
+    #[test]
+    fn receive_backoff_doubles_up_to_max() {
+        assert_eq!(receive_backoff(1), INITIAL_RECEIVE_BACKOFF);
+        assert_eq!(receive_backoff(2), INITIAL_RECEIVE_BACKOFF * 2);
+        assert_eq!(receive_backoff(3), INITIAL_RECEIVE_BACKOFF * 4);
+        assert_eq!(receive_backoff(100), MAX_RECEIVE_BACKOFF);
+    }
+
+    #[test]
+    fn config_sqs() {
+        let config: QueueTriggerConfig = serde_json::from_value(serde_json::json!({
+            "component": "worker",
+            "backend": "sqs",
+            "address": "us-east-1",
+            "queue": "https://sqs.us-east-1.amazonaws.com/123456789012/orders",
+        }))
+        .unwrap();
+        assert!(matches!(config.backend, QueueBackendType::Sqs));
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use redis::{aio::MultiplexedConnection, IntoConnectionInfo, Script};
+use spin_outbound_networking::dns::Resolver;
+
+use crate::backend::{Message, QueueBackend};
+
+// Moves expired in-flight messages to the retry list, then receives up to
+// ARGV[3] messages, retries first. New messages are assigned an ID and their
+// body is stored until they are acknowledged.
+const RECEIVE_SCRIPT: &str = r"
+local expired = redis.call('ZRANGEBYSCORE', KEYS[3], '-inf', ARGV[1])
+for _, id in ipairs(expired) do
+  redis.call('ZREM', KEYS[3], id)
+  redis.call('LPUSH', KEYS[2], id)
+end
+local received = {}
+while #received < tonumber(ARGV[3]) do
+  local id = redis.call('RPOP', KEYS[2])
+  local body
+  if id then
+    body = redis.call('HGET', KEYS[4], id)
+  else
+    body = redis.call('RPOP', KEYS[1])
+    if not body then break end
+    id = tostring(redis.call('INCR', KEYS[6]))
+    redis.call('HSET', KEYS[4], id, body)
+  end
+  -- A retried message may have been acknowledged after it expired
+  if body then
+    local count = redis.call('HINCRBY', KEYS[5], id, 1)
+    redis.call('ZADD', KEYS[3], ARGV[2], id)
+    table.insert(received, {id, body, count})
+  end
+end
+return received
+";
+
+/// A queue backed by a Redis list.
+///
+/// Producers push messages onto the list at `queue` with `LPUSH`. Received
+/// messages are tracked using the `<queue>:*` keys until acknowledged.
+pub struct RedisQueue {
+    connection: MultiplexedConnection,
+    queue: String,
+    receive_script: Script,
+}
+
+impl RedisQueue {
+    pub async fn connect(address: &str, queue: &str, resolver: &Resolver) -> Result<Self> {
+        let connection = resolver
+            .connect_redis(address.into_connection_info()?, |client| async move {
+                client.get_multiplexed_tokio_connection().await
+            })
+            .await
+            .with_context(|| format!("Queue trigger failed to connect to {address}"))?;
+        Ok(Self {
+            connection,
+            queue: queue.to_owned(),
+            receive_script: Script::new(RECEIVE_SCRIPT),
+        })
+    }
+
+    fn key(&self, suffix: &str) -> String {
+        format!("{}:{suffix}", self.queue)
+    }
+
+    fn forget(&self, pipe: &mut redis::Pipeline, message: &Message) {
+        pipe.zrem(self.key("inflight"), &message.id)
+            .hdel(self.key("bodies"), &message.id)
+            .hdel(self.key("receives"), &message.id);
+    }
+}
+
+#[async_trait]
+impl QueueBackend for RedisQueue {
+    async fn receive(
+        &self,
+        max_messages: usize,
+        visibility_timeout: Duration,
+    ) -> Result<Vec<Message>> {
+        let now = unix_millis(Duration::ZERO)?;
+        let deadline = unix_millis(visibility_timeout)?;
+        let received: Vec<(String, Vec<u8>, u32)> = self
+            .receive_script
+            .key(&self.queue)
+            .key(self.key("retry"))
+            .key(self.key("inflight"))
+            .key(self.key("bodies"))
+            .key(self.key("receives"))
+            .key(self.key("ids"))
+            .arg(now)
+            .arg(deadline)
+            .arg(max_messages)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(received
+            .into_iter()
+            .map(|(id, body, receive_count)| Message {
+                receipt: id.clone(),
+                id,
+                body,
+                receive_count,
+            })
+            .collect())
+    }
+
+    async fn ack(&self, message: &Message) -> Result<()> {
+        let mut pipe = redis::pipe();
+        self.forget(pipe.atomic(), message);
+        pipe.query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn extend_visibility(
+        &self,
+        message: &Message,
+        visibility_timeout: Duration,
+    ) -> Result<()> {
+        redis::cmd("ZADD")
+            .arg(self.key("inflight"))
+            .arg("XX")
+            .arg(unix_millis(visibility_timeout)?)
+            .arg(&message.id)
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn release(&self, message: &Message) -> Result<()> {
+        // Expire the message; it is moved to the retry list by the next receive
+        redis::cmd("ZADD")
+            .arg(self.key("inflight"))
+            .arg("XX")
+            .arg(0)
+            .arg(&message.id)
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn dead_letter(&self, message: &Message, dead_letter_queue: &str) -> Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.atomic().lpush(dead_letter_queue, &message.body);
+        self.forget(&mut pipe, message);
+        pipe.query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+}
+
+// Returns the given offset from now, in milliseconds since the Unix epoch.
+fn unix_millis(offset: Duration) -> Result<u64> {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH)? + offset;
+    Ok(time.as_millis().try_into()?)
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_sqs::{
+    types::{MessageSystemAttributeName, QueueAttributeName},
+    Client,
+};
+
+use crate::backend::{Message, QueueBackend};
+
+// The most messages SQS returns from a single receive
+const MAX_RECEIVE_MESSAGES: usize = 10;
+
+/// An Amazon SQS queue.
+///
+/// The queue, and any dead-letter queue, are given by URL. Credentials are
+/// discovered from the environment, AWS profiles or instance metadata.
+/// Messages are settled using their receipt handles.
+pub struct SqsQueue {
+    client: Client,
+    queue_url: String,
+}
+
+impl SqsQueue {
+    /// Connects to the queue at `queue_url`. `address` is the queue's AWS
+    /// region, such as `us-east-1`, or an endpoint URL for an SQS-compatible
+    /// service, in which case the region is discovered from the environment.
+    pub async fn connect(address: &str, queue_url: &str) -> Result<Self> {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest());
+        if address.starts_with("http://") || address.starts_with("https://") {
+            loader = loader.endpoint_url(address);
+        } else if !address.is_empty() {
+            loader = loader.region(Region::new(address.to_owned()));
+        }
+        Ok(Self {
+            client: Client::new(&loader.load().await),
+            queue_url: queue_url.to_owned(),
+        })
+    }
+}
+
+#[async_trait]
+impl QueueBackend for SqsQueue {
+    async fn receive(
+        &self,
+        max_messages: usize,
+        visibility_timeout: Duration,
+    ) -> Result<Vec<Message>> {
+        let output = self
+            .client
+            .receive_message()
+            .queue_url(&self.queue_url)
+            .max_number_of_messages(max_messages.min(MAX_RECEIVE_MESSAGES) as i32)
+            .visibility_timeout(timeout_secs(visibility_timeout)?)
+            .attribute_names(QueueAttributeName::All)
+            .send()
+            .await?;
+        output
+            .messages
+            .unwrap_or_default()
+            .into_iter()
+            .map(|message| {
+                let receive_count = message
+                    .attributes()
+                    .and_then(|attrs| {
+                        attrs.get(&MessageSystemAttributeName::ApproximateReceiveCount)
+                    })
+                    .and_then(|count| count.parse().ok())
+                    .unwrap_or(1);
+                Ok(Message {
+                    id: message.message_id.context("SQS message has no ID")?,
+                    receipt: message
+                        .receipt_handle
+                        .context("SQS message has no receipt handle")?,
+                    body: message.body.unwrap_or_default().into_bytes(),
+                    receive_count,
+                })
+            })
+            .collect()
+    }
+
+    async fn ack(&self, message: &Message) -> Result<()> {
+        self.client
+            .delete_message()
+            .queue_url(&self.queue_url)
+            .receipt_handle(&message.receipt)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn extend_visibility(
+        &self,
+        message: &Message,
+        visibility_timeout: Duration,
+    ) -> Result<()> {
+        self.client
+            .change_message_visibility()
+            .queue_url(&self.queue_url)
+            .receipt_handle(&message.receipt)
+            .visibility_timeout(timeout_secs(visibility_timeout)?)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn release(&self, message: &Message) -> Result<()> {
+        self.client
+            .change_message_visibility()
+            .queue_url(&self.queue_url)
+            .receipt_handle(&message.receipt)
+            .visibility_timeout(0)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn dead_letter(&self, message: &Message, dead_letter_queue: &str) -> Result<()> {
+        // SQS message bodies are text, so this only fails for bodies which
+        // didn't come from SQS
+        let body = std::str::from_utf8(&message.body).context("SQS message body is not UTF-8")?;
+        self.client
+            .send_message()
+            .queue_url(dead_letter_queue)
+            .message_body(body)
+            .send()
+            .await?;
+        self.ack(message).await
+    }
+}
+
+fn timeout_secs(timeout: Duration) -> Result<i32> {
+    timeout
+        .as_secs()
+        .try_into()
+        .context("visibility timeout is too long")
+}
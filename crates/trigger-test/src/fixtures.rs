@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result};
+use http::Response;
+use hyper::body::Bytes;
+use serde::{Deserialize, Serialize};
+use spin_http::{body, Body};
+
+/// Canned responses to the outbound HTTP requests made by tests, loaded from
+/// a TOML file of `[[fixture]]` tables.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpFixtures {
+    #[serde(default, rename = "fixture")]
+    fixtures: Vec<HttpFixture>,
+}
+
+/// A canned response to the requests matching a method and URL.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpFixture {
+    /// Request method to match; any method if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Request URL to match. A URL ending in `*` matches every URL with that
+    /// prefix.
+    pub url: String,
+    /// Response status code
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Response body
+    #[serde(default)]
+    pub body: String,
+    // Tables must follow plain values in TOML, so this is the last field
+    /// Response headers
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl From<Vec<HttpFixture>> for HttpFixtures {
+    fn from(fixtures: Vec<HttpFixture>) -> Self {
+        Self { fixtures }
+    }
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl HttpFixtures {
+    /// Loads fixtures from a TOML file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read HTTP fixtures file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse HTTP fixtures file {}", path.display()))
+    }
+
+    /// Writes fixtures to a TOML file, which [`HttpFixtures::from_file`] can
+    /// load again.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = toml::to_string(self).context("Failed to serialize HTTP fixtures")?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write HTTP fixtures file {}", path.display()))
+    }
+
+    /// Appends the fixtures from `other`, which match only requests that
+    /// none of these fixtures match.
+    pub fn extend(&mut self, other: HttpFixtures) {
+        self.fixtures.extend(other.fixtures);
+    }
+
+    /// Returns the first fixture, in file order, matching a request.
+    pub fn find(&self, method: &str, url: &str) -> Option<&HttpFixture> {
+        self.fixtures
+            .iter()
+            .find(|fixture| fixture.matches(method, url))
+    }
+}
+
+impl HttpFixture {
+    fn matches(&self, method: &str, url: &str) -> bool {
+        let method_matches = self
+            .method
+            .as_deref()
+            .map_or(true, |m| m.eq_ignore_ascii_case(method));
+        let url_matches = match self.url.strip_suffix('*') {
+            Some(prefix) => url.starts_with(prefix),
+            None => url == self.url,
+        };
+        method_matches && url_matches
+    }
+
+    /// Returns the response to a matching request.
+    pub fn response(&self) -> Result<Response<Body>> {
+        let mut builder = Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+            .body(body::full(Bytes::from(self.body.clone())))
+            .context("invalid HTTP fixture response")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> HttpFixtures {
+        toml::from_str(
+            r#"
+            [[fixture]]
+            method = "POST"
+            url = "https://api.example.com/users"
+            status = 201
+
+            [[fixture]]
+            url = "https://api.example.com/users/*"
+            headers = { content-type = "application/json" }
+            body = '{"id": 1}'
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fixtures_match_method_and_url() {
+        let fixtures = fixtures();
+        let created = fixtures
+            .find("post", "https://api.example.com/users")
+            .unwrap();
+        assert_eq!(created.status, 201);
+        assert!(fixtures
+            .find("GET", "https://api.example.com/users")
+            .is_none());
+        assert!(fixtures.find("POST", "https://example.com/users").is_none());
+    }
+
+    #[test]
+    fn url_wildcards_match_prefixes() -> Result<()> {
+        let fixtures = fixtures();
+        let user = fixtures
+            .find("GET", "https://api.example.com/users/1")
+            .unwrap();
+        let response = user.response()?;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        Ok(())
+    }
+
+    #[test]
+    fn saved_fixtures_load_again() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cassette.toml");
+        fixtures().save(&path)?;
+
+        let loaded = HttpFixtures::from_file(&path)?;
+        let user = loaded
+            .find("GET", "https://api.example.com/users/1")
+            .unwrap();
+        assert_eq!(user.body, r#"{"id": 1}"#);
+        assert_eq!(user.headers["content-type"], "application/json");
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+//! Implementation for the Spin test trigger, which runs the tests exported by
+//! an app's test components for `spin test`.
+
+mod fixtures;
+mod recorder;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use http_body_util::BodyExt;
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use spin_core::{async_trait, Data, OutboundWasiHttpHandler, Store, WasiVersion};
+use spin_trigger::{EitherInstance, TriggerAppEngine, TriggerExecutor};
+use wasmtime_wasi_http::{
+    types::{HostFutureIncomingResponse, IncomingResponseInternal, OutgoingRequest},
+    WasiHttpView,
+};
+
+pub use crate::{
+    fixtures::{HttpFixture, HttpFixtures},
+    recorder::HttpRecorder,
+};
+
+const TEST_EXPORT: &str = "fermyon:spin/test@2.1.0";
+
+/// The Spin test trigger.
+pub struct TestTrigger {
+    engine: TriggerAppEngine<Self>,
+    component_ids: Vec<String>,
+}
+
+/// Test trigger configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestTriggerConfig {
+    /// Component ID of the test component
+    pub component: String,
+    /// Trigger executor (currently unused)
+    #[serde(default, skip_serializing)]
+    pub executor: IgnoredAny,
+}
+
+/// Options for a test run.
+#[derive(Debug, Default)]
+pub struct TestRunConfig {
+    /// If set, only tests whose full names contain this are run
+    pub filter: Option<String>,
+    /// Responses to the outbound HTTP requests made by tests
+    pub http_fixtures: HttpFixtures,
+    /// If set, requests matching no fixture are sent to the network, and
+    /// recorded here
+    pub http_recorder: Option<Arc<HttpRecorder>>,
+}
+
+/// Store data for a running test.
+#[derive(Default)]
+pub struct TestRuntimeData {
+    http_fixtures: Arc<HttpFixtures>,
+    http_recorder: Option<Arc<HttpRecorder>>,
+}
+
+impl OutboundWasiHttpHandler for TestRuntimeData {
+    // Tests reach the network only when recording: requests are otherwise
+    // answered from fixtures, and trap if no fixture matches.
+    fn send_request(
+        data: &mut Data<Self>,
+        request: OutgoingRequest,
+    ) -> wasmtime::Result<wasmtime::component::Resource<HostFutureIncomingResponse>>
+    where
+        Self: Sized,
+    {
+        let scheme = if request.use_tls { "https" } else { "http" };
+        let path_and_query = request
+            .request
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let url = format!("{scheme}://{}{path_and_query}", request.authority);
+        let method = request.request.method().clone();
+        let between_bytes_timeout = request.between_bytes_timeout;
+        let this = data.as_ref();
+        let fixture = this.http_fixtures.find(method.as_str(), &url).cloned();
+        let recorder = this.http_recorder.clone();
+
+        let handle = wasmtime_wasi::preview2::spawn(async move {
+            let fixture = match (fixture, recorder) {
+                (Some(fixture), _) => fixture,
+                (None, Some(recorder)) => {
+                    let (parts, body) = request.request.into_parts();
+                    let body = body
+                        .collect()
+                        .await
+                        .map_err(|err| anyhow!("Failed to read request body: {err:?}"))?
+                        .to_bytes();
+                    recorder.send(method, url, parts.headers, body).await?
+                }
+                (None, None) => bail!("no HTTP fixture matches request {method} {url}"),
+            };
+            Ok(IncomingResponseInternal {
+                resp: fixture.response()?,
+                worker: Arc::new(wasmtime_wasi::preview2::spawn(async { Ok(()) })),
+                between_bytes_timeout,
+            })
+        });
+        Ok(data.table().push(HostFutureIncomingResponse::new(handle))?)
+    }
+}
+
+#[async_trait]
+impl TriggerExecutor for TestTrigger {
+    const TRIGGER_TYPE: &'static str = "test";
+    type RuntimeData = TestRuntimeData;
+    type TriggerConfig = TestTriggerConfig;
+    type RunConfig = TestRunConfig;
+
+    async fn new(engine: TriggerAppEngine<Self>) -> Result<Self> {
+        let component_ids = engine
+            .trigger_configs()
+            .map(|(_, config)| config.component.clone())
+            .collect();
+        Ok(Self {
+            engine,
+            component_ids,
+        })
+    }
+
+    /// Run the app's tests, printing a report in the style of `cargo test`.
+    /// Returns an error if any test fails.
+    async fn run(self, config: Self::RunConfig) -> Result<()> {
+        let mut tests = vec![];
+        for component_id in &self.component_ids {
+            let names = self
+                .list_tests(component_id)
+                .await
+                .with_context(|| format!("Failed to list tests in component {component_id:?}"))?;
+            tests.extend(names.into_iter().map(|name| TestCase {
+                component_id: component_id.clone(),
+                name,
+            }));
+        }
+        let total = tests.len();
+        if let Some(filter) = &config.filter {
+            tests.retain(|test| test.full_name().contains(filter.as_str()));
+        }
+
+        let http_fixtures = Arc::new(config.http_fixtures);
+        let http_recorder = config.http_recorder;
+        let start = Instant::now();
+        println!();
+        println!("running {} {}", tests.len(), tests_noun(tests.len()));
+        let mut failures = vec![];
+        for test in &tests {
+            let outcome = self.run_test(test, &http_fixtures, &http_recorder).await;
+            if outcome.failure.is_none() {
+                println!("test {} ... ok", test.full_name());
+            } else {
+                println!("test {} ... FAILED", test.full_name());
+                failures.push((test, outcome));
+            }
+        }
+
+        let summary = Summary {
+            passed: tests.len() - failures.len(),
+            failed: failures.len(),
+            filtered_out: total - tests.len(),
+            elapsed: start.elapsed(),
+        };
+        if !failures.is_empty() {
+            println!();
+            println!("failures:");
+            for (test, outcome) in &failures {
+                outcome.print(test);
+            }
+            println!();
+            println!("failures:");
+            for (test, _) in &failures {
+                println!("    {}", test.full_name());
+            }
+        }
+        println!();
+        println!("{summary}");
+        println!();
+
+        if summary.failed > 0 {
+            bail!(
+                "{} of {} {} failed",
+                summary.failed,
+                tests.len(),
+                tests_noun(tests.len())
+            );
+        }
+        Ok(())
+    }
+}
+
+impl TestTrigger {
+    async fn instantiate(
+        &self,
+        component_id: &str,
+    ) -> Result<(spin_core::Instance, Store<TestRuntimeData>)> {
+        let store_builder = self
+            .engine
+            .store_builder(component_id, WasiVersion::Preview2)?;
+        self.instantiate_with_store(component_id, store_builder)
+            .await
+    }
+
+    async fn instantiate_with_store(
+        &self,
+        component_id: &str,
+        store_builder: spin_core::StoreBuilder,
+    ) -> Result<(spin_core::Instance, Store<TestRuntimeData>)> {
+        let (instance, store) = self
+            .engine
+            .prepare_instance_with_store(component_id, store_builder)
+            .await?;
+        let EitherInstance::Component(instance) = instance else {
+            unreachable!()
+        };
+        Ok((instance, store))
+    }
+
+    async fn list_tests(&self, component_id: &str) -> Result<Vec<String>> {
+        let (instance, mut store) = self.instantiate(component_id).await?;
+        let func = instance
+            .exports(&mut store)
+            .instance(TEST_EXPORT)
+            .ok_or_else(|| anyhow!("no {TEST_EXPORT} instance found"))?
+            .typed_func::<(), (Vec<String>,)>("list-tests")?;
+        let (names,) = func.call_async(&mut store, ()).await?;
+        Ok(names)
+    }
+
+    // Runs a test in a new instance of its component, capturing its output.
+    #[tracing::instrument(
+        name = "spin_trigger_test.run_test",
+        skip_all,
+        fields(
+            spin.component_id = test.component_id.as_str(),
+            spin.test_name = test.name.as_str()
+        )
+    )]
+    async fn run_test(
+        &self,
+        test: &TestCase,
+        http_fixtures: &Arc<HttpFixtures>,
+        http_recorder: &Option<Arc<HttpRecorder>>,
+    ) -> TestOutcome {
+        let mut outcome = TestOutcome::default();
+        let result = async {
+            let mut store_builder = self
+                .engine
+                .store_builder(&test.component_id, WasiVersion::Preview2)?;
+            outcome.stdout = Some(store_builder.stdout_buffered()?);
+            outcome.stderr = Some(store_builder.stderr_buffered()?);
+            let (instance, mut store) = self
+                .instantiate_with_store(&test.component_id, store_builder)
+                .await?;
+            store.as_mut().data_mut().as_mut().http_fixtures = http_fixtures.clone();
+            store.as_mut().data_mut().as_mut().http_recorder = http_recorder.clone();
+
+            let func = instance
+                .exports(&mut store)
+                .instance(TEST_EXPORT)
+                .ok_or_else(|| anyhow!("no {TEST_EXPORT} instance found"))?
+                .typed_func::<(String,), (Result<(), String>,)>("run-test")?;
+            let (result,) = func.call_async(&mut store, (test.name.clone(),)).await?;
+            anyhow::Ok(result)
+        }
+        .await;
+        outcome.failure = match result {
+            Ok(Ok(())) => None,
+            Ok(Err(message)) => Some(message),
+            Err(err) => Some(format!("{err:?}")),
+        };
+        outcome
+    }
+}
+
+struct TestCase {
+    component_id: String,
+    name: String,
+}
+
+impl TestCase {
+    fn full_name(&self) -> String {
+        format!("{}::{}", self.component_id, self.name)
+    }
+}
+
+#[derive(Default)]
+struct TestOutcome {
+    failure: Option<String>,
+    stdout: Option<spin_core::OutputBuffer>,
+    stderr: Option<spin_core::OutputBuffer>,
+}
+
+impl TestOutcome {
+    fn print(&self, test: &TestCase) {
+        println!();
+        println!("---- {} ----", test.full_name());
+        for (stream, buffer) in [("stdout", &self.stdout), ("stderr", &self.stderr)] {
+            let Some(contents) = buffer.as_ref().map(|b| b.contents()) else {
+                continue;
+            };
+            if !contents.is_empty() {
+                println!("---- {stream} ----");
+                print!("{}", String::from_utf8_lossy(&contents));
+                if !contents.ends_with(b"\n") {
+                    println!();
+                }
+            }
+        }
+        if let Some(failure) = &self.failure {
+            println!("{failure}");
+        }
+    }
+}
+
+struct Summary {
+    passed: usize,
+    failed: usize,
+    filtered_out: usize,
+    elapsed: Duration,
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = if self.failed == 0 { "ok" } else { "FAILED" };
+        write!(
+            f,
+            "test result: {result}. {} passed; {} failed; {} filtered out; finished in {:.2}s",
+            self.passed,
+            self.failed,
+            self.filtered_out,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+fn tests_noun(count: usize) -> &'static str {
+    if count == 1 {
+        "test"
+    } else {
+        "tests"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_reports_counts() {
+        let summary = Summary {
+            passed: 2,
+            failed: 1,
+            filtered_out: 3,
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(
+            summary.to_string(),
+            "test result: FAILED. 2 passed; 1 failed; 3 filtered out; finished in 1.50s"
+        );
+    }
+}
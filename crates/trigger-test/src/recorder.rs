@@ -0,0 +1,80 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use anyhow::{Context, Result};
+use http::{header, HeaderMap, Method};
+use hyper::body::Bytes;
+
+use crate::{HttpFixture, HttpFixtures};
+
+/// Sends the outbound HTTP requests made by tests to the network, recording
+/// each exchange so that it can be replayed as a fixture.
+#[derive(Default)]
+pub struct HttpRecorder {
+    client: reqwest::Client,
+    recorded: Mutex<Vec<HttpFixture>>,
+}
+
+impl HttpRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the recorded exchanges, in the order their responses arrived.
+    pub fn fixtures(&self) -> HttpFixtures {
+        self.recorded.lock().unwrap().clone().into()
+    }
+
+    // Sends a request, returning its response as a fixture matching just that
+    // request.
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        url: String,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<HttpFixture> {
+        let response = self
+            .client
+            .request(method.clone(), &url)
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send request {method} {url}"))?;
+
+        let status = response.status().as_u16();
+        let mut headers = BTreeMap::<String, String>::new();
+        for (name, value) in response.headers() {
+            // The replayed body is not chunked, whatever the recorded one was
+            if name == header::TRANSFER_ENCODING || name == header::CONNECTION {
+                continue;
+            }
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            headers
+                .entry(name.to_string())
+                .and_modify(|values| {
+                    values.push_str(", ");
+                    values.push_str(value);
+                })
+                .or_insert_with(|| value.to_owned());
+        }
+        let body = response
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to read response to {method} {url}"))?;
+        let body = String::from_utf8(body.to_vec())
+            .with_context(|| format!("Cannot record non-UTF-8 response to {method} {url}"))?;
+
+        let fixture = HttpFixture {
+            method: Some(method.to_string()),
+            url,
+            status,
+            body,
+            headers,
+        };
+        self.recorded.lock().unwrap().push(fixture.clone());
+        Ok(fixture)
+    }
+}
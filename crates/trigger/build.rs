@@ -0,0 +1,36 @@
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Componentized modules embed spin-componentize's adapter, so compiled
+    // components are only reusable by builds with the same spin-componentize
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let componentize = Path::new(&manifest_dir)
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.exists())
+        .and_then(|path| {
+            println!("cargo:rerun-if-changed={}", path.display());
+            std::fs::read_to_string(path).ok()
+        })
+        .and_then(|lock| locked_package_id(&lock, "spin-componentize"))
+        .unwrap_or_else(|| "unknown".to_owned());
+    println!("cargo:rustc-env=SPIN_COMPONENTIZE_ID={componentize}");
+}
+
+/// Returns the source (or, for path dependencies, the version) of the named
+/// package in the given `Cargo.lock`.
+fn locked_package_id(lock: &str, name: &str) -> Option<String> {
+    let name_line = format!("name = {name:?}");
+    let package = lock
+        .split("[[package]]")
+        .find(|package| package.lines().any(|line| line.trim() == name_line))?;
+    let field = |key: &str| {
+        package
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(key))
+            .map(|value| value.trim_matches('"').to_owned())
+    };
+    field("source = ").or_else(|| field("version = "))
+}
@@ -0,0 +1,175 @@
+//! Liveness, readiness and metrics endpoints, served on a separate admin port.
+
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    server::conn::http1,
+    service::service_fn,
+    Request, Response, StatusCode,
+};
+use tokio::net::TcpListener;
+
+use crate::{RuntimeConfig, ShutdownSignal};
+
+const LIVENESS_PATH: &str = "/healthz";
+const READINESS_PATH: &str = "/readyz";
+const METRICS_PATH: &str = "/metrics";
+
+// The Prometheus text exposition format
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+// How long to wait before checking unreachable resources again
+const RESOURCE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// A key that is never expected to exist, used to check key-value stores
+const READINESS_CHECK_KEY: &str = "__spin_readiness_check";
+
+/// Whether the trigger is ready to receive work.
+///
+/// The trigger is ready once [`Readiness::set_ready`] has been called, until
+/// it starts shutting down.
+pub(crate) struct Readiness {
+    ready: AtomicBool,
+    shutdown_signal: ShutdownSignal,
+}
+
+impl Readiness {
+    pub fn new(shutdown_signal: ShutdownSignal) -> Self {
+        Self {
+            ready: AtomicBool::new(false),
+            shutdown_signal,
+        }
+    }
+
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire) && !self.shutdown_signal.is_triggered()
+    }
+}
+
+/// Serves the liveness and readiness endpoints, and the metrics endpoint if
+/// [`spin_metrics::enable`] has been called, on `listen_addr` in the
+/// background.
+pub(crate) async fn serve(listen_addr: SocketAddr, readiness: Arc<Readiness>) -> Result<()> {
+    let listener = TcpListener::bind(listen_addr)
+        .await
+        .with_context(|| format!("Unable to listen for admin requests on {listen_addr}"))?;
+    tracing::info!("Serving admin endpoints on {listen_addr}");
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("Failed to accept admin connection: {err}");
+                    continue;
+                }
+            };
+            let readiness = readiness.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let response = handle(&request, &readiness);
+                    async move { Ok::<_, Infallible>(response) }
+                });
+                if let Err(err) = http1::Builder::new()
+                    .serve_connection(stream, service)
+                    .await
+                {
+                    tracing::debug!("Error serving admin connection: {err}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle(request: &Request<Incoming>, readiness: &Readiness) -> Response<Full<Bytes>> {
+    let (status, body) = match request.uri().path() {
+        LIVENESS_PATH => (StatusCode::OK, "ok"),
+        READINESS_PATH if readiness.is_ready() => (StatusCode::OK, "ready"),
+        READINESS_PATH => (StatusCode::SERVICE_UNAVAILABLE, "not ready"),
+        METRICS_PATH if spin_metrics::is_enabled() => {
+            let mut response = Response::new(Full::new(Bytes::from(spin_metrics::render())));
+            response.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                hyper::header::HeaderValue::from_static(METRICS_CONTENT_TYPE),
+            );
+            return response;
+        }
+        _ => (StatusCode::NOT_FOUND, "not found"),
+    };
+    let mut response = Response::new(Full::new(Bytes::from_static(body.as_bytes())));
+    *response.status_mut() = status;
+    response
+}
+
+/// Marks the trigger ready once all key-value stores and SQLite databases in
+/// `runtime_config` are reachable, checking again periodically until they are.
+pub(crate) async fn set_ready_when_reachable(
+    runtime_config: RuntimeConfig,
+    readiness: Arc<Readiness>,
+) {
+    loop {
+        match check_resources(&runtime_config).await {
+            Ok(()) => {
+                readiness.set_ready();
+                tracing::info!("All resources reachable: ready");
+                return;
+            }
+            Err(err) => tracing::warn!("Not ready: {err:#}"),
+        }
+        tokio::time::sleep(RESOURCE_CHECK_INTERVAL).await;
+    }
+}
+
+async fn check_resources(runtime_config: &RuntimeConfig) -> Result<()> {
+    for (name, manager) in runtime_config.key_value_stores()? {
+        let store = manager
+            .get(&name)
+            .await
+            .with_context(|| format!("key-value store {name:?} is unreachable"))?;
+        store
+            .exists(READINESS_CHECK_KEY)
+            .await
+            .with_context(|| format!("key-value store {name:?} is unreachable"))?;
+    }
+    for (name, connection) in runtime_config.sqlite_databases()? {
+        connection
+            .query("SELECT 1", vec![])
+            .await
+            .with_context(|| format!("SQLite database {name:?} is unreachable"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readiness_flips() {
+        let shutdown_signal = ShutdownSignal::default();
+        let readiness = Readiness::new(shutdown_signal.clone());
+        assert!(!readiness.is_ready());
+
+        readiness.set_ready();
+        assert!(readiness.is_ready());
+
+        // No longer ready once shutting down, so load balancers stop sending work
+        shutdown_signal.trigger();
+        assert!(!readiness.is_ready());
+    }
+}
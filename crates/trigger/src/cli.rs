@@ -1,28 +1,43 @@
-use std::path::PathBuf;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
-use clap::{Args, IntoApp, Parser};
+use clap::{ArgEnum, Args, IntoApp, Parser};
 use serde::de::DeserializeOwned;
 use spin_app::Loader;
-use spin_common::{arg_parser::parse_kv, sloth};
+use spin_common::{arg_parser::parse_kv, sloth, ui::quoted_path};
 
+use crate::admin::{self, Readiness};
+use crate::debugger::{self, DebugAdapter};
+use crate::limits::ResourceLimits;
+use crate::metrics::{InvocationMetrics, InvocationUsageLog};
 use crate::network::Network;
+use crate::profiling::GuestProfiling;
 use crate::runtime_config::llm::LLmOptions;
 use crate::runtime_config::sqlite::SqlitePersistenceMessageHook;
 use crate::stdio::StdioLoggingTriggerHooks;
 use crate::{
-    loader::TriggerLoader,
+    loader::{AdmissionPolicy, SignatureVerifier, TriggerLoader},
     runtime_config::{key_value::KeyValuePersistenceMessageHook, RuntimeConfig},
-    stdio::FollowComponents,
+    stdio::{FollowComponents, LogRotation},
 };
-use crate::{TriggerExecutor, TriggerExecutorBuilder};
+use crate::{CrashReporter, ShutdownSignal, TriggerExecutor, TriggerExecutorBuilder};
 
 pub const APP_LOG_DIR: &str = "APP_LOG_DIR";
 pub const DISABLE_WASMTIME_CACHE: &str = "DISABLE_WASMTIME_CACHE";
 pub const FOLLOW_LOG_OPT: &str = "FOLLOW_ID";
+pub const TRUSTED_KEY_OPT: &str = "TRUSTED_KEY";
 pub const WASMTIME_CACHE_FILE: &str = "WASMTIME_CACHE_FILE";
 pub const RUNTIME_CONFIG_FILE: &str = "RUNTIME_CONFIG_FILE";
 
+// Signals closer together than this are treated as one
+const DUPLICATE_SIGNAL_WINDOW: Duration = Duration::from_secs(1);
+
 // Set by `spin up`
 pub const SPIN_LOCKED_URL: &str = "SPIN_LOCKED_URL";
 pub const SPIN_LOCAL_APP_DIR: &str = "SPIN_LOCAL_APP_DIR";
@@ -48,7 +63,21 @@ where
     )]
     pub log: Option<PathBuf>,
 
-    /// Disable Wasmtime cache.
+    /// Rotate a component's stdout or stderr log file once it reaches this
+    /// many bytes. By default log files grow without limit.
+    #[clap(long = "log-max-size", env = "SPIN_LOG_MAX_SIZE")]
+    pub log_max_size: Option<u64>,
+
+    /// With `--log-max-size`, the number of rotated log files to keep for
+    /// each component's stdout and stderr, in addition to the current one.
+    #[clap(
+        long = "log-max-files",
+        env = "SPIN_LOG_MAX_FILES",
+        default_value = "5"
+    )]
+    pub log_max_files: usize,
+
+    /// Disable Wasmtime cache and the compiled component cache.
     #[clap(
         name = DISABLE_WASMTIME_CACHE,
         long = "disable-cache",
@@ -92,6 +121,102 @@ where
     #[clap(long = "allow-transient-write")]
     pub allow_transient_write: bool,
 
+    /// Memory-map component sources rather than reading them into memory.
+    /// This reduces peak memory use when loading very large components.
+    #[clap(long = "low-memory-load")]
+    pub low_memory_load: bool,
+
+    /// Maximum number of components to load concurrently at startup.
+    /// Defaults to the number of available CPUs.
+    #[clap(long = "load-parallelism")]
+    pub load_parallelism: Option<usize>,
+
+    /// Reload components when their source files change, without restarting.
+    #[clap(long = "hot-reload")]
+    pub hot_reload: bool,
+
+    /// Serve liveness (`/healthz`) and readiness (`/readyz`) endpoints on
+    /// this address. The app is ready once all components are loaded and all
+    /// key-value stores and SQLite databases are reachable.
+    #[clap(long = "admin-listen", env = "SPIN_ADMIN_LISTEN")]
+    pub admin_listen: Option<SocketAddr>,
+
+    /// Record per-component invocation counts, latencies, fuel and memory
+    /// use, and outbound calls, and serve them in Prometheus format at
+    /// `/metrics` on the admin address. Enables fuel metering, which slows
+    /// down Wasm execution.
+    #[clap(long = "metrics", requires = "admin_listen")]
+    pub metrics: bool,
+
+    /// Sample the guest call stacks of the given component(s), writing a
+    /// profile of each invocation in collapsed stack format, for flamegraph
+    /// tools such as `inferno-flamegraph`, to `profiles/` in the state
+    /// directory. Can be used multiple times.
+    #[clap(long = "profile-component", multiple_occurrences = true)]
+    pub profile_components: Vec<String>,
+
+    /// When a component traps, write a core dump of it, in `wasm-coredump`
+    /// format, and the context of the trapped invocation to `crashes/` in
+    /// the state directory, for post-mortem debugging with tools such as
+    /// `wasmgdb`.
+    #[clap(long = "capture-coredumps")]
+    pub capture_coredumps: bool,
+
+    /// Compile components with debug info and without optimizations, so that
+    /// a debugger attached to the Spin process can set breakpoints in guest
+    /// source. Spin writes a launch configuration attaching `lldb-dap`, LLDB's
+    /// Debug Adapter Protocol server, to `debug/launch.json` in the state
+    /// directory, for VS Code's `.vscode/launch.json` or another DAP client.
+    /// Native debuggers can also attach with `gdb -p <pid>`, or with
+    /// `lldb -p <pid>` after `settings set plugin.jit-loader.gdb.enable on`.
+    /// Consider raising or removing execution timeouts while debugging.
+    #[clap(long = "debug")]
+    pub debug: bool,
+
+    /// With --debug, start `lldb-dap` listening for a Debug Adapter Protocol
+    /// client on this port, and point the launch configuration at it. Set
+    /// SPIN_LLDB_DAP to use an `lldb-dap` which isn't on the path.
+    #[clap(long = "debug-adapter-port", requires = "debug")]
+    pub debug_adapter_port: Option<u16>,
+
+    /// On SIGINT or SIGTERM, stop accepting new requests and wait up to this
+    /// many seconds for in-flight requests to complete before exiting. A
+    /// second signal exits immediately.
+    #[clap(long = "drain-timeout", default_value = "30")]
+    pub drain_timeout: u64,
+
+    /// The format of runtime logs on stderr. `json` writes one JSON object per
+    /// line, with the level, trigger type, and component and request ids, and
+    /// also applies to followed component output.
+    #[clap(
+        long = "log-format",
+        arg_enum,
+        default_value = "text",
+        env = "SPIN_LOG_FORMAT"
+    )]
+    pub log_format: LogFormat,
+
+    /// Verify signatures of the application and its components against this
+    /// Ed25519 public key (base64-encoded). Signatures are read from `.sig`
+    /// files alongside the signed content. Can be used multiple times.
+    #[clap(
+        name = TRUSTED_KEY_OPT,
+        long = "trusted-key",
+        multiple_occurrences = true,
+    )]
+    pub trusted_keys: Vec<PathBuf>,
+
+    /// Refuse to run unless the application and all its components have
+    /// signatures valid for a trusted key.
+    #[clap(long = "require-signed", requires = TRUSTED_KEY_OPT)]
+    pub require_signed: bool,
+
+    /// Refuse to run the application unless it satisfies the rules in this
+    /// admission policy file, such as requiring signed components or
+    /// capping memory limits.
+    #[clap(long = "admission-policy", env = "SPIN_ADMISSION_POLICY")]
+    pub admission_policy: Option<PathBuf>,
+
     /// Configuration file for config providers and wasmtime config.
     #[clap(
         name = RUNTIME_CONFIG_FILE,
@@ -127,6 +252,15 @@ where
     pub help_args_only: bool,
 }
 
+/// The format of runtime logs.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable text
+    Text,
+    /// JSON lines, for log aggregators
+    Json,
+}
+
 /// An empty implementation of clap::Args to be used as TriggerExecutor::RunConfig
 /// for executors that do not need additional CLI args.
 #[derive(Args)]
@@ -147,58 +281,179 @@ where
             return Ok(());
         }
 
+        let (setup, runtime_config) = self.prepare(Executor::TRIGGER_TYPE).await?;
+        let loader = self.loader(&setup.working_dir, &runtime_config)?;
+        let executor = self
+            .build_executor::<Executor>(&setup, loader, &runtime_config, self.init_data(), None)
+            .await?;
+        setup
+            .run_until_shutdown(executor.run(self.run_config))
+            .await
+    }
+
+    // Sets up what all the executors run by the process share, returning
+    // the runtime config for the executors to be built with.
+    async fn prepare(&self, trigger_type: &str) -> Result<(TriggerSetup, RuntimeConfig)> {
+        if self.log_format == LogFormat::Json {
+            spin_telemetry::enable_json_logs(trigger_type)?;
+        }
+
         // Required env vars
         let working_dir = std::env::var(SPIN_WORKING_DIR).context(SPIN_WORKING_DIR)?;
         let locked_url = std::env::var(SPIN_LOCKED_URL).context(SPIN_LOCKED_URL)?;
 
-        let init_data = crate::HostComponentInitData::new(
+        if self.metrics {
+            spin_metrics::enable();
+        }
+        let shutdown_signal = ShutdownSignal::default();
+        // Serve liveness while components load
+        let readiness = match self.admin_listen {
+            Some(admin_listen) => {
+                let readiness = Arc::new(Readiness::new(shutdown_signal.clone()));
+                admin::serve(admin_listen, readiness.clone()).await?;
+                Some((readiness, self.build_runtime_config()?))
+            }
+            None => None,
+        };
+        let runtime_config = self.build_runtime_config()?;
+        // Enable before loading so that spans from loading are exported too
+        if let Some(otel) = runtime_config.otel_opts() {
+            otel.enable()?;
+        }
+        let debug_adapter = if self.debug {
+            self.start_debugging(&runtime_config, &working_dir)?
+        } else {
+            None
+        };
+
+        let setup = TriggerSetup {
+            working_dir,
+            locked_url,
+            shutdown_signal,
+            readiness,
+            drain_timeout: Duration::from_secs(self.drain_timeout),
+            _debug_adapter: debug_adapter,
+        };
+        Ok((setup, runtime_config))
+    }
+
+    // Writes the launch configuration for debugging guests, and starts the
+    // debug adapter if one was asked for.
+    fn start_debugging(
+        &self,
+        runtime_config: &RuntimeConfig,
+        working_dir: &str,
+    ) -> Result<Option<DebugAdapter>> {
+        let debug_dir = runtime_config
+            .state_dir()
+            .unwrap_or_else(|| working_dir.into())
+            .join("debug");
+        let launch_config = debugger::write_launch_config(&debug_dir, self.debug_adapter_port)?;
+        terminal::einfo!(
+            "Guest debugging enabled:",
+            "attach a debugger to process {}, or with lldb-dap using {}",
+            std::process::id(),
+            quoted_path(&launch_config)
+        );
+        let Some(port) = self.debug_adapter_port else {
+            return Ok(None);
+        };
+        let adapter = DebugAdapter::start(port)?;
+        terminal::einfo!("Debug adapter listening:", "lldb-dap on port {port}");
+        Ok(Some(adapter))
+    }
+
+    fn init_data(&self) -> crate::HostComponentInitData {
+        crate::HostComponentInitData::new(
             &*self.key_values,
             &*self.sqlite_statements,
             LLmOptions { use_gpu: true },
-        );
-
-        let loader = TriggerLoader::new(working_dir, self.allow_transient_write);
-        let executor = self.build_executor(loader, locked_url, init_data).await?;
-
-        let run_fut = executor.run(self.run_config);
+        )
+    }
 
-        let (abortable, abort_handle) = futures::future::abortable(run_fut);
-        ctrlc::set_handler(move || abort_handle.abort())?;
-        match abortable.await {
-            Ok(Ok(())) => {
-                tracing::info!("Trigger executor shut down: exiting");
-                Ok(())
-            }
-            Ok(Err(err)) => {
-                tracing::error!("Trigger executor failed");
-                Err(err)
-            }
-            Err(_aborted) => {
-                tracing::info!("User requested shutdown: exiting");
-                Ok(())
+    fn loader(&self, working_dir: &str, runtime_config: &RuntimeConfig) -> Result<TriggerLoader> {
+        let mut loader = TriggerLoader::new(working_dir, self.allow_transient_write)
+            .with_low_memory_load(self.low_memory_load)
+            .with_host_component_providers(runtime_config.host_component_providers()?);
+        // Cached components were compiled without debug info
+        if !self.disable_cache && !self.debug {
+            if let Some(cache_dir) = compiled_component_cache_dir() {
+                loader = loader.with_compiled_cache(cache_dir);
             }
         }
+        let policy = self
+            .admission_policy
+            .as_deref()
+            .map(AdmissionPolicy::from_file)
+            .transpose()?;
+        if !self.trusted_keys.is_empty() {
+            let require_signed =
+                self.require_signed || policy.as_ref().is_some_and(|p| p.require_signed);
+            let verifier = SignatureVerifier::new(&self.trusted_keys, require_signed)?;
+            loader = loader.with_signature_verifier(verifier);
+        }
+        if let Some(policy) = policy {
+            loader = loader.with_admission_policy(policy);
+        }
+        Ok(loader)
     }
 
-    async fn build_executor(
+    async fn build_executor<E: TriggerExecutor>(
         &self,
+        setup: &TriggerSetup,
         loader: impl Loader + Send + Sync + 'static,
-        locked_url: String,
+        runtime_config: &RuntimeConfig,
         init_data: crate::HostComponentInitData,
-    ) -> Result<Executor> {
-        let runtime_config = self.build_runtime_config()?;
-
+        shared_engine: Option<spin_core::wasmtime::Engine>,
+    ) -> Result<E>
+    where
+        E::TriggerConfig: DeserializeOwned,
+    {
         let _sloth_guard = warn_if_wasm_build_slothful();
 
-        let mut builder = TriggerExecutorBuilder::new(loader);
-        self.update_config(builder.config_mut())?;
+        let mut builder = TriggerExecutorBuilder::<E>::new(loader);
+        self.update_config(builder.config_mut(), runtime_config)?;
+        if let Some(engine) = shared_engine {
+            builder.shared_engine(engine);
+        }
+        if let Some(load_parallelism) = self.load_parallelism {
+            builder.load_parallelism(load_parallelism);
+        }
+        if self.hot_reload {
+            builder.hot_reload();
+        }
+        builder.shutdown_signal(setup.shutdown_signal.clone());
+        if self.capture_coredumps {
+            let state_dir = runtime_config.state_dir().context(
+                "--capture-coredumps requires an application state directory; set one with --state-dir",
+            )?;
+            let crash_reporter = CrashReporter::new(state_dir.join("crashes"))?;
+            println!(
+                "Writing core dumps of trapped components to {}",
+                quoted_path(crash_reporter.dir().join(""))
+            );
+            builder.crash_reporter(crash_reporter);
+        }
 
-        builder.hooks(StdioLoggingTriggerHooks::new(self.follow_components()));
-        builder.hooks(Network);
+        builder.hooks(
+            StdioLoggingTriggerHooks::new(self.follow_components())
+                .with_log_rotation(self.log_rotation()),
+        );
+        builder.hooks(Network::default());
+        builder.hooks(ResourceLimits::default());
+        builder.hooks(InvocationUsageLog);
+        if self.metrics {
+            builder.hooks(InvocationMetrics);
+        }
+        if !self.profile_components.is_empty() {
+            builder.hooks(GuestProfiling::new(self.profile_components.clone()));
+        }
         builder.hooks(KeyValuePersistenceMessageHook);
         builder.hooks(SqlitePersistenceMessageHook);
 
-        builder.build(locked_url, runtime_config, init_data).await
+        builder
+            .build(setup.locked_url.clone(), runtime_config, init_data)
+            .await
     }
 
     fn build_runtime_config(&self) -> Result<RuntimeConfig> {
@@ -216,6 +471,13 @@ where
         Ok(config)
     }
 
+    fn log_rotation(&self) -> Option<LogRotation> {
+        self.log_max_size.map(|max_size| LogRotation {
+            max_size,
+            max_files: self.log_max_files,
+        })
+    }
+
     fn follow_components(&self) -> FollowComponents {
         if self.silence_component_logs {
             FollowComponents::None
@@ -227,20 +489,197 @@ where
         }
     }
 
-    fn update_config(&self, config: &mut spin_core::Config) -> Result<()> {
+    fn update_config(
+        &self,
+        config: &mut spin_core::Config,
+        runtime_config: &RuntimeConfig,
+    ) -> Result<()> {
         // Apply --cache / --disable-cache
         if !self.disable_cache {
             config.enable_cache(&self.cache)?;
         }
 
+        crate::runtime_config::pooling_allocator::update_config(runtime_config, config);
+        crate::runtime_config::component_limits::update_config(runtime_config, config);
         if self.disable_pooling {
             config.disable_pooling();
         }
 
+        if self.metrics {
+            config.consume_fuel();
+        }
+        if self.capture_coredumps {
+            config.coredump_on_trap();
+        }
+        if self.debug {
+            config.debug_guests();
+        }
+
         Ok(())
     }
 }
 
+/// A command that runs the TriggerExecutors of two trigger types of an app,
+/// such as HTTP and Redis, in one process. The executors share the trigger
+/// options, and so the runtime config and loader cache, and a Wasmtime
+/// engine, so that they share compiled code and the pooling allocator's
+/// reserved memory.
+///
+/// Default key-value stores and SQLite databases are shared where they are
+/// persisted to the state directory; in-memory stores are not shared.
+#[derive(Parser, Debug)]
+#[clap(
+    usage = "spin [COMMAND] [OPTIONS]",
+    next_help_heading = "TRIGGER OPTIONS"
+)]
+pub struct MultiTriggerExecutorCommand<A: TriggerExecutor, B: TriggerExecutor>
+where
+    A::RunConfig: Args,
+    B::RunConfig: Args,
+{
+    #[clap(flatten)]
+    pub common: TriggerExecutorCommand<A>,
+
+    #[clap(flatten)]
+    pub run_config: B::RunConfig,
+}
+
+impl<A: TriggerExecutor, B: TriggerExecutor> MultiTriggerExecutorCommand<A, B>
+where
+    A::RunConfig: Args,
+    B::RunConfig: Args,
+    A::TriggerConfig: DeserializeOwned,
+    B::TriggerConfig: DeserializeOwned,
+{
+    pub async fn run(self) -> Result<()> {
+        let common = self.common;
+        if common.help_args_only {
+            Self::command()
+                .disable_help_flag(true)
+                .help_template("{all-args}")
+                .print_long_help()?;
+            return Ok(());
+        }
+
+        let trigger_type = format!("{}+{}", A::TRIGGER_TYPE, B::TRIGGER_TYPE);
+        let (setup, runtime_config) = common.prepare(&trigger_type).await?;
+
+        // Runs the epoch ticker thread of the shared engine, so must be held
+        // until the executors exit
+        let mut config = spin_core::Config::default();
+        common.update_config(&mut config, &runtime_config)?;
+        let ticker_engine = spin_core::Engine::<()>::builder(&config)?.build();
+        let shared_engine = ticker_engine.as_ref().clone();
+
+        // The executors load the app with the same loader and runtime config
+        let loader = Arc::new(common.loader(&setup.working_dir, &runtime_config)?);
+        let first = common
+            .build_executor::<A>(
+                &setup,
+                loader.clone(),
+                &runtime_config,
+                common.init_data(),
+                Some(shared_engine.clone()),
+            )
+            .await?;
+        // Initial key-values and SQLite statements apply once, not per executor
+        let no_init_data = crate::HostComponentInitData {
+            llm: LLmOptions { use_gpu: true },
+            ..Default::default()
+        };
+        let second = common
+            .build_executor::<B>(
+                &setup,
+                loader,
+                &runtime_config,
+                no_init_data,
+                Some(shared_engine),
+            )
+            .await?;
+
+        let (first_config, second_config) = (common.run_config, self.run_config);
+        let run_fut = async move {
+            futures::try_join!(first.run(first_config), second.run(second_config))?;
+            Ok(())
+        };
+        let result = setup.run_until_shutdown(run_fut).await;
+        drop(ticker_engine);
+        result
+    }
+}
+
+/// What the executors run by a trigger command share: the app to load, and
+/// the handling of readiness and shutdown.
+struct TriggerSetup {
+    working_dir: String,
+    locked_url: String,
+    shutdown_signal: ShutdownSignal,
+    // With the runtime config of the stores to check for readiness
+    readiness: Option<(Arc<Readiness>, RuntimeConfig)>,
+    drain_timeout: Duration,
+    // Runs until the executors exit, if started with --debug-adapter-port
+    _debug_adapter: Option<DebugAdapter>,
+}
+
+impl TriggerSetup {
+    /// Marks the app ready once its stores are reachable, then runs the
+    /// executors until they exit or are shut down by a signal.
+    async fn run_until_shutdown(self, run_fut: impl Future<Output = Result<()>>) -> Result<()> {
+        if let Some((readiness, runtime_config)) = self.readiness {
+            tokio::spawn(admin::set_ready_when_reachable(runtime_config, readiness));
+        }
+
+        let (abortable, abort_handle) = futures::future::abortable(run_fut);
+        let shutdown_signal = self.shutdown_signal;
+        let drain_timeout = self.drain_timeout;
+        let mut first_signal_time: Option<Instant> = None;
+        ctrlc::set_handler(move || {
+            if !shutdown_signal.trigger() {
+                // `spin up` forwards signals it receives, so a single Ctrl-C
+                // may arrive twice in quick succession; ignore the duplicate
+                let elapsed = first_signal_time.map(|t| t.elapsed());
+                if elapsed.unwrap_or_default() >= DUPLICATE_SIGNAL_WINDOW {
+                    // Second signal: don't wait for draining to finish
+                    abort_handle.abort();
+                }
+                return;
+            }
+            first_signal_time = Some(Instant::now());
+            tracing::info!("Shutting down: waiting up to {drain_timeout:?} for in-flight requests");
+            let abort_handle = abort_handle.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(drain_timeout);
+                abort_handle.abort();
+            });
+        })?;
+        let result = abortable.await;
+        spin_telemetry::shutdown();
+        match result {
+            Ok(Ok(())) => {
+                tracing::info!("Trigger executor shut down: exiting");
+                Ok(())
+            }
+            Ok(Err(err)) => {
+                tracing::error!("Trigger executor failed");
+                Err(err)
+            }
+            Err(_aborted) => {
+                tracing::info!("User requested shutdown: exiting");
+                Ok(())
+            }
+        }
+    }
+}
+
+const COMPILED_COMPONENT_CACHE_DIR: &str = "compiled_components";
+
+fn compiled_component_cache_dir() -> Option<PathBuf> {
+    let dir = dirs::cache_dir()?
+        .join("spin")
+        .join(COMPILED_COMPONENT_CACHE_DIR);
+    Some(dir)
+}
+
 const SLOTH_WARNING_DELAY_MILLIS: u64 = 1250;
 
 fn warn_if_wasm_build_slothful() -> sloth::SlothGuard {
@@ -0,0 +1,94 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use spin_common::ui::quoted_path;
+use spin_core::Store;
+
+/// Writes a core dump of each component that traps, along with the context
+/// of the invocation it was handling, to a crash directory.
+///
+/// Core dumps are only captured if [`spin_core::Config::coredump_on_trap`] is
+/// enabled. They can be inspected with tools such as `wasmgdb`.
+#[derive(Clone, Debug)]
+pub struct CrashReporter {
+    dir: PathBuf,
+    // Distinguishes crashes in the same millisecond
+    sequence: Arc<AtomicU64>,
+}
+
+impl CrashReporter {
+    /// Creates a reporter writing to the given directory, which is created if
+    /// it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create crash dir {}", quoted_path(&dir)))?;
+        Ok(Self {
+            dir,
+            sequence: Default::default(),
+        })
+    }
+
+    /// Returns the crash directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// If `err` is a trap carrying a core dump, writes the core dump of
+    /// `store` to `<component>-<timestamp>-<n>.coredump` in the crash
+    /// directory, and `context` to a `.json` file of the same name, and
+    /// prints the path of the core dump.
+    ///
+    /// Returns the path of the core dump, if one was written. Failures to
+    /// write are logged rather than returned, so as not to mask `err`.
+    pub fn capture<T>(
+        &self,
+        component_id: &str,
+        store: &mut Store<T>,
+        err: &anyhow::Error,
+        context: serde_json::Value,
+    ) -> Option<PathBuf> {
+        let coredump = store.coredump(err, component_id)?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let stem = format!(
+            "{}-{millis}-{sequence}",
+            sanitize_filename::sanitize(component_id)
+        );
+        let coredump_path = self.dir.join(format!("{stem}.coredump"));
+        let context_path = self.dir.join(format!("{stem}.json"));
+
+        let context = serde_json::json!({
+            "component_id": component_id,
+            "error": format!("{err:?}"),
+            "context": context,
+        });
+        let result = std::fs::write(&coredump_path, coredump)
+            .with_context(|| format!("Failed to write {}", quoted_path(&coredump_path)))
+            .and_then(|()| {
+                let json = serde_json::to_vec_pretty(&context)?;
+                std::fs::write(&context_path, json)
+                    .with_context(|| format!("Failed to write {}", quoted_path(&context_path)))
+            });
+        if let Err(err) = result {
+            tracing::warn!("Failed to capture crash of component {component_id:?}: {err:?}");
+            return None;
+        }
+
+        eprintln!(
+            "Component {component_id:?} trapped: core dump written to {}",
+            quoted_path(&coredump_path)
+        );
+        Some(coredump_path)
+    }
+}
@@ -0,0 +1,102 @@
+//! Guest debugging over the Debug Adapter Protocol, through `lldb-dap`.
+//!
+//! With `--debug`, components are compiled with debug info, which registers
+//! them with the GDB JIT interface, and Spin writes a launch configuration
+//! for attaching `lldb-dap` to the Spin process. With `--debug-adapter-port`,
+//! Spin also starts `lldb-dap` itself, listening for a client on that port.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+use spin_common::ui::quoted_path;
+
+/// Makes LLDB load guest code registered with the GDB JIT interface.
+const LLDB_JIT_LOADER_SETTING: &str = "settings set plugin.jit-loader.gdb.enable on";
+
+/// Names the `lldb-dap` executable to start, if it isn't `lldb-dap` on the
+/// path.
+const LLDB_DAP_ENV: &str = "SPIN_LLDB_DAP";
+
+const LAUNCH_CONFIG_FILE: &str = "launch.json";
+
+/// Writes a VS Code-style launch configuration attaching `lldb-dap` to this
+/// process to `dir`, returning its path. If `adapter_port` is given, the
+/// configuration connects to the adapter listening on that port rather than
+/// starting one.
+pub(crate) fn write_launch_config(dir: &Path, adapter_port: Option<u16>) -> Result<PathBuf> {
+    let mut attach = serde_json::json!({
+        "name": "Attach to Spin",
+        "type": "lldb-dap",
+        "request": "attach",
+        "pid": std::process::id(),
+        "initCommands": [LLDB_JIT_LOADER_SETTING],
+    });
+    if let Some(port) = adapter_port {
+        attach["debugServer"] = port.into();
+    }
+    let config = serde_json::json!({
+        "version": "0.2.0",
+        "configurations": [attach],
+    });
+
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create {}", quoted_path(dir)))?;
+    let path = dir.join(LAUNCH_CONFIG_FILE);
+    std::fs::write(&path, serde_json::to_vec_pretty(&config)?)
+        .with_context(|| format!("failed to write {}", quoted_path(&path)))?;
+    Ok(path)
+}
+
+/// A running `lldb-dap`, which is stopped when this is dropped.
+pub(crate) struct DebugAdapter {
+    child: Child,
+}
+
+impl DebugAdapter {
+    /// Starts `lldb-dap` listening for a Debug Adapter Protocol client on the
+    /// given port.
+    pub fn start(port: u16) -> Result<Self> {
+        let program = std::env::var_os(LLDB_DAP_ENV).unwrap_or_else(|| "lldb-dap".into());
+        let child = Command::new(&program)
+            .arg("--port")
+            .arg(port.to_string())
+            .stdin(Stdio::null())
+            .spawn()
+            .with_context(|| {
+                format!(
+                    "failed to start debug adapter {}; install lldb-dap, or set {LLDB_DAP_ENV} to its path",
+                    quoted_path(&program)
+                )
+            })?;
+        Ok(Self { child })
+    }
+}
+
+impl Drop for DebugAdapter {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launch_config_attaches_to_this_process() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = write_launch_config(dir.path(), Some(4711))?;
+        let config: serde_json::Value = serde_json::from_slice(&std::fs::read(path)?)?;
+        let attach = &config["configurations"][0];
+        assert_eq!(attach["type"], "lldb-dap");
+        assert_eq!(attach["request"], "attach");
+        assert_eq!(attach["pid"], std::process::id());
+        assert_eq!(attach["initCommands"][0], LLDB_JIT_LOADER_SETTING);
+        assert_eq!(attach["debugServer"], 4711);
+        Ok(())
+    }
+}
@@ -1,39 +1,67 @@
+mod admin;
 pub mod cli;
+mod crash;
+mod debugger;
+mod limits;
 pub mod loader;
+mod metrics;
 mod network;
+pub mod plugin;
+mod profiling;
+mod reload;
 mod runtime_config;
+mod shutdown;
 mod stdio;
 
-use std::{collections::HashMap, marker::PhantomData};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
 pub use async_trait::async_trait;
-use runtime_config::llm::LLmOptions;
+use futures::{StreamExt, TryStreamExt};
+use reload::SourceWatcher;
 use serde::de::DeserializeOwned;
 
 use spin_app::{App, AppComponent, AppLoader, AppTrigger, Loader, OwnedApp, APP_NAME_KEY};
+use spin_common::url::parse_file_url;
 use spin_core::{
     Config, Engine, EngineBuilder, Instance, InstancePre, ModuleInstance, ModuleInstancePre,
     OutboundWasiHttpHandler, Store, StoreBuilder, WasiVersion,
 };
+use spin_outbound_networking::dns::Resolver;
 
-pub use crate::runtime_config::RuntimeConfig;
+pub use crate::crash::CrashReporter;
+pub use crate::runtime_config::{llm::LLmOptions, RuntimeConfig};
+pub use crate::shutdown::ShutdownSignal;
 
 pub enum EitherInstancePre<T> {
     Component(InstancePre<T>),
     Module(ModuleInstancePre<T>),
 }
 
+impl<T> Clone for EitherInstancePre<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Component(pre) => Self::Component(pre.clone()),
+            Self::Module(pre) => Self::Module(pre.clone()),
+        }
+    }
+}
+
 pub enum EitherInstance {
     Component(Instance),
     Module(ModuleInstance),
 }
 
 #[async_trait]
-pub trait TriggerExecutor: Sized + Send + Sync {
+pub trait TriggerExecutor: Sized + Send + Sync + 'static {
     const TRIGGER_TYPE: &'static str;
     type RuntimeData: OutboundWasiHttpHandler + Default + Send + Sync + 'static;
-    type TriggerConfig;
+    type TriggerConfig: Send + Sync + 'static;
     type RunConfig;
 
     /// Create a new trigger executor.
@@ -66,6 +94,11 @@ pub struct TriggerExecutorBuilder<Executor: TriggerExecutor> {
     config: Config,
     hooks: Vec<Box<dyn TriggerHooks>>,
     disable_default_host_components: bool,
+    load_parallelism: usize,
+    hot_reload: bool,
+    shutdown_signal: ShutdownSignal,
+    crash_reporter: Option<CrashReporter>,
+    shared_engine: Option<spin_core::wasmtime::Engine>,
     _phantom: PhantomData<Executor>,
 }
 
@@ -77,6 +110,11 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
             config: Default::default(),
             hooks: Default::default(),
             disable_default_host_components: false,
+            load_parallelism: default_load_parallelism(),
+            hot_reload: false,
+            shutdown_signal: Default::default(),
+            crash_reporter: None,
+            shared_engine: None,
             _phantom: PhantomData,
         }
     }
@@ -98,17 +136,57 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
         self
     }
 
+    /// Sets the maximum number of components to load concurrently. Defaults
+    /// to the available parallelism of the host.
+    pub fn load_parallelism(&mut self, load_parallelism: usize) -> &mut Self {
+        self.load_parallelism = load_parallelism;
+        self
+    }
+
+    /// Enables hot reloading of components whose sources change.
+    /// See [`TriggerAppEngine::enable_hot_reload`].
+    pub fn hot_reload(&mut self) -> &mut Self {
+        self.hot_reload = true;
+        self
+    }
+
+    /// Sets the signal used to shut down the executor gracefully.
+    /// See [`TriggerAppEngine::shutdown_signal`].
+    pub fn shutdown_signal(&mut self, shutdown_signal: ShutdownSignal) -> &mut Self {
+        self.shutdown_signal = shutdown_signal;
+        self
+    }
+
+    /// Sets the reporter used to capture core dumps of trapped components.
+    /// See [`TriggerAppEngine::crash_reporter`].
+    pub fn crash_reporter(&mut self, crash_reporter: CrashReporter) -> &mut Self {
+        self.crash_reporter = Some(crash_reporter);
+        self
+    }
+
+    /// Builds the executor's engine on an existing Wasmtime engine, such as
+    /// one shared with executors for the app's other trigger types.
+    /// See [`Engine::builder_for`].
+    pub fn shared_engine(&mut self, engine: spin_core::wasmtime::Engine) -> &mut Self {
+        self.shared_engine = Some(engine);
+        self
+    }
+
     pub async fn build(
         mut self,
         app_uri: String,
-        runtime_config: runtime_config::RuntimeConfig,
+        runtime_config: &runtime_config::RuntimeConfig,
         init_data: HostComponentInitData,
     ) -> Result<Executor>
     where
         Executor::TriggerConfig: DeserializeOwned,
     {
+        let resolver = runtime_config::dns::build_resolver(runtime_config)?;
         let engine = {
-            let mut builder = Engine::builder(&self.config)?;
+            let mut builder = match &self.shared_engine {
+                Some(engine) => Engine::builder_for(engine, &self.config)?,
+                None => Engine::builder(&self.config)?,
+            };
 
             if !self.disable_default_host_components {
                 // Wasmtime 15: WASI@0.2.0-rc-2023-11-10
@@ -117,44 +195,70 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
                 // Wasmtime 14: WASI@0.2.0-rc-2023-10-18
                 builder.link_import(|l, _| spin_core::wasi_2023_10_18::add_to_linker(l))?;
 
+                builder.link_import(|l, _| spin_core::usage::add_to_linker(l))?;
+
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    outbound_redis::OutboundRedisComponent,
+                    outbound_redis::OutboundRedisComponent::new(resolver.clone()),
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    outbound_mysql::OutboundMysql::default(),
+                    outbound_mqtt::OutboundMqttComponent,
+                )?;
+                self.loader.add_dynamic_host_component(
+                    &mut builder,
+                    runtime_config::outbound_mysql::build_component(
+                        runtime_config,
+                        resolver.clone(),
+                    ),
                 )?;
-                self.loader
-                    .add_dynamic_host_component(&mut builder, outbound_pg::OutboundPg::default())?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    runtime_config::llm::build_component(&runtime_config, init_data.llm.use_gpu)
-                        .await,
+                    outbound_pg::OutboundPg::new(resolver.clone()),
+                )?;
+                self.loader.add_dynamic_host_component(
+                    &mut builder,
+                    runtime_config::llm::build_component(runtime_config, init_data.llm.use_gpu)
+                        .await?,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
                     runtime_config::key_value::build_key_value_component(
-                        &runtime_config,
+                        runtime_config,
                         &init_data.kv,
                     )
                     .await?,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    runtime_config::sqlite::build_component(&runtime_config, &init_data.sqlite)
-                        .await?,
+                    runtime_config::blobstore::build_component(runtime_config)?,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    outbound_http::OutboundHttpComponent,
+                    runtime_config::lock::build_component(runtime_config)?,
                 )?;
                 self.loader.add_dynamic_host_component(
                     &mut builder,
-                    spin_variables::VariablesHostComponent::new(
-                        runtime_config.variables_providers(),
-                    ),
+                    runtime_config::host_component::build_component(runtime_config)?,
+                )?;
+                self.loader.add_dynamic_host_component(
+                    &mut builder,
+                    runtime_config::sqlite::build_component(runtime_config, &init_data.sqlite)
+                        .await?,
                 )?;
+                self.loader.add_dynamic_host_component(
+                    &mut builder,
+                    runtime_config::outbound_http::build_component(runtime_config, resolver)?,
+                )?;
+                let mut variables = spin_variables::VariablesHostComponent::new(
+                    runtime_config.variables_providers(),
+                );
+                if let Some(secs) = runtime_config.variables_opts().refresh_interval_secs {
+                    anyhow::ensure!(secs > 0, "variables refresh_interval_secs must be positive");
+                    variables = variables.with_refresh_interval(Duration::from_secs(secs));
+                }
+                self.loader
+                    .add_dynamic_host_component(&mut builder, variables)?;
             }
 
             Executor::configure_engine(&mut builder)?;
@@ -167,11 +271,42 @@ impl<Executor: TriggerExecutor> TriggerExecutorBuilder<Executor> {
 
         self.hooks
             .iter_mut()
-            .try_for_each(|h| h.app_loaded(app.borrowed(), &runtime_config))?;
+            .try_for_each(|h| h.app_loaded(app.borrowed(), runtime_config))?;
 
         // Run trigger executor
-        Executor::new(TriggerAppEngine::new(engine, app_name, app, self.hooks).await?).await
+        let mut app_engine =
+            TriggerAppEngine::new(engine, app_name, app, self.hooks, self.load_parallelism).await?;
+        if self.hot_reload {
+            app_engine.enable_hot_reload();
+        }
+        app_engine.shutdown_signal = self.shutdown_signal;
+        app_engine.crash_reporter = self.crash_reporter;
+        app_engine.resolver = resolver;
+        app_engine.variables =
+            Arc::new(build_variables_resolver(app_engine.app(), runtime_config)?);
+        Executor::new(app_engine).await
+    }
+}
+
+// Resolves the app's variables with the runtime config's providers, for the
+// trigger's own use; components resolve variables through their host component.
+fn build_variables_resolver(
+    app: &App,
+    runtime_config: &runtime_config::RuntimeConfig,
+) -> Result<spin_variables::Resolver> {
+    let mut resolver = spin_variables::Resolver::new(
+        app.variables().map(|(key, var)| (key.clone(), var.clone())),
+    )?;
+    for provider in runtime_config.variables_providers() {
+        resolver.add_provider(provider);
     }
+    Ok(resolver)
+}
+
+fn default_load_parallelism() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
 }
 
 /// Initialization data for host components.
@@ -202,27 +337,42 @@ impl HostComponentInitData {
 /// Execution context for a TriggerExecutor executing a particular App.
 pub struct TriggerAppEngine<Executor: TriggerExecutor> {
     /// Engine to be used with this executor.
-    pub engine: Engine<Executor::RuntimeData>,
+    pub engine: Arc<Engine<Executor::RuntimeData>>,
     /// Name of the app for e.g. logging.
     pub app_name: String,
     // An owned wrapper of the App.
-    app: OwnedApp,
+    app: Arc<OwnedApp>,
     // Trigger hooks
     hooks: Vec<Box<dyn TriggerHooks>>,
     // Trigger configs for this trigger type, with order matching `app.triggers_with_type(Executor::TRIGGER_TYPE)`
-    trigger_configs: Vec<Executor::TriggerConfig>,
+    trigger_configs: Arc<Vec<Executor::TriggerConfig>>,
     // Map of {Component ID -> InstancePre} for each component.
-    component_instance_pres: HashMap<String, EitherInstancePre<Executor::RuntimeData>>,
+    component_instance_pres: InstancePres<Executor::RuntimeData>,
+    // Component ID -> number of times the component has been hot reloaded
+    reload_counts: ReloadCounts,
+    // Watches component sources for changes and reloads them, if hot reload
+    // is enabled.
+    _source_watcher: Option<SourceWatcher>,
+    // Triggered when the executor should shut down gracefully.
+    shutdown_signal: ShutdownSignal,
+    // Captures core dumps of trapped components, if enabled.
+    crash_reporter: Option<CrashReporter>,
+    // Resolves hostnames for connections made by the trigger itself.
+    resolver: Resolver,
+    // Resolves app variables for configuration of the trigger itself.
+    variables: Arc<spin_variables::Resolver>,
 }
 
 impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
     /// Returns a new TriggerAppEngine. May return an error if trigger config validation or
-    /// component pre-instantiation fails.
+    /// component pre-instantiation fails. Up to `load_parallelism` components are loaded
+    /// concurrently.
     pub async fn new(
         engine: Engine<Executor::RuntimeData>,
         app_name: String,
         app: OwnedApp,
         hooks: Vec<Box<dyn TriggerHooks>>,
+        load_parallelism: usize,
     ) -> Result<Self>
     where
         <Executor as TriggerExecutor>::TriggerConfig: DeserializeOwned,
@@ -232,7 +382,10 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
             .triggers_with_type(Executor::TRIGGER_TYPE)
             .map(|trigger| {
                 Ok((
-                    trigger.component()?.id().to_owned(),
+                    // Triggers without a component are handled by the executor
+                    trigger
+                        .optional_component()?
+                        .map(|component| component.id().to_owned()),
                     trigger.typed_config().with_context(|| {
                         format!("invalid trigger configuration for {:?}", trigger.id())
                     })?,
@@ -240,7 +393,7 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
             })
             .collect::<Result<Vec<_>>>()?;
 
-        let mut component_instance_pres = HashMap::default();
+        let mut components_to_load = vec![];
         for component in app.borrowed().components() {
             let id = component.id();
             // There is an issue here for triggers that consider the trigger config during
@@ -248,15 +401,10 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
             // `executor` field and that should not differ from trigger to trigger.
             let trigger_config = trigger_configs
                 .iter()
-                .find(|(c, _)| c == id)
+                .find(|(c, _)| c.as_deref() == Some(id))
                 .map(|(_, cfg)| cfg);
             if let Some(config) = trigger_config {
-                component_instance_pres.insert(
-                    id.to_owned(),
-                    Executor::instantiate_pre(&engine, &component, config)
-                        .await
-                        .with_context(|| format!("Failed to instantiate component '{id}'"))?,
-                );
+                components_to_load.push((component, config));
             } else {
                 tracing::warn!(
                     "component '{id}' is not used by any triggers in app '{app_name}'",
@@ -266,16 +414,119 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
             }
         }
 
+        // Load components concurrently
+        let engine_ref = &engine;
+        let component_instance_pres = futures::stream::iter(components_to_load)
+            .map(|(component, config)| async move {
+                let id = component.id().to_owned();
+                let start = Instant::now();
+                let pre = Executor::instantiate_pre(engine_ref, &component, config)
+                    .await
+                    .with_context(|| format!("Failed to instantiate component '{id}'"))?;
+                tracing::info!("Loaded component '{id}' in {:?}", start.elapsed());
+                anyhow::Ok((id, pre))
+            })
+            .buffer_unordered(load_parallelism.max(1))
+            .try_collect::<HashMap<_, _>>()
+            .await?;
+
         Ok(Self {
-            engine,
+            engine: Arc::new(engine),
             app_name,
-            app,
+            app: Arc::new(app),
             hooks,
-            trigger_configs: trigger_configs.into_iter().map(|(_, v)| v).collect(),
-            component_instance_pres,
+            trigger_configs: Arc::new(trigger_configs.into_iter().map(|(_, v)| v).collect()),
+            component_instance_pres: Arc::new(RwLock::new(component_instance_pres)),
+            reload_counts: Default::default(),
+            _source_watcher: None,
+            shutdown_signal: Default::default(),
+            crash_reporter: None,
+            resolver: Default::default(),
+            variables: Default::default(),
         })
     }
 
+    /// Enables hot reloading of components. A component whose local source
+    /// file changes is recompiled in the background, and used by later
+    /// instantiations once it compiles; instances that are already running
+    /// are unaffected.
+    pub fn enable_hot_reload(&mut self) {
+        let loaded_ids = self
+            .component_instance_pres
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let component_sources = self
+            .app()
+            .components()
+            .filter(|component| loaded_ids.iter().any(|id| id == component.id()))
+            .filter_map(|component| {
+                let source = component.source().content.source.as_deref()?;
+                let path = parse_file_url(source).ok()?;
+                Some((component.id().to_owned(), path))
+            })
+            .collect();
+        let locked_app_path = parse_file_url(self.app().uri()).ok();
+
+        let reloader = Arc::new(ComponentReloader::<Executor> {
+            engine: self.engine.clone(),
+            app: self.app.clone(),
+            trigger_configs: self.trigger_configs.clone(),
+            component_instance_pres: self.component_instance_pres.clone(),
+            reload_counts: self.reload_counts.clone(),
+        });
+        self._source_watcher = Some(reload::watch_sources(
+            component_sources,
+            locked_app_path,
+            move |component_id| {
+                let reloader = reloader.clone();
+                async move { reloader.reload(&component_id).await }
+            },
+        ));
+    }
+
+    /// Returns the number of times the given component has been hot reloaded.
+    /// Executors which instantiate a component ahead of use should discard
+    /// instances prepared before this last changed.
+    pub fn reload_count(&self, component_id: &str) -> u64 {
+        let reload_counts = self.reload_counts.lock().unwrap();
+        reload_counts.get(component_id).copied().unwrap_or_default()
+    }
+
+    /// Returns the signal that is triggered when the executor should shut
+    /// down gracefully: stop accepting new work, wait for in-flight
+    /// invocations, and return from [`TriggerExecutor::run`].
+    pub fn shutdown_signal(&self) -> &ShutdownSignal {
+        &self.shutdown_signal
+    }
+
+    /// Returns the reporter that executors should pass the stores and errors
+    /// of failed invocations to, if core dump capture is enabled.
+    pub fn crash_reporter(&self) -> Option<&CrashReporter> {
+        self.crash_reporter.as_ref()
+    }
+
+    /// Returns the resolver that executors should use for their own
+    /// outbound connections, such as to a Redis server, so that they honour
+    /// the runtime config's DNS settings.
+    pub fn resolver(&self) -> &Resolver {
+        &self.resolver
+    }
+
+    /// Resolves the app variable with the given name, for configuration which
+    /// the trigger itself needs, such as credentials, without putting secrets
+    /// in the trigger config.
+    pub async fn resolve_variable(&self, name: &str) -> Result<String> {
+        let key = spin_variables::Key::new(name)
+            .with_context(|| format!("invalid variable name {name:?}"))?;
+        self.variables
+            .resolve_app_variable(key)
+            .await
+            .with_context(|| format!("failed to resolve variable {name:?}"))
+    }
+
     /// Returns a reference to the App.
     pub fn app(&self) -> &App {
         self.app.borrowed()
@@ -285,7 +536,7 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
     pub fn trigger_configs(&self) -> impl Iterator<Item = (AppTrigger, &Executor::TriggerConfig)> {
         self.app()
             .triggers_with_type(Executor::TRIGGER_TYPE)
-            .zip(&self.trigger_configs)
+            .zip(self.trigger_configs.iter())
     }
 
     /// Returns a new StoreBuilder for the given component ID.
@@ -313,6 +564,11 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
     }
 
     /// Returns a new Store and Instance for the given component ID and StoreBuilder.
+    #[tracing::instrument(
+        name = "spin_trigger.prepare_instance",
+        skip_all,
+        fields(spin.component_id = component_id)
+    )]
     pub async fn prepare_instance_with_store(
         &self,
         component_id: &str,
@@ -322,12 +578,26 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
 
         // Build Store
         component.apply_store_config(&mut store_builder).await?;
+        // Send wasi:http requests with the outbound HTTP component's clients
+        if let Some(handle) = self
+            .engine
+            .find_host_component_handle::<Arc<outbound_http::OutboundHttpComponent>>()
+        {
+            let outbound_http = store_builder
+                .host_components_data()
+                .get_or_insert(handle)
+                .clone();
+            store_builder.outgoing_request_sender(outbound_http);
+        }
         let mut store = store_builder.build()?;
 
         // Instantiate
         let pre = self
             .component_instance_pres
+            .read()
+            .unwrap()
             .get(component_id)
+            .cloned()
             .expect("component_instance_pres missing valid component_id");
 
         let instance = match pre {
@@ -361,6 +631,59 @@ impl<Executor: TriggerExecutor> TriggerAppEngine<Executor> {
     }
 }
 
+// Map of {Component ID -> InstancePre}, shared with the hot reloader
+type InstancePres<T> = Arc<RwLock<HashMap<String, EitherInstancePre<T>>>>;
+
+type ReloadCounts = Arc<Mutex<HashMap<String, u64>>>;
+
+/// Recompiles components whose sources change, from the source watcher's
+/// task, replacing their `InstancePre`s only once they compile.
+struct ComponentReloader<Executor: TriggerExecutor> {
+    engine: Arc<Engine<Executor::RuntimeData>>,
+    app: Arc<OwnedApp>,
+    trigger_configs: Arc<Vec<Executor::TriggerConfig>>,
+    component_instance_pres: InstancePres<Executor::RuntimeData>,
+    reload_counts: ReloadCounts,
+}
+
+impl<Executor: TriggerExecutor> ComponentReloader<Executor> {
+    async fn reload(&self, component_id: &str) {
+        match self.reload_component(component_id).await {
+            Ok(()) => terminal::step!("Reloaded", "component {component_id:?}"),
+            Err(err) => terminal::error!(
+                "Failed to reload component {component_id:?}; continuing to use the previous version: {err:#}"
+            ),
+        }
+    }
+
+    async fn reload_component(&self, component_id: &str) -> Result<()> {
+        let app = self.app.borrowed();
+        let component = app
+            .get_component(component_id)
+            .with_context(|| format!("app has no component {component_id:?}"))?;
+        let config = app
+            .triggers_with_type(Executor::TRIGGER_TYPE)
+            .zip(self.trigger_configs.iter())
+            .find_map(|(trigger, config)| {
+                let is_match = trigger.component().ok()?.id() == component_id;
+                is_match.then_some(config)
+            })
+            .with_context(|| format!("no trigger configured for component {component_id:?}"))?;
+        let pre = Executor::instantiate_pre(&self.engine, &component, config).await?;
+        self.component_instance_pres
+            .write()
+            .unwrap()
+            .insert(component_id.to_owned(), pre);
+        *self
+            .reload_counts
+            .lock()
+            .unwrap()
+            .entry(component_id.to_owned())
+            .or_default() += 1;
+        Ok(())
+    }
+}
+
 /// TriggerHooks allows a Spin environment to hook into a TriggerAppEngine's
 /// configuration and execution processes.
 pub trait TriggerHooks: Send + Sync {
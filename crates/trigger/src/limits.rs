@@ -0,0 +1,76 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::Deserialize;
+use spin_app::{App, MetadataKey};
+
+use crate::{runtime_config::component_limits::ComponentLimitsOpts, RuntimeConfig, TriggerHooks};
+
+/// Metadata key for per-component resource limits.
+pub const LIMITS_KEY: MetadataKey<ComponentLimits> = MetadataKey::new("limits");
+
+/// Per-component resource limits, applied to each of the component's stores.
+#[derive(Debug, Default, Deserialize)]
+pub struct ComponentLimits {
+    pub max_memory_size: Option<usize>,
+    pub max_table_elements: Option<u32>,
+    pub max_instances: Option<usize>,
+    pub max_tables: Option<usize>,
+    pub max_execution_time_ms: Option<u64>,
+}
+
+/// Applies the limits from each component's manifest, and any hard limits
+/// from `[component_limits.<component-id>]` runtime config sections.
+#[derive(Default)]
+pub struct ResourceLimits {
+    runtime_limits: HashMap<String, ComponentLimitsOpts>,
+}
+
+impl TriggerHooks for ResourceLimits {
+    fn app_loaded(&mut self, _app: &App, runtime_config: &RuntimeConfig) -> anyhow::Result<()> {
+        self.runtime_limits = runtime_config
+            .component_limits()
+            .into_iter()
+            .map(|(id, opts)| (id.to_owned(), opts.clone()))
+            .collect();
+        Ok(())
+    }
+
+    fn component_store_builder(
+        &self,
+        component: &spin_app::AppComponent,
+        store_builder: &mut spin_core::StoreBuilder,
+    ) -> anyhow::Result<()> {
+        let limits = component.get_metadata(LIMITS_KEY)?.unwrap_or_default();
+        let runtime_limits = self
+            .runtime_limits
+            .get(component.id())
+            .cloned()
+            .unwrap_or_default();
+
+        // Runtime config limits are hard limits, so a manifest can't raise them
+        let max_memory_size = limits
+            .max_memory_size
+            .into_iter()
+            .chain(runtime_limits.max_memory_size)
+            .min();
+        if let Some(max_memory_size) = max_memory_size {
+            store_builder.max_memory_size(max_memory_size);
+        }
+        if let Some(max_fuel) = runtime_limits.max_fuel {
+            store_builder.max_fuel(max_fuel);
+        }
+        if let Some(max_table_elements) = limits.max_table_elements {
+            store_builder.max_table_elements(max_table_elements);
+        }
+        if let Some(max_instances) = limits.max_instances {
+            store_builder.max_instances(max_instances);
+        }
+        if let Some(max_tables) = limits.max_tables {
+            store_builder.max_tables(max_tables);
+        }
+        if let Some(max_execution_time_ms) = limits.max_execution_time_ms {
+            store_builder.execution_time_limit(Duration::from_millis(max_execution_time_ms));
+        }
+        Ok(())
+    }
+}
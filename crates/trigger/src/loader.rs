@@ -1,21 +1,65 @@
 #![allow(dead_code)] // Refactor WIP
 
-use std::path::PathBuf;
+mod compiled_cache;
+mod compose;
+mod policy;
+mod signature;
 
-use anyhow::{ensure, Context, Result};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::{bail, ensure, Context, Result};
 use async_trait::async_trait;
 use spin_app::{
-    locked::{LockedApp, LockedComponentSource},
+    locked::{LockedApp, LockedComponentSource, PRECOMPILED_COMPONENT_CONTENT_TYPE},
     AppComponent, Loader,
 };
 use spin_core::StoreBuilder;
+use spin_loader::http::{download_bytes, verified_download};
 use tokio::fs;
 
-use spin_common::{ui::quoted_path, url::parse_file_url};
+use spin_common::{
+    sha256::{hex_digest_from_bytes, hex_digest_from_file},
+    ui::quoted_path,
+    url::parse_file_url,
+};
+
+use self::compiled_cache::CompiledComponentCache;
+pub use self::policy::AdmissionPolicy;
+use self::signature::signature_path;
+pub use self::signature::SignatureVerifier;
+use crate::runtime_config::host_component::HostComponentProvider;
+
+/// URL scheme prefix for component sources hosted in an OCI registry.
+pub const OCI_URL_PREFIX: &str = "oci://";
+
+// Working dir subdirectory for synthetic single-file mount directories
+const FILE_MOUNTS_DIR: &str = "file_mounts";
+// Working dir subdirectory for component sources downloaded over HTTP(S)
+const DOWNLOADS_DIR: &str = "downloads";
 
 pub struct TriggerLoader {
     working_dir: PathBuf,
     allow_transient_write: bool,
+    compiled_cache: Option<CompiledComponentCache>,
+    low_memory_load: bool,
+    signature_verifier: Option<SignatureVerifier>,
+    admission_policy: Option<AdmissionPolicy>,
+    // Provider component sources, keyed by the interface each provides
+    provided_interfaces: Vec<(String, PathBuf)>,
+    // Set if the locked app signature was verified; this also vouches for
+    // component sources with digests
+    app_signature_verified: AtomicBool,
+    // (Component ID, guest path) -> host and guest directories to preopen,
+    // for single-file mounts prepared when the app was loaded
+    file_mounts: Mutex<HashMap<(String, PathBuf), (PathBuf, PathBuf)>>,
 }
 
 impl TriggerLoader {
@@ -23,18 +67,556 @@ impl TriggerLoader {
         Self {
             working_dir: working_dir.into(),
             allow_transient_write,
+            compiled_cache: None,
+            low_memory_load: false,
+            signature_verifier: None,
+            admission_policy: None,
+            provided_interfaces: vec![],
+            app_signature_verified: AtomicBool::new(false),
+            file_mounts: Default::default(),
+        }
+    }
+
+    /// Enables caching of compiled components in the given directory. Cached
+    /// components are reused across loads of unchanged component sources.
+    pub fn with_compiled_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.compiled_cache = Some(CompiledComponentCache::new(dir));
+        self
+    }
+
+    /// Enables low-memory component loading, which memory-maps component
+    /// sources rather than reading them into memory.
+    pub fn with_low_memory_load(mut self, enable: bool) -> Self {
+        self.low_memory_load = enable;
+        self
+    }
+
+    /// Enables verification of detached signatures on the locked app and on
+    /// component sources.
+    pub fn with_signature_verifier(mut self, verifier: SignatureVerifier) -> Self {
+        self.signature_verifier = Some(verifier);
+        self
+    }
+
+    /// Refuses to load apps which break the given admission policy.
+    pub fn with_admission_policy(mut self, policy: AdmissionPolicy) -> Self {
+        self.admission_policy = Some(policy);
+        self
+    }
+
+    /// Satisfies components' imports of the interfaces of the given host
+    /// component providers by composing them with the providers. A
+    /// component's own dependencies take precedence over providers.
+    pub fn with_host_component_providers(
+        mut self,
+        providers: impl IntoIterator<Item = HostComponentProvider>,
+    ) -> Self {
+        for provider in providers {
+            let Some(source) = provider.source else {
+                continue;
+            };
+            for interface in provider.interfaces {
+                self.provided_interfaces.push((interface, source.clone()));
+            }
+        }
+        self
+    }
+
+    /// Resolves the given component source to a local file path, pulling it
+    /// from a registry if necessary, and verifies its digest if one is given.
+    async fn component_source_path(&self, source: &LockedComponentSource) -> Result<PathBuf> {
+        let source_uri = source
+            .content
+            .source
+            .as_ref()
+            .context("LockedComponentSource missing source field")?;
+        let path = if let Some(reference) = source_uri.strip_prefix(OCI_URL_PREFIX) {
+            self.pull_oci_component(reference).await?
+        } else if is_http_url(source_uri) {
+            self.download_component(source_uri, source.content.digest.as_deref())
+                .await?
+        } else {
+            parse_file_url(source_uri)?
+        };
+        if let Some(digest) = &source.content.digest {
+            verify_digest(&path, digest)?;
+        }
+        self.verify_component_signature(source, source_uri, &path)
+            .await?;
+        Ok(path)
+    }
+
+    async fn verify_component_signature(
+        &self,
+        source: &LockedComponentSource,
+        source_uri: &str,
+        path: &Path,
+    ) -> Result<()> {
+        let Some(verifier) = &self.signature_verifier else {
+            return Ok(());
+        };
+        // A (verified) digest in a signed locked app is as good as a signature
+        if source.content.digest.is_some() && self.app_signature_verified.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let signature = if source_uri.starts_with(OCI_URL_PREFIX) {
+            None
+        } else if is_http_url(source_uri) {
+            download_signature(source_uri).await
+        } else {
+            read_signature(path).await?
+        };
+        let content = fs::read(path)
+            .await
+            .with_context(|| format!("failed to read component source at {}", quoted_path(path)))?;
+        verifier.verify(
+            &format!("component source {source_uri:?}"),
+            &content,
+            signature.as_deref(),
+        )?;
+        Ok(())
+    }
+
+    /// Prepares the app's single-file mounts, so that instantiating a
+    /// component doesn't touch the filesystem.
+    async fn prepare_file_mounts(&self, app: &LockedApp) -> Result<()> {
+        for component in &app.components {
+            for content_dir in &component.files {
+                let Some(source_uri) = content_dir.content.source.as_deref() else {
+                    continue;
+                };
+                let source_path = self.working_dir.join(parse_file_url(source_uri)?);
+                if !source_path.is_file() {
+                    continue;
+                }
+                let mount = self
+                    .prepare_file_mount(&component.id, &source_path, &content_dir.path)
+                    .await?;
+                self.file_mounts
+                    .lock()
+                    .unwrap()
+                    .insert((component.id.clone(), content_dir.path.clone()), mount);
+            }
+        }
+        Ok(())
+    }
+
+    /// WASI can only preopen directories, so a single file is mounted by
+    /// placing it in a synthetic directory which is preopened at the parent of
+    /// the file's guest path. The file is hard linked where possible (falling
+    /// back to a copy) and only updated when the source changes. Returns the
+    /// host and guest directories to preopen.
+    async fn prepare_file_mount(
+        &self,
+        component_id: &str,
+        source_path: &Path,
+        guest_path: &Path,
+    ) -> Result<(PathBuf, PathBuf)> {
+        let file_name = guest_path
+            .file_name()
+            .with_context(|| format!("invalid file mount path {}", quoted_path(guest_path)))?;
+        let guest_dir = match guest_path.parent() {
+            Some(parent) if parent != Path::new("") && parent != Path::new("/") => {
+                parent.to_owned()
+            }
+            // The synthetic directory would replace any directory mounted at
+            // the root
+            _ => bail!(
+                "cannot mount file {} at the root directory; mount it in a subdirectory such as /config",
+                quoted_path(source_path)
+            ),
+        };
+
+        // Files sharing a guest directory share a synthetic host directory
+        let host_dir = self
+            .working_dir
+            .join(FILE_MOUNTS_DIR)
+            .join(component_id)
+            .join(hex_digest_from_bytes(
+                guest_dir.to_string_lossy().as_bytes(),
+            ));
+        let host_path = host_dir.join(file_name);
+
+        let source_meta = fs::metadata(source_path).await?;
+        if let Ok(host_meta) = fs::metadata(&host_path).await {
+            // A hard link shares the source metadata; a copy is newer than its source
+            if host_meta.len() == source_meta.len()
+                && host_meta.modified()? >= source_meta.modified()?
+            {
+                return Ok((host_dir, guest_dir));
+            }
+        }
+
+        fs::create_dir_all(&host_dir).await.with_context(|| {
+            format!(
+                "failed to create file mount directory {}",
+                quoted_path(&host_dir)
+            )
+        })?;
+        // Link or copy to a new path and move that into place, so that an
+        // out-of-date link is replaced rather than written through, which
+        // would overwrite the source
+        static NEXT_TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+        let temp_path = host_dir.join(format!(
+            ".{}-{}.tmp",
+            std::process::id(),
+            NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Left over from an earlier process with the same ID
+        match fs::remove_file(&temp_path).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        if fs::hard_link(source_path, &temp_path).await.is_err() {
+            fs::copy(source_path, &temp_path).await.with_context(|| {
+                format!(
+                    "failed to copy {} to {}",
+                    quoted_path(source_path),
+                    quoted_path(&temp_path)
+                )
+            })?;
+        }
+        fs::rename(&temp_path, &host_path).await.with_context(|| {
+            format!(
+                "failed to move {} to {}",
+                quoted_path(&temp_path),
+                quoted_path(&host_path)
+            )
+        })?;
+        Ok((host_dir, guest_dir))
+    }
+
+    async fn download_component(&self, url: &str, digest: Option<&str>) -> Result<PathBuf> {
+        let downloads_dir = self.working_dir.join(DOWNLOADS_DIR);
+        fs::create_dir_all(&downloads_dir).await.with_context(|| {
+            format!(
+                "failed to create downloads directory {}",
+                quoted_path(&downloads_dir)
+            )
+        })?;
+        let context = || format!("failed to download component from {url:?}");
+        match digest {
+            Some(digest) => {
+                let dest = downloads_dir.join(digest.replace(':', "-"));
+                if !dest.exists() {
+                    verified_download(url, digest, &dest)
+                        .await
+                        .with_context(context)?;
+                }
+                Ok(dest)
+            }
+            None => {
+                // Without a digest there is no way to tell if a previous
+                // download is stale, so always download afresh
+                let dest = downloads_dir.join(hex_digest_from_bytes(url));
+                let bytes = download_bytes(url, None).await.with_context(context)?;
+                fs::write(&dest, bytes)
+                    .await
+                    .with_context(|| format!("failed to write {}", quoted_path(&dest)))?;
+                Ok(dest)
+            }
         }
     }
+
+    async fn pull_oci_component(&self, reference: &str) -> Result<PathBuf> {
+        let mut client = spin_oci::Client::new(false, Some(self.working_dir.clone()))
+            .await
+            .context("cannot create registry client")?;
+        client
+            .pull_component(reference)
+            .await
+            .with_context(|| format!("cannot pull component from registry reference {reference:?}"))
+    }
+}
+
+impl TriggerLoader {
+    async fn load_cached_component(
+        &self,
+        cache: &CompiledComponentCache,
+        engine: &spin_core::wasmtime::Engine,
+        source: &LockedComponentSource,
+        path: &Path,
+    ) -> Result<spin_core::Component> {
+        // The source only needs to be read to compute the key if it has no digest
+        let (key, bytes) = match &source.content.digest {
+            Some(digest) => (cache.key(engine, digest), None),
+            None => {
+                let bytes = self.read_component_source(path).await?;
+                (cache.key_for_bytes(engine, &bytes), Some(bytes))
+            }
+        };
+        if let Some(component) = cache.get(engine, &key).await {
+            return Ok(component);
+        }
+
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => self.read_component_source(path).await?,
+        };
+        let component = compile_component(engine, bytes, path).await?;
+        if let Err(err) = cache.put(&key, &component).await {
+            tracing::warn!("Failed to cache compiled component: {err:#}");
+        }
+        Ok(component)
+    }
+
+    /// Loads a component precompiled by `spin precompile`.
+    async fn load_precompiled_component(
+        &self,
+        engine: &spin_core::wasmtime::Engine,
+        source: &LockedComponentSource,
+    ) -> Result<spin_core::Component> {
+        ensure!(
+            source.dependencies.is_empty(),
+            "precompiled components cannot have dependencies; compose them before precompiling"
+        );
+        let path = self.component_source_path(source).await?;
+        // Safety: the lock file is trusted to the same degree as the code it
+        // runs, and `deserialize_file` checks that the artifact was compiled
+        // for this target and a compatible engine configuration.
+        unsafe { spin_core::Component::deserialize_file(engine, &path) }.with_context(|| {
+            format!(
+                "loading precompiled component {}; it may need to be precompiled again for this version of Spin",
+                quoted_path(&path)
+            )
+        })
+    }
+
+    /// Composes the given source with its dependencies and the providers of
+    /// its `provided` imports, and compiles the result. Compositions bypass
+    /// the compiled component cache, as their content depends on all of their
+    /// sources.
+    async fn load_composed_component(
+        &self,
+        engine: &spin_core::wasmtime::Engine,
+        source: &LockedComponentSource,
+        provided: Vec<(String, PathBuf)>,
+    ) -> Result<spin_core::Component> {
+        let root = self.component_source_path(source).await?;
+        let mut dependencies = provided;
+        for (import_name, dependency) in &source.dependencies {
+            let path = self
+                .component_source_path(dependency)
+                .await
+                .with_context(|| format!("failed to load dependency {import_name:?}"))?;
+            dependencies.push((import_name.clone(), path));
+        }
+
+        let engine = engine.clone();
+        let working_dir = self.working_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            let composed = compose::compose_blocking(&working_dir, &root, &dependencies)?;
+            spin_core::Component::new(&engine, composed)
+                .with_context(|| format!("loading composed component {}", quoted_path(&root)))
+        })
+        .await
+        .context("component composition task failed")?
+    }
+
+    /// Returns the providers of the component's imports which its own
+    /// dependencies don't satisfy, keyed by the interface each provides.
+    async fn provided_imports(
+        &self,
+        source: &LockedComponentSource,
+    ) -> Result<Vec<(String, PathBuf)>> {
+        if self.provided_interfaces.is_empty() {
+            return Ok(vec![]);
+        }
+        let path = self.component_source_path(source).await?;
+        let bytes = self.read_component_source(&path).await?;
+        let imports = component_imports(&bytes)
+            .with_context(|| format!("failed to parse {}", quoted_path(&path)))?;
+        let provided = self
+            .provided_interfaces
+            .iter()
+            .filter(|(interface, _)| !source.dependencies.contains_key(interface))
+            .filter(|(interface, _)| imports.contains(interface.as_str()))
+            .cloned()
+            .collect();
+        Ok(provided)
+    }
+
+    async fn read_component_source(&self, path: &Path) -> Result<SourceBytes> {
+        let context = || {
+            format!(
+                "failed to read component source from disk at path '{}'",
+                path.display()
+            )
+        };
+        if self.low_memory_load {
+            let file = std::fs::File::open(path).with_context(context)?;
+            // Safety: the mapping is only used while the component is loaded;
+            // a source modified concurrently will at worst fail to compile.
+            let mmap = unsafe { memmap2::Mmap::map(&file) }.with_context(context)?;
+            Ok(SourceBytes::Mapped(mmap))
+        } else {
+            let bytes = fs::read(path).await.with_context(context)?;
+            Ok(SourceBytes::Owned(bytes))
+        }
+    }
+}
+
+/// Component source bytes, either read into memory or (with low-memory
+/// loading enabled) memory-mapped from the source file.
+enum SourceBytes {
+    Owned(Vec<u8>),
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for SourceBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            Self::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// Returns the names of the imports of the given component. Modules have no
+/// component imports, so none of their imports can be provided.
+fn component_imports(bytes: &[u8]) -> Result<HashSet<&str>> {
+    let mut imports = HashSet::new();
+    // Nested modules and components have imports of their own, which are
+    // satisfied inside the component
+    let mut depth = 0usize;
+    for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+        match payload? {
+            wasmparser::Payload::ModuleSection { .. }
+            | wasmparser::Payload::ComponentSection { .. } => depth += 1,
+            wasmparser::Payload::End(_) => depth = depth.saturating_sub(1),
+            wasmparser::Payload::ComponentImportSection(section) if depth == 0 => {
+                for import in section {
+                    imports.insert(import?.name.0);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(imports)
+}
+
+/// Componentizes (if necessary) and compiles the given source on a blocking
+/// thread, so that multiple components can be compiled concurrently.
+async fn compile_component(
+    engine: &spin_core::wasmtime::Engine,
+    bytes: SourceBytes,
+    path: &Path,
+) -> Result<spin_core::Component> {
+    let engine = engine.clone();
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || compile_component_blocking(&engine, bytes, &path))
+        .await
+        .context("component compilation task failed")?
+}
+
+fn compile_component_blocking(
+    engine: &spin_core::wasmtime::Engine,
+    bytes: SourceBytes,
+    path: &Path,
+) -> Result<spin_core::Component> {
+    let context = || format!("loading module {}", quoted_path(path));
+    let componentized = match spin_componentize::componentize_if_necessary(&bytes)? {
+        Cow::Borrowed(component) => {
+            return spin_core::Component::new(engine, component).with_context(context)
+        }
+        Cow::Owned(componentized) => componentized,
+    };
+    // Release the source module before compiling its componentized form
+    drop(bytes);
+    spin_core::Component::new(engine, componentized).with_context(context)
+}
+
+fn is_http_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Splits an optional `#sha256:<hex>` digest pin from the end of the given URL.
+fn split_digest_pin(url: &str) -> (&str, Option<&str>) {
+    match url.rsplit_once('#') {
+        Some((url, digest)) if digest.starts_with("sha256:") => (url, Some(digest)),
+        _ => (url, None),
+    }
+}
+
+/// Downloads the detached signature for the given URL, returning `None` if
+/// it cannot be downloaded.
+async fn download_signature(url: &str) -> Option<Vec<u8>> {
+    let signature_url = format!("{url}.{}", signature::SIGNATURE_EXTENSION);
+    match download_bytes(&signature_url, None).await {
+        Ok(signature) => Some(signature),
+        Err(err) => {
+            tracing::debug!("No signature downloaded from {signature_url:?}: {err:#}");
+            None
+        }
+    }
+}
+
+/// Reads the detached signature for the given file, returning `None` if it
+/// doesn't exist.
+async fn read_signature(path: &Path) -> Result<Option<Vec<u8>>> {
+    let signature_path = signature_path(path);
+    if !fs::try_exists(&signature_path).await.unwrap_or(false) {
+        return Ok(None);
+    }
+    let signature = fs::read(&signature_path)
+        .await
+        .with_context(|| format!("failed to read signature {}", quoted_path(&signature_path)))?;
+    Ok(Some(signature))
+}
+
+fn verify_digest(path: &Path, expected_digest: &str) -> Result<()> {
+    let actual_digest = format!(
+        "sha256:{}",
+        hex_digest_from_file(path)
+            .with_context(|| format!("failed to read component source at {}", quoted_path(path)))?
+    );
+    ensure!(
+        actual_digest == expected_digest,
+        "invalid component digest for {}; expected {expected_digest}, found {actual_digest}",
+        quoted_path(path),
+    );
+    Ok(())
 }
 
 #[async_trait]
 impl Loader for TriggerLoader {
     async fn load_app(&self, url: &str) -> Result<LockedApp> {
-        let path = parse_file_url(url)?;
-        let contents = std::fs::read(&path)
-            .with_context(|| format!("failed to read manifest at {}", quoted_path(&path)))?;
-        let app =
-            serde_json::from_slice(&contents).context("failed to parse app lock file JSON")?;
+        let (contents, signature) = if is_http_url(url) {
+            let (url, digest) = split_digest_pin(url);
+            let contents = download_bytes(url, digest)
+                .await
+                .with_context(|| format!("failed to download manifest from {url:?}"))?;
+            let signature = match &self.signature_verifier {
+                Some(_) => download_signature(url).await,
+                None => None,
+            };
+            (contents, signature)
+        } else {
+            let path = parse_file_url(url)?;
+            let contents = std::fs::read(&path)
+                .with_context(|| format!("failed to read manifest at {}", quoted_path(&path)))?;
+            let signature = match &self.signature_verifier {
+                Some(_) => read_signature(&path).await?,
+                None => None,
+            };
+            (contents, signature)
+        };
+        if let Some(verifier) = &self.signature_verifier {
+            let verified = verifier.verify(
+                &format!("application {url:?}"),
+                &contents,
+                signature.as_deref(),
+            )?;
+            self.app_signature_verified
+                .store(verified, Ordering::Relaxed);
+        }
+        let app = LockedApp::from_json(&contents).context("failed to parse app lock file")?;
+        if let Some(policy) = &self.admission_policy {
+            policy.admit(&app, self.signature_verifier.as_ref())?;
+        }
+        self.prepare_file_mounts(&app).await?;
         Ok(app)
     }
 
@@ -43,21 +625,24 @@ impl Loader for TriggerLoader {
         engine: &spin_core::wasmtime::Engine,
         source: &LockedComponentSource,
     ) -> Result<spin_core::Component> {
-        let source = source
-            .content
-            .source
-            .as_ref()
-            .context("LockedComponentSource missing source field")?;
-        let path = parse_file_url(source)?;
-        let bytes = fs::read(&path).await.with_context(|| {
-            format!(
-                "failed to read component source from disk at path '{}'",
-                path.display()
-            )
-        })?;
-        let component = spin_componentize::componentize_if_necessary(&bytes)?;
-        spin_core::Component::new(engine, component.as_ref())
-            .with_context(|| format!("loading module {}", quoted_path(&path)))
+        if source.content_type == PRECOMPILED_COMPONENT_CONTENT_TYPE {
+            return self.load_precompiled_component(engine, source).await;
+        }
+        let provided = self.provided_imports(source).await?;
+        if !source.dependencies.is_empty() || !provided.is_empty() {
+            return self.load_composed_component(engine, source, provided).await;
+        }
+        let path = self.component_source_path(source).await?;
+        match &self.compiled_cache {
+            Some(cache) => {
+                self.load_cached_component(cache, engine, source, &path)
+                    .await
+            }
+            None => {
+                let bytes = self.read_component_source(&path).await?;
+                compile_component(engine, bytes, &path).await
+            }
+        }
     }
 
     async fn load_module(
@@ -65,12 +650,7 @@ impl Loader for TriggerLoader {
         engine: &spin_core::wasmtime::Engine,
         source: &LockedComponentSource,
     ) -> Result<spin_core::Module> {
-        let source = source
-            .content
-            .source
-            .as_ref()
-            .context("LockedComponentSource missing source field")?;
-        let path = parse_file_url(source)?;
+        let path = self.component_source_path(source).await?;
         spin_core::Module::from_file(engine, &path)
             .with_context(|| format!("loading module {}", quoted_path(&path)))
     }
@@ -87,18 +667,100 @@ impl Loader for TriggerLoader {
                 .as_deref()
                 .with_context(|| format!("Missing 'source' on files mount {content_dir:?}"))?;
             let source_path = self.working_dir.join(parse_file_url(source_uri)?);
-            ensure!(
-                source_path.is_dir(),
-                "TriggerLoader only supports directory mounts; {} is not a directory",
-                quoted_path(&source_path),
-            );
             let guest_path = content_dir.path.clone();
-            if self.allow_transient_write {
-                store_builder.read_write_preopened_dir(source_path, guest_path)?;
+            let file_mount = self
+                .file_mounts
+                .lock()
+                .unwrap()
+                .get(&(component.id().to_owned(), guest_path.clone()))
+                .cloned();
+            let (host_dir, guest_dir) = if let Some(file_mount) = file_mount {
+                file_mount
+            } else if source_path.is_dir() {
+                (source_path, guest_path)
             } else {
-                store_builder.read_only_preopened_dir(source_path, guest_path)?;
+                bail!(
+                    "TriggerLoader only supports file and directory mounts; {} is neither",
+                    quoted_path(&source_path),
+                );
+            };
+            if self.allow_transient_write || content_dir.writable {
+                store_builder.read_write_preopened_dir(host_dir, guest_dir)?;
+            } else {
+                store_builder.read_only_preopened_dir(host_dir, guest_dir)?;
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_digest_pin_from_url() {
+        assert_eq!(
+            split_digest_pin("https://example.com/spin.lock#sha256:abc"),
+            ("https://example.com/spin.lock", Some("sha256:abc"))
+        );
+        assert_eq!(
+            split_digest_pin("https://example.com/spin.lock"),
+            ("https://example.com/spin.lock", None)
+        );
+        assert_eq!(
+            split_digest_pin("https://example.com/spin.lock#other"),
+            ("https://example.com/spin.lock#other", None)
+        );
+    }
+
+    #[test]
+    fn component_imports_ignore_names_outside_import_sections() {
+        // A module with a custom section named after an interface
+        let interface = b"acme:billing/api@1.0.0";
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        module.extend([0, interface.len() as u8 + 1, interface.len() as u8]);
+        module.extend(interface);
+        assert!(component_imports(&module).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn file_mount_is_replaced_when_source_is() -> Result<()> {
+        let working_dir = tempfile::tempdir()?;
+        let source_dir = tempfile::tempdir()?;
+        let source_path = source_dir.path().join("config.toml");
+        std::fs::write(&source_path, "old")?;
+        let loader = TriggerLoader::new(working_dir.path(), false);
+        let guest_path = Path::new("/etc/config.toml");
+
+        let (host_dir, guest_dir) = loader
+            .prepare_file_mount("component", &source_path, guest_path)
+            .await?;
+        assert_eq!(guest_dir, Path::new("/etc"));
+        assert_eq!(std::fs::read(host_dir.join("config.toml"))?, b"old");
+
+        // As editors save files: write a new file and move it over the old one
+        let new_path = source_dir.path().join("config.toml.new");
+        std::fs::write(&new_path, "newer")?;
+        std::fs::rename(&new_path, &source_path)?;
+        let (host_dir, _) = loader
+            .prepare_file_mount("component", &source_path, guest_path)
+            .await?;
+        assert_eq!(std::fs::read(host_dir.join("config.toml"))?, b"newer");
+        assert_eq!(std::fs::read(&source_path)?, b"newer");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn file_mount_at_root_is_rejected() -> Result<()> {
+        let working_dir = tempfile::tempdir()?;
+        let source_path = working_dir.path().join("config.toml");
+        std::fs::write(&source_path, "config")?;
+        let loader = TriggerLoader::new(working_dir.path(), false);
+        loader
+            .prepare_file_mount("component", &source_path, Path::new("/config.toml"))
+            .await
+            .unwrap_err();
+        Ok(())
+    }
+}
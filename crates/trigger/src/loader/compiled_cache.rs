@@ -0,0 +1,159 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::Write,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path};
+use spin_core::wasmtime::Engine;
+use tokio::fs;
+
+const COMPILED_COMPONENT_EXTENSION: &str = "cwasm";
+
+/// Identifies the build of Spin compiling components. Modules are
+/// componentized before they are compiled, so their compiled form depends on
+/// the version of Spin and of the spin-componentize adapter as well as on
+/// their source.
+const BUILD_ID: &str = concat!(env!("CARGO_PKG_VERSION"), "+", env!("SPIN_COMPONENTIZE_ID"));
+
+/// An on-disk cache of compiled (serialized) components.
+///
+/// Entries are keyed by the component source digest, the build of Spin and a
+/// hash of the engine configuration, so a change to any of them results in a
+/// cache miss.
+#[derive(Debug)]
+pub struct CompiledComponentCache {
+    dir: PathBuf,
+}
+
+impl CompiledComponentCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the cache key for the given engine and component source digest.
+    pub fn key(&self, engine: &Engine, source_digest: &str) -> String {
+        build_key(BUILD_ID, engine, source_digest)
+    }
+
+    /// Returns the cache key for the given engine and component source bytes.
+    pub fn key_for_bytes(&self, engine: &Engine, bytes: &[u8]) -> String {
+        let digest = format!("sha256:{}", hex_digest_from_bytes(bytes));
+        self.key(engine, &digest)
+    }
+
+    /// Loads the compiled component for the given key, if present. Any error
+    /// deserializing a cached entry is logged and treated as a cache miss.
+    pub async fn get(&self, engine: &Engine, key: &str) -> Option<spin_core::Component> {
+        let path = self.entry_path(key);
+        if !fs::try_exists(&path).await.unwrap_or(false) {
+            return None;
+        }
+        // Safety: entries in this cache are only written by `Self::put` from
+        // `Component::serialize` output, and are validated against the engine
+        // by `deserialize_file`.
+        match unsafe { spin_core::Component::deserialize_file(engine, &path) } {
+            Ok(component) => {
+                tracing::debug!("Loaded compiled component from {}", quoted_path(&path));
+                Some(component)
+            }
+            Err(err) => {
+                tracing::warn!(
+                    "Ignoring invalid compiled component cache entry {}: {err:#}",
+                    quoted_path(&path)
+                );
+                None
+            }
+        }
+    }
+
+    /// Stores the given compiled component under the given key.
+    pub async fn put(&self, key: &str, component: &spin_core::Component) -> Result<()> {
+        let bytes = component
+            .serialize()
+            .context("failed to serialize compiled component")?;
+        fs::create_dir_all(&self.dir).await.with_context(|| {
+            format!(
+                "failed to create compiled component cache directory {}",
+                quoted_path(&self.dir)
+            )
+        })?;
+        // Write to a uniquely-named temporary file first so readers never
+        // observe a partial entry, even if several processes store it at once
+        let dir = self.dir.clone();
+        let path = self.entry_path(key);
+        tokio::task::spawn_blocking(move || {
+            let mut temp_file = tempfile::NamedTempFile::new_in(&dir)
+                .with_context(|| format!("failed to create a file in {}", quoted_path(&dir)))?;
+            temp_file
+                .write_all(&bytes)
+                .with_context(|| format!("failed to write {}", quoted_path(temp_file.path())))?;
+            temp_file
+                .persist(&path)
+                .with_context(|| format!("failed to write {}", quoted_path(&path)))?;
+            Ok::<_, anyhow::Error>(())
+        })
+        .await?
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(key)
+            .with_extension(COMPILED_COMPONENT_EXTENSION)
+    }
+}
+
+fn build_key(build_id: &str, engine: &Engine, source_digest: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    build_id.hash(&mut hasher);
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    let build_hash = hasher.finish();
+    // Digests are conventionally prefixed with e.g. "sha256:"
+    let source_digest = source_digest.replace(':', "-");
+    format!("{source_digest}-{build_hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_depends_on_source_digest() {
+        let cache = CompiledComponentCache::new("unused");
+        let engine = Engine::default();
+
+        let key = cache.key(&engine, "sha256:abc");
+        assert_eq!(key, cache.key(&engine, "sha256:abc"));
+        assert_ne!(key, cache.key(&engine, "sha256:def"));
+        assert!(!key.contains(':'), "key {key:?} is not a safe file name");
+    }
+
+    #[test]
+    fn key_depends_on_build() {
+        let engine = Engine::default();
+
+        let key = build_key("2.1.0+componentize-a", &engine, "sha256:abc");
+        assert_ne!(
+            key,
+            build_key("2.1.0+componentize-b", &engine, "sha256:abc")
+        );
+        assert_ne!(
+            key,
+            build_key("2.2.0+componentize-a", &engine, "sha256:abc")
+        );
+    }
+
+    #[test]
+    fn key_for_bytes_matches_digest_key() {
+        let cache = CompiledComponentCache::new("unused");
+        let engine = Engine::default();
+
+        let digest = format!("sha256:{}", hex_digest_from_bytes("spin"));
+        assert_eq!(
+            cache.key(&engine, &digest),
+            cache.key_for_bytes(&engine, b"spin")
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path};
+use wasm_compose::{
+    composer::ComponentComposer,
+    config::{Config, Dependency},
+};
+
+// Working dir subdirectory for componentized inputs to compositions
+const COMPOSE_DIR: &str = "compose";
+
+/// Composes the component at `root` with the given dependencies, each of
+/// which satisfies the root's import of the same name. Modules are
+/// componentized first. Returns the composed component's bytes.
+pub(super) fn compose_blocking(
+    working_dir: &Path,
+    root: &Path,
+    dependencies: &[(String, PathBuf)],
+) -> Result<Vec<u8>> {
+    let compose_dir = working_dir.join(COMPOSE_DIR);
+    std::fs::create_dir_all(&compose_dir).with_context(|| {
+        format!(
+            "failed to create composition directory {}",
+            quoted_path(&compose_dir)
+        )
+    })?;
+
+    let root = componentized_path(&compose_dir, root)?;
+    let mut config = Config {
+        dir: compose_dir.clone(),
+        ..Default::default()
+    };
+    for (import_name, path) in dependencies {
+        let path = componentized_path(&compose_dir, path)?;
+        config
+            .dependencies
+            .insert(import_name.clone(), Dependency { path });
+    }
+
+    ComponentComposer::new(&root, &config)
+        .compose()
+        .with_context(|| format!("failed to compose {}", quoted_path(&root)))
+}
+
+// wasm-compose reads its inputs from files, so modules are componentized into
+// the composition directory; components are used where they are.
+fn componentized_path(compose_dir: &Path, path: &Path) -> Result<PathBuf> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("failed to read component source at {}", quoted_path(path)))?;
+    match spin_componentize::componentize_if_necessary(&bytes)? {
+        Cow::Borrowed(_) => Ok(path.to_owned()),
+        Cow::Owned(componentized) => {
+            let dest = compose_dir.join(format!("{}.wasm", hex_digest_from_bytes(&bytes)));
+            std::fs::write(&dest, componentized)
+                .with_context(|| format!("failed to write {}", quoted_path(&dest)))?;
+            Ok(dest)
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use spin_app::locked::{LockedApp, LockedComponent};
+use spin_common::ui::quoted_path;
+use spin_outbound_networking::{AllowedHostConfig, AllowedHostsConfig};
+
+use super::SignatureVerifier;
+use crate::limits::ComponentLimits;
+
+/// Rules an application must satisfy to be loaded, read from a TOML
+/// admission policy file:
+///
+/// ```toml
+/// # Components must be signed by a trusted key
+/// require_signed = true
+/// # Hosts components may not allow in `allowed_outbound_hosts`: an entry
+/// # which could allow any of the same URLs as one of these is denied
+/// denied_outbound_hosts = ["*://*.internal.acme.com:*", "redis://*:*"]
+/// # Host environment variables components may not pass through with
+/// # `environment_allowlist`, where a name ending in `*` matches a prefix
+/// denied_environment_variables = ["AWS_*", "DATABASE_URL"]
+/// # Components must limit their memory to at most this many bytes
+/// max_memory_size = 268435456
+/// # Component sources must start with one of these
+/// allowed_sources = ["oci://ghcr.io/acme/"]
+/// ```
+///
+/// An app which breaks any rule is refused before any of its components are
+/// loaded, with every broken rule listed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdmissionPolicy {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Requires the app and all its components to be signed. This needs
+    /// signature verification to be enabled, with at least one trusted key.
+    #[serde(default)]
+    pub require_signed: bool,
+    /// Denies `allowed_outbound_hosts` entries which overlap any of these.
+    ///
+    /// Entries are compared as written, without resolving hostnames, so a
+    /// CIDR denial such as `*://10.0.0.0/8:*` only denies entries naming
+    /// addresses in that range. It doesn't deny hostnames which resolve into
+    /// the range, `localhost`, or `self`, so it can't keep components off a
+    /// network on its own; block that at the network layer instead.
+    #[serde(default)]
+    pub denied_outbound_hosts: Vec<String>,
+    #[serde(default)]
+    pub denied_environment_variables: Vec<String>,
+    pub max_memory_size: Option<usize>,
+    pub allowed_sources: Option<Vec<String>>,
+}
+
+impl AdmissionPolicy {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read admission policy {}", quoted_path(path)))?;
+        let mut policy: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse admission policy {}", quoted_path(path)))?;
+        AllowedHostsConfig::parse(&policy.denied_outbound_hosts).with_context(|| {
+            format!(
+                "invalid denied_outbound_hosts in admission policy {}",
+                quoted_path(path)
+            )
+        })?;
+        policy.path = path.to_owned();
+        Ok(policy)
+    }
+
+    /// Checks the app against the policy. Signatures themselves are checked
+    /// as the app and its components are loaded; this checks that they will
+    /// be required.
+    pub fn admit(&self, app: &LockedApp, verifier: Option<&SignatureVerifier>) -> Result<()> {
+        let mut denials = vec![];
+        if self.require_signed && !verifier.is_some_and(SignatureVerifier::requires_signed) {
+            denials.push(
+                "the policy requires signed components, but signature verification is not enabled (use --trusted-key)".to_owned(),
+            );
+        }
+        for component in &app.components {
+            denials.extend(
+                self.component_denials(component)
+                    .into_iter()
+                    .map(|denial| format!("component {:?} {denial}", component.id)),
+            );
+        }
+        if denials.is_empty() {
+            return Ok(());
+        }
+        bail!(
+            "application denied by admission policy {}:\n{}",
+            quoted_path(&self.path),
+            denials
+                .iter()
+                .map(|denial| format!("  - {denial}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+
+    fn component_denials(&self, component: &LockedComponent) -> Vec<String> {
+        let mut denials = vec![];
+
+        let outbound_hosts = component
+            .metadata
+            .get("allowed_outbound_hosts")
+            .and_then(|hosts| serde_json::from_value::<Vec<String>>(hosts.clone()).ok())
+            .unwrap_or_default();
+        let denied_hosts = self
+            .denied_outbound_hosts
+            .iter()
+            .filter_map(|host| AllowedHostConfig::parse(host).ok())
+            .collect::<Vec<_>>();
+        for host in outbound_hosts {
+            let Ok(allowed) = AllowedHostConfig::parse(&host) else {
+                // The component will fail to load anyway
+                continue;
+            };
+            if let Some(denied) = denied_hosts.iter().find(|denied| allowed.overlaps(denied)) {
+                denials.push(format!(
+                    "allows outbound host {host:?}, which overlaps denied host \"{denied}\""
+                ));
+            }
+        }
+
+        let env_allowlist = component
+            .metadata
+            .get("environment_allowlist")
+            .and_then(|names| serde_json::from_value::<Vec<String>>(names.clone()).ok())
+            .unwrap_or_default();
+        for name in env_allowlist {
+            if let Some(denied) = self
+                .denied_environment_variables
+                .iter()
+                .find(|denied| env_patterns_overlap(&name, denied))
+            {
+                denials.push(format!(
+                    "passes through host environment variable {name:?}, which overlaps denied variable {denied:?}"
+                ));
+            }
+        }
+
+        if let Some(cap) = self.max_memory_size {
+            let limits = component
+                .metadata
+                .get("limits")
+                .and_then(|limits| serde_json::from_value::<ComponentLimits>(limits.clone()).ok())
+                .unwrap_or_default();
+            match limits.max_memory_size {
+                None => denials.push(format!(
+                    "has no memory limit, but the policy requires `max_memory_size` of at most {cap} bytes"
+                )),
+                Some(size) if size > cap => denials.push(format!(
+                    "has a memory limit of {size} bytes, which is more than the policy's {cap} bytes"
+                )),
+                Some(_) => (),
+            }
+        }
+
+        if let Some(allowed) = &self.allowed_sources {
+            let sources = std::iter::once(&component.source)
+                .chain(component.source.dependencies.values())
+                .filter_map(|source| source.content.source.as_deref());
+            for source in sources {
+                if !allowed.iter().any(|prefix| source.starts_with(prefix)) {
+                    denials.push(format!(
+                        "has source {source:?}, which is not an allowed source"
+                    ));
+                }
+            }
+        }
+
+        denials
+    }
+}
+
+// Whether two environment variable names, either of which may end in a `*`
+// matching any suffix, could match the same variable.
+fn env_patterns_overlap(a: &str, b: &str) -> bool {
+    match (a.strip_suffix('*'), b.strip_suffix('*')) {
+        (Some(a), Some(b)) => a.starts_with(b) || b.starts_with(a),
+        (Some(prefix), None) => b.starts_with(prefix),
+        (None, Some(prefix)) => a.starts_with(prefix),
+        (None, None) => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn app(component: serde_json::Value) -> LockedApp {
+        let app = json!({
+            "spin_lock_version": 0,
+            "triggers": [],
+            "components": [component],
+        });
+        LockedApp::from_json(&serde_json::to_vec(&app).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn admits_complying_apps() {
+        let policy: AdmissionPolicy = toml::from_str(
+            r#"
+            denied_outbound_hosts = ["*://10.0.0.0/8:*", "redis://*:*"]
+            denied_environment_variables = ["AWS_*"]
+            max_memory_size = 1000
+            allowed_sources = ["oci://ghcr.io/acme/"]
+            "#,
+        )
+        .unwrap();
+        let app = app(json!({
+            "id": "web",
+            "metadata": {
+                "allowed_outbound_hosts": ["https://api.acme.com"],
+                "environment_allowlist": ["OTEL_*"],
+                "limits": { "max_memory_size": 1000 },
+            },
+            "source": {
+                "content_type": "application/wasm",
+                "source": "oci://ghcr.io/acme/web:1.0",
+            },
+        }));
+        policy.admit(&app, None).unwrap();
+    }
+
+    #[test]
+    fn lists_every_denial() {
+        let policy: AdmissionPolicy = toml::from_str(
+            r#"
+            require_signed = true
+            denied_outbound_hosts = ["*://*:*"]
+            denied_environment_variables = ["AWS_SECRET_ACCESS_KEY"]
+            max_memory_size = 1000
+            allowed_sources = ["oci://ghcr.io/acme/"]
+            "#,
+        )
+        .unwrap();
+        let app = app(json!({
+            "id": "web",
+            "metadata": {
+                "allowed_outbound_hosts": ["*://*:*"],
+                "environment_allowlist": ["AWS_*"],
+                "limits": { "max_memory_size": 2000 },
+            },
+            "source": {
+                "content_type": "application/wasm",
+                "source": "file:///tmp/web.wasm",
+            },
+        }));
+        let err = policy.admit(&app, None).unwrap_err().to_string();
+        for expected in [
+            "requires signed components",
+            "allows outbound host \"*://*:*\"",
+            "\"AWS_*\", which overlaps denied variable \"AWS_SECRET_ACCESS_KEY\"",
+            "memory limit of 2000 bytes",
+            "\"file:///tmp/web.wasm\", which is not an allowed source",
+        ] {
+            assert!(err.contains(expected), "{err}");
+        }
+    }
+
+    #[test]
+    fn cidr_denials_only_match_literal_addresses() {
+        let policy: AdmissionPolicy =
+            toml::from_str(r#"denied_outbound_hosts = ["*://10.0.0.0/8:*"]"#).unwrap();
+        let app_allowing = |host: &str| {
+            app(json!({
+                "id": "web",
+                "metadata": { "allowed_outbound_hosts": [host] },
+                "source": {
+                    "content_type": "application/wasm",
+                    "source": "file:///tmp/web.wasm",
+                },
+            }))
+        };
+        policy
+            .admit(&app_allowing("http://10.1.2.3:80"), None)
+            .unwrap_err();
+        for host in [
+            "http://db.internal.acme.com:80",
+            "http://localhost:80",
+            "http://self",
+        ] {
+            policy.admit(&app_allowing(host), None).unwrap();
+        }
+    }
+
+    #[test]
+    fn denies_outbound_hosts_overlapping_denied_hosts() {
+        let policy: AdmissionPolicy =
+            toml::from_str(r#"denied_outbound_hosts = ["*://*:*"]"#).unwrap();
+        for host in [
+            "https://*:*",
+            "*://*:443",
+            "http://*",
+            "https://api.acme.com",
+        ] {
+            let app = app(json!({
+                "id": "web",
+                "metadata": { "allowed_outbound_hosts": [host] },
+                "source": { "content_type": "application/wasm" },
+            }));
+            let err = policy.admit(&app, None).unwrap_err().to_string();
+            assert!(
+                err.contains("overlaps denied host \"*://*:*\""),
+                "{host}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn environment_patterns_overlap() {
+        assert!(env_patterns_overlap("AWS_REGION", "AWS_REGION"));
+        assert!(env_patterns_overlap("AWS_*", "AWS_SECRET_ACCESS_KEY"));
+        assert!(env_patterns_overlap("AWS_SECRET_ACCESS_KEY", "AWS_*"));
+        assert!(env_patterns_overlap("*", "AWS_*"));
+        assert!(env_patterns_overlap("AWS_*", "A*"));
+        assert!(!env_patterns_overlap("AWS_REGION", "AWS_SECRET_ACCESS_KEY"));
+        assert!(!env_patterns_overlap("OTEL_*", "AWS_*"));
+    }
+}
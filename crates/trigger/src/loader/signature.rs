@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use spin_common::ui::quoted_path;
+
+/// File extension of detached signatures, which are stored alongside the
+/// content they sign (e.g. `spin.lock.sig` for `spin.lock`).
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+const ED25519_PUBLIC_KEY_LEN: usize = 32;
+
+/// Verifies detached Ed25519 signatures on locked apps and component sources.
+///
+/// Signature and public key files contain the base64-encoded raw signature or
+/// key bytes.
+#[derive(Debug)]
+pub struct SignatureVerifier {
+    trusted_keys: Vec<Vec<u8>>,
+    require_signed: bool,
+}
+
+impl SignatureVerifier {
+    /// Creates a verifier trusting the public keys in the given files. If
+    /// `require_signed` is set, unsigned content fails verification.
+    pub fn new(trusted_key_paths: &[PathBuf], require_signed: bool) -> Result<Self> {
+        let trusted_keys = trusted_key_paths
+            .iter()
+            .map(|path| read_public_key(path))
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(
+            !(require_signed && trusted_keys.is_empty()),
+            "requiring signed applications needs at least one trusted key"
+        );
+        Ok(Self {
+            trusted_keys,
+            require_signed,
+        })
+    }
+
+    /// Whether unsigned content fails verification.
+    pub fn requires_signed(&self) -> bool {
+        self.require_signed
+    }
+
+    /// Verifies `content` against `signature`, which is `None` if the content
+    /// is unsigned. Returns whether the content was verified; unsigned content
+    /// is an error only if signatures are required.
+    pub fn verify(&self, what: &str, content: &[u8], signature: Option<&[u8]>) -> Result<bool> {
+        let Some(signature) = signature else {
+            if self.require_signed {
+                bail!("{what} is not signed, and signatures are required");
+            }
+            tracing::warn!("{what} is not signed; skipping signature verification");
+            return Ok(false);
+        };
+        let signature = BASE64
+            .decode(trim_ascii(signature))
+            .with_context(|| format!("invalid signature encoding for {what}"))?;
+        let verified = self.trusted_keys.iter().any(|key| {
+            UnparsedPublicKey::new(&ED25519, key)
+                .verify(content, &signature)
+                .is_ok()
+        });
+        ensure!(
+            verified,
+            "{what} signature is not valid for any trusted key"
+        );
+        tracing::debug!("Verified signature for {what}");
+        Ok(true)
+    }
+}
+
+/// Returns the path of the detached signature for the given file.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut sig_path = path.as_os_str().to_owned();
+    sig_path.push(".");
+    sig_path.push(SIGNATURE_EXTENSION);
+    sig_path.into()
+}
+
+fn read_public_key(path: &Path) -> Result<Vec<u8>> {
+    let encoded = std::fs::read(path)
+        .with_context(|| format!("failed to read trusted key {}", quoted_path(path)))?;
+    let key = BASE64
+        .decode(trim_ascii(&encoded))
+        .with_context(|| format!("invalid trusted key encoding in {}", quoted_path(path)))?;
+    ensure!(
+        key.len() == ED25519_PUBLIC_KEY_LEN,
+        "trusted key {} is not an Ed25519 public key",
+        quoted_path(path)
+    );
+    Ok(key)
+}
+
+fn trim_ascii(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |end| end + 1);
+    &bytes[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+
+    use super::*;
+
+    fn verifier_and_key_pair(require_signed: bool) -> (SignatureVerifier, Ed25519KeyPair) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let verifier = SignatureVerifier {
+            trusted_keys: vec![key_pair.public_key().as_ref().to_vec()],
+            require_signed,
+        };
+        (verifier, key_pair)
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        let (verifier, key_pair) = verifier_and_key_pair(false);
+        let signature = BASE64.encode(key_pair.sign(b"content"));
+
+        assert!(verifier
+            .verify("content", b"content", Some(signature.as_bytes()))
+            .unwrap());
+        verifier
+            .verify("content", b"tampered", Some(signature.as_bytes()))
+            .unwrap_err();
+    }
+
+    #[test]
+    fn unsigned_content_fails_only_if_required() {
+        let (verifier, _) = verifier_and_key_pair(false);
+        assert!(!verifier.verify("content", b"content", None).unwrap());
+
+        let (verifier, _) = verifier_and_key_pair(true);
+        verifier.verify("content", b"content", None).unwrap_err();
+    }
+
+    #[test]
+    fn signature_path_appends_extension() {
+        assert_eq!(
+            signature_path(Path::new("app/spin.lock")),
+            PathBuf::from("app/spin.lock.sig")
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use crate::TriggerHooks;
+
+/// Records per-component invocation metrics when each store is dropped.
+pub struct InvocationMetrics;
+
+impl TriggerHooks for InvocationMetrics {
+    fn component_store_builder(
+        &self,
+        component: &spin_app::AppComponent,
+        store_builder: &mut spin_core::StoreBuilder,
+    ) -> anyhow::Result<()> {
+        let component_id = component.id().to_owned();
+        store_builder.on_drop(move |stats| {
+            spin_metrics::record_invocation(
+                &component_id,
+                &spin_metrics::Invocation {
+                    duration: stats.lifetime,
+                    fuel_consumed: stats.fuel_consumed,
+                    memory_consumed: stats.memory_consumed,
+                },
+            );
+        });
+        Ok(())
+    }
+}
+
+/// Logs each invocation's resource usage when its store is dropped.
+pub struct InvocationUsageLog;
+
+impl TriggerHooks for InvocationUsageLog {
+    fn component_store_builder(
+        &self,
+        component: &spin_app::AppComponent,
+        store_builder: &mut spin_core::StoreBuilder,
+    ) -> anyhow::Result<()> {
+        let component_id = component.id().to_owned();
+        store_builder.on_drop(move |stats| {
+            tracing::info!(
+                component_id,
+                duration_ms = stats.lifetime.as_millis() as u64,
+                fuel_consumed = stats.fuel_consumed,
+                memory_consumed = stats.memory_consumed,
+                "Component invocation finished"
+            );
+        });
+        Ok(())
+    }
+}
@@ -1,8 +1,80 @@
-use crate::TriggerHooks;
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-pub struct Network;
+use ipnet::IpNet;
+use spin_outbound_networking::dns::Resolver;
+
+use crate::{runtime_config, RuntimeConfig, TriggerHooks};
+
+/// How often the addresses of allowed hosts are looked up again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Grants each component the socket (`wasi:sockets`) access its
+/// `allowed_outbound_hosts` allow. Only entries which allow any scheme, such
+/// as `*://db.example.com:5432`, allow sockets.
+///
+/// Socket connections are checked against addresses rather than names, so
+/// each instance may only connect to the addresses its allowed hostnames
+/// resolved to when it was prepared. A component can't reach a host which
+/// isn't allowed by connecting to its address directly, or by resolving an
+/// allowed name which has since been pointed elsewhere.
+///
+/// Names are resolved as configured by the runtime config's `[dns]` section,
+/// by a background task which looks each name up when the app is loaded
+/// and every [`REFRESH_INTERVAL`] after, so that preparing an instance never
+/// waits on a lookup. Until a name's first lookup completes, instances may
+/// not open sockets to it.
+#[derive(Default)]
+pub struct Network {
+    addrs: AllowedAddrs,
+    refresher: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for Network {
+    fn drop(&mut self) {
+        if let Some(refresher) = self.refresher.take() {
+            refresher.abort();
+        }
+    }
+}
 
 impl TriggerHooks for Network {
+    fn app_loaded(
+        &mut self,
+        app: &spin_app::App,
+        runtime_config: &RuntimeConfig,
+    ) -> anyhow::Result<()> {
+        let resolver = runtime_config::dns::build_resolver(runtime_config)?;
+        let mut hosts = HashSet::new();
+        for component in app.components() {
+            let allowed = component
+                .get_metadata(spin_outbound_networking::ALLOWED_HOSTS_KEY)?
+                .unwrap_or_default();
+            let allowed = spin_outbound_networking::AllowedHostsConfig::parse(&allowed)?;
+            hosts.extend(socket_hostnames(&allowed));
+        }
+        if hosts.is_empty() {
+            return Ok(());
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("Allowed outbound hosts can't be resolved outside a Tokio runtime, so sockets may only connect to allowed addresses");
+            return Ok(());
+        };
+        let addrs = self.addrs.clone();
+        let hosts = hosts.into_iter().collect::<Vec<_>>();
+        self.refresher = Some(runtime.spawn(async move {
+            loop {
+                addrs.refresh(&resolver, &hosts).await;
+                tokio::time::sleep(REFRESH_INTERVAL).await;
+            }
+        }));
+        Ok(())
+    }
+
     fn component_store_builder(
         &self,
         component: &spin_app::AppComponent,
@@ -20,20 +92,24 @@ impl TriggerHooks for Network {
                 for config in configs {
                     if config.scheme().allows_any() {
                         match config.host() {
-                            spin_outbound_networking::HostConfig::Any => {
-                                store_builder.inherit_limited_network()
-                            }
-                            spin_outbound_networking::HostConfig::ToSelf => {}
+                            spin_outbound_networking::HostConfig::Any => match config.port() {
+                                spin_outbound_networking::PortConfig::Any => {
+                                    store_builder.inherit_limited_network()
+                                }
+                                // Any host, but only on the allowed ports
+                                port => {
+                                    for ip_net in ["0.0.0.0/0", "::/0"] {
+                                        add_ip_net(store_builder, ip_net.parse()?, port);
+                                    }
+                                }
+                            },
+                            spin_outbound_networking::HostConfig::ToSelf
+                            | spin_outbound_networking::HostConfig::AnyComponent => {}
                             spin_outbound_networking::HostConfig::List(hosts) => {
                                 for host in hosts {
-                                    let Ok(ip_net) =
-                                        // Parse the host as an `IpNet` cidr block and if it fails
-                                        // then try parsing again with `/32` appended to the end.
-                                        host.parse().or_else(|_| format!("{host}/32").parse())
-                                    else {
-                                        continue;
-                                    };
-                                    add_ip_net(store_builder, ip_net, config.port());
+                                    for ip_net in self.addrs.nets(host) {
+                                        add_ip_net(store_builder, ip_net, config.port());
+                                    }
                                 }
                             }
                             spin_outbound_networking::HostConfig::Cidr(ip_net) => {
@@ -71,3 +147,111 @@ fn add_ip_net(
         }
     }
 }
+
+// Returns the hostnames which allowed hosts permit sockets to, and which
+// must be resolved to do so.
+fn socket_hostnames(
+    allowed: &spin_outbound_networking::AllowedHostsConfig,
+) -> impl Iterator<Item = String> + '_ {
+    let configs = match allowed {
+        spin_outbound_networking::AllowedHostsConfig::All => &[][..],
+        spin_outbound_networking::AllowedHostsConfig::SpecificHosts(configs) => configs,
+    };
+    configs
+        .iter()
+        .filter(|config| config.scheme().allows_any())
+        .filter_map(|config| match config.host() {
+            spin_outbound_networking::HostConfig::List(hosts) => Some(hosts),
+            _ => None,
+        })
+        .flatten()
+        // Wildcard subdomains can't be resolved, so only allow outbound HTTP
+        .filter(|host| !host.contains('*') && parse_ip(host).is_none())
+        .map(|host| host.to_ascii_lowercase())
+}
+
+fn parse_ip(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+/// The most recently resolved addresses of allowed hostnames, shared with the
+/// task which refreshes them.
+#[derive(Clone, Default)]
+struct AllowedAddrs {
+    resolved: Arc<RwLock<HashMap<String, Vec<IpAddr>>>>,
+}
+
+impl AllowedAddrs {
+    // Returns the networks an allowed host may be reached at: the host itself
+    // if it is an address, or the addresses it last resolved to.
+    fn nets(&self, host: &str) -> Vec<IpNet> {
+        if let Some(ip) = parse_ip(host) {
+            return vec![IpNet::from(ip)];
+        }
+        let resolved = self.resolved.read().unwrap();
+        match resolved.get(&host.to_ascii_lowercase()) {
+            Some(addrs) => addrs.iter().copied().map(IpNet::from).collect(),
+            None => vec![],
+        }
+    }
+
+    // Looks up each of the hosts, keeping a host's previous addresses if its
+    // lookup fails. The table is only locked to store each result.
+    async fn refresh(&self, resolver: &Resolver, hosts: &[String]) {
+        for host in hosts {
+            match resolver.lookup(host).await {
+                Ok(addrs) => {
+                    self.resolved
+                        .write()
+                        .unwrap()
+                        .insert(host.to_owned(), addrs);
+                }
+                Err(err) => {
+                    tracing::warn!("Failed to resolve allowed outbound host {host:?}: {err:#}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn allowed_hosts_are_pinned_to_their_resolved_addresses() {
+        let db: IpAddr = "10.1.2.3".parse().unwrap();
+        let resolver = Resolver::new(spin_outbound_networking::dns::DnsConfig {
+            hosts: [("db.internal".to_owned(), vec![db])].into(),
+            ..Default::default()
+        });
+        let addrs = AllowedAddrs::default();
+        assert_eq!(
+            addrs.nets("192.168.1.10"),
+            ["192.168.1.10/32".parse::<IpNet>().unwrap()]
+        );
+        // Names aren't looked up as instances are prepared
+        assert!(addrs.nets("db.internal").is_empty());
+
+        addrs.refresh(&resolver, &["db.internal".to_owned()]).await;
+        assert_eq!(addrs.nets("DB.internal"), [IpNet::from(db)]);
+    }
+
+    #[test]
+    fn only_hostnames_allowed_for_sockets_are_resolved() {
+        let allowed = spin_outbound_networking::AllowedHostsConfig::parse(&[
+            "*://DB.internal:5432",
+            "*://10.0.0.1:5432",
+            "*://*.example.com:443",
+            "https://api.example.com",
+        ])
+        .unwrap();
+        assert_eq!(
+            socket_hostnames(&allowed).collect::<Vec<_>>(),
+            ["db.internal"]
+        );
+    }
+}
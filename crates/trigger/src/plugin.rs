@@ -0,0 +1,78 @@
+//! The interface between Spin and out-of-tree trigger executors.
+//!
+//! A trigger type `<type>` which Spin doesn't have built in is provided by
+//! a Spin plugin named `trigger-<type>`. `spin up` runs an app whose
+//! triggers are of that type, or which is run with `--trigger <type>`, by
+//! running the plugin's executable with:
+//!
+//! * `SPIN_LOCKED_URL` set to the URL of the app's lock file, and
+//!   `SPIN_WORKING_DIR` to the directory to load the app's files into;
+//! * `SPIN_LOCAL_APP_DIR` set to the app's directory, for local apps;
+//! * [`SPIN_TRIGGER_PROTOCOL_VERSION`] set to the version of this interface
+//!   which Spin speaks, [`PROTOCOL_VERSION`];
+//! * the trigger options passed to `spin up` as arguments.
+//!
+//! To print its trigger options for `spin up --help`, the plugin is run with
+//! only the `--help-args-only` argument.
+//!
+//! A plugin written in Rust implements [`TriggerExecutor`] and calls [`run`]
+//! from `main`, which speaks this interface and loads the app with the same
+//! loader, host components and trigger options as Spin's built-in triggers.
+
+use anyhow::{bail, Result};
+use clap::{Args, Parser};
+use serde::de::DeserializeOwned;
+
+use crate::{cli::TriggerExecutorCommand, TriggerExecutor};
+
+/// The environment variable holding the version of the trigger plugin
+/// interface which Spin speaks.
+pub const SPIN_TRIGGER_PROTOCOL_VERSION: &str = "SPIN_TRIGGER_PROTOCOL_VERSION";
+
+/// The version of the trigger plugin interface described in this module.
+/// Changed only for changes which existing plugins can't work with.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Runs a trigger plugin's executor with the app and trigger options Spin
+/// passed to the plugin.
+pub async fn run<Executor: TriggerExecutor>() -> Result<()>
+where
+    Executor::RunConfig: Args,
+    Executor::TriggerConfig: DeserializeOwned,
+{
+    check_protocol_version(std::env::var(SPIN_TRIGGER_PROTOCOL_VERSION).ok().as_deref())?;
+    TriggerExecutorCommand::<Executor>::parse().run().await
+}
+
+// Plugins may also be run directly, as `spin trigger-<type>`, in which case
+// the tooling is up to whoever runs them
+fn check_protocol_version(version: Option<&str>) -> Result<()> {
+    let Some(version) = version else {
+        return Ok(());
+    };
+    match version.parse::<u32>() {
+        Ok(PROTOCOL_VERSION) => Ok(()),
+        Ok(version) if version > PROTOCOL_VERSION => bail!(
+            "This version of Spin speaks trigger plugin protocol version {version}, but this plugin only supports version {PROTOCOL_VERSION}: upgrade the plugin"
+        ),
+        _ => bail!(
+            "This version of Spin speaks trigger plugin protocol version {version:?}, but this plugin requires version {PROTOCOL_VERSION}: upgrade Spin"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_the_current_protocol_version() {
+        assert!(check_protocol_version(None).is_ok());
+        assert!(check_protocol_version(Some("1")).is_ok());
+
+        let newer = check_protocol_version(Some("2")).unwrap_err();
+        assert!(newer.to_string().contains("upgrade the plugin"), "{newer}");
+        let older = check_protocol_version(Some("0")).unwrap_err();
+        assert!(older.to_string().contains("upgrade Spin"), "{older}");
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use spin_common::ui::quoted_path;
+
+use crate::{runtime_config::RuntimeConfig, TriggerHooks};
+
+/// Samples the guest call stacks of the given components, writing each
+/// invocation's profile to the `profiles` directory of the app state dir when
+/// its store is dropped.
+pub struct GuestProfiling {
+    components: HashSet<String>,
+    dir: Option<PathBuf>,
+    // Distinguishes invocations dropped in the same millisecond
+    sequence: Arc<AtomicU64>,
+}
+
+impl GuestProfiling {
+    pub fn new(components: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            components: components.into_iter().collect(),
+            dir: None,
+            sequence: Default::default(),
+        }
+    }
+}
+
+impl TriggerHooks for GuestProfiling {
+    fn app_loaded(
+        &mut self,
+        app: &spin_app::App,
+        runtime_config: &RuntimeConfig,
+    ) -> anyhow::Result<()> {
+        let component_ids: HashSet<_> = app.components().map(|c| c.id().to_owned()).collect();
+        let mut unknown = self
+            .components
+            .difference(&component_ids)
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            unknown.sort();
+            bail!(
+                "The following component(s) specified in --profile-component do not exist in the application: {}",
+                unknown
+                    .iter()
+                    .map(|id| id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        let Some(state_dir) = runtime_config.state_dir() else {
+            bail!("--profile-component requires an application state directory; set one with --state-dir");
+        };
+        let dir = state_dir.join("profiles");
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create profile dir {}", quoted_path(&dir)))?;
+        println!("Writing guest profiles to {}", quoted_path(dir.join("")));
+        self.dir = Some(dir);
+        Ok(())
+    }
+
+    fn component_store_builder(
+        &self,
+        component: &spin_app::AppComponent,
+        store_builder: &mut spin_core::StoreBuilder,
+    ) -> anyhow::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        if !self.components.contains(component.id()) {
+            return Ok(());
+        }
+        let profile = store_builder.profile();
+        let component_id = component.id().to_owned();
+        let dir = dir.clone();
+        let sequence = self.sequence.clone();
+        store_builder.on_drop(move |_| {
+            // Invocations too short to be sampled have nothing to show
+            if profile.sample_count() == 0 {
+                return;
+            }
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let sequence = sequence.fetch_add(1, Ordering::Relaxed);
+            let path = dir.join(format!("{component_id}-{millis}-{sequence}.folded"));
+            if let Err(err) = std::fs::write(&path, profile.collapsed_stacks()) {
+                tracing::warn!(
+                    "Failed to write guest profile {}: {err}",
+                    quoted_path(&path)
+                );
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+//! Hot reloading of components whose sources change on disk.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use spin_common::ui::quoted_path;
+use tokio::task::JoinHandle;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches component source files (and the locked app file, if any) for
+/// changes, calling `reload` with the ID of each changed component. Reloads
+/// run on the watcher's task, one at a time. The watcher stops when the
+/// returned [`SourceWatcher`] is dropped.
+pub(crate) fn watch_sources<F, Fut>(
+    component_sources: Vec<(String, PathBuf)>,
+    locked_app_path: Option<PathBuf>,
+    reload: F,
+) -> SourceWatcher
+where
+    F: Fn(String) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let task = tokio::spawn(async move {
+        let mut modified_times: HashMap<PathBuf, Option<SystemTime>> = component_sources
+            .iter()
+            .map(|(_, path)| path)
+            .chain(&locked_app_path)
+            .map(|path| (path.clone(), modified_time(path)))
+            .collect();
+
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let mut changed = |path: &PathBuf| {
+                let modified = modified_time(path);
+                let previous = modified_times.insert(path.clone(), modified);
+                previous != Some(modified)
+            };
+
+            for (component_id, path) in &component_sources {
+                if changed(path) {
+                    tracing::info!(
+                        "Source {} changed; reloading component '{component_id}'",
+                        quoted_path(path)
+                    );
+                    reload(component_id.clone()).await;
+                }
+            }
+            if let Some(path) = &locked_app_path {
+                if changed(path) {
+                    terminal::warn!(
+                        "Application {} changed. Only component source changes are reloaded; restart to apply other changes.",
+                        quoted_path(path)
+                    );
+                }
+            }
+        }
+    });
+    SourceWatcher { task }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Returned by [`watch_sources`]; stops the watcher when dropped.
+pub(crate) struct SourceWatcher {
+    task: JoinHandle<()>,
+}
+
+impl Drop for SourceWatcher {
+    fn drop(&mut self) {
+        self.task.abort()
+    }
+}
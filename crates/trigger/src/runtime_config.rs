@@ -1,5 +1,14 @@
+pub mod blobstore;
+pub mod component_limits;
+pub mod dns;
+pub mod host_component;
 pub mod key_value;
 pub mod llm;
+pub mod lock;
+pub mod otel;
+pub mod outbound_http;
+pub mod outbound_mysql;
+pub mod pooling_allocator;
 pub mod sqlite;
 pub mod variables_provider;
 
@@ -10,16 +19,26 @@ use std::{
     sync::Arc,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use serde::Deserialize;
 use spin_common::ui::quoted_path;
+use spin_host_provider::ProviderAddress;
 use spin_sqlite::Connection;
 
 use self::{
+    blobstore::{BlobStore, BlobStoreOpts},
+    component_limits::ComponentLimitsOpts,
+    dns::DnsOpts,
+    host_component::{HostComponentProvider, HostComponentProviderOpts},
     key_value::{KeyValueStore, KeyValueStoreOpts},
-    llm::LlmComputeOpts,
+    llm::{LlmComputeOpts, LlmEmbeddingCacheOpts},
+    lock::LockStoreOpts,
+    otel::OtelOpts,
+    outbound_http::OutboundHttpOpts,
+    outbound_mysql::OutboundMysqlOpts,
+    pooling_allocator::PoolingAllocatorOpts,
     sqlite::SqliteDatabaseOpts,
-    variables_provider::{VariablesProvider, VariablesProviderOpts},
+    variables_provider::{VariablesOpts, VariablesProvider, VariablesProviderOpts},
 };
 
 pub const DEFAULT_STATE_DIR: &str = ".spin";
@@ -55,6 +74,11 @@ impl RuntimeConfig {
         let mut opts: RuntimeConfigOpts = toml::from_slice(&bytes).with_context(|| {
             format!("Failed to parse runtime config file {}", quoted_path(&path))
         })?;
+        for provider in &opts.variables_providers {
+            provider
+                .validate()
+                .with_context(|| format!("Invalid variables provider in {}", quoted_path(&path)))?;
+        }
         opts.file_path = Some(path);
         self.files.push(opts);
         Ok(())
@@ -74,12 +98,13 @@ impl RuntimeConfig {
 
     /// Return an iterator of named configured [`KeyValueStore`]s.
     pub fn key_value_stores(&self) -> Result<impl IntoIterator<Item = (String, KeyValueStore)>> {
+        let resolver = dns::build_resolver(self)?;
         let mut stores = HashMap::new();
         // Insert explicitly-configured stores
         for opts in self.opts_layers() {
             for (name, store) in &opts.key_value_stores {
                 if !stores.contains_key(name) {
-                    let store = store.build_store(opts)?;
+                    let store = store.build_store(opts, &resolver)?;
                     stores.insert(name.to_owned(), store);
                 }
             }
@@ -87,12 +112,47 @@ impl RuntimeConfig {
         // Upsert default store
         if !stores.contains_key("default") {
             let store = KeyValueStoreOpts::default_store_opts(self)
+                .build_store(&RuntimeConfigOpts::default(), &resolver)?;
+            stores.insert("default".into(), store);
+        }
+        Ok(stores.into_iter())
+    }
+
+    /// Return an iterator of named configured [`BlobStore`]s.
+    pub fn blob_stores(&self) -> Result<impl IntoIterator<Item = (String, BlobStore)>> {
+        let mut stores = HashMap::new();
+        // Insert explicitly-configured stores
+        for opts in self.opts_layers() {
+            for (name, store) in &opts.blob_stores {
+                if !stores.contains_key(name) {
+                    let store = store.build_store(opts)?;
+                    stores.insert(name.to_owned(), store);
+                }
+            }
+        }
+        // Upsert default store
+        if !stores.contains_key("default") {
+            let store = BlobStoreOpts::default_store_opts(self)
                 .build_store(&RuntimeConfigOpts::default())?;
             stores.insert("default".into(), store);
         }
         Ok(stores.into_iter())
     }
 
+    /// Return the [`LockManager`](spin_lock::LockManager) holding locks
+    /// shared between instances of the app.
+    pub fn lock_manager(&self) -> Result<Arc<dyn spin_lock::LockManager>> {
+        let resolver = dns::build_resolver(self)?;
+        match self
+            .opts_layers()
+            .find_map(|opts| Some((opts.lock_store.as_ref()?, opts)))
+        {
+            Some((store, opts)) => store.build_manager(opts, &resolver),
+            None => LockStoreOpts::default_store_opts(self)
+                .build_manager(&RuntimeConfigOpts::default(), &resolver),
+        }
+    }
+
     // Return the "default" key value store config.
     fn default_key_value_opts(&self) -> KeyValueStoreOpts {
         self.opts_layers()
@@ -131,6 +191,19 @@ impl RuntimeConfig {
         Ok(databases.into_iter())
     }
 
+    /// Return the configured migrations of each SQLite database.
+    pub fn sqlite_migrations(&self) -> Result<HashMap<String, Vec<PathBuf>>> {
+        let mut migrations = HashMap::new();
+        for opts in self.opts_layers() {
+            for (name, database) in &opts.sqlite_databases {
+                if !migrations.contains_key(name) {
+                    migrations.insert(name.to_owned(), database.migrations(opts)?);
+                }
+            }
+        }
+        Ok(migrations)
+    }
+
     /// Set the state dir, overriding any other runtime config source.
     pub fn set_state_dir(&mut self, state_dir: impl Into<String>) {
         self.overrides.state_dir = Some(state_dir.into());
@@ -183,6 +256,109 @@ impl RuntimeConfig {
         }
     }
 
+    pub fn llm_embedding_cache(&self) -> Option<&LlmEmbeddingCacheOpts> {
+        self.find_opt(|opts| &opts.llm_embedding_cache)
+    }
+
+    pub fn dns_opts(&self) -> DnsOpts {
+        self.find_opt(|opts| &opts.dns).cloned().unwrap_or_default()
+    }
+
+    pub fn outbound_http_opts(&self) -> OutboundHttpOpts {
+        self.find_opt(|opts| &opts.outbound_http)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn outbound_mysql_opts(&self) -> OutboundMysqlOpts {
+        self.find_opt(|opts| &opts.outbound_mysql)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn pooling_allocator_opts(&self) -> Option<&PoolingAllocatorOpts> {
+        self.find_opt(|opts| &opts.pooling_allocator)
+    }
+
+    /// Return the configured per-component limits, keyed by component ID.
+    pub fn component_limits(&self) -> HashMap<&str, &ComponentLimitsOpts> {
+        let mut limits = HashMap::new();
+        for opts in self.opts_layers() {
+            for (id, component_limits) in &opts.component_limits {
+                limits.entry(id.as_str()).or_insert(component_limits);
+            }
+        }
+        limits
+    }
+
+    /// Return the configured host component providers. An interface may be
+    /// provided by only one provider.
+    pub fn host_component_providers(&self) -> Result<Vec<HostComponentProvider>> {
+        let mut providers: Vec<HostComponentProvider> = vec![];
+        for opts in self.opts_layers() {
+            for (name, provider) in &opts.host_component_providers {
+                if providers.iter().any(|p| &p.name == name) {
+                    continue;
+                }
+                ensure!(
+                    provider.source.is_some() || provider.address.is_some(),
+                    "host component provider {name:?} needs a `source`, an `address`, or both"
+                );
+                ensure!(
+                    provider.source.is_some() == !provider.interfaces.is_empty(),
+                    "host component provider {name:?} needs both a `source` and the `interfaces` it provides, or neither"
+                );
+                let source = provider
+                    .source
+                    .as_ref()
+                    .map(|source| resolve_config_path(source, opts))
+                    .transpose()?;
+                let address = match &provider.address {
+                    Some(address) => match ProviderAddress::parse(address)? {
+                        ProviderAddress::Unix(path) => {
+                            Some(ProviderAddress::Unix(resolve_config_path(&path, opts)?))
+                        }
+                        address => Some(address),
+                    },
+                    None => None,
+                };
+                providers.push(HostComponentProvider {
+                    name: name.to_owned(),
+                    source,
+                    interfaces: provider.interfaces.clone(),
+                    address,
+                    components: provider.components.clone(),
+                });
+            }
+        }
+        for (index, provider) in providers.iter().enumerate() {
+            for other in &providers[index + 1..] {
+                if let Some(interface) = provider
+                    .interfaces
+                    .iter()
+                    .find(|interface| other.interfaces.contains(interface))
+                {
+                    bail!(
+                        "host component providers {:?} and {:?} both provide {interface:?}",
+                        provider.name,
+                        other.name
+                    );
+                }
+            }
+        }
+        Ok(providers)
+    }
+
+    pub fn otel_opts(&self) -> Option<&OtelOpts> {
+        self.find_opt(|opts| &opts.otel)
+    }
+
+    pub fn variables_opts(&self) -> VariablesOpts {
+        self.find_opt(|opts| &opts.variables)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Returns an iterator of RuntimeConfigOpts in order of decreasing precedence
     fn opts_layers(&self) -> impl Iterator<Item = &RuntimeConfigOpts> {
         std::iter::once(&self.overrides).chain(self.files.iter().rev())
@@ -206,15 +382,48 @@ pub struct RuntimeConfigOpts {
     #[serde(default)]
     pub llm_compute: Option<LlmComputeOpts>,
 
+    #[serde(default)]
+    pub llm_embedding_cache: Option<LlmEmbeddingCacheOpts>,
+
+    #[serde(default)]
+    pub dns: Option<DnsOpts>,
+
+    #[serde(default)]
+    pub outbound_http: Option<OutboundHttpOpts>,
+
+    #[serde(default)]
+    pub outbound_mysql: Option<OutboundMysqlOpts>,
+
+    #[serde(default)]
+    pub pooling_allocator: Option<PoolingAllocatorOpts>,
+
+    #[serde(default)]
+    pub otel: Option<OtelOpts>,
+
+    #[serde(default)]
+    pub variables: Option<VariablesOpts>,
+
     #[serde(rename = "variables_provider", alias = "config_provider", default)]
     pub variables_providers: Vec<VariablesProviderOpts>,
 
     #[serde(rename = "key_value_store", default)]
     pub key_value_stores: HashMap<String, KeyValueStoreOpts>,
 
+    #[serde(rename = "blob_store", default)]
+    pub blob_stores: HashMap<String, BlobStoreOpts>,
+
+    #[serde(default)]
+    pub lock_store: Option<LockStoreOpts>,
+
     #[serde(rename = "sqlite_database", default)]
     pub sqlite_databases: HashMap<String, SqliteDatabaseOpts>,
 
+    #[serde(default)]
+    pub component_limits: HashMap<String, ComponentLimitsOpts>,
+
+    #[serde(rename = "host_component_provider", default)]
+    pub host_component_providers: HashMap<String, HostComponentProviderOpts>,
+
     #[serde(skip)]
     pub file_path: Option<PathBuf>,
 }
@@ -407,6 +616,474 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn redis_key_value_store_options_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [key_value_store.default]
+                type = "redis"
+                url = "redis://127.0.0.1/"
+                pool_size = 4
+                ttl_secs = 60
+                key_prefix = "myapp"
+                namespace_by_component = true
+            },
+        );
+
+        let KeyValueStoreOpts::Redis(opts) = config.default_key_value_opts() else {
+            panic!("expected default Redis store");
+        };
+        assert_eq!(opts.pool_size, Some(4));
+        assert_eq!(opts.ttl_secs, Some(60));
+        assert_eq!(opts.key_prefix.as_deref(), Some("myapp"));
+        assert!(opts.namespace_by_component);
+
+        Ok(())
+    }
+
+    #[test]
+    fn aws_dynamo_key_value_store_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [key_value_store.default]
+                type = "aws_dynamo"
+                table = "spin-kv"
+                region = "eu-west-1"
+                consistent_read = true
+                namespace_by_component = true
+            },
+        );
+        assert_eq!(config.key_value_stores().unwrap().into_iter().count(), 1);
+
+        let KeyValueStoreOpts::AwsDynamo(opts) = config.default_key_value_opts() else {
+            panic!("expected default DynamoDB store");
+        };
+        assert_eq!(opts.table, "spin-kv");
+        assert_eq!(opts.region.as_deref(), Some("eu-west-1"));
+        assert!(opts.access_key.is_none());
+        assert!(opts.consistent_read);
+        assert!(opts.namespace_by_component);
+
+        Ok(())
+    }
+
+    #[test]
+    fn blob_stores_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert_eq!(config.blob_stores().unwrap().into_iter().count(), 1);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [blob_store.default]
+                type = "spin"
+                path = "blobs"
+
+                [blob_store.assets]
+                type = "s3"
+                bucket = "my-assets"
+                region = "us-east-1"
+
+                [blob_store.archive]
+                type = "gcs"
+                bucket = "my-archive"
+                access_key = "GOOG1EXAMPLE"
+                secret_key = "secret"
+
+                [blob_store.uploads]
+                type = "azure_blob"
+                account = "myaccount"
+                container = "uploads"
+            },
+        );
+        let mut names: Vec<_> = config
+            .blob_stores()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["archive", "assets", "default", "uploads"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lock_store_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        config.lock_manager()?;
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [lock_store]
+                type = "redis"
+                url = "redis://127.0.0.1/"
+                key_prefix = "myapp-locks"
+            },
+        );
+        let Some(LockStoreOpts::Redis(opts)) = config.find_opt(|opts| &opts.lock_store) else {
+            panic!("expected Redis lock store");
+        };
+        assert_eq!(opts.key_prefix.as_deref(), Some("myapp-locks"));
+        config.lock_manager()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn llm_embedding_cache_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.llm_embedding_cache().is_none());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [llm_embedding_cache]
+            },
+        );
+        assert_eq!(config.llm_embedding_cache().unwrap().store, "default");
+
+        Ok(())
+    }
+
+    #[test]
+    fn outbound_http_opts_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.outbound_http_opts().pool_max_idle_per_host.is_none());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [outbound_http]
+                pool_max_idle_per_host = 8
+                pool_idle_timeout_secs = 30
+            },
+        );
+        let opts = config.outbound_http_opts();
+        assert_eq!(opts.pool_max_idle_per_host, Some(8));
+        assert_eq!(opts.pool_idle_timeout_secs, Some(30));
+
+        Ok(())
+    }
+
+    #[test]
+    fn outbound_http_tls_opts_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [[outbound_http.tls]]
+                hosts = ["https://*.internal.example.com"]
+                ca_certs = ["certs/ca.pem"]
+                client_cert = "certs/client.pem"
+                client_key = "certs/client.key"
+            },
+        );
+        let opts = config.outbound_http_opts();
+        assert_eq!(opts.tls.len(), 1);
+        assert_eq!(opts.tls[0].hosts, ["https://*.internal.example.com"]);
+        assert_eq!(opts.tls[0].ca_certs, [PathBuf::from("certs/ca.pem")]);
+        assert_eq!(
+            opts.tls[0].client_key.as_deref(),
+            Some(Path::new("certs/client.key"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn dns_opts_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(!dns::build_resolver(&config)?.is_custom());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [dns]
+                servers = ["10.0.0.53", "10.0.0.54:5353"]
+                cache_ttl_secs = 0
+
+                [dns.hosts]
+                db = ["10.1.2.3"]
+            },
+        );
+        let opts = config.dns_opts();
+        assert_eq!(opts.servers, ["10.0.0.53", "10.0.0.54:5353"]);
+        assert_eq!(opts.cache_ttl_secs, Some(0));
+        assert_eq!(opts.hosts["db"], ["10.1.2.3".parse::<std::net::IpAddr>()?]);
+        assert!(dns::build_resolver(&config)?.is_custom());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [dns]
+                servers = ["dns.internal"]
+            },
+        );
+        assert!(dns::build_resolver(&config).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn outbound_mysql_opts_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.outbound_mysql_opts().statement_cache_size.is_none());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [outbound_mysql]
+                statement_cache_size = 64
+            },
+        );
+        assert_eq!(config.outbound_mysql_opts().statement_cache_size, Some(64));
+
+        Ok(())
+    }
+
+    #[test]
+    fn otel_opts_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.otel_opts().is_none());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [otel]
+                endpoint = "http://localhost:4318"
+                protocol = "http/protobuf"
+                headers = { authorization = "Bearer token" }
+            },
+        );
+        let otel = config.otel_opts().unwrap();
+        assert_eq!(otel.endpoint, "http://localhost:4318");
+        assert_eq!(otel.protocol, spin_telemetry::OtlpProtocol::HttpProtobuf);
+        assert_eq!(otel.headers["authorization"], "Bearer token");
+        assert!(otel.service_name.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn variables_opts_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.variables_opts().refresh_interval_secs.is_none());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [variables]
+                refresh_interval_secs = 30
+            },
+        );
+        assert_eq!(config.variables_opts().refresh_interval_secs, Some(30));
+
+        Ok(())
+    }
+
+    #[test]
+    fn component_limits_from_files() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.component_limits().is_empty());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [component_limits.first]
+                max_fuel = 1000
+                max_memory_size = 65536
+
+                [component_limits.second]
+                max_memory_size = 131072
+            },
+        );
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [component_limits.first]
+                max_fuel = 500
+            },
+        );
+        let limits = config.component_limits();
+        assert_eq!(limits["first"].max_fuel, Some(500));
+        assert_eq!(limits["first"].max_memory_size, None);
+        assert_eq!(limits["second"].max_memory_size, Some(131072));
+
+        Ok(())
+    }
+
+    #[test]
+    fn host_component_providers_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [host_component_provider.billing]
+                source = "/opt/acme/billing.wasm"
+                interfaces = ["acme:billing/api@1.0.0"]
+            },
+        );
+        let providers = config.host_component_providers()?;
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].name, "billing");
+        assert_eq!(
+            providers[0].source.as_deref(),
+            Some(Path::new("/opt/acme/billing.wasm"))
+        );
+        assert_eq!(providers[0].address, None);
+        assert_eq!(providers[0].interfaces, ["acme:billing/api@1.0.0"]);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [host_component_provider.billing_next]
+                source = "/opt/acme/billing-next.wasm"
+                interfaces = ["acme:billing/api@1.0.0"]
+            },
+        );
+        assert!(config.host_component_providers().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn host_providers_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [host_component_provider.billing]
+                address = "unix:/run/acme/billing.sock"
+                components = ["checkout"]
+            },
+        );
+        let providers = config.host_component_providers()?;
+        assert_eq!(providers[0].source, None);
+        assert_eq!(
+            providers[0].address,
+            Some(ProviderAddress::Unix("/run/acme/billing.sock".into()))
+        );
+        assert_eq!(
+            providers[0].components.as_deref(),
+            Some(&["checkout".to_owned()][..])
+        );
+
+        for invalid in [
+            toml! {
+                [host_component_provider.none]
+                components = ["checkout"]
+            },
+            toml! {
+                [host_component_provider.no_interfaces]
+                source = "/opt/acme/billing.wasm"
+            },
+            toml! {
+                [host_component_provider.no_source]
+                address = "127.0.0.1:7000"
+                interfaces = ["acme:billing/api@1.0.0"]
+            },
+        ] {
+            let mut config = RuntimeConfig::new(None);
+            merge_config_toml(&mut config, invalid);
+            assert!(config.host_component_providers().is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn vault_approle_variables_provider_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [[variables_provider]]
+                type = "vault"
+                url = "http://vault"
+                mount = "root"
+                cache_ttl_secs = 60
+
+                [variables_provider.approle]
+                role_id = "role"
+                secret_id = "secret"
+            },
+        );
+        assert_eq!(config.variables_providers().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cloud_variables_providers_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [[variables_provider]]
+                type = "aws_secrets_manager"
+                region = "us-east-1"
+                prefix = "my-app/"
+
+                [[variables_provider]]
+                type = "gcp_secret_manager"
+
+                [[variables_provider]]
+                type = "azure_key_vault"
+                vault_url = "https://my-vault.vault.azure.net"
+            },
+        );
+        assert_eq!(config.variables_providers().len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn vault_variables_provider_requires_one_auth_method() {
+        let data = toml::to_vec(&toml! {
+            [[variables_provider]]
+            type = "vault"
+            url = "http://vault"
+            mount = "root"
+        })
+        .unwrap();
+        let mut file = NamedTempFile::new().expect("temp file");
+        file.write_all(&data).expect("write toml");
+        RuntimeConfig::new(None)
+            .merge_config_file(file.path())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn pooling_allocator_from_file() -> Result<()> {
+        let mut config = RuntimeConfig::new(None);
+        assert!(config.pooling_allocator_opts().is_none());
+
+        merge_config_toml(
+            &mut config,
+            toml! {
+                [pooling_allocator]
+                max_instances = 10000
+                memory_pages = 1024
+            },
+        );
+        let opts = config.pooling_allocator_opts().unwrap();
+        assert!(opts.enabled);
+        assert_eq!(opts.max_instances, Some(10000));
+        assert_eq!(opts.memory_pages, Some(1024));
+        assert_eq!(opts.table_elements, None);
+
+        Ok(())
+    }
+
     fn merge_config_toml(config: &mut RuntimeConfig, value: toml::Value) {
         let data = toml::to_vec(&value).expect("encode toml");
         let mut file = NamedTempFile::new().expect("temp file");
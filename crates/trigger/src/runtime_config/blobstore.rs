@@ -0,0 +1,169 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::runtime_config::RuntimeConfig;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use spin_blobstore::{BlobStoreComponent, Container, MemoryContainer};
+use spin_blobstore_azure::{BlobStoreAzure, BlobStoreAzureOptions};
+use spin_blobstore_fs::BlobStoreFs;
+use spin_blobstore_s3::{BlobStoreS3, BlobStoreS3Options, S3Credentials};
+
+use super::{resolve_config_path, RuntimeConfigOpts};
+
+const DEFAULT_SPIN_BLOB_STORE_DIRNAME: &str = "blobs";
+
+pub type BlobStore = Arc<dyn Container>;
+
+/// Builds a [`BlobStoreComponent`] from the given [`RuntimeConfig`].
+pub fn build_component(runtime_config: &RuntimeConfig) -> Result<BlobStoreComponent> {
+    Ok(BlobStoreComponent::new(runtime_config.blob_stores()?))
+}
+
+// Holds deserialized options from a `[blob_store.<name>]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum BlobStoreOpts {
+    Spin(SpinBlobStoreOpts),
+    S3(S3BlobStoreOpts),
+    Gcs(GcsBlobStoreOpts),
+    AzureBlob(AzureBlobStoreOpts),
+}
+
+impl BlobStoreOpts {
+    pub fn default_store_opts(runtime_config: &RuntimeConfig) -> Self {
+        Self::Spin(SpinBlobStoreOpts::default_store_opts(runtime_config))
+    }
+
+    pub fn build_store(&self, config_opts: &RuntimeConfigOpts) -> Result<BlobStore> {
+        match self {
+            Self::Spin(opts) => opts.build_store(config_opts),
+            Self::S3(opts) => opts.build_store(),
+            Self::Gcs(opts) => opts.build_store(),
+            Self::AzureBlob(opts) => opts.build_store(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpinBlobStoreOpts {
+    /// The directory holding the store's objects. If not set, objects are
+    /// held in memory.
+    pub path: Option<PathBuf>,
+}
+
+impl SpinBlobStoreOpts {
+    fn default_store_opts(runtime_config: &RuntimeConfig) -> Self {
+        // If the state dir is set, build the default path
+        let path = runtime_config
+            .state_dir()
+            .map(|dir| dir.join(DEFAULT_SPIN_BLOB_STORE_DIRNAME));
+        Self { path }
+    }
+
+    fn build_store(&self, config_opts: &RuntimeConfigOpts) -> Result<BlobStore> {
+        match self.path.as_ref() {
+            Some(path) => {
+                let path = resolve_config_path(path, config_opts)?;
+                Ok(Arc::new(BlobStoreFs::new(path)))
+            }
+            None => Ok(Arc::new(MemoryContainer::new())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3BlobStoreOpts {
+    pub bucket: String,
+    /// The bucket's region. If not set, it is discovered from the environment.
+    pub region: Option<String>,
+    /// The URL of an S3-compatible service to use instead of AWS.
+    pub endpoint: Option<String>,
+    /// Access key ID. If not set, credentials are discovered from the
+    /// environment, AWS profiles or instance metadata.
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub token: Option<String>,
+    /// Whether to address the bucket in the URL path rather than the host name.
+    #[serde(default)]
+    pub force_path_style: bool,
+    /// Prefix for the names of all objects in the store.
+    pub prefix: Option<String>,
+}
+
+impl S3BlobStoreOpts {
+    fn build_store(&self) -> Result<BlobStore> {
+        let credentials = match (&self.access_key, &self.secret_key) {
+            (Some(access_key), Some(secret_key)) => Some(S3Credentials {
+                access_key_id: access_key.clone(),
+                secret_access_key: secret_key.clone(),
+                session_token: self.token.clone(),
+            }),
+            (None, None) if self.token.is_none() => None,
+            _ => bail!("S3 blob store needs both access_key and secret_key, or neither"),
+        };
+        let options = BlobStoreS3Options {
+            region: self.region.clone(),
+            endpoint: self.endpoint.clone(),
+            credentials,
+            force_path_style: self.force_path_style,
+            prefix: self.prefix.clone(),
+        };
+        Ok(Arc::new(BlobStoreS3::new(self.bucket.clone(), options)))
+    }
+}
+
+/// A Google Cloud Storage bucket, accessed through its S3-compatible API
+/// with an HMAC key.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GcsBlobStoreOpts {
+    pub bucket: String,
+    /// The HMAC key's access ID.
+    pub access_key: String,
+    /// The HMAC key's secret.
+    pub secret_key: String,
+    /// Prefix for the names of all objects in the store.
+    pub prefix: Option<String>,
+}
+
+impl GcsBlobStoreOpts {
+    fn build_store(&self) -> Result<BlobStore> {
+        let options = BlobStoreS3Options {
+            prefix: self.prefix.clone(),
+            ..BlobStoreS3Options::gcs(S3Credentials {
+                access_key_id: self.access_key.clone(),
+                secret_access_key: self.secret_key.clone(),
+                session_token: None,
+            })
+        };
+        Ok(Arc::new(BlobStoreS3::new(self.bucket.clone(), options)))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AzureBlobStoreOpts {
+    pub account: String,
+    pub container: String,
+    /// The account's access key. If not set, credentials are discovered from
+    /// the environment, a managed identity or the Azure CLI.
+    pub key: Option<String>,
+    /// Prefix for the names of all objects in the store.
+    pub prefix: Option<String>,
+}
+
+impl AzureBlobStoreOpts {
+    fn build_store(&self) -> Result<BlobStore> {
+        let options = BlobStoreAzureOptions {
+            key: self.key.clone(),
+            prefix: self.prefix.clone(),
+        };
+        Ok(Arc::new(BlobStoreAzure::new(
+            self.account.clone(),
+            self.container.clone(),
+            options,
+        )))
+    }
+}
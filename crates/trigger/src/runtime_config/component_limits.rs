@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::RuntimeConfig;
+
+/// Enables fuel metering if any `[component_limits.<component-id>]` runtime
+/// config section sets a fuel limit.
+pub(crate) fn update_config(runtime_config: &RuntimeConfig, config: &mut spin_core::Config) {
+    let limits = runtime_config.component_limits();
+    if limits.values().any(|opts| opts.max_fuel.is_some()) {
+        config.consume_fuel();
+    }
+}
+
+// Holds deserialized options from a `[component_limits.<component-id>]`
+// runtime config section.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentLimitsOpts {
+    /// Maximum size in bytes of any of the component's linear memories.
+    pub max_memory_size: Option<usize>,
+    /// Maximum fuel a single invocation of the component may consume.
+    pub max_fuel: Option<u64>,
+}
@@ -0,0 +1,64 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use spin_outbound_networking::dns::{DnsConfig, Resolver, DEFAULT_CACHE_TTL};
+
+use crate::RuntimeConfig;
+
+const DEFAULT_DNS_PORT: u16 = 53;
+
+/// Builds the [`Resolver`] for outbound connections from the given
+/// [`RuntimeConfig`].
+pub(crate) fn build_resolver(runtime_config: &RuntimeConfig) -> Result<Resolver> {
+    let opts = runtime_config.dns_opts();
+    let servers = opts
+        .servers
+        .iter()
+        .map(|server| {
+            server
+                .parse::<SocketAddr>()
+                .or_else(|_| {
+                    server
+                        .parse::<IpAddr>()
+                        .map(|ip| SocketAddr::new(ip, DEFAULT_DNS_PORT))
+                })
+                .with_context(|| {
+                    format!(
+                        "invalid dns server {server:?}: expected an IP address and optional port"
+                    )
+                })
+        })
+        .collect::<Result<_>>()?;
+    Ok(Resolver::new(DnsConfig {
+        servers,
+        hosts: opts.hosts,
+        cache_ttl: opts
+            .cache_ttl_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CACHE_TTL),
+    }))
+}
+
+// Holds deserialized options from a `[dns]` runtime config section. These
+// apply to outbound HTTP (including `wasi:http`), Redis, PostgreSQL and MySQL
+// connections, to Redis key-value and lock stores, to the Redis and queue
+// triggers and the HTTP response cache, and to socket permissions.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DnsOpts {
+    /// DNS servers to query instead of the system's resolver, as IP
+    /// addresses with an optional port (53 by default).
+    #[serde(default)]
+    pub servers: Vec<String>,
+    /// Addresses to use for particular hostnames without querying DNS.
+    #[serde(default)]
+    pub hosts: HashMap<String, Vec<IpAddr>>,
+    /// How long, in seconds, resolved addresses are reused. Zero disables
+    /// caching.
+    pub cache_ttl_secs: Option<u64>,
+}
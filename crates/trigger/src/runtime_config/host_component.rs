@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Deserialize;
+use spin_host_provider::{HostProvider, HostProviderComponent, ProviderAddress};
+
+use crate::runtime_config::RuntimeConfig;
+
+/// Builds a [`HostProviderComponent`] for the host providers in the given
+/// [`RuntimeConfig`] which have an address.
+pub fn build_component(runtime_config: &RuntimeConfig) -> Result<HostProviderComponent> {
+    let providers = runtime_config
+        .host_component_providers()?
+        .into_iter()
+        .filter_map(|provider| {
+            let address = provider.address?;
+            Some((
+                provider.name,
+                HostProvider::new(address, provider.components),
+            ))
+        });
+    Ok(HostProviderComponent::new(providers))
+}
+
+/// Provides APIs, such as proprietary host APIs, to app components.
+///
+/// A provider with an `address` is a process run by the platform alongside
+/// Spin, which components call through the `fermyon:spin/host-provider`
+/// interface. It runs outside the components' sandboxes, so it can hold
+/// credentials and reach hosts the components can't; `components` limits
+/// which components may call it.
+///
+/// A provider with a `source` is a Wasm component exporting the provider's
+/// `interfaces`. Components importing a provided interface are composed with
+/// the provider's component when they are loaded, so it runs in the importing
+/// component's sandbox and with its permissions. It may implement the
+/// interfaces itself, or on top of calls to the provider's process.
+#[derive(Clone, Debug)]
+pub struct HostComponentProvider {
+    pub name: String,
+    pub source: Option<PathBuf>,
+    pub interfaces: Vec<String>,
+    pub address: Option<ProviderAddress>,
+    pub components: Option<Vec<String>>,
+}
+
+// Holds deserialized options from a `[host_component_provider.<name>]`
+// runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostComponentProviderOpts {
+    /// The provider's Wasm component, which exports the provided interfaces.
+    /// Relative paths are relative to the runtime config file.
+    pub source: Option<PathBuf>,
+    /// The interfaces provided to app components which import them, such as
+    /// `acme:billing/api@1.0.0`.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// The address the provider's process listens on, as `host:port` or
+    /// `unix:<path>`. Relative socket paths are relative to the runtime
+    /// config file.
+    pub address: Option<String>,
+    /// The IDs of the components which may call the provider's process. All
+    /// components may call it if this is not given.
+    pub components: Option<Vec<String>>,
+}
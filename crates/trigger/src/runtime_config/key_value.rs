@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, fs, path::PathBuf, sync::Arc, time::Duration};
 
 use crate::{runtime_config::RuntimeConfig, TriggerHooks};
 use anyhow::{bail, Context, Result};
@@ -8,8 +8,11 @@ use spin_key_value::{
     CachingStoreManager, DelegatingStoreManager, KeyValueComponent, StoreManager,
     KEY_VALUE_STORES_KEY,
 };
-use spin_key_value_azure::KeyValueAzureCosmos;
+use spin_key_value_aws::{AwsCredentials, KeyValueAwsDynamo, KeyValueAwsDynamoOptions};
+use spin_key_value_azure::{KeyValueAzureCosmos, KeyValueAzureCosmosOptions};
+use spin_key_value_redis::{KeyValueRedis, KeyValueRedisOptions};
 use spin_key_value_sqlite::{DatabaseLocation, KeyValueSqlite};
+use spin_outbound_networking::dns::Resolver;
 
 use super::{resolve_config_path, RuntimeConfigOpts};
 
@@ -48,11 +51,17 @@ pub async fn build_key_value_component(
         }
     }
 
+    let caching_manager = Arc::new(CachingStoreManager::new(DelegatingStoreManager::new(
+        stores.clone(),
+    )));
     let delegating_manager = DelegatingStoreManager::new(stores);
-    let caching_manager = Arc::new(CachingStoreManager::new(delegating_manager));
-    Ok(KeyValueComponent::new(spin_key_value::manager(move |_| {
-        caching_manager.clone()
-    })))
+    Ok(KeyValueComponent::new(spin_key_value::manager(
+        move |component| match delegating_manager.component_scoped(component.id()) {
+            // Stores namespaced per component need a manager of their own
+            Some(scoped_manager) => Arc::new(CachingStoreManager::new(scoped_manager)),
+            None => caching_manager.clone(),
+        },
+    )))
 }
 
 // Holds deserialized options from a `[key_value_store.<name>]` runtime config section.
@@ -62,6 +71,7 @@ pub enum KeyValueStoreOpts {
     Spin(SpinKeyValueStoreOpts),
     Redis(RedisKeyValueStoreOpts),
     AzureCosmos(AzureCosmosConfig),
+    AwsDynamo(AwsDynamoKeyValueStoreOpts),
 }
 
 impl KeyValueStoreOpts {
@@ -69,11 +79,16 @@ impl KeyValueStoreOpts {
         Self::Spin(SpinKeyValueStoreOpts::default_store_opts(runtime_config))
     }
 
-    pub fn build_store(&self, config_opts: &RuntimeConfigOpts) -> Result<KeyValueStore> {
+    pub fn build_store(
+        &self,
+        config_opts: &RuntimeConfigOpts,
+        resolver: &Resolver,
+    ) -> Result<KeyValueStore> {
         match self {
             Self::Spin(opts) => opts.build_store(config_opts),
-            Self::Redis(opts) => opts.build_store(),
+            Self::Redis(opts) => opts.build_store(resolver),
             Self::AzureCosmos(opts) => opts.build_store(),
+            Self::AwsDynamo(opts) => opts.build_store(),
         }
     }
 }
@@ -111,35 +126,111 @@ impl SpinKeyValueStoreOpts {
 #[derive(Clone, Debug, Deserialize)]
 pub struct RedisKeyValueStoreOpts {
     pub url: String,
+    /// Number of connections to the server.
+    pub pool_size: Option<usize>,
+    /// Expiry, in seconds, of values written to the store.
+    pub ttl_secs: Option<u64>,
+    /// Prefix for all keys in the store.
+    pub key_prefix: Option<String>,
+    /// Whether each component gets its own keyspace.
+    #[serde(default)]
+    pub namespace_by_component: bool,
 }
 
 impl RedisKeyValueStoreOpts {
-    fn build_store(&self) -> Result<KeyValueStore> {
-        let kv_redis = spin_key_value_redis::KeyValueRedis::new(self.url.clone())?;
+    fn build_store(&self, resolver: &Resolver) -> Result<KeyValueStore> {
+        if self.pool_size == Some(0) {
+            bail!("Redis key-value store pool_size must be at least 1");
+        }
+        let options = KeyValueRedisOptions {
+            pool_size: self.pool_size,
+            ttl: self.ttl_secs.map(Duration::from_secs),
+            key_prefix: self.key_prefix.clone(),
+            namespace_by_component: self.namespace_by_component,
+        };
+        let kv_redis =
+            KeyValueRedis::new(self.url.clone(), options)?.with_resolver(resolver.clone());
         Ok(Arc::new(kv_redis))
     }
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct AzureCosmosConfig {
-    key: String,
+    /// The account's primary key. If not set, credentials are discovered from
+    /// the environment, a managed identity or the Azure CLI.
+    key: Option<String>,
     account: String,
     database: String,
     container: String,
+    /// Prefix for all keys in the store.
+    key_prefix: Option<String>,
+    /// Whether each component gets its own keyspace.
+    #[serde(default)]
+    namespace_by_component: bool,
 }
 
 impl AzureCosmosConfig {
     pub fn build_store(&self) -> Result<Arc<dyn StoreManager>> {
+        let options = KeyValueAzureCosmosOptions {
+            key: self.key.clone(),
+            key_prefix: self.key_prefix.clone(),
+            namespace_by_component: self.namespace_by_component,
+        };
         let kv_azure_cosmos = KeyValueAzureCosmos::new(
-            self.key.clone(),
             self.account.clone(),
             self.database.clone(),
             self.container.clone(),
+            options,
         )?;
         Ok(Arc::new(kv_azure_cosmos))
     }
 }
 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AwsDynamoKeyValueStoreOpts {
+    /// The table holding the store, whose partition key is a string named `key`.
+    pub table: String,
+    /// The table's region. If not set, it is discovered from the environment.
+    pub region: Option<String>,
+    /// Access key ID. If not set, credentials are discovered from the
+    /// environment, AWS profiles or instance metadata.
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub token: Option<String>,
+    /// Whether reads are strongly consistent.
+    #[serde(default)]
+    pub consistent_read: bool,
+    /// Prefix for all keys in the store.
+    pub key_prefix: Option<String>,
+    /// Whether each component gets its own keyspace.
+    #[serde(default)]
+    pub namespace_by_component: bool,
+}
+
+impl AwsDynamoKeyValueStoreOpts {
+    fn build_store(&self) -> Result<KeyValueStore> {
+        let credentials = match (&self.access_key, &self.secret_key) {
+            (Some(access_key), Some(secret_key)) => Some(AwsCredentials {
+                access_key_id: access_key.clone(),
+                secret_access_key: secret_key.clone(),
+                session_token: self.token.clone(),
+            }),
+            (None, None) if self.token.is_none() => None,
+            _ => bail!("DynamoDB key-value store needs both access_key and secret_key, or neither"),
+        };
+        let options = KeyValueAwsDynamoOptions {
+            region: self.region.clone(),
+            credentials,
+            consistent_read: self.consistent_read,
+            key_prefix: self.key_prefix.clone(),
+            namespace_by_component: self.namespace_by_component,
+        };
+        let kv_dynamo = KeyValueAwsDynamo::new(self.table.clone(), options);
+        Ok(Arc::new(kv_dynamo))
+    }
+}
+
 // Prints startup messages about the default key value store config.
 pub struct KeyValuePersistenceMessageHook;
 
@@ -168,6 +259,12 @@ impl TriggerHooks for KeyValuePersistenceMessageHook {
             KeyValueStoreOpts::AzureCosmos(store_opts) => {
                 println!("Storing default key-value data to Azure CosmosDB: account: {}, database: {}, container: {}", store_opts.account, store_opts.database, store_opts.container);
             }
+            KeyValueStoreOpts::AwsDynamo(store_opts) => {
+                println!(
+                    "Storing default key-value data to DynamoDB table {}",
+                    store_opts.table
+                );
+            }
         }
         Ok(())
     }
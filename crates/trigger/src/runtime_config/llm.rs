@@ -1,9 +1,16 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
 use async_trait::async_trait;
+use spin_key_value::StoreManager;
 use spin_llm::LlmEngine;
-use spin_llm_remote_http::RemoteHttpLlmEngine;
-use spin_world::v2::llm as wasi_llm;
+use spin_llm_remote_http::{OpenAiApi, OpenAiLlmEngine, OpenAiModel, RemoteHttpLlmEngine};
+use spin_world::v2_1::llm as wasi_llm;
 use url::Url;
 
+const DEFAULT_OPENAI_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_AZURE_OPENAI_API_VERSION: &str = "2024-02-01";
+
 #[derive(Default)]
 pub struct LLmOptions {
     pub use_gpu: bool,
@@ -12,8 +19,32 @@ pub struct LLmOptions {
 pub(crate) async fn build_component(
     runtime_config: &crate::RuntimeConfig,
     use_gpu: bool,
-) -> spin_llm::LlmComponent {
-    match runtime_config.llm_compute() {
+) -> anyhow::Result<spin_llm::LlmComponent> {
+    let component = build_compute_component(runtime_config, use_gpu).await?;
+    let Some(cache) = runtime_config.llm_embedding_cache() else {
+        return Ok(component);
+    };
+    let store = runtime_config
+        .key_value_stores()?
+        .into_iter()
+        .find_map(|(name, manager)| (name == cache.store).then_some(manager))
+        .with_context(|| {
+            format!(
+                "LLM embedding cache uses key-value store '{}', which is not defined",
+                cache.store
+            )
+        })?
+        .get(&cache.store)
+        .await
+        .context("Failed to open LLM embedding cache store")?;
+    Ok(component.with_embedding_cache(store))
+}
+
+async fn build_compute_component(
+    runtime_config: &crate::RuntimeConfig,
+    use_gpu: bool,
+) -> anyhow::Result<spin_llm::LlmComponent> {
+    Ok(match runtime_config.llm_compute() {
         #[cfg(feature = "llm")]
         LlmComputeOpts::Spin => {
             let path = runtime_config
@@ -34,7 +65,75 @@ pub(crate) async fn build_component(
                 RemoteHttpLlmEngine::new(config.url.to_owned(), config.auth_token.to_owned());
             spin_llm::LlmComponent::new(move || Box::new(engine.clone()))
         }
-    }
+        LlmComputeOpts::OpenAi(config) => {
+            tracing::log::info!("Using an OpenAI-compatible API for LLMs");
+            let url = match &config.url {
+                Some(url) => url.clone(),
+                None => DEFAULT_OPENAI_URL.parse()?,
+            };
+            let engine = OpenAiLlmEngine::new(
+                OpenAiApi::OpenAi,
+                url,
+                resolve_api_key(
+                    &config.api_key,
+                    &config.api_key_env,
+                    &config.api_key_variable,
+                    runtime_config,
+                )
+                .await?,
+                model_mappings(&config.models),
+            );
+            spin_llm::LlmComponent::new(move || Box::new(engine.clone()))
+        }
+        LlmComputeOpts::AzureOpenAi(config) => {
+            tracing::log::info!("Using Azure OpenAI for LLMs");
+            let api = OpenAiApi::Azure {
+                api_version: config
+                    .api_version
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_AZURE_OPENAI_API_VERSION.into()),
+            };
+            let engine = OpenAiLlmEngine::new(
+                api,
+                config.url.clone(),
+                resolve_api_key(
+                    &config.api_key,
+                    &config.api_key_env,
+                    &config.api_key_variable,
+                    runtime_config,
+                )
+                .await?,
+                model_mappings(&config.models),
+            );
+            spin_llm::LlmComponent::new(move || Box::new(engine.clone()))
+        }
+    })
+}
+
+fn model_mappings(models: &HashMap<String, ModelOpts>) -> HashMap<String, OpenAiModel> {
+    models
+        .iter()
+        .map(|(model, opts)| {
+            let mapping = OpenAiModel {
+                name: opts.name.clone(),
+                url: opts.url.clone(),
+            };
+            (model.clone(), mapping)
+        })
+        .collect()
+}
+
+// Holds deserialized options from the `[llm_embedding_cache]` runtime config section.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LlmEmbeddingCacheOpts {
+    /// The key-value store in which to cache embeddings.
+    #[serde(default = "default_embedding_cache_store")]
+    pub store: String,
+}
+
+fn default_embedding_cache_store() -> String {
+    "default".into()
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -42,6 +141,8 @@ pub(crate) async fn build_component(
 pub enum LlmComputeOpts {
     Spin,
     RemoteHttp(RemoteHttpComputeOpts),
+    OpenAi(OpenAiComputeOpts),
+    AzureOpenAi(AzureOpenAiComputeOpts),
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -50,6 +151,93 @@ pub struct RemoteHttpComputeOpts {
     auth_token: String,
 }
 
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenAiComputeOpts {
+    /// The API's base URL. Defaults to the OpenAI API.
+    #[serde(default)]
+    url: Option<Url>,
+    #[serde(default)]
+    api_key: Option<String>,
+    /// Name of an environment variable containing the API key, to avoid
+    /// storing it in the runtime config file.
+    #[serde(default)]
+    api_key_env: Option<String>,
+    /// Name of a variable containing the API key, resolved by the runtime
+    /// config's variables providers.
+    #[serde(default)]
+    api_key_variable: Option<String>,
+    /// Where each model the app uses is served, by the name the app uses.
+    #[serde(default)]
+    models: HashMap<String, ModelOpts>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AzureOpenAiComputeOpts {
+    /// The Azure OpenAI resource's endpoint, e.g. `https://my-resource.openai.azure.com`.
+    url: Url,
+    #[serde(default)]
+    api_version: Option<String>,
+    #[serde(default)]
+    api_key: Option<String>,
+    /// Name of an environment variable containing the API key, to avoid
+    /// storing it in the runtime config file.
+    #[serde(default)]
+    api_key_env: Option<String>,
+    /// Name of a variable containing the API key, resolved by the runtime
+    /// config's variables providers.
+    #[serde(default)]
+    api_key_variable: Option<String>,
+    /// The deployment serving each model the app uses, by the name the app uses.
+    #[serde(default)]
+    models: HashMap<String, ModelOpts>,
+}
+
+// Resolves an API key given in the runtime config file, or by an environment
+// variable or Spin variable named there.
+async fn resolve_api_key(
+    api_key: &Option<String>,
+    api_key_env: &Option<String>,
+    api_key_variable: &Option<String>,
+    runtime_config: &crate::RuntimeConfig,
+) -> anyhow::Result<String> {
+    match (api_key, api_key_env, api_key_variable) {
+        (Some(key), None, None) => Ok(key.clone()),
+        (None, Some(var), None) => std::env::var(var).with_context(|| {
+            format!("failed to read LLM API key from environment variable '{var}'")
+        }),
+        (None, None, Some(variable)) => {
+            let key = spin_variables::Key::new(variable)
+                .with_context(|| format!("invalid LLM API key variable '{variable}'"))?;
+            for provider in runtime_config.variables_providers() {
+                if let Some(value) = provider.get(&key).await? {
+                    return Ok(value);
+                }
+            }
+            anyhow::bail!("no variables provider resolved LLM API key variable '{variable}'")
+        }
+        (None, None, None) => anyhow::bail!(
+            "LLM compute config must set one of 'api_key', 'api_key_env' or 'api_key_variable'"
+        ),
+        _ => anyhow::bail!(
+            "LLM compute config may set only one of 'api_key', 'api_key_env' or 'api_key_variable'"
+        ),
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelOpts {
+    /// The name the API knows the model by: the model name for OpenAI, or the
+    /// deployment name for Azure OpenAI.
+    #[serde(default, alias = "deployment")]
+    name: Option<String>,
+    /// The URL of the API serving the model, if not the configured one.
+    #[serde(default)]
+    url: Option<Url>,
+}
+
 #[derive(Clone)]
 struct NoopLlmEngine;
 
@@ -76,3 +264,46 @@ impl LlmEngine for NoopLlmEngine {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn azure_open_ai_compute_opts() {
+        let opts: LlmComputeOpts = toml::from_str(
+            r#"
+            type = "azure_open_ai"
+            url = "https://my-resource.openai.azure.com"
+            api_key_env = "SPIN_TEST_UNSET_AZURE_OPENAI_KEY"
+            models = { "llama2-chat" = { deployment = "gpt-chat" } }
+            "#,
+        )
+        .unwrap();
+        let LlmComputeOpts::AzureOpenAi(config) = opts else {
+            panic!("expected Azure OpenAI compute opts");
+        };
+        assert!(config.api_version.is_none());
+        let models = model_mappings(&config.models);
+        assert_eq!(models["llama2-chat"].name.as_deref(), Some("gpt-chat"));
+
+        let runtime_config = crate::RuntimeConfig::new(None);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(resolve_api_key(
+            &config.api_key,
+            &config.api_key_env,
+            &config.api_key_variable,
+            &runtime_config,
+        ))
+        .unwrap_err();
+        let key = rt.block_on(resolve_api_key(
+            &Some("secret".into()),
+            &None,
+            &None,
+            &runtime_config,
+        ));
+        assert_eq!(key.unwrap(), "secret");
+    }
+}
@@ -0,0 +1,87 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use crate::runtime_config::RuntimeConfig;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use spin_lock::{DatabaseLocation, LockComponent, LockManager, LockRedis, LockSqlite};
+use spin_outbound_networking::dns::Resolver;
+
+use super::{resolve_config_path, RuntimeConfigOpts};
+
+const DEFAULT_SPIN_LOCK_FILENAME: &str = "sqlite_lock.db";
+
+/// Builds a [`LockComponent`] from the given [`RuntimeConfig`].
+pub fn build_component(runtime_config: &RuntimeConfig) -> Result<LockComponent> {
+    Ok(LockComponent::new(runtime_config.lock_manager()?))
+}
+
+// Holds deserialized options from a `[lock_store]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum LockStoreOpts {
+    Spin(SpinLockStoreOpts),
+    Redis(RedisLockStoreOpts),
+}
+
+impl LockStoreOpts {
+    pub fn default_store_opts(runtime_config: &RuntimeConfig) -> Self {
+        Self::Spin(SpinLockStoreOpts::default_store_opts(runtime_config))
+    }
+
+    pub fn build_manager(
+        &self,
+        config_opts: &RuntimeConfigOpts,
+        resolver: &Resolver,
+    ) -> Result<Arc<dyn LockManager>> {
+        match self {
+            Self::Spin(opts) => opts.build_manager(config_opts),
+            Self::Redis(opts) => opts.build_manager(resolver),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpinLockStoreOpts {
+    pub path: Option<PathBuf>,
+}
+
+impl SpinLockStoreOpts {
+    fn default_store_opts(runtime_config: &RuntimeConfig) -> Self {
+        // If the state dir is set, build the default path
+        let path = runtime_config
+            .state_dir()
+            .map(|dir| dir.join(DEFAULT_SPIN_LOCK_FILENAME));
+        Self { path }
+    }
+
+    fn build_manager(&self, config_opts: &RuntimeConfigOpts) -> Result<Arc<dyn LockManager>> {
+        let location = match self.path.as_ref() {
+            Some(path) => {
+                let path = resolve_config_path(path, config_opts)?;
+                // Create the database's parent directory if necessary
+                fs::create_dir_all(path.parent().unwrap())
+                    .context("Failed to create lock store")?;
+                DatabaseLocation::Path(path)
+            }
+            None => DatabaseLocation::InMemory,
+        };
+        Ok(Arc::new(LockSqlite::new(location)))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedisLockStoreOpts {
+    pub url: String,
+    /// Prefix for the keys holding locks. Defaults to `spin-lock`.
+    pub key_prefix: Option<String>,
+}
+
+impl RedisLockStoreOpts {
+    fn build_manager(&self, resolver: &Resolver) -> Result<Arc<dyn LockManager>> {
+        Ok(Arc::new(
+            LockRedis::new(&self.url, self.key_prefix.clone())?.with_resolver(resolver.clone()),
+        ))
+    }
+}
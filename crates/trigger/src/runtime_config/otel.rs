@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::Deserialize;
+use spin_telemetry::{OtlpConfig, OtlpProtocol};
+
+const DEFAULT_SERVICE_NAME: &str = "spin";
+const DEFAULT_FILTER: &str = "info";
+
+// Holds deserialized options from an `[otel]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OtelOpts {
+    /// The OTLP collector endpoint, e.g. `http://localhost:4317`.
+    pub endpoint: String,
+    /// Either `grpc` (the default) or `http/protobuf`.
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+    /// Headers to send with each export, e.g. for authentication.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// The `service.name` to report spans under. Defaults to `spin`.
+    pub service_name: Option<String>,
+    /// Which spans to export, in `RUST_LOG` syntax. Defaults to `info`.
+    pub filter: Option<String>,
+}
+
+impl OtelOpts {
+    /// Starts exporting spans as configured.
+    pub(crate) fn enable(&self) -> Result<()> {
+        spin_telemetry::enable_otlp(&OtlpConfig {
+            endpoint: self.endpoint.clone(),
+            protocol: self.protocol,
+            headers: self.headers.clone(),
+            service_name: self
+                .service_name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SERVICE_NAME.into()),
+            filter: self.filter.clone().unwrap_or_else(|| DEFAULT_FILTER.into()),
+        })
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use outbound_http::{ConnectionPoolConfig, DestinationTlsConfig, OutboundHttpComponent};
+use serde::Deserialize;
+use spin_outbound_networking::dns::Resolver;
+
+use crate::{runtime_config::resolve_config_path, RuntimeConfig};
+
+/// Builds an [`OutboundHttpComponent`] from the given [`RuntimeConfig`].
+pub(crate) fn build_component(
+    runtime_config: &RuntimeConfig,
+    resolver: Resolver,
+) -> Result<OutboundHttpComponent> {
+    let opts = runtime_config.outbound_http_opts();
+    // Certificate paths are relative to the file the options came from
+    let config_opts = runtime_config
+        .opts_layers()
+        .find(|opts| opts.outbound_http.is_some());
+    let destination_tls = opts
+        .tls
+        .iter()
+        .map(|tls| {
+            let read = |path: &PathBuf| -> Result<Vec<u8>> {
+                let path = match config_opts {
+                    Some(config_opts) => resolve_config_path(path, config_opts)?,
+                    None => path.clone(),
+                };
+                fs::read(&path).with_context(|| format!("failed to read {}", path.display()))
+            };
+            let client_identity = match (&tls.client_cert, &tls.client_key) {
+                (Some(cert), Some(key)) => Some((read(cert)?, read(key)?)),
+                (None, None) => None,
+                _ => bail!("outbound_http.tls client_cert and client_key must be set together"),
+            };
+            Ok(DestinationTlsConfig {
+                hosts: tls.hosts.clone(),
+                ca_certs: tls.ca_certs.iter().map(read).collect::<Result<_>>()?,
+                client_identity,
+            })
+        })
+        .collect::<Result<_>>()?;
+    OutboundHttpComponent::new(
+        ConnectionPoolConfig {
+            max_idle_per_host: opts.pool_max_idle_per_host,
+            idle_timeout: opts.pool_idle_timeout_secs.map(Duration::from_secs),
+        },
+        destination_tls,
+        resolver,
+    )
+}
+
+// Holds deserialized options from an `[outbound_http]` runtime config section.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutboundHttpOpts {
+    /// Maximum number of idle connections kept open to each destination host.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long, in seconds, an idle connection is kept open.
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// TLS options for particular destinations, from `[[outbound_http.tls]]`
+    /// sections.
+    #[serde(default)]
+    pub tls: Vec<OutboundHttpTlsOpts>,
+}
+
+/// TLS options for requests to the destinations matching `hosts`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutboundHttpTlsOpts {
+    /// Destinations, in the same form as `allowed_outbound_hosts`.
+    pub hosts: Vec<String>,
+    /// PEM files of CAs to trust, in addition to the system's.
+    #[serde(default)]
+    pub ca_certs: Vec<PathBuf>,
+    /// PEM file of the client certificate chain to present.
+    pub client_cert: Option<PathBuf>,
+    /// PEM file of the client certificate's PKCS#8 key.
+    pub client_key: Option<PathBuf>,
+}
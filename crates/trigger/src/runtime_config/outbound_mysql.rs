@@ -0,0 +1,19 @@
+use outbound_mysql::OutboundMysql;
+use serde::Deserialize;
+use spin_outbound_networking::dns::Resolver;
+
+use crate::RuntimeConfig;
+
+/// Builds an [`OutboundMysql`] component from the given [`RuntimeConfig`].
+pub(crate) fn build_component(runtime_config: &RuntimeConfig, resolver: Resolver) -> OutboundMysql {
+    let opts = runtime_config.outbound_mysql_opts();
+    OutboundMysql::new(opts.statement_cache_size, resolver)
+}
+
+// Holds deserialized options from an `[outbound_mysql]` runtime config section.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutboundMysqlOpts {
+    /// Number of prepared statements cached by each connection.
+    pub statement_cache_size: Option<usize>,
+}
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+use spin_core::PoolingLimits;
+
+use crate::RuntimeConfig;
+
+/// Applies the `[pooling_allocator]` runtime config section to the given
+/// engine config.
+pub(crate) fn update_config(runtime_config: &RuntimeConfig, config: &mut spin_core::Config) {
+    let Some(opts) = runtime_config.pooling_allocator_opts() else {
+        return;
+    };
+    if !opts.enabled {
+        config.disable_pooling();
+        return;
+    }
+    config.pooling_limits(&PoolingLimits {
+        total_component_instances: opts.max_instances,
+        memory_pages: opts.memory_pages,
+        table_elements: opts.table_elements,
+    });
+}
+
+// Holds deserialized options from a `[pooling_allocator]` runtime config section.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolingAllocatorOpts {
+    /// Whether to use the pooling allocator. Defaults to `true`.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Maximum number of concurrent component instances.
+    pub max_instances: Option<u32>,
+    /// Maximum number of 64KiB Wasm pages in any linear memory.
+    pub memory_pages: Option<u64>,
+    /// Maximum number of elements in any table.
+    pub table_elements: Option<u32>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
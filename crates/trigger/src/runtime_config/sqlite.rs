@@ -1,13 +1,21 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::{runtime_config::RuntimeConfig, TriggerHooks};
 use anyhow::Context;
 use spin_common::ui::quoted_path;
-use spin_sqlite::{Connection, ConnectionsStore, SqliteComponent, DATABASES_KEY};
+use spin_sqlite::{
+    Connection, ConnectionsStore, SqliteComponent, DATABASES_KEY, READ_ONLY_DATABASES_KEY,
+};
 
 use super::RuntimeConfigOpts;
 
 const DEFAULT_SQLITE_DB_FILENAME: &str = "sqlite_db.db";
+// The table recording which migrations have been applied to a database
+const MIGRATIONS_TABLE: &str = "schema_migrations";
 
 pub(crate) async fn build_component(
     runtime_config: &RuntimeConfig,
@@ -18,6 +26,14 @@ pub(crate) async fn build_component(
         .context("Failed to build sqlite component")?
         .into_iter()
         .collect();
+    for (name, migrations) in runtime_config.sqlite_migrations()? {
+        let Some(database) = databases.get(&name) else {
+            continue;
+        };
+        apply_migrations(database.as_ref(), &migrations)
+            .await
+            .with_context(|| format!("Failed to migrate sqlite database '{name}'"))?;
+    }
     execute_statements(sqlite_statements, &databases).await?;
     let connections_store =
         Arc::new(SimpleConnectionsStore(databases)) as Arc<dyn ConnectionsStore>;
@@ -75,6 +91,98 @@ async fn execute_statements(
     Ok(())
 }
 
+/// Applies the migrations in the given files and directories of `.sql` files
+/// which have not yet been applied to the database, recording each in the
+/// `schema_migrations` table. The files of a directory are applied in file
+/// name order, and a migration is identified by its file name.
+async fn apply_migrations(connection: &dyn Connection, paths: &[PathBuf]) -> anyhow::Result<()> {
+    let files = migration_files(paths)?;
+    if files.is_empty() {
+        return Ok(());
+    }
+    connection
+        .execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE} (
+                version TEXT PRIMARY KEY,
+                applied_at INTEGER NOT NULL
+            );"
+        ))
+        .await
+        .context("failed to create migrations table")?;
+    let applied: HashSet<String> = connection
+        .query(&format!("SELECT version FROM {MIGRATIONS_TABLE}"), vec![])
+        .await
+        .context("failed to read applied migrations")?
+        .rows
+        .into_iter()
+        .filter_map(|row| match row.values.into_iter().next() {
+            Some(spin_world::v2::sqlite::Value::Text(version)) => Some(version),
+            _ => None,
+        })
+        .collect();
+
+    for (version, file) in files {
+        if applied.contains(&version) {
+            continue;
+        }
+        let sql = std::fs::read_to_string(&file)
+            .with_context(|| format!("could not read migration {}", quoted_path(&file)))?;
+        // The migration and its record are applied together or not at all
+        let version_literal = version.replace('\'', "''");
+        let result = connection
+            .execute_batch(&format!(
+                "BEGIN;\n{sql}\n;\nINSERT INTO {MIGRATIONS_TABLE} (version, applied_at) \
+                 VALUES ('{version_literal}', CAST(strftime('%s', 'now') AS INTEGER));\nCOMMIT;"
+            ))
+            .await;
+        if let Err(e) = result {
+            // Leave the connection usable if the migration fails part way
+            _ = connection.execute_batch("ROLLBACK;").await;
+            return Err(e)
+                .with_context(|| format!("failed to apply migration {}", quoted_path(&file)));
+        }
+        tracing::info!("Applied sqlite migration {version}");
+    }
+    Ok(())
+}
+
+// Returns the migration files at the given paths, with their versions, in the
+// order they are applied.
+fn migration_files(paths: &[PathBuf]) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            let mut dir_files = std::fs::read_dir(path)
+                .with_context(|| format!("could not read migrations in {}", quoted_path(path)))?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            dir_files.retain(|file| file.extension().is_some_and(|ext| ext == "sql"));
+            dir_files.sort();
+            files.extend(dir_files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    let mut seen = HashSet::new();
+    files
+        .into_iter()
+        .map(|file| {
+            let version = migration_version(&file)?;
+            if !seen.insert(version.clone()) {
+                anyhow::bail!("more than one migration is named '{version}'");
+            }
+            Ok((version, file))
+        })
+        .collect()
+}
+
+fn migration_version(file: &Path) -> anyhow::Result<String> {
+    file.file_name()
+        .and_then(|name| name.to_str())
+        .map(ToOwned::to_owned)
+        .with_context(|| format!("invalid migration file name {}", quoted_path(file)))
+}
+
 // Holds deserialized options from a `[sqlite_database.<name>]` runtime config section.
 #[derive(Clone, Debug, serde::Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
@@ -94,12 +202,28 @@ impl SqliteDatabaseOpts {
             Self::Libsql(opts) => opts.build(),
         }
     }
+
+    /// Returns the paths of the database's migrations, resolved against the
+    /// runtime config file's directory.
+    pub fn migrations(&self, config_opts: &RuntimeConfigOpts) -> anyhow::Result<Vec<PathBuf>> {
+        let migrations = match self {
+            Self::Spin(opts) => &opts.migrations,
+            Self::Libsql(opts) => &opts.migrations,
+        };
+        migrations
+            .iter()
+            .map(|path| super::resolve_config_path(path, config_opts))
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SpinSqliteDatabaseOpts {
     pub path: Option<PathBuf>,
+    /// SQL migration files, or directories of them, to apply at startup.
+    #[serde(default)]
+    pub migrations: Vec<PathBuf>,
 }
 
 impl SpinSqliteDatabaseOpts {
@@ -107,7 +231,10 @@ impl SpinSqliteDatabaseOpts {
         let path = runtime_config
             .state_dir()
             .map(|dir| dir.join(DEFAULT_SQLITE_DB_FILENAME));
-        Self { path }
+        Self {
+            path,
+            migrations: vec![],
+        }
     }
 
     fn build(&self, config_opts: &RuntimeConfigOpts) -> anyhow::Result<Arc<dyn Connection>> {
@@ -131,30 +258,62 @@ impl SpinSqliteDatabaseOpts {
 #[serde(deny_unknown_fields)]
 pub struct LibsqlOpts {
     url: String,
-    token: String,
+    /// Auth token. May be omitted for servers which don't require one.
+    #[serde(default)]
+    token: Option<String>,
+    /// Name of an environment variable containing the auth token, to avoid
+    /// storing it in the runtime config file.
+    #[serde(default)]
+    token_env: Option<String>,
+    /// SQL migration files, or directories of them, to apply at startup.
+    #[serde(default)]
+    migrations: Vec<PathBuf>,
 }
 
 impl LibsqlOpts {
     fn build(&self) -> anyhow::Result<Arc<dyn Connection>> {
-        let url = &check_url(&self.url).with_context(|| {
+        let url = normalize_url(&self.url).with_context(|| {
             format!(
                 "unexpected libSQL URL '{}' in runtime config file ",
                 self.url
             )
         })?;
-        let client = spin_sqlite_libsql::LibsqlClient::create(url, self.token.clone())
+        let client = spin_sqlite_libsql::LibsqlClient::create(&url, self.token()?)
             .context("failed to create SQLite client")?;
         Ok(Arc::new(client))
     }
+
+    fn token(&self) -> anyhow::Result<String> {
+        match (&self.token, &self.token_env) {
+            (Some(_), Some(_)) => {
+                anyhow::bail!("libSQL database config may not set both 'token' and 'token_env'")
+            }
+            (Some(token), None) => Ok(token.clone()),
+            (None, Some(var)) => std::env::var(var).with_context(|| {
+                format!("failed to read libSQL auth token from environment variable '{var}'")
+            }),
+            (None, None) => Ok(String::new()),
+        }
+    }
 }
 
-// Checks an incoming url is in the shape we expect
-fn check_url(url: &str) -> anyhow::Result<&str> {
+// Checks an incoming url is in the shape we expect, returning the HTTP(S) URL
+// to connect to. libSQL servers accept HTTP requests on the same port as
+// WebSocket connections, so `libsql://`, `ws://` and `wss://` URLs are
+// connected to over HTTP(S).
+fn normalize_url(url: &str) -> anyhow::Result<String> {
     if url.starts_with("https://") || url.starts_with("http://") {
-        Ok(url)
+        Ok(url.to_owned())
+    } else if let Some(rest) = url
+        .strip_prefix("libsql://")
+        .or_else(|| url.strip_prefix("wss://"))
+    {
+        Ok(format!("https://{rest}"))
+    } else if let Some(rest) = url.strip_prefix("ws://") {
+        Ok(format!("http://{rest}"))
     } else {
         Err(anyhow::anyhow!(
-            "URL does not start with 'https://' or 'http://'. Spin currently only supports talking to libSQL databases over HTTP(S)"
+            "URL does not start with 'https://', 'http://', 'libsql://', 'wss://' or 'ws://'"
         ))
     }
 }
@@ -168,10 +327,14 @@ impl TriggerHooks for SqlitePersistenceMessageHook {
         runtime_config: &RuntimeConfig,
     ) -> anyhow::Result<()> {
         if app.components().all(|c| {
-            c.get_metadata(DATABASES_KEY)
-                .unwrap_or_default()
-                .unwrap_or_default()
-                .is_empty()
+            [DATABASES_KEY, READ_ONLY_DATABASES_KEY]
+                .into_iter()
+                .all(|key| {
+                    c.get_metadata(key)
+                        .unwrap_or_default()
+                        .unwrap_or_default()
+                        .is_empty()
+                })
         }) {
             return Ok(());
         }
@@ -194,3 +357,84 @@ impl TriggerHooks for SqlitePersistenceMessageHook {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_libsql_urls() {
+        for (url, expected) in [
+            ("https://db.example.com", "https://db.example.com"),
+            ("http://localhost:8080", "http://localhost:8080"),
+            ("libsql://db.example.com", "https://db.example.com"),
+            ("wss://db.example.com", "https://db.example.com"),
+            ("ws://localhost:8080", "http://localhost:8080"),
+        ] {
+            assert_eq!(normalize_url(url).unwrap(), expected);
+        }
+        normalize_url("file:local.db").unwrap_err();
+    }
+
+    #[test]
+    fn libsql_token_sources() {
+        let opts = |token: Option<&str>, token_env: Option<&str>| LibsqlOpts {
+            url: "libsql://db.example.com".into(),
+            token: token.map(Into::into),
+            token_env: token_env.map(Into::into),
+            migrations: vec![],
+        };
+        assert_eq!(opts(Some("secret"), None).token().unwrap(), "secret");
+        assert_eq!(opts(None, None).token().unwrap(), "");
+        opts(Some("secret"), Some("TOKEN")).token().unwrap_err();
+        opts(None, Some("SPIN_TEST_UNSET_LIBSQL_TOKEN"))
+            .token()
+            .unwrap_err();
+    }
+
+    #[test]
+    fn applies_migrations_once() -> anyhow::Result<()> {
+        use spin_sqlite_inproc::{InProcConnection, InProcDatabaseLocation};
+
+        let dir = tempfile::tempdir()?;
+        let migrations = dir.path().join("migrations");
+        std::fs::create_dir(&migrations)?;
+        std::fs::write(
+            migrations.join("0001_create.sql"),
+            "CREATE TABLE items (name TEXT);",
+        )?;
+        std::fs::write(
+            migrations.join("0002_seed.sql"),
+            "INSERT INTO items VALUES ('one');",
+        )?;
+        std::fs::write(migrations.join("README.md"), "not a migration")?;
+
+        let connection = InProcConnection::new(InProcDatabaseLocation::InMemory)?;
+        let count = || async {
+            let result = connection
+                .query("SELECT COUNT(*) FROM items", vec![])
+                .await
+                .unwrap();
+            match &result.rows[0].values[0] {
+                spin_world::v2::sqlite::Value::Integer(n) => *n,
+                other => panic!("unexpected count {other:?}"),
+            }
+        };
+        let rt = tokio::runtime::Builder::new_current_thread().build()?;
+        rt.block_on(async {
+            apply_migrations(&connection, &[migrations.clone()]).await?;
+            assert_eq!(count().await, 1);
+            // Migrations already applied are skipped
+            apply_migrations(&connection, &[migrations.clone()]).await?;
+            assert_eq!(count().await, 1);
+
+            std::fs::write(
+                migrations.join("0003_more.sql"),
+                "INSERT INTO items VALUES ('two');",
+            )?;
+            apply_migrations(&connection, &[migrations]).await?;
+            assert_eq!(count().await, 2);
+            anyhow::Ok(())
+        })
+    }
+}
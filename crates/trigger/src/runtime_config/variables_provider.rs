@@ -1,18 +1,38 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
+use anyhow::{bail, Result};
 use serde::Deserialize;
-use spin_variables::provider::{env::EnvProvider, vault::VaultProvider};
+use spin_variables::provider::{
+    aws::AwsSecretsManagerProvider,
+    azure::AzureKeyVaultProvider,
+    env::EnvProvider,
+    gcp::GcpSecretManagerProvider,
+    vault::{VaultAuth, VaultProvider},
+};
 
 use super::RuntimeConfig;
 
 pub type VariablesProvider = Box<dyn spin_variables::Provider>;
 
+// Holds deserialized options from a `[variables]` runtime config section.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariablesOpts {
+    /// How often to refresh variables providers, so that updated values are
+    /// seen without restarting. Providers are never refreshed if unset.
+    #[serde(default)]
+    pub refresh_interval_secs: Option<u64>,
+}
+
 // Holds deserialized options from a `[[config_provider]]` runtime config section.
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum VariablesProviderOpts {
     Env(EnvVariablesProviderOpts),
     Vault(VaultVariablesProviderOpts),
+    AwsSecretsManager(AwsSecretsManagerVariablesProviderOpts),
+    GcpSecretManager(GcpSecretManagerVariablesProviderOpts),
+    AzureKeyVault(AzureKeyVaultVariablesProviderOpts),
 }
 
 impl VariablesProviderOpts {
@@ -26,6 +46,17 @@ impl VariablesProviderOpts {
         match self {
             Self::Env(opts) => opts.build_provider(),
             Self::Vault(opts) => opts.build_provider(),
+            Self::AwsSecretsManager(opts) => opts.build_provider(),
+            Self::GcpSecretManager(opts) => opts.build_provider(),
+            Self::AzureKeyVault(opts) => opts.build_provider(),
+        }
+    }
+
+    /// Checks for invalid combinations of options.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Vault(opts) => opts.validate(),
+            _ => Ok(()),
         }
     }
 }
@@ -66,19 +97,121 @@ impl EnvVariablesProviderOpts {
 #[serde(deny_unknown_fields)]
 pub struct VaultVariablesProviderOpts {
     pub url: String,
-    pub token: String,
+    /// Token to authenticate with. Exactly one of `token` and `approle` must be set.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// AppRole credentials to log in with.
+    #[serde(default)]
+    pub approle: Option<VaultAppRoleOpts>,
     pub mount: String,
     #[serde(default)]
     pub prefix: Option<String>,
+    /// How long to cache values read from Vault. Values are not cached if unset.
+    #[serde(default)]
+    pub cache_ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultAppRoleOpts {
+    /// Mount path of the AppRole auth method.
+    #[serde(default = "default_approle_mount")]
+    pub mount: String,
+    pub role_id: String,
+    pub secret_id: String,
+}
+
+fn default_approle_mount() -> String {
+    "approle".into()
 }
 
 impl VaultVariablesProviderOpts {
     pub fn build_provider(&self) -> VariablesProvider {
-        Box::new(VaultProvider::new(
-            &self.url,
-            &self.token,
-            &self.mount,
-            self.prefix.as_deref(),
+        let auth = match &self.approle {
+            Some(approle) => VaultAuth::AppRole {
+                mount: approle.mount.clone(),
+                role_id: approle.role_id.clone(),
+                secret_id: approle.secret_id.clone(),
+            },
+            None => VaultAuth::Token(self.token.clone().unwrap_or_default()),
+        };
+        let mut provider = VaultProvider::new(&self.url, auth, &self.mount, self.prefix.as_deref());
+        if let Some(cache_ttl_secs) = self.cache_ttl_secs {
+            provider = provider.with_cache_ttl(Duration::from_secs(cache_ttl_secs));
+        }
+        Box::new(provider)
+    }
+
+    fn validate(&self) -> Result<()> {
+        match (&self.token, &self.approle) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => bail!("Vault variables provider must set exactly one of 'token' and 'approle'"),
+        }
+    }
+}
+
+/// Credentials are discovered from the environment; see
+/// [`AwsSecretsManagerProvider`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AwsSecretsManagerVariablesProviderOpts {
+    /// Discovered from the environment or AWS profile if not set.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// A prefix to add to variable names to get secret names.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl AwsSecretsManagerVariablesProviderOpts {
+    pub fn build_provider(&self) -> VariablesProvider {
+        Box::new(AwsSecretsManagerProvider::new(
+            self.region.clone(),
+            self.prefix.clone(),
+        ))
+    }
+}
+
+/// Credentials are discovered from the environment; see
+/// [`GcpSecretManagerProvider`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GcpSecretManagerVariablesProviderOpts {
+    /// Defaults to the `GOOGLE_CLOUD_PROJECT` environment variable or the
+    /// project of the instance Spin is running on.
+    #[serde(default)]
+    pub project: Option<String>,
+    /// A prefix to add to variable names to get secret IDs.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl GcpSecretManagerVariablesProviderOpts {
+    pub fn build_provider(&self) -> VariablesProvider {
+        Box::new(GcpSecretManagerProvider::new(
+            self.project.clone(),
+            self.prefix.clone(),
+        ))
+    }
+}
+
+/// Credentials are discovered from the environment; see
+/// [`AzureKeyVaultProvider`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AzureKeyVaultVariablesProviderOpts {
+    /// The vault URL, e.g. `https://my-vault.vault.azure.net`.
+    pub vault_url: String,
+    /// A prefix to add to variable names to get secret names.
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl AzureKeyVaultVariablesProviderOpts {
+    pub fn build_provider(&self) -> VariablesProvider {
+        Box::new(AzureKeyVaultProvider::new(
+            &self.vault_url,
+            self.prefix.clone(),
         ))
     }
 }
@@ -0,0 +1,61 @@
+//! Graceful shutdown of trigger executors.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Signals a trigger executor to shut down gracefully.
+///
+/// Once the signal is triggered, executors should stop accepting new work
+/// (connections, messages, scheduled invocations), wait for in-flight
+/// invocations to complete, and then return from
+/// [`TriggerExecutor::run`](crate::TriggerExecutor::run).
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self {
+            sender: Arc::new(watch::channel(false).0),
+        }
+    }
+}
+
+impl ShutdownSignal {
+    /// Triggers the signal. Returns `false` if it had already been triggered.
+    pub fn trigger(&self) -> bool {
+        !self.sender.send_replace(true)
+    }
+
+    /// Returns whether the signal has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Completes once the signal has been triggered.
+    pub async fn triggered(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender is kept alive by `self`, so this can't fail
+        _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triggers_once() {
+        let signal = ShutdownSignal::default();
+        let clone = signal.clone();
+        assert!(!clone.is_triggered());
+
+        assert!(signal.trigger());
+        assert!(!signal.trigger());
+        assert!(clone.is_triggered());
+        // Completes immediately once triggered
+        futures::executor::block_on(clone.triggered());
+    }
+}
@@ -1,6 +1,8 @@
 use std::{
     collections::HashSet,
+    io::Write,
     path::{Path, PathBuf},
+    sync::Mutex,
     task::Poll,
 };
 
@@ -38,10 +40,59 @@ impl Default for FollowComponents {
     }
 }
 
+/// Size-based rotation of component log files.
+#[derive(Clone, Debug)]
+pub struct LogRotation {
+    /// The size in bytes at which a log file is rotated
+    pub max_size: u64,
+    /// The number of rotated files to keep, as `<file>.1` (the newest) to
+    /// `<file>.<max_files>`
+    pub max_files: usize,
+}
+
+impl LogRotation {
+    /// Rotates the log file at `log_path` if it has reached the maximum size.
+    fn rotate_if_full(&self, log_path: &Path) -> std::io::Result<()> {
+        match std::fs::metadata(log_path) {
+            Ok(metadata) if metadata.len() >= self.max_size => (),
+            Ok(_) => return Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let rotated_path = |n: usize| {
+            let mut path = log_path.as_os_str().to_owned();
+            path.push(format!(".{n}"));
+            PathBuf::from(path)
+        };
+        if self.max_files == 0 {
+            return std::fs::remove_file(log_path);
+        }
+        // Shift each rotated file along by one, dropping the oldest
+        remove_if_exists(&rotated_path(self.max_files))?;
+        for n in (1..self.max_files).rev() {
+            let path = rotated_path(n);
+            if path.exists() {
+                std::fs::rename(&path, rotated_path(n + 1))?;
+            }
+        }
+        std::fs::rename(log_path, rotated_path(1))
+    }
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
 /// Implements TriggerHooks, writing logs to a log file and (optionally) stderr
 pub struct StdioLoggingTriggerHooks {
     follow_components: FollowComponents,
     log_dir: Option<PathBuf>,
+    log_rotation: Option<LogRotation>,
+    // Held while rotating, so that concurrent invocations don't rotate twice
+    rotation_lock: Mutex<()>,
 }
 
 impl StdioLoggingTriggerHooks {
@@ -49,9 +100,19 @@ impl StdioLoggingTriggerHooks {
         Self {
             follow_components,
             log_dir: None,
+            log_rotation: None,
+            rotation_lock: Mutex::new(()),
         }
     }
 
+    /// Rotates log files as configured by `log_rotation`. Files are checked
+    /// as each invocation starts, so a single long-running invocation may
+    /// write past the maximum size.
+    pub fn with_log_rotation(mut self, log_rotation: Option<LogRotation>) -> Self {
+        self.log_rotation = log_rotation;
+        self
+    }
+
     fn component_stdio_writer(
         &self,
         component_id: &str,
@@ -60,9 +121,20 @@ impl StdioLoggingTriggerHooks {
     ) -> Result<ComponentStdioWriter> {
         let sanitized_component_id = sanitize_filename::sanitize(component_id);
         let log_path = log_dir.join(format!("{sanitized_component_id}_{log_suffix}.txt"));
+        if let Some(log_rotation) = &self.log_rotation {
+            let _lock = self.rotation_lock.lock().unwrap();
+            log_rotation
+                .rotate_if_full(&log_path)
+                .with_context(|| format!("Failed to rotate log file {}", quoted_path(&log_path)))?;
+        }
         let follow = self.follow_components.should_follow(component_id);
-        ComponentStdioWriter::new(&log_path, follow)
-            .with_context(|| format!("Failed to open log file {}", quoted_path(&log_path)))
+        let writer = ComponentStdioWriter::new(&log_path, follow)
+            .with_context(|| format!("Failed to open log file {}", quoted_path(&log_path)))?;
+        if follow && spin_telemetry::json_logs_enabled() {
+            Ok(writer.follow_as_json(component_id, log_suffix))
+        } else {
+            Ok(writer)
+        }
     }
 
     fn validate_follows(&self, app: &spin_app::App) -> anyhow::Result<()> {
@@ -132,6 +204,7 @@ pub struct ComponentStdioWriter {
     async_file: tokio::fs::File,
     state: ComponentStdioWriterState,
     follow: bool,
+    json_follow: Option<JsonFollow>,
 }
 
 #[derive(Debug)]
@@ -155,8 +228,56 @@ impl ComponentStdioWriter {
             sync_file,
             state: ComponentStdioWriterState::File,
             follow,
+            json_follow: None,
         })
     }
+
+    /// Writes followed output to stderr as JSON log lines rather than as is.
+    pub fn follow_as_json(mut self, component_id: &str, stream: &str) -> Self {
+        self.json_follow = Some(JsonFollow {
+            component_id: component_id.to_owned(),
+            stream: stream.to_owned(),
+            pending: vec![],
+        });
+        self
+    }
+}
+
+// Buffers followed output until a whole line can be logged
+struct JsonFollow {
+    component_id: String,
+    stream: String,
+    pending: Vec<u8>,
+}
+
+impl JsonFollow {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.pending.extend_from_slice(buf);
+        while let Some(newline) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=newline).collect();
+            self.log_line(&line[..newline])?;
+        }
+        Ok(())
+    }
+
+    fn log_line(&self, line: &[u8]) -> std::io::Result<()> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if let Some(json) =
+            spin_telemetry::component_output_json_line(&self.component_id, &self.stream, line)
+        {
+            writeln!(std::io::stderr(), "{json}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for JsonFollow {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            _ = self.log_line(&self.pending);
+        }
+    }
 }
 
 impl AsyncWrite for ComponentStdioWriter {
@@ -176,7 +297,9 @@ impl AsyncWrite for ComponentStdioWriter {
                         Ok(e) => e,
                         Err(e) => return Poll::Ready(Err(e)),
                     };
-                    if this.follow {
+                    if let Some(json_follow) = &mut this.json_follow {
+                        return Poll::Ready(json_follow.write(&buf[..written]).map(|_| written));
+                    } else if this.follow {
                         this.state = ComponentStdioWriterState::Follow(0..written);
                     } else {
                         return Poll::Ready(Ok(written));
@@ -236,7 +359,9 @@ impl AsyncWrite for ComponentStdioWriter {
 impl std::io::Write for ComponentStdioWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let written = self.sync_file.write(buf)?;
-        if self.follow {
+        if let Some(json_follow) = &mut self.json_follow {
+            json_follow.write(&buf[..written])?;
+        } else if self.follow {
             std::io::stderr().write_all(&buf[..written])?;
         }
         Ok(written)
@@ -258,3 +383,34 @@ fn bullet_list<S: std::fmt::Display>(items: impl IntoIterator<Item = S>) -> Stri
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_full_log_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log_path = dir.path().join("hello_stdout.txt");
+        let rotation = LogRotation {
+            max_size: 4,
+            max_files: 2,
+        };
+
+        // Not yet full
+        std::fs::write(&log_path, "one")?;
+        rotation.rotate_if_full(&log_path)?;
+        assert_eq!(std::fs::read_to_string(&log_path)?, "one");
+
+        for contents in ["first", "second", "third"] {
+            std::fs::write(&log_path, contents)?;
+            rotation.rotate_if_full(&log_path)?;
+            assert!(!log_path.exists());
+        }
+        let rotated = |n| dir.path().join(format!("hello_stdout.txt.{n}"));
+        assert_eq!(std::fs::read_to_string(rotated(1))?, "third");
+        assert_eq!(std::fs::read_to_string(rotated(2))?, "second");
+        assert!(!rotated(3).exists());
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use anyhow::Result;
 use once_cell::sync::OnceCell;
@@ -11,6 +14,7 @@ use crate::{Error, Key, Provider, Resolver};
 pub struct VariablesHostComponent {
     providers: Mutex<Vec<Box<dyn Provider>>>,
     resolver: Arc<OnceCell<Resolver>>,
+    refresh_interval: Option<Duration>,
 }
 
 impl VariablesHostComponent {
@@ -18,8 +22,39 @@ impl VariablesHostComponent {
         Self {
             providers: Mutex::new(providers),
             resolver: Default::default(),
+            refresh_interval: None,
         }
     }
+
+    /// Refreshes providers every `interval`, so that updated values are seen
+    /// by subsequent component invocations.
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = Some(interval);
+        self
+    }
+
+    // Periodically refreshes the resolver's providers until this component
+    // and all component data are dropped.
+    fn spawn_refresh_task(&self, interval: Duration) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("Not refreshing variables: no async runtime");
+            return;
+        };
+        let resolver = Arc::downgrade(&self.resolver);
+        runtime.spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.tick().await; // The first tick completes immediately
+            loop {
+                ticks.tick().await;
+                let Some(resolver) = resolver.upgrade() else {
+                    return;
+                };
+                if let Some(resolver) = resolver.get() {
+                    resolver.refresh().await;
+                }
+            }
+        });
+    }
 }
 
 impl HostComponent for VariablesHostComponent {
@@ -59,6 +94,9 @@ impl DynamicHostComponent for VariablesHostComponent {
             for provider in self.providers.lock().unwrap().drain(..) {
                 resolver.add_provider(provider);
             }
+            if let Some(interval) = self.refresh_interval {
+                self.spawn_refresh_task(interval);
+            }
             Ok::<_, anyhow::Error>(resolver)
         })?;
         data.component_id = Some(component.id().to_string());
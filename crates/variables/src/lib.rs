@@ -59,6 +59,16 @@ impl Resolver {
         self.providers.push(provider);
     }
 
+    /// Refreshes all providers, so that subsequent resolutions see updated
+    /// values. Providers that fail to refresh keep any cached values.
+    pub async fn refresh(&self) {
+        for provider in &self.providers {
+            if let Err(err) = provider.refresh().await {
+                tracing::warn!("Failed to refresh variables provider: {err:#}");
+            }
+        }
+    }
+
     /// Resolves a variable value for the given path.
     pub async fn resolve(&self, component_id: &str, key: Key<'_>) -> Result<String> {
         let configs = self.component_configs.get(component_id).ok_or_else(|| {
@@ -73,6 +83,15 @@ impl Resolver {
         self.resolve_template(template).await
     }
 
+    /// Resolves an application variable, rather than a component variable.
+    pub async fn resolve_app_variable(&self, key: Key<'_>) -> Result<String> {
+        let key = key.as_ref();
+        if !self.variables.contains_key(key) {
+            return Err(Error::Undefined(format!("no variable {key:?}")));
+        }
+        self.resolve_variable(key).await
+    }
+
     async fn resolve_template(&self, template: &Template) -> Result<String> {
         let mut resolved_parts: Vec<Cow<str>> = Vec::with_capacity(template.parts().len());
         for part in template.parts() {
@@ -249,6 +268,30 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn resolve_app_variable() {
+        let mut resolver = Resolver::new([(
+            "required".into(),
+            Variable {
+                default: None,
+                secret: true,
+            },
+        )])
+        .unwrap();
+        resolver.add_provider(Box::new(TestProvider));
+        assert_eq!(
+            resolver
+                .resolve_app_variable(Key("required"))
+                .await
+                .unwrap(),
+            "provider-value"
+        );
+        assert!(matches!(
+            resolver.resolve_app_variable(Key("undeclared")).await,
+            Err(Error::Undefined(_))
+        ));
+    }
+
     #[test]
     fn keys_good() {
         for key in ["a", "abc", "a1b2c3", "a_1", "a_1_b_3"] {
@@ -8,9 +8,21 @@ use crate::Key;
 pub mod env;
 pub mod vault;
 
+pub mod aws;
+pub mod azure;
+pub mod gcp;
+
+mod token;
+
 /// A config provider.
 #[async_trait]
 pub trait Provider: Debug + Send + Sync {
     /// Returns the value at the given config path, if it exists.
     async fn get(&self, key: &Key) -> anyhow::Result<Option<String>>;
+
+    /// Discards any values cached by the provider, so that subsequent calls to
+    /// `get` return up-to-date values.
+    async fn refresh(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use aws_config::{timeout::TimeoutConfig, BehaviorVersion, Region};
+use aws_sdk_secretsmanager::Client;
+use tokio::sync::OnceCell;
+
+use crate::{Key, Provider};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A config Provider that uses AWS Secrets Manager.
+///
+/// Credentials are discovered from the environment, AWS profiles, the
+/// container credentials endpoint (as used by ECS and EKS Pod Identity) or
+/// instance metadata.
+#[derive(Debug)]
+pub struct AwsSecretsManagerProvider {
+    region: Option<String>,
+    prefix: Option<String>,
+    // Created on first use
+    client: OnceCell<Client>,
+}
+
+impl AwsSecretsManagerProvider {
+    /// Creates a new AwsSecretsManagerProvider. If `region` is `None` it is
+    /// discovered from the environment or AWS profile. Secret names are the
+    /// variable name with `prefix`, if any, prepended.
+    pub fn new(region: Option<impl Into<String>>, prefix: Option<impl Into<String>>) -> Self {
+        Self {
+            region: region.map(Into::into),
+            prefix: prefix.map(Into::into),
+            client: OnceCell::new(),
+        }
+    }
+
+    async fn connect(&self) -> Client {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest()).timeout_config(
+            TimeoutConfig::builder()
+                .operation_timeout(REQUEST_TIMEOUT)
+                .build(),
+        );
+        if let Some(region) = &self.region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        Client::new(&loader.load().await)
+    }
+}
+
+#[async_trait]
+impl Provider for AwsSecretsManagerProvider {
+    async fn get(&self, key: &Key) -> Result<Option<String>> {
+        let secret_id = format!("{}{}", self.prefix.as_deref().unwrap_or_default(), key.0);
+        let client = self.client.get_or_init(|| self.connect()).await;
+        match client.get_secret_value().secret_id(&secret_id).send().await {
+            Ok(secret) => secret
+                .secret_string
+                .map(Some)
+                .ok_or_else(|| anyhow!("Secret {secret_id:?} is binary, not a string")),
+            // AWS doesn't have this entry so pass along the chain
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_resource_not_found_exception()) =>
+            {
+                Ok(None)
+            }
+            // Other AWS error so bail rather than looking elsewhere
+            Err(err) => Err(err).context("Failed to check AWS Secrets Manager for config"),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::{Deserialize, Deserializer};
+
+use super::token::{Expiring, TokenCache};
+use crate::{Key, Provider};
+
+const KEY_VAULT_RESOURCE: &str = "https://vault.azure.net";
+const KEY_VAULT_API_VERSION: &str = "7.4";
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+/// A config Provider that uses Azure Key Vault.
+///
+/// Credentials are discovered from the environment: a service principal
+/// secret in `AZURE_TENANT_ID`, `AZURE_CLIENT_ID` and `AZURE_CLIENT_SECRET`
+/// if set, otherwise the managed identity of the App Service, Container App
+/// or virtual machine Spin is running on.
+#[derive(Debug)]
+pub struct AzureKeyVaultProvider {
+    vault_url: String,
+    prefix: Option<String>,
+    client: reqwest::Client,
+    token: TokenCache<String>,
+}
+
+impl AzureKeyVaultProvider {
+    /// Creates a new AzureKeyVaultProvider for the vault at `vault_url`, e.g.
+    /// `https://my-vault.vault.azure.net`. Key Vault secret names may not
+    /// contain underscores, so secret names are the variable name with
+    /// underscores replaced by dashes and `prefix`, if any, prepended.
+    pub fn new(vault_url: impl Into<String>, prefix: Option<impl Into<String>>) -> Self {
+        Self {
+            vault_url: vault_url.into().trim_end_matches('/').to_owned(),
+            prefix: prefix.map(Into::into),
+            client: Default::default(),
+            token: Default::default(),
+        }
+    }
+
+    fn secret_name(&self, key: &Key) -> String {
+        format!(
+            "{}{}",
+            self.prefix.as_deref().unwrap_or_default(),
+            key.0.replace('_', "-")
+        )
+    }
+
+    async fn token(&self) -> Result<String> {
+        self.token
+            .get(|| async {
+                let client_id = std::env::var("AZURE_CLIENT_ID").ok();
+                let request = match (
+                    std::env::var("AZURE_TENANT_ID"),
+                    &client_id,
+                    std::env::var("AZURE_CLIENT_SECRET"),
+                ) {
+                    (Ok(tenant_id), Some(client_id), Ok(client_secret)) => self
+                        .client
+                        .post(format!(
+                            "https://login.microsoftonline.com/{tenant_id}/oauth2/v2.0/token"
+                        ))
+                        .form(&[
+                            ("grant_type", "client_credentials"),
+                            ("client_id", client_id.as_str()),
+                            ("client_secret", client_secret.as_str()),
+                            ("scope", &format!("{KEY_VAULT_RESOURCE}/.default")),
+                        ]),
+                    _ => self.managed_identity_request(client_id.as_deref()),
+                };
+
+                #[derive(Deserialize)]
+                struct TokenResponse {
+                    access_token: String,
+                    // A number from Entra ID but a string from managed identity endpoints
+                    #[serde(deserialize_with = "number_or_string")]
+                    expires_in: u64,
+                }
+                let response: TokenResponse = request
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+                    .context("Failed to get Azure access token")?;
+                Ok(Expiring::new(
+                    response.access_token,
+                    Some(Duration::from_secs(response.expires_in)),
+                ))
+            })
+            .await
+    }
+
+    fn managed_identity_request(&self, client_id: Option<&str>) -> reqwest::RequestBuilder {
+        let mut query = vec![("resource", KEY_VAULT_RESOURCE)];
+        if let Some(client_id) = client_id {
+            query.push(("client_id", client_id));
+        }
+        match (
+            std::env::var("IDENTITY_ENDPOINT"),
+            std::env::var("IDENTITY_HEADER"),
+        ) {
+            // App Service and Container Apps
+            (Ok(endpoint), Ok(header)) => {
+                query.push(("api-version", "2019-08-01"));
+                self.client
+                    .get(endpoint)
+                    .query(&query)
+                    .header("X-IDENTITY-HEADER", header)
+            }
+            // Virtual machines
+            _ => {
+                query.push(("api-version", "2018-02-01"));
+                self.client
+                    .get(IMDS_TOKEN_URL)
+                    .query(&query)
+                    .header("Metadata", "true")
+            }
+        }
+    }
+}
+
+fn number_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(n) => Ok(n),
+        NumberOrString::String(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+#[async_trait]
+impl Provider for AzureKeyVaultProvider {
+    async fn get(&self, key: &Key) -> Result<Option<String>> {
+        let name = self.secret_name(key);
+        let token = self.token().await?;
+        let response = self
+            .client
+            .get(format!("{}/secrets/{name}", self.vault_url))
+            .query(&[("api-version", KEY_VAULT_API_VERSION)])
+            .bearer_auth(token)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to check Azure Key Vault for config")?;
+
+        match response.status() {
+            status if status.is_success() => {
+                #[derive(Deserialize)]
+                struct Secret {
+                    value: String,
+                }
+                let secret: Secret = response.json().await?;
+                Ok(Some(secret.value))
+            }
+            // Key Vault doesn't have this entry so pass along the chain
+            StatusCode::NOT_FOUND => Ok(None),
+            // Other error so bail rather than looking elsewhere
+            status => bail!(
+                "Failed to check Azure Key Vault for config: {status}: {}",
+                response.text().await.unwrap_or_default()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_names_use_dashes() {
+        let provider = AzureKeyVaultProvider::new("https://vault/", Some("app-"));
+        assert_eq!(provider.vault_url, "https://vault");
+        let key = Key::new("db_password").unwrap();
+        assert_eq!(provider.secret_name(&key), "app-db-password");
+    }
+
+    #[test]
+    fn parses_expires_in() {
+        #[derive(Deserialize)]
+        struct Token {
+            #[serde(deserialize_with = "number_or_string")]
+            expires_in: u64,
+        }
+        let token: Token = serde_json::from_str(r#"{"expires_in": 3599}"#).unwrap();
+        assert_eq!(token.expires_in, 3599);
+        let token: Token = serde_json::from_str(r#"{"expires_in": "86400"}"#).unwrap();
+        assert_eq!(token.expires_in, 86400);
+    }
+}
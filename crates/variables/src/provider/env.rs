@@ -1,4 +1,4 @@
-use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+use std::{collections::HashMap, path::PathBuf, sync::Mutex, time::SystemTime};
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -13,7 +13,14 @@ const LEGACY_ENV_PREFIX: &str = "SPIN_CONFIG";
 pub struct EnvProvider {
     prefix: Option<String>,
     dotenv_path: Option<PathBuf>,
-    dotenv_cache: Mutex<Option<HashMap<String, String>>>,
+    dotenv_cache: Mutex<Option<DotenvCache>>,
+}
+
+#[derive(Debug)]
+struct DotenvCache {
+    // Modification time of the dotenv file when it was loaded
+    modified: Option<SystemTime>,
+    values: HashMap<String, String>,
 }
 
 impl EnvProvider {
@@ -66,11 +73,18 @@ impl EnvProvider {
             .dotenv_cache
             .lock()
             .expect("dotenv_cache lock poisoned");
+        // Reload the file if it has changed since it was cached
+        let modified = std::fs::metadata(self.dotenv_path.as_deref().unwrap())
+            .and_then(|metadata| metadata.modified())
+            .ok();
         let cache = match maybe_cache.as_mut() {
-            Some(cache) => cache,
-            None => maybe_cache.insert(self.load_dotenv()?),
+            Some(cache) if cache.modified == modified => cache,
+            _ => maybe_cache.insert(DotenvCache {
+                modified,
+                values: self.load_dotenv()?,
+            }),
         };
-        Ok(cache.get(key).cloned())
+        Ok(cache.values.get(key).cloned())
     }
 
     fn load_dotenv(&self) -> Result<HashMap<String, String>> {
@@ -87,6 +101,14 @@ impl Provider for EnvProvider {
     async fn get(&self, key: &Key) -> Result<Option<String>> {
         tokio::task::block_in_place(|| self.get_sync(key))
     }
+
+    async fn refresh(&self) -> Result<()> {
+        self.dotenv_cache
+            .lock()
+            .expect("dotenv_cache lock poisoned")
+            .take();
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -126,6 +148,26 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn provider_refresh_dotenv() {
+        let dotenv_path = temp_dir().join("spin-env-provider-refresh-test");
+        std::fs::write(&dotenv_path, b"TESTING_SPIN_ENV_KEY3=old_val").unwrap();
+
+        let provider = EnvProvider::new(Some("TESTING_SPIN"), Some(dotenv_path.clone()));
+        let key = Key::new("env_key3").unwrap();
+        assert_eq!(
+            provider.get_sync(&key).unwrap(),
+            Some("old_val".to_string())
+        );
+
+        std::fs::write(&dotenv_path, b"TESTING_SPIN_ENV_KEY3=new_val").unwrap();
+        provider.refresh().await.unwrap();
+        assert_eq!(
+            provider.get_sync(&key).unwrap(),
+            Some("new_val".to_string())
+        );
+    }
+
     #[test]
     fn provider_get_missing() {
         let key = Key::new("please_do_not_ever_set_this_during_tests").unwrap();
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use super::token::{Expiring, TokenCache};
+use crate::{Key, Provider};
+
+const METADATA_URL: &str = "http://metadata.google.internal/computeMetadata/v1";
+const SECRET_MANAGER_URL: &str = "https://secretmanager.googleapis.com/v1";
+
+/// A config Provider that uses Google Cloud Secret Manager.
+///
+/// An access token is taken from the `GOOGLE_OAUTH_ACCESS_TOKEN` environment
+/// variable or, failing that, from the metadata server of the Compute Engine,
+/// GKE or Cloud Run instance Spin is running on.
+#[derive(Debug)]
+pub struct GcpSecretManagerProvider {
+    project: Option<String>,
+    prefix: Option<String>,
+    client: reqwest::Client,
+    token: TokenCache<String>,
+}
+
+impl GcpSecretManagerProvider {
+    /// Creates a new GcpSecretManagerProvider. If `project` is `None` it is
+    /// read from the `GOOGLE_CLOUD_PROJECT` environment variable or the
+    /// metadata server. Secret IDs are the variable name with `prefix`, if
+    /// any, prepended.
+    pub fn new(project: Option<impl Into<String>>, prefix: Option<impl Into<String>>) -> Self {
+        Self {
+            project: project.map(Into::into),
+            prefix: prefix.map(Into::into),
+            client: Default::default(),
+            token: Default::default(),
+        }
+    }
+
+    async fn project(&self) -> Result<String> {
+        if let Some(project) = &self.project {
+            return Ok(project.clone());
+        }
+        if let Ok(project) = std::env::var("GOOGLE_CLOUD_PROJECT") {
+            return Ok(project);
+        }
+        self.metadata("project/project-id")
+            .await?
+            .text()
+            .await
+            .context("No Google Cloud project configured")
+    }
+
+    async fn token(&self) -> Result<String> {
+        self.token
+            .get(|| async {
+                if let Ok(token) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+                    return Ok(Expiring::new(token, None));
+                }
+
+                #[derive(Deserialize)]
+                struct TokenResponse {
+                    access_token: String,
+                    expires_in: u64,
+                }
+                let response: TokenResponse = self
+                    .metadata("instance/service-accounts/default/token")
+                    .await?
+                    .json()
+                    .await
+                    .context("Failed to get Google Cloud access token")?;
+                Ok(Expiring::new(
+                    response.access_token,
+                    Some(Duration::from_secs(response.expires_in)),
+                ))
+            })
+            .await
+    }
+
+    async fn metadata(&self, path: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .client
+            .get(format!("{METADATA_URL}/{path}"))
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .context("Failed to query Google Cloud metadata server")?
+            .error_for_status()?)
+    }
+}
+
+#[async_trait]
+impl Provider for GcpSecretManagerProvider {
+    async fn get(&self, key: &Key) -> Result<Option<String>> {
+        let secret_id = format!("{}{}", self.prefix.as_deref().unwrap_or_default(), key.0);
+        let project = self.project().await?;
+        let token = self.token().await?;
+        let response = self
+            .client
+            .get(format!(
+                "{SECRET_MANAGER_URL}/projects/{project}/secrets/{secret_id}/versions/latest:access"
+            ))
+            .bearer_auth(token)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to check Google Cloud Secret Manager for config")?;
+
+        match response.status() {
+            status if status.is_success() => {
+                #[derive(Deserialize)]
+                struct AccessSecretVersionResponse {
+                    payload: Payload,
+                }
+                #[derive(Deserialize)]
+                struct Payload {
+                    data: String,
+                }
+                let secret: AccessSecretVersionResponse = response.json().await?;
+                let data = STANDARD
+                    .decode(secret.payload.data)
+                    .with_context(|| format!("Secret {secret_id:?} is not valid base64"))?;
+                let value = String::from_utf8(data)
+                    .with_context(|| format!("Secret {secret_id:?} is not valid UTF-8"))?;
+                Ok(Some(value))
+            }
+            // Secret Manager doesn't have this entry so pass along the chain
+            StatusCode::NOT_FOUND => Ok(None),
+            // Other error so bail rather than looking elsewhere
+            status => bail!(
+                "Failed to check Google Cloud Secret Manager for config: {status}: {}",
+                response.text().await.unwrap_or_default()
+            ),
+        }
+    }
+}
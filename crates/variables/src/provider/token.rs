@@ -0,0 +1,71 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use tokio::sync::Mutex;
+
+// Refresh tokens this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(300);
+
+/// A credential and when it expires.
+pub(crate) struct Expiring<T> {
+    pub value: T,
+    pub expires_at: Option<Instant>,
+}
+
+impl<T> Expiring<T> {
+    pub fn new(value: T, expires_in: Option<Duration>) -> Self {
+        Self {
+            value,
+            expires_at: expires_in.map(|expires_in| Instant::now() + expires_in),
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => Instant::now() + EXPIRY_MARGIN < expires_at,
+            None => true,
+        }
+    }
+}
+
+/// Caches a credential until shortly before it expires.
+pub(crate) struct TokenCache<T> {
+    current: Mutex<Option<Expiring<T>>>,
+}
+
+impl<T> std::fmt::Debug for TokenCache<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't print credentials
+        f.debug_struct("TokenCache").finish_non_exhaustive()
+    }
+}
+
+impl<T> Default for TokenCache<T> {
+    fn default() -> Self {
+        Self {
+            current: Default::default(),
+        }
+    }
+}
+
+impl<T: Clone> TokenCache<T> {
+    /// Returns the cached credential, fetching a new one if there is no fresh
+    /// one cached.
+    pub async fn get<F, Fut>(&self, fetch: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Expiring<T>>>,
+    {
+        let mut current = self.current.lock().await;
+        if let Some(token) = current.as_ref().filter(|token| token.is_fresh()) {
+            return Ok(token.value.clone());
+        }
+        let token = fetch().await?;
+        let value = token.value.clone();
+        *current = Some(token);
+        Ok(value)
+    }
+}
@@ -1,10 +1,19 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex as AsyncMutex;
 use vaultrs::{
-    client::{VaultClient, VaultClientSettingsBuilder},
+    api::AuthInfo,
+    auth::approle,
+    client::{Client, VaultClient, VaultClientSettingsBuilder},
     error::ClientError,
-    kv2,
+    kv2, token,
 };
 
 use crate::{Key, Provider};
@@ -13,25 +22,176 @@ use crate::{Key, Provider};
 #[derive(Debug)]
 pub struct VaultProvider {
     url: String,
-    token: String,
+    auth: VaultAuth,
     mount: String,
     prefix: Option<String>,
+    cache_ttl: Option<Duration>,
+    session: AsyncMutex<Option<Session>>,
+    cache: Mutex<HashMap<String, CachedValue>>,
+}
+
+/// How a [`VaultProvider`] authenticates to Vault.
+#[derive(Clone)]
+pub enum VaultAuth {
+    /// Use the given token.
+    Token(String),
+    /// Log in with the AppRole auth method mounted at `mount`. The resulting
+    /// token is renewed before it expires.
+    AppRole {
+        mount: String,
+        role_id: String,
+        secret_id: String,
+    },
+}
+
+impl std::fmt::Debug for VaultAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't print credentials
+        match self {
+            Self::Token(_) => f.write_str("Token"),
+            Self::AppRole { mount, role_id, .. } => f
+                .debug_struct("AppRole")
+                .field("mount", mount)
+                .field("role_id", role_id)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+// An authenticated client.
+struct Session {
+    client: VaultClient,
+    // When to renew the client's token, if it expires
+    renew_at: Option<Instant>,
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("renew_at", &self.renew_at)
+            .finish_non_exhaustive()
+    }
+}
+
+struct CachedValue {
+    value: Option<String>,
+    expires_at: Instant,
+}
+
+impl std::fmt::Debug for CachedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Don't print secrets
+        f.debug_struct("CachedValue")
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
 }
 
 impl VaultProvider {
     pub fn new(
         url: impl Into<String>,
-        token: impl Into<String>,
+        auth: VaultAuth,
         mount: impl Into<String>,
         prefix: Option<impl Into<String>>,
     ) -> Self {
         Self {
             url: url.into(),
-            token: token.into(),
+            auth,
             mount: mount.into(),
             prefix: prefix.map(Into::into),
+            cache_ttl: None,
+            session: Default::default(),
+            cache: Default::default(),
+        }
+    }
+
+    /// Caches values (and their absence) read from Vault for `ttl`.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    fn cached(&self, path: &str) -> Option<Option<String>> {
+        let cache = self.cache.lock().unwrap();
+        let cached = cache.get(path)?;
+        (Instant::now() < cached.expires_at).then(|| cached.value.clone())
+    }
+
+    fn cache(&self, path: String, value: Option<String>) {
+        if let Some(ttl) = self.cache_ttl {
+            let expires_at = Instant::now() + ttl;
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(path, CachedValue { value, expires_at });
         }
     }
+
+    fn new_client(&self, token: &str) -> Result<VaultClient> {
+        Ok(VaultClient::new(
+            VaultClientSettingsBuilder::default()
+                .address(&self.url)
+                .token(token)
+                .build()?,
+        )?)
+    }
+
+    // Returns a new session, logging in if necessary.
+    async fn login(&self) -> Result<Session> {
+        match &self.auth {
+            VaultAuth::Token(token) => Ok(Session {
+                client: self.new_client(token)?,
+                renew_at: None,
+            }),
+            VaultAuth::AppRole {
+                mount,
+                role_id,
+                secret_id,
+            } => {
+                let mut client = self.new_client("")?;
+                let auth = approle::login(&client, mount, role_id, secret_id)
+                    .await
+                    .context("Failed to log in to Vault with AppRole")?;
+                client.set_token(&auth.client_token);
+                Ok(Session {
+                    client,
+                    renew_at: renew_at(&auth),
+                })
+            }
+        }
+    }
+
+    // Renews the session's token if it is due, logging in again if renewal fails.
+    async fn refresh(&self, session: &mut Option<Session>) -> Result<()> {
+        let due = match session {
+            Some(Session {
+                renew_at: Some(renew_at),
+                ..
+            }) => Instant::now() >= *renew_at,
+            Some(_) => false,
+            None => true,
+        };
+        if !due {
+            return Ok(());
+        }
+        if let Some(current) = session.as_mut() {
+            match token::renew_self(&current.client, None).await {
+                Ok(auth) => {
+                    current.renew_at = renew_at(&auth);
+                    return Ok(());
+                }
+                Err(e) => tracing::warn!("Failed to renew Vault token, logging in again: {e}"),
+            }
+        }
+        *session = Some(self.login().await?);
+        Ok(())
+    }
+}
+
+// Renew tokens halfway through their lease.
+fn renew_at(auth: &AuthInfo) -> Option<Instant> {
+    (auth.renewable && auth.lease_duration > 0)
+        .then(|| Instant::now() + Duration::from_secs(auth.lease_duration) / 2)
 }
 
 #[derive(Deserialize, Serialize)]
@@ -42,22 +202,30 @@ struct Secret {
 #[async_trait]
 impl Provider for VaultProvider {
     async fn get(&self, key: &Key) -> Result<Option<String>> {
-        let client = VaultClient::new(
-            VaultClientSettingsBuilder::default()
-                .address(&self.url)
-                .token(&self.token)
-                .build()?,
-        )?;
         let path = match &self.prefix {
             Some(prefix) => format!("{}/{}", prefix, key.0),
             None => key.0.to_string(),
         };
-        match kv2::read::<Secret>(&client, &self.mount, &path).await {
-            Ok(secret) => Ok(Some(secret.value)),
+        if let Some(value) = self.cached(&path) {
+            return Ok(value);
+        }
+
+        let mut session = self.session.lock().await;
+        self.refresh(&mut session).await?;
+        let client = &session.as_ref().unwrap().client;
+        let value = match kv2::read::<Secret>(client, &self.mount, &path).await {
+            Ok(secret) => Some(secret.value),
             // Vault doesn't have this entry so pass along the chain
-            Err(ClientError::APIError { code: 404, .. }) => Ok(None),
+            Err(ClientError::APIError { code: 404, .. }) => None,
             // Other Vault error so bail rather than looking elsewhere
-            Err(e) => Err(e).context("Failed to check Vault for config"),
-        }
+            Err(e) => return Err(e).context("Failed to check Vault for config"),
+        };
+        self.cache(path, value.clone());
+        Ok(value)
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        self.cache.lock().unwrap().clear();
+        Ok(())
     }
 }
@@ -167,6 +167,37 @@ mod redis {
             }
         }
     }
+
+    impl From<v2::redis::RedisParameter> for v2_1::redis::RedisParameter {
+        fn from(value: v2::redis::RedisParameter) -> Self {
+            match value {
+                v2::redis::RedisParameter::Int64(i) => v2_1::redis::RedisParameter::Int64(i),
+                v2::redis::RedisParameter::Binary(b) => v2_1::redis::RedisParameter::Binary(b),
+            }
+        }
+    }
+
+    impl From<v2_1::redis::RedisResult> for v2::redis::RedisResult {
+        fn from(value: v2_1::redis::RedisResult) -> Self {
+            match value {
+                v2_1::redis::RedisResult::Nil => v2::redis::RedisResult::Nil,
+                v2_1::redis::RedisResult::Status(s) => v2::redis::RedisResult::Status(s),
+                v2_1::redis::RedisResult::Int64(i) => v2::redis::RedisResult::Int64(i),
+                v2_1::redis::RedisResult::Binary(b) => v2::redis::RedisResult::Binary(b),
+            }
+        }
+    }
+
+    impl From<v2_1::redis::Error> for v2::redis::Error {
+        fn from(value: v2_1::redis::Error) -> Self {
+            match value {
+                v2_1::redis::Error::InvalidAddress => v2::redis::Error::InvalidAddress,
+                v2_1::redis::Error::TooManyConnections => v2::redis::Error::TooManyConnections,
+                v2_1::redis::Error::TypeError => v2::redis::Error::TypeError,
+                v2_1::redis::Error::Other(s) => v2::redis::Error::Other(s),
+            }
+        }
+    }
 }
 
 mod llm {
@@ -217,4 +248,64 @@ mod llm {
             }
         }
     }
+    impl From<v2::llm::InferencingParams> for v2_1::llm::InferencingParams {
+        fn from(value: v2::llm::InferencingParams) -> Self {
+            Self {
+                max_tokens: value.max_tokens,
+                repeat_penalty: value.repeat_penalty,
+                repeat_penalty_last_n_token_count: value.repeat_penalty_last_n_token_count,
+                temperature: value.temperature,
+                top_k: value.top_k,
+                top_p: value.top_p,
+            }
+        }
+    }
+
+    impl From<v2_1::llm::InferencingResult> for v2::llm::InferencingResult {
+        fn from(value: v2_1::llm::InferencingResult) -> Self {
+            Self {
+                text: value.text,
+                usage: v2::llm::InferencingUsage {
+                    prompt_token_count: value.usage.prompt_token_count,
+                    generated_token_count: value.usage.generated_token_count,
+                },
+            }
+        }
+    }
+
+    impl From<v2_1::llm::EmbeddingsResult> for v2::llm::EmbeddingsResult {
+        fn from(value: v2_1::llm::EmbeddingsResult) -> Self {
+            Self {
+                embeddings: value.embeddings,
+                usage: v2::llm::EmbeddingsUsage {
+                    prompt_token_count: value.usage.prompt_token_count,
+                },
+            }
+        }
+    }
+
+    impl From<v2_1::llm::Error> for v2::llm::Error {
+        fn from(value: v2_1::llm::Error) -> Self {
+            match value {
+                v2_1::llm::Error::ModelNotSupported => Self::ModelNotSupported,
+                v2_1::llm::Error::RuntimeError(s) => Self::RuntimeError(s),
+                v2_1::llm::Error::InvalidInput(s) => Self::InvalidInput(s),
+            }
+        }
+    }
+}
+
+mod key_value {
+    use super::*;
+
+    impl From<v2_1::key_value::Error> for v2::key_value::Error {
+        fn from(value: v2_1::key_value::Error) -> Self {
+            match value {
+                v2_1::key_value::Error::StoreTableFull => v2::key_value::Error::StoreTableFull,
+                v2_1::key_value::Error::NoSuchStore => v2::key_value::Error::NoSuchStore,
+                v2_1::key_value::Error::AccessDenied => v2::key_value::Error::AccessDenied,
+                v2_1::key_value::Error::Other(s) => v2::key_value::Error::Other(s),
+            }
+        }
+    }
 }
@@ -0,0 +1,58 @@
+//! Spin blob storage
+//!
+//! This module provides access to containers of objects too large to keep in key-value storage, which may be
+//! implemented by the host in various ways (e.g. via a local directory, or a cloud object store such as S3).
+
+use super::wit::v2_1::blobstore;
+
+#[doc(inline)]
+pub use blobstore::{Container, Error, IncomingData, ObjectMetadata, OutgoingData};
+
+impl Container {
+    /// Open the default container.
+    ///
+    /// This is equivalent to `Container::open("default")`.
+    pub fn open_default() -> Result<Self, Error> {
+        Self::open("default")
+    }
+}
+
+// The number of bytes requested from the host by each read of an `IncomingData`.
+const READ_CHUNK_SIZE: u64 = 64 * 1024;
+
+impl IncomingData {
+    /// Read the rest of the object into memory.
+    pub fn read_all(&self) -> Result<Vec<u8>, Error> {
+        let mut data = vec![];
+        loop {
+            let chunk = self.read(READ_CHUNK_SIZE)?;
+            if chunk.is_empty() {
+                return Ok(data);
+            }
+            data.extend(chunk);
+        }
+    }
+}
+
+impl std::io::Read for IncomingData {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let chunk = IncomingData::read(self, buf.len() as u64).map_err(io_error)?;
+        buf[..chunk.len()].copy_from_slice(&chunk);
+        Ok(chunk.len())
+    }
+}
+
+impl std::io::Write for OutgoingData {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        OutgoingData::write(self, buf).map_err(io_error)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn io_error(err: Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, err)
+}
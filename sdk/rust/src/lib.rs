@@ -8,6 +8,9 @@ pub mod key_value;
 /// SQLite storage.
 pub mod sqlite;
 
+/// Blob storage.
+pub mod blobstore;
+
 /// Large Language Model APIs
 pub mod llm;
 
@@ -26,6 +29,7 @@ pub mod wit {
         path: "./wit",
     });
     pub use fermyon::spin2_0_0 as v2;
+    pub use fermyon::spin2_1_0 as v2_1;
 }
 
 /// Needed by the export macro
@@ -53,7 +57,9 @@ pub mod http;
 pub mod redis {
     use std::hash::{Hash, Hasher};
 
-    pub use super::wit::v2::redis::{Connection, Error, Payload, RedisParameter, RedisResult};
+    pub use super::wit::v2_1::redis::{
+        Connection, Error, Payload, RedisCommand, RedisParameter, RedisResult, StreamEntry,
+    };
 
     impl PartialEq for RedisResult {
         fn eq(&self, other: &Self) -> bool {
@@ -93,5 +99,10 @@ pub mod mysql;
 #[doc(inline)]
 pub use wit::v2::variables;
 
+/// Locks shared by all the instances of an application, for electing a leader
+/// or otherwise deciding which instance does some work.
+#[doc(inline)]
+pub use wit::spin::lock::lock;
+
 #[doc(hidden)]
 pub use wit_bindgen;
@@ -1,6 +1,6 @@
-pub use crate::wit::v2::llm::{
-    self, EmbeddingsResult, EmbeddingsUsage, Error, InferencingParams, InferencingResult,
-    InferencingUsage,
+pub use crate::wit::v2_1::llm::{
+    self, EmbeddingsResult, EmbeddingsUsage, Error, InferenceStream, InferencingParams,
+    InferencingResult, InferencingUsage,
 };
 
 /// The model use for inferencing
@@ -50,6 +50,43 @@ pub fn infer_with_options(
     llm::infer(&model.to_string(), prompt, Some(options))
 }
 
+/// Perform inferencing using the provided model and prompt, receiving the
+/// generated text as the model produces it
+pub fn infer_stream(model: InferencingModel, prompt: &str) -> Result<InferenceStream, Error> {
+    llm::infer_stream(&model.to_string(), prompt, None)
+}
+
+/// Perform inferencing using the provided model, prompt, and options,
+/// receiving the generated text as the model produces it
+pub fn infer_stream_with_options(
+    model: InferencingModel,
+    prompt: &str,
+    options: InferencingParams,
+) -> Result<InferenceStream, Error> {
+    llm::infer_stream(&model.to_string(), prompt, Some(options))
+}
+
+impl Iterator for InferenceStream {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        InferenceStream::next(self).transpose()
+    }
+}
+
+/// Format generated text as a server-sent event, for forwarding an
+/// [`InferenceStream`] to a client as a `text/event-stream` response body.
+pub fn sse_event(text: &str) -> String {
+    let mut event = String::new();
+    for line in text.split('\n') {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    event
+}
+
 /// Model used for generating embeddings
 #[allow(missing_docs)]
 #[derive(Debug, Clone, Copy)]
@@ -14,7 +14,7 @@
 //! | `Vec<u8>`  | binary(list\<u8\>)  | BYTEA                        |
 
 #[doc(inline)]
-pub use super::wit::v2::postgres::{Connection, Error as PgError};
+pub use super::wit::v2_1::postgres::{Connection, Error as PgError, Transaction};
 #[doc(inline)]
 pub use super::wit::v2::rdbms_types::*;
 
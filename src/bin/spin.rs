@@ -1,6 +1,5 @@
 use anyhow::Error;
 use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
-use is_terminal::IsTerminal;
 use lazy_static::lazy_static;
 use spin_cli::commands::external::predefined_externals;
 use spin_cli::commands::{
@@ -8,17 +7,31 @@ use spin_cli::commands::{
     cloud::{DeployCommand, LoginCommand},
     doctor::DoctorCommand,
     external::execute_external_subcommand,
+    kv::KvCommands,
+    lint::LintCommand,
+    lock::LockCommands,
     new::{AddCommand, NewCommand},
+    optimize::OptimizeCommand,
     plugins::PluginCommands,
+    precompile::PrecompileCommand,
     registry::RegistryCommands,
+    replay::ReplayCommand,
+    sbom::SbomCommand,
     templates::TemplateCommands,
+    test::TestCommand,
     up::UpCommand,
     watch::WatchCommand,
 };
 use spin_cli::{build_info::*, subprocess::ExitStatusError};
+use spin_core::I32Exit;
 use spin_redis_engine::RedisTrigger;
 use spin_trigger::cli::help::HelpArgsOnlyTrigger;
-use spin_trigger::cli::TriggerExecutorCommand;
+use spin_trigger::cli::{MultiTriggerExecutorCommand, TriggerExecutorCommand};
+use spin_trigger_command::CommandTrigger;
+use spin_trigger_cron::CronTrigger;
+use spin_trigger_grpc::GrpcTrigger;
+use spin_trigger_mqtt::MqttTrigger;
+use spin_trigger_queue::QueueTrigger;
 use spin_trigger_http::HttpTrigger;
 
 #[tokio::main]
@@ -29,6 +42,9 @@ async fn main() {
             // exited unsuccessfully and thus already printed error messages. No need
             // to print anything additional.
             Some(e) => e.code(),
+            // A command trigger's guest exited unsuccessfully; its status is
+            // ours and it has printed whatever it had to say.
+            None if err.is::<I32Exit>() => err.downcast_ref::<I32Exit>().map_or(1, |exit| exit.0),
             // Otherwise we print the error chain.
             None => {
                 terminal::error!("{err}");
@@ -42,14 +58,7 @@ async fn main() {
 }
 
 async fn _main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_writer(std::io::stderr)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("watchexec=off".parse()?),
-        )
-        .with_ansi(std::io::stderr().is_terminal())
-        .init();
+    spin_telemetry::init()?;
 
     let plugin_help_entries = plugin_help_entries();
 
@@ -139,12 +148,29 @@ enum SpinApp {
     #[clap(alias = "w")]
     Watch(WatchCommand),
     Doctor(DoctorCommand),
+    Precompile(PrecompileCommand),
+    #[clap(subcommand, alias = "key-value")]
+    Kv(KvCommands),
+    Test(TestCommand),
+    Lint(LintCommand),
+    #[clap(subcommand)]
+    Lock(LockCommands),
+    Optimize(OptimizeCommand),
+    Replay(ReplayCommand),
+    Sbom(SbomCommand),
 }
 
 #[derive(Subcommand)]
 enum TriggerCommands {
     Http(TriggerExecutorCommand<HttpTrigger>),
     Redis(TriggerExecutorCommand<RedisTrigger>),
+    Cron(TriggerExecutorCommand<CronTrigger>),
+    Queue(TriggerExecutorCommand<QueueTrigger>),
+    Mqtt(TriggerExecutorCommand<MqttTrigger>),
+    Grpc(TriggerExecutorCommand<GrpcTrigger>),
+    Command(TriggerExecutorCommand<CommandTrigger>),
+    #[clap(name = spin_cli::HTTP_REDIS_TRIGGER_TYPE)]
+    HttpRedis(MultiTriggerExecutorCommand<HttpTrigger, RedisTrigger>),
     #[clap(name = spin_cli::HELP_ARGS_ONLY_TRIGGER_TYPE, hide = true)]
     HelpArgsOnly(TriggerExecutorCommand<HelpArgsOnlyTrigger>),
 }
@@ -163,11 +189,25 @@ impl SpinApp {
             Self::Build(cmd) => cmd.run().await,
             Self::Trigger(TriggerCommands::Http(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::Redis(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Cron(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Queue(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Mqtt(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Grpc(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::Command(cmd)) => cmd.run().await,
+            Self::Trigger(TriggerCommands::HttpRedis(cmd)) => cmd.run().await,
             Self::Trigger(TriggerCommands::HelpArgsOnly(cmd)) => cmd.run().await,
             Self::Plugins(cmd) => cmd.run().await,
             Self::External(cmd) => execute_external_subcommand(cmd, app).await,
             Self::Watch(cmd) => cmd.run().await,
             Self::Doctor(cmd) => cmd.run().await,
+            Self::Precompile(cmd) => cmd.run().await,
+            Self::Kv(cmd) => cmd.run().await,
+            Self::Test(cmd) => cmd.run().await,
+            Self::Lint(cmd) => cmd.run().await,
+            Self::Lock(cmd) => cmd.run().await,
+            Self::Optimize(cmd) => cmd.run().await,
+            Self::Replay(cmd) => cmd.run().await,
+            Self::Sbom(cmd) => cmd.run().await,
         }
     }
 }
@@ -8,14 +8,30 @@ pub mod cloud;
 pub mod doctor;
 /// Commands for external subcommands (i.e. plugins)
 pub mod external;
+/// Commands for inspecting and seeding an application's key-value stores.
+pub mod kv;
+/// Command for checking an application's manifest and runtime config for problems.
+pub mod lint;
+/// Commands for working with locked application files.
+pub mod lock;
 /// Command for creating a new application.
 pub mod new;
+/// Command for pre-initializing an application's components.
+pub mod optimize;
 /// Command for adding a plugin to Spin
 pub mod plugins;
+/// Command for compiling an application's components ahead of time.
+pub mod precompile;
 /// Commands for working with OCI registries.
 pub mod registry;
+/// Command for re-sending captured requests to a running application.
+pub mod replay;
+/// Command for writing a software bill of materials for an application.
+pub mod sbom;
 /// Commands for working with templates.
 pub mod templates;
+/// Command for running an application's tests.
+pub mod test;
 /// Commands for starting the runtime.
 pub mod up;
 /// Command for rebuilding and restarting a Spin app when files change.
@@ -27,6 +27,16 @@ pub struct BuildCommand {
     #[clap(short = 'c', long, multiple = true)]
     pub component_id: Vec<String>,
 
+    /// Rebuild components even if their build inputs are unchanged since
+    /// they were last built.
+    #[clap(long = "force")]
+    pub force: bool,
+
+    /// Use the build commands of the manifest's `[profile.<PROFILE>]`
+    /// overlay. With `--up`, the profile is also applied to the running app.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
     /// Run the application after building.
     #[clap(name = BUILD_UP_OPT, short = 'u', long = "up")]
     pub up: bool,
@@ -38,7 +48,13 @@ pub struct BuildCommand {
 impl BuildCommand {
     pub async fn run(self) -> Result<()> {
         let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
-        spin_build::build(&manifest_file, &self.component_id).await?;
+        spin_build::build_with_profile(
+            &manifest_file,
+            &self.component_id,
+            self.force,
+            self.profile.as_deref(),
+        )
+        .await?;
 
         if self.up {
             let mut cmd = UpCommand::parse_from(
@@ -49,6 +65,7 @@ impl BuildCommand {
                 .chain(self.up_args),
             );
             cmd.file_source = Some(manifest_file);
+            cmd.profile = cmd.profile.or(self.profile);
             cmd.run().await
         } else {
             Ok(())
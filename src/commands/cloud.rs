@@ -1,12 +1,22 @@
+use std::path::{Path, PathBuf};
+
 use crate::commands::external::execute_external_subcommand;
-use anyhow::Result;
+use crate::opts::DEFAULT_MANIFEST_FILE;
+use anyhow::{Context, Result};
 use clap::Args;
+use spin_common::{sha256::hex_digest_from_file, ui::quoted_path};
+use spin_loader::FilesMountStrategy;
+use spin_locked_app::locked::{ContentPath, ContentRef, LockedApp};
+
+// Handled by Spin rather than passed through to the plugin
+const DRY_RUN_OPT: &str = "--dry-run";
 
 #[derive(Debug, Args, PartialEq)]
 #[clap(
     about = "Package and upload an application to the Fermyon Cloud.",
     allow_hyphen_values = true,
-    disable_help_flag = true
+    disable_help_flag = true,
+    after_help = "Pass --dry-run to print the application as it would be deployed, as JSON, without contacting the Fermyon Cloud."
 )]
 pub struct DeployCommand {
     /// All args to be passed through to the plugin
@@ -28,12 +38,108 @@ pub struct LoginCommand {
 
 impl DeployCommand {
     pub async fn run(self, app: clap::App<'_>) -> Result<()> {
+        if self.args.iter().any(|arg| arg == DRY_RUN_OPT) {
+            return self.dry_run().await;
+        }
         let mut cmd = vec!["cloud".to_string(), "deploy".to_string()];
         cmd.append(&mut self.args.clone());
         execute_external_subcommand(cmd, app).await
     }
 }
 
+impl DeployCommand {
+    async fn dry_run(&self) -> Result<()> {
+        let app_source = manifest_arg(&self.args).unwrap_or(DEFAULT_MANIFEST_FILE);
+        let manifest_path = spin_common::paths::resolve_manifest_file_path(app_source)?;
+        let working_dir = tempfile::tempdir()?;
+        let locked = spin_loader::from_file(
+            &manifest_path,
+            FilesMountStrategy::Copy(working_dir.path().into()),
+            None,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to load manifest from {}",
+                quoted_path(&manifest_path)
+            )
+        })?;
+        let locked = resolve_content(locked)?;
+        println!("{}", String::from_utf8(locked.to_json()?)?);
+        Ok(())
+    }
+}
+
+// Replaces local file references with content digests, as the app will be
+// stored once deployed, so that the output doesn't depend on where it was
+// loaded from.
+fn resolve_content(mut locked: LockedApp) -> Result<LockedApp> {
+    for component in &mut locked.components {
+        let source = file_source(&component.source.content)
+            .context("component loaded from disk should contain a file source")?;
+        component.source.content = digest_content_ref(&source)?;
+        for dependency in component.source.dependencies.values_mut() {
+            // Registry dependencies have no file source and are left as they are
+            if let Some(source) = file_source(&dependency.content) {
+                dependency.content = digest_content_ref(&source)?;
+            }
+        }
+
+        let mut files = vec![];
+        for mount in &component.files {
+            let source = file_source(&mount.content)
+                .context("file mount loaded from disk should contain a file source")?;
+            for entry in walkdir::WalkDir::new(&source) {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                // Can unwrap because we got to 'entry' from walking 'source'
+                let rel_path = entry.path().strip_prefix(&source).unwrap();
+                files.push(ContentPath {
+                    content: digest_content_ref(entry.path())?,
+                    path: mount.path.join(rel_path),
+                    writable: mount.writable,
+                });
+            }
+        }
+        component.files = files;
+    }
+    locked.metadata.remove("origin");
+    Ok(locked)
+}
+
+fn file_source(content: &ContentRef) -> Option<PathBuf> {
+    let url = url::Url::parse(content.source.as_deref()?).ok()?;
+    url.to_file_path().ok()
+}
+
+fn digest_content_ref(path: &Path) -> Result<ContentRef> {
+    let digest = hex_digest_from_file(path)
+        .with_context(|| format!("Failed to read {}", quoted_path(path)))?;
+    Ok(ContentRef {
+        digest: Some(format!("sha256:{digest}")),
+        ..Default::default()
+    })
+}
+
+// Finds the app manifest option among the args otherwise meant for the plugin
+fn manifest_arg(args: &[String]) -> Option<&str> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if matches!(arg.as_str(), "-f" | "--from" | "--file") {
+            return args.next().map(String::as_str);
+        }
+        if let Some(value) = arg
+            .strip_prefix("--from=")
+            .or_else(|| arg.strip_prefix("--file="))
+        {
+            return Some(value);
+        }
+    }
+    None
+}
+
 impl LoginCommand {
     pub async fn run(self, app: clap::App<'_>) -> Result<()> {
         let mut cmd = vec!["cloud".to_string(), "login".to_string()];
@@ -41,3 +147,25 @@ impl LoginCommand {
         execute_external_subcommand(cmd, app).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_manifest_arg() {
+        assert_eq!(manifest_arg(&args(&["--dry-run"])), None);
+        assert_eq!(
+            manifest_arg(&args(&["--dry-run", "-f", "app/spin.toml"])),
+            Some("app/spin.toml")
+        );
+        assert_eq!(
+            manifest_arg(&args(&["--from=app", "--dry-run"])),
+            Some("app")
+        );
+    }
+}
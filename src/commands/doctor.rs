@@ -4,6 +4,7 @@ use anyhow::Result;
 use clap::Parser;
 use dialoguer::{console::Emoji, Confirm, Select};
 use spin_doctor::{Diagnosis, DryRunNotSupported, PatientDiagnosis};
+use spin_trigger::cli::RUNTIME_CONFIG_FILE;
 
 use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
 
@@ -21,6 +22,15 @@ pub struct DoctorCommand {
         default_value = DEFAULT_MANIFEST_FILE
     )]
     pub app_source: PathBuf,
+
+    /// The runtime config file to check. If omitted, any runtime-config.toml
+    /// file beside the application manifest is checked.
+    #[clap(
+        name = RUNTIME_CONFIG_FILE,
+        long = "runtime-config-file",
+        env = RUNTIME_CONFIG_FILE
+    )]
+    pub runtime_config_file: Option<PathBuf>,
 }
 
 impl DoctorCommand {
@@ -35,6 +45,9 @@ impl DoctorCommand {
         );
 
         let mut checkup = spin_doctor::Checkup::new(manifest_file)?;
+        if let Some(runtime_config_file) = self.runtime_config_file {
+            checkup.set_runtime_config_path(runtime_config_file);
+        }
         let mut has_problems = false;
         while let Some(PatientDiagnosis { diagnosis, patient }) = checkup.next_diagnosis().await? {
             show_diagnosis(&*diagnosis);
@@ -0,0 +1,202 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use spin_key_value::{DelegatingStoreManager, Store, StoreManager};
+use spin_trigger::{cli::RUNTIME_CONFIG_FILE, RuntimeConfig};
+
+use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+
+/// Commands for inspecting and seeding the key-value stores of an application.
+#[derive(Subcommand, Debug)]
+pub enum KvCommands {
+    /// Print the value of a key.
+    Get(Get),
+    /// Set the value of a key.
+    Set(Set),
+    /// Delete a key.
+    Delete(Delete),
+    /// List the keys in a store.
+    List(List),
+}
+
+impl KvCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            KvCommands::Get(cmd) => cmd.run().await,
+            KvCommands::Set(cmd) => cmd.run().await,
+            KvCommands::Delete(cmd) => cmd.run().await,
+            KvCommands::List(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Options locating a key-value store as the application sees it when run
+/// with `spin up`.
+#[derive(Args, Debug)]
+pub struct StoreOpts {
+    /// The application whose stores to use. This may be a manifest
+    /// (spin.toml) file, or a directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// The runtime config file configuring the application's stores, as
+    /// passed to `spin up`.
+    #[clap(
+        name = RUNTIME_CONFIG_FILE,
+        long = "runtime-config-file",
+        env = RUNTIME_CONFIG_FILE
+    )]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// The application state directory, as passed to `spin up`. Defaults
+    /// to `.spin/` beside the manifest.
+    #[clap(long)]
+    pub state_dir: Option<String>,
+
+    /// The name of the store.
+    #[clap(short = 's', long = "store", default_value = "default")]
+    pub store: String,
+
+    /// The component whose keys to use, for stores which keep a namespace
+    /// per component.
+    #[clap(short = 'c', long = "component")]
+    pub component: Option<String>,
+}
+
+impl StoreOpts {
+    async fn open(&self) -> Result<Arc<dyn Store>> {
+        let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
+        let app_dir = manifest_file.parent().map(Path::to_path_buf);
+        let mut runtime_config = RuntimeConfig::new(app_dir);
+        if let Some(state_dir) = &self.state_dir {
+            runtime_config.set_state_dir(state_dir);
+        }
+        if let Some(config_file) = &self.runtime_config_file {
+            runtime_config.merge_config_file(config_file)?;
+        }
+        let stores: HashMap<_, _> = runtime_config
+            .key_value_stores()
+            .context("Failed to configure key-value stores")?
+            .into_iter()
+            .collect();
+        let mut manager = DelegatingStoreManager::new(stores);
+        if let Some(component) = &self.component {
+            if let Some(scoped) = manager.component_scoped(component) {
+                manager = scoped;
+            }
+        }
+        manager
+            .get(&self.store)
+            .await
+            .with_context(|| format!("Failed to open key-value store {:?}", self.store))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Get {
+    #[clap(flatten)]
+    pub store: StoreOpts,
+
+    /// The key to print.
+    pub key: String,
+}
+
+impl Get {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        let value = store
+            .get(&self.key)
+            .await
+            .with_context(|| format!("Failed to get key {:?}", self.key))?
+            .ok_or_else(|| anyhow!("No such key {:?}", self.key))?;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&value)?;
+        if !value.ends_with(b"\n") {
+            writeln!(stdout)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Set {
+    #[clap(flatten)]
+    pub store: StoreOpts,
+
+    /// The key to set.
+    pub key: String,
+
+    /// The value to set. If omitted, the value is read from standard input.
+    pub value: Option<String>,
+}
+
+impl Set {
+    pub async fn run(self) -> Result<()> {
+        let value = match self.value {
+            Some(value) => value.into_bytes(),
+            None => {
+                let mut value = vec![];
+                std::io::Read::read_to_end(&mut std::io::stdin(), &mut value)
+                    .context("Failed to read value from standard input")?;
+                value
+            }
+        };
+        let store = self.store.open().await?;
+        store
+            .set(&self.key, &value)
+            .await
+            .with_context(|| format!("Failed to set key {:?}", self.key))?;
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct Delete {
+    #[clap(flatten)]
+    pub store: StoreOpts,
+
+    /// The key to delete.
+    pub key: String,
+}
+
+impl Delete {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        store
+            .delete(&self.key)
+            .await
+            .with_context(|| format!("Failed to delete key {:?}", self.key))?;
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct List {
+    #[clap(flatten)]
+    pub store: StoreOpts,
+}
+
+impl List {
+    pub async fn run(self) -> Result<()> {
+        let store = self.store.open().await?;
+        let mut keys = store.get_keys().await.context("Failed to list keys")?;
+        keys.sort();
+        for key in keys {
+            println!("{key}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+use spin_common::ui::quoted_path;
+use spin_http::{config::HttpTriggerConfig, routes::HostRouter};
+use spin_manifest::{
+    normalize::normalize_manifest,
+    profile::apply_profile,
+    schema::v2::{self, AppManifest, ComponentSpec},
+    ManifestVersion,
+};
+use spin_trigger::RuntimeConfig;
+
+use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+
+/// Check an application manifest, and optionally a runtime config file, for
+/// problems which would stop the application from loading or which are
+/// probably mistakes.
+#[derive(Parser, Debug)]
+#[clap(about = "Check the Spin application manifest for problems")]
+pub struct LintCommand {
+    /// The application to check. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// A runtime config file to check as well.
+    #[clap(long = "runtime-config-file")]
+    pub runtime_config_file: Option<PathBuf>,
+
+    /// Print the JSON schema of the manifest, for editor integration, instead
+    /// of checking an application.
+    #[clap(long = "schema")]
+    pub schema: bool,
+}
+
+impl LintCommand {
+    pub async fn run(self) -> Result<()> {
+        if self.schema {
+            print!("{}", v2::JSON_SCHEMA);
+            return Ok(());
+        }
+
+        let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
+        let mut findings = lint_manifest_file(&manifest_file);
+        if let Some(path) = &self.runtime_config_file {
+            if let Err(err) = RuntimeConfig::new(None).merge_config_file(path) {
+                findings.push(Finding::error(format!("{err:#}")));
+            }
+        }
+
+        for finding in &findings {
+            println!("{finding}");
+        }
+        let errors = findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            bail!("{} has {errors} error(s)", quoted_path(&manifest_file));
+        }
+        if findings.is_empty() {
+            println!("No problems found in {}", quoted_path(&manifest_file));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+struct Finding {
+    severity: Severity,
+    message: String,
+}
+
+impl Finding {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}", self.message)
+    }
+}
+
+fn lint_manifest_file(path: &Path) -> Vec<Finding> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            return vec![Finding::error(format!(
+                "Failed to read {}: {err}",
+                quoted_path(path)
+            ))]
+        }
+    };
+    lint_manifest_str(&contents)
+}
+
+fn lint_manifest_str(contents: &str) -> Vec<Finding> {
+    let mut findings = vec![];
+    if let Ok(ManifestVersion::V1) = ManifestVersion::detect(contents) {
+        findings.push(Finding::warning(
+            "Manifest version 1 is deprecated: run `spin doctor` to upgrade to version 2",
+        ));
+    }
+    // Unknown keys are rejected when parsing
+    match spin_manifest::manifest_from_str(contents) {
+        Ok(manifest) => findings.extend(lint_manifest(manifest)),
+        Err(err) => findings.push(Finding::error(err.to_string())),
+    }
+    findings
+}
+
+fn lint_manifest(mut manifest: AppManifest) -> Vec<Finding> {
+    let mut findings = vec![];
+
+    for name in manifest.profiles.keys() {
+        if let Err(err) = apply_profile(&mut manifest.clone(), name) {
+            findings.push(Finding::error(err.to_string()));
+        }
+    }
+
+    // Moves inline components to the top level and fills in trigger IDs, so
+    // that every trigger can be reported by ID
+    normalize_manifest(&mut manifest);
+
+    for (id, component) in &manifest.components {
+        if !component.deprecated_allowed_http_hosts().is_empty() {
+            findings.push(Finding::warning(format!(
+                "Component `{id}` uses the deprecated `allowed_http_hosts`: use `allowed_outbound_hosts` instead"
+            )));
+        }
+    }
+
+    for trigger in manifest.triggers.values().flatten() {
+        let specs = trigger
+            .component
+            .iter()
+            .chain(trigger.components.values().flat_map(|specs| specs.0.iter()));
+        for spec in specs {
+            if let ComponentSpec::Reference(id) = spec {
+                if !manifest.components.contains_key(id) {
+                    findings.push(Finding::error(format!(
+                        "Trigger `{}` refers to component `{id}`, which does not exist",
+                        trigger.id
+                    )));
+                }
+            }
+        }
+    }
+
+    findings.extend(lint_http_routes(&manifest));
+
+    let referenced = manifest
+        .components
+        .values()
+        .flat_map(|component| component.variables.values())
+        .flat_map(|template| template_variables(template))
+        .collect::<HashSet<_>>();
+    for name in manifest.variables.keys() {
+        if !referenced.contains(name.as_ref()) {
+            findings.push(Finding::warning(format!(
+                "Variable `{name}` is not used by any component"
+            )));
+        }
+    }
+
+    findings
+}
+
+fn lint_http_routes(manifest: &AppManifest) -> Vec<Finding> {
+    let mut findings = vec![];
+    let Some(triggers) = manifest.triggers.get("http") else {
+        return findings;
+    };
+
+    let mut routes = vec![];
+    for trigger in triggers {
+        // Check the config the HTTP trigger will see when it loads the app
+        let mut config = match serde_json::to_value(&trigger.config) {
+            Ok(config) => config,
+            Err(err) => {
+                findings.push(Finding::error(format!(
+                    "Invalid config for HTTP trigger `{}`: {err}",
+                    trigger.id
+                )));
+                continue;
+            }
+        };
+        if let (Some(ComponentSpec::Reference(id)), Some(object)) =
+            (&trigger.component, config.as_object_mut())
+        {
+            object.insert("component".into(), id.to_string().into());
+        }
+        match serde_json::from_value::<HttpTriggerConfig>(config) {
+            Ok(config) => {
+                let id = if config.component.is_empty() {
+                    trigger.id.clone()
+                } else {
+                    config.component
+                };
+                routes.push((id, config.host, config.route));
+            }
+            Err(err) => findings.push(Finding::error(format!(
+                "Invalid config for HTTP trigger `{}`: {err}",
+                trigger.id
+            ))),
+        }
+    }
+
+    let base = manifest
+        .application
+        .trigger_global_configs
+        .get("http")
+        .and_then(|config| config.get("base"))
+        .and_then(|base| base.as_str())
+        .unwrap_or("/");
+    let routes = routes
+        .iter()
+        .map(|(id, host, route)| (id.as_str(), host.as_deref(), route.as_str()));
+    match HostRouter::build(base, routes) {
+        Ok((_, duplicates)) => {
+            for dup in duplicates {
+                findings.push(Finding::error(format!(
+                    "Route `{}{}` of `{}` is also the route of `{}`, so `{}` will never be used",
+                    dup.host.as_deref().unwrap_or_default(),
+                    dup.route.full_pattern_non_empty(),
+                    dup.replaced_id,
+                    dup.effective_id,
+                    dup.replaced_id,
+                )));
+            }
+        }
+        Err(err) => findings.push(Finding::error(format!("Invalid HTTP routes: {err}"))),
+    }
+    findings
+}
+
+// Returns the names in the `{{ name }}` expressions of a template.
+fn template_variables(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split("{{")
+        .skip(1)
+        .filter_map(|rest| Some(rest.split_once("}}")?.0.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(manifest: &str) -> Vec<String> {
+        lint_manifest_str(manifest)
+            .iter()
+            .map(|finding| finding.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn clean_manifest_has_no_findings() {
+        let findings = messages(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "clean"
+            [variables]
+            greeting = { default = "hello" }
+            [[trigger.http]]
+            route = "/..."
+            component = "web"
+            [component.web]
+            source = "web.wasm"
+            variables = { message = "{{ greeting }}, world" }
+            "#,
+        );
+        assert!(findings.is_empty(), "{findings:?}");
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        let findings = messages(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "typo"
+            [[trigger.http]]
+            route = "/..."
+            component = "web"
+            [component.web]
+            source = "web.wasm"
+            allowed_outbund_hosts = ["https://example.com"]
+            "#,
+        );
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].starts_with("error: "), "{findings:?}");
+        assert!(
+            findings[0].contains("allowed_outbund_hosts"),
+            "{findings:?}"
+        );
+    }
+
+    #[test]
+    fn reports_conflicts_and_unused_and_deprecated_settings() {
+        let findings = messages(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "problems"
+            [variables]
+            unused = { default = "x" }
+            [[trigger.http]]
+            route = "/api/..."
+            component = "first"
+            [[trigger.http]]
+            route = "/api/..."
+            component = "second"
+            [[trigger.http]]
+            route = "/missing"
+            component = "missing"
+            [component.first]
+            source = "first.wasm"
+            allowed_http_hosts = ["example.com"]
+            [component.second]
+            source = "second.wasm"
+            "#,
+        );
+        assert_eq!(
+            findings,
+            [
+                "warning: Component `first` uses the deprecated `allowed_http_hosts`: use `allowed_outbound_hosts` instead",
+                "error: Trigger `missing-http-trigger` refers to component `missing`, which does not exist",
+                "error: Route `/api/...` of `first` is also the route of `second`, so `first` will never be used",
+                "warning: Variable `unused` is not used by any component",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_template_variables() {
+        let names = template_variables("{{ scheme }}://{{host}}/{ path }").collect::<Vec<_>>();
+        assert_eq!(names, ["scheme", "host"]);
+    }
+}
@@ -0,0 +1,548 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use spin_common::ui::quoted_path;
+use spin_http::config::HttpTriggerConfig;
+use spin_locked_app::locked::{self, LockedApp, LockedComponent};
+
+/// Commands for working with locked application (spin.lock) files.
+#[derive(Subcommand, Debug)]
+pub enum LockCommands {
+    /// Report how one locked application differs from another.
+    Diff(DiffCommand),
+}
+
+impl LockCommands {
+    pub async fn run(self) -> Result<()> {
+        match self {
+            LockCommands::Diff(cmd) => cmd.run().await,
+        }
+    }
+}
+
+/// Compare two locked applications, such as the lock files of the deployed
+/// and the proposed versions of an app, and report the changes a deployment
+/// reviewer cares about: which components' Wasm changed, which routes were
+/// added or removed, how the app's variables changed, and what each
+/// component newly asks of the host, such as outbound hosts or stores.
+#[derive(Parser, Debug)]
+#[clap(about = "Report the differences between two locked applications")]
+pub struct DiffCommand {
+    /// The original lock file.
+    pub old: PathBuf,
+
+    /// The changed lock file.
+    pub new: PathBuf,
+
+    /// The format in which to print the differences.
+    #[clap(value_enum, long = "format", default_value = "text")]
+    pub format: DiffFormat,
+
+    /// Exit with an error if the locked applications differ.
+    #[clap(long = "exit-code", takes_value = false)]
+    pub exit_code: bool,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum DiffFormat {
+    Text,
+    Json,
+}
+
+impl DiffCommand {
+    pub async fn run(self) -> Result<()> {
+        let old = read_locked_app(&self.old)?;
+        let new = read_locked_app(&self.new)?;
+        let diff = LockDiff::new(&old, &new);
+
+        match self.format {
+            DiffFormat::Text if diff.is_empty() => println!("No differences"),
+            DiffFormat::Text => print!("{diff}"),
+            DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        }
+
+        if self.exit_code && !diff.is_empty() {
+            bail!(
+                "{} and {} differ",
+                quoted_path(&self.old),
+                quoted_path(&self.new)
+            );
+        }
+        Ok(())
+    }
+}
+
+fn read_locked_app(path: &Path) -> Result<LockedApp> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", quoted_path(path)))?;
+    LockedApp::from_json(&contents)
+        .with_context(|| format!("Failed to parse lock file {}", quoted_path(path)))
+}
+
+// Component metadata naming things the host must provide or allow for the
+// component to run.
+const HOST_REQUIREMENTS: [&str; 7] = [
+    "allowed_outbound_hosts",
+    "key_value_stores",
+    "databases",
+    "read_only_databases",
+    "blob_stores",
+    "ai_models",
+    "environment_allowlist",
+];
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct LockDiff {
+    components: Vec<ComponentChange>,
+    added_routes: Vec<Route>,
+    removed_routes: Vec<Route>,
+    variables: Vec<VariableChange>,
+    host_requirements: Vec<HostRequirementChange>,
+}
+
+/// A component, or one of its dependencies, whose source was added, removed
+/// or changed.
+#[derive(Debug, PartialEq, Serialize)]
+struct ComponentChange {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependency: Option<String>,
+    change: Change,
+    old_digest: Option<String>,
+    new_digest: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    fn new<T>(old: &Option<T>, new: &Option<T>) -> Self {
+        match (old, new) {
+            (None, Some(_)) => Self::Added,
+            (Some(_), None) => Self::Removed,
+            _ => Self::Changed,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+struct Route {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    route: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    static_dir: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct VariableChange {
+    name: String,
+    old: Option<VariableSchema>,
+    new: Option<VariableSchema>,
+}
+
+/// How a variable is declared. The defaults of secret variables are
+/// compared but never shown.
+#[derive(Debug, PartialEq, Serialize)]
+struct VariableSchema {
+    required: bool,
+    secret: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct HostRequirementChange {
+    component: String,
+    requirement: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl LockDiff {
+    fn new(old: &LockedApp, new: &LockedApp) -> Self {
+        let old_components = components_by_id(old);
+        let new_components = components_by_id(new);
+        let ids = old_components
+            .keys()
+            .chain(new_components.keys())
+            .copied()
+            .collect::<BTreeSet<_>>();
+
+        let mut diff = Self::default();
+        for id in ids {
+            let old = old_components.get(id).copied();
+            let new = new_components.get(id).copied();
+            diff.components.extend(component_changes(id, old, new));
+            diff.host_requirements
+                .extend(host_requirement_changes(id, old, new));
+        }
+
+        let old_routes = routes(old);
+        let new_routes = routes(new);
+        diff.added_routes = routes_difference(&new_routes, &old_routes);
+        diff.removed_routes = routes_difference(&old_routes, &new_routes);
+
+        let names = old
+            .variables
+            .keys()
+            .chain(new.variables.keys())
+            .collect::<BTreeSet<_>>();
+        for name in names {
+            let old = old.variables.get(name).map(variable_schema);
+            let new = new.variables.get(name).map(variable_schema);
+            if old != new {
+                diff.variables.push(VariableChange {
+                    name: name.clone(),
+                    old: old.map(redacted),
+                    new: new.map(redacted),
+                });
+            }
+        }
+        diff
+    }
+
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+fn components_by_id(app: &LockedApp) -> BTreeMap<&str, &LockedComponent> {
+    app.components.iter().map(|c| (c.id.as_str(), c)).collect()
+}
+
+fn component_changes(
+    id: &str,
+    old: Option<&LockedComponent>,
+    new: Option<&LockedComponent>,
+) -> Vec<ComponentChange> {
+    let digest = |c: Option<&LockedComponent>| c.and_then(|c| c.source.content.digest.clone());
+    let mut changes = vec![];
+    if old.is_none() || new.is_none() || digest(old) != digest(new) {
+        changes.push(ComponentChange {
+            id: id.to_owned(),
+            dependency: None,
+            change: Change::new(&old, &new),
+            old_digest: digest(old),
+            new_digest: digest(new),
+        });
+    }
+    // Dependencies of added or removed components go with the component
+    if let (Some(old), Some(new)) = (old, new) {
+        let old_deps = &old.source.dependencies;
+        let new_deps = &new.source.dependencies;
+        let names = old_deps
+            .keys()
+            .chain(new_deps.keys())
+            .collect::<BTreeSet<_>>();
+        for name in names {
+            let old = old_deps.get(name);
+            let new = new_deps.get(name);
+            let digest = |d: Option<&locked::LockedComponentSource>| {
+                d.and_then(|d| d.content.digest.clone())
+            };
+            if old.is_none() || new.is_none() || digest(old) != digest(new) {
+                changes.push(ComponentChange {
+                    id: id.to_owned(),
+                    dependency: Some(name.clone()),
+                    change: Change::new(&old, &new),
+                    old_digest: digest(old),
+                    new_digest: digest(new),
+                });
+            }
+        }
+    }
+    changes
+}
+
+fn host_requirement_changes(
+    id: &str,
+    old: Option<&LockedComponent>,
+    new: Option<&LockedComponent>,
+) -> Vec<HostRequirementChange> {
+    let values = |c: Option<&LockedComponent>, key: &'static str| -> BTreeSet<String> {
+        c.and_then(|c| {
+            let value = c.metadata.get(key)?;
+            serde_json::from_value::<Vec<String>>(value.clone()).ok()
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+    };
+    HOST_REQUIREMENTS
+        .into_iter()
+        .filter_map(|key| {
+            let old = values(old, key);
+            let new = values(new, key);
+            let added = new.difference(&old).cloned().collect::<Vec<_>>();
+            let removed = old.difference(&new).cloned().collect::<Vec<_>>();
+            (!added.is_empty() || !removed.is_empty()).then(|| HostRequirementChange {
+                component: id.to_owned(),
+                requirement: key.to_owned(),
+                added,
+                removed,
+            })
+        })
+        .collect()
+}
+
+fn routes(app: &LockedApp) -> BTreeSet<Route> {
+    app.triggers
+        .iter()
+        .filter(|t| t.trigger_type == "http")
+        .filter_map(|t| serde_json::from_value::<HttpTriggerConfig>(t.trigger_config.clone()).ok())
+        .map(|config| Route {
+            host: config.host,
+            route: config.route,
+            component: (!config.component.is_empty()).then_some(config.component),
+            static_dir: config.static_dir,
+        })
+        .collect()
+}
+
+fn routes_difference(routes: &BTreeSet<Route>, other: &BTreeSet<Route>) -> Vec<Route> {
+    routes
+        .iter()
+        .filter(|r| !other.contains(r))
+        .cloned()
+        .collect()
+}
+
+fn variable_schema(variable: &locked::Variable) -> VariableSchema {
+    VariableSchema {
+        required: variable.default.is_none(),
+        secret: variable.secret,
+        default: variable.default.clone(),
+    }
+}
+
+fn redacted(schema: VariableSchema) -> VariableSchema {
+    VariableSchema {
+        default: schema.default.map(|d| {
+            if schema.secret {
+                "<redacted>".into()
+            } else {
+                d
+            }
+        }),
+        ..schema
+    }
+}
+
+impl fmt::Display for LockDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digest = |d: &Option<String>| d.clone().unwrap_or_else(|| "no digest".into());
+        if !self.components.is_empty() {
+            writeln!(f, "Components:")?;
+        }
+        for change in &self.components {
+            let name = match &change.dependency {
+                Some(dependency) => format!("{} (dependency {dependency:?})", change.id),
+                None => change.id.clone(),
+            };
+            let (old, new) = (digest(&change.old_digest), digest(&change.new_digest));
+            match change.change {
+                Change::Added => writeln!(f, "  + {name} ({new})")?,
+                Change::Removed => writeln!(f, "  - {name} ({old})")?,
+                Change::Changed => writeln!(f, "  ~ {name}: {old} -> {new}")?,
+            }
+        }
+        if !self.added_routes.is_empty() || !self.removed_routes.is_empty() {
+            writeln!(f, "Routes:")?;
+        }
+        for route in &self.added_routes {
+            writeln!(f, "  + {route}")?;
+        }
+        for route in &self.removed_routes {
+            writeln!(f, "  - {route}")?;
+        }
+        if !self.variables.is_empty() {
+            writeln!(f, "Variables:")?;
+        }
+        for change in &self.variables {
+            match (&change.old, &change.new) {
+                (None, Some(new)) => writeln!(f, "  + {} ({new})", change.name)?,
+                (Some(old), None) => writeln!(f, "  - {} ({old})", change.name)?,
+                (Some(old), Some(new)) => writeln!(f, "  ~ {}: {old} -> {new}", change.name)?,
+                (None, None) => {}
+            }
+        }
+        if !self.host_requirements.is_empty() {
+            writeln!(f, "Host requirements:")?;
+        }
+        for change in &self.host_requirements {
+            for value in &change.added {
+                writeln!(
+                    f,
+                    "  + {}: {} {value}",
+                    change.component, change.requirement
+                )?;
+            }
+            for value in &change.removed {
+                writeln!(
+                    f,
+                    "  - {}: {} {value}",
+                    change.component, change.requirement
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(host) = &self.host {
+            write!(f, "{host}")?;
+        }
+        write!(f, "{}", self.route)?;
+        match (&self.component, &self.static_dir) {
+            (Some(component), _) => write!(f, " -> {component}"),
+            (None, Some(dir)) => write!(f, " -> static {dir:?}"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for VariableSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.default {
+            Some(default) => write!(f, "default {default:?}")?,
+            None => write!(f, "required")?,
+        }
+        if self.secret {
+            write!(f, ", secret")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn locked_app(
+        components: serde_json::Value,
+        routes: &[(&str, &str)],
+        variables: serde_json::Value,
+    ) -> LockedApp {
+        let triggers = routes
+            .iter()
+            .map(|(route, component)| {
+                json!({
+                    "id": format!("trigger-{component}"),
+                    "trigger_type": "http",
+                    "trigger_config": { "route": route, "component": component },
+                })
+            })
+            .collect::<Vec<_>>();
+        let app = json!({
+            "spin_lock_version": 0,
+            "variables": variables,
+            "triggers": triggers,
+            "components": components,
+        });
+        LockedApp::from_json(&serde_json::to_vec(&app).unwrap()).unwrap()
+    }
+
+    fn component(id: &str, digest: &str, outbound_hosts: &[&str]) -> serde_json::Value {
+        json!({
+            "id": id,
+            "metadata": { "allowed_outbound_hosts": outbound_hosts },
+            "source": {
+                "content_type": "application/wasm",
+                "digest": digest,
+            },
+        })
+    }
+
+    #[test]
+    fn identical_apps_have_no_differences() {
+        let app = || {
+            locked_app(
+                json!([component("web", "sha256:1", &[])]),
+                &[("/...", "web")],
+                json!({ "token": { "secret": true } }),
+            )
+        };
+        let diff = LockDiff::new(&app(), &app());
+        assert!(diff.is_empty(), "{diff:?}");
+    }
+
+    #[test]
+    fn reports_changes_for_review() {
+        let old = locked_app(
+            json!([
+                component("web", "sha256:1", &[]),
+                component("legacy", "sha256:2", &[]),
+            ]),
+            &[("/...", "web"), ("/old/...", "legacy")],
+            json!({ "token": { "default": "abc", "secret": true } }),
+        );
+        let new = locked_app(
+            json!([
+                component("web", "sha256:3", &["https://api.example.com"]),
+                component("api", "sha256:4", &[]),
+            ]),
+            &[("/...", "web"), ("/api/...", "api")],
+            json!({ "token": { "default": "xyz", "secret": true }, "region": {} }),
+        );
+
+        let diff = LockDiff::new(&old, &new);
+
+        let components = diff
+            .components
+            .iter()
+            .map(|c| (c.id.as_str(), &c.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            components,
+            [
+                ("api", &Change::Added),
+                ("legacy", &Change::Removed),
+                ("web", &Change::Changed),
+            ]
+        );
+        let routes = |routes: &[Route]| routes.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        assert_eq!(routes(&diff.added_routes), ["/api/... -> api"]);
+        assert_eq!(routes(&diff.removed_routes), ["/old/... -> legacy"]);
+
+        let variables = diff
+            .variables
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(variables, ["region", "token"]);
+        let token = &diff.variables[1];
+        assert_eq!(
+            token.new.as_ref().unwrap().default.as_deref(),
+            Some("<redacted>")
+        );
+
+        assert_eq!(
+            diff.host_requirements,
+            [HostRequirementChange {
+                component: "web".into(),
+                requirement: "allowed_outbound_hosts".into(),
+                added: vec!["https://api.example.com".into()],
+                removed: vec![],
+            }]
+        );
+        assert!(!diff.to_string().contains("xyz"));
+    }
+}
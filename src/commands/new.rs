@@ -10,7 +10,10 @@ use itertools::Itertools;
 use path_absolutize::Absolutize;
 use tokio;
 
-use spin_templates::{RunOptions, Template, TemplateManager, TemplateVariantInfo};
+use spin_templates::{
+    InstallOptions, ProgressReporter, RunOptions, Template, TemplateManager, TemplateSource,
+    TemplateVariantInfo,
+};
 
 use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
 
@@ -39,6 +42,12 @@ pub struct TemplateNewCommandCore {
     )]
     pub tags: Vec<String>,
 
+    /// Use templates from an OCI registry reference, as pushed by `spin templates push`,
+    /// rather than installed templates. The templates are not installed. Pin the
+    /// templates to exact content with a digest reference.
+    #[clap(long = "from-oci")]
+    pub from_oci: Option<String>,
+
     /// The directory in which to create the new application or component.
     /// The default is the name argument.
     #[clap(short = 'o', long = "output", group = "location")]
@@ -121,8 +130,28 @@ impl AddCommand {
 
 impl TemplateNewCommandCore {
     pub async fn run(&self, variant: TemplateVariantInfo) -> Result<()> {
-        let template_manager = TemplateManager::try_default()
-            .context("Failed to construct template directory path")?;
+        // Templates from a registry are installed into a directory which lasts
+        // only as long as the command
+        let oci_templates_dir = tempfile::tempdir()?;
+        let template_manager = match &self.from_oci {
+            Some(reference) => {
+                let template_manager = TemplateManager::in_dir(oci_templates_dir.path());
+                let installation_results = template_manager
+                    .install(
+                        &TemplateSource::Oci(reference.clone()),
+                        &InstallOptions::default(),
+                        &QuietProgressReporter,
+                    )
+                    .await
+                    .with_context(|| format!("Failed to get templates from {reference}"))?;
+                if installation_results.installed.is_empty() {
+                    bail!("{reference} contains no valid templates");
+                }
+                template_manager
+            }
+            None => TemplateManager::try_default()
+                .context("Failed to construct template directory path")?,
+        };
 
         let (name, template_id) = self.resolve_name_template_syntax(&template_manager, &variant)?;
 
@@ -326,6 +355,12 @@ async fn list_or_install_templates(
     }
 }
 
+struct QuietProgressReporter;
+
+impl ProgressReporter for QuietProgressReporter {
+    fn report(&self, _: impl AsRef<str>) {}
+}
+
 async fn prompt_name(variant: &TemplateVariantInfo) -> anyhow::Result<String> {
     let noun = variant.prompt_noun();
     let mut prompt = format!("Enter a name for your new {noun}");
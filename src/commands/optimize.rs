@@ -0,0 +1,193 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path, url::parse_file_url};
+use spin_loader::FilesMountStrategy;
+use spin_locked_app::locked::LockedComponent;
+
+use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+
+// The lock file written to the output directory
+const LOCK_FILE: &str = "spin.lock";
+
+/// Pre-initialize an application's components with Wizer, running each
+/// component's initialization function once and snapshotting the resulting
+/// memory, so that instances start from the initialized state. This removes
+/// language runtime startup, such as loading an interpreter's standard
+/// library, from every request.
+///
+/// Writes the pre-initialized components and a lock file which references
+/// them. Run the result by passing the lock file's URL as `SPIN_LOCKED_URL`
+/// to a trigger executor.
+#[derive(Parser, Debug)]
+#[clap(about = "Pre-initialize an application's components to speed up instantiation")]
+pub struct OptimizeCommand {
+    /// The application to optimize. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// Component ID to pre-initialize. This can be specified multiple times.
+    /// The default is all components.
+    #[clap(short = 'c', long, multiple = true)]
+    pub component_id: Vec<String>,
+
+    /// The function each component exports to initialize itself.
+    #[clap(long = "init-func", default_value = "wizer.initialize")]
+    pub init_func: String,
+
+    /// The Wizer executable to run.
+    #[clap(long = "wizer", env = "SPIN_WIZER", default_value = "wizer")]
+    pub wizer: PathBuf,
+
+    /// The directory to write the lock file, pre-initialized components and
+    /// application files to. Sources are referenced by absolute path, so the
+    /// output must be used where it is written.
+    #[clap(short = 'o', long = "output", default_value = "optimized")]
+    pub output: PathBuf,
+}
+
+impl OptimizeCommand {
+    pub async fn run(self) -> Result<()> {
+        let wizer = which::which(&self.wizer).with_context(|| {
+            format!(
+                "Could not find Wizer at {}: install it with `cargo install wizer --all-features`, or pass its path with --wizer",
+                quoted_path(&self.wizer)
+            )
+        })?;
+
+        let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
+        tokio::fs::create_dir_all(&self.output)
+            .await
+            .with_context(|| format!("Failed to create {}", quoted_path(&self.output)))?;
+        let output = self.output.canonicalize()?;
+
+        let files_mount_strategy = FilesMountStrategy::Copy(output.join("assets"));
+        let mut locked_app = spin_loader::from_file(&manifest_file, files_mount_strategy, None)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load manifest from {}",
+                    quoted_path(&manifest_file)
+                )
+            })?;
+
+        for id in &self.component_id {
+            if !locked_app.components.iter().any(|c| &c.id == id) {
+                bail!("No component {id:?} in {}", quoted_path(&manifest_file));
+            }
+        }
+
+        for component in &mut locked_app.components {
+            if !self.component_id.is_empty() && !self.component_id.contains(&component.id) {
+                continue;
+            }
+            self.optimize_component(&wizer, component, &output)
+                .with_context(|| {
+                    format!("Failed to pre-initialize component {:?}", component.id)
+                })?;
+        }
+
+        let lock_path = output.join(LOCK_FILE);
+        let contents =
+            serde_json::to_vec_pretty(&locked_app).context("Failed to serialize locked app")?;
+        tokio::fs::write(&lock_path, contents)
+            .await
+            .with_context(|| format!("Failed to write {}", quoted_path(&lock_path)))?;
+        let lock_url = url::Url::from_file_path(&lock_path)
+            .map_err(|_| anyhow!("Cannot convert to file URL: {}", quoted_path(&lock_path)))?;
+        println!("Optimized application written to {lock_url}");
+        Ok(())
+    }
+
+    fn optimize_component(
+        &self,
+        wizer: &Path,
+        component: &mut LockedComponent,
+        output: &Path,
+    ) -> Result<()> {
+        let source = component
+            .source
+            .content
+            .source
+            .as_deref()
+            .context("component loaded from disk should contain a file source")?;
+        let path = parse_file_url(source)?;
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("Failed to read {}", quoted_path(&path)))?;
+        if is_component(&bytes) {
+            terminal::warn!(
+                "Component {:?} is built as a component rather than a module, so will not be pre-initialized. Pre-initialize it with its language toolchain instead.",
+                component.id
+            );
+            return Ok(());
+        }
+
+        let dest = output.join(format!("{}.wasm", component.id));
+        let mut cmd = std::process::Command::new(wizer);
+        cmd.arg(&path)
+            .arg("-o")
+            .arg(&dest)
+            .arg("--init-func")
+            .arg(&self.init_func)
+            .arg("--allow-wasi");
+        // Initialization sees the same files the component sees at runtime
+        for mount in &component.files {
+            if let Some(source) = &mount.content.source {
+                let host_path = parse_file_url(source)?;
+                cmd.arg("--mapdir").arg(format!(
+                    "{}::{}",
+                    mount.path.display(),
+                    host_path.display()
+                ));
+            }
+        }
+        let status = cmd
+            .status()
+            .with_context(|| format!("Failed to run {}", quoted_path(wizer)))?;
+        if !status.success() {
+            bail!("Wizer failed with {status}");
+        }
+
+        let optimized = std::fs::read(&dest)
+            .with_context(|| format!("Failed to read {}", quoted_path(&dest)))?;
+        let dest_url = url::Url::from_file_path(&dest)
+            .map_err(|_| anyhow!("Cannot convert to file URL: {}", quoted_path(&dest)))?;
+        component.source.content.source = Some(dest_url.to_string());
+        component.source.content.digest =
+            Some(format!("sha256:{}", hex_digest_from_bytes(&optimized)));
+        println!(
+            "Pre-initialized component {:?} ({} to {} bytes)",
+            component.id,
+            bytes.len(),
+            optimized.len()
+        );
+        Ok(())
+    }
+}
+
+// Wizer snapshots core modules. Components share the Wasm magic number but
+// have a different version and layer in their header.
+fn is_component(bytes: &[u8]) -> bool {
+    bytes.get(4..8) == Some(&[0x0d, 0x00, 0x01, 0x00][..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distinguishes_components_from_modules() {
+        assert!(!is_component(b"\0asm\x01\0\0\0"));
+        assert!(is_component(b"\0asm\x0d\0\x01\0"));
+        assert!(!is_component(b"\0asm"));
+    }
+}
@@ -1,9 +1,11 @@
 // Needed for clap derive: https://github.com/clap-rs/clap/issues/4857
 #![allow(clippy::almost_swapped)]
 
-use anyhow::{anyhow, Context, Result};
+mod lockfile;
+
+use anyhow::{anyhow, bail, Context, Result};
 use clap::{Parser, Subcommand};
-use semver::Version;
+use semver::{Version, VersionReq};
 use spin_plugins::{
     error::Error,
     lookup::{fetch_plugins_repo, plugins_repo_url, PluginLookup},
@@ -17,6 +19,8 @@ use url::Url;
 use crate::build_info::*;
 use crate::opts::*;
 
+use self::lockfile::{LockedPlugin, PluginsLock, PLUGINS_LOCK_FILE};
+
 /// Install/uninstall Spin plugins.
 #[derive(Subcommand, Debug)]
 pub enum PluginCommands {
@@ -63,7 +67,7 @@ pub struct Install {
         name = PLUGIN_NAME_OPT,
         conflicts_with = PLUGIN_REMOTE_PLUGIN_MANIFEST_OPT,
         conflicts_with = PLUGIN_LOCAL_PLUGIN_MANIFEST_OPT,
-        required_unless_present_any = [PLUGIN_REMOTE_PLUGIN_MANIFEST_OPT, PLUGIN_LOCAL_PLUGIN_MANIFEST_OPT],
+        required_unless_present_any = [PLUGIN_REMOTE_PLUGIN_MANIFEST_OPT, PLUGIN_LOCAL_PLUGIN_MANIFEST_OPT, PLUGIN_LOCKED_OPT],
     )]
     pub name: Option<String>,
 
@@ -105,17 +109,52 @@ pub struct Install {
         requires(PLUGIN_NAME_OPT)
     )]
     pub version: Option<Version>,
+
+    /// Install the highest compatible version of the plugin satisfying this
+    /// requirement, such as `^0.7`, from the centralized plugins repository.
+    #[clap(
+        long = "version-req",
+        conflicts_with = "version",
+        requires(PLUGIN_NAME_OPT)
+    )]
+    pub version_req: Option<VersionReq>,
+
+    /// Record the plugin's version requirement and installed version in the
+    /// project's `spin-plugins.lock`. The requirement is that given by
+    /// `--version` or `--version-req`, or else compatibility with the
+    /// installed version.
+    #[clap(long = "save", takes_value = false, requires(PLUGIN_NAME_OPT))]
+    pub save: bool,
+
+    /// Install the exact versions of the plugins recorded in the project's
+    /// `spin-plugins.lock`, downgrading installed plugins if necessary.
+    #[clap(
+        name = PLUGIN_LOCKED_OPT,
+        long = "locked",
+        takes_value = false,
+        conflicts_with_all = &[PLUGIN_NAME_OPT, PLUGIN_REMOTE_PLUGIN_MANIFEST_OPT, PLUGIN_LOCAL_PLUGIN_MANIFEST_OPT],
+    )]
+    pub locked: bool,
 }
 
 impl Install {
     pub async fn run(&self) -> Result<()> {
+        if self.locked {
+            return self.install_locked().await;
+        }
+        let manager = PluginManager::try_default()?;
+        let version = match (&self.name, &self.version_req) {
+            (Some(name), Some(req)) => Some(
+                resolve_version_req(&manager, name, req, self.override_compatibility_check).await?,
+            ),
+            _ => self.version.clone(),
+        };
         let manifest_location = match (&self.local_manifest_src, &self.remote_manifest_src, &self.name) {
             (Some(path), None, None) => ManifestLocation::Local(path.to_path_buf()),
             (None, Some(url), None) => ManifestLocation::Remote(url.clone()),
-            (None, None, Some(name)) => ManifestLocation::PluginsRepository(PluginLookup::new(name, self.version.clone())),
+            (None, None, Some(name)) => ManifestLocation::PluginsRepository(PluginLookup::new(name, version)),
             _ => return Err(anyhow::anyhow!("For plugin lookup, must provide exactly one of: plugin name, url to manifest, local path to manifest")),
         };
-        let manager = PluginManager::try_default()?;
         // Downgrades are only allowed via the `upgrade` subcommand
         let downgrade = false;
         let manifest = manager
@@ -134,8 +173,100 @@ impl Install {
             &manifest_location,
         )
         .await?;
+        if self.save {
+            self.save_to_lock(&manifest)?;
+        }
+        Ok(())
+    }
+
+    fn save_to_lock(&self, manifest: &PluginManifest) -> Result<()> {
+        let version = manifest
+            .try_version()
+            .with_context(|| format!("Plugin '{}' has an invalid version", manifest.name()))?;
+        let requirement = match (&self.version_req, &self.version) {
+            (Some(req), _) => req.clone(),
+            (None, Some(version)) => VersionReq::parse(&format!("={version}"))?,
+            (None, None) => VersionReq::parse(&format!("^{version}"))?,
+        };
+        let path = Path::new(PLUGINS_LOCK_FILE);
+        let mut lock = PluginsLock::load_or_default(path)?;
+        lock.upsert(LockedPlugin {
+            name: manifest.name(),
+            requirement,
+            version,
+        });
+        lock.save(path)?;
+        println!("Saved plugin '{}' to {PLUGINS_LOCK_FILE}", manifest.name());
         Ok(())
     }
+
+    async fn install_locked(&self) -> Result<()> {
+        let path = Path::new(PLUGINS_LOCK_FILE);
+        if !path.exists() {
+            bail!("No {PLUGINS_LOCK_FILE} in the current directory: save plugins to it with `spin plugins install <NAME> --save`");
+        }
+        let lock = PluginsLock::load(path)?;
+        let manager = PluginManager::try_default()?;
+        for plugin in &lock.plugins {
+            if !plugin.requirement.matches(&plugin.version) {
+                bail!(
+                    "{PLUGINS_LOCK_FILE} records version {} of plugin '{}', which does not satisfy its requirement {}",
+                    plugin.version,
+                    plugin.name,
+                    plugin.requirement
+                );
+            }
+            let manifest_location = ManifestLocation::PluginsRepository(PluginLookup::new(
+                &plugin.name,
+                Some(plugin.version.clone()),
+            ));
+            let manifest = manager
+                .get_manifest(
+                    &manifest_location,
+                    self.override_compatibility_check,
+                    SPIN_VERSION,
+                )
+                .await?;
+            // The lock file specifies exact versions, older or newer
+            let downgrade = true;
+            try_install(
+                &manifest,
+                &manager,
+                self.yes_to_all,
+                self.override_compatibility_check,
+                downgrade,
+                &manifest_location,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the highest version of the plugin in the plugins repository
+/// which satisfies the requirement and, unless overridden, is compatible
+/// with this version of Spin.
+async fn resolve_version_req(
+    manager: &PluginManager,
+    name: &str,
+    req: &VersionReq,
+    override_compatibility_check: bool,
+) -> Result<Version> {
+    let plugins_dir = manager.store().get_plugins_directory();
+    fetch_plugins_repo(&plugins_repo_url()?, plugins_dir, false).await?;
+    let name = name.to_lowercase();
+    manager
+        .store()
+        .catalogue_manifests()?
+        .into_iter()
+        .filter(|m| m.name() == name && m.has_compatible_package())
+        .filter(|m| override_compatibility_check || m.is_compatible_spin_version(SPIN_VERSION))
+        .filter_map(|m| m.try_version().ok())
+        .filter(|version| req.matches(version))
+        .max()
+        .with_context(|| {
+            format!("No compatible version of plugin '{name}' satisfies the requirement {req}")
+        })
 }
 
 /// Uninstalls specified plugin.
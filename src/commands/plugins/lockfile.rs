@@ -0,0 +1,105 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use spin_common::ui::quoted_path;
+
+/// The project-local file recording the plugins a project uses.
+pub const PLUGINS_LOCK_FILE: &str = "spin-plugins.lock";
+
+const HEADER: &str = "# Plugins used by this project, maintained by `spin plugins install --save`.\n# Run `spin plugins install --locked` to install these exact versions.\n\n";
+
+/// The plugins a project uses: the version requirement of each, and the
+/// version of each which was installed when it was saved.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginsLock {
+    #[serde(rename = "plugin", default)]
+    pub plugins: Vec<LockedPlugin>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedPlugin {
+    pub name: String,
+    pub requirement: VersionReq,
+    pub version: Version,
+}
+
+impl PluginsLock {
+    /// Reads the lock file at `path`, or returns an empty lock if there is
+    /// no such file.
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", quoted_path(path)))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse plugins lock file {}", quoted_path(path)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = toml::to_string(self).context("Failed to serialize plugins lock file")?;
+        std::fs::write(path, format!("{HEADER}{contents}"))
+            .with_context(|| format!("Failed to write {}", quoted_path(path)))
+    }
+
+    /// Records a plugin, replacing any existing record of it. Plugins are
+    /// kept in name order so that the file diffs cleanly.
+    pub fn upsert(&mut self, plugin: LockedPlugin) {
+        self.plugins.retain(|p| p.name != plugin.name);
+        self.plugins.push(plugin);
+        self.plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(name: &str, requirement: &str, version: &str) -> LockedPlugin {
+        LockedPlugin {
+            name: name.to_owned(),
+            requirement: requirement.parse().unwrap(),
+            version: version.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn upsert_replaces_and_sorts() {
+        let mut lock = PluginsLock::default();
+        lock.upsert(locked("js2wasm", "^0.6", "0.6.1"));
+        lock.upsert(locked("cloud", "^0.7", "0.7.0"));
+        lock.upsert(locked("js2wasm", "=0.5.1", "0.5.1"));
+        assert_eq!(
+            lock.plugins,
+            [
+                locked("cloud", "^0.7", "0.7.0"),
+                locked("js2wasm", "=0.5.1", "0.5.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PLUGINS_LOCK_FILE);
+        assert!(PluginsLock::load_or_default(&path)
+            .unwrap()
+            .plugins
+            .is_empty());
+
+        let mut lock = PluginsLock::default();
+        lock.upsert(locked("cloud", "^0.7", "0.7.0"));
+        lock.save(&path).unwrap();
+
+        let loaded = PluginsLock::load(&path).unwrap();
+        assert_eq!(loaded.plugins, lock.plugins);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path, url::parse_file_url};
+use spin_loader::FilesMountStrategy;
+use spin_locked_app::locked::{
+    ContentRef, LockedApp, LockedComponent, LockedComponentSource,
+    PRECOMPILED_COMPONENT_CONTENT_TYPE,
+};
+
+use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+
+// The lock file written to the output directory
+const LOCK_FILE: &str = "spin.lock";
+
+/// Compile an application's components ahead of time, writing the compiled
+/// components and a lock file which references them. Run the result by
+/// passing the lock file's URL as `SPIN_LOCKED_URL` to a trigger executor.
+#[derive(Parser, Debug)]
+#[clap(about = "Compile an application's components ahead of time")]
+pub struct PrecompileCommand {
+    /// The application to precompile. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// The target triple to compile for, e.g. "aarch64-unknown-linux-gnu".
+    /// If omitted, components are compiled for the host.
+    #[clap(long = "target")]
+    pub target: Option<String>,
+
+    /// The directory to write the lock file, compiled components and
+    /// application files to. Sources are referenced by absolute path, so the
+    /// output must be used where it is written.
+    #[clap(short = 'o', long = "output", default_value = "precompiled")]
+    pub output: PathBuf,
+}
+
+impl PrecompileCommand {
+    pub async fn run(self) -> Result<()> {
+        let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
+        tokio::fs::create_dir_all(&self.output)
+            .await
+            .with_context(|| format!("Failed to create {}", quoted_path(&self.output)))?;
+        let output = self.output.canonicalize()?;
+
+        let files_mount_strategy = FilesMountStrategy::Copy(output.join("assets"));
+        let mut locked_app = spin_loader::from_file(&manifest_file, files_mount_strategy, None)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load manifest from {}",
+                    quoted_path(&manifest_file)
+                )
+            })?;
+
+        let mut config = spin_core::Config::default();
+        if let Some(target) = &self.target {
+            config
+                .target(target)
+                .with_context(|| format!("Unsupported target {target:?}"))?;
+        }
+        let mut builder = spin_core::Engine::<()>::builder(&config)?;
+        builder.epoch_ticker_thread(false);
+        let engine = builder.build();
+
+        let module_components = module_component_ids(&locked_app);
+        for component in &mut locked_app.components {
+            if module_components.contains(&component.id) {
+                terminal::warn!(
+                    "Component {:?} is run as a module, so will not be precompiled",
+                    component.id
+                );
+                continue;
+            }
+            if !component.source.dependencies.is_empty() {
+                terminal::warn!(
+                    "Component {:?} has dependencies, so will not be precompiled",
+                    component.id
+                );
+                continue;
+            }
+            precompile_component(engine.as_ref(), component, &output)
+                .with_context(|| format!("Failed to precompile component {:?}", component.id))?;
+        }
+
+        let lock_path = output.join(LOCK_FILE);
+        let contents =
+            serde_json::to_vec_pretty(&locked_app).context("Failed to serialize locked app")?;
+        tokio::fs::write(&lock_path, contents)
+            .await
+            .with_context(|| format!("Failed to write {}", quoted_path(&lock_path)))?;
+        let lock_url = url::Url::from_file_path(&lock_path)
+            .map_err(|_| anyhow!("Cannot convert to file URL: {}", quoted_path(&lock_path)))?;
+        println!("Precompiled application written to {lock_url}");
+        Ok(())
+    }
+}
+
+fn precompile_component(
+    engine: &spin_core::wasmtime::Engine,
+    component: &mut LockedComponent,
+    output: &Path,
+) -> Result<()> {
+    let source = component
+        .source
+        .content
+        .source
+        .as_deref()
+        .context("component loaded from disk should contain a file source")?;
+    let path = parse_file_url(source)?;
+    let bytes =
+        std::fs::read(&path).with_context(|| format!("Failed to read {}", quoted_path(&path)))?;
+    let componentized = spin_componentize::componentize_if_necessary(&bytes)?;
+    let compiled = engine.precompile_component(&componentized)?;
+
+    let dest = output.join(format!("{}.cwasm", component.id));
+    std::fs::write(&dest, &compiled)
+        .with_context(|| format!("Failed to write {}", quoted_path(&dest)))?;
+    let dest_url = url::Url::from_file_path(&dest)
+        .map_err(|_| anyhow!("Cannot convert to file URL: {}", quoted_path(&dest)))?;
+    component.source = LockedComponentSource {
+        content_type: PRECOMPILED_COMPONENT_CONTENT_TYPE.into(),
+        content: ContentRef {
+            source: Some(dest_url.to_string()),
+            digest: Some(format!("sha256:{}", hex_digest_from_bytes(&compiled))),
+            ..Default::default()
+        },
+        dependencies: Default::default(),
+    };
+    Ok(())
+}
+
+// Wagi components are loaded as modules rather than components, so can't be
+// precompiled as components.
+fn module_component_ids(locked_app: &LockedApp) -> HashSet<String> {
+    locked_app
+        .triggers
+        .iter()
+        .filter(|trigger| trigger.trigger_config["executor"]["type"].as_str() == Some("wagi"))
+        .filter_map(|trigger| trigger.trigger_config["component"].as_str())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_wagi_components() {
+        let locked_app = LockedApp::from_json(
+            serde_json::json!({
+                "spin_lock_version": 0,
+                "triggers": [
+                    {
+                        "id": "trigger-spin",
+                        "trigger_type": "http",
+                        "trigger_config": { "component": "spin", "route": "/" }
+                    },
+                    {
+                        "id": "trigger-wagi",
+                        "trigger_type": "http",
+                        "trigger_config": {
+                            "component": "wagi",
+                            "route": "/wagi",
+                            "executor": { "type": "wagi" }
+                        }
+                    }
+                ],
+                "components": []
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            module_component_ids(&locked_app),
+            HashSet::from(["wagi".to_owned()])
+        );
+    }
+}
@@ -1,8 +1,11 @@
 use crate::opts::*;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
 use indicatif::{ProgressBar, ProgressStyle};
-use spin_oci::Client;
+use spin_oci::{
+    client::{PlatformArtifact, PushOptions},
+    Client,
+};
 use std::{io::Read, path::PathBuf, time::Duration};
 
 /// Commands for working with OCI registries to distribute applications.
@@ -53,6 +56,19 @@ pub struct Push {
     #[clap(long, takes_value = false, env = ALWAYS_BUILD_ENV)]
     pub build: bool,
 
+    /// An annotation to attach to the pushed application, of the form
+    /// `key=value`, e.g. `org.opencontainers.image.source=https://github.com/...`.
+    /// May be repeated. The title, version, description and authors annotations
+    /// default to those of the application.
+    #[clap(long = "annotation", parse(try_from_str = parse_annotation))]
+    pub annotations: Vec<(String, String)>,
+
+    /// A platform-specific artifact, such as a plugin or runtime binary, to
+    /// publish alongside the application, of the form `<os>/<arch>=<path>`,
+    /// e.g. `linux/amd64=target/release/plugin`. May be repeated.
+    #[clap(long = "platform-artifact")]
+    pub platform_artifacts: Vec<PlatformArtifact>,
+
     /// Reference in the registry of the Spin application.
     /// This is a string whose format is defined by the registry standard, and generally consists of <registry>/<username>/<application-name>:<version>. E.g. ghcr.io/ogghead/spin-test-app:0.1.0
     #[clap()]
@@ -63,14 +79,18 @@ impl Push {
     pub async fn run(self) -> Result<()> {
         let app_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
         if self.build {
-            spin_build::build(&app_file, &[]).await?;
+            spin_build::build(&app_file, &[], false).await?;
         }
 
         let mut client = spin_oci::Client::new(self.insecure, None).await?;
 
         let _spinner = create_dotted_spinner(2000, "Pushing app to the Registry".to_owned());
 
-        let digest = client.push(&app_file, &self.reference).await?;
+        let options = PushOptions {
+            annotations: self.annotations.into_iter().collect(),
+            platform_artifacts: self.platform_artifacts,
+        };
+        let digest = client.push(&app_file, &self.reference, &options).await?;
         match digest {
             Some(digest) => println!("Pushed with digest {digest}"),
             None => println!("Pushed; the registry did not return the digest"),
@@ -80,6 +100,14 @@ impl Push {
     }
 }
 
+fn parse_annotation(s: &str) -> Result<(String, String)> {
+    let parts: Vec<_> = s.splitn(2, '=').collect();
+    if parts.len() != 2 {
+        bail!("Annotation must be of the form `key=value`");
+    }
+    Ok((parts[0].to_owned(), parts[1].to_owned()))
+}
+
 #[derive(Parser, Debug)]
 pub struct Pull {
     /// Ignore server certificate errors
@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use clap::Parser;
+use reqwest::{
+    header::{HeaderName, HeaderValue, CONTENT_LENGTH, HOST},
+    Method, Url,
+};
+use spin_common::ui::quoted_path;
+use spin_http::capture::{CapturedRequest, REDACTED};
+
+/// Re-send inbound requests captured by the HTTP trigger's
+/// `--capture-requests` mode to a running application, in the order they
+/// were received, printing the status of each response.
+#[derive(Parser, Debug)]
+#[clap(about = "Re-send captured requests to a running application")]
+pub struct ReplayCommand {
+    /// Captured request files, or directories of them, as written by
+    /// `spin up --capture-requests`.
+    #[clap(required = true)]
+    pub captures: Vec<PathBuf>,
+
+    /// The base URL of the application to send requests to.
+    #[clap(long = "url", default_value = "http://127.0.0.1:3000")]
+    pub url: Url,
+}
+
+impl ReplayCommand {
+    pub async fn run(self) -> Result<()> {
+        let mut paths = vec![];
+        for capture in &self.captures {
+            paths.extend(capture_files(capture)?);
+        }
+        ensure!(!paths.is_empty(), "No captured requests found");
+
+        let client = reqwest::Client::new();
+        let mut failed = 0;
+        for path in &paths {
+            let request = CapturedRequest::from_file(path)?;
+            match self.send(&client, &request).await {
+                Ok(status) => println!("{} {} -> {status}", request.method, request.uri),
+                Err(err) => {
+                    failed += 1;
+                    println!("{} {} -> error: {err:#}", request.method, request.uri);
+                }
+            }
+        }
+        ensure!(
+            failed == 0,
+            "{failed} of {} requests could not be sent",
+            paths.len()
+        );
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        request: &CapturedRequest,
+    ) -> Result<reqwest::StatusCode> {
+        let url = self
+            .url
+            .join(&request.uri)
+            .with_context(|| format!("Invalid captured URI {:?}", request.uri))?;
+        let method = Method::from_bytes(request.method.as_bytes())
+            .with_context(|| format!("Invalid captured method {:?}", request.method))?;
+        let mut builder = client.request(method, url).body(request.body()?);
+        for (name, value) in &request.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())?;
+            // The client sets these for the replayed request
+            if name == HOST || name == CONTENT_LENGTH {
+                continue;
+            }
+            // Redacted credentials can't be replayed
+            if value == REDACTED {
+                continue;
+            }
+            builder = builder.header(name, HeaderValue::from_str(value)?);
+        }
+        Ok(builder.send().await?.status())
+    }
+}
+
+// Returns the capture file, or the capture files in a directory in the order
+// they were written.
+fn capture_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut files = std::fs::read_dir(path)
+        .with_context(|| format!("Failed to read {}", quoted_path(path)))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    files.retain(|file| file.extension().is_some_and(|ext| ext == "json"));
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,383 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use serde_json::{json, Value};
+use spin_common::{sha256::hex_digest_from_bytes, ui::quoted_path};
+use spin_loader::FilesMountStrategy;
+use spin_locked_app::{
+    locked::{LockedApp, LockedComponentSource},
+    APP_NAME_KEY, APP_VERSION_KEY,
+};
+
+use crate::{
+    build_info::SPIN_VERSION,
+    opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE},
+};
+
+/// Write a software bill of materials for an application, listing each
+/// component's Wasm binary, and each binary it is composed with, with its
+/// SHA-256 hash, where it came from and how it was built.
+#[derive(Parser, Debug)]
+#[clap(about = "Write a software bill of materials (SBOM) for an application")]
+pub struct SbomCommand {
+    /// The application to describe. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// A lock file to describe instead of an application manifest, such as
+    /// the lock file of a deployed application.
+    #[clap(long = "lock-file", conflicts_with = APP_MANIFEST_FILE_OPT)]
+    pub lock_file: Option<PathBuf>,
+
+    /// The SBOM format to write.
+    #[clap(value_enum, long = "format", default_value = "cyclonedx")]
+    pub format: SbomFormat,
+
+    /// The file to write the SBOM to. If omitted, it is written to stdout.
+    #[clap(short = 'o', long = "output")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    Cyclonedx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+impl SbomCommand {
+    pub async fn run(self) -> Result<()> {
+        let locked_app = match &self.lock_file {
+            Some(path) => {
+                let contents = std::fs::read(path)
+                    .with_context(|| format!("Failed to read {}", quoted_path(path)))?;
+                LockedApp::from_json(&contents)
+                    .with_context(|| format!("Failed to parse lock file {}", quoted_path(path)))?
+            }
+            None => {
+                let manifest_file =
+                    spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
+                spin_loader::from_file(&manifest_file, FilesMountStrategy::Direct, None)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to load manifest from {}",
+                            quoted_path(&manifest_file)
+                        )
+                    })?
+            }
+        };
+
+        let sbom = Sbom::new(&locked_app);
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let serial = uuid::Uuid::new_v4();
+        let document = match self.format {
+            SbomFormat::Cyclonedx => sbom.to_cyclonedx(&timestamp, &serial),
+            SbomFormat::Spdx => sbom.to_spdx(&timestamp, &serial),
+        };
+        let contents = serde_json::to_string_pretty(&document)?;
+
+        match &self.output {
+            Some(path) => std::fs::write(path, &contents)
+                .with_context(|| format!("Failed to write {}", quoted_path(path)))?,
+            None => println!("{contents}"),
+        }
+        Ok(())
+    }
+}
+
+/// The binaries an application is made of.
+#[derive(Debug)]
+struct Sbom {
+    name: String,
+    version: Option<String>,
+    binaries: Vec<Binary>,
+}
+
+#[derive(Debug)]
+struct Binary {
+    /// Unique within the SBOM: the component ID, or `<component>/<import>`
+    /// for a binary a component is composed with.
+    id: String,
+    component: String,
+    /// The import the binary satisfies, if it is a dependency.
+    dependency: Option<String>,
+    sha256: Option<String>,
+    source: Option<String>,
+    build_command: Option<String>,
+    build_workdir: Option<String>,
+}
+
+impl Sbom {
+    fn new(app: &LockedApp) -> Self {
+        let name = app
+            .get_metadata(APP_NAME_KEY)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| "spin-app".into());
+        let version = app.get_metadata(APP_VERSION_KEY).ok().flatten();
+
+        let mut binaries = vec![];
+        for component in &app.components {
+            let build = component.metadata.get("build");
+            let build_field = |field: &str| {
+                build
+                    .and_then(|b| b.get(field))
+                    .and_then(Value::as_str)
+                    .map(String::from)
+            };
+            binaries.push(Binary {
+                id: component.id.clone(),
+                component: component.id.clone(),
+                dependency: None,
+                sha256: sha256(&component.source),
+                source: component.source.content.source.clone(),
+                build_command: build_field("command"),
+                build_workdir: build_field("workdir"),
+            });
+            for (import, dependency) in &component.source.dependencies {
+                binaries.push(Binary {
+                    id: format!("{}/{import}", component.id),
+                    component: component.id.clone(),
+                    dependency: Some(import.clone()),
+                    sha256: sha256(dependency),
+                    source: dependency.content.source.clone(),
+                    build_command: None,
+                    build_workdir: None,
+                });
+            }
+        }
+        Self {
+            name,
+            version,
+            binaries,
+        }
+    }
+
+    fn to_cyclonedx(&self, timestamp: &str, serial: &uuid::Uuid) -> Value {
+        let components = self
+            .binaries
+            .iter()
+            .map(|binary| {
+                let mut properties =
+                    vec![json!({ "name": "spin:component", "value": binary.component })];
+                if let Some(import) = &binary.dependency {
+                    properties.push(json!({ "name": "spin:dependency", "value": import }));
+                }
+                if let Some(command) = &binary.build_command {
+                    properties.push(json!({ "name": "spin:build:command", "value": command }));
+                }
+                if let Some(workdir) = &binary.build_workdir {
+                    properties.push(json!({ "name": "spin:build:workdir", "value": workdir }));
+                }
+                let mut component = json!({
+                    "type": "application",
+                    "bom-ref": binary.id,
+                    "name": binary.id,
+                    "properties": properties,
+                });
+                if let Some(sha256) = &binary.sha256 {
+                    component["hashes"] = json!([{ "alg": "SHA-256", "content": sha256 }]);
+                }
+                if let Some(source) = &binary.source {
+                    component["externalReferences"] =
+                        json!([{ "type": "distribution", "url": source }]);
+                }
+                component
+            })
+            .collect::<Vec<_>>();
+        let dependencies = self
+            .binaries
+            .iter()
+            .filter(|b| b.dependency.is_none())
+            .map(|component| {
+                let depends_on = self
+                    .binaries
+                    .iter()
+                    .filter(|b| b.dependency.is_some() && b.component == component.id)
+                    .map(|b| b.id.as_str())
+                    .collect::<Vec<_>>();
+                json!({ "ref": component.id, "dependsOn": depends_on })
+            })
+            .collect::<Vec<_>>();
+
+        let mut app = json!({ "type": "application", "bom-ref": "app", "name": self.name });
+        if let Some(version) = &self.version {
+            app["version"] = json!(version);
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "serialNumber": format!("urn:uuid:{serial}"),
+            "version": 1,
+            "metadata": {
+                "timestamp": timestamp,
+                "tools": [{ "vendor": "Fermyon", "name": "spin", "version": SPIN_VERSION }],
+                "component": app,
+            },
+            "components": components,
+            "dependencies": dependencies,
+        })
+    }
+
+    fn to_spdx(&self, timestamp: &str, serial: &uuid::Uuid) -> Value {
+        let spdx_id = |id: &str| {
+            let id = id.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "-");
+            format!("SPDXRef-Component-{id}")
+        };
+        let mut app = json!({
+            "name": self.name,
+            "SPDXID": "SPDXRef-App",
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+        });
+        if let Some(version) = &self.version {
+            app["versionInfo"] = json!(version);
+        }
+        let mut packages = vec![app];
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": "SPDXRef-App",
+        })];
+        for binary in &self.binaries {
+            let mut package = json!({
+                "name": binary.id,
+                "SPDXID": spdx_id(&binary.id),
+                "downloadLocation": binary.source.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+                "primaryPackagePurpose": "APPLICATION",
+            });
+            if let Some(sha256) = &binary.sha256 {
+                package["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]);
+            }
+            if let Some(command) = &binary.build_command {
+                let comment = match &binary.build_workdir {
+                    Some(workdir) => format!("Built with `{command}` in {workdir}"),
+                    None => format!("Built with `{command}`"),
+                };
+                package["comment"] = json!(comment);
+            }
+            packages.push(package);
+            relationships.push(match &binary.dependency {
+                None => json!({
+                    "spdxElementId": "SPDXRef-App",
+                    "relationshipType": "CONTAINS",
+                    "relatedSpdxElement": spdx_id(&binary.id),
+                }),
+                Some(_) => json!({
+                    "spdxElementId": spdx_id(&binary.component),
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": spdx_id(&binary.id),
+                }),
+            });
+        }
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": self.name,
+            "documentNamespace": format!("https://spdx.org/spdxdocs/{}-{serial}", self.name),
+            "creationInfo": {
+                "created": timestamp,
+                "creators": [format!("Tool: spin-{SPIN_VERSION}")],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+}
+
+// Inline sources, as in lock files written for tests, have no digest but can
+// still be hashed.
+fn sha256(source: &LockedComponentSource) -> Option<String> {
+    match (&source.content.digest, &source.content.inline) {
+        (Some(digest), _) => Some(digest.strip_prefix("sha256:").unwrap_or(digest).to_owned()),
+        (None, Some(inline)) => Some(hex_digest_from_bytes(inline)),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked_app() -> LockedApp {
+        let app = json!({
+            "spin_lock_version": 0,
+            "metadata": { "name": "shop", "version": "1.2.0" },
+            "triggers": [],
+            "components": [{
+                "id": "cart",
+                "metadata": { "build": { "command": "cargo build --release", "workdir": "cart" } },
+                "source": {
+                    "content_type": "application/wasm",
+                    "source": "file:///app/cart.wasm",
+                    "digest": "sha256:abc123",
+                    "dependencies": {
+                        "acme:auth/api": {
+                            "content_type": "application/wasm",
+                            "source": "oci://ghcr.io/acme/auth:1.0",
+                        },
+                    },
+                },
+            }],
+        });
+        LockedApp::from_json(&serde_json::to_vec(&app).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn cyclonedx_lists_components_and_dependencies() {
+        let uuid = uuid::Uuid::nil();
+        let bom = Sbom::new(&locked_app()).to_cyclonedx("2024-01-01T00:00:00Z", &uuid);
+
+        assert_eq!(bom["metadata"]["component"]["name"], "shop");
+        assert_eq!(bom["metadata"]["component"]["version"], "1.2.0");
+        let cart = &bom["components"][0];
+        assert_eq!(cart["name"], "cart");
+        assert_eq!(cart["hashes"][0]["content"], "abc123");
+        assert_eq!(
+            cart["externalReferences"][0]["url"],
+            "file:///app/cart.wasm"
+        );
+        assert!(cart["properties"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({ "name": "spin:build:command", "value": "cargo build --release" })));
+        let auth = &bom["components"][1];
+        assert_eq!(auth["name"], "cart/acme:auth/api");
+        assert!(auth.get("hashes").is_none());
+        assert_eq!(bom["dependencies"][0]["dependsOn"][0], "cart/acme:auth/api");
+    }
+
+    #[test]
+    fn spdx_relates_packages_to_the_app() {
+        let uuid = uuid::Uuid::nil();
+        let doc = Sbom::new(&locked_app()).to_spdx("2024-01-01T00:00:00Z", &uuid);
+
+        let packages = doc["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[1]["SPDXID"], "SPDXRef-Component-cart");
+        assert_eq!(packages[1]["checksums"][0]["checksumValue"], "abc123");
+        assert_eq!(
+            packages[2]["downloadLocation"],
+            "oci://ghcr.io/acme/auth:1.0"
+        );
+        let relationships = doc["relationships"].as_array().unwrap();
+        assert_eq!(relationships[2]["relationshipType"], "DEPENDS_ON");
+        assert_eq!(
+            relationships[2]["relatedSpdxElement"],
+            "SPDXRef-Component-cart-acme-auth-api"
+        );
+    }
+}
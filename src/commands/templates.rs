@@ -15,6 +15,7 @@ use crate::build_info::*;
 
 const INSTALL_FROM_DIR_OPT: &str = "FROM_DIR";
 const INSTALL_FROM_GIT_OPT: &str = "FROM_GIT";
+const INSTALL_FROM_OCI_OPT: &str = "FROM_OCI";
 const UPGRADE_ONLY: &str = "GIT_URL";
 
 const DEFAULT_TEMPLATES_INSTALL_PROMPT: &str =
@@ -24,7 +25,7 @@ const DEFAULT_TEMPLATE_REPO: &str = "https://github.com/fermyon/spin";
 /// Commands for working with WebAssembly component templates.
 #[derive(Subcommand, Debug)]
 pub enum TemplateCommands {
-    /// Install templates from a Git repository or local directory.
+    /// Install templates from a Git repository, local directory or OCI registry.
     ///
     /// The files of the templates are copied to the local template store: a
     /// directory in your data or home directory.
@@ -41,6 +42,10 @@ pub enum TemplateCommands {
 
     /// List the installed templates.
     List(List),
+
+    /// Push templates to an OCI registry, from which they can be installed
+    /// with `spin templates install --oci` or used with `spin new --from-oci`.
+    Push(Push),
 }
 
 impl TemplateCommands {
@@ -50,11 +55,12 @@ impl TemplateCommands {
             TemplateCommands::Upgrade(cmd) => cmd.run().await,
             TemplateCommands::Uninstall(cmd) => cmd.run().await,
             TemplateCommands::List(cmd) => cmd.run().await,
+            TemplateCommands::Push(cmd) => cmd.run().await,
         }
     }
 }
 
-/// Install templates from a Git repository or local directory.
+/// Install templates from a Git repository, local directory or OCI registry.
 #[derive(Parser, Debug)]
 pub struct Install {
     /// The URL of the templates git repository.
@@ -62,7 +68,7 @@ pub struct Install {
     #[clap(
         name = INSTALL_FROM_GIT_OPT,
         long = "git",
-        conflicts_with = INSTALL_FROM_DIR_OPT,
+        conflicts_with_all = &[INSTALL_FROM_DIR_OPT, INSTALL_FROM_OCI_OPT],
     )]
     pub git: Option<String>,
 
@@ -74,10 +80,20 @@ pub struct Install {
     #[clap(
         name = INSTALL_FROM_DIR_OPT,
         long = "dir",
-        conflicts_with = INSTALL_FROM_GIT_OPT,
+        conflicts_with_all = &[INSTALL_FROM_GIT_OPT, INSTALL_FROM_OCI_OPT],
     )]
     pub dir: Option<PathBuf>,
 
+    /// The OCI registry reference of templates pushed with `spin templates push`.
+    /// Pin the templates to exact content with a digest reference, such as
+    /// `registry.example.com/templates@sha256:...`.
+    #[clap(
+        name = INSTALL_FROM_OCI_OPT,
+        long = "oci",
+        conflicts_with_all = &[INSTALL_FROM_GIT_OPT, INSTALL_FROM_DIR_OPT],
+    )]
+    pub oci: Option<String>,
+
     /// If present, updates existing templates instead of skipping.
     #[clap(long = "upgrade", alias = "update")]
     pub update: bool,
@@ -118,13 +134,16 @@ impl Install {
     pub async fn run(self) -> Result<()> {
         let template_manager = TemplateManager::try_default()
             .context("Failed to construct template directory path")?;
-        let source = match (&self.git, &self.dir) {
-            (Some(git), None) => TemplateSource::try_from_git(git, &self.branch, SPIN_VERSION)?,
-            (None, Some(dir)) => {
+        let source = match (&self.git, &self.dir, &self.oci) {
+            (Some(git), None, None) => {
+                TemplateSource::try_from_git(git, &self.branch, SPIN_VERSION)?
+            }
+            (None, Some(dir), None) => {
                 let abs_dir = dir.absolutize().map(|d| d.to_path_buf());
                 TemplateSource::File(abs_dir.unwrap_or_else(|_| dir.clone()))
             }
-            _ => anyhow::bail!("Exactly one of `git` and `dir` sources must be specified"),
+            (None, None, Some(reference)) => TemplateSource::Oci(reference.clone()),
+            _ => anyhow::bail!("Exactly one of `git`, `dir` and `oci` sources must be specified"),
         };
 
         let reporter = ConsoleProgressReporter;
@@ -187,6 +206,7 @@ impl Upgrade {
                 git: self.git.clone(),
                 branch: self.branch.clone(),
                 dir: None,
+                oci: None,
                 update: true,
             };
 
@@ -437,6 +457,45 @@ impl Uninstall {
     }
 }
 
+/// Push templates to an OCI registry.
+#[derive(Parser, Debug)]
+pub struct Push {
+    /// Directory containing the templates to push, in a "templates"
+    /// subdirectory, as for `spin templates install --dir`.
+    #[clap(long = "dir", default_value = ".")]
+    pub dir: PathBuf,
+
+    /// Ignore server certificate errors.
+    #[clap(short = 'k', long = "insecure", takes_value = false)]
+    pub insecure: bool,
+
+    /// The registry reference to push to, such as `registry.example.com/templates:v1`.
+    pub reference: String,
+}
+
+impl Push {
+    pub async fn run(self) -> Result<()> {
+        let dir = self
+            .dir
+            .absolutize()
+            .with_context(|| format!("Failed to resolve {}", self.dir.display()))?;
+        if !dir.join("templates").is_dir() {
+            anyhow::bail!("{} does not contain a 'templates' directory", dir.display());
+        }
+
+        let mut client = spin_oci::Client::new(self.insecure, None).await?;
+        let digest = client
+            .push_archive(&dir, &self.reference)
+            .await
+            .context("Failed to push templates")?;
+        match digest {
+            Some(digest) => println!("Pushed with digest {digest}"),
+            None => println!("Pushed; the registry did not report the digest"),
+        }
+        Ok(())
+    }
+}
+
 /// List the installed templates.
 #[derive(Parser, Debug)]
 pub struct List {
@@ -0,0 +1,203 @@
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use clap::Parser;
+use reqwest::Url;
+use spin_common::ui::quoted_path;
+use spin_loader::FilesMountStrategy;
+use spin_locked_app::locked::LockedApp;
+use spin_trigger::{
+    loader::TriggerLoader, HostComponentInitData, LLmOptions, RuntimeConfig, TriggerExecutor,
+    TriggerExecutorBuilder,
+};
+use spin_trigger_test::{HttpFixtures, HttpRecorder, TestRunConfig, TestTrigger};
+use tempfile::TempDir;
+
+use crate::opts::{APP_MANIFEST_FILE_OPT, DEFAULT_MANIFEST_FILE};
+
+// Each kind of store a component may use: its runtime config table, and the
+// component metadata listing the labels it uses
+const STORE_KINDS: &[(&str, &[&str])] = &[
+    ("key_value_store", &["key_value_stores"]),
+    ("sqlite_database", &["databases", "read_only_databases"]),
+    ("blob_store", &["blob_stores"]),
+];
+
+/// Run the tests in an application's test components.
+///
+/// A test component is one used by a `[[trigger.test]]` trigger, and exports
+/// the `fermyon:spin/test` interface (the `test-suite` world). Each test runs
+/// in a new instance against in-memory stores; outbound HTTP requests are
+/// answered from fixtures instead of the network.
+#[derive(Parser, Debug)]
+#[clap(about = "Run the tests in an application's test components")]
+pub struct TestCommand {
+    /// The application to test. This may be a manifest (spin.toml) file, or a
+    /// directory containing a spin.toml file.
+    /// If omitted, it defaults to "spin.toml".
+    #[clap(
+        name = APP_MANIFEST_FILE_OPT,
+        short = 'f',
+        long = "from",
+        alias = "file",
+        default_value = DEFAULT_MANIFEST_FILE
+    )]
+    pub app_source: PathBuf,
+
+    /// A TOML file of `[[fixture]]` responses to the tests' outbound HTTP
+    /// requests. A request no fixture matches fails its test.
+    #[clap(long = "http-fixtures")]
+    pub http_fixtures: Option<PathBuf>,
+
+    /// A file of recorded outbound HTTP exchanges, in the same format as
+    /// `--http-fixtures`, to replay for requests no fixture matches. If the
+    /// file does not exist, requests are sent to the network and the
+    /// exchanges recorded to it.
+    #[clap(long = "http-cassette")]
+    pub http_cassette: Option<PathBuf>,
+
+    /// Record the `--http-cassette` file again, even if it exists.
+    #[clap(long = "record-http", requires = "http_cassette")]
+    pub record_http: bool,
+
+    /// Run only the tests whose names, in the form `component::test`,
+    /// contain this string.
+    pub filter: Option<String>,
+}
+
+impl TestCommand {
+    pub async fn run(self) -> Result<()> {
+        let manifest_file = spin_common::paths::resolve_manifest_file_path(&self.app_source)?;
+        let mut http_fixtures = match &self.http_fixtures {
+            Some(path) => HttpFixtures::from_file(path)?,
+            None => HttpFixtures::default(),
+        };
+        let mut http_recorder = None;
+        if let Some(cassette) = &self.http_cassette {
+            if self.record_http || !cassette.exists() {
+                http_recorder = Some(Arc::new(HttpRecorder::new()));
+            } else {
+                http_fixtures.extend(HttpFixtures::from_file(cassette)?);
+            }
+        }
+
+        let mut locked_app =
+            spin_loader::from_file(&manifest_file, FilesMountStrategy::Direct, None)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load manifest from {}",
+                        quoted_path(&manifest_file)
+                    )
+                })?;
+        locked_app
+            .triggers
+            .retain(|trigger| trigger.trigger_type == TestTrigger::TRIGGER_TYPE);
+        ensure!(
+            !locked_app.triggers.is_empty(),
+            "No test components in {}: add a `[[trigger.test]]` for each test component",
+            quoted_path(&manifest_file)
+        );
+
+        let working_dir = TempDir::with_prefix("spintest-")?;
+        let locked_path = working_dir.path().join("spin.lock");
+        let locked_app_contents = locked_app
+            .to_json()
+            .context("failed to serialize locked app")?;
+        tokio::fs::write(&locked_path, locked_app_contents)
+            .await
+            .with_context(|| format!("failed to write {}", quoted_path(&locked_path)))?;
+        let locked_url = Url::from_file_path(&locked_path)
+            .map_err(|_| anyhow!("cannot convert to file URL: {}", quoted_path(&locked_path)))?
+            .to_string();
+
+        let runtime_config_path = working_dir.path().join("runtime-config.toml");
+        tokio::fs::write(&runtime_config_path, in_memory_runtime_config(&locked_app))
+            .await
+            .with_context(|| format!("failed to write {}", quoted_path(&runtime_config_path)))?;
+        let mut runtime_config = RuntimeConfig::new(None);
+        runtime_config.merge_config_file(&runtime_config_path)?;
+
+        let loader = TriggerLoader::new(working_dir.path(), false);
+        let init_data = HostComponentInitData::new(vec![], vec![], LLmOptions { use_gpu: false });
+        let executor = TriggerExecutorBuilder::<TestTrigger>::new(loader)
+            .build(locked_url, &runtime_config, init_data)
+            .await?;
+        let result = executor
+            .run(TestRunConfig {
+                filter: self.filter,
+                http_fixtures,
+                http_recorder: http_recorder.clone(),
+            })
+            .await;
+
+        // Save what was recorded even if tests failed, as failures may be
+        // what is being tested
+        if let (Some(recorder), Some(cassette)) = (http_recorder, &self.http_cassette) {
+            recorder.fixtures().save(cassette)?;
+            println!(
+                "Recorded outbound HTTP requests to {}",
+                quoted_path(cassette)
+            );
+        }
+        result
+    }
+}
+
+// Returns a runtime config defining an in-memory store for each store label
+// used by the app's components, so that tests never touch real state.
+fn in_memory_runtime_config(locked_app: &LockedApp) -> String {
+    let mut config = String::new();
+    for (table, metadata_keys) in STORE_KINDS {
+        let labels = locked_app
+            .components
+            .iter()
+            .flat_map(|component| {
+                metadata_keys
+                    .iter()
+                    .filter_map(|key| component.metadata.get(*key)?.as_array())
+                    .flatten()
+                    .filter_map(|label| label.as_str())
+            })
+            .collect::<BTreeSet<_>>();
+        for label in labels {
+            config.push_str(&format!("[{table}.{label}]\ntype = \"spin\"\n\n"));
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_config_covers_every_label() {
+        let locked_app: LockedApp = serde_json::from_value(serde_json::json!({
+            "spin_lock_version": 0,
+            "triggers": [],
+            "components": [
+                {
+                    "id": "a",
+                    "metadata": {
+                        "key_value_stores": ["default", "cache"],
+                        "read_only_databases": ["reports"],
+                    },
+                    "source": { "content_type": "application/wasm" },
+                },
+                {
+                    "id": "b",
+                    "metadata": { "key_value_stores": ["cache"] },
+                    "source": { "content_type": "application/wasm" },
+                },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(
+            in_memory_runtime_config(&locked_app),
+            "[key_value_store.cache]\ntype = \"spin\"\n\n\
+             [key_value_store.default]\ntype = \"spin\"\n\n\
+             [sqlite_database.reports]\ntype = \"spin\"\n\n"
+        );
+    }
+}
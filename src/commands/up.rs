@@ -6,14 +6,17 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use clap::{CommandFactory, Parser};
 use reqwest::Url;
 use spin_app::locked::LockedApp;
 use spin_common::ui::quoted_path;
 use spin_loader::FilesMountStrategy;
 use spin_oci::OciLoader;
-use spin_trigger::cli::{SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR};
+use spin_trigger::{
+    cli::{SPIN_LOCAL_APP_DIR, SPIN_LOCKED_URL, SPIN_WORKING_DIR},
+    plugin::{PROTOCOL_VERSION, SPIN_TRIGGER_PROTOCOL_VERSION},
+};
 use tempfile::TempDir;
 
 use crate::opts::*;
@@ -82,11 +85,17 @@ pub struct UpCommand {
     #[clap(long = "temp")]
     pub tmp: Option<PathBuf>,
 
-    /// For local apps with directory mounts and no excluded files, mount them directly instead of using a temporary
+    /// For local apps with no excluded files, mount files and directories directly instead of using a temporary
     /// directory.
     ///
     /// This allows you to update the assets on the host filesystem such that the updates are visible to the guest
-    /// without a restart.  This cannot be used with registry apps or apps which use file patterns and/or exclusions.
+    /// without a restart.  File patterns are resolved at startup, so files added later are not mounted.  This cannot
+    /// be used with registry apps or apps which use exclusions.
+    ///
+    /// Individual files can't be mounted directly: each is exposed through a hard link, or a copy if linking fails,
+    /// in a directory under the working directory, made when the app is loaded.  Edits made in place to a linked
+    /// file are visible to the guest; a file which is replaced rather than edited in place, or which was copied,
+    /// is only updated on restart.
     #[clap(long, takes_value = false)]
     pub direct_mounts: bool,
 
@@ -96,6 +105,18 @@ pub struct UpCommand {
     #[clap(long, takes_value = false, env = ALWAYS_BUILD_ENV)]
     pub build: bool,
 
+    /// For local apps, apply the manifest's `[profile.<PROFILE>]` overlay,
+    /// which overrides variables, allowed outbound hosts, and build commands
+    /// for an environment such as `dev` or `prod`.
+    #[clap(long = "profile")]
+    pub profile: Option<String>,
+
+    /// Run only the application's triggers of this type, such as one
+    /// provided by a `trigger-<TYPE>` plugin. By default, the trigger type
+    /// is that of the application's triggers.
+    #[clap(long = "trigger")]
+    pub trigger_type: Option<String>,
+
     /// All other args, to be passed through to the trigger
     #[clap(hide = true)]
     pub trigger_args: Vec<OsString>,
@@ -137,7 +158,7 @@ impl UpCommand {
         }
 
         if self.build {
-            app_source.build().await?;
+            app_source.build(self.profile.as_deref()).await?;
         }
 
         // Get working dir holder and hold on to it for the rest of the function.
@@ -150,8 +171,11 @@ impl UpCommand {
 
         let resolved_app_source = self.resolve_app_source(&app_source, &working_dir).await?;
 
-        let trigger_cmd = trigger_command_for_resolved_app_source(&resolved_app_source)
-            .with_context(|| format!("Couldn't find trigger executor for {app_source}"))?;
+        let trigger_cmd = trigger_command_for_resolved_app_source(
+            &resolved_app_source,
+            self.trigger_type.as_deref(),
+        )
+        .with_context(|| format!("Couldn't find trigger executor for {app_source}"))?;
 
         if self.help {
             return self.run_trigger(trigger_cmd, None).await;
@@ -198,7 +222,8 @@ impl UpCommand {
         // The docs for `current_exe` warn that this may be insecure because it could be executed
         // via hard-link. I think it should be fine as long as we aren't `setuid`ing this binary.
         let mut cmd = std::process::Command::new(std::env::current_exe().unwrap());
-        cmd.args(&trigger_cmd);
+        cmd.args(&trigger_cmd)
+            .env(SPIN_TRIGGER_PROTOCOL_VERSION, PROTOCOL_VERSION.to_string());
 
         if let Some(RunTriggerOpts {
             locked_app,
@@ -335,16 +360,26 @@ impl UpCommand {
                 } else {
                     FilesMountStrategy::Copy(working_dir.join("assets"))
                 };
-                spin_loader::from_file(&manifest_path, files_mount_strategy, None)
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "Failed to load manifest from {}",
-                            quoted_path(&manifest_path)
-                        )
-                    })
+                spin_loader::from_file_with_profile(
+                    &manifest_path,
+                    files_mount_strategy,
+                    None,
+                    self.profile.clone(),
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to load manifest from {}",
+                        quoted_path(&manifest_path)
+                    )
+                })
+            }
+            ResolvedAppSource::OciRegistry { locked_app } => {
+                if self.profile.is_some() {
+                    bail!("--profile can only be used with local apps");
+                }
+                Ok(locked_app)
             }
-            ResolvedAppSource::OciRegistry { locked_app } => Ok(locked_app),
         }
     }
 
@@ -424,11 +459,26 @@ fn trigger_command(trigger_type: &str) -> Vec<String> {
     vec!["trigger".to_owned(), trigger_type.to_owned()]
 }
 
-fn trigger_command_for_resolved_app_source(resolved: &ResolvedAppSource) -> Result<Vec<String>> {
-    let trigger_type = resolved.trigger_type()?;
+fn trigger_command_for_resolved_app_source(
+    resolved: &ResolvedAppSource,
+    requested_type: Option<&str>,
+) -> Result<Vec<String>> {
+    let trigger_type = match requested_type {
+        Some(trigger_type) => {
+            ensure!(
+                resolved.trigger_types().contains(trigger_type),
+                "The application has no triggers of type '{trigger_type}'"
+            );
+            trigger_type
+        }
+        None => resolved.trigger_type()?,
+    };
 
     match trigger_type {
-        "http" | "redis" => Ok(trigger_command(trigger_type)),
+        "http" | "redis" | "cron" | "queue" | "mqtt" | "grpc" | "command" => {
+            Ok(trigger_command(trigger_type))
+        }
+        HTTP_REDIS_TRIGGER_TYPE => Ok(trigger_command(trigger_type)),
         _ => {
             let cmd = resolve_trigger_plugin(trigger_type)?;
             Ok(vec![cmd])
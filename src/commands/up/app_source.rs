@@ -7,6 +7,12 @@ use anyhow::ensure;
 use spin_common::ui::quoted_path;
 use spin_locked_app::locked::LockedApp;
 use spin_manifest::schema::v2::AppManifest;
+use spin_trigger::TriggerExecutor;
+use spin_trigger_test::TestTrigger;
+
+use crate::opts::HTTP_REDIS_TRIGGER_TYPE;
+
+const TEST_TRIGGER_TYPE: &str = TestTrigger::TRIGGER_TYPE;
 
 /// A source from which an App may be loaded.
 #[derive(Debug, PartialEq, Eq)]
@@ -50,9 +56,9 @@ impl AppSource {
         }
     }
 
-    pub async fn build(&self) -> anyhow::Result<()> {
+    pub async fn build(&self, profile: Option<&str>) -> anyhow::Result<()> {
         match self {
-            Self::File(path) => spin_build::build(path, &[]).await,
+            Self::File(path) => spin_build::build_with_profile(path, &[], false, profile).await,
             _ => Ok(()),
         }
     }
@@ -83,20 +89,41 @@ pub enum ResolvedAppSource {
 }
 
 impl ResolvedAppSource {
-    pub fn trigger_type(&self) -> anyhow::Result<&str> {
-        let types = match self {
-            ResolvedAppSource::File { manifest, .. } => {
-                manifest.triggers.keys().collect::<HashSet<_>>()
-            }
+    /// The types of the app's triggers which `spin up` can run.
+    pub fn trigger_types(&self) -> HashSet<&str> {
+        let mut types = match self {
+            ResolvedAppSource::File { manifest, .. } => manifest
+                .triggers
+                .keys()
+                .map(|t| t.as_str())
+                .collect::<HashSet<_>>(),
             ResolvedAppSource::OciRegistry { locked_app } => locked_app
                 .triggers
                 .iter()
-                .map(|t| &t.trigger_type)
+                .map(|t| t.trigger_type.as_str())
                 .collect::<HashSet<_>>(),
         };
+        // Test triggers are run by `spin test`, never by `spin up`
+        types.remove(TEST_TRIGGER_TYPE);
+        types
+    }
+
+    pub fn trigger_type(&self) -> anyhow::Result<&str> {
+        let types = self.trigger_types();
 
         ensure!(!types.is_empty(), "no triggers in app");
-        ensure!(types.len() == 1, "multiple trigger types not yet supported");
+        // HTTP and Redis triggers can be run together in one process
+        if types.len() == 2
+            && types
+                .iter()
+                .all(|trigger_type| matches!(*trigger_type, "http" | "redis"))
+        {
+            return Ok(HTTP_REDIS_TRIGGER_TYPE);
+        }
+        ensure!(
+            types.len() == 1,
+            "multiple trigger types are only supported for HTTP and Redis triggers together: use --trigger to run one type"
+        );
         Ok(types.into_iter().next().unwrap())
     }
 }
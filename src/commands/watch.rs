@@ -1,6 +1,7 @@
 use std::{
+    collections::HashSet,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -17,6 +18,9 @@ use crate::opts::{
     WATCH_SKIP_BUILD_OPT,
 };
 
+// Passed through `spin up` to the trigger
+const HOT_RELOAD_OPT: &str = "--hot-reload";
+
 mod buildifier;
 mod filters;
 mod reconfiguriser;
@@ -74,12 +78,14 @@ pub struct WatchCommand {
 impl WatchCommand {
     pub async fn run(self) -> Result<()> {
         // Strategy:
-        // * The Uppificator runs `spin up`, and watches the manifest artifacts (component.source and component.files)
-        //   (and the manifest if build is not in play). When it detects a change, it restarts `spin up`.
-        //   THAT'S ALL, THAT'S ALL IT DOES.
+        // * The Uppificator runs `spin up --hot-reload`, and watches the manifest and the component.files
+        //   artifacts. When it detects a change, it restarts `spin up`. THAT'S ALL, THAT'S ALL IT DOES.
         //   * If `spin up` crashes, the Uppificator restarts it.  BUT APART FROM THAT THAT'S ALL IT DOES OKAY.
+        //   * Changes to component.source files don't need a restart: `spin up` reloads just the changed
+        //     components itself.
         // * The Buildifier, if in play, watches the manifest and component.build.watch collections. When it detects a
-        //   change, it PAUSES the Uppificator, does the build, then unpauses the Uppificator.
+        //   change, it PAUSES the Uppificator, builds the components (and their dependents) whose watch
+        //   patterns match the changed files, then unpauses the Uppificator. A manifest change rebuilds everything.
         //   * It is on the Uppificator to recognise if any interesting files have changed when it unpauses.
         // * The Reconfiguriser watches the manifest *only*. When it detects a change, it reconfigures the `watchexec`
         //   instances that underlie the Uppificator and Buildifier. There is no need to trigger a reload as
@@ -107,19 +113,30 @@ impl WatchCommand {
         let (manifest_tx, manifest_rx) = tokio::sync::watch::channel(Uuid::new_v4());
         let (stop_tx, stop_rx) = tokio::sync::watch::channel(Uuid::new_v4());
 
+        let source_code_paths = ChangedPaths::default();
+
         let mut buildifier = Buildifier {
             spin_bin: spin_bin.clone(),
             manifest: manifest_file.clone(),
+            manifest_dir: manifest_dir.clone(),
             clear_screen: self.clear,
             has_ever_built: false,
             watched_changes: source_code_rx,
+            changed_paths: source_code_paths.clone(),
             uppificator_pauser: pause_tx,
         };
 
+        // Rebuilt components are reloaded by `spin up` itself, so only asset and
+        // manifest changes need a restart
+        let mut up_args = self.up_args.clone();
+        if !up_args.iter().any(|arg| arg == HOT_RELOAD_OPT) {
+            up_args.push(HOT_RELOAD_OPT.to_owned());
+        }
+
         let mut uppificator = Uppificator {
             spin_bin: spin_bin.clone(),
             manifest: manifest_file.clone(),
-            up_args: self.up_args.clone(),
+            up_args,
             clear_screen: self.clear,
             watched_changes: artifact_rx,
             pause_feed: pause_rx,
@@ -137,7 +154,6 @@ impl WatchCommand {
         let contains_direct_mounts = self.up_args.contains(&"--direct-mounts".to_owned());
 
         let artifact_filterer = Box::new(ArtifactFilterFactory {
-            skip_build: self.skip_build,
             skip_assets: contains_direct_mounts,
         });
         let (artifact_watcher, artifact_watcher_handle) = self
@@ -146,6 +162,7 @@ impl WatchCommand {
                 &manifest_dir,
                 artifact_filterer,
                 artifact_tx,
+                ChangedPaths::default(),
                 "reload",
             )
             .await
@@ -160,6 +177,7 @@ impl WatchCommand {
                 &manifest_dir,
                 build_filterer,
                 source_code_tx,
+                source_code_paths,
                 "build",
             )
             .await
@@ -179,6 +197,7 @@ impl WatchCommand {
                 &manifest_dir,
                 manifest_filterer,
                 manifest_tx,
+                ChangedPaths::default(),
                 "reconfigure",
             )
             .await
@@ -240,6 +259,7 @@ impl WatchCommand {
         manifest_dir: &Path,
         filter_factory: Box<dyn FilterFactory>,
         notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+        changed_paths: ChangedPaths,
         impact_description: &'static str,
     ) -> anyhow::Result<(ReconfigurableWatcher, tokio::task::JoinHandle<()>)> {
         let rtf = RuntimeConfigFactory {
@@ -247,6 +267,7 @@ impl WatchCommand {
             manifest_dir: manifest_dir.to_owned(),
             filter_factory,
             notifier,
+            changed_paths,
             impact_description,
             debounce: Duration::from_millis(self.debounce),
         };
@@ -270,20 +291,24 @@ pub struct RuntimeConfigFactory {
     manifest_dir: PathBuf,
     filter_factory: Box<dyn FilterFactory>,
     notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+    changed_paths: ChangedPaths,
     impact_description: &'static str,
     debounce: Duration,
 }
 
 impl RuntimeConfigFactory {
     async fn build_config(&self) -> anyhow::Result<watchexec::config::RuntimeConfig> {
-        let manifest_str = tokio::fs::read_to_string(&self.manifest_file).await?;
-        let manifest = spin_manifest::manifest_from_str(&manifest_str)?;
+        let manifest = spin_manifest::manifest_from_file(&self.manifest_file)?;
         let filterer = self
             .filter_factory
             .build_filter(&self.manifest_file, &self.manifest_dir, &manifest)
             .await?;
 
-        let handler = NotifyOnFileChange::new(self.notifier.clone(), self.impact_description);
+        let handler = NotifyOnFileChange::new(
+            self.notifier.clone(),
+            self.changed_paths.clone(),
+            self.impact_description,
+        );
 
         let mut rt = watchexec::config::RuntimeConfig::default();
         rt.pathset([&self.manifest_dir]);
@@ -302,17 +327,20 @@ impl RuntimeConfigFactory {
 struct NotifyOnFileChange {
     despurifier: despurifier::Despurifier,
     notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+    changed_paths: ChangedPaths,
     impact_description: &'static str,
 }
 
 impl NotifyOnFileChange {
     fn new(
         notifier: Arc<tokio::sync::watch::Sender<Uuid>>,
+        changed_paths: ChangedPaths,
         impact_description: &'static str,
     ) -> Self {
         Self {
             despurifier: despurifier::Despurifier::new(),
             notifier,
+            changed_paths,
             impact_description,
         }
     }
@@ -331,6 +359,13 @@ impl watchexec::handler::Handler<watchexec::action::Action> for NotifyOnFileChan
                 self.impact_description,
                 paths_of(&action)
             );
+            self.changed_paths.extend(
+                action
+                    .events
+                    .iter()
+                    .filter_map(path_of_event)
+                    .map(Path::to_owned),
+            );
             _ = self.notifier.send(Uuid::new_v4());
         }
         action.outcome(watchexec::action::Outcome::DoNothing);
@@ -338,6 +373,22 @@ impl watchexec::handler::Handler<watchexec::action::Action> for NotifyOnFileChan
     }
 }
 
+// The paths changed since a consumer of change notifications last checked.
+// The notification itself carries no data, so the consumer takes these
+// when it is notified.
+#[derive(Clone, Default)]
+pub(crate) struct ChangedPaths(Arc<Mutex<HashSet<PathBuf>>>);
+
+impl ChangedPaths {
+    fn extend(&self, paths: impl IntoIterator<Item = PathBuf>) {
+        self.0.lock().unwrap().extend(paths);
+    }
+
+    pub fn take(&self) -> HashSet<PathBuf> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
 fn paths_of(action: &watchexec::action::Action) -> String {
     action
         .events
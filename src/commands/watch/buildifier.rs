@@ -1,18 +1,38 @@
 use command_group::AsyncCommandGroup;
-use std::path::PathBuf;
+use std::{collections::BTreeSet, path::PathBuf};
 use uuid::Uuid;
 
-use super::uppificator::Pause;
+use super::{filters::affected_components, uppificator::Pause, ChangedPaths};
 
 pub(crate) struct Buildifier {
     pub spin_bin: PathBuf,
     pub manifest: PathBuf,
+    pub manifest_dir: PathBuf,
     pub clear_screen: bool,
     pub has_ever_built: bool,
-    pub watched_changes: tokio::sync::watch::Receiver<Uuid>, // TODO: refine which component(s) a change affects
+    pub watched_changes: tokio::sync::watch::Receiver<Uuid>,
+    pub changed_paths: ChangedPaths,
     pub uppificator_pauser: tokio::sync::mpsc::Sender<Pause>,
 }
 
+// Which components a build should include
+enum BuildScope {
+    All,
+    Components(BTreeSet<String>),
+}
+
+impl BuildScope {
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Components(mut ids), Self::Components(other_ids)) => {
+                ids.extend(other_ids);
+                Self::Components(ids)
+            }
+            _ => Self::All,
+        }
+    }
+}
+
 impl Buildifier {
     #[allow(clippy::collapsible_if)]
     pub(crate) async fn run(&mut self) {
@@ -46,9 +66,16 @@ impl Buildifier {
     }
 
     pub(crate) async fn build_once(&mut self) -> std::io::Result<bool> {
+        let mut scope = self.changed_scope();
         loop {
             let mut cmd = tokio::process::Command::new(&self.spin_bin);
             cmd.arg("build").arg("-f").arg(&self.manifest);
+            if let BuildScope::Components(ids) = &scope {
+                tracing::debug!("Rebuilding changed components: {ids:?}");
+                for id in ids {
+                    cmd.arg("-c").arg(id);
+                }
+            }
             let mut child = cmd.group_spawn()?;
 
             tokio::select! {
@@ -63,10 +90,36 @@ impl Buildifier {
                     if self.clear_screen {
                         _ = clearscreen::clear();
                     }
+                    // The cancelled build may not have finished its components
+                    scope = scope.merge(self.changed_scope());
                     continue;
                 }
 
             }
         }
     }
+
+    // Takes the paths changed since the last build, and works out which
+    // components they require rebuilding
+    fn changed_scope(&self) -> BuildScope {
+        let changed_paths = self.changed_paths.take();
+        // Nothing changed yet on the first build; and a manifest change may
+        // affect any component
+        if !self.has_ever_built
+            || changed_paths.is_empty()
+            || changed_paths.contains(&self.manifest)
+        {
+            return BuildScope::All;
+        }
+        let manifest = match spin_manifest::manifest_from_file(&self.manifest) {
+            Ok(manifest) => manifest,
+            Err(_) => return BuildScope::All,
+        };
+        let affected = affected_components(&self.manifest_dir, &manifest, &changed_paths);
+        if affected.is_empty() {
+            BuildScope::All
+        } else {
+            BuildScope::Components(affected)
+        }
+    }
 }
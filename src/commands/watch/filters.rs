@@ -1,4 +1,5 @@
 use std::{
+    collections::{BTreeSet, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -18,7 +19,6 @@ pub(crate) trait FilterFactory: Send + Sync {
 }
 
 pub(crate) struct ArtifactFilterFactory {
-    pub skip_build: bool,
     pub skip_assets: bool,
 }
 
@@ -33,18 +33,9 @@ impl FilterFactory for ArtifactFilterFactory {
         manifest_dir: &Path,
         manifest: &v2::AppManifest,
     ) -> anyhow::Result<Arc<watchexec_filterer_globset::GlobsetFilterer>> {
-        let manifest_glob = if self.skip_build {
-            vec![stringize_path(manifest_file)?]
-        } else {
-            vec![] // In this case, manifest changes trigger a rebuild, which will poke the uppificator anyway
-        };
-        let wasm_globs = manifest
-            .components
-            .values()
-            .filter_map(|c| match &c.source {
-                v2::ComponentSource::Local(path) => Some(path.clone()),
-                _ => None,
-            });
+        // Component sources aren't watched: `spin up --hot-reload` reloads
+        // changed components without a restart
+        let manifest_glob = vec![stringize_path(manifest_file)?];
         let asset_globs = match self.skip_assets {
             true => {
                 tracing::debug!("Skipping asset globs from being watched");
@@ -60,7 +51,6 @@ impl FilterFactory for ArtifactFilterFactory {
 
         let artifact_globs = manifest_glob
             .into_iter()
-            .chain(wasm_globs)
             .chain(asset_globs)
             .map(|s| (s, None))
             .collect::<Vec<_>>();
@@ -131,6 +121,10 @@ fn create_source_globs(cid: &str, c: &v2::Component) -> Vec<String> {
         );
         return vec![];
     };
+    source_globs(build)
+}
+
+fn source_globs(build: &v2::ComponentBuildConfig) -> Vec<String> {
     build
         .workdir
         .as_deref()
@@ -167,6 +161,51 @@ impl FilterFactory for ManifestFilterFactory {
     }
 }
 
+/// Returns the IDs of the components whose build watch patterns match any of
+/// `changed_paths`, along with the components that depend on them (directly
+/// or transitively) via `build.depends_on`.
+pub(crate) fn affected_components(
+    manifest_dir: &Path,
+    manifest: &v2::AppManifest,
+    changed_paths: &HashSet<PathBuf>,
+) -> BTreeSet<String> {
+    let mut affected: BTreeSet<String> = manifest
+        .components
+        .iter()
+        .filter(|(_, c)| {
+            let Some(build) = &c.build else {
+                return false;
+            };
+            source_globs(build)
+                .iter()
+                .filter_map(|glob| {
+                    glob::Pattern::new(&manifest_dir.join(glob).to_string_lossy()).ok()
+                })
+                .any(|pattern| changed_paths.iter().any(|path| pattern.matches_path(path)))
+        })
+        .map(|(cid, _)| cid.to_string())
+        .collect();
+
+    loop {
+        let dependents: Vec<String> = manifest
+            .components
+            .iter()
+            .filter(|(cid, _)| !affected.contains(cid.as_ref()))
+            .filter(|(_, c)| {
+                c.build
+                    .iter()
+                    .flat_map(|b| &b.depends_on)
+                    .any(|dependency| affected.contains(dependency))
+            })
+            .map(|(cid, _)| cid.to_string())
+            .collect();
+        if dependents.is_empty() {
+            return affected;
+        }
+        affected.extend(dependents);
+    }
+}
+
 fn stringize_path(path: &Path) -> anyhow::Result<String> {
     match path.to_str() {
         Some(s) => Ok(s.to_owned()),
@@ -182,3 +221,44 @@ fn standard_ignores() -> Vec<(String, Option<PathBuf>)> {
     .map(|pat| (pat.to_owned(), None))
     .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_changes_to_components_and_dependents() {
+        let manifest = spin_manifest::manifest_from_str(
+            r#"
+            spin_manifest_version = 2
+            [application]
+            name = "watch-test"
+            [[trigger.http]]
+            route = "/..."
+            component = "app"
+            [component.app]
+            source = "app/app.wasm"
+            build = { command = "make", workdir = "app", watch = ["src/**/*.rs"], depends_on = ["lib"] }
+            [component.lib]
+            source = "lib/lib.wasm"
+            build = { command = "make", workdir = "lib", watch = ["src/**/*.rs"] }
+            [component.other]
+            source = "other/other.wasm"
+            build = { command = "make", workdir = "other", watch = ["*.go"] }
+            "#,
+        )
+        .unwrap();
+        let manifest_dir = Path::new("/project");
+        let affected = |paths: &[&str]| {
+            let paths = paths.iter().map(PathBuf::from).collect();
+            affected_components(manifest_dir, &manifest, &paths)
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(affected(&["/project/app/src/main.rs"]), ["app"]);
+        assert_eq!(affected(&["/project/lib/src/deep/lib.rs"]), ["app", "lib"]);
+        assert_eq!(affected(&["/project/other/main.go"]), ["other"]);
+        assert!(affected(&["/project/README.md"]).is_empty());
+    }
+}
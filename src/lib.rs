@@ -3,4 +3,4 @@ pub mod commands;
 pub(crate) mod opts;
 pub mod subprocess;
 
-pub use opts::HELP_ARGS_ONLY_TRIGGER_TYPE;
+pub use opts::{HELP_ARGS_ONLY_TRIGGER_TYPE, HTTP_REDIS_TRIGGER_TYPE};
@@ -6,8 +6,10 @@ pub const PLUGIN_NAME_OPT: &str = "PLUGIN_NAME";
 pub const PLUGIN_REMOTE_PLUGIN_MANIFEST_OPT: &str = "REMOTE_PLUGIN_MANIFEST";
 pub const PLUGIN_LOCAL_PLUGIN_MANIFEST_OPT: &str = "LOCAL_PLUGIN_MANIFEST";
 pub const PLUGIN_ALL_OPT: &str = "ALL";
+pub const PLUGIN_LOCKED_OPT: &str = "LOCKED";
 pub const PLUGIN_OVERRIDE_COMPATIBILITY_CHECK_FLAG: &str = "override-compatibility-check";
 pub const HELP_ARGS_ONLY_TRIGGER_TYPE: &str = "provide-help-args-no-app";
+pub const HTTP_REDIS_TRIGGER_TYPE: &str = "http+redis";
 pub const FROM_REGISTRY_OPT: &str = "REGISTRY_REFERENCE";
 pub const WATCH_CLEAR_OPT: &str = "CLEAR";
 pub const WATCH_DEBOUNCE_OPT: &str = "DEBOUNCE";